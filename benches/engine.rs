@@ -0,0 +1,231 @@
+//! Benchmarks of each phase of rendering a page (parsing its HTML, matching selectors, computing
+//! values, building its box tree, and laying it out), on a few representative pages, so that
+//! performance regressions in any of them show up.
+//!
+//! Run them with `cargo bench`, or compare against a saved baseline with
+//! `cargo bench -- --save-baseline main` and then `cargo bench -- --baseline main`.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use kosmonaut_core::dom::parser::parse_html;
+use kosmonaut_core::dom::traits::TendrilSink;
+use kosmonaut_core::dom::tree::NodeRef;
+use kosmonaut_core::gfx::font::use_test_fonts;
+use kosmonaut_core::layout::box_tree::build_box_tree;
+use kosmonaut_core::layout::global_layout;
+use kosmonaut_core::page::user_agent_stylesheet;
+use kosmonaut_core::style::media::{Device, MediaType};
+use kosmonaut_core::style::stylesheet::{
+    apply_stylesheet_to_node, parse_css_to_stylesheet, Stylesheet,
+};
+use kosmonaut_core::style::{apply_styles, cascade_and_compute, CascadeOrigin};
+
+const VIEWPORT_WIDTH_PX: f32 = 1280.;
+const VIEWPORT_HEIGHT_PX: f32 = 720.;
+
+/// A page to benchmark, with the author stylesheet that styles it.
+struct Page {
+    name: &'static str,
+    html: String,
+    author_sheets: Vec<Stylesheet>,
+}
+
+impl Page {
+    fn new(name: &'static str, html: String, mut css: String) -> Page {
+        Page {
+            name,
+            html,
+            author_sheets: vec![parse_css_to_stylesheet(
+                Some(format!("{}.css", name)),
+                &mut css,
+            )],
+        }
+    }
+
+    fn from_files(name: &'static str, html_path: &str, css_path: &str) -> Page {
+        Page::new(
+            name,
+            std::fs::read_to_string(html_path).expect("couldn't read benchmark page"),
+            std::fs::read_to_string(css_path).expect("couldn't read benchmark stylesheet"),
+        )
+    }
+}
+
+/// A long article: headings, paragraphs of text with inline elements, lists, and asides, styled
+/// by class, descendant, and attribute selectors and a media query.
+fn article_page(sections: usize) -> Page {
+    let mut html = String::from("<!DOCTYPE html><html><body><main class=\"article\">");
+    for section in 0..sections {
+        html.push_str(&format!(
+            "<section id=\"s{0}\" class=\"section{1}\">\
+             <h2 class=\"heading\">Section {0}</h2>\
+             <p>Lorem ipsum <em>dolor</em> sit amet, <a href=\"#s{0}\">consectetur</a> adipiscing \
+             elit, sed do <strong>eiusmod tempor</strong> incididunt ut labore et dolore magna.</p>\
+             <ul><li>First point</li><li class=\"highlight\">Second point</li><li>Third</li></ul>\
+             <aside data-kind=\"note\"><p>An aside, with <span class=\"muted\">muted</span> \
+             text.</p></aside>\
+             </section>",
+            section,
+            if section % 2 == 0 { " even" } else { "" }
+        ));
+    }
+    html.push_str("</main></body></html>");
+    let css = ".article { margin: 0 auto; max-width: 800px; padding: 16px; font-size: 16px }
+        .article section { margin-bottom: 24px; border-bottom: 1px solid #ddd }
+        .article .even { background-color: #fafafa }
+        section > .heading { font-size: 24px; margin: 8px 0 }
+        p { line-height: 1.5; margin: 8px 0 }
+        p em, p strong { color: #333 }
+        a { color: blue }
+        ul li { margin-left: 24px }
+        li.highlight { background-color: yellow }
+        aside[data-kind=note] { border-left: 4px solid orange; padding-left: 12px }
+        aside .muted { color: gray }
+        @media (max-width: 600px) { .article { padding: 4px } }"
+        .to_owned();
+    Page::new("article", html, css)
+}
+
+/// Blocks nested `depth` deep, each with some inline content, for the recursive parts of styling
+/// and layout.
+fn nested_page(depth: usize) -> Page {
+    let mut html = String::from("<!DOCTYPE html><html><body>");
+    for level in 0..depth {
+        html.push_str(&format!(
+            "<div class=\"level\">Level {} <span>text</span>",
+            level
+        ));
+    }
+    for _ in 0..depth {
+        html.push_str("</div>");
+    }
+    html.push_str("</body></html>");
+    let css = ".level { padding: 1px; border: 1px solid black }
+        .level .level span { color: red }
+        div > span { font-size: 12px }"
+        .to_owned();
+    Page::new("nested", html, css)
+}
+
+fn pages() -> Vec<Page> {
+    vec![
+        Page::from_files(
+            "rainbow-divs",
+            "tests/websrc/rainbow-divs.html",
+            "tests/websrc/rainbow-divs.css",
+        ),
+        article_page(100),
+        nested_page(200),
+    ]
+}
+
+fn ua_sheets() -> Vec<Stylesheet> {
+    vec![user_agent_stylesheet()]
+}
+
+fn device() -> Device {
+    Device::new(MediaType::Screen, VIEWPORT_WIDTH_PX, VIEWPORT_HEIGHT_PX)
+}
+
+/// Matches the selectors of the UA and author stylesheets against every element of `dom`.
+fn match_selectors(dom: &NodeRef, ua_sheets: &[Stylesheet], page: &Page, device: &Device) {
+    for sheet in ua_sheets {
+        apply_stylesheet_to_node(dom, sheet, CascadeOrigin::UserAgent, device);
+    }
+    for sheet in &page.author_sheets {
+        apply_stylesheet_to_node(dom, sheet, CascadeOrigin::Author, device);
+    }
+}
+
+fn styled_dom(ua_sheets: &[Stylesheet], page: &Page, device: &Device) -> NodeRef {
+    let dom = parse_html().one(page.html.as_str());
+    apply_styles(dom.clone(), device, ua_sheets, &[], &page.author_sheets);
+    dom
+}
+
+fn bench_phases(c: &mut Criterion) {
+    use_test_fonts();
+    let ua_sheets = ua_sheets();
+    let device = device();
+    let pages = pages();
+
+    let mut group = c.benchmark_group("parse_html");
+    for page in &pages {
+        group.bench_function(page.name, |b| {
+            b.iter(|| parse_html().one(page.html.as_str()))
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("match_selectors");
+    for page in &pages {
+        group.bench_function(page.name, |b| {
+            b.iter_batched(
+                || parse_html().one(page.html.as_str()),
+                |dom| {
+                    match_selectors(&dom, &ua_sheets, page, &device);
+                    dom
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("compute_values");
+    for page in &pages {
+        group.bench_function(page.name, |b| {
+            b.iter_batched(
+                || {
+                    let dom = parse_html().one(page.html.as_str());
+                    match_selectors(&dom, &ua_sheets, page, &device);
+                    dom
+                },
+                |dom| {
+                    cascade_and_compute(&dom, &device);
+                    dom
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("build_box_tree");
+    for page in &pages {
+        let dom = styled_dom(&ua_sheets, page, &device);
+        group.bench_function(page.name, |b| b.iter(|| build_box_tree(dom.clone(), None)));
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("layout");
+    for page in &pages {
+        let dom = styled_dom(&ua_sheets, page, &device);
+        group.bench_function(page.name, |b| {
+            b.iter_batched(
+                || build_box_tree(dom.clone(), None).expect("page should generate boxes"),
+                |mut box_tree| {
+                    global_layout(&mut box_tree, VIEWPORT_WIDTH_PX, VIEWPORT_HEIGHT_PX, 1.);
+                    box_tree
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("full");
+    for page in &pages {
+        group.bench_function(page.name, |b| {
+            b.iter(|| {
+                let dom = styled_dom(&ua_sheets, page, &device);
+                let mut box_tree = build_box_tree(dom, None).expect("page should generate boxes");
+                global_layout(&mut box_tree, VIEWPORT_WIDTH_PX, VIEWPORT_HEIGHT_PX, 1.);
+                box_tree
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_phases);
+criterion_main!(benches);
@@ -0,0 +1,11 @@
+//! Feeds arbitrary input into selector parsing (e.g. of `querySelector` arguments), which must
+//! fail rather than panic on invalid selectors.
+
+#![no_main]
+
+use kosmonaut_core::style::select::Selectors;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = Selectors::compile_str(&String::from_utf8_lossy(data));
+});
@@ -0,0 +1,13 @@
+//! Feeds arbitrary input into stylesheet parsing, which must recover from any error rather than
+//! panic.
+
+#![no_main]
+
+use kosmonaut_core::style::stylesheet::parse_css_to_stylesheet;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // Stylesheets are decoded before they're parsed, so the parser only ever sees valid UTF-8.
+    let mut css = String::from_utf8_lossy(data).into_owned();
+    parse_css_to_stylesheet(None, &mut css);
+});
@@ -0,0 +1,108 @@
+#ifndef KOSMONAUT_H
+#define KOSMONAUT_H
+
+/* Generated by cbindgen from src/ffi.rs.  Don't edit by hand. */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+// An element found with `kosmonaut_hit_test`.
+typedef struct KosmonautNode KosmonautNode;
+
+// A page loaded with `kosmonaut_load_html`.
+typedef struct KosmonautPage KosmonautPage;
+
+// Loads the document `html` is the source of, styled by the user agent stylesheet and the
+// author stylesheet `css` (if it isn't null), resolving the relative URLs of its images against
+// the directory `base_dir` (or the current directory, if it's null).  The page's viewport is
+// the default window size, at one device pixel per CSS pixel, until it's set with
+// `kosmonaut_set_viewport`.
+//
+// Returns null if `html` is null or any of the strings aren't UTF-8.  The returned page must be
+// freed with `kosmonaut_free`.
+//
+// # Safety
+//
+// Each string must be null or NUL-terminated.
+KosmonautPage *kosmonaut_load_html(const char *html, const char *css, const char *base_dir);
+
+// Frees `page`, which may be null.
+//
+// # Safety
+//
+// `page` must be null or have been returned by `kosmonaut_load_html`, and not freed already.
+void kosmonaut_free(KosmonautPage *page);
+
+// Sets the viewport of `page` to `width` by `height` device pixels, at `scale_factor` device
+// pixels per CSS pixel.
+//
+// # Safety
+//
+// `page` must be a live page returned by `kosmonaut_load_html`.
+void kosmonaut_set_viewport(KosmonautPage *page, float width, float height, float scale_factor);
+
+// Lays out `page` in its viewport.  Returns false if layout failed.
+//
+// Rendering and hit testing lay pages out as needed, so this only needs calling to control when
+// that work happens.
+//
+// # Safety
+//
+// `page` must be a live page returned by `kosmonaut_load_html`.
+bool kosmonaut_layout(KosmonautPage *page);
+
+// Renders `page` into `pixels`, as rows of straight (not premultiplied) RGBA pixels the size of
+// its viewport in device pixels, from top to bottom.  Nothing is written unless `len` is at
+// least the size of the image (i.e. width × height × 4 bytes).
+//
+// Returns the size of the image in bytes, or 0 if rendering failed, so the size of the buffer
+// needed can be found by passing a null `pixels` and a `len` of 0.
+//
+// # Safety
+//
+// `page` must be a live page returned by `kosmonaut_load_html`, and `pixels` must be valid for
+// writes of `len` bytes.
+size_t kosmonaut_render_rgba(KosmonautPage *page, uint8_t *pixels, size_t len);
+
+// Finds the element painted at (`x`, `y`) in `page`, in CSS pixels relative to its viewport.
+// Returns null if nothing was painted there.  The returned node must be freed with
+// `kosmonaut_node_free`.
+//
+// # Safety
+//
+// `page` must be a live page returned by `kosmonaut_load_html`.
+KosmonautNode *kosmonaut_hit_test(KosmonautPage *page, float x, float y);
+
+// The local name of the element `node` (e.g. `div`), which must be freed with
+// `kosmonaut_string_free`.
+//
+// # Safety
+//
+// `node` must be a live node returned by `kosmonaut_hit_test`.
+char *kosmonaut_node_local_name(const KosmonautNode *node);
+
+// The value of the attribute `name` of the element `node`, which must be freed with
+// `kosmonaut_string_free`, or null if it has no such attribute.
+//
+// # Safety
+//
+// `node` must be a live node returned by `kosmonaut_hit_test`, and `name` must be
+// NUL-terminated.
+char *kosmonaut_node_attribute(const KosmonautNode *node, const char *name);
+
+// Frees `node`, which may be null.
+//
+// # Safety
+//
+// `node` must be null or have been returned by `kosmonaut_hit_test`, and not freed already.
+void kosmonaut_node_free(KosmonautNode *node);
+
+// Frees `string`, which may be null.
+//
+// # Safety
+//
+// `string` must be null or have been returned by Kosmonaut, and not freed already.
+void kosmonaut_string_free(char *string);
+
+#endif /* KOSMONAUT_H */
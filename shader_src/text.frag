@@ -1,12 +1,13 @@
 #version 330 core
 in vec2 TexCoords;
+in vec4 TintColor;
 out vec4 color;
 
+// The glyph atlas, holding white glyphs to be tinted with the text color and color glyphs, which
+// are tinted with white.
 uniform sampler2D text;
-uniform vec3 textColor;
 
 void main()
 {
-    vec4 sampled = vec4(1.0, 1.0, 1.0, texture(text, TexCoords).r);
-    color = vec4(textColor, 1.0) * sampled;
+    color = TintColor * texture(text, TexCoords);
 }
\ No newline at end of file
@@ -0,0 +1,30 @@
+struct VertexOutput {
+    [[builtin(position)]] position: vec4<f32>;
+    [[location(0)]] tex_coords: vec2<f32>;
+    [[location(1)]] tint: vec4<f32>;
+};
+
+// The glyph atlas, holding white glyphs to be tinted with the text color and color glyphs, which
+// are tinted with white.
+[[group(0), binding(0)]]
+var glyph_atlas: texture_2d<f32>;
+[[group(0), binding(1)]]
+var glyph_sampler: sampler;
+
+[[stage(vertex)]]
+fn vs_main(
+    [[location(0)]] position: vec2<f32>,
+    [[location(1)]] tex_coords: vec2<f32>,
+    [[location(2)]] tint: vec4<f32>
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.position = vec4<f32>(position, 0.0, 1.0);
+    out.tex_coords = tex_coords;
+    out.tint = tint;
+    return out;
+}
+
+[[stage(fragment)]]
+fn fs_main(vertex: VertexOutput) -> [[location(0)]] vec4<f32> {
+    return vertex.tint * textureSample(glyph_atlas, glyph_sampler, vertex.tex_coords);
+}
@@ -0,0 +1,589 @@
+//! The accessibility tree: what assistive technologies (e.g. screen readers) are told about a
+//! document.  Each node of the tree has a role, like `heading` or `link`, a name, and any states
+//! that apply to it, like whether it's checked.  The tree is derived from the DOM and computed
+//! styles, so elements that aren't rendered, and their descendants, aren't in it.
+//!
+//! Elements without a role of their own (e.g. `<div>` and `<span>`), and those whose role is
+//! `none` or `presentation`, are left out of the tree, with their children taking their place.
+//!
+//! https://www.w3.org/TR/core-aam-1.2/#mapping_general
+//! https://www.w3.org/TR/html-aam-1.0/#html-element-role-mappings
+
+use std::io::Write;
+
+use strum::IntoEnumIterator;
+
+use crate::dom::form::{button_label, collapse_whitespace, is_checked, value, FormControlKind};
+use crate::dom::iter::NodeIterator;
+use crate::dom::metadata::document_title;
+use crate::dom::tree::{ElementData, NodeRef};
+use crate::style::values::computed::display::DisplayBox;
+use crate::style::values::computed::Display;
+
+/// The roles of accessibility tree nodes.
+///
+/// https://www.w3.org/TR/wai-aria-1.2/#role_definitions
+#[derive(Clone, Copy, Debug, EnumIter, Eq, PartialEq)]
+pub enum Role {
+    Article,
+    Banner,
+    Button,
+    Cell,
+    Checkbox,
+    ColumnHeader,
+    Combobox,
+    Complementary,
+    ContentInfo,
+    Dialog,
+    Document,
+    Figure,
+    Form,
+    Heading,
+    Image,
+    Link,
+    List,
+    ListItem,
+    Main,
+    Navigation,
+    Paragraph,
+    Radio,
+    Region,
+    Row,
+    Separator,
+    Table,
+    /// A run of text.  This isn't an ARIA role, so it can't be given with a `role` attribute.
+    StaticText,
+    TextBox,
+}
+
+impl Role {
+    /// The ARIA name of the role, e.g. `columnheader`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Role::Article => "article",
+            Role::Banner => "banner",
+            Role::Button => "button",
+            Role::Cell => "cell",
+            Role::Checkbox => "checkbox",
+            Role::ColumnHeader => "columnheader",
+            Role::Combobox => "combobox",
+            Role::Complementary => "complementary",
+            Role::ContentInfo => "contentinfo",
+            Role::Dialog => "dialog",
+            Role::Document => "document",
+            Role::Figure => "figure",
+            Role::Form => "form",
+            Role::Heading => "heading",
+            Role::Image => "img",
+            Role::Link => "link",
+            Role::List => "list",
+            Role::ListItem => "listitem",
+            Role::Main => "main",
+            Role::Navigation => "navigation",
+            Role::Paragraph => "paragraph",
+            Role::Radio => "radio",
+            Role::Region => "region",
+            Role::Row => "row",
+            Role::Separator => "separator",
+            Role::Table => "table",
+            Role::StaticText => "text",
+            Role::TextBox => "textbox",
+        }
+    }
+
+    /// The role with the given ARIA name (ASCII case-insensitively), if it's supported.
+    pub fn from_aria_name(name: &str) -> Option<Role> {
+        Role::iter()
+            .find(|role| *role != Role::StaticText && role.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Whether nodes with this role are named by their contents when nothing else names them.
+    ///
+    /// https://www.w3.org/TR/wai-aria-1.2/#namefromcontent
+    fn is_named_from_content(self) -> bool {
+        matches!(
+            self,
+            Role::Button
+                | Role::Cell
+                | Role::Checkbox
+                | Role::ColumnHeader
+                | Role::Heading
+                | Role::Link
+                | Role::Radio
+                | Role::Row
+                | Role::StaticText
+        )
+    }
+}
+
+/// A node of the accessibility tree.
+#[derive(Clone, Debug)]
+pub struct AccessibilityNode {
+    /// The DOM node this was derived from.
+    pub node: NodeRef,
+    pub role: Role,
+    /// The accessible name, with whitespace collapsed.  Empty if the node has no name.
+    ///
+    /// https://www.w3.org/TR/accname-1.2/#dfn-accessible-name
+    pub name: String,
+    /// The level of a heading, e.g. 2 for an `<h2>`.
+    pub level: Option<u32>,
+    /// Whether a checkbox or radio button is checked.
+    pub checked: Option<bool>,
+    /// The current value of a text box.
+    pub value: Option<String>,
+    pub disabled: bool,
+    pub focused: bool,
+    pub children: Vec<AccessibilityNode>,
+}
+
+/// Builds the accessibility tree of the styled `document`, whose root is a `document` node named
+/// by the document's title.  The documents of iframes are included in place of their iframes.
+pub fn build_accessibility_tree(document: &NodeRef) -> AccessibilityNode {
+    let mut children = Vec::new();
+    for child in document.children() {
+        push_accessibility_nodes(&child, &mut children);
+    }
+    AccessibilityNode {
+        node: document.clone(),
+        role: Role::Document,
+        name: document_title(document).unwrap_or_default(),
+        level: None,
+        checked: None,
+        value: None,
+        disabled: false,
+        focused: false,
+        children,
+    }
+}
+
+/// Pushes the accessibility node of `node` onto `nodes`, or if it doesn't have a role, the
+/// accessibility nodes of its children.
+fn push_accessibility_nodes(node: &NodeRef, nodes: &mut Vec<AccessibilityNode>) {
+    if let Some(text) = node.as_text() {
+        let text = collapse_whitespace(&text.borrow());
+        if !text.is_empty() {
+            nodes.push(AccessibilityNode {
+                node: node.clone(),
+                role: Role::StaticText,
+                name: text,
+                level: None,
+                checked: None,
+                value: None,
+                disabled: false,
+                focused: false,
+                children: Vec::new(),
+            });
+        }
+        return;
+    }
+    let element = match node.as_element() {
+        Some(element) if !is_hidden(node, element) => element,
+        _ => return,
+    };
+    let mut children = Vec::new();
+    // Form controls are rendered as widgets, whose contents (e.g. the `<option>`s of a `<select>`)
+    // aren't rendered as such.
+    if FormControlKind::of(node).is_none() {
+        for child in node.flat_tree_children() {
+            push_accessibility_nodes(&child, &mut children);
+        }
+    }
+    if let Some(context) = element.nested_browsing_context.borrow().as_ref() {
+        children.push(build_accessibility_tree(&context.document()));
+    }
+    let role = match role(node, element) {
+        Some(role) => role,
+        None => {
+            nodes.append(&mut children);
+            return;
+        }
+    };
+    let attributes = element.attributes.borrow();
+    let is_true = |name: &str| attributes.get(name).map_or(false, |value| value == "true");
+    let kind = FormControlKind::of(node);
+    nodes.push(AccessibilityNode {
+        node: node.clone(),
+        role,
+        name: accessible_name(node, element, role),
+        level: if role == Role::Heading {
+            Some(heading_level(element))
+        } else {
+            None
+        },
+        checked: match kind {
+            Some(FormControlKind::Checkbox) | Some(FormControlKind::Radio) => {
+                Some(is_checked(element))
+            }
+            _ if role == Role::Checkbox || role == Role::Radio => Some(is_true("aria-checked")),
+            _ => None,
+        },
+        value: value(node),
+        disabled: (kind.is_some() && attributes.contains(local_name!("disabled")))
+            || is_true("aria-disabled"),
+        focused: element.focused.get(),
+        children,
+    });
+}
+
+/// Whether `element` and its descendants are left out of the accessibility tree, because they
+/// aren't rendered or are hidden with `aria-hidden`.
+///
+/// https://www.w3.org/TR/wai-aria-1.2/#tree_exclusion
+fn is_hidden(node: &NodeRef, element: &ElementData) -> bool {
+    // Scripts aren't rendered, but the UA stylesheet doesn't hide them yet.
+    if element.name.ns == ns!(html) && element.name.local == local_name!("script") {
+        return true;
+    }
+    if node.computed_values().display == Display::Box(DisplayBox::None) {
+        return true;
+    }
+    element
+        .attributes
+        .borrow()
+        .get("aria-hidden")
+        .map_or(false, |aria_hidden| aria_hidden.trim() == "true")
+}
+
+/// The role of `element`: the first supported role in its `role` attribute, or otherwise the
+/// implicit role of the element.  `None` if the element is left out of the accessibility tree
+/// in favor of its children.
+///
+/// https://www.w3.org/TR/wai-aria-1.2/#host_general_role
+fn role(node: &NodeRef, element: &ElementData) -> Option<Role> {
+    if let Some(roles) = element.attributes.borrow().get("role") {
+        for role in roles.split_ascii_whitespace() {
+            if role.eq_ignore_ascii_case("none") || role.eq_ignore_ascii_case("presentation") {
+                return None;
+            }
+            if let Some(role) = Role::from_aria_name(role) {
+                return Some(role);
+            }
+        }
+    }
+    implicit_role(node, element)
+}
+
+/// https://www.w3.org/TR/html-aam-1.0/#html-element-role-mappings
+fn implicit_role(node: &NodeRef, element: &ElementData) -> Option<Role> {
+    if element.name.ns != ns!(html) {
+        return None;
+    }
+    if let Some(kind) = FormControlKind::of(node) {
+        return Some(match kind {
+            FormControlKind::TextField | FormControlKind::TextArea => Role::TextBox,
+            FormControlKind::Checkbox => Role::Checkbox,
+            FormControlKind::Radio => Role::Radio,
+            FormControlKind::Button => Role::Button,
+            FormControlKind::Select => Role::Combobox,
+        });
+    }
+    let attributes = element.attributes.borrow();
+    Some(match element.name.local {
+        local_name!("a") | local_name!("area") if attributes.contains(local_name!("href")) => {
+            Role::Link
+        }
+        local_name!("article") => Role::Article,
+        local_name!("aside") => Role::Complementary,
+        local_name!("dialog") => Role::Dialog,
+        local_name!("figure") => Role::Figure,
+        local_name!("form") => Role::Form,
+        local_name!("h1")
+        | local_name!("h2")
+        | local_name!("h3")
+        | local_name!("h4")
+        | local_name!("h5")
+        | local_name!("h6") => Role::Heading,
+        local_name!("hr") => Role::Separator,
+        // Images with empty alt text are decorative.
+        local_name!("img") if attributes.get(local_name!("alt")) != Some("") => Role::Image,
+        local_name!("li") => Role::ListItem,
+        local_name!("main") => Role::Main,
+        local_name!("menu") | local_name!("ol") | local_name!("ul") => Role::List,
+        local_name!("nav") => Role::Navigation,
+        local_name!("p") => Role::Paragraph,
+        local_name!("table") => Role::Table,
+        local_name!("td") => Role::Cell,
+        local_name!("th") => Role::ColumnHeader,
+        local_name!("tr") => Role::Row,
+        // Headers and footers are only landmarks of the whole page.
+        local_name!("header") if !is_in_sectioning_element(node) => Role::Banner,
+        local_name!("footer") if !is_in_sectioning_element(node) => Role::ContentInfo,
+        // Sections are only landmarks if they're named.
+        local_name!("section")
+            if attributes.contains("aria-label") || attributes.contains("aria-labelledby") =>
+        {
+            Role::Region
+        }
+        _ => return None,
+    })
+}
+
+fn is_in_sectioning_element(node: &NodeRef) -> bool {
+    node.ancestors().elements().any(|ancestor| {
+        ancestor.name.ns == ns!(html)
+            && matches!(
+                ancestor.name.local,
+                local_name!("article")
+                    | local_name!("aside")
+                    | local_name!("main")
+                    | local_name!("nav")
+                    | local_name!("section")
+            )
+    })
+}
+
+/// The level of a heading: its `aria-level`, or otherwise the number of its `<h1>`-`<h6>`
+/// element, or otherwise 2.
+///
+/// https://www.w3.org/TR/wai-aria-1.2/#heading
+fn heading_level(element: &ElementData) -> u32 {
+    let aria_level = element
+        .attributes
+        .borrow()
+        .get("aria-level")
+        .and_then(|level| level.trim().parse::<u32>().ok())
+        .filter(|level| *level > 0);
+    aria_level.unwrap_or(match element.name.local {
+        local_name!("h1") => 1,
+        local_name!("h3") => 3,
+        local_name!("h4") => 4,
+        local_name!("h5") => 5,
+        local_name!("h6") => 6,
+        _ => 2,
+    })
+}
+
+/// Computes the accessible name of `element`, which has the given role, from the first of these
+/// that isn't empty: the elements its `aria-labelledby` refers to, its `aria-label`, the name its
+/// element type gives it (e.g. the `alt` of an `<img>`), its contents if its role is named from
+/// them, and its `title`.
+///
+/// TODO: `<label>`s don't name the controls they label yet.
+///
+/// https://www.w3.org/TR/accname-1.2/#mapping_additional_nd_te
+fn accessible_name(node: &NodeRef, element: &ElementData, role: Role) -> String {
+    let attributes = element.attributes.borrow();
+    if let Some(ids) = attributes.get("aria-labelledby") {
+        // Ids are looked up in the tree of the element, which may be a shadow tree.
+        let tree = node
+            .containing_shadow_root()
+            .or_else(|| node.inclusive_ancestors().last())
+            .unwrap();
+        let name = ids
+            .split_ascii_whitespace()
+            .filter_map(|id| {
+                tree.descendants().find(|node| {
+                    node.as_element().map_or(false, |element| {
+                        element.attributes.borrow().get(local_name!("id")) == Some(id)
+                    })
+                })
+            })
+            .map(|labelling_node| text_from_contents(&labelling_node))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let name = collapse_whitespace(&name);
+        if !name.is_empty() {
+            return name;
+        }
+    }
+    let candidates = vec![
+        attributes.get("aria-label").map(str::to_owned),
+        native_name(node, element),
+        if role.is_named_from_content() {
+            Some(text_from_contents(node))
+        } else {
+            None
+        },
+        attributes.get(local_name!("title")).map(str::to_owned),
+    ];
+    candidates
+        .into_iter()
+        .flatten()
+        .map(|name| collapse_whitespace(&name))
+        .find(|name| !name.is_empty())
+        .unwrap_or_default()
+}
+
+/// The name the type of `element` gives it, if any.
+///
+/// https://www.w3.org/TR/html-aam-1.0/#accessible-name-and-description-computation
+fn native_name(node: &NodeRef, element: &ElementData) -> Option<String> {
+    if element.name.ns != ns!(html) {
+        return None;
+    }
+    if FormControlKind::of(node) == Some(FormControlKind::Button) {
+        return Some(button_label(node));
+    }
+    match element.name.local {
+        local_name!("img") | local_name!("area") => element
+            .attributes
+            .borrow()
+            .get(local_name!("alt"))
+            .map(str::to_owned),
+        local_name!("input") | local_name!("textarea") => element
+            .attributes
+            .borrow()
+            .get(local_name!("placeholder"))
+            .map(str::to_owned),
+        _ => None,
+    }
+}
+
+/// The text of `node`'s rendered contents, with images contributing their alt text.  Hidden
+/// descendants don't contribute anything.
+///
+/// https://www.w3.org/TR/accname-1.2/#step2F
+fn text_from_contents(node: &NodeRef) -> String {
+    let mut text = String::new();
+    for child in node.flat_tree_children() {
+        if let Some(child_text) = child.as_text() {
+            text.push_str(&child_text.borrow());
+            continue;
+        }
+        let element = match child.as_element() {
+            Some(element) if !is_hidden(&child, element) => element,
+            _ => continue,
+        };
+        if element.name.ns == ns!(html) && element.name.local == local_name!("img") {
+            if let Some(alt) = element.attributes.borrow().get(local_name!("alt")) {
+                text.push(' ');
+                text.push_str(alt);
+                text.push(' ');
+            }
+            continue;
+        }
+        text.push_str(&text_from_contents(&child));
+    }
+    text
+}
+
+/// Writes `node` and its descendants, indenting each by its depth in the tree, e.g.:
+///
+/// document "Title"
+///   heading "Welcome" level=1
+///     text "Welcome"
+///   checkbox "Subscribe" checked=false focused
+///
+/// Used in the `dump-a11y` command.
+pub fn dump_accessibility_tree<W: Write>(
+    node: &AccessibilityNode,
+    write_to: &mut W,
+    indent_spaces: usize,
+) {
+    let mut line = format!(
+        "{:indent_spaces$}{}",
+        "",
+        node.role.name(),
+        indent_spaces = indent_spaces
+    );
+    if !node.name.is_empty() {
+        line.push_str(&format!(" \"{}\"", node.name));
+    }
+    if let Some(level) = node.level {
+        line.push_str(&format!(" level={}", level));
+    }
+    if let Some(checked) = node.checked {
+        line.push_str(&format!(" checked={}", checked));
+    }
+    if let Some(value) = &node.value {
+        line.push_str(&format!(" value=\"{}\"", value));
+    }
+    if node.disabled {
+        line.push_str(" disabled");
+    }
+    if node.focused {
+        line.push_str(" focused");
+    }
+    writeln!(write_to, "{}", line).expect("error writing accessibility tree dump");
+    for child in &node.children {
+        dump_accessibility_tree(child, write_to, indent_spaces + 2);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::traits::*;
+    use crate::style::test_utils::styled_document;
+
+    fn dump(html: &str) -> String {
+        let document = styled_document(html, "");
+        let mut dump = Vec::new();
+        dump_accessibility_tree(&build_accessibility_tree(&document), &mut dump, 0);
+        String::from_utf8(dump).unwrap()
+    }
+
+    #[test]
+    fn derives_roles_names_and_hierarchy_from_the_dom() {
+        assert_eq!(
+            dump(
+                "<title>Page</title>\
+                 <header><nav aria-label=Site><a href=/>Home</a> <a>No href</a></nav></header>\
+                 <main>\
+                   <h1>Welcome <img src=friend.png alt=friend></h1>\
+                   <div role=presentation><p>Some <span>text</span></p></div>\
+                   <section aria-labelledby=s><h3 id=s>Section</h3><header>Not a banner</header></section>\
+                   <div role='bogus heading' aria-level=4>Custom</div>\
+                   <ul><li>Item</li></ul>\
+                   <img src=spacer.png alt=''>\
+                 </main>"
+            ),
+            "document \"Page\"
+  banner
+    navigation \"Site\"
+      link \"Home\"
+        text \"Home\"
+      text \"No href\"
+  main
+    heading \"Welcome friend\" level=1
+      text \"Welcome\"
+      img \"friend\"
+    paragraph
+      text \"Some\"
+      text \"text\"
+    region \"Section\"
+      heading \"Section\" level=3
+        text \"Section\"
+      text \"Not a banner\"
+    heading \"Custom\" level=4
+      text \"Custom\"
+    list
+      listitem
+        text \"Item\"
+"
+        );
+    }
+
+    #[test]
+    fn leaves_out_hidden_elements() {
+        assert_eq!(
+            dump(
+                "<p aria-hidden=true>Hidden</p>\
+                 <div style='display: none'><button>Nope</button></div>\
+                 <script>var x;</script>\
+                 <p>Shown</p>"
+            ),
+            "document
+  paragraph
+    text \"Shown\"
+"
+        );
+    }
+
+    #[test]
+    fn exposes_the_states_of_form_controls() {
+        let dump = dump(
+            "<input type=checkbox checked aria-label=Subscribe>\
+             <input type=radio title=Other disabled>\
+             <input value=hello placeholder=Greeting>\
+             <button title=Close>X</button>\
+             <div role=checkbox aria-checked=true>Custom</div>",
+        );
+        assert!(dump.contains("  checkbox \"Subscribe\" checked=true\n"));
+        assert!(dump.contains("  radio \"Other\" checked=false disabled\n"));
+        assert!(dump.contains("  textbox \"Greeting\" value=\"hello\"\n"));
+        assert!(dump.contains("  button \"X\"\n"));
+        assert!(dump.contains("  checkbox \"Custom\" checked=true\n    text \"Custom\"\n"));
+    }
+}
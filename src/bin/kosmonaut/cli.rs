@@ -0,0 +1,667 @@
+use clap::{App, Arg, ArgMatches, SubCommand};
+use kosmonaut_core::gfx::backend::Renderer;
+use kosmonaut_core::layout::{DumpLayoutVerbosity, LayoutDumpFormat};
+use kosmonaut_core::style::media::{ContrastPreference, ForcedColors, MotionPreference};
+use std::str::FromStr;
+
+pub fn setup_and_get_cli_args<'a>() -> ArgMatches<'a> {
+    let headed_or_headless_applicable =
+        "Applicable in both headed and headless (e.g. dump-layout) contexts.";
+    App::new("Kosmonaut")
+        .version("0.1")
+        .author("Tyler Wilcock (twilco)")
+        .about("A web browser for the space age 🚀")
+        .arg(
+            Arg::with_name("files")
+                .short("f")
+                .long("files")
+                .value_name("SPACE SEPARATED FILE PATHS")
+                .help(
+                    "Pass files for Kosmonaut to render.  XHTML files (`.xhtml` or `.xht`) are \
+                     parsed as XML.  When more than one HTML file is given, the window opens each \
+                     in its own tab, while other commands render the first.",
+                )
+                .multiple(true)
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("width")
+                .short("w")
+                .long("width")
+                .value_name("NUMBER")
+                .help(&format!("Inner window width.  {}", headed_or_headless_applicable))
+                .takes_value(true)
+                .validator(is_num_validator)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("height")
+                .short("h")
+                .long("height")
+                .value_name("NUMBER")
+                .help(&format!("Inner window height.  {}", headed_or_headless_applicable))
+                .takes_value(true)
+                .validator(is_num_validator)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("viewport")
+                .long("viewport")
+                .value_name("WIDTHxHEIGHT")
+                .help(&format!("Inner window width and height, e.g. 800x600.  Shorthand for width and height.  {}", headed_or_headless_applicable))
+                .takes_value(true)
+                .validator(is_viewport_validator)
+                .conflicts_with_all(&["width", "height"])
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("scale-factor")
+                .short("s")
+                .long("scale-factor")
+                .alias("dpr")
+                .value_name("NUMBER")
+                .help(&format!("Device/window scale factor (device pixel ratio), in device pixels per CSS pixel, which resolution media queries are evaluated against.  Overrides the window's own scale factor, so output is the same on any machine.  {}", headed_or_headless_applicable))
+                .takes_value(true)
+                .validator(is_num_validator)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("prefers-color-scheme")
+                .long("prefers-color-scheme")
+                .value_name("COLOR SCHEME")
+                .help(&format!("The color scheme the user prefers, which prefers-color-scheme media queries are evaluated against, and the system colors of pages supporting it (with the color-scheme property) are in.  `system` follows the OS's theme in a window, even as it changes, and is light when running headless, so output is the same on any machine.  {}", headed_or_headless_applicable))
+                .takes_value(true)
+                .possible_values(&[ColorSchemePreference::System.to_cli_string(), ColorSchemePreference::Light.to_cli_string(), ColorSchemePreference::Dark.to_cli_string()])
+                .default_value(ColorSchemePreference::System.to_cli_string())
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("prefers-reduced-motion")
+                .long("prefers-reduced-motion")
+                .value_name("PREFERENCE")
+                .help(&format!("Whether the user prefers less motion, which prefers-reduced-motion media queries are evaluated against.  Reducing motion also makes scrolling jump rather than animate, and freezes animated images at their first frame.  `system` follows the OS's accessibility settings in a window, and is no-preference when running headless.  {}", headed_or_headless_applicable))
+                .takes_value(true)
+                .possible_values(&["system", "no-preference", "reduce"])
+                .default_value("system")
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("prefers-contrast")
+                .long("prefers-contrast")
+                .value_name("PREFERENCE")
+                .help(&format!("The contrast the user prefers, which prefers-contrast media queries are evaluated against.  `system` follows the OS's accessibility settings in a window, and is no-preference when running headless.  {}", headed_or_headless_applicable))
+                .takes_value(true)
+                .possible_values(&["system", "no-preference", "more", "less", "custom"])
+                .default_value("system")
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("forced-colors")
+                .long("forced-colors")
+                .value_name("FORCED COLORS")
+                .help(&format!("Whether the user has forced a limited palette of colors (e.g. with a high contrast theme), which forced-colors media queries are evaluated against.  `system` follows the OS's accessibility settings in a window, and is none when running headless.  {}", headed_or_headless_applicable))
+                .takes_value(true)
+                .possible_values(&["system", "none", "active"])
+                .default_value("system")
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("media")
+                .long("media")
+                .value_name("MEDIA TYPE")
+                .help(&format!("The media type media queries are evaluated against.  {}", headed_or_headless_applicable))
+                .takes_value(true)
+                .possible_values(&[EmulatedMedia::Screen.to_cli_string(), EmulatedMedia::Print.to_cli_string()])
+                .default_value(EmulatedMedia::Screen.to_cli_string())
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("test-fonts")
+                .long("test-fonts")
+                .help(&format!("Renders all text with a bundled Ahem-style font, in which every glyph is a box one em wide, so that output is the same on any machine whatever fonts are installed.  {}", headed_or_headless_applicable))
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("log-css-errors")
+                .long("log-css-errors")
+                .help(&format!("Logs each CSS rule and declaration dropped because it couldn't be parsed, or isn't supported, to stderr as STYLESHEET:LINE:COLUMN: MESSAGE.  {}", headed_or_headless_applicable))
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("report-unsupported")
+                .long("report-unsupported")
+                .help(&format!("Prints a summary of the CSS properties, values, at-rules, and selectors, and the HTML elements, that the page uses but Kosmonaut doesn't implement to stderr, most used first.  {}", headed_or_headless_applicable))
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("trace-output")
+                .long("trace-output")
+                .value_name("JSON FILE PATH")
+                .help(&format!("Traces how long loading, styling, building the box tree, laying out, and painting the page take, and writes the trace to the given file in the Chrome trace event format (which chrome://tracing and https://ui.perfetto.dev can open).  {}", headed_or_headless_applicable))
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("screenshot")
+                .long("screenshot")
+                .value_name("PNG FILE PATH")
+                .help("Renders the page once, without opening a window, and writes it to the given PNG file.  Requires width, height, and scale-factor.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("memory-report")
+                .long("memory-report")
+                .help("Loads, lays out, and paints the page once, without opening a window, and prints how much heap memory its stylesheets, DOM, computed styles, image cache, box tree, and display list each retain."),
+        )
+        .arg(
+            Arg::with_name("print-to-pdf")
+                .long("print-to-pdf")
+                .value_name("PDF FILE PATH")
+                .help("Lays out the page for print, without opening a window, and writes its pages to the given PDF file, with the fonts its text is styled with embedded in it.")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("remote-debugging-port")
+                .long("remote-debugging-port")
+                .value_name("PORT")
+                .help("Serves a subset of the Chrome DevTools Protocol (DOM.getDocument, DOM.getBoxModel, CSS.getComputedStyleForNode, and Page.captureScreenshot) on the given port of localhost, so devtools frontends and automation tools can inspect the window's active tab.")
+                .takes_value(true)
+                .validator(is_port_validator),
+        )
+        .arg(
+            Arg::with_name("remote-allow-origins")
+                .long("remote-allow-origins")
+                .value_name("COMMA SEPARATED ORIGINS")
+                .help("The origins of the web pages allowed to connect to the server started by --remote-debugging-port, e.g. `http://localhost:3000`, or `*` to allow any.  Clients that aren't web pages can always connect.")
+                .takes_value(true)
+                .multiple(true)
+                .use_delimiter(true)
+                .requires("remote-debugging-port"),
+        )
+        .arg(
+            Arg::with_name("renderer")
+                .long("renderer")
+                .value_name("RENDERER")
+                .help("The rendering backend to paint the window with.")
+                .takes_value(true)
+                .possible_values(&[Renderer::OpenGl.to_cli_string(), Renderer::Wgpu.to_cli_string()])
+                .default_value(Renderer::OpenGl.to_cli_string()),
+        )
+        .subcommand(
+            SubCommand::with_name("dump-layout")
+                .about("Dumps layout-tree as text to stdout after first global layout, exiting afterwards.")
+                .arg(
+                    Arg::with_name("verbose")
+                        .short("v")
+                        .long("verbose")
+                        .value_name("LEVEL")
+                        .help("Set to true (or 1) to make layout dumps more verbose (e.g. include margin, border, padding values, containing blocks, and the id, classes, and DOM path of the element each box belongs to), or to 2 to also include line boxes, the inline box and text fragments on them, and the baselines of lines and boxes.")
+                        .takes_value(true)
+                        .validator(is_verbosity_validator)
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .help("The format to dump the layout-tree in: indented text to read, or a JSON tree (always including margin, border, and padding values) for other tools to consume.")
+                        .takes_value(true)
+                        .possible_values(&[LayoutDumpFormat::Text.to_cli_string(), LayoutDumpFormat::Json.to_cli_string()])
+                        .default_value(LayoutDumpFormat::Text.to_cli_string()),
+                )
+                .arg(
+                    Arg::with_name("filter")
+                        .long("filter")
+                        .value_name("SELECTOR")
+                        .help("Only dump the boxes generated by elements matching the given CSS selector, e.g. `main > .card`.")
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::with_name("include-descendants")
+                        .long("include-descendants")
+                        .help("Dump the descendants of the boxes matching `--filter` along with them.")
+                        .requires("filter")
+                )
+                .arg(
+                    Arg::with_name("diff")
+                        .long("diff")
+                        .value_name("PREVIOUS DUMP PATH")
+                        .help("Compare the text layout-tree dump with one saved to the given file, only dumping the boxes whose geometry differs, before and after.")
+                        .takes_value(true)
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("dump-display-list")
+                .about("Dumps the display list's paint commands, in painting order, as text to stdout after first global layout, exiting afterwards.")
+        )
+        .subcommand(
+            SubCommand::with_name("dump-stacking")
+                .about("Dumps the stacking context tree, and the boxes painted in each stacking context in painting order, as text to stdout after first global layout, exiting afterwards.")
+        )
+        .subcommand(
+            SubCommand::with_name("dump-styles")
+                .about("Dumps the computed values of each element's properties as text to stdout after styling, exiting afterwards.")
+                .arg(
+                    Arg::with_name("properties")
+                        .short("p")
+                        .long("properties")
+                        .value_name("SPACE SEPARATED PROPERTY NAMES")
+                        .help("The longhand properties to dump, e.g. `display font-size`.  All of them are dumped if none are given.")
+                        .multiple(true)
+                        .takes_value(true)
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("dump-a11y")
+                .about("Dumps the accessibility tree (each node's role, name, and states) as text to stdout after styling, exiting afterwards.")
+        )
+        .subcommand(
+            SubCommand::with_name("repl")
+                .about("Starts an interactive console reading commands (e.g. `load page.html`, `set viewport 800x600`, `query .card`, `dump layout`) from stdin and running them against a page kept between them, exiting once stdin ends or `quit` is run.  The first HTML file passed with --files is loaded to begin with, and pages are styled by the CSS files passed with it.  Run `help` in the console for its commands.")
+        )
+        .subcommand(
+            SubCommand::with_name("reftest")
+                .about("Renders the test and reference pages of each reftest in a manifest headlessly, compares their pixels, and reports which tests pass and fail, exiting afterwards.  Pages are rendered at 800x600 and a scale factor of 1 unless width, height, or scale-factor are given.")
+                .arg(
+                    Arg::with_name("manifest")
+                        .value_name("MANIFEST PATH")
+                        .help("The manifest listing the reftests to run, one per line, e.g. `== test.html test-ref.html`, `!= test.html test-ref.html`, or `fuzzy(2,40) == test.html test-ref.html`.")
+                        .required(true)
+                )
+                .arg(
+                    Arg::with_name("diff-dir")
+                        .long("diff-dir")
+                        .value_name("DIRECTORY PATH")
+                        .help("Write PNGs of the test, the reference, and the pixels that differ between them to the given directory for each failing reftest.")
+                        .takes_value(true)
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("wpt")
+                .about("Runs the reftests, crashtests, and check-layout-th.js tests of a Web Platform Tests checkout headlessly, and writes a wptreport JSON of their results to stdout, exiting afterwards.  Pages are rendered at 800x600 and a scale factor of 1 unless width, height, or scale-factor are given.")
+                .arg(
+                    Arg::with_name("checkout")
+                        .value_name("WPT CHECKOUT PATH")
+                        .help("The root of the Web Platform Tests checkout to run tests from.")
+                        .required(true)
+                )
+                .arg(
+                    Arg::with_name("include")
+                        .long("include")
+                        .value_name("SPACE SEPARATED PATHS")
+                        .help("Only run the tests under the given paths, relative to the root of the checkout, e.g. `css/CSS2/box`.")
+                        .multiple(true)
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::with_name("report")
+                        .long("report")
+                        .value_name("REPORT PATH")
+                        .help("Write the wptreport to the given file rather than stdout.")
+                        .takes_value(true)
+                )
+        )
+        .get_matches()
+}
+
+fn is_num_validator(string: String) -> Result<(), String> {
+    match string.parse::<f32>() {
+        Ok(_) => Ok(()),
+        Err(_) => Err(format!("given arg '{}' is not a number", string)),
+    }
+}
+
+fn is_port_validator(string: String) -> Result<(), String> {
+    match string.parse::<u16>() {
+        Ok(_) => Ok(()),
+        Err(_) => Err(format!("given arg '{}' is not a port number", string)),
+    }
+}
+
+fn is_viewport_validator(string: String) -> Result<(), String> {
+    match parse_viewport(&string) {
+        Some(_) => Ok(()),
+        None => Err(format!(
+            "given arg '{}' is not of the form WIDTHxHEIGHT",
+            string
+        )),
+    }
+}
+
+/// Parses a viewport size of the form `WIDTHxHEIGHT`, e.g. `800x600`.
+pub(crate) fn parse_viewport(string: &str) -> Option<(f32, f32)> {
+    let mut dimensions = string.splitn(2, 'x');
+    let width = dimensions.next()?.parse::<f32>().ok()?;
+    let height = dimensions.next()?.parse::<f32>().ok()?;
+    Some((width, height))
+}
+
+fn is_verbosity_validator(string: String) -> Result<(), String> {
+    match &string[..] {
+        "2" => Ok(()),
+        _ => is_bool_validator(string),
+    }
+}
+
+fn is_bool_validator(string: String) -> Result<(), String> {
+    match string.parse::<bool>() {
+        Ok(_) => Ok(()),
+        Err(_) => match &string[..] {
+            "0" | "1" => Ok(()),
+            _ => Err(format!("given arg '{}' is not a bool value", string)),
+        },
+    }
+}
+
+pub fn html_file_path_from_files<'a>(arg_matches: &'a ArgMatches<'a>) -> Option<&'a str> {
+    html_file_paths_from_files(arg_matches).into_iter().next()
+}
+
+/// Every HTML (or XHTML) file passed in `files`, in the order they were given.
+pub fn html_file_paths_from_files<'a>(arg_matches: &'a ArgMatches<'a>) -> Vec<&'a str> {
+    arg_matches
+        .values_of("files")
+        .map(|files| {
+            files
+                .filter(|file| {
+                    [".html", ".xhtml", ".xht"]
+                        .iter()
+                        .any(|ext| file.ends_with(ext))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub fn css_file_paths_from_files<'a>(arg_matches: &'a ArgMatches<'a>) -> Option<Vec<&'a str>> {
+    let files_opt = arg_matches.values_of("files");
+    files_opt.map(|files| {
+        files
+            .filter(|file| {
+                let parts = file.split('.');
+                if let Some(last_part) = parts.last() {
+                    return last_part == "css";
+                }
+                false
+            })
+            .collect::<Vec<_>>()
+    })
+}
+
+pub fn dump_layout_tree(arg_matches: &ArgMatches) -> bool {
+    arg_matches.subcommand_matches("dump-layout").is_some()
+}
+
+pub fn dump_display_list(arg_matches: &ArgMatches) -> bool {
+    arg_matches
+        .subcommand_matches("dump-display-list")
+        .is_some()
+}
+
+pub fn dump_stacking_contexts(arg_matches: &ArgMatches) -> bool {
+    arg_matches.subcommand_matches("dump-stacking").is_some()
+}
+
+pub fn dump_styles(arg_matches: &ArgMatches) -> bool {
+    arg_matches.subcommand_matches("dump-styles").is_some()
+}
+
+pub fn dump_accessibility_tree(arg_matches: &ArgMatches) -> bool {
+    arg_matches.subcommand_matches("dump-a11y").is_some()
+}
+
+pub fn repl(arg_matches: &ArgMatches) -> bool {
+    arg_matches.subcommand_matches("repl").is_some()
+}
+
+/// The names of the properties to dump with `dump-styles`, or `None` to dump all of them.
+pub fn dump_styles_properties<'a>(arg_matches: &'a ArgMatches<'a>) -> Option<Vec<&'a str>> {
+    arg_matches
+        .subcommand_matches("dump-styles")
+        .and_then(|dump_styles_arg_matches| dump_styles_arg_matches.values_of("properties"))
+        .map(|properties| properties.collect())
+}
+
+/// The selector restricting `dump-layout` to the boxes generated by matching elements, if any.
+pub fn dump_layout_filter<'a>(arg_matches: &'a ArgMatches<'a>) -> Option<&'a str> {
+    arg_matches
+        .subcommand_matches("dump-layout")
+        .and_then(|dump_layout_arg_matches| dump_layout_arg_matches.value_of("filter"))
+}
+
+/// Whether `dump-layout` should dump the descendants of boxes matching its filter.
+pub fn dump_layout_filter_includes_descendants(arg_matches: &ArgMatches) -> bool {
+    arg_matches
+        .subcommand_matches("dump-layout")
+        .map_or(false, |dump_layout_arg_matches| {
+            dump_layout_arg_matches.is_present("include-descendants")
+        })
+}
+
+/// The path of a previous layout-tree dump to compare `dump-layout`'s dump with, if any.
+pub fn dump_layout_diff_path<'a>(arg_matches: &'a ArgMatches<'a>) -> Option<&'a str> {
+    arg_matches
+        .subcommand_matches("dump-layout")
+        .and_then(|dump_layout_arg_matches| dump_layout_arg_matches.value_of("diff"))
+}
+
+/// The path of the manifest listing the reftests to run, if running reftests.
+pub fn reftest_manifest_path<'a>(arg_matches: &'a ArgMatches<'a>) -> Option<&'a str> {
+    arg_matches
+        .subcommand_matches("reftest")
+        .and_then(|reftest_arg_matches| reftest_arg_matches.value_of("manifest"))
+}
+
+/// The directory to write images of failing reftests to, if any.
+pub fn reftest_diff_dir<'a>(arg_matches: &'a ArgMatches<'a>) -> Option<&'a str> {
+    arg_matches
+        .subcommand_matches("reftest")
+        .and_then(|reftest_arg_matches| reftest_arg_matches.value_of("diff-dir"))
+}
+
+/// The root of the Web Platform Tests checkout to run tests from, if running them.
+pub fn wpt_checkout_path<'a>(arg_matches: &'a ArgMatches<'a>) -> Option<&'a str> {
+    arg_matches
+        .subcommand_matches("wpt")
+        .and_then(|wpt_arg_matches| wpt_arg_matches.value_of("checkout"))
+}
+
+/// The paths within the Web Platform Tests checkout to run the tests under.  Empty to run all of
+/// them.
+pub fn wpt_include_paths<'a>(arg_matches: &'a ArgMatches<'a>) -> Vec<&'a str> {
+    arg_matches
+        .subcommand_matches("wpt")
+        .and_then(|wpt_arg_matches| wpt_arg_matches.values_of("include"))
+        .map_or_else(Vec::new, |paths| paths.collect())
+}
+
+/// The file to write the wptreport of a Web Platform Tests run to, if not stdout.
+pub fn wpt_report_path<'a>(arg_matches: &'a ArgMatches<'a>) -> Option<&'a str> {
+    arg_matches
+        .subcommand_matches("wpt")
+        .and_then(|wpt_arg_matches| wpt_arg_matches.value_of("report"))
+}
+
+pub fn dump_layout_tree_verbose(arg_matches: &ArgMatches) -> Option<DumpLayoutVerbosity> {
+    let dump_layout_arg_matches = arg_matches.subcommand_matches("dump-layout")?;
+    if dump_layout_arg_matches.value_of("verbose") == Some("2") {
+        return Some(DumpLayoutVerbosity::VeryVerbose);
+    }
+    try_get_bool(dump_layout_arg_matches, "verbose").map(|bool_verbose| match bool_verbose {
+        true => DumpLayoutVerbosity::Verbose,
+        false => DumpLayoutVerbosity::NonVerbose,
+    })
+}
+
+pub fn layout_dump_format(arg_matches: &ArgMatches) -> LayoutDumpFormat {
+    match arg_matches
+        .subcommand_matches("dump-layout")
+        .and_then(|dump_layout_arg_matches| dump_layout_arg_matches.value_of("format"))
+    {
+        Some(format) if format == LayoutDumpFormat::Json.to_cli_string() => LayoutDumpFormat::Json,
+        _ => LayoutDumpFormat::Text,
+    }
+}
+
+pub fn renderer(arg_matches: &ArgMatches) -> Renderer {
+    match arg_matches.value_of("renderer") {
+        Some(renderer) if renderer == Renderer::Wgpu.to_cli_string() => Renderer::Wgpu,
+        _ => Renderer::OpenGl,
+    }
+}
+
+/// The color schemes the user can ask pages to be rendered in.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ColorSchemePreference {
+    /// The color scheme of the OS's theme.
+    System,
+    Light,
+    Dark,
+}
+
+impl ColorSchemePreference {
+    pub fn to_cli_string(&self) -> &'static str {
+        match self {
+            ColorSchemePreference::System => "system",
+            ColorSchemePreference::Light => "light",
+            ColorSchemePreference::Dark => "dark",
+        }
+    }
+}
+
+pub fn prefers_color_scheme(arg_matches: &ArgMatches) -> ColorSchemePreference {
+    match arg_matches.value_of("prefers-color-scheme") {
+        Some(scheme) if scheme == ColorSchemePreference::Light.to_cli_string() => {
+            ColorSchemePreference::Light
+        }
+        Some(scheme) if scheme == ColorSchemePreference::Dark.to_cli_string() => {
+            ColorSchemePreference::Dark
+        }
+        _ => ColorSchemePreference::System,
+    }
+}
+
+/// The motion preference given on the command line, or `None` to follow the OS.
+pub fn prefers_reduced_motion(arg_matches: &ArgMatches) -> Option<MotionPreference> {
+    match arg_matches.value_of("prefers-reduced-motion") {
+        Some("no-preference") => Some(MotionPreference::NoPreference),
+        Some("reduce") => Some(MotionPreference::Reduce),
+        _ => None,
+    }
+}
+
+/// The contrast preference given on the command line, or `None` to follow the OS.
+pub fn prefers_contrast(arg_matches: &ArgMatches) -> Option<ContrastPreference> {
+    match arg_matches.value_of("prefers-contrast") {
+        Some("no-preference") => Some(ContrastPreference::NoPreference),
+        Some("more") => Some(ContrastPreference::More),
+        Some("less") => Some(ContrastPreference::Less),
+        Some("custom") => Some(ContrastPreference::Custom),
+        _ => None,
+    }
+}
+
+/// Whether colors are forced according to the command line, or `None` to follow the OS.
+pub fn forced_colors(arg_matches: &ArgMatches) -> Option<ForcedColors> {
+    match arg_matches.value_of("forced-colors") {
+        Some("none") => Some(ForcedColors::None),
+        Some("active") => Some(ForcedColors::Active),
+        _ => None,
+    }
+}
+
+/// The media types pages can be rendered as, whatever they are actually being rendered to.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum EmulatedMedia {
+    Screen,
+    Print,
+}
+
+impl EmulatedMedia {
+    pub fn to_cli_string(&self) -> &'static str {
+        match self {
+            EmulatedMedia::Screen => "screen",
+            EmulatedMedia::Print => "print",
+        }
+    }
+}
+
+pub fn emulated_media(arg_matches: &ArgMatches) -> EmulatedMedia {
+    match arg_matches.value_of("media") {
+        Some(media) if media == EmulatedMedia::Print.to_cli_string() => EmulatedMedia::Print,
+        _ => EmulatedMedia::Screen,
+    }
+}
+
+pub fn test_fonts(arg_matches: &ArgMatches) -> bool {
+    arg_matches.is_present("test-fonts")
+}
+
+pub fn log_css_errors(arg_matches: &ArgMatches) -> bool {
+    arg_matches.is_present("log-css-errors")
+}
+
+pub fn report_unsupported(arg_matches: &ArgMatches) -> bool {
+    arg_matches.is_present("report-unsupported")
+}
+
+pub fn trace_output_path<'a>(arg_matches: &'a ArgMatches<'a>) -> Option<&'a str> {
+    arg_matches.value_of("trace-output")
+}
+
+pub fn screenshot_path<'a>(arg_matches: &'a ArgMatches<'a>) -> Option<&'a str> {
+    arg_matches.value_of("screenshot")
+}
+
+pub fn remote_debugging_port(arg_matches: &ArgMatches) -> Option<u16> {
+    try_get_arg::<u16>(arg_matches, "remote-debugging-port")
+}
+
+/// The origins of the web pages allowed to connect to the devtools server.
+pub fn remote_allow_origins<'a>(arg_matches: &'a ArgMatches<'a>) -> Vec<&'a str> {
+    arg_matches
+        .values_of("remote-allow-origins")
+        .map_or_else(Vec::new, |origins| origins.collect())
+}
+
+pub fn memory_report(arg_matches: &ArgMatches) -> bool {
+    arg_matches.is_present("memory-report")
+}
+
+pub fn print_to_pdf_path<'a>(arg_matches: &'a ArgMatches<'a>) -> Option<&'a str> {
+    arg_matches.value_of("print-to-pdf")
+}
+
+pub fn inner_window_width(arg_matches: &ArgMatches) -> Option<f32> {
+    try_get_arg::<f32>(arg_matches, "width").or_else(|| viewport(arg_matches).map(|(w, _)| w))
+}
+
+pub fn inner_window_height(arg_matches: &ArgMatches) -> Option<f32> {
+    try_get_arg::<f32>(arg_matches, "height").or_else(|| viewport(arg_matches).map(|(_, h)| h))
+}
+
+fn viewport(arg_matches: &ArgMatches) -> Option<(f32, f32)> {
+    arg_matches.value_of("viewport").and_then(parse_viewport)
+}
+
+pub fn scale_factor(arg_matches: &ArgMatches) -> Option<f32> {
+    try_get_arg::<f32>(arg_matches, "scale-factor")
+}
+
+fn try_get_arg<'a, T: FromStr>(arg_matches: &ArgMatches, arg_name: &'a str) -> Option<T> {
+    arg_matches
+        .value_of(arg_name)
+        .map(|arg_str| arg_str.parse::<T>().ok())
+        .unwrap_or(None)
+}
+
+fn try_get_bool<'a>(arg_matches: &ArgMatches, arg_name: &'a str) -> Option<bool> {
+    try_get_arg::<bool>(arg_matches, arg_name).or_else(|| {
+        let arg_match = arg_matches.value_of(arg_name);
+        arg_match.and_then(|val| match val {
+            "0" => Some(false),
+            "1" => Some(true),
+            _ => None,
+        })
+    })
+}
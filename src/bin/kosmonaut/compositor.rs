@@ -0,0 +1,721 @@
+//! The browser window's event loop, run on the window's thread: the compositor.  It paints the
+//! last frame the pipeline sent, scrolled to where the user has scrolled it, and sends the
+//! pipeline the input that needs the DOM, so the window stays responsive while the pipeline
+//! styles and lays out documents on its own thread (see `pipeline`).
+
+use std::collections::HashMap;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use copypasta::{ClipboardContext, ClipboardProvider};
+use glutin::dpi::PhysicalSize;
+use glutin::event::{
+    ElementState, Event, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta,
+    VirtualKeyCode, WindowEvent,
+};
+use glutin::event_loop::{ControlFlow, EventLoop, EventLoopProxy};
+use glutin::window::{CursorIcon, Icon, Theme, Window};
+
+use crate::devtools;
+use crate::devtools::server::ServerOptions;
+use crate::pipeline::{
+    CompositorMsg, Frame, KeyInput, LoadedDocuments, MouseAction, MouseInput, Pipeline,
+    PipelineMsg, Viewport,
+};
+use crate::system::system_color_scheme;
+use crate::tab::{TabCommand, TabSwitch, ZoomStep};
+use kosmonaut_core::dom::editing::{CaretMovement, EditCommand};
+use kosmonaut_core::dom::event::{KeyboardEvent, Modifiers, MouseEvent};
+use kosmonaut_core::dom::focus::FocusDirection;
+use kosmonaut_core::dom::tree::OpaqueNode;
+use kosmonaut_core::gfx::backend::Backend;
+use kosmonaut_core::gfx::kosmonaut_icon;
+use kosmonaut_core::layout::rect::Point;
+use kosmonaut_core::layout::scroll::{RootScroller, LINE_SCROLL_DISTANCE_PX};
+use kosmonaut_core::style::media::ColorScheme;
+use kosmonaut_core::style::values::computed::Cursor;
+use kosmonaut_core::{dom, trace};
+
+/// How often frames are painted while the document is being smoothly scrolled, which is about 60
+/// times a second.
+const ANIMATION_FRAME_INTERVAL: Duration = Duration::from_micros(16_667);
+
+/// Opens a window compositing the frames of the documents the pipeline loads with `load`, each in
+/// its own tab, and runs its event loop until the window (or its last tab) is closed.
+///
+/// If the documents follow the color scheme of the OS's theme, `system_color_scheme` is the color
+/// scheme they were loaded in, and they're restyled whenever it changes.  If the user prefers
+/// reduced motion, scrolling jumps straight to where it's scrolled to rather than animating.  If
+/// a devtools server is given, devtools clients can inspect the active tab through it.
+pub fn run_event_loop<L>(
+    event_loop: EventLoop<CompositorMsg>,
+    backend: Box<dyn Backend>,
+    cli_specified_scale_factor: Option<f32>,
+    system_color_scheme: Option<ColorScheme>,
+    reduce_motion: bool,
+    devtools_server: Option<ServerOptions>,
+    load: L,
+) where
+    L: FnOnce() -> LoadedDocuments + Send + 'static,
+{
+    let scale = cli_specified_scale_factor.unwrap_or_else(|| {
+        sanitize_windowed_context_scale_factor(backend.window().scale_factor() as f32)
+    });
+    let proxy = event_loop.create_proxy();
+    // Messages can't be sent once the event loop has exited, by which time nothing's left to
+    // receive them.
+    let pipeline = Pipeline::spawn(load, window_viewport(backend.window(), scale), move |msg| {
+        proxy.send_event(msg).ok();
+    });
+    if let Some(options) = devtools_server {
+        let port = options.port;
+        let pipeline = pipeline.clone();
+        let send_to_pipeline = move |command| pipeline.send(PipelineMsg::DevTools(command));
+        if let Err(err) = devtools::server::serve(options, send_to_pipeline) {
+            eprintln!("couldn't serve devtools on port {}: {}", port, err);
+        }
+    }
+    let mut compositor = Compositor {
+        proxy: event_loop.create_proxy(),
+        backend,
+        pipeline,
+        cli_specified_scale_factor,
+        scale,
+        system_color_scheme,
+        checking_system_color_scheme: false,
+        reduce_motion,
+        frame: None,
+        scrollers: HashMap::new(),
+        reported_scroll: None,
+        modifiers: ModifiersState::empty(),
+        cursor_position: Point::default(),
+        clipboard: None,
+    };
+    event_loop.run(move |event, _, control_flow| {
+        let now = Instant::now();
+        *control_flow = compositor.settle_scroll(now);
+        match event {
+            Event::LoopDestroyed => trace::finish(),
+            Event::UserEvent(msg) => compositor.handle_message(msg, now, control_flow),
+            Event::RedrawRequested(_) => compositor.paint(now),
+            Event::WindowEvent { ref event, .. } => {
+                compositor.handle_window_event(event, now, control_flow)
+            }
+            _ => (),
+        }
+    });
+}
+
+/// What the compositor keeps between the events of the window's event loop.
+struct Compositor {
+    /// Sends the compositor messages from threads other than the pipeline's.
+    proxy: EventLoopProxy<CompositorMsg>,
+    backend: Box<dyn Backend>,
+    pipeline: Pipeline,
+    /// The scale factor given on the command line, which overrides the window's, so Kosmonaut
+    /// renders the same on any machine.
+    cli_specified_scale_factor: Option<f32>,
+    /// Device pixels per CSS pixel, before the document is zoomed.
+    scale: f32,
+    /// The color scheme of the OS's theme, if the documents follow it.
+    system_color_scheme: Option<ColorScheme>,
+    /// Whether the color scheme of the OS's theme is being found, on a thread of its own.
+    checking_system_color_scheme: bool,
+    reduce_motion: bool,
+    /// The last frame the pipeline sent, which is painted until the next arrives.
+    frame: Option<Frame>,
+    /// The scroll position of each open document, which is kept by the compositor so scrolling
+    /// doesn't wait on the pipeline.
+    scrollers: HashMap<OpaqueNode, RootScroller>,
+    /// The document and scroll position last reported to the pipeline, which lays out
+    /// `content-visibility: auto` elements as they come into view.
+    reported_scroll: Option<(OpaqueNode, Point)>,
+    modifiers: ModifiersState,
+    /// The position of the mouse cursor in CSS pixels, relative to the viewport.
+    cursor_position: Point,
+    /// The system clipboard, which is opened the first time text is copied.
+    clipboard: Option<ClipboardContext>,
+}
+
+impl Compositor {
+    /// The scroller of the document the last frame is of, if a frame has arrived.
+    fn scroller(&self) -> Option<&RootScroller> {
+        self.frame
+            .as_ref()
+            .and_then(|frame| self.scrollers.get(&frame.document))
+    }
+
+    fn scroller_mut(&mut self) -> Option<&mut RootScroller> {
+        let frame = self.frame.as_ref()?;
+        Some(self.scrollers.entry(frame.document).or_default())
+    }
+
+    /// Device pixels per CSS pixel, which until the first frame arrives isn't zoomed.
+    fn css_scale(&self) -> f32 {
+        self.frame
+            .as_ref()
+            .map_or(self.scale, |frame| frame.scale_factor)
+    }
+
+    fn scroll_position(&self, now: Instant) -> Point {
+        self.scroller()
+            .map_or(Point::default(), |scroller| scroller.position_at(now))
+    }
+
+    /// Settles a continuous scroll on a snap position once it stops, and returns how long the
+    /// event loop can wait: until the next frame of a smooth scroll is painted, or a continuous
+    /// one settles.
+    fn settle_scroll(&mut self, now: Instant) -> ControlFlow {
+        let reduce_motion = self.reduce_motion;
+        if let Some(scroller) = self.scroller_mut() {
+            if scroller.settle(now, !reduce_motion) {
+                self.backend.window().request_redraw();
+            }
+        }
+        let scroller = self.scroller();
+        let is_scrolling = scroller.map_or(false, |scroller| scroller.is_animating(now));
+        match scroller.and_then(RootScroller::settles_at) {
+            _ if is_scrolling => ControlFlow::WaitUntil(now + ANIMATION_FRAME_INTERVAL),
+            Some(settles_at) => ControlFlow::WaitUntil(settles_at),
+            None => ControlFlow::Wait,
+        }
+    }
+
+    /// Scrolls the document by `delta`, smoothly unless the user prefers reduced motion.
+    fn scroll_by(&mut self, delta: Point, now: Instant) {
+        let reduce_motion = self.reduce_motion;
+        if let Some(scroller) = self.scroller_mut() {
+            if reduce_motion {
+                scroller.jump_by(delta, now);
+            } else {
+                scroller.scroll_by(delta, now);
+            }
+            self.backend.window().request_redraw()
+        }
+    }
+
+    fn handle_message(&mut self, msg: CompositorMsg, now: Instant, control_flow: &mut ControlFlow) {
+        match msg {
+            CompositorMsg::Frame(new_frame) => {
+                let scroller = self.scrollers.entry(new_frame.document).or_default();
+                scroller.set_max_position(new_frame.max_scroll_position);
+                scroller.set_snap_positions(new_frame.snap_positions.clone());
+                self.frame = Some(new_frame);
+                self.backend.window().request_redraw()
+            }
+            CompositorMsg::ScrollBy(delta) => self.scroll_by(delta, now),
+            CompositorMsg::ScrollTo { position, smooth } => {
+                let reduce_motion = self.reduce_motion;
+                if let Some(scroller) = self.scroller_mut() {
+                    if smooth && !reduce_motion {
+                        scroller.scroll_to(position, now);
+                    } else {
+                        scroller.jump_to(position);
+                    }
+                    self.backend.window().request_redraw()
+                }
+            }
+            CompositorMsg::SetTitle(title) => self.backend.window().set_title(&title),
+            CompositorMsg::SetCursor(cursor) => match cursor_icon(cursor) {
+                Some(icon) => {
+                    self.backend.window().set_cursor_icon(icon);
+                    self.backend.window().set_cursor_visible(true);
+                }
+                None => self.backend.window().set_cursor_visible(false),
+            },
+            CompositorMsg::SetClipboard(text) => {
+                if self.clipboard.is_none() {
+                    self.clipboard = ClipboardContext::new()
+                        .map_err(|err| eprintln!("couldn't open the clipboard: {}", err))
+                        .ok();
+                }
+                if let Some(clipboard) = &mut self.clipboard {
+                    if let Err(err) = clipboard.set_contents(text) {
+                        eprintln!("couldn't copy to the clipboard: {}", err);
+                    }
+                }
+            }
+            CompositorMsg::SetIcon(icon) => {
+                let icon = icon.and_then(|icon| {
+                    Icon::from_rgba(icon.rgba, icon.width, icon.height)
+                        .map_err(|err| eprintln!("couldn't use the document's icon: {}", err))
+                        .ok()
+                });
+                self.backend
+                    .window()
+                    .set_window_icon(Some(icon.unwrap_or_else(kosmonaut_icon)));
+            }
+            CompositorMsg::DocumentClosed(document) => {
+                self.scrollers.remove(&document);
+            }
+            CompositorMsg::SystemColorScheme(color_scheme) => {
+                self.checking_system_color_scheme = false;
+                self.set_system_color_scheme(color_scheme)
+            }
+            CompositorMsg::Exit => *control_flow = ControlFlow::Exit,
+        }
+    }
+
+    /// Paints the last frame, scrolled to where it's scrolled to `now`, and tells the pipeline
+    /// where that is if it's moved.
+    fn paint(&mut self, now: Instant) {
+        let scroll_position = self.scroll_position(now);
+        if let Some(frame) = &self.frame {
+            let mut display_list = frame.display_list.clone();
+            display_list.translate(-scroll_position.x, -scroll_position.y);
+            self.backend.paint(display_list, frame.scale_factor);
+            if self.reported_scroll != Some((frame.document, scroll_position)) {
+                self.reported_scroll = Some((frame.document, scroll_position));
+                self.pipeline.send(PipelineMsg::Scroll(scroll_position));
+            }
+        }
+    }
+
+    /// Tells the pipeline the window's viewport changed, e.g. because the window was resized.
+    fn resize(&mut self, physical_size: PhysicalSize<u32>) {
+        self.backend.resize(physical_size);
+        self.pipeline.send(PipelineMsg::Resize(window_viewport(
+            self.backend.window(),
+            self.scale,
+        )));
+        self.backend.window().request_redraw()
+    }
+
+    /// Finds the color scheme of the OS's theme on a thread of its own, which sends it back as a
+    /// `SystemColorScheme` message, so the window isn't held up by the programs run to find it.
+    /// Nothing's done if it's already being found.
+    fn check_system_color_scheme(&mut self) {
+        if self.checking_system_color_scheme {
+            return;
+        }
+        let proxy = self.proxy.clone();
+        let spawned = thread::Builder::new()
+            .name("system color scheme".to_owned())
+            .spawn(move || {
+                // The window may have closed by the time it's found.
+                proxy
+                    .send_event(CompositorMsg::SystemColorScheme(system_color_scheme()))
+                    .ok();
+            });
+        match spawned {
+            Ok(_) => self.checking_system_color_scheme = true,
+            Err(err) => eprintln!("couldn't check the OS's color scheme: {}", err),
+        }
+    }
+
+    fn set_system_color_scheme(&mut self, color_scheme: ColorScheme) {
+        if self.system_color_scheme != Some(color_scheme) {
+            self.system_color_scheme = Some(color_scheme);
+            self.pipeline.send(PipelineMsg::ColorScheme(color_scheme));
+        }
+    }
+
+    fn handle_window_event(
+        &mut self,
+        event: &WindowEvent,
+        now: Instant,
+        control_flow: &mut ControlFlow,
+    ) {
+        let css_scale = self.css_scale();
+        let scroll_position = self.scroll_position(now);
+        match event {
+            // The initial containing block is the size of the window, so resizing it reflows the
+            // document (in the pipeline's next frame), and can change which media queries match
+            // and what viewport-percentage lengths compute to.
+            WindowEvent::Resized(physical_size) => self.resize(*physical_size),
+            WindowEvent::ScaleFactorChanged {
+                scale_factor,
+                new_inner_size,
+            } => {
+                if self.cli_specified_scale_factor.is_none() {
+                    self.scale = sanitize_windowed_context_scale_factor(*scale_factor as f32);
+                }
+                self.resize(**new_inner_size)
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = Point {
+                    x: position.x as f32 / css_scale,
+                    y: position.y as f32 / css_scale,
+                };
+                self.pipeline.send(PipelineMsg::Mouse(MouseInput {
+                    action: MouseAction::Move,
+                    event: dom_mouse_event(
+                        self.cursor_position,
+                        dom::event::MouseButton::Primary,
+                        self.modifiers,
+                    ),
+                    scroll_position,
+                }));
+            }
+            // Mouse wheels scroll by lines, which are smoothly scrolled to.
+            WindowEvent::MouseWheel {
+                delta: MouseScrollDelta::LineDelta(x, y),
+                ..
+            } => {
+                let delta = Point {
+                    x: -x * LINE_SCROLL_DISTANCE_PX,
+                    y: -y * LINE_SCROLL_DISTANCE_PX,
+                };
+                self.scroll_by(delta, now)
+            }
+            // Trackpads report how far they scrolled in device pixels, and already scroll
+            // smoothly.
+            WindowEvent::MouseWheel {
+                delta: MouseScrollDelta::PixelDelta(position),
+                ..
+            } => {
+                if let Some(scroller) = self.scroller_mut() {
+                    let delta = Point {
+                        x: -position.x as f32 / css_scale,
+                        y: -position.y as f32 / css_scale,
+                    };
+                    scroller.drag_by(delta, now);
+                    self.backend.window().request_redraw()
+                }
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                if let Some(button) = dom_mouse_button(*button) {
+                    self.pipeline.send(PipelineMsg::Mouse(MouseInput {
+                        action: match state {
+                            ElementState::Pressed => MouseAction::Press,
+                            ElementState::Released => MouseAction::Release,
+                        },
+                        event: dom_mouse_event(self.cursor_position, button, self.modifiers),
+                        scroll_position,
+                    }));
+                }
+            }
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers = *modifiers,
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => self.handle_key(*state, *keycode, css_scale),
+            WindowEvent::ReceivedCharacter(ch) => {
+                if let Some(command) = edit_command_for_character(*ch, self.modifiers) {
+                    self.pipeline.send(PipelineMsg::Edit(command));
+                }
+            }
+            WindowEvent::ThemeChanged(theme) if self.system_color_scheme.is_some() => self
+                .set_system_color_scheme(match theme {
+                    Theme::Light => ColorScheme::Light,
+                    Theme::Dark => ColorScheme::Dark,
+                }),
+            // Only Windows says when its theme changes, so otherwise the theme is checked whenever
+            // the window is focused, as it will have lost focus to the OS's settings while the
+            // theme was changed.
+            WindowEvent::Focused(true) if self.system_color_scheme.is_some() => {
+                self.check_system_color_scheme()
+            }
+            WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+            _ => (),
+        }
+    }
+
+    fn handle_key(&mut self, state: ElementState, keycode: VirtualKeyCode, css_scale: f32) {
+        let modifiers = self.modifiers;
+        let pressed = state == ElementState::Pressed;
+        if pressed && is_inspect_shortcut(keycode, modifiers) {
+            // Like the tab switcher's shortcuts, this is handled by the browser.
+            self.pipeline.send(PipelineMsg::ToggleInspector);
+            return;
+        }
+        // The tab switcher's shortcuts are handled by the browser, so pages can't cancel them.
+        if let Some(command) = tab_command_for_key(keycode, modifiers).filter(|_| pressed) {
+            self.pipeline.send(PipelineMsg::Tab(command));
+            return;
+        }
+        let viewport_height = self.backend.window().inner_size().height as f32 / css_scale;
+        self.pipeline.send(PipelineMsg::Key(KeyInput {
+            pressed,
+            event: KeyboardEvent {
+                key: key_value(keycode, modifiers),
+                modifiers: dom_modifiers(modifiers),
+            },
+            zoom: zoom_step_for_key(keycode, modifiers),
+            scroll: scroll_delta_for_key(keycode, modifiers, viewport_height),
+            edit: edit_command_for_key(keycode, modifiers),
+            copy: is_copy_shortcut(keycode, modifiers),
+            focus_navigation: focus_direction_for_key(keycode, modifiers),
+        }));
+    }
+}
+
+/// The tab switcher command a press of `keycode` runs, if it's one of its shortcuts, which
+/// are the same as other browsers': Ctrl (or Cmd) with `T` to open a new tab, `W` to close
+/// the active one, Tab or Page Down to switch to the next tab, Shift+Tab or Page Up to the
+/// previous one, `1` through `8` to the tab at that position, and `9` to the last tab.
+fn tab_command_for_key(keycode: VirtualKeyCode, modifiers: ModifiersState) -> Option<TabCommand> {
+    if !(modifiers.ctrl() || modifiers.logo()) {
+        return None;
+    }
+    let digit_keys = [
+        VirtualKeyCode::Key1,
+        VirtualKeyCode::Key2,
+        VirtualKeyCode::Key3,
+        VirtualKeyCode::Key4,
+        VirtualKeyCode::Key5,
+        VirtualKeyCode::Key6,
+        VirtualKeyCode::Key7,
+        VirtualKeyCode::Key8,
+    ];
+    let command = match keycode {
+        VirtualKeyCode::T => TabCommand::New,
+        VirtualKeyCode::W => TabCommand::Close,
+        VirtualKeyCode::Tab if modifiers.shift() => TabCommand::Switch(TabSwitch::Previous),
+        VirtualKeyCode::Tab | VirtualKeyCode::PageDown => TabCommand::Switch(TabSwitch::Next),
+        VirtualKeyCode::PageUp => TabCommand::Switch(TabSwitch::Previous),
+        VirtualKeyCode::Key9 => TabCommand::Switch(TabSwitch::Last),
+        keycode => TabCommand::Switch(TabSwitch::Index(
+            digit_keys
+                .iter()
+                .position(|digit_key| *digit_key == keycode)?,
+        )),
+    };
+    Some(command)
+}
+
+/// The size of `window`'s viewport, at `scale_factor` device pixels per CSS pixel.
+fn window_viewport(window: &Window, scale_factor: f32) -> Viewport {
+    let inner_window_size = window.inner_size();
+    Viewport {
+        width: inner_window_size.width as f32,
+        height: inner_window_size.height as f32,
+        scale_factor,
+    }
+}
+
+fn dom_modifiers(modifiers: ModifiersState) -> Modifiers {
+    Modifiers {
+        shift: modifiers.shift(),
+        ctrl: modifiers.ctrl(),
+        alt: modifiers.alt(),
+        meta: modifiers.logo(),
+    }
+}
+
+fn dom_mouse_button(button: MouseButton) -> Option<dom::event::MouseButton> {
+    match button {
+        MouseButton::Left => Some(dom::event::MouseButton::Primary),
+        MouseButton::Middle => Some(dom::event::MouseButton::Auxiliary),
+        MouseButton::Right => Some(dom::event::MouseButton::Secondary),
+        MouseButton::Other(_) => None,
+    }
+}
+
+fn dom_mouse_event(
+    position: Point,
+    button: dom::event::MouseButton,
+    modifiers: ModifiersState,
+) -> MouseEvent {
+    MouseEvent {
+        client_x: position.x,
+        client_y: position.y,
+        button,
+        modifiers: dom_modifiers(modifiers),
+    }
+}
+
+/// The key value of the given key.
+///
+/// TODO: The key value of a character key is the character it types, which depends on the
+/// keyboard layout.  For now, only letters and digits are given key values.
+/// https://w3c.github.io/uievents-key/
+fn key_value(keycode: VirtualKeyCode, modifiers: ModifiersState) -> String {
+    let key = match keycode {
+        VirtualKeyCode::Back => "Backspace",
+        VirtualKeyCode::Tab => "Tab",
+        VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => "Enter",
+        VirtualKeyCode::Escape => "Escape",
+        VirtualKeyCode::Space => " ",
+        VirtualKeyCode::Insert => "Insert",
+        VirtualKeyCode::Delete => "Delete",
+        VirtualKeyCode::Home => "Home",
+        VirtualKeyCode::End => "End",
+        VirtualKeyCode::PageUp => "PageUp",
+        VirtualKeyCode::PageDown => "PageDown",
+        VirtualKeyCode::Left => "ArrowLeft",
+        VirtualKeyCode::Right => "ArrowRight",
+        VirtualKeyCode::Up => "ArrowUp",
+        VirtualKeyCode::Down => "ArrowDown",
+        VirtualKeyCode::LShift | VirtualKeyCode::RShift => "Shift",
+        VirtualKeyCode::LControl | VirtualKeyCode::RControl => "Control",
+        VirtualKeyCode::LAlt | VirtualKeyCode::RAlt => "Alt",
+        VirtualKeyCode::LWin | VirtualKeyCode::RWin => "Meta",
+        _ => {
+            // Letter keys are named after their letter, and digit keys `Key0` through `Key9`.
+            let name = format!("{:?}", keycode);
+            let name = name.strip_prefix("Key").unwrap_or(&name);
+            return match name.chars().next() {
+                Some(ch) if name.len() == 1 && ch.is_ascii_digit() => name.to_owned(),
+                Some(ch) if name.len() == 1 && modifiers.shift() => ch.to_string(),
+                Some(ch) if name.len() == 1 => ch.to_ascii_lowercase().to_string(),
+                _ => "Unidentified".to_owned(),
+            };
+        }
+    };
+    key.to_owned()
+}
+
+/// Translates a typed character into an editing command.  Control characters (e.g. from
+/// backspace) are handled as key presses instead, except for line breaks.
+fn edit_command_for_character(ch: char, modifiers: ModifiersState) -> Option<EditCommand> {
+    if modifiers.ctrl() || modifiers.logo() {
+        return None;
+    }
+    match ch {
+        '\r' | '\n' => Some(EditCommand::Insert('\n')),
+        ch if ch.is_control() => None,
+        ch => Some(EditCommand::Insert(ch)),
+    }
+}
+
+/// Translates a press of a key that doesn't type a character into an editing command.
+fn edit_command_for_key(keycode: VirtualKeyCode, modifiers: ModifiersState) -> Option<EditCommand> {
+    let move_caret = |movement| {
+        Some(EditCommand::Move {
+            movement,
+            extend_selection: modifiers.shift(),
+        })
+    };
+    match keycode {
+        VirtualKeyCode::Back => Some(EditCommand::DeleteBackward),
+        VirtualKeyCode::Delete => Some(EditCommand::DeleteForward),
+        VirtualKeyCode::Left => move_caret(CaretMovement::Left),
+        VirtualKeyCode::Right => move_caret(CaretMovement::Right),
+        VirtualKeyCode::Up => move_caret(CaretMovement::Up),
+        VirtualKeyCode::Down => move_caret(CaretMovement::Down),
+        VirtualKeyCode::Home => move_caret(CaretMovement::LineStart),
+        VirtualKeyCode::End => move_caret(CaretMovement::LineEnd),
+        VirtualKeyCode::A if modifiers.ctrl() || modifiers.logo() => Some(EditCommand::SelectAll),
+        _ => None,
+    }
+}
+
+/// The OS cursor to show for `cursor`, or `None` if it hides the cursor.
+fn cursor_icon(cursor: Cursor) -> Option<CursorIcon> {
+    Some(match cursor {
+        Cursor::None => return None,
+        Cursor::Auto | Cursor::Default => CursorIcon::Default,
+        Cursor::ContextMenu => CursorIcon::ContextMenu,
+        Cursor::Help => CursorIcon::Help,
+        Cursor::Pointer => CursorIcon::Hand,
+        Cursor::Progress => CursorIcon::Progress,
+        Cursor::Wait => CursorIcon::Wait,
+        Cursor::Cell => CursorIcon::Cell,
+        Cursor::Crosshair => CursorIcon::Crosshair,
+        Cursor::Text => CursorIcon::Text,
+        Cursor::VerticalText => CursorIcon::VerticalText,
+        Cursor::Alias => CursorIcon::Alias,
+        Cursor::Copy => CursorIcon::Copy,
+        Cursor::Move => CursorIcon::Move,
+        Cursor::NoDrop => CursorIcon::NoDrop,
+        Cursor::NotAllowed => CursorIcon::NotAllowed,
+        Cursor::Grab => CursorIcon::Grab,
+        Cursor::Grabbing => CursorIcon::Grabbing,
+        Cursor::EResize => CursorIcon::EResize,
+        Cursor::NResize => CursorIcon::NResize,
+        Cursor::NeResize => CursorIcon::NeResize,
+        Cursor::NwResize => CursorIcon::NwResize,
+        Cursor::SResize => CursorIcon::SResize,
+        Cursor::SeResize => CursorIcon::SeResize,
+        Cursor::SwResize => CursorIcon::SwResize,
+        Cursor::WResize => CursorIcon::WResize,
+        Cursor::EwResize => CursorIcon::EwResize,
+        Cursor::NsResize => CursorIcon::NsResize,
+        Cursor::NeswResize => CursorIcon::NeswResize,
+        Cursor::NwseResize => CursorIcon::NwseResize,
+        Cursor::ColResize => CursorIcon::ColResize,
+        Cursor::RowResize => CursorIcon::RowResize,
+        Cursor::AllScroll => CursorIcon::AllScroll,
+        Cursor::ZoomIn => CursorIcon::ZoomIn,
+        Cursor::ZoomOut => CursorIcon::ZoomOut,
+    })
+}
+
+/// Which way a press of `keycode` moves focus, if it's Tab (forward) or Shift+Tab (backward).
+fn focus_direction_for_key(
+    keycode: VirtualKeyCode,
+    modifiers: ModifiersState,
+) -> Option<FocusDirection> {
+    match keycode {
+        VirtualKeyCode::Tab if !(modifiers.ctrl() || modifiers.logo() || modifiers.alt()) => {
+            Some(if modifiers.shift() {
+                FocusDirection::Backward
+            } else {
+                FocusDirection::Forward
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Whether a press of `keycode` toggles inspecting the page, i.e. whether it's Ctrl (or Cmd)
+/// with `Shift+C`.
+fn is_inspect_shortcut(keycode: VirtualKeyCode, modifiers: ModifiersState) -> bool {
+    keycode == VirtualKeyCode::C && modifiers.shift() && (modifiers.ctrl() || modifiers.logo())
+}
+
+/// Whether a press of `keycode` copies the selected text, i.e. whether it's Ctrl (or Cmd)
+/// with `C`.
+fn is_copy_shortcut(keycode: VirtualKeyCode, modifiers: ModifiersState) -> bool {
+    keycode == VirtualKeyCode::C && (modifiers.ctrl() || modifiers.logo())
+}
+
+/// How a press of `keycode` zooms the page, if it's one of the zoom shortcuts: Ctrl (or Cmd)
+/// with `+` or `-` to step through the zoom levels, or with `0` to reset the zoom.
+fn zoom_step_for_key(keycode: VirtualKeyCode, modifiers: ModifiersState) -> Option<ZoomStep> {
+    if !(modifiers.ctrl() || modifiers.logo()) {
+        return None;
+    }
+    match keycode {
+        VirtualKeyCode::Equals | VirtualKeyCode::Plus | VirtualKeyCode::NumpadAdd => {
+            Some(ZoomStep::In)
+        }
+        VirtualKeyCode::Minus | VirtualKeyCode::NumpadSubtract => Some(ZoomStep::Out),
+        VirtualKeyCode::Key0 | VirtualKeyCode::Numpad0 => Some(ZoomStep::Reset),
+        _ => None,
+    }
+}
+
+/// How far a press of `keycode` scrolls the document, if it's a key that scrolls: the arrow
+/// keys scroll by a line, Page Up, Page Down, and Space (with Shift to go up) by a page, and
+/// Home and End to the start or end of the document.
+fn scroll_delta_for_key(
+    keycode: VirtualKeyCode,
+    modifiers: ModifiersState,
+    viewport_height: f32,
+) -> Option<Point> {
+    // Pages scroll by a little less than the viewport, so some of what was in view stays in
+    // view for context.
+    let page = viewport_height * 0.875;
+    let (x, y) = match keycode {
+        VirtualKeyCode::Up => (0., -LINE_SCROLL_DISTANCE_PX),
+        VirtualKeyCode::Down => (0., LINE_SCROLL_DISTANCE_PX),
+        VirtualKeyCode::Left => (-LINE_SCROLL_DISTANCE_PX, 0.),
+        VirtualKeyCode::Right => (LINE_SCROLL_DISTANCE_PX, 0.),
+        VirtualKeyCode::PageUp => (0., -page),
+        VirtualKeyCode::PageDown => (0., page),
+        VirtualKeyCode::Space if modifiers.shift() => (0., -page),
+        VirtualKeyCode::Space => (0., page),
+        // Scrolls are clamped to the document, so scrolling infinitely far reaches its ends.
+        VirtualKeyCode::Home => (0., f32::NEG_INFINITY),
+        VirtualKeyCode::End => (0., f32::INFINITY),
+        _ => return None,
+    };
+    Some(Point { x, y })
+}
+
+fn sanitize_windowed_context_scale_factor(scale_factor: f32) -> f32 {
+    // Round the scale factor Glutin / Winit reports to the nearest integer.
+    // This is a hack, and should go away eventually.  I've done it to make Kosmonaut match Firefox's
+    // scale factor on X11, as before we were getting a scale factor of 1.16 while Firefox and others
+    // use a scale factor of 1.  This behavior is definitely wrong, as sometimes fractional scaling
+    // _is_ correct (e.g. Windows allows 1.25, 1.5, etc).  Read more here:
+    // https://docs.rs/winit/0.24.0/winit/dpi/index.html#how-is-the-scale-factor-calculated
+    scale_factor.round()
+}
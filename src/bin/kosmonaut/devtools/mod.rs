@@ -0,0 +1,361 @@
+//! A subset of the Chrome DevTools Protocol (CDP), which lets existing devtools frontends and
+//! automation tools inspect the active tab of the window: its DOM, the computed styles and box
+//! model of its elements, and screenshots of it.
+//!
+//! Clients connect to the server (see `server::serve`), which sends each command they send to the
+//! pipeline as a `DevToolsCommand`, since the DOM never leaves the pipeline's thread.  The pipeline
+//! brings the active tab's rendering up to date, runs the command with `run_command`, and replies
+//! with its result, which the server sends back to the client.
+//!
+//! Nodes are identified to clients by their `OpaqueNode`, so the same node keeps its id for as
+//! long as it lives without the server having to remember which nodes it described.
+//!
+//! The window has a built-in inspector too, which highlights the element under the mouse (see
+//! `overlay`).
+//!
+//! https://chromedevtools.github.io/devtools-protocol/
+
+pub mod overlay;
+pub mod server;
+
+use kosmonaut_core::dom::metadata;
+use kosmonaut_core::dom::tree::{NodeData, NodeRef};
+use kosmonaut_core::gfx::display::DisplayList;
+use kosmonaut_core::gfx::raster::rasterize;
+use kosmonaut_core::image::encode_png;
+use kosmonaut_core::layout::geometry::box_geometry;
+use kosmonaut_core::layout::layout_box::LayoutBox;
+use kosmonaut_core::layout::rect::{Point, Rect};
+use kosmonaut_core::style::properties::id::LonghandId;
+use serde_json::{json, Value};
+use std::sync::mpsc::Sender;
+use strum::IntoEnumIterator;
+
+/// The id of the only target clients can inspect, which is whatever document the active tab shows.
+pub const PAGE_TARGET_ID: &str = "page";
+
+/// A command a devtools client sent, to be run by the pipeline against the active tab.
+#[derive(Clone, Debug)]
+pub struct DevToolsCommand {
+    /// The name of the command, e.g. `DOM.getDocument`.
+    pub method: String,
+    pub params: Value,
+    /// Where to send the result of the command.
+    pub reply: Sender<Result<Value, CommandError>>,
+}
+
+/// Why a command failed, as reported to the client.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommandError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl CommandError {
+    fn method_not_found(method: &str) -> CommandError {
+        CommandError {
+            code: -32601,
+            message: format!("'{}' wasn't found", method),
+        }
+    }
+
+    fn invalid_params(message: &str) -> CommandError {
+        CommandError {
+            code: -32602,
+            message: message.to_owned(),
+        }
+    }
+
+    fn server_error(message: &str) -> CommandError {
+        CommandError {
+            code: -32000,
+            message: message.to_owned(),
+        }
+    }
+}
+
+/// What commands are run against: the active tab's document, as it was last painted.
+pub struct Target<'a> {
+    pub dom: &'a NodeRef,
+    /// The URL of the document.
+    pub url: String,
+    pub box_tree: Option<&'a LayoutBox>,
+    /// The display list the document was last painted with, before it was scrolled.
+    pub display_list: &'a DisplayList,
+    /// The size of the viewport in device pixels.
+    pub width: u32,
+    pub height: u32,
+    /// The number of device pixels per CSS pixel, including the tab's zoom.
+    pub scale_factor: f32,
+}
+
+impl Target<'_> {
+    /// Where the document is scrolled to, which the geometry of what the client sees is relative
+    /// to.
+    fn scroll_position(&self) -> Point {
+        self.dom
+            .as_document()
+            .map_or(Point::default(), |document| document.scroll_position.get())
+    }
+
+    /// The node of the document with the given id, which clients got from an earlier command.
+    fn node(&self, params: &Value) -> Result<NodeRef, CommandError> {
+        let node_id = params["nodeId"]
+            .as_u64()
+            .ok_or_else(|| CommandError::invalid_params("nodeId: integer value expected"))?;
+        self.dom
+            .shadow_including_inclusive_descendants()
+            .find(|node| node.opaque().id() as u64 == node_id)
+            .ok_or_else(|| CommandError::server_error("Could not find node with given id"))
+    }
+}
+
+/// Runs the command named `method` with the given parameters against `target`, returning its
+/// result.
+///
+/// Clients enable the domains they use before sending their commands, so enabling (or disabling)
+/// any of the domains supported succeeds, though it does nothing, as none of them send events.
+pub fn run_command(target: &Target, method: &str, params: &Value) -> Result<Value, CommandError> {
+    match method {
+        "DOM.enable" | "DOM.disable" | "CSS.enable" | "CSS.disable" | "Page.enable"
+        | "Page.disable" => Ok(json!({})),
+        "DOM.getDocument" => {
+            // Only the document's children are described unless a depth is given, with -1 meaning
+            // the whole tree.
+            let depth = params["depth"].as_i64().unwrap_or(1);
+            Ok(json!({ "root": describe_node(target, target.dom, depth) }))
+        }
+        "DOM.getBoxModel" => get_box_model(target, &target.node(params)?),
+        "CSS.getComputedStyleForNode" => get_computed_style(&target.node(params)?),
+        "Page.captureScreenshot" => capture_screenshot(target, params),
+        "Target.getTargetInfo" => Ok(json!({
+            "targetInfo": {
+                "targetId": PAGE_TARGET_ID,
+                "type": "page",
+                "title": metadata::document_title(target.dom).unwrap_or_default(),
+                "url": target.url,
+                "attached": true,
+            }
+        })),
+        _ => Err(CommandError::method_not_found(method)),
+    }
+}
+
+/// Describes `node` as a `DOM.Node`, along with its descendants `depth` levels deep (or all of
+/// them if `depth` is negative).
+///
+/// https://chromedevtools.github.io/devtools-protocol/tot/DOM/#type-Node
+fn describe_node(target: &Target, node: &NodeRef, depth: i64) -> Value {
+    let node_id = node.opaque().id();
+    let (node_type, node_name, local_name, node_value) = match node.data() {
+        NodeData::Element(element) => (
+            1,
+            element.name.local.to_ascii_uppercase(),
+            element.name.local.to_string(),
+            String::new(),
+        ),
+        NodeData::Text(text) => (3, "#text".to_owned(), String::new(), text.borrow().clone()),
+        NodeData::ProcessingInstruction(contents) => {
+            let (instruction_target, data) = contents.borrow().clone();
+            (7, instruction_target, String::new(), data)
+        }
+        NodeData::Comment(text) => (
+            8,
+            "#comment".to_owned(),
+            String::new(),
+            text.borrow().clone(),
+        ),
+        NodeData::Document(_) => (9, "#document".to_owned(), String::new(), String::new()),
+        NodeData::Doctype(doctype) => (10, doctype.name.clone(), String::new(), String::new()),
+        NodeData::DocumentFragment | NodeData::ShadowRoot(_) => (
+            11,
+            "#document-fragment".to_owned(),
+            String::new(),
+            String::new(),
+        ),
+    };
+    let mut description = json!({
+        "nodeId": node_id,
+        "backendNodeId": node_id,
+        "nodeType": node_type,
+        "nodeName": node_name,
+        "localName": local_name,
+        "nodeValue": node_value,
+        "childNodeCount": node.children().count(),
+    });
+    if depth != 0 {
+        description["children"] = node
+            .children()
+            .map(|child| describe_node(target, &child, depth - 1))
+            .collect();
+    }
+    match node.data() {
+        NodeData::Element(element) => {
+            description["attributes"] = element
+                .attributes
+                .borrow()
+                .map
+                .iter()
+                .flat_map(|(name, attribute)| vec![name.local.to_string(), attribute.value.clone()])
+                .collect();
+        }
+        NodeData::Document(_) => {
+            description["documentURL"] = json!(target.url);
+            description["baseURL"] = json!(target.url);
+        }
+        NodeData::Doctype(doctype) => {
+            description["publicId"] = json!(doctype.public_id);
+            description["systemId"] = json!(doctype.system_id);
+        }
+        _ => {}
+    }
+    description
+}
+
+/// The computed value of each longhand property of `element`.
+///
+/// TODO: Values are written as they are in `dump-styles`, rather than serialized as CSS.
+fn get_computed_style(element: &NodeRef) -> Result<Value, CommandError> {
+    if element.as_element().is_none() {
+        return Err(CommandError::server_error("Node is not an Element"));
+    }
+    let computed_values = element.computed_values();
+    let computed_style = LonghandId::iter()
+        .map(|longhand| {
+            json!({
+                "name": longhand.name(),
+                "value": computed_values.dump_style_format(longhand),
+            })
+        })
+        .collect::<Vec<_>>();
+    Ok(json!({ "computedStyle": computed_style }))
+}
+
+/// The content, padding, border, and margin boxes of the first box `node` generated, relative to
+/// the viewport, in CSS pixels.
+///
+/// https://chromedevtools.github.io/devtools-protocol/tot/DOM/#type-BoxModel
+fn get_box_model(target: &Target, node: &NodeRef) -> Result<Value, CommandError> {
+    let geometry = target
+        .box_tree
+        .and_then(|box_tree| box_geometry(box_tree, node))
+        .ok_or_else(|| CommandError::server_error("Could not compute box model."))?;
+    let scroll_position = target.scroll_position();
+    let quad = |rect: Rect| {
+        let rect = rect.translated(-scroll_position.x, -scroll_position.y);
+        let (left, top) = (rect.start_x, rect.start_y);
+        let (right, bottom) = (left + rect.width.px(), top + rect.height.px());
+        json!([left, top, right, top, right, bottom, left, bottom])
+    };
+    Ok(json!({
+        "model": {
+            "content": quad(geometry.content_box),
+            "padding": quad(geometry.padding_box),
+            "border": quad(geometry.border_box),
+            "margin": quad(geometry.margin_box),
+            "width": geometry.border_box.width.px(),
+            "height": geometry.border_box.height.px(),
+        }
+    }))
+}
+
+/// Rasterizes what the viewport shows into a base64-encoded PNG.  Only PNGs are supported.
+fn capture_screenshot(target: &Target, params: &Value) -> Result<Value, CommandError> {
+    if !matches!(params["format"].as_str(), None | Some("png")) {
+        return Err(CommandError::invalid_params(
+            "Only PNG screenshots are supported",
+        ));
+    }
+    let scroll_position = target.scroll_position();
+    let mut display_list = target.display_list.clone();
+    display_list.translate(-scroll_position.x, -scroll_position.y);
+    let screenshot = rasterize(
+        &display_list,
+        target.width,
+        target.height,
+        target.scale_factor,
+    );
+    let png = encode_png(&screenshot)
+        .map_err(|err| CommandError::server_error(&format!("Couldn't encode PNG: {}", err)))?;
+    Ok(json!({ "data": base64::encode(png) }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kosmonaut_core::dom::traits::*;
+    use kosmonaut_core::gfx::display::build_display_list;
+    use kosmonaut_core::image::cache::ImageCache;
+    use kosmonaut_core::style::test_utils::laid_out_document;
+    use std::time::Duration;
+
+    #[test]
+    fn inspects_the_dom_styles_and_boxes_of_the_document() {
+        let (document, box_tree) = laid_out_document(
+            "<!DOCTYPE html><div id=a class=card>hi</div>",
+            "body { margin-top: 0px; margin-right: 0px } \
+             body { margin-bottom: 0px; margin-left: 0px } \
+             div { height: 50px; padding-top: 5px; margin-bottom: 10px }",
+        );
+        let image_cache = ImageCache::new(".");
+        let display_list = build_display_list(&box_tree, &image_cache, Duration::default(), 1.);
+        let target = Target {
+            dom: &document,
+            url: "file:///test.html".to_owned(),
+            box_tree: Some(&box_tree),
+            display_list: &display_list,
+            width: 800,
+            height: 600,
+            scale_factor: 1.,
+        };
+        let run = |method, params| run_command(&target, method, &params);
+
+        let root = run("DOM.getDocument", json!({ "depth": -1 })).unwrap()["root"].clone();
+        assert_eq!(root["nodeName"], "#document");
+        assert_eq!(root["documentURL"], "file:///test.html");
+        assert_eq!(root["children"][0]["nodeType"], 10);
+        let div = &root["children"][1]["children"][1]["children"][0];
+        assert_eq!(div["nodeName"], "DIV");
+        assert_eq!(div["attributes"], json!(["class", "card", "id", "a"]));
+        assert_eq!(div["children"][0]["nodeValue"], "hi");
+        // Only the document's children are described by default.
+        let shallow = run("DOM.getDocument", json!({})).unwrap();
+        assert_eq!(shallow["root"]["children"][1]["childNodeCount"], 2);
+        assert!(shallow["root"]["children"][1]["children"].is_null());
+
+        let node_id = json!({ "nodeId": div["nodeId"] });
+        let model = run("DOM.getBoxModel", node_id.clone()).unwrap()["model"].clone();
+        assert_eq!(
+            model["content"],
+            json!([0., 5., 800., 5., 800., 55., 0., 55.])
+        );
+        assert_eq!(
+            model["margin"],
+            json!([0., 0., 800., 0., 800., 65., 0., 65.])
+        );
+        assert_eq!(model["height"], 55.);
+        let computed_style = run("CSS.getComputedStyleForNode", node_id).unwrap();
+        assert!(computed_style["computedStyle"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|property| property["name"] == "display"));
+
+        let text_id = json!({ "nodeId": div["children"][0]["nodeId"] });
+        assert!(run("CSS.getComputedStyleForNode", text_id).is_err());
+        assert_eq!(
+            run("DOM.getBoxModel", json!({ "nodeId": 0 }))
+                .unwrap_err()
+                .code,
+            -32000
+        );
+        assert_eq!(
+            run("Runtime.evaluate", json!({})).unwrap_err(),
+            CommandError::method_not_found("Runtime.evaluate")
+        );
+
+        let screenshot = run("Page.captureScreenshot", json!({})).unwrap();
+        let png = base64::decode(screenshot["data"].as_str().unwrap()).unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+    }
+}
@@ -0,0 +1,246 @@
+//! The inspector overlay, which highlights the element under the mouse while the user inspects the
+//! page, like the element highlighters of other browsers' devtools: the element's content,
+//! padding, border, and margin boxes are painted over the page in translucent colors, along with a
+//! tooltip of its tag name, id, classes, and size.
+
+use cssparser::RGBA;
+use kosmonaut_core::dom::tree::NodeRef;
+use kosmonaut_core::gfx::display::{DisplayCommand, DisplayList, TextCommand};
+use kosmonaut_core::layout::geometry::{box_geometry, BoxGeometry};
+use kosmonaut_core::layout::layout_box::LayoutBox;
+use kosmonaut_core::layout::rect::Rect;
+use kosmonaut_core::layout::text::shape_text;
+use kosmonaut_core::layout::{dump_layout_element, DumpLayoutFormat};
+use kosmonaut_core::style::values::computed::length::CSSPixelLength;
+use kosmonaut_core::style::values::computed::{Color, ComputedValues, FontSize};
+use pathfinder_geometry::vector::Vector2F;
+
+const CONTENT_COLOR: RGBA = RGBA {
+    red: 111,
+    green: 168,
+    blue: 220,
+    alpha: 168,
+};
+const PADDING_COLOR: RGBA = RGBA {
+    red: 147,
+    green: 196,
+    blue: 125,
+    alpha: 140,
+};
+const BORDER_COLOR: RGBA = RGBA {
+    red: 255,
+    green: 229,
+    blue: 153,
+    alpha: 168,
+};
+const MARGIN_COLOR: RGBA = RGBA {
+    red: 246,
+    green: 178,
+    blue: 107,
+    alpha: 168,
+};
+const TOOLTIP_BACKGROUND_COLOR: RGBA = RGBA {
+    red: 51,
+    green: 51,
+    blue: 51,
+    alpha: 240,
+};
+/// The font size of the tooltip's text, in CSS pixels.
+const TOOLTIP_FONT_SIZE_PX: f32 = 12.;
+/// The space between the tooltip's text and its edges, and between the tooltip and the element.
+const TOOLTIP_PADDING_PX: f32 = 4.;
+
+/// Paints the highlight of `element` over what `display_list` paints, in a layer of its own, so
+/// that moving the highlight from element to element doesn't repaint the page.  The tooltip is put
+/// below the element, or above it if it'd go past the bottom of `viewport` (the area of the page
+/// the viewport shows).  Nothing is painted if `element` generated no boxes in `box_tree`.
+pub fn prepare_highlight(
+    display_list: &mut DisplayList,
+    box_tree: &LayoutBox,
+    element: &NodeRef,
+    viewport: Rect,
+) {
+    let geometry = match box_geometry(box_tree, element) {
+        Some(geometry) => geometry,
+        None => return,
+    };
+    display_list.push(DisplayCommand::LayerBoundary);
+    prepare_ring(
+        display_list,
+        MARGIN_COLOR,
+        geometry.margin_box,
+        geometry.border_box,
+    );
+    prepare_ring(
+        display_list,
+        BORDER_COLOR,
+        geometry.border_box,
+        geometry.padding_box,
+    );
+    prepare_ring(
+        display_list,
+        PADDING_COLOR,
+        geometry.padding_box,
+        geometry.content_box,
+    );
+    display_list.push(DisplayCommand::RectSolidColor(
+        CONTENT_COLOR,
+        geometry.content_box,
+    ));
+    prepare_tooltip(display_list, element, &geometry, viewport);
+}
+
+/// Paints the area of `outer` outside of `inner`, which it contains, as four strips.  Strips
+/// without area (e.g. the margins of a box without any, or negative margins) aren't painted.
+fn prepare_ring(display_list: &mut DisplayList, color: RGBA, outer: Rect, inner: Rect) {
+    let (outer_right, outer_bottom) = (right(outer), bottom(outer));
+    let (inner_right, inner_bottom) = (right(inner), bottom(inner));
+    let strips = [
+        // Top
+        (outer.start_x, outer.start_y, outer_right, inner.start_y),
+        // Bottom
+        (outer.start_x, inner_bottom, outer_right, outer_bottom),
+        // Left
+        (outer.start_x, inner.start_y, inner.start_x, inner_bottom),
+        // Right
+        (inner_right, inner.start_y, outer_right, inner_bottom),
+    ];
+    for &(left, top, right, bottom) in &strips {
+        if right > left && bottom > top {
+            display_list.push(DisplayCommand::RectSolidColor(
+                color,
+                rect(left, top, right - left, bottom - top),
+            ));
+        }
+    }
+}
+
+/// Paints the tooltip naming `element` (e.g. `div#main.card`) and giving the size of its border
+/// box, under (or over) its margin box.  The tooltip is sized to fit its text, shaped in the
+/// initial font at `TOOLTIP_FONT_SIZE_PX`.
+fn prepare_tooltip(
+    display_list: &mut DisplayList,
+    element: &NodeRef,
+    geometry: &BoxGeometry,
+    viewport: Rect,
+) {
+    let text = format!(
+        "{}  {} \u{d7} {}",
+        dump_layout_element(element).unwrap_or_default(),
+        geometry.border_box.width.dump_layout_format(),
+        geometry.border_box.height.dump_layout_format()
+    );
+    let style = tooltip_text_style();
+    let shaped = shape_text(&text, &style, 0.);
+    let text_width = shaped.advance;
+    let text_height = shaped.ascent + shaped.descent;
+    let width = text_width + 2. * TOOLTIP_PADDING_PX;
+    let height = text_height + 2. * TOOLTIP_PADDING_PX;
+    let margin_box = geometry.margin_box;
+    let below = bottom(margin_box) + TOOLTIP_PADDING_PX;
+    let top = if below + height > bottom(viewport) {
+        (margin_box.start_y - TOOLTIP_PADDING_PX - height).max(viewport.start_y)
+    } else {
+        below
+    };
+    let left = margin_box.start_x.max(viewport.start_x);
+    display_list.push(DisplayCommand::RectSolidColor(
+        TOOLTIP_BACKGROUND_COLOR,
+        rect(left, top, width, height),
+    ));
+    let text_rect = rect(
+        left + TOOLTIP_PADDING_PX,
+        top + TOOLTIP_PADDING_PX,
+        text_width,
+        text_height,
+    );
+    display_list.push(DisplayCommand::Text(TextCommand::new(
+        text,
+        shaped.runs,
+        style.color.rgba(),
+        Vector2F::new(text_rect.start_x, text_rect.start_y + shaped.ascent),
+        text_rect,
+    )));
+}
+
+/// The style the tooltip's text is shaped and painted in: the initial style, but white and at
+/// `TOOLTIP_FONT_SIZE_PX`.
+fn tooltip_text_style() -> ComputedValues {
+    ComputedValues {
+        color: Color::white(),
+        font_size: FontSize {
+            size: CSSPixelLength::new(TOOLTIP_FONT_SIZE_PX),
+            keyword_size: None,
+        },
+        ..ComputedValues::default()
+    }
+}
+
+fn rect(x: f32, y: f32, width: f32, height: f32) -> Rect {
+    Rect {
+        start_x: x,
+        start_y: y,
+        width: CSSPixelLength::new(width),
+        height: CSSPixelLength::new(height),
+    }
+}
+
+fn right(rect: Rect) -> f32 {
+    rect.start_x + rect.width.px()
+}
+
+fn bottom(rect: Rect) -> f32 {
+    rect.start_y + rect.height.px()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kosmonaut_core::dom::traits::*;
+    use kosmonaut_core::gfx::font::use_test_fonts;
+    use kosmonaut_core::style::test_utils::laid_out_document;
+
+    #[test]
+    fn highlights_the_boxes_of_the_element_with_a_tooltip() {
+        use_test_fonts();
+        let (document, box_tree) = laid_out_document(
+            "<!DOCTYPE html><div id=a class=card></div>",
+            "body { margin-top: 0px; margin-right: 0px } \
+             body { margin-bottom: 0px; margin-left: 0px } \
+             div { height: 50px; padding-top: 5px; margin-left: 10px }",
+        );
+        let div = document.select_first("div").unwrap().as_node().clone();
+        let highlight = |viewport| {
+            let mut display_list = DisplayList::new();
+            prepare_highlight(&mut display_list, &box_tree, &div, viewport);
+            display_list.commands().to_vec()
+        };
+
+        let commands = highlight(rect(0., 0., 800., 600.));
+        assert_eq!(
+            commands[..4],
+            [
+                DisplayCommand::LayerBoundary,
+                DisplayCommand::RectSolidColor(MARGIN_COLOR, rect(0., 0., 10., 55.)),
+                DisplayCommand::RectSolidColor(PADDING_COLOR, rect(10., 0., 790., 5.)),
+                DisplayCommand::RectSolidColor(CONTENT_COLOR, rect(10., 5., 790., 50.)),
+            ]
+        );
+        match &commands[5] {
+            DisplayCommand::Text(text_command) => {
+                assert_eq!(text_command.text(), "div#a.card  790 \u{d7} 55");
+                assert_eq!(text_command.rect().start_y, 63.);
+                // Each of the 20 chars is a 12px box in the test font.
+                assert_eq!(text_command.runs()[0].glyphs.len(), 20);
+                assert_eq!(text_command.rect().width.px(), 240.);
+            }
+            command => panic!("expected the tooltip's text, got {:?}", command),
+        }
+
+        // Tooltips that would go past the bottom of the viewport go above the element.
+        match &highlight(rect(0., 0., 800., 80.))[4] {
+            DisplayCommand::RectSolidColor(_, tooltip) => assert_eq!(tooltip.start_y, 0.),
+            command => panic!("expected the tooltip, got {:?}", command),
+        }
+    }
+}
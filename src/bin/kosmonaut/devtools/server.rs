@@ -0,0 +1,297 @@
+//! The server devtools clients connect to.  It speaks just enough HTTP for clients to discover
+//! the page they can inspect (at `/json/version` and `/json/list`), and the protocol itself over
+//! WebSocket connections to the page's `webSocketDebuggerUrl`.
+//!
+//! Each connection is served on a thread of its own, which sends the commands it receives to the
+//! pipeline one at a time, and waits for each result before reading the next command.
+//!
+//! Though the server only listens on the loopback interface, web pages can still try to reach it
+//! through the browsers they're open in.  So requests must name the server by `localhost` or an IP
+//! address in their `Host` header, which a page can't do through a domain it's rebound to the
+//! loopback address, and WebSocket connections are refused to pages whose origin isn't allowed.
+//!
+//! https://chromedevtools.github.io/devtools-protocol/#endpoints
+
+use crate::devtools::{CommandError, DevToolsCommand, PAGE_TARGET_ID};
+use serde_json::{json, Value};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{IpAddr, TcpListener, TcpStream};
+use std::sync::mpsc::channel;
+use std::sync::Arc;
+use std::thread;
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tungstenite::http::StatusCode;
+use tungstenite::Message;
+
+/// Where the server listens, and which web pages may connect to it.
+#[derive(Clone, Debug)]
+pub struct ServerOptions {
+    /// The port of the loopback interface to listen on.
+    pub port: u16,
+    /// The origins of the web pages allowed to connect (see `is_allowed_origin`).
+    pub allowed_origins: Vec<String>,
+}
+
+/// Listens for devtools clients as `options` describes, on a thread of its own, and sends each
+/// command they send to `send_to_pipeline`.  Only local clients can connect, as the protocol
+/// gives them full access to the page.
+pub fn serve<F>(options: ServerOptions, send_to_pipeline: F) -> io::Result<()>
+where
+    F: Fn(DevToolsCommand) + Clone + Send + 'static,
+{
+    let ServerOptions {
+        port,
+        allowed_origins,
+    } = options;
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    let allowed_origins = Arc::new(allowed_origins);
+    thread::Builder::new()
+        .name("devtools".to_owned())
+        .spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let allowed_origins = allowed_origins.clone();
+                let send_to_pipeline = send_to_pipeline.clone();
+                thread::spawn(move || {
+                    if let Err(err) =
+                        serve_connection(stream, port, &allowed_origins, send_to_pipeline)
+                    {
+                        eprintln!("devtools connection failed: {}", err);
+                    }
+                });
+            }
+        })?;
+    Ok(())
+}
+
+/// Serves a client's HTTP request, or its WebSocket connection if it requested one.
+fn serve_connection<F>(
+    stream: TcpStream,
+    port: u16,
+    allowed_origins: &[String],
+    send_to_pipeline: F,
+) -> Result<(), String>
+where
+    F: Fn(DevToolsCommand),
+{
+    // The request is peeked at rather than read, so the WebSocket handshake can read it again.
+    let mut request_head = [0; 1024];
+    let read = stream
+        .peek(&mut request_head)
+        .map_err(|err| err.to_string())?;
+    let request_line = String::from_utf8_lossy(&request_head[..read])
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_owned();
+    let path = request_line.split(' ').nth(1).unwrap_or("/");
+    if path.starts_with("/devtools/") {
+        serve_websocket(stream, allowed_origins, send_to_pipeline)
+    } else {
+        serve_http(stream, port, path, send_to_pipeline).map_err(|err| err.to_string())
+    }
+}
+
+/// Responds to a request for one of the JSON endpoints, describing the page, or with a 404 for
+/// any other path.
+fn serve_http<F>(stream: TcpStream, port: u16, path: &str, send_to_pipeline: F) -> io::Result<()>
+where
+    F: Fn(DevToolsCommand),
+{
+    // The rest of the request (its headers) is read before responding, so the client doesn't
+    // see the connection reset.
+    let mut reader = BufReader::new(&stream);
+    let mut line = String::new();
+    let mut host = None;
+    while reader.read_line(&mut line)? > 0 && line.trim_end() != "" {
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("host") {
+                host = Some(value.trim().to_owned());
+            }
+        }
+        line.clear();
+    }
+    if !host.as_deref().map_or(false, is_allowed_host) {
+        let body = "Host header must be localhost or an IP address";
+        return write!(
+            &stream,
+            "HTTP/1.1 403 Forbidden\r\nContent-Type: text/plain; charset=UTF-8\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+    }
+    let websocket_url = format!("ws://127.0.0.1:{}/devtools/page/{}", port, PAGE_TARGET_ID);
+    let body = match path.trim_end_matches('/') {
+        "/json/version" => Some(json!({
+            "Browser": format!("Kosmonaut/{}", env!("CARGO_PKG_VERSION")),
+            "Protocol-Version": "1.3",
+            "webSocketDebuggerUrl": websocket_url,
+        })),
+        "/json" | "/json/list" => {
+            let target_info = run(&send_to_pipeline, "Target.getTargetInfo", json!({}))
+                .map(|result| result["targetInfo"].clone())
+                .unwrap_or_default();
+            Some(json!([{
+                "description": "",
+                "id": PAGE_TARGET_ID,
+                "type": "page",
+                "title": target_info["title"],
+                "url": target_info["url"],
+                "webSocketDebuggerUrl": websocket_url,
+            }]))
+        }
+        _ => None,
+    };
+    let (status, body) = match body {
+        Some(body) => ("200 OK", body.to_string()),
+        None => ("404 Not Found", String::new()),
+    };
+    write!(
+        &stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json; charset=UTF-8\r\nContent-Length: {}\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+/// Runs the commands the client sends over the WebSocket connection until it closes, unless its
+/// handshake came from a host or origin that isn't allowed.
+fn serve_websocket<F>(
+    stream: TcpStream,
+    allowed_origins: &[String],
+    send_to_pipeline: F,
+) -> Result<(), String>
+where
+    F: Fn(DevToolsCommand),
+{
+    let check_handshake = |request: &Request, response: Response| {
+        let header = |name: &str| {
+            request
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        };
+        let refusal = if !header("Host").map_or(false, is_allowed_host) {
+            "Host header must be localhost or an IP address".to_owned()
+        } else if !is_allowed_origin(header("Origin"), allowed_origins) {
+            format!(
+                "Origin {} isn't allowed; pass it to --remote-allow-origins to allow it",
+                header("Origin").unwrap_or_default()
+            )
+        } else {
+            return Ok(response);
+        };
+        let mut error_response = ErrorResponse::new(Some(refusal));
+        *error_response.status_mut() = StatusCode::FORBIDDEN;
+        Err(error_response)
+    };
+    let mut socket =
+        tungstenite::accept_hdr(stream, check_handshake).map_err(|err| err.to_string())?;
+    loop {
+        let text = match socket.read_message() {
+            Ok(Message::Text(text)) => text,
+            Ok(Message::Close(_)) | Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+            // Pings are answered by the socket itself.
+            Ok(_) => continue,
+            Err(err) => return Err(err.to_string()),
+        };
+        let response = match serde_json::from_str::<Value>(&text) {
+            Ok(request) => {
+                let id = request["id"].clone();
+                let method = request["method"].as_str().unwrap_or_default();
+                let params = request.get("params").cloned().unwrap_or_else(|| json!({}));
+                match run(&send_to_pipeline, method, params) {
+                    Ok(result) => json!({ "id": id, "result": result }),
+                    Err(CommandError { code, message }) => {
+                        json!({ "id": id, "error": { "code": code, "message": message } })
+                    }
+                }
+            }
+            Err(err) => json!({
+                "error": { "code": -32700, "message": format!("Message must be valid JSON: {}", err) }
+            }),
+        };
+        socket
+            .write_message(Message::Text(response.to_string()))
+            .map_err(|err| err.to_string())?;
+    }
+}
+
+/// Whether `host`, the value of a request's `Host` header, names this server by `localhost` or an
+/// IP address, which a web page can only do if it's being served from the same address.  Any
+/// other name could have been pointed at the loopback address by whoever controls it.
+fn is_allowed_host(host: &str) -> bool {
+    let hostname = match host.strip_prefix('[') {
+        // IPv6 addresses are bracketed, to tell their colons from the port's.
+        Some(bracketed) => bracketed.split_once(']').map_or("", |(address, _)| address),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+    hostname.eq_ignore_ascii_case("localhost") || hostname.parse::<IpAddr>().is_ok()
+}
+
+/// Whether a WebSocket connection from `origin`, the value of its handshake's `Origin` header, is
+/// allowed.  Clients that aren't web pages, such as automation tools, send no origin, and are
+/// always allowed.  Pages are allowed if their origin is in `allowed_origins`, or it contains `*`.
+fn is_allowed_origin(origin: Option<&str>, allowed_origins: &[String]) -> bool {
+    origin.map_or(true, |origin| {
+        allowed_origins
+            .iter()
+            .any(|allowed_origin| allowed_origin == "*" || allowed_origin == origin)
+    })
+}
+
+/// Sends the command named `method` to the pipeline, and waits for its result.
+fn run<F>(send_to_pipeline: &F, method: &str, params: Value) -> Result<Value, CommandError>
+where
+    F: Fn(DevToolsCommand),
+{
+    let (reply, result) = channel();
+    send_to_pipeline(DevToolsCommand {
+        method: method.to_owned(),
+        params,
+        reply,
+    });
+    // The pipeline drops commands once it's closed its last tab, by which time the window is
+    // closing too.
+    result.recv().unwrap_or_else(|_| {
+        Err(CommandError::server_error(
+            "The page was closed before the command could run",
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_allows_hosts_that_are_localhost_or_ip_addresses() {
+        assert!(is_allowed_host("localhost:9222"));
+        assert!(is_allowed_host("LOCALHOST"));
+        assert!(is_allowed_host("127.0.0.1:9222"));
+        assert!(is_allowed_host("[::1]:9222"));
+        assert!(!is_allowed_host("attacker.example:9222"));
+        assert!(!is_allowed_host("localhost.attacker.example"));
+        assert!(!is_allowed_host("[::1"));
+        assert!(!is_allowed_host(""));
+    }
+
+    #[test]
+    fn only_allows_pages_from_allowed_origins() {
+        let allowed_origins = vec!["http://localhost:3000".to_owned()];
+        assert!(is_allowed_origin(None, &[]));
+        assert!(is_allowed_origin(
+            Some("http://localhost:3000"),
+            &allowed_origins
+        ));
+        assert!(!is_allowed_origin(
+            Some("https://attacker.example"),
+            &allowed_origins
+        ));
+        assert!(!is_allowed_origin(Some("http://localhost:3000"), &[]));
+        assert!(is_allowed_origin(
+            Some("https://attacker.example"),
+            &["*".to_owned()]
+        ));
+    }
+}
@@ -0,0 +1,434 @@
+//! The commands that load a page without opening a window: dumping its layout, display list,
+//! stacking contexts, styles, or accessibility tree, screenshotting it, printing it to PDF, and
+//! reporting the memory it retains.
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+use clap::ArgMatches;
+use strum::IntoEnumIterator;
+
+use crate::cli::{
+    dump_accessibility_tree, dump_display_list, dump_layout_diff_path, dump_layout_filter,
+    dump_layout_filter_includes_descendants, dump_layout_tree, dump_layout_tree_verbose,
+    dump_stacking_contexts, dump_styles, dump_styles_properties, inner_window_height,
+    inner_window_width, layout_dump_format, print_to_pdf_path, scale_factor, screenshot_path,
+};
+use kosmonaut_core::a11y;
+use kosmonaut_core::dom::parser::{parse_html_with_options, ParseOpts};
+use kosmonaut_core::dom::traits::TendrilSink;
+use kosmonaut_core::dom::tree::NodeRef;
+use kosmonaut_core::gfx::display::{self, build_page_display_list};
+use kosmonaut_core::gfx::pdf::{print_to_pdf, PAGE_AREA_HEIGHT_PX, PAGE_AREA_WIDTH_PX};
+use kosmonaut_core::gfx::raster::rasterize;
+use kosmonaut_core::image::cache::ImageCache;
+use kosmonaut_core::image::{load_document_images, save_png, DecodedImage};
+use kosmonaut_core::layout::box_tree::build_box_tree;
+use kosmonaut_core::layout::diff::diff_layout_dumps;
+use kosmonaut_core::layout::layout_box::LayoutBox;
+use kosmonaut_core::layout::{
+    global_layout, DumpLayout, DumpLayoutFilter, DumpLayoutJson, DumpLayoutOptions,
+    DumpLayoutVerbosity, LayoutDumpFormat,
+};
+use kosmonaut_core::memory::MemoryReport;
+use kosmonaut_core::page::{layout_document, load_document, LoadedDocument};
+use kosmonaut_core::script::ScriptEngineSlot;
+use kosmonaut_core::style::media::Device;
+use kosmonaut_core::style::properties::id::{LonghandId, PropertyId};
+use kosmonaut_core::style::select::Selectors;
+use kosmonaut_core::style::stylesheet::Stylesheet;
+use kosmonaut_core::style::{self, apply_styles};
+
+/// Runs the headless command given on the command line, if any, on `document`: a dump, a
+/// screenshot, or printing to PDF.  Returns whether one was run.
+pub fn run_headless_command(arg_matches: &ArgMatches, document: LoadedDocument) -> bool {
+    let scale_factor_opt = scale_factor(arg_matches);
+    let (inner_width_opt, inner_height_opt) = (
+        inner_window_width(arg_matches),
+        inner_window_height(arg_matches),
+    );
+    if dump_layout_tree(arg_matches) {
+        let scale_factor = scale_factor_opt
+            .expect("scale factor must be explicitly specified when running layout dump");
+        let filter = dump_layout_filter(arg_matches).map(|selector| DumpLayoutFilter {
+            selectors: Selectors::compile_str(selector)
+                .unwrap_or_else(|_| panic!("invalid selector given to --filter: {}", selector)),
+            include_descendants: dump_layout_filter_includes_descendants(arg_matches),
+        });
+        let format = layout_dump_format(arg_matches);
+        let diff_against = dump_layout_diff_path(arg_matches).map(|path| {
+            assert!(
+                format == LayoutDumpFormat::Text,
+                "dump-layout --diff only compares text dumps"
+            );
+            std::fs::read_to_string(path).expect("couldn't read previous layout dump")
+        });
+        run_layout_dump(
+            document.dom,
+            &document.image_cache,
+            inner_width_opt,
+            inner_height_opt,
+            scale_factor,
+            DumpLayoutOptions {
+                verbosity: dump_layout_tree_verbose(arg_matches)
+                    .unwrap_or(DumpLayoutVerbosity::NonVerbose),
+                format,
+                filter,
+                diff_against,
+            },
+        );
+    } else if dump_display_list(arg_matches) {
+        let scale_factor = scale_factor_opt
+            .expect("scale factor must be explicitly specified when running display list dump");
+        run_display_list_dump(
+            document.dom,
+            &document.image_cache,
+            inner_width_opt,
+            inner_height_opt,
+            scale_factor,
+        );
+    } else if dump_stacking_contexts(arg_matches) {
+        let scale_factor = scale_factor_opt
+            .expect("scale factor must be explicitly specified when running stacking context dump");
+        run_stacking_context_dump(
+            document.dom,
+            &document.image_cache,
+            inner_width_opt,
+            inner_height_opt,
+            scale_factor,
+        );
+    } else if dump_styles(arg_matches) {
+        run_style_dump(&document.dom, dump_styles_properties(arg_matches));
+    } else if dump_accessibility_tree(arg_matches) {
+        a11y::dump_accessibility_tree(
+            &a11y::build_accessibility_tree(&document.dom),
+            &mut std::io::stdout(),
+            0,
+        );
+    } else if let Some(screenshot_path) = screenshot_path(arg_matches) {
+        let scale_factor = scale_factor_opt
+            .expect("scale factor must be explicitly specified when taking a screenshot");
+        run_screenshot(
+            document.dom,
+            &document.image_cache,
+            inner_width_opt,
+            inner_height_opt,
+            scale_factor,
+            Path::new(screenshot_path),
+        );
+    } else if let Some(print_to_pdf_path) = print_to_pdf_path(arg_matches) {
+        run_print_to_pdf(
+            document.dom,
+            &document.image_cache,
+            Path::new(print_to_pdf_path),
+        );
+    } else {
+        return false;
+    }
+    true
+}
+
+fn run_layout_dump(
+    styled_dom: NodeRef,
+    image_cache: &ImageCache,
+    inner_width_opt: Option<f32>,
+    inner_height_opt: Option<f32>,
+    scale_factor: f32,
+    options: DumpLayoutOptions,
+) {
+    let write_to = &mut std::io::stdout();
+    let inner_width = inner_width_opt
+        .expect("Inner window width CLI arg 'width' must be specified for dump-layout.");
+    let inner_height = inner_height_opt
+        .expect("Inner window height CLI arg 'height' must be specified for dump-layout.");
+    load_document_images(
+        &styled_dom,
+        image_cache,
+        inner_width / scale_factor,
+        scale_factor,
+    );
+    let box_tree = build_box_tree(styled_dom, None).map(|mut box_tree| {
+        global_layout(&mut box_tree, inner_width, inner_height, scale_factor);
+        box_tree
+    });
+    match &options.diff_against {
+        Some(previous_dump) => {
+            let mut dump = Vec::new();
+            write_layout_dump(box_tree.as_ref(), &mut dump, &options);
+            diff_layout_dumps(
+                previous_dump,
+                &String::from_utf8(dump).expect("layout dump wasn't UTF-8"),
+                write_to,
+            );
+        }
+        None => write_layout_dump(box_tree.as_ref(), write_to, &options),
+    }
+}
+
+fn write_layout_dump<W: Write>(
+    box_tree: Option<&LayoutBox>,
+    write_to: &mut W,
+    options: &DumpLayoutOptions,
+) {
+    match box_tree {
+        Some(box_tree) => match (&options.filter, options.format) {
+            (None, LayoutDumpFormat::Text) => box_tree.dump_layout(write_to, 0, options.verbosity),
+            (None, LayoutDumpFormat::Json) => {
+                writeln!(write_to, "{:#}", box_tree.dump_layout_json())
+                    .expect("could not write layout dump")
+            }
+            (Some(filter), LayoutDumpFormat::Text) => {
+                for layout_box in box_tree.filtered_for_dump(filter) {
+                    layout_box.dump_layout(write_to, 0, options.verbosity);
+                }
+            }
+            (Some(filter), LayoutDumpFormat::Json) => {
+                // Any number of boxes can match the filter, so the dump is a list of trees.
+                let boxes = box_tree
+                    .filtered_for_dump(filter)
+                    .iter()
+                    .map(|layout_box| layout_box.dump_layout_json())
+                    .collect::<Vec<_>>();
+                writeln!(write_to, "{:#}", serde_json::Value::from(boxes))
+                    .expect("could not write layout dump")
+            }
+        },
+        None if options.format == LayoutDumpFormat::Json => {
+            writeln!(write_to, "null").expect("could not write layout dump");
+        }
+        None => {
+            write_to
+                .write("empty box tree".as_bytes())
+                .expect("could not write layout dump");
+        }
+    };
+}
+
+fn run_display_list_dump(
+    styled_dom: NodeRef,
+    image_cache: &ImageCache,
+    inner_width_opt: Option<f32>,
+    inner_height_opt: Option<f32>,
+    scale_factor: f32,
+) {
+    let inner_width = inner_width_opt
+        .expect("Inner window width CLI arg 'width' must be specified for dump-display-list.");
+    let inner_height = inner_height_opt
+        .expect("Inner window height CLI arg 'height' must be specified for dump-display-list.");
+    load_document_images(
+        &styled_dom,
+        image_cache,
+        inner_width / scale_factor,
+        scale_factor,
+    );
+    let mut box_tree = build_box_tree(styled_dom, None);
+    if let Some(box_tree) = box_tree.as_mut() {
+        global_layout(box_tree, inner_width, inner_height, scale_factor);
+    }
+    build_page_display_list(
+        box_tree.as_ref(),
+        image_cache,
+        Duration::default(),
+        scale_factor,
+    )
+    .dump(&mut std::io::stdout());
+}
+
+fn run_stacking_context_dump(
+    styled_dom: NodeRef,
+    image_cache: &ImageCache,
+    inner_width_opt: Option<f32>,
+    inner_height_opt: Option<f32>,
+    scale_factor: f32,
+) {
+    let inner_width = inner_width_opt
+        .expect("Inner window width CLI arg 'width' must be specified for dump-stacking.");
+    let inner_height = inner_height_opt
+        .expect("Inner window height CLI arg 'height' must be specified for dump-stacking.");
+    load_document_images(
+        &styled_dom,
+        image_cache,
+        inner_width / scale_factor,
+        scale_factor,
+    );
+    match build_box_tree(styled_dom, None) {
+        Some(mut box_tree) => {
+            global_layout(&mut box_tree, inner_width, inner_height, scale_factor);
+            display::dump_stacking_contexts(&box_tree, &mut std::io::stdout());
+        }
+        None => {
+            std::io::stdout()
+                .write("empty box tree".as_bytes())
+                .expect("could not write stacking context dump");
+        }
+    }
+}
+
+fn run_style_dump(styled_dom: &NodeRef, property_names: Option<Vec<&str>>) {
+    let longhands: Vec<LonghandId> = match property_names {
+        Some(property_names) => property_names
+            .into_iter()
+            .map(|name| match PropertyId::parse(name) {
+                Some(PropertyId::Longhand(longhand)) => longhand,
+                _ => panic!(
+                    "'{}' isn't a supported longhand property for dump-styles",
+                    name
+                ),
+            })
+            .collect(),
+        None => LonghandId::iter().collect(),
+    };
+    style::dump::dump_styles(styled_dom, &mut std::io::stdout(), 0, &longhands);
+}
+
+/// Lays out the document at the given viewport size, rasterizes it on the CPU, and writes the
+/// result to a PNG at `path`.  No window is opened, so this works headless.
+fn run_screenshot(
+    styled_dom: NodeRef,
+    image_cache: &ImageCache,
+    inner_width_opt: Option<f32>,
+    inner_height_opt: Option<f32>,
+    scale_factor: f32,
+    path: &Path,
+) {
+    let inner_width = inner_width_opt
+        .expect("Inner window width CLI arg 'width' must be specified for screenshots.");
+    let inner_height = inner_height_opt
+        .expect("Inner window height CLI arg 'height' must be specified for screenshots.");
+    let screenshot = render_headless(
+        styled_dom,
+        image_cache,
+        inner_width,
+        inner_height,
+        scale_factor,
+    );
+    save_png(&screenshot, path).expect("couldn't write screenshot");
+}
+
+/// Lays out the document at the given viewport size and rasterizes it on the CPU, without opening
+/// a window.
+fn render_headless(
+    styled_dom: NodeRef,
+    image_cache: &ImageCache,
+    inner_width: f32,
+    inner_height: f32,
+    scale_factor: f32,
+) -> DecodedImage {
+    load_document_images(
+        &styled_dom,
+        image_cache,
+        inner_width / scale_factor,
+        scale_factor,
+    );
+    let mut box_tree = build_box_tree(styled_dom, None);
+    if let Some(box_tree) = box_tree.as_mut() {
+        global_layout(box_tree, inner_width, inner_height, scale_factor);
+    }
+    let display_list = build_page_display_list(
+        box_tree.as_ref(),
+        image_cache,
+        Duration::default(),
+        scale_factor,
+    );
+    rasterize(
+        &display_list,
+        inner_width as u32,
+        inner_height as u32,
+        scale_factor,
+    )
+}
+
+/// Loads, lays out, and paints (without rasterizing) the page at `html_file` on `device`, like
+/// `--screenshot` does, and prints how much heap memory each part of it retains, after the parts
+/// already measured in `report`.
+pub fn run_memory_report(
+    mut report: MemoryReport,
+    html_file: &Path,
+    device: &Device,
+    ua_sheets: &[Stylesheet],
+    author_sheets: &[Stylesheet],
+    scale_factor: f32,
+) {
+    let script_engine = ScriptEngineSlot::default();
+    // Scripts run as the document is parsed, so the memory they retain is counted with the DOM.
+    let dom = report.measure("DOM", || {
+        parse_html_with_options(ParseOpts {
+            script_engine: Some(script_engine.clone()),
+            ..ParseOpts::default()
+        })
+        .from_utf8()
+        .read_from(&mut File::open(html_file).expect("couldn't open html file"))
+        .expect("couldn't parse html file")
+    });
+    report.measure("computed styles", || {
+        apply_styles(dom.clone(), device, ua_sheets, &[], author_sheets)
+    });
+    let image_cache = report.measure("image cache", || {
+        let image_cache = ImageCache::new(html_file.parent().unwrap_or_else(|| Path::new("")));
+        load_document_images(&dom, &image_cache, device.viewport_width(), scale_factor);
+        image_cache
+    });
+    let box_tree = report.measure("box tree", || {
+        let mut box_tree = build_box_tree(dom.clone(), None);
+        layout_document(&mut box_tree, &dom, device, ua_sheets, author_sheets);
+        box_tree
+    });
+    let _display_list = report.measure("display list", || {
+        build_page_display_list(
+            box_tree.as_ref(),
+            &image_cache,
+            Duration::default(),
+            scale_factor,
+        )
+    });
+    print!("{}", report);
+}
+
+/// Loads the page at `html_file`, styled only by `ua_sheets` and its own `style` attributes, and
+/// renders it headless on `device`.  URLs of images starting with a slash are resolved against
+/// `document_root`, if given (see `ImageCache::with_document_root`).
+pub fn render_page(
+    html_file: &Path,
+    document_root: Option<&Path>,
+    device: &Device,
+    ua_sheets: &[Stylesheet],
+) -> std::io::Result<DecodedImage> {
+    let mut document = load_document(html_file, device, ua_sheets, &[])?;
+    if let Some(document_root) = document_root {
+        document.image_cache = document.image_cache.with_document_root(document_root);
+    }
+    Ok(render_headless(
+        document.dom,
+        &document.image_cache,
+        device.viewport_width() * device.resolution(),
+        device.viewport_height() * device.resolution(),
+        device.resolution(),
+    ))
+}
+
+/// Lays out the document in the page area of a printed page, fragments it into pages, and writes
+/// them to a PDF at `path`.  Like screenshots, this works headless.
+fn run_print_to_pdf(styled_dom: NodeRef, image_cache: &ImageCache, path: &Path) {
+    // Pages are painted as vectors, so the scale factor only affects the resolution images are
+    // loaded at.
+    let scale_factor = 1.;
+    load_document_images(&styled_dom, image_cache, PAGE_AREA_WIDTH_PX, scale_factor);
+    let mut box_tree = build_box_tree(styled_dom, None);
+    if let Some(box_tree) = box_tree.as_mut() {
+        global_layout(
+            box_tree,
+            PAGE_AREA_WIDTH_PX,
+            PAGE_AREA_HEIGHT_PX,
+            scale_factor,
+        );
+    }
+    let display_list = build_page_display_list(
+        box_tree.as_ref(),
+        image_cache,
+        Duration::default(),
+        scale_factor,
+    );
+    std::fs::write(path, print_to_pdf(&display_list)).expect("couldn't write pdf");
+}
@@ -0,0 +1,310 @@
+//! The `kosmonaut` binary: a browser window built on the engine in `kosmonaut_core`, and the
+//! headless tools (layout dumps, screenshots, reftests, the console, and so on) used to develop
+//! and test it.
+
+#[macro_use]
+extern crate html5ever;
+
+mod cli;
+mod compositor;
+mod devtools;
+mod headless;
+mod pipeline;
+mod reftest;
+mod repl;
+mod system;
+mod tab;
+mod watch;
+mod wpt;
+
+use std::path::{Path, PathBuf};
+
+use clap::ArgMatches;
+use glutin::event_loop::EventLoop;
+
+use crate::cli::{
+    css_file_paths_from_files, emulated_media, forced_colors, html_file_path_from_files,
+    html_file_paths_from_files, inner_window_height, inner_window_width, log_css_errors,
+    memory_report, prefers_color_scheme, prefers_contrast, prefers_reduced_motion,
+    print_to_pdf_path, reftest_diff_dir, reftest_manifest_path, remote_allow_origins,
+    remote_debugging_port, renderer, repl, report_unsupported, scale_factor,
+    setup_and_get_cli_args, test_fonts, trace_output_path, wpt_checkout_path, wpt_include_paths,
+    wpt_report_path, ColorSchemePreference, EmulatedMedia,
+};
+use crate::compositor::run_event_loop;
+use crate::devtools::server::ServerOptions;
+use crate::headless::{run_headless_command, run_memory_report};
+use crate::pipeline::LoadedDocuments;
+use crate::reftest::{run_reftests, REFTEST_INNER_WINDOW_HEIGHT_PX, REFTEST_INNER_WINDOW_WIDTH_PX};
+use crate::repl::Repl;
+use crate::system::{
+    system_color_scheme, system_contrast_preference, system_forced_colors, system_motion_preference,
+};
+use crate::wpt::run_wpt;
+use kosmonaut_core::gfx::backend::init_backend;
+use kosmonaut_core::gfx::pdf::{PAGE_AREA_HEIGHT_PX, PAGE_AREA_WIDTH_PX};
+use kosmonaut_core::gfx::{DEFAULT_INNER_WINDOW_HEIGHT_PX, DEFAULT_INNER_WINDOW_WIDTH_PX};
+use kosmonaut_core::memory::{CountingAllocator, MemoryReport};
+use kosmonaut_core::page::{
+    load_document, load_stylesheet, screen_device, user_agent_stylesheet, Page,
+};
+use kosmonaut_core::style::media::{
+    ColorScheme, ContrastPreference, Device, ForcedColors, MediaType, MotionPreference,
+};
+use kosmonaut_core::style::stylesheet::Stylesheet;
+use kosmonaut_core::unsupported::UnsupportedFeatures;
+use kosmonaut_core::{gfx, style, trace};
+
+/// Counts heap allocations, so `--memory-report` can measure how much memory each part of a page
+/// retains.
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Welcome to Kosmonaut.
+///
+/// > The path of a kosmonaut is not an easy, triumphant march to glory. You have to get to know the
+/// > meaning not just of joy but also of grief before being allowed in the spacecraft cabin.
+///     - Yuri Gagarin
+fn main() {
+    let arg_matches = setup_and_get_cli_args();
+    let trace = trace_output_path(&arg_matches).map(|path| trace::start(Path::new(path)));
+    let fallback_local_html = "tests/websrc/rainbow-divs.html";
+    let html_file =
+        Path::new(html_file_path_from_files(&arg_matches).unwrap_or(fallback_local_html));
+    let mut memory = MemoryReport::new();
+    let (ua_sheets, author_sheets) = memory.measure("stylesheets", || {
+        (
+            vec![user_agent_stylesheet()],
+            get_author_sheets(css_file_paths_from_files(&arg_matches).as_deref()),
+        )
+    });
+    if log_css_errors(&arg_matches) {
+        for sheet in ua_sheets.iter().chain(author_sheets.iter()) {
+            for diagnostic in sheet.diagnostics() {
+                eprintln!("{}:{}", sheet.name(), diagnostic);
+            }
+        }
+    }
+    if test_fonts(&arg_matches) {
+        gfx::font::use_test_fonts();
+    }
+    let device = cli_device(&arg_matches);
+    if let Some(manifest_path) = reftest_manifest_path(&arg_matches) {
+        let all_passed = run_reftests(
+            Path::new(manifest_path),
+            &reftest_device(&arg_matches, &device),
+            &ua_sheets,
+            reftest_diff_dir(&arg_matches).map(Path::new),
+        );
+        drop(trace);
+        std::process::exit(if all_passed { 0 } else { 1 });
+    }
+    if let Some(checkout_path) = wpt_checkout_path(&arg_matches) {
+        let report = run_wpt(
+            Path::new(checkout_path),
+            &wpt_include_paths(&arg_matches),
+            &reftest_device(&arg_matches, &device),
+            &ua_sheets,
+        );
+        let report = serde_json::to_string_pretty(&report).expect("couldn't serialize wptreport");
+        match wpt_report_path(&arg_matches) {
+            Some(report_path) => {
+                std::fs::write(report_path, report).expect("couldn't write wptreport")
+            }
+            None => println!("{}", report),
+        }
+        return;
+    }
+    if repl(&arg_matches) {
+        run_repl(&arg_matches, author_sheets);
+        return;
+    }
+    if memory_report(&arg_matches) {
+        run_memory_report(
+            memory,
+            html_file,
+            &device,
+            &ua_sheets,
+            &author_sheets,
+            scale_factor(&arg_matches).unwrap_or(1.),
+        );
+        return;
+    }
+    let document = load_document(html_file, &device, &ua_sheets, &author_sheets)
+        .expect("couldn't load html file");
+    if report_unsupported(&arg_matches) {
+        let mut unsupported = UnsupportedFeatures::new();
+        for sheet in &author_sheets {
+            unsupported.record_stylesheet(sheet);
+        }
+        unsupported.record_document(&document.dom);
+        eprint!("{}", unsupported.summary());
+    }
+    if run_headless_command(&arg_matches, document) {
+        return;
+    }
+    // Documents and stylesheets can't be sent between threads, so the pipeline's thread loads its
+    // own copies of them.
+    drop((ua_sheets, author_sheets));
+    open_window(&arg_matches, device, html_file);
+}
+
+/// The device pages are laid out on, as described on the command line.  The OS's color scheme
+/// and accessibility settings are only followed in a window (see `open_window`), so that output
+/// is the same on any machine.
+fn cli_device(arg_matches: &ArgMatches) -> Device {
+    let mut device = if print_to_pdf_path(arg_matches).is_some() {
+        Device::new(MediaType::Print, PAGE_AREA_WIDTH_PX, PAGE_AREA_HEIGHT_PX)
+    } else {
+        // A window's scale factor isn't known until it's opened, at which point the document is
+        // restyled if its viewport turns out to match different rules.
+        screen_device(
+            inner_window_width(arg_matches).unwrap_or(DEFAULT_INNER_WINDOW_WIDTH_PX),
+            inner_window_height(arg_matches).unwrap_or(DEFAULT_INNER_WINDOW_HEIGHT_PX),
+            scale_factor(arg_matches).unwrap_or(1.),
+        )
+    };
+    if emulated_media(arg_matches) == EmulatedMedia::Print {
+        device.set_media_type(MediaType::Print);
+    }
+    device.set_color_scheme(match prefers_color_scheme(arg_matches) {
+        ColorSchemePreference::System | ColorSchemePreference::Light => ColorScheme::Light,
+        ColorSchemePreference::Dark => ColorScheme::Dark,
+    });
+    device.set_motion_preference(
+        prefers_reduced_motion(arg_matches).unwrap_or(MotionPreference::NoPreference),
+    );
+    device.set_contrast_preference(
+        prefers_contrast(arg_matches).unwrap_or(ContrastPreference::NoPreference),
+    );
+    device.set_forced_colors(forced_colors(arg_matches).unwrap_or(ForcedColors::None));
+    device
+}
+
+/// The device reftests and Web Platform Tests are rendered on, which is `device` at the reftest
+/// viewport size, unless another is given.
+fn reftest_device(arg_matches: &ArgMatches, device: &Device) -> Device {
+    let mut reftest_device = screen_device(
+        inner_window_width(arg_matches).unwrap_or(REFTEST_INNER_WINDOW_WIDTH_PX),
+        inner_window_height(arg_matches).unwrap_or(REFTEST_INNER_WINDOW_HEIGHT_PX),
+        scale_factor(arg_matches).unwrap_or(1.),
+    );
+    reftest_device.set_media_type(device.media_type());
+    reftest_device.set_color_scheme(device.color_scheme());
+    reftest_device.set_motion_preference(device.motion_preference());
+    reftest_device.set_contrast_preference(device.contrast_preference());
+    reftest_device.set_forced_colors(device.forced_colors());
+    reftest_device
+}
+
+/// Runs the console on stdin and stdout.  Unlike other commands, the console starts without a
+/// page unless one is given, so that one can be loaded from it.
+fn run_repl(arg_matches: &ArgMatches, author_sheets: Vec<Stylesheet>) {
+    let page = html_file_path_from_files(arg_matches).map(|html_file| {
+        Page::load_document(Path::new(html_file), author_sheets.clone())
+            .expect("couldn't load html file")
+    });
+    let stdin = std::io::stdin();
+    Repl::new(
+        page,
+        author_sheets,
+        inner_window_width(arg_matches).unwrap_or(DEFAULT_INNER_WINDOW_WIDTH_PX),
+        inner_window_height(arg_matches).unwrap_or(DEFAULT_INNER_WINDOW_HEIGHT_PX),
+        scale_factor(arg_matches).unwrap_or(1.),
+    )
+    .run(stdin.lock(), &mut std::io::stdout(), true)
+    .expect("couldn't run console");
+}
+
+/// Opens a window with a tab for each HTML file given on the command line (or `html_file`, if
+/// none were), loaded on `device` once it follows whichever of the OS's preferences weren't
+/// overridden on the command line.
+fn open_window(arg_matches: &ArgMatches, mut device: Device, html_file: &Path) {
+    // Each HTML file is opened in its own tab, with the first active.
+    let mut html_files: Vec<PathBuf> = html_file_paths_from_files(arg_matches)
+        .into_iter()
+        .map(PathBuf::from)
+        .collect();
+    if html_files.is_empty() {
+        html_files.push(html_file.to_owned());
+    }
+    let css_file_paths = css_file_paths_from_files(arg_matches)
+        .map(|paths| paths.into_iter().map(str::to_owned).collect::<Vec<_>>());
+    let event_loop = EventLoop::with_user_event();
+    let backend = init_backend(
+        renderer(arg_matches),
+        &event_loop,
+        inner_window_width(arg_matches),
+        inner_window_height(arg_matches),
+    );
+    let system_color_scheme_opt =
+        if prefers_color_scheme(arg_matches) == ColorSchemePreference::System {
+            let color_scheme = system_color_scheme();
+            device.set_color_scheme(color_scheme);
+            Some(color_scheme)
+        } else {
+            None
+        };
+    if prefers_reduced_motion(arg_matches).is_none() {
+        device.set_motion_preference(system_motion_preference());
+    }
+    if prefers_contrast(arg_matches).is_none() {
+        device.set_contrast_preference(system_contrast_preference());
+    }
+    if forced_colors(arg_matches).is_none() {
+        device.set_forced_colors(system_forced_colors());
+    }
+    let reduce_motion = device.motion_preference() == MotionPreference::Reduce;
+    run_event_loop(
+        event_loop,
+        backend,
+        scale_factor(arg_matches),
+        system_color_scheme_opt,
+        reduce_motion,
+        remote_debugging_port(arg_matches).map(|port| ServerOptions {
+            port,
+            allowed_origins: remote_allow_origins(arg_matches)
+                .into_iter()
+                .map(str::to_owned)
+                .collect(),
+        }),
+        move || {
+            let ua_sheets = vec![user_agent_stylesheet()];
+            let author_sheets = get_author_sheets(css_file_paths.as_deref());
+            let css_paths = css_file_paths.iter().flatten().map(PathBuf::from).collect();
+            let documents = html_files
+                .iter()
+                .map(|html_file| {
+                    load_document(html_file, &device, &ua_sheets, &author_sheets)
+                        .expect("couldn't load html file")
+                })
+                .collect();
+            LoadedDocuments {
+                documents,
+                device,
+                ua_sheets,
+                author_sheets,
+                css_paths,
+            }
+        },
+    );
+}
+
+fn get_author_sheets<S: AsRef<str>>(css_file_paths: Option<&[S]>) -> Vec<Stylesheet> {
+    css_file_paths
+        .map(|css_file_paths| {
+            css_file_paths
+                .iter()
+                .map(|css_file_path| {
+                    load_stylesheet(Path::new(css_file_path.as_ref()))
+                        .expect("couldn't read css file to string")
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_else(|| {
+            vec![style::stylesheet::parse_css_to_stylesheet(
+                Some("rainbow-divs.css".to_owned()),
+                &mut std::fs::read_to_string("tests/websrc/rainbow-divs.css").expect("file fail"),
+            )]
+        })
+}
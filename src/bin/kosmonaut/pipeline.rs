@@ -0,0 +1,1553 @@
+//! The pipeline, which runs the documents of a window's tabs (their scripts, styling, layout, and
+//! display list building) on a thread of its own, so that a long layout or script doesn't stop
+//! the window's thread, the compositor, from handling input and painting.
+//!
+//! The two threads communicate by messages.  The compositor sends the pipeline the input that
+//! needs the DOM (e.g. clicks and key presses) and changes to the viewport, while the pipeline
+//! sends the compositor a frame (a display list) whenever the active tab's rendering changes,
+//! along with what to show in the window's title bar and the cursor to show over the document.
+//! The compositor keeps the scroll position of each document, and scrolls the last frame itself,
+//! so scrolling stays smooth however long the next frame takes.  The commands of devtools clients
+//! are sent to the pipeline as messages too (see `devtools`).
+//!
+//! DOMs, stylesheets, and box trees are built of `Rc`s, so they're created on the pipeline thread
+//! and never leave it; frames refer to nodes by `OpaqueNode`.
+
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::process;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::devtools::{self, overlay, DevToolsCommand};
+use crate::tab::{Tab, TabCommand, Tabs, ZoomStep};
+use crate::watch::{FileWatcher, WATCH_INTERVAL};
+use kosmonaut_core::dom::details;
+use kosmonaut_core::dom::editing::EditCommand;
+use kosmonaut_core::dom::event::{Event, EventKind, KeyboardEvent, MouseButton, MouseEvent};
+use kosmonaut_core::dom::focus::{self, FocusDirection};
+use kosmonaut_core::dom::form::{self, FormControlKind};
+use kosmonaut_core::dom::metadata;
+use kosmonaut_core::dom::mutation;
+use kosmonaut_core::dom::navigation::{self, LinkTarget};
+use kosmonaut_core::dom::submission::{self, FormMethod};
+use kosmonaut_core::dom::tree::{NodeRef, OpaqueNode};
+use kosmonaut_core::gfx::display::{build_page_display_list, DisplayList};
+use kosmonaut_core::gfx::DEFAULT_WINDOW_TITLE;
+use kosmonaut_core::image::load_document_images;
+use kosmonaut_core::layout::box_tree::{build_box_tree, update_box_tree};
+use kosmonaut_core::layout::content_visibility::update_relevance;
+use kosmonaut_core::layout::rect::{Point, Rect};
+use kosmonaut_core::layout::scroll::{
+    max_scroll_position, resolve_scroll, snap_positions, ScrollOptionsBehavior, ScrollTarget,
+    SnapPositions,
+};
+use kosmonaut_core::page::{
+    layout_document, load_document, load_stylesheet, screen_device, update_color_scheme,
+    update_device, LoadedDocument,
+};
+use kosmonaut_core::style::invalidation::InvalidationMap;
+use kosmonaut_core::style::media::{ColorScheme, Device, MotionPreference};
+use kosmonaut_core::style::stylesheet::Stylesheet;
+use kosmonaut_core::style::values::computed::length::CSSPixelLength;
+use kosmonaut_core::style::values::computed::Cursor;
+use kosmonaut_core::style::{apply_styles, restyle_mutations};
+
+/// How often frames are built while scripts are waiting on `requestAnimationFrame`, which is
+/// about 60 times a second.
+const ANIMATION_FRAME_INTERVAL: Duration = Duration::from_micros(16_667);
+
+/// The size and scale factor of the window's viewport.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    /// The width of the viewport in device pixels.
+    pub width: f32,
+    /// The height of the viewport in device pixels.
+    pub height: f32,
+    /// The number of device pixels per CSS pixel, before the page is zoomed.
+    pub scale_factor: f32,
+}
+
+/// What the pipeline starts with, which is created on the pipeline thread.
+pub struct LoadedDocuments {
+    /// The documents to open, each in its own tab, the first of which is active.  There must be
+    /// at least one.
+    pub documents: Vec<LoadedDocument>,
+    /// The device the documents were styled for.
+    pub device: Device,
+    pub ua_sheets: Vec<Stylesheet>,
+    pub author_sheets: Vec<Stylesheet>,
+    /// The files `author_sheets` were parsed from, in the same order, which are parsed again when
+    /// they change.  Author sheets that weren't parsed from a file (e.g. the default one) aren't
+    /// reloaded.
+    pub css_paths: Vec<PathBuf>,
+}
+
+/// A message from the compositor (or the devtools server) to the pipeline.
+#[derive(Clone, Debug)]
+pub enum PipelineMsg {
+    /// The window was resized, or its scale factor changed.
+    Resize(Viewport),
+    Mouse(MouseInput),
+    Key(KeyInput),
+    /// A character was typed, which edits the focused text control (if any).
+    Edit(EditCommand),
+    Tab(TabCommand),
+    /// The color scheme the user prefers changed (e.g. the OS switched to its dark theme).
+    ColorScheme(ColorScheme),
+    /// The compositor scrolled the active tab's document to the given position, which can bring
+    /// `content-visibility: auto` elements into view (or take them out of it).
+    Scroll(Point),
+    /// A devtools client sent a command to run against the active tab.
+    DevTools(DevToolsCommand),
+    /// Start or stop inspecting the page, highlighting the element under the mouse.
+    ToggleInspector,
+}
+
+/// What the mouse did.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MouseAction {
+    Move,
+    Press,
+    Release,
+}
+
+/// Input from the mouse, to be dispatched to the node of the active tab's document under it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MouseInput {
+    pub action: MouseAction,
+    /// The position of the mouse, relative to the viewport, and the button pressed or released.
+    pub event: MouseEvent,
+    /// Where the document was scrolled to as the compositor showed it, so the node under the
+    /// mouse can be found in the (unscrolled) frame.
+    pub scroll_position: Point,
+}
+
+/// A key press or release, to be dispatched to the focused element, along with what the key does
+/// by default if it's pressed and the page doesn't cancel it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyInput {
+    pub pressed: bool,
+    pub event: KeyboardEvent,
+    /// How the key zooms the page, if it's a zoom shortcut.
+    pub zoom: Option<ZoomStep>,
+    /// How far the key scrolls the document, if it's a key that scrolls and no text control is
+    /// focused.
+    pub scroll: Option<Point>,
+    /// How the key edits the focused text control, if any.
+    pub edit: Option<EditCommand>,
+    /// Whether the key copies the selected text to the clipboard.
+    pub copy: bool,
+    /// Which way the key moves focus, if it's Tab or Shift+Tab.
+    pub focus_navigation: Option<FocusDirection>,
+}
+
+/// A message to the compositor, from the pipeline (or, for `SystemColorScheme`, from the thread
+/// the compositor checks the OS's theme on).
+#[derive(Clone, Debug, PartialEq)]
+pub enum CompositorMsg {
+    Frame(Frame),
+    /// Smoothly scroll the document shown by the given distance, in CSS pixels.
+    ScrollBy(Point),
+    /// Scroll the document shown to the given position, in CSS pixels, smoothly if `smooth` (and
+    /// the user doesn't prefer reduced motion).
+    ScrollTo {
+        position: Point,
+        smooth: bool,
+    },
+    SetTitle(String),
+    /// Show the given cursor while the mouse is over the document.  This is never `Cursor::Auto`,
+    /// which is resolved to the cursor it stands for.
+    SetCursor(Cursor),
+    /// Put the given text on the system clipboard, as it was copied.
+    SetClipboard(String),
+    /// Show the given icon, or the default icon if it's `None`.
+    SetIcon(Option<WindowIcon>),
+    /// The document with the given node was closed (e.g. it was navigated away from, or its tab
+    /// was closed), so its scroll position can be forgotten.
+    DocumentClosed(OpaqueNode),
+    /// The OS's theme is in the given color scheme.  Finding it can mean running a program, so
+    /// it's found off the compositor's thread.
+    SystemColorScheme(ColorScheme),
+    /// The last tab was closed, so the window should be too.
+    Exit,
+}
+
+/// What the active tab paints.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    /// The document painted, whose scroll position the display list is painted at.
+    pub document: OpaqueNode,
+    /// The display list painting the document, before it's scrolled.
+    pub display_list: DisplayList,
+    /// The furthest position the document can be scrolled to, in CSS pixels.
+    pub max_scroll_position: Point,
+    /// The positions scrolls of the document snap to.
+    pub snap_positions: SnapPositions,
+    /// The number of device pixels per CSS pixel to paint the display list at, which is the
+    /// viewport's scale factor multiplied by the tab's zoom.
+    pub scale_factor: f32,
+}
+
+/// An icon for the window, as straight RGBA pixels.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WindowIcon {
+    pub rgba: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A handle to the pipeline thread.  The thread runs until it closes its last tab, or every handle
+/// to it is dropped.  If it panics, the process exits, as it would have were the pipeline
+/// run on the compositor's thread, rather than leaving a window that's never updated.
+#[derive(Clone)]
+pub struct Pipeline {
+    sender: Sender<PipelineMsg>,
+}
+
+impl Pipeline {
+    /// Starts the pipeline thread, which first calls `load` to load the documents of its tabs,
+    /// and then sends each message for the compositor to `send_to_compositor`.
+    pub fn spawn<L, C>(load: L, viewport: Viewport, send_to_compositor: C) -> Pipeline
+    where
+        L: FnOnce() -> LoadedDocuments + Send + 'static,
+        C: FnMut(CompositorMsg) + Send + 'static,
+    {
+        let (sender, receiver) = channel();
+        thread::Builder::new()
+            .name("pipeline".to_owned())
+            .spawn(move || {
+                let run = AssertUnwindSafe(|| {
+                    PipelineThread::new(load(), viewport, send_to_compositor).run(receiver)
+                });
+                if panic::catch_unwind(run).is_err() {
+                    process::exit(101);
+                }
+            })
+            .expect("couldn't spawn the pipeline thread");
+        Pipeline { sender }
+    }
+
+    /// Sends `msg` to the pipeline.  Messages sent after the pipeline has closed its last tab are
+    /// dropped.
+    pub fn send(&self, msg: PipelineMsg) {
+        self.sender.send(msg).ok();
+    }
+}
+
+struct PipelineThread<C> {
+    tabs: Tabs,
+    /// The path of the document the window was opened with, which new tabs open.
+    home_path: PathBuf,
+    ua_sheets: Vec<Stylesheet>,
+    author_sheets: Vec<Stylesheet>,
+    /// The files `author_sheets` were parsed from.
+    css_paths: Vec<PathBuf>,
+    /// Watches the stylesheets and the tabs' documents, which are reloaded when they change.
+    watcher: FileWatcher,
+    last_watch_check: Instant,
+    /// Which elements changes to the DOM restyle, which depends only on the stylesheets.
+    invalidation_map: InvalidationMap,
+    viewport: Viewport,
+    /// The color scheme the user prefers, which the active tab's document is styled for.
+    color_scheme: ColorScheme,
+    send_to_compositor: C,
+    /// The display list of the last frame, which input is hit tested against, so it hits what
+    /// the compositor shows.
+    display_list: DisplayList,
+    /// Whether the active tab's rendering may have changed since its last frame.
+    needs_frame: bool,
+    /// The origin of the timeline that animated images are played back on.
+    animation_start: Instant,
+    last_frame: Instant,
+    /// The title and icon `href` shown by the window, which are `None` until they're first set.
+    shown_title: Option<String>,
+    shown_icon_href: Option<String>,
+    /// The cursor shown over the document, which is the default cursor until the mouse moves.
+    shown_cursor: Cursor,
+    /// Whether the user is inspecting the page, so the mouse picks the element to highlight rather
+    /// than interacting with the page.
+    inspecting: bool,
+}
+
+impl<C: FnMut(CompositorMsg)> PipelineThread<C> {
+    fn new(loaded: LoadedDocuments, viewport: Viewport, send_to_compositor: C) -> Self {
+        let LoadedDocuments {
+            documents,
+            device,
+            ua_sheets,
+            author_sheets,
+            css_paths,
+        } = loaded;
+        let home_path = documents[0].path.clone();
+        let tabs = Tabs::new(
+            documents
+                .into_iter()
+                .map(|document| Tab::new(document, device.clone()))
+                .collect(),
+        );
+        let invalidation_map = InvalidationMap::new(ua_sheets.iter().chain(author_sheets.iter()));
+        let mut watcher = FileWatcher::new();
+        for path in &css_paths {
+            watcher.watch(path);
+        }
+        let mut pipeline = PipelineThread {
+            tabs,
+            home_path,
+            ua_sheets,
+            author_sheets,
+            css_paths,
+            watcher,
+            last_watch_check: Instant::now(),
+            invalidation_map,
+            viewport,
+            color_scheme: device.color_scheme(),
+            send_to_compositor,
+            display_list: DisplayList::new(),
+            needs_frame: true,
+            animation_start: Instant::now(),
+            last_frame: Instant::now(),
+            shown_title: None,
+            shown_icon_href: None,
+            shown_cursor: Cursor::Default,
+            inspecting: false,
+        };
+        pipeline.update_viewport();
+        pipeline.update_window_metadata();
+        pipeline
+    }
+
+    /// Handles messages, runs timers, and builds frames until the last tab is closed, or the
+    /// compositor hangs up.
+    fn run(mut self, receiver: Receiver<PipelineMsg>) {
+        loop {
+            let msg = match self.time_until_next_update() {
+                Some(timeout) => match receiver.recv_timeout(timeout) {
+                    Ok(msg) => Some(msg),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                },
+                None => match receiver.recv() {
+                    Ok(msg) => Some(msg),
+                    Err(_) => return,
+                },
+            };
+            match msg {
+                // Messages that arrived while the last frame was being built are all handled
+                // before the next, so input is never more than a frame behind.
+                Some(msg) => {
+                    for msg in std::iter::once(msg).chain(receiver.try_iter()) {
+                        if !self.handle(msg) {
+                            (self.send_to_compositor)(CompositorMsg::Exit);
+                            return;
+                        }
+                    }
+                }
+                // It's only time to check the watched files for changes.
+                None if self.last_watch_check.elapsed() >= WATCH_INTERVAL => {}
+                // It's time to run timers, or to build the next frame of an animation.
+                None => self.needs_frame = true,
+            }
+            if self.last_watch_check.elapsed() >= WATCH_INTERVAL {
+                self.reload_changed_files();
+            }
+            for tab in self.tabs.iter_mut() {
+                if let Some(script_engine) = &mut tab.script_engine {
+                    script_engine.run_due_timers();
+                }
+            }
+            self.continue_loading();
+            if self.needs_frame || mutation::has_mutations(&self.tabs.active().dom) {
+                self.update_the_rendering();
+            }
+        }
+    }
+
+    /// Parses the next chunk of each document that's still loading.  The parts of the active
+    /// tab's document that were parsed are restyled and laid out in the next frame, like the
+    /// changes scripts make (see `update_the_rendering`).
+    fn continue_loading(&mut self) {
+        let active_document = self.tabs.active().dom.opaque();
+        let mut active_changed = false;
+        for tab in self.tabs.iter_mut() {
+            if tab.load_next_chunk(&self.ua_sheets, &self.author_sheets)
+                && tab.dom.opaque() == active_document
+            {
+                active_changed = true;
+            }
+        }
+        if active_changed {
+            // The document's title may have been parsed.
+            self.update_window_metadata();
+            self.needs_frame = true;
+        }
+    }
+
+    /// How far animated images in the active tab have played, or `None` if its user prefers
+    /// reduced motion, in which case they're frozen at their first frame.
+    fn animation_time(&self) -> Option<Duration> {
+        match self.tabs.active().device.motion_preference() {
+            MotionPreference::Reduce => None,
+            MotionPreference::NoPreference => Some(self.animation_start.elapsed()),
+        }
+    }
+
+    /// How long until timers in any tab are due, the watched files are next checked, the next
+    /// chunk of a loading document is parsed, or the active tab's animated images, scripts, or a
+    /// change to its rendering need a new frame.  `None` if nothing will happen until the next
+    /// message.
+    fn time_until_next_update(&mut self) -> Option<Duration> {
+        let watch_delay =
+            (self.last_watch_check + WATCH_INTERVAL).saturating_duration_since(Instant::now());
+        let next_frame_delay =
+            (self.last_frame + ANIMATION_FRAME_INTERVAL).saturating_duration_since(Instant::now());
+        let needs_frame = self.needs_frame;
+        let loading = self.tabs.iter_mut().any(|tab| tab.loading.is_some());
+        let timer_delay = self
+            .tabs
+            .iter_mut()
+            .filter_map(|tab| tab.script_engine.as_mut()?.time_until_next_timer())
+            .min();
+        let animation_time = self.animation_time();
+        let tab = self.tabs.active_mut();
+        let animation_frame_delay = match &mut tab.script_engine {
+            Some(script_engine) if script_engine.has_animation_frame_callbacks() => {
+                Some(next_frame_delay)
+            }
+            _ => None,
+        };
+        let image_delay = animation_time
+            .and_then(|animation_time| tab.image_cache.time_until_next_frame(animation_time));
+        [
+            timer_delay,
+            Some(watch_delay),
+            animation_frame_delay,
+            image_delay,
+            Some(Duration::default()).filter(|_| needs_frame || loading),
+        ]
+        .iter()
+        .flatten()
+        .copied()
+        .min()
+    }
+
+    /// Handles `msg`, returning false if it closed the last tab.
+    fn handle(&mut self, msg: PipelineMsg) -> bool {
+        match msg {
+            PipelineMsg::Resize(viewport) => {
+                self.viewport = viewport;
+                self.update_viewport();
+                self.needs_frame = true;
+            }
+            PipelineMsg::Mouse(input) => self.handle_mouse(input),
+            PipelineMsg::Key(input) => self.handle_key(input),
+            PipelineMsg::Edit(command) => self.edit_focused_control(command),
+            PipelineMsg::Tab(command) => return self.handle_tab_command(command),
+            PipelineMsg::ColorScheme(color_scheme) => {
+                self.color_scheme = color_scheme;
+                self.update_viewport();
+                self.needs_frame = true;
+            }
+            PipelineMsg::Scroll(scroll_position) => self.handle_scroll(scroll_position),
+            PipelineMsg::DevTools(command) => self.run_devtools_command(command),
+            PipelineMsg::ToggleInspector => self.toggle_inspector(),
+        }
+        true
+    }
+
+    /// Records where the active tab's document was scrolled to, building a new frame if that
+    /// changed which of its `content-visibility: auto` elements are relevant to the user, so they
+    /// skip their contents (or stop skipping them).
+    fn handle_scroll(&mut self, scroll_position: Point) {
+        let Viewport {
+            width,
+            height,
+            scale_factor,
+        } = self.viewport;
+        let tab = self.tabs.active_mut();
+        if let Some(document) = tab.dom.as_document() {
+            document.scroll_position.set(scroll_position);
+        }
+        let scale_factor = scale_factor * tab.zoom;
+        if let Some(box_tree) = tab.box_tree.as_mut() {
+            let viewport = Rect {
+                start_x: scroll_position.x,
+                start_y: scroll_position.y,
+                width: CSSPixelLength::new(width / scale_factor),
+                height: CSSPixelLength::new(height / scale_factor),
+            };
+            if update_relevance(box_tree, viewport) {
+                self.needs_frame = true;
+            }
+        }
+    }
+
+    /// Runs a devtools client's command against the active tab's document, bringing its rendering
+    /// up to date first, so the client inspects what the window shows.
+    fn run_devtools_command(&mut self, command: DevToolsCommand) {
+        if self.needs_frame || mutation::has_mutations(&self.tabs.active().dom) {
+            self.update_the_rendering();
+        }
+        let tab = self.tabs.active();
+        let path = tab.path.canonicalize().unwrap_or_else(|_| tab.path.clone());
+        let target = devtools::Target {
+            dom: &tab.dom,
+            url: format!("file://{}", path.display()),
+            box_tree: tab.box_tree.as_ref(),
+            display_list: &self.display_list,
+            width: self.viewport.width as u32,
+            height: self.viewport.height as u32,
+            scale_factor: self.viewport.scale_factor * tab.zoom,
+        };
+        let result = devtools::run_command(&target, &command.method, &command.params);
+        // The client may have disconnected while waiting.
+        command.reply.send(result).ok();
+    }
+
+    /// Starts or stops inspecting the page.  Once inspecting stops, the highlighted element of each
+    /// tab stops being highlighted.
+    fn toggle_inspector(&mut self) {
+        self.inspecting = !self.inspecting;
+        if !self.inspecting {
+            for tab in self.tabs.iter_mut() {
+                tab.inspected = None;
+            }
+            self.needs_frame = true;
+        }
+    }
+
+    fn handle_mouse(&mut self, input: MouseInput) {
+        let MouseInput {
+            action,
+            event: mouse_event,
+            scroll_position,
+        } = input;
+        let tab = self.tabs.active_mut();
+        // The display list is hit tested before it's scrolled.
+        let point = Point {
+            x: mouse_event.client_x + scroll_position.x,
+            y: mouse_event.client_y + scroll_position.y,
+        };
+        let hit_nodes = self.display_list.hit_test(&tab.dom, point);
+        // While the page is inspected, the mouse only moves the highlight to the innermost element
+        // under it, and the page sees none of its events.
+        if self.inspecting {
+            let hovered = hit_nodes
+                .iter()
+                .find(|node| node.as_element().is_some())
+                .cloned();
+            if hovered != tab.inspected {
+                tab.inspected = hovered;
+                self.needs_frame = true;
+            }
+            return;
+        }
+        let text = tab
+            .box_tree
+            .as_ref()
+            .and_then(|box_tree| box_tree.text_at(point));
+        let cursor = cursor_over(&hit_nodes, text.as_ref());
+        if cursor != self.shown_cursor {
+            (self.send_to_compositor)(CompositorMsg::SetCursor(cursor));
+            self.shown_cursor = cursor;
+        }
+        // Dragging with the primary button held selects the text it's dragged over.
+        if action == MouseAction::Move && tab.selecting && tab.extend_selection(point) {
+            self.needs_frame = true;
+        }
+        if action == MouseAction::Release && mouse_event.button == MouseButton::Primary {
+            tab.selecting = false;
+        }
+        let target = match hit_nodes.first() {
+            Some(target) => target.clone(),
+            None => return,
+        };
+        let event_type = match action {
+            MouseAction::Move => "mousemove",
+            MouseAction::Press => "mousedown",
+            MouseAction::Release => "mouseup",
+        };
+        let not_canceled = target.dispatch_event(&mut Event::mouse(event_type, mouse_event));
+        if action == MouseAction::Move || mouse_event.button != MouseButton::Primary {
+            return;
+        }
+        if action == MouseAction::Press {
+            tab.pressed_target = Some(target);
+            if !not_canceled {
+                return;
+            }
+            // Pressing a focusable element (or something in one) focuses it, while pressing
+            // anywhere else removes focus.
+            let pressed_focusable = hit_nodes.iter().find_map(focus::click_focus_target);
+            // Pressing anywhere but a form control starts selecting the document's text.
+            let pressed_control = pressed_focusable
+                .as_ref()
+                .filter(|node| FormControlKind::of(node).is_some());
+            if pressed_control.is_none() && tab.start_selecting(point) {
+                self.needs_frame = true;
+            }
+            if pressed_focusable != tab.focused {
+                focus::update_focus(tab.focused.as_ref(), pressed_focusable.as_ref(), true);
+                tab.focused = pressed_focusable;
+                self.needs_frame = true;
+            }
+            return;
+        }
+        // A click is targeted at the nearest common inclusive ancestor of the nodes the button was
+        // pressed and released over.
+        // https://w3c.github.io/uievents/#click
+        let click_target = tab.pressed_target.take().and_then(|pressed_target| {
+            pressed_target.inclusive_ancestors().find(|ancestor| {
+                target
+                    .inclusive_ancestors()
+                    .any(|released_ancestor| released_ancestor == *ancestor)
+            })
+        });
+        let click_target = match click_target {
+            Some(click_target) => click_target,
+            None => return,
+        };
+        self.click(&click_target, Some(mouse_event));
+    }
+
+    /// Fires a `click` event at `target`, and unless it's canceled, runs the activation behavior
+    /// of `target`.  Clicks from the keyboard (e.g. pressing Enter on a focused link) have no
+    /// `mouse_event`.
+    fn click(&mut self, target: &NodeRef, mouse_event: Option<MouseEvent>) {
+        let click_event = || match mouse_event {
+            Some(mouse_event) => Event::mouse("click", mouse_event),
+            None => Event::new("click", true, true, EventKind::Plain),
+        };
+        if !target.dispatch_event(&mut click_event()) {
+            return;
+        }
+        // The activation behavior of a click is that of the nearest inclusive ancestor of its
+        // target that has one, i.e. a link, the summary of a `<details>` element, a form control
+        // or an option of a `<select>`, or a `<label>`.
+        // https://dom.spec.whatwg.org/#eventtarget-activation-behavior
+        for node in target.inclusive_ancestors() {
+            if let Some(href) = navigation::hyperlink_href(&node) {
+                let in_new_tab = mouse_event.map_or(false, |mouse_event| {
+                    mouse_event.modifiers.ctrl || mouse_event.modifiers.meta
+                });
+                self.follow_link(&href, in_new_tab);
+                return;
+            }
+            // Toggling the `<details>` element is picked up as a mutation, which restyles it.
+            if details::toggle(&node) {
+                return;
+            }
+            if self.activate_form_control(&node) {
+                return;
+            }
+            // Clicking a label clicks the control it labels (unless the control itself was
+            // clicked), and focuses it.
+            // https://html.spec.whatwg.org/multipage/forms.html#the-label-element
+            if let Some(control) = form::labeled_control(&node) {
+                let tab = self.tabs.active_mut();
+                let clicked_control = target
+                    .inclusive_ancestors()
+                    .any(|ancestor| ancestor == control);
+                if !clicked_control && !form::is_disabled(&control) {
+                    let by_pointer = mouse_event.is_some();
+                    focus::update_focus(tab.focused.as_ref(), Some(&control), by_pointer);
+                    tab.focused = Some(control.clone());
+                    self.needs_frame = true;
+                    if control.dispatch_event(&mut click_event()) {
+                        self.activate_form_control(&control);
+                    }
+                }
+                return;
+            }
+        }
+    }
+
+    /// Runs the activation behavior of `node` if it's a form control (or an option of one) that
+    /// has one, returning whether it did.
+    fn activate_form_control(&mut self, node: &NodeRef) -> bool {
+        if let Some(form) = submission::submit_button_form(node) {
+            self.submit_form(&form, Some(node));
+            return true;
+        }
+        let changed = if let Some(changed) = form::toggle_checkedness(node) {
+            if changed {
+                Some(node.clone())
+            } else {
+                None
+            }
+        } else if let Some(select) = form::click_option(node) {
+            // TODO: Only fire `input` and `change` when picking the option changed the selection.
+            // https://html.spec.whatwg.org/multipage/form-elements.html#send-select-update-notifications
+            Some(select)
+        } else if form::toggle_dropdown(node) {
+            None
+        } else {
+            return false;
+        };
+        if let Some(control) = changed {
+            control.dispatch_event(&mut Event::new("input", true, false, EventKind::Plain));
+            control.dispatch_event(&mut Event::new("change", true, false, EventKind::Plain));
+        }
+        self.needs_frame = true;
+        true
+    }
+
+    /// Submits `form` from the active tab's document, with `submitter` being the submit button that
+    /// submitted it (if any), unless a `submit` event listener cancels the submission.
+    ///
+    /// Documents are only loaded from files, which can't be sent a request body, so `POST`
+    /// submissions are refused with an error rather than navigating to their action as if they
+    /// were `GET` submissions, which would drop the entries submitted.
+    ///
+    /// TODO: Send the body of `POST` submissions once documents can be loaded over the network.
+    ///
+    /// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#form-submission-algorithm
+    fn submit_form(&mut self, form: &NodeRef, submitter: Option<&NodeRef>) {
+        if !form.dispatch_event(&mut Event::new("submit", true, true, EventKind::Plain)) {
+            return;
+        }
+        let form_submission = submission::plan_submission(form, submitter);
+        if form_submission.method == FormMethod::Post {
+            eprintln!(
+                "couldn't submit form to '{}': POST submissions need a network connection, and \
+                 documents can only be loaded from files",
+                form_submission.url
+            );
+            return;
+        }
+        self.follow_link(&form_submission.url, false);
+    }
+
+    /// Scrolls the active tab's document to `target`, as the page or a navigation (e.g. to a
+    /// fragment) asks, rather than the user.
+    ///
+    /// https://drafts.csswg.org/cssom-view/#perform-a-scroll
+    fn scroll_to(&mut self, target: &ScrollTarget, behavior: ScrollOptionsBehavior) {
+        let resolved = self
+            .tabs
+            .active()
+            .box_tree
+            .as_ref()
+            .and_then(|box_tree| resolve_scroll(box_tree, target, behavior));
+        if let Some((position, smooth)) = resolved {
+            (self.send_to_compositor)(CompositorMsg::ScrollTo { position, smooth });
+        }
+    }
+
+    /// Follows the link to `href` from the active tab's document, opening it in a new tab in the
+    /// background if `in_new_tab`.
+    ///
+    /// https://html.spec.whatwg.org/multipage/links.html#following-hyperlinks-2
+    fn follow_link(&mut self, href: &str, in_new_tab: bool) {
+        let tab = self.tabs.active_mut();
+        let path = match navigation::resolve_link(&tab.dom, &tab.path, href) {
+            Ok(LinkTarget::Document(path)) => path,
+            Ok(LinkTarget::Fragment(fragment)) => {
+                // The document is scrolled once it's restyled for its new `:target`.
+                tab.navigate_to_fragment(&fragment);
+                self.needs_frame = true;
+                return;
+            }
+            Err(err) => {
+                eprintln!("couldn't follow link '{}': {:?}", href, err);
+                return;
+            }
+        };
+        // The same stylesheets are applied to the new document, since they come from the command
+        // line rather than the document.  It's shown as it's parsed, between frames.
+        let mut new_tab = match Tab::begin_loading(
+            &path,
+            tab.device.clone(),
+            &self.ua_sheets,
+            &self.author_sheets,
+        ) {
+            Ok(new_tab) => new_tab,
+            Err(err) => {
+                eprintln!("couldn't load '{}': {}", path.display(), err);
+                return;
+            }
+        };
+        // The element a fragment indicates can only be found once it's been parsed.
+        let fragment = navigation::url_fragment(href);
+        if let Some(fragment) = fragment {
+            new_tab.finish_loading(&self.ua_sheets, &self.author_sheets);
+            new_tab.navigate_to_fragment(fragment);
+        }
+        if in_new_tab {
+            self.tabs.open(new_tab, true);
+        } else {
+            // Otherwise, the current document is torn down and replaced.
+            let closed = tab.dom.opaque();
+            tab.navigate(new_tab);
+            (self.send_to_compositor)(CompositorMsg::DocumentClosed(closed));
+            self.update_viewport();
+            self.needs_frame = true;
+        }
+        self.update_window_metadata();
+    }
+
+    fn handle_key(&mut self, input: KeyInput) {
+        let tab = self.tabs.active_mut();
+        // Key events are targeted at the focused element, or the body if nothing is focused.
+        // https://w3c.github.io/uievents/#events-keyboard-event-order
+        let target = tab
+            .focused
+            .clone()
+            .or_else(|| document_body(&tab.dom))
+            .unwrap_or_else(|| tab.dom.clone());
+        let event_type = if input.pressed { "keydown" } else { "keyup" };
+        let not_canceled =
+            target.dispatch_event(&mut Event::keyboard(event_type, input.event.clone()));
+        if !(input.pressed && not_canceled) {
+            return;
+        }
+        if input.copy {
+            if let Some(text) = tab.selected_text() {
+                (self.send_to_compositor)(CompositorMsg::SetClipboard(text));
+            }
+        }
+        if let Some(step) = input.zoom {
+            if tab.zoom_by(step) {
+                self.update_viewport();
+                self.needs_frame = true;
+            }
+        } else if let Some(direction) = input.focus_navigation {
+            // https://html.spec.whatwg.org/multipage/interaction.html#sequential-focus-navigation
+            let next = focus::next_focus_target(&tab.dom, tab.focused.as_ref(), direction);
+            if next.is_some() && next != tab.focused {
+                focus::update_focus(tab.focused.as_ref(), next.as_ref(), false);
+                tab.focused = next;
+                self.needs_frame = true;
+            }
+        } else if let Some(target) =
+            access_key(&input.event).and_then(|key| focus::access_key_target(&tab.dom, key))
+        {
+            // https://html.spec.whatwg.org/multipage/interaction.html#the-accesskey-attribute
+            if focus::is_focusable(&target) && tab.focused.as_ref() != Some(&target) {
+                focus::update_focus(tab.focused.as_ref(), Some(&target), false);
+                tab.focused = Some(target.clone());
+            }
+            self.click(&target, None);
+            self.needs_frame = true;
+        } else if let Some(focused) = tab
+            .focused
+            .clone()
+            .filter(|focused| focus::is_activated_by_key(focused, &input.event.key))
+        {
+            // Enter and Space click the focused element, instead of scrolling the document.
+            self.click(&focused, None);
+            self.needs_frame = true;
+        } else if tab.focused_text_control().is_none() {
+            // Keys scroll the document unless they're editing a focused text control.
+            if let Some(delta) = input.scroll {
+                (self.send_to_compositor)(CompositorMsg::ScrollBy(delta));
+            }
+        } else if let Some(command) = input.edit {
+            self.edit_focused_control(command);
+        }
+    }
+
+    fn edit_focused_control(&mut self, command: EditCommand) {
+        if let Some(control) = self.tabs.active().focused_text_control() {
+            // Pressing Enter in a text field submits its form, by clicking its default button if
+            // it has one.
+            // https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#implicit-submission
+            if command == EditCommand::Insert('\n') {
+                match submission::implicit_submission(&control) {
+                    Some((_, Some(default_button))) => {
+                        self.click(&default_button, None);
+                        return;
+                    }
+                    Some((form, None)) => {
+                        self.submit_form(&form, None);
+                        return;
+                    }
+                    None => {}
+                }
+            }
+            form::edit(&control, command);
+            // TODO: Only fire `input` when the edit changed the value.
+            // https://w3c.github.io/uievents/#event-type-input
+            control.dispatch_event(&mut Event::new("input", true, false, EventKind::Plain));
+            self.needs_frame = true;
+        }
+    }
+
+    /// Runs the tab switcher's `command`, returning false if it closed the last tab.
+    fn handle_tab_command(&mut self, command: TabCommand) -> bool {
+        match command {
+            TabCommand::New => {
+                let device = self.tabs.active().device.clone();
+                match Tab::begin_loading(
+                    &self.home_path,
+                    device,
+                    &self.ua_sheets,
+                    &self.author_sheets,
+                ) {
+                    Ok(tab) => self.tabs.open(tab, false),
+                    Err(err) => {
+                        eprintln!("couldn't load '{}': {}", self.home_path.display(), err);
+                        return true;
+                    }
+                }
+            }
+            TabCommand::Close => match self.tabs.close_active() {
+                Some(closed) => {
+                    (self.send_to_compositor)(CompositorMsg::DocumentClosed(closed.dom.opaque()))
+                }
+                None => return false,
+            },
+            TabCommand::Switch(switch) => {
+                if !self.tabs.switch(switch) {
+                    return true;
+                }
+            }
+        }
+        // Background tabs weren't restyled as the window changed, so the tab now active is
+        // brought up to date with it.
+        self.update_viewport();
+        self.update_window_metadata();
+        self.needs_frame = true;
+        true
+    }
+
+    /// Reloads the files that changed on disk since they were last checked, so the window can be
+    /// used as a live preview of the HTML and CSS being edited.  Changed stylesheets restyle every
+    /// tab, and changed documents are loaded again in the tabs showing them, scrolled to where
+    /// they were.
+    fn reload_changed_files(&mut self) {
+        self.last_watch_check = Instant::now();
+        // Tabs may have been opened on, or navigated to, documents that aren't watched yet.
+        for tab in self.tabs.iter_mut() {
+            self.watcher.watch(&tab.path);
+        }
+        let changed = self.watcher.changed_files();
+        if changed.is_empty() {
+            return;
+        }
+        if self.css_paths.iter().any(|path| changed.contains(path)) {
+            self.reload_stylesheets();
+        }
+        for tab in self.tabs.iter_mut() {
+            if !changed.contains(&tab.path) {
+                continue;
+            }
+            let document =
+                match load_document(&tab.path, &tab.device, &self.ua_sheets, &self.author_sheets) {
+                    Ok(document) => document,
+                    // The file may be missing while it's being saved, and is reloaded once it's
+                    // back.
+                    Err(err) => {
+                        eprintln!("couldn't reload '{}': {}", tab.path.display(), err);
+                        continue;
+                    }
+                };
+            let closed = tab.dom.opaque();
+            let scroll_position = tab
+                .dom
+                .as_document()
+                .map(|document| document.scroll_position.get());
+            tab.navigate(Tab::new(document, tab.device.clone()));
+            tab.pending_scroll = scroll_position.map(ScrollTarget::Position);
+            (self.send_to_compositor)(CompositorMsg::DocumentClosed(closed));
+        }
+        self.update_viewport();
+        self.update_window_metadata();
+        self.needs_frame = true;
+    }
+
+    /// Parses the author sheets from their files again, and restyles every tab with them.  The
+    /// sheets are kept as they were if any of their files can't be read.
+    fn reload_stylesheets(&mut self) {
+        let author_sheets = self
+            .css_paths
+            .iter()
+            .map(|path| load_stylesheet(path))
+            .collect::<io::Result<Vec<_>>>();
+        self.author_sheets = match author_sheets {
+            Ok(author_sheets) => author_sheets,
+            Err(err) => {
+                eprintln!("couldn't reload stylesheets: {}", err);
+                return;
+            }
+        };
+        self.invalidation_map =
+            InvalidationMap::new(self.ua_sheets.iter().chain(self.author_sheets.iter()));
+        for tab in self.tabs.iter_mut() {
+            apply_styles(
+                tab.dom.clone(),
+                &tab.device,
+                &self.ua_sheets,
+                &[],
+                &self.author_sheets,
+            );
+            tab.box_tree = build_box_tree(tab.dom.clone(), None);
+        }
+    }
+
+    /// Brings the active tab up to date with the viewport and the color scheme the user prefers,
+    /// restyling it if that changes its device, and selecting its images for the viewport's size.
+    fn update_viewport(&mut self) {
+        let Viewport {
+            width,
+            height,
+            scale_factor,
+        } = self.viewport;
+        let tab = self.tabs.active_mut();
+        let scale_factor = scale_factor * tab.zoom;
+        update_device(
+            &mut tab.device,
+            screen_device(width, height, scale_factor),
+            &tab.dom,
+            &self.ua_sheets,
+            &self.author_sheets,
+            &mut tab.box_tree,
+        );
+        update_color_scheme(
+            &mut tab.device,
+            self.color_scheme,
+            &tab.dom,
+            &self.ua_sheets,
+            &self.author_sheets,
+            &mut tab.box_tree,
+        );
+        load_document_images(
+            &tab.dom,
+            &tab.image_cache,
+            width / scale_factor,
+            scale_factor,
+        );
+    }
+
+    /// Runs the active tab's animation frame callbacks, restyles and lays it out, and sends the
+    /// compositor a new frame.
+    ///
+    /// https://html.spec.whatwg.org/multipage/webappapis.html#update-the-rendering
+    fn update_the_rendering(&mut self) {
+        let Viewport {
+            width,
+            height,
+            scale_factor,
+        } = self.viewport;
+        let tab = self.tabs.active_mut();
+        if let Some(script_engine) = &mut tab.script_engine {
+            script_engine.run_animation_frame_callbacks();
+        }
+        // Timers, animation frame callbacks, and input may have changed the DOM since the last
+        // frame.  Background tabs are brought up to date once they're activated.
+        let scale_factor = scale_factor * tab.zoom;
+        let mutations = mutation::take_mutations(&tab.dom);
+        if !mutations.is_empty() {
+            let restyle = restyle_mutations(
+                &tab.dom,
+                &mutations,
+                &self.invalidation_map,
+                &tab.device,
+                &self.ua_sheets,
+                &[],
+                &self.author_sheets,
+            );
+            update_box_tree(&mut tab.box_tree, &tab.dom, &mutations, &restyle);
+            load_document_images(
+                &tab.dom,
+                &tab.image_cache,
+                width / scale_factor,
+                scale_factor,
+            );
+        }
+        layout_document(
+            &mut tab.box_tree,
+            &tab.dom,
+            &tab.device,
+            &self.ua_sheets,
+            &self.author_sheets,
+        );
+        let (max_scroll_position, snap_positions) = match tab.box_tree.as_ref() {
+            Some(box_tree) => {
+                let (viewport_width, viewport_height) =
+                    (width / scale_factor, height / scale_factor);
+                (
+                    max_scroll_position(box_tree, viewport_width, viewport_height),
+                    snap_positions(box_tree, viewport_width, viewport_height),
+                )
+            }
+            None => (Point::default(), SnapPositions::default()),
+        };
+        let mut display_list = build_page_display_list(
+            tab.box_tree.as_ref(),
+            &tab.image_cache,
+            self.animation_time().unwrap_or_default(),
+            scale_factor,
+        );
+        if let (Some(inspected), Some(box_tree)) = (&tab.inspected, &tab.box_tree) {
+            let scroll_position = tab
+                .dom
+                .as_document()
+                .map_or(Point::default(), |document| document.scroll_position.get());
+            let viewport = Rect {
+                start_x: scroll_position.x,
+                start_y: scroll_position.y,
+                width: CSSPixelLength::new(width / scale_factor),
+                height: CSSPixelLength::new(height / scale_factor),
+            };
+            overlay::prepare_highlight(&mut display_list, box_tree, inspected, viewport);
+        }
+        let frame = Frame {
+            document: tab.dom.opaque(),
+            display_list: display_list.clone(),
+            max_scroll_position,
+            snap_positions,
+            scale_factor,
+        };
+        self.display_list = display_list;
+        (self.send_to_compositor)(CompositorMsg::Frame(frame));
+        // Fragments are scrolled to once the document is laid out, so the element they indicate
+        // has a position.
+        if let Some(target) = tab.pending_scroll.take() {
+            self.scroll_to(&target, ScrollOptionsBehavior::Auto);
+        }
+        self.needs_frame = false;
+        self.last_frame = Instant::now();
+        if !mutations.is_empty() {
+            // Scripts may have changed the document's title or icon.
+            self.update_window_metadata();
+        }
+    }
+
+    /// Sends the compositor the title and icon of the active tab's document, unless they're what
+    /// the window already shows.  Documents without a title or icon get the defaults.  While more
+    /// than one tab is open, the title is prefixed with the position of the active tab, since
+    /// there's no tab strip to show it in.
+    fn update_window_metadata(&mut self) {
+        let Tab {
+            dom: document,
+            image_cache,
+            ..
+        } = self.tabs.active();
+        let title = metadata::document_title(document).filter(|title| !title.is_empty());
+        let title = title.as_deref().unwrap_or(DEFAULT_WINDOW_TITLE);
+        let title = if self.tabs.count() > 1 {
+            format!(
+                "[{}/{}] {}",
+                self.tabs.active_index() + 1,
+                self.tabs.count(),
+                title
+            )
+        } else {
+            title.to_owned()
+        };
+        if self.shown_title.as_ref() != Some(&title) {
+            (self.send_to_compositor)(CompositorMsg::SetTitle(title.clone()));
+            self.shown_title = Some(title);
+        }
+        let icon_href = metadata::icon_href(document);
+        if icon_href != self.shown_icon_href {
+            let icon = icon_href.as_ref().and_then(|href| {
+                // Window icons are small, so SVG icons are rasterized at a typical icon size.
+                let image = match image_cache.load(href) {
+                    Ok(image) => image_cache.rasterize(&image, 64., 64.),
+                    Err(err) => {
+                        eprintln!("couldn't load icon '{}': {:?}", href, err);
+                        return None;
+                    }
+                };
+                let image = image.image();
+                Some(WindowIcon {
+                    rgba: image.frame(0).pixels().to_vec(),
+                    width: image.width(),
+                    height: image.height(),
+                })
+            });
+            (self.send_to_compositor)(CompositorMsg::SetIcon(icon));
+            self.shown_icon_href = icon_href;
+        }
+    }
+}
+
+/// The cursor to show over `hit_nodes`, the chain of nodes under the mouse that
+/// `DisplayList::hit_test` found, where `text` is the text node under the mouse (if any).  An
+/// `auto` cursor is a text cursor over text and text controls, and the default cursor elsewhere.
+///
+/// https://drafts.csswg.org/css-ui-4/#valdef-cursor-auto
+fn cursor_over(hit_nodes: &[NodeRef], text: Option<&NodeRef>) -> Cursor {
+    let target = match hit_nodes.first() {
+        Some(target) => target,
+        None => return Cursor::Default,
+    };
+    match target.computed_values().cursor {
+        Cursor::Auto => {
+            let over_text = text.and_then(|text| text.parent()).as_ref() == Some(target);
+            let over_text_control =
+                FormControlKind::of(target).map_or(false, FormControlKind::is_text_control);
+            if over_text || over_text_control {
+                Cursor::Text
+            } else {
+                Cursor::Default
+            }
+        }
+        cursor => cursor,
+    }
+}
+
+/// The body element of `document`, if it has one.
+fn document_body(document: &NodeRef) -> Option<NodeRef> {
+    document.descendants().find(|node| {
+        node.as_element()
+            .map_or(false, |element| element.name.local == local_name!("body"))
+    })
+}
+
+/// The access key pressed by `event`, if it's a character key pressed with Alt (and no other
+/// modifier but Shift), which is how access keys are pressed in other browsers on Windows and
+/// Linux.
+///
+/// https://html.spec.whatwg.org/multipage/interaction.html#keyboard-shortcuts-disclaimer
+fn access_key(event: &KeyboardEvent) -> Option<char> {
+    let modifiers = event.modifiers;
+    if !modifiers.alt || modifiers.ctrl || modifiers.meta {
+        return None;
+    }
+    let mut chars = event.key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(key), None) if !key.is_whitespace() => Some(key),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tab::TabSwitch;
+    use cssparser::RGBA;
+    use kosmonaut_core::dom::event::Modifiers;
+    use kosmonaut_core::gfx::display::DisplayCommand;
+    use kosmonaut_core::page::user_agent_stylesheet;
+    use kosmonaut_core::style::media::MediaType;
+    use kosmonaut_core::style::stylesheet::parse_css_to_stylesheet;
+    use std::sync::mpsc::Receiver;
+    use tempdir::TempDir;
+
+    fn assert_send<T: Send>() {}
+
+    /// Starts a pipeline with a tab for each of `html_files`, in an 800x600 viewport.
+    fn spawn(html_files: Vec<PathBuf>) -> (Pipeline, Receiver<CompositorMsg>) {
+        let (sender, receiver) = channel();
+        let pipeline = Pipeline::spawn(
+            move || {
+                let device = Device::new(MediaType::Screen, 800., 600.);
+                let ua_sheets = vec![user_agent_stylesheet()];
+                let author_sheets = vec![parse_css_to_stylesheet(
+                    None,
+                    &mut "body { margin-top: 0px; margin-right: 0px; margin-bottom: 0px } \
+                          body { margin-left: 0px } div { height: 100px }"
+                        .to_owned(),
+                )];
+                let documents = html_files
+                    .iter()
+                    .map(|path| load_document(path, &device, &ua_sheets, &author_sheets).unwrap())
+                    .collect();
+                LoadedDocuments {
+                    documents,
+                    device,
+                    ua_sheets,
+                    author_sheets,
+                    css_paths: Vec::new(),
+                }
+            },
+            Viewport {
+                width: 800.,
+                height: 600.,
+                scale_factor: 1.,
+            },
+            move |msg| sender.send(msg).unwrap(),
+        );
+        (pipeline, receiver)
+    }
+
+    fn next_frame(receiver: &Receiver<CompositorMsg>) -> Frame {
+        receiver
+            .iter()
+            .find_map(|msg| match msg {
+                CompositorMsg::Frame(frame) => Some(frame),
+                _ => None,
+            })
+            .expect("the pipeline stopped before sending a frame")
+    }
+
+    fn write_html(dir: &TempDir, name: &str, html: &str) -> PathBuf {
+        let path = dir.path().join(name);
+        std::fs::write(&path, html).unwrap();
+        path
+    }
+
+    fn press_key(pipeline: &Pipeline, key: &str, focus_navigation: Option<FocusDirection>) {
+        pipeline.send(PipelineMsg::Key(KeyInput {
+            pressed: true,
+            event: KeyboardEvent {
+                key: key.to_owned(),
+                modifiers: Modifiers::default(),
+            },
+            zoom: None,
+            scroll: None,
+            edit: None,
+            copy: false,
+            focus_navigation,
+        }));
+    }
+
+    #[test]
+    fn messages_can_be_sent_between_threads() {
+        assert_send::<PipelineMsg>();
+        assert_send::<CompositorMsg>();
+    }
+
+    #[test]
+    fn sends_frames_of_the_active_tab() {
+        let dir = TempDir::new("pipeline").unwrap();
+        let a = write_html(&dir, "a.html", "<title>A</title><div></div>");
+        let b = write_html(&dir, "b.html", "<title>B</title><div></div><div></div>");
+        let (pipeline, receiver) = spawn(vec![a, b]);
+        let frame = next_frame(&receiver);
+        assert_eq!(frame.scale_factor, 1.);
+        assert_eq!(frame.max_scroll_position, Point::default());
+
+        pipeline.send(PipelineMsg::Tab(TabCommand::Switch(TabSwitch::Next)));
+        let mut title = None;
+        let frame_b = receiver
+            .iter()
+            .find_map(|msg| match msg {
+                CompositorMsg::SetTitle(new_title) => {
+                    title = Some(new_title);
+                    None
+                }
+                CompositorMsg::Frame(frame) => Some(frame),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(title.as_deref(), Some("[2/2] B"));
+        assert_ne!(frame_b.document, frame.document);
+
+        // At two device pixels per CSS pixel, the 200px tall document overflows the 100px tall
+        // viewport by 100px.
+        pipeline.send(PipelineMsg::Resize(Viewport {
+            width: 800.,
+            height: 200.,
+            scale_factor: 2.,
+        }));
+        let frame_b = next_frame(&receiver);
+        assert_eq!(frame_b.scale_factor, 2.);
+        assert_eq!(frame_b.max_scroll_position, Point { x: 0., y: 100. });
+
+        pipeline.send(PipelineMsg::Tab(TabCommand::Close));
+        assert!(receiver
+            .iter()
+            .any(|msg| msg == CompositorMsg::DocumentClosed(frame_b.document)));
+        pipeline.send(PipelineMsg::Tab(TabCommand::Close));
+        assert!(receiver.iter().any(|msg| msg == CompositorMsg::Exit));
+    }
+
+    #[test]
+    fn dispatches_mouse_input_to_the_node_under_the_mouse() {
+        let dir = TempDir::new("pipeline").unwrap();
+        write_html(&dir, "next.html", "<title>Next</title>");
+        let html = write_html(
+            &dir,
+            "index.html",
+            "<div></div><div><a href=next.html><div></div></a></div>",
+        );
+        let (pipeline, receiver) = spawn(vec![html]);
+        let first_frame = next_frame(&receiver);
+        // The link is 100px down the document, which the compositor has scrolled down by 95px.
+        for action in &[MouseAction::Press, MouseAction::Release] {
+            pipeline.send(PipelineMsg::Mouse(MouseInput {
+                action: *action,
+                event: MouseEvent {
+                    client_x: 10.,
+                    client_y: 10.,
+                    button: MouseButton::Primary,
+                    modifiers: Modifiers::default(),
+                },
+                scroll_position: Point { x: 0., y: 95. },
+            }));
+        }
+        assert!(receiver
+            .iter()
+            .any(|msg| msg == CompositorMsg::DocumentClosed(first_frame.document)));
+        assert!(receiver
+            .iter()
+            .any(|msg| msg == CompositorMsg::SetTitle("Next".to_owned())));
+    }
+
+    #[test]
+    fn follows_links_focused_with_the_keyboard() {
+        let dir = TempDir::new("pipeline").unwrap();
+        write_html(&dir, "next.html", "<title>Next</title>");
+        let html = write_html(
+            &dir,
+            "index.html",
+            "<button tabindex=-1>Skipped</button><a href=next.html>Next</a>",
+        );
+        let (pipeline, receiver) = spawn(vec![html]);
+        next_frame(&receiver);
+        press_key(&pipeline, "Tab", Some(FocusDirection::Forward));
+        press_key(&pipeline, "Enter", None);
+        assert!(receiver
+            .iter()
+            .any(|msg| msg == CompositorMsg::SetTitle("Next".to_owned())));
+    }
+
+    #[test]
+    fn shows_documents_linked_to_as_they_load() {
+        let dir = TempDir::new("pipeline").unwrap();
+        write_html(
+            &dir,
+            "long.html",
+            &format!("<title>Long</title>{}", "<div></div>".repeat(10_000)),
+        );
+        let html = write_html(&dir, "index.html", "<a href=long.html>Long</a>");
+        let (pipeline, receiver) = spawn(vec![html]);
+        let first_frame = next_frame(&receiver);
+        press_key(&pipeline, "Tab", Some(FocusDirection::Forward));
+        press_key(&pipeline, "Enter", None);
+        let frames = receiver
+            .iter()
+            .filter_map(|msg| match msg {
+                CompositorMsg::Frame(frame) if frame.document != first_frame.document => {
+                    Some(frame.max_scroll_position.y)
+                }
+                _ => None,
+            })
+            .take_while(|max_scroll_y| *max_scroll_y < 10_000. * 100. - 600.)
+            .collect::<Vec<_>>();
+        // The document, whose source is longer than a chunk, was shown before all of its 100px
+        // tall `<div>`s had been parsed.
+        assert!(!frames.is_empty());
+        assert!(frames[0] > 0.);
+    }
+
+    #[test]
+    fn scrolls_to_fragments_linked_to() {
+        let dir = TempDir::new("pipeline").unwrap();
+        let html = write_html(
+            &dir,
+            "index.html",
+            "<style>html { scroll-behavior: smooth }</style>\
+             <a href=#end>End</a><div></div><p id=end>End</p>",
+        );
+        let (pipeline, receiver) = spawn(vec![html]);
+        next_frame(&receiver);
+        press_key(&pipeline, "Tab", Some(FocusDirection::Forward));
+        press_key(&pipeline, "Enter", None);
+        let (position, smooth) = receiver
+            .iter()
+            .find_map(|msg| match msg {
+                CompositorMsg::ScrollTo { position, smooth } => Some((position, smooth)),
+                _ => None,
+            })
+            .unwrap();
+        // The paragraph is below the link and the 100px tall `<div>`.
+        assert!(position.y > 100.);
+        assert!(smooth);
+    }
+
+    #[test]
+    fn scrolls_to_the_fragments_of_documents_linked_to() {
+        let dir = TempDir::new("pipeline").unwrap();
+        let html = write_html(&dir, "index.html", "<a href=other.html#end>Other</a>");
+        write_html(
+            &dir,
+            "other.html",
+            "<style>:target { margin-top: 200px }</style><p id=end>End</p>",
+        );
+        let (pipeline, receiver) = spawn(vec![html]);
+        next_frame(&receiver);
+        press_key(&pipeline, "Tab", Some(FocusDirection::Forward));
+        press_key(&pipeline, "Enter", None);
+        let position = receiver
+            .iter()
+            .find_map(|msg| match msg {
+                CompositorMsg::ScrollTo { position, .. } => Some(position),
+                _ => None,
+            })
+            .unwrap();
+        // The paragraph is scrolled to after it's restyled as the target.
+        assert!(position.y >= 200.);
+    }
+
+    #[test]
+    fn restyles_when_the_preferred_color_scheme_changes() {
+        let dir = TempDir::new("pipeline").unwrap();
+        let html = write_html(
+            &dir,
+            "index.html",
+            "<style>:root { color-scheme: light dark }</style><div></div>",
+        );
+        let (pipeline, receiver) = spawn(vec![html]);
+        let viewport_background = |frame: Frame| {
+            frame
+                .display_list
+                .commands()
+                .iter()
+                .find_map(|command| match command {
+                    DisplayCommand::ViewportBackground(rgba) => Some(*rgba),
+                    _ => None,
+                })
+                .unwrap()
+        };
+        assert_eq!(
+            viewport_background(next_frame(&receiver)),
+            RGBA::new(255, 255, 255, 255)
+        );
+        pipeline.send(PipelineMsg::ColorScheme(ColorScheme::Dark));
+        assert_eq!(
+            viewport_background(next_frame(&receiver)),
+            RGBA::new(18, 18, 18, 255)
+        );
+    }
+
+    #[test]
+    fn shows_the_cursor_for_the_node_under_the_mouse() {
+        let dir = TempDir::new("pipeline").unwrap();
+        let html = write_html(
+            &dir,
+            "index.html",
+            "<div>Some text</div>\
+             <div><a href=next.html><div></div></a></div>\
+             <div style='cursor: wait'></div>",
+        );
+        let (pipeline, receiver) = spawn(vec![html]);
+        next_frame(&receiver);
+        let move_to = |client_y| {
+            pipeline.send(PipelineMsg::Mouse(MouseInput {
+                action: MouseAction::Move,
+                event: MouseEvent {
+                    client_x: 10.,
+                    client_y,
+                    button: MouseButton::Primary,
+                    modifiers: Modifiers::default(),
+                },
+                scroll_position: Point::default(),
+            }))
+        };
+        let next_cursor = || {
+            receiver
+                .iter()
+                .find_map(|msg| match msg {
+                    CompositorMsg::SetCursor(cursor) => Some(cursor),
+                    _ => None,
+                })
+                .unwrap()
+        };
+        move_to(5.);
+        assert_eq!(next_cursor(), Cursor::Text);
+        // Below the text, the `auto` cursor is the default one.
+        move_to(50.);
+        assert_eq!(next_cursor(), Cursor::Default);
+        move_to(150.);
+        assert_eq!(next_cursor(), Cursor::Pointer);
+        move_to(250.);
+        assert_eq!(next_cursor(), Cursor::Wait);
+    }
+}
@@ -0,0 +1,353 @@
+//! Reftests, which check that a test page renders to the same pixels as a reference page that gets
+//! there with simpler (or already well-tested) features, complementing the textual layout dumps
+//! for paint-level features.
+//!
+//! Reftests are listed in a manifest, one per line, loosely following Gecko's reftest.list format:
+//!
+//! ```text
+//! # Comments start with a hash.
+//! == border-shorthand.html border-shorthand-ref.html
+//! != background-color.html background-color-ref.html
+//! fuzzy(2,40) == image-scaling.html image-scaling-ref.html
+//! ```
+//!
+//! `==` tests pass if both pages render to the same pixels, and `!=` tests if they don't.  Pages
+//! are resolved against the directory containing the manifest.  `fuzzy(MAX_DIFFERENCE,MAX_PIXELS)`
+//! lets up to `MAX_PIXELS` pixels differ, in no color channel by more than `MAX_DIFFERENCE`.
+//!
+//! https://firefox-source-docs.mozilla.org/layout/Reftest.html
+
+use std::path::{Path, PathBuf};
+
+use kosmonaut_core::image::{save_png, DecodedImage};
+use kosmonaut_core::style::media::Device;
+use kosmonaut_core::style::stylesheet::Stylesheet;
+
+use crate::headless::render_page;
+
+/// Whether a reftest expects its pages to render the same.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReftestKind {
+    /// `==`: the pages must render to the same pixels.
+    Match,
+    /// `!=`: the pages must render to different pixels.
+    Mismatch,
+}
+
+/// How much the pages of a reftest can differ and still be considered the same.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Fuzzy {
+    /// The greatest difference allowed in any color channel of any pixel.
+    pub max_difference: u8,
+    /// The greatest number of pixels allowed to differ.
+    pub max_pixels: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reftest {
+    pub kind: ReftestKind,
+    pub test: PathBuf,
+    pub reference: PathBuf,
+    pub fuzzy: Fuzzy,
+}
+
+impl Reftest {
+    /// Whether the test passes, given how its pages' pixels compare.
+    pub fn passes(&self, comparison: &PixelComparison) -> bool {
+        let same = comparison.differing_pixels == 0
+            || (comparison.max_difference <= self.fuzzy.max_difference
+                && comparison.differing_pixels <= self.fuzzy.max_pixels);
+        match self.kind {
+            ReftestKind::Match => same,
+            ReftestKind::Mismatch => !same,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ManifestError {
+    Io(std::io::Error),
+    /// A line of the manifest (numbered from one) wasn't a reftest.
+    Syntax {
+        line: usize,
+        text: String,
+    },
+}
+
+impl From<std::io::Error> for ManifestError {
+    fn from(err: std::io::Error) -> Self {
+        ManifestError::Io(err)
+    }
+}
+
+/// Reads the reftests listed in the manifest at `path`.
+pub fn read_manifest(path: &Path) -> Result<Vec<Reftest>, ManifestError> {
+    let manifest = std::fs::read_to_string(path)?;
+    parse_manifest(&manifest, path.parent().unwrap_or_else(|| Path::new("")))
+}
+
+/// Parses the reftests listed in `manifest`, resolving their pages against `base_dir`.
+fn parse_manifest(manifest: &str, base_dir: &Path) -> Result<Vec<Reftest>, ManifestError> {
+    let mut reftests = Vec::new();
+    for (index, line) in manifest.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let syntax_error = || ManifestError::Syntax {
+            line: index + 1,
+            text: line.to_owned(),
+        };
+        let mut tokens = line.split_whitespace().peekable();
+        let fuzzy = match tokens.peek() {
+            Some(token) if token.starts_with("fuzzy(") => {
+                let fuzzy = parse_fuzzy(token).ok_or_else(syntax_error)?;
+                tokens.next();
+                fuzzy
+            }
+            _ => Fuzzy::default(),
+        };
+        let kind = match tokens.next() {
+            Some("==") => ReftestKind::Match,
+            Some("!=") => ReftestKind::Mismatch,
+            _ => return Err(syntax_error()),
+        };
+        match (tokens.next(), tokens.next(), tokens.next()) {
+            (Some(test), Some(reference), None) => reftests.push(Reftest {
+                kind,
+                test: base_dir.join(test),
+                reference: base_dir.join(reference),
+                fuzzy,
+            }),
+            _ => return Err(syntax_error()),
+        }
+    }
+    Ok(reftests)
+}
+
+/// Parses `fuzzy(MAX_DIFFERENCE,MAX_PIXELS)`.
+fn parse_fuzzy(token: &str) -> Option<Fuzzy> {
+    let args = token.strip_prefix("fuzzy(")?.strip_suffix(')')?;
+    let mut args = args.split(',');
+    let fuzzy = Fuzzy {
+        max_difference: args.next()?.trim().parse().ok()?,
+        max_pixels: args.next()?.trim().parse().ok()?,
+    };
+    match args.next() {
+        Some(_) => None,
+        None => Some(fuzzy),
+    }
+}
+
+/// How two renderings of the same size differ.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PixelComparison {
+    /// The number of pixels that differ in any color channel.
+    pub differing_pixels: usize,
+    /// The greatest difference in any color channel of any pixel.
+    pub max_difference: u8,
+}
+
+/// Compares two renderings, given as RGBA8 pixels, of the same size.
+pub fn compare_pixels(test: &[u8], reference: &[u8]) -> PixelComparison {
+    assert_eq!(test.len(), reference.len());
+    let mut comparison = PixelComparison {
+        differing_pixels: 0,
+        max_difference: 0,
+    };
+    for (test_pixel, reference_pixel) in test.chunks_exact(4).zip(reference.chunks_exact(4)) {
+        let difference = channel_difference(test_pixel, reference_pixel);
+        if difference > 0 {
+            comparison.differing_pixels += 1;
+            comparison.max_difference = comparison.max_difference.max(difference);
+        }
+    }
+    comparison
+}
+
+/// An image, as RGBA8 pixels, highlighting where two renderings of the same size differ.  Pixels
+/// that differ are opaque red, and those that don't are faded copies of the test's, so the
+/// differences can be told apart from the page around them.
+pub fn diff_image(test: &[u8], reference: &[u8]) -> Vec<u8> {
+    assert_eq!(test.len(), reference.len());
+    let mut diff = Vec::with_capacity(test.len());
+    for (test_pixel, reference_pixel) in test.chunks_exact(4).zip(reference.chunks_exact(4)) {
+        if channel_difference(test_pixel, reference_pixel) > 0 {
+            diff.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            diff.extend_from_slice(&test_pixel[..3]);
+            diff.push(test_pixel[3] / 4);
+        }
+    }
+    diff
+}
+
+/// The greatest difference in any color channel between two RGBA8 pixels.
+fn channel_difference(pixel: &[u8], other_pixel: &[u8]) -> u8 {
+    pixel
+        .iter()
+        .zip(other_pixel)
+        .map(|(channel, other_channel)| (*channel as i16 - *other_channel as i16).abs() as u8)
+        .max()
+        .unwrap_or(0)
+}
+
+/// The viewport size reftests are rendered at unless another is given, which is the same as the
+/// Web Platform Tests'.
+pub const REFTEST_INNER_WINDOW_WIDTH_PX: f32 = 800.;
+pub const REFTEST_INNER_WINDOW_HEIGHT_PX: f32 = 600.;
+
+/// Runs the reftests listed in the manifest at `manifest_path`, rendering their pages headless on
+/// `device`, and prints whether each passed.  Images of each failing test, its reference, and the
+/// pixels that differ between them are written to `diff_dir`, if given.  Returns whether every
+/// test passed.
+///
+/// Pages are only styled by the user agent sheet and their own `style` attributes, since
+/// `<style>` and `<link>` stylesheets aren't loaded yet.
+pub fn run_reftests(
+    manifest_path: &Path,
+    device: &Device,
+    ua_sheets: &[Stylesheet],
+    diff_dir: Option<&Path>,
+) -> bool {
+    let reftests = read_manifest(manifest_path)
+        .unwrap_or_else(|err| panic!("couldn't read reftest manifest: {:?}", err));
+    let render = |html_file: &Path| render_page(html_file, None, device, ua_sheets);
+    let mut passed = 0;
+    for reftest in &reftests {
+        let description = reftest_description(reftest);
+        let (test, reference) = match (render(&reftest.test), render(&reftest.reference)) {
+            (Ok(test), Ok(reference)) => (test, reference),
+            (Err(err), _) | (_, Err(err)) => {
+                println!("FAIL {} (couldn't load page: {})", description, err);
+                continue;
+            }
+        };
+        let (test_pixels, reference_pixels) = (test.frame(0).pixels(), reference.frame(0).pixels());
+        let comparison = compare_pixels(test_pixels, reference_pixels);
+        if reftest.passes(&comparison) {
+            println!("PASS {}", description);
+            passed += 1;
+            continue;
+        }
+        println!(
+            "FAIL {} ({} pixels differ, by up to {})",
+            description, comparison.differing_pixels, comparison.max_difference
+        );
+        if let Some(diff_dir) = diff_dir {
+            let name = reftest
+                .test
+                .file_stem()
+                .map_or("reftest".into(), |stem| stem.to_string_lossy());
+            let diff = DecodedImage::new(
+                test.width(),
+                test.height(),
+                diff_image(test_pixels, reference_pixels),
+            );
+            std::fs::create_dir_all(diff_dir).expect("couldn't create reftest diff directory");
+            for (image, suffix) in &[(&test, ""), (&reference, "-ref"), (&diff, "-diff")] {
+                save_png(image, &diff_dir.join(format!("{}{}.png", name, suffix)))
+                    .expect("couldn't write reftest image");
+            }
+        }
+    }
+    println!("{} of {} reftests passed", passed, reftests.len());
+    passed == reftests.len()
+}
+
+/// How a reftest is written in its manifest, e.g. `== test.html test-ref.html`.
+fn reftest_description(reftest: &Reftest) -> String {
+    let kind = match reftest.kind {
+        ReftestKind::Match => "==",
+        ReftestKind::Mismatch => "!=",
+    };
+    format!(
+        "{} {} {}",
+        kind,
+        reftest.test.display(),
+        reftest.reference.display()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_manifests() {
+        let manifest = "# Borders\n\
+                        == border.html border-ref.html\n\
+                        \n\
+                        fuzzy(2,40) != image.html image-ref.html # scaled images blur\n";
+        let reftests = parse_manifest(manifest, Path::new("tests/reftests")).unwrap();
+        assert_eq!(
+            reftests,
+            vec![
+                Reftest {
+                    kind: ReftestKind::Match,
+                    test: PathBuf::from("tests/reftests/border.html"),
+                    reference: PathBuf::from("tests/reftests/border-ref.html"),
+                    fuzzy: Fuzzy::default(),
+                },
+                Reftest {
+                    kind: ReftestKind::Mismatch,
+                    test: PathBuf::from("tests/reftests/image.html"),
+                    reference: PathBuf::from("tests/reftests/image-ref.html"),
+                    fuzzy: Fuzzy {
+                        max_difference: 2,
+                        max_pixels: 40,
+                    },
+                },
+            ]
+        );
+        assert!(matches!(
+            parse_manifest("== a.html\n", Path::new("")),
+            Err(ManifestError::Syntax { line: 1, .. })
+        ));
+        assert!(matches!(
+            parse_manifest("== a.html b.html\nfuzzy(2) == a.html b.html", Path::new("")),
+            Err(ManifestError::Syntax { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn fuzzy_tests_tolerate_small_differences() {
+        let white = [255; 4 * 4];
+        let mut off_white = white;
+        off_white[0] = 253;
+        off_white[4] = 254;
+        let comparison = compare_pixels(&off_white, &white);
+        assert_eq!(
+            comparison,
+            PixelComparison {
+                differing_pixels: 2,
+                max_difference: 2,
+            }
+        );
+        let mut reftest = Reftest {
+            kind: ReftestKind::Match,
+            test: PathBuf::from("test.html"),
+            reference: PathBuf::from("ref.html"),
+            fuzzy: Fuzzy::default(),
+        };
+        assert!(!reftest.passes(&comparison));
+        assert!(reftest.passes(&compare_pixels(&white, &white)));
+        reftest.fuzzy = Fuzzy {
+            max_difference: 2,
+            max_pixels: 2,
+        };
+        assert!(reftest.passes(&comparison));
+        reftest.kind = ReftestKind::Mismatch;
+        assert!(!reftest.passes(&comparison));
+    }
+
+    #[test]
+    fn diff_images_highlight_differing_pixels() {
+        let test = [10, 20, 30, 255, 0, 0, 0, 255];
+        let reference = [10, 20, 30, 255, 0, 0, 1, 255];
+        assert_eq!(
+            diff_image(&test, &reference),
+            vec![10, 20, 30, 63, 255, 0, 0, 255]
+        );
+    }
+}
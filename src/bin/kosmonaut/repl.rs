@@ -0,0 +1,290 @@
+//! An interactive console for exploring how a page is rendered without restarting Kosmonaut for
+//! each question.  Commands are read a line at a time and run against a `Page`, which is kept
+//! between them, e.g.:
+//!
+//! ```text
+//! > load tests/websrc/rainbow-divs.html
+//! > set viewport 800x600
+//! > query .rainbow
+//! > dump layout verbose
+//! ```
+
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use crate::cli;
+use kosmonaut_core::a11y::{build_accessibility_tree, dump_accessibility_tree};
+use kosmonaut_core::dom::traits::*;
+use kosmonaut_core::gfx::display::dump_stacking_contexts;
+use kosmonaut_core::layout::geometry::box_geometry;
+use kosmonaut_core::layout::{
+    dump_layout_element, DumpLayout, DumpLayoutFormat, DumpLayoutVerbosity,
+};
+use kosmonaut_core::page::Page;
+use kosmonaut_core::style::dump::dump_styles;
+use kosmonaut_core::style::properties::id::{LonghandId, PropertyId};
+use kosmonaut_core::style::select::Selectors;
+use kosmonaut_core::style::stylesheet::Stylesheet;
+use strum::IntoEnumIterator;
+
+const HELP: &str = "\
+Commands:
+  load <path>                   Load the HTML document at <path> (or a file:// URL), styled by
+                                the stylesheets Kosmonaut was started with.
+  set viewport <W>x<H>[@<S>]    Set the viewport to W by H device pixels, at S device pixels
+                                per CSS pixel (1 if not given).
+  query <selector>              List the elements matching <selector>, with their border boxes.
+  dump layout [verbose|2]       Dump the box tree, laid out in the viewport.
+  dump display-list             Dump the display list's paint commands, in painting order.
+  dump stacking                 Dump the stacking context tree and the boxes painted in each.
+  dump styles [<property>...]   Dump the computed values of each element's properties.
+  dump a11y                     Dump the accessibility tree.
+  render <path>                 Render the page to a PNG at <path>.
+  help                          Show this help.
+  quit                          Leave the console.";
+
+/// The state kept between commands: the page loaded last (if any), the stylesheets pages are
+/// loaded with, and the viewport they're rendered in.
+pub struct Repl {
+    page: Option<Page>,
+    author_sheets: Vec<Stylesheet>,
+    /// The width and height of the viewport in device pixels, and its scale factor.
+    viewport: (f32, f32, f32),
+}
+
+impl Repl {
+    /// Starts a console with `page` loaded (if given), which loads pages styled by
+    /// `author_sheets` in a viewport of `width` by `height` device pixels at `scale_factor`.
+    pub fn new(
+        page: Option<Page>,
+        author_sheets: Vec<Stylesheet>,
+        width: f32,
+        height: f32,
+        scale_factor: f32,
+    ) -> Repl {
+        let mut repl = Repl {
+            page,
+            author_sheets,
+            viewport: (width, height, scale_factor),
+        };
+        repl.update_viewport();
+        repl
+    }
+
+    /// Runs the commands read from `input`, writing what they print to `output`, until `quit` is
+    /// run or the input ends.  A prompt is written before each command is read if `prompt`.
+    pub fn run<R: BufRead, W: Write>(
+        &mut self,
+        input: R,
+        output: &mut W,
+        prompt: bool,
+    ) -> io::Result<()> {
+        let mut lines = input.lines();
+        loop {
+            if prompt {
+                write!(output, "> ")?;
+                output.flush()?;
+            }
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => return Ok(()),
+            };
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            match words[..] {
+                [] => {}
+                ["quit"] | ["exit"] => return Ok(()),
+                _ => {
+                    if let Err(message) = self.run_command(&words, output) {
+                        writeln!(output, "error: {}", message)?;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Runs the command made of `words`, returning a message describing why if it couldn't be
+    /// run.
+    fn run_command<W: Write>(&mut self, words: &[&str], output: &mut W) -> Result<(), String> {
+        match words {
+            ["help"] => writeln!(output, "{}", HELP).map_err(|err| err.to_string()),
+            ["load", path] => {
+                let path = Path::new(path.strip_prefix("file://").unwrap_or(*path));
+                let page = Page::load_document(path, self.author_sheets.clone())
+                    .map_err(|err| format!("couldn't load '{}': {}", path.display(), err))?;
+                self.page = Some(page);
+                self.update_viewport();
+                writeln!(output, "loaded {}", path.display()).map_err(|err| err.to_string())
+            }
+            ["set", "viewport", size] => {
+                self.viewport = parse_viewport(size).ok_or_else(|| {
+                    format!(
+                        "'{}' isn't a viewport size, e.g. 800x600 or 800x600@2",
+                        size
+                    )
+                })?;
+                self.update_viewport();
+                Ok(())
+            }
+            ["query", selector @ ..] => self.query(&selector.join(" "), output),
+            ["dump", "layout"] => self.dump_layout(DumpLayoutVerbosity::NonVerbose, output),
+            ["dump", "layout", "verbose"] | ["dump", "layout", "1"] => {
+                self.dump_layout(DumpLayoutVerbosity::Verbose, output)
+            }
+            ["dump", "layout", "2"] => self.dump_layout(DumpLayoutVerbosity::VeryVerbose, output),
+            ["dump", "display-list"] => {
+                self.page()?.display_list().dump(output);
+                Ok(())
+            }
+            ["dump", "stacking"] => {
+                match self.page()?.layout() {
+                    Some(box_tree) => dump_stacking_contexts(box_tree, output),
+                    None => writeln!(output, "empty box tree").map_err(|err| err.to_string())?,
+                }
+                Ok(())
+            }
+            ["dump", "styles", property_names @ ..] => {
+                let longhands = if property_names.is_empty() {
+                    LonghandId::iter().collect()
+                } else {
+                    property_names
+                        .iter()
+                        .map(|name| match PropertyId::parse(name) {
+                            Some(PropertyId::Longhand(longhand)) => Ok(longhand),
+                            _ => Err(format!("'{}' isn't a supported longhand property", name)),
+                        })
+                        .collect::<Result<Vec<_>, _>>()?
+                };
+                dump_styles(self.page()?.document(), output, 0, &longhands);
+                Ok(())
+            }
+            ["dump", "a11y"] => {
+                let tree = build_accessibility_tree(self.page()?.document());
+                dump_accessibility_tree(&tree, output, 0);
+                Ok(())
+            }
+            ["render", path] => self
+                .page()?
+                .render_to(Path::new(path))
+                .map_err(|err| format!("couldn't render to '{}': {}", path, err)),
+            _ => Err(format!(
+                "unknown command '{}' (try `help`)",
+                words.join(" ")
+            )),
+        }
+    }
+
+    fn page(&mut self) -> Result<&mut Page, String> {
+        self.page
+            .as_mut()
+            .ok_or_else(|| "no page is loaded (try `load <path>`)".to_owned())
+    }
+
+    fn update_viewport(&mut self) {
+        let (width, height, scale_factor) = self.viewport;
+        if let Some(page) = &mut self.page {
+            page.set_viewport(width, height, scale_factor);
+        }
+    }
+
+    /// Writes a line for each element matching `selector`, in tree order, giving its border box
+    /// (in CSS pixels, relative to the document) if it generated any boxes.
+    fn query<W: Write>(&mut self, selector: &str, output: &mut W) -> Result<(), String> {
+        let selectors = Selectors::compile_str(selector)
+            .map_err(|_| format!("'{}' isn't a valid selector", selector))?;
+        let page = self.page()?;
+        let document = page.document().clone();
+        let box_tree = page.layout();
+        let mut matched = false;
+        for element in document.select(&selectors) {
+            matched = true;
+            let node = element.as_node();
+            let element = dump_layout_element(node).unwrap_or_default();
+            let line = match box_tree.and_then(|box_tree| box_geometry(box_tree, node)) {
+                Some(geometry) => {
+                    let border_box = geometry.border_box;
+                    format!(
+                        "{} at ({},{}) size {}x{}",
+                        element,
+                        border_box.start_x.dump_layout_format(),
+                        border_box.start_y.dump_layout_format(),
+                        border_box.width.dump_layout_format(),
+                        border_box.height.dump_layout_format()
+                    )
+                }
+                None => format!("{} (no boxes)", element),
+            };
+            writeln!(output, "{}", line).map_err(|err| err.to_string())?;
+        }
+        if !matched {
+            writeln!(output, "no elements match '{}'", selector).map_err(|err| err.to_string())?;
+        }
+        Ok(())
+    }
+
+    fn dump_layout<W: Write>(
+        &mut self,
+        verbosity: DumpLayoutVerbosity,
+        output: &mut W,
+    ) -> Result<(), String> {
+        match self.page()?.layout() {
+            Some(box_tree) => box_tree.dump_layout(output, 0, verbosity),
+            None => writeln!(output, "empty box tree").map_err(|err| err.to_string())?,
+        }
+        Ok(())
+    }
+}
+
+/// Parses a viewport size written like `800x600` or `800x600@2` into its width, height, and scale
+/// factor.
+fn parse_viewport(size: &str) -> Option<(f32, f32, f32)> {
+    let mut parts = size.splitn(2, '@');
+    let (width, height) = cli::parse_viewport(parts.next()?)?;
+    let scale_factor = match parts.next() {
+        Some(scale_factor) => scale_factor.parse::<f32>().ok()?,
+        None => 1.,
+    };
+    if width > 0. && height > 0. && scale_factor > 0. {
+        Some((width, height, scale_factor))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn runs_commands_against_the_loaded_page() {
+        let tempdir = TempDir::new("repl").unwrap();
+        let html_file = tempdir.path().join("page.html");
+        std::fs::write(
+            &html_file,
+            "<body style=\"margin-top: 0px; margin-right: 0px; margin-left: 0px\"><div class=card \
+             style=\"height: 50px\"></div><p class=card style=\"display: none\"></p>",
+        )
+        .unwrap();
+        let input = format!(
+            "query div\nload {}\nset viewport 400x300@2\nquery .card\nset viewport 0x1\n\
+             frobnicate\nquit\nquery div\n",
+            html_file.display()
+        );
+        let mut output = Vec::new();
+        Repl::new(None, Vec::new(), 800., 600., 1.)
+            .run(input.as_bytes(), &mut output, false)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!(
+                "error: no page is loaded (try `load <path>`)\n\
+                 loaded {}\n\
+                 div.card at (0,0) size 200x50\n\
+                 p.card (no boxes)\n\
+                 error: '0x1' isn't a viewport size, e.g. 800x600 or 800x600@2\n\
+                 error: unknown command 'frobnicate' (try `help`)\n",
+                html_file.display()
+            )
+        );
+    }
+}
@@ -0,0 +1,140 @@
+//! The user's preferences set in the OS (its theme and accessibility settings), which a window
+//! follows unless others are given on the command line.  Each is read from the command-line tool
+//! the platform exposes it through, so nothing is linked against the platform's settings APIs.
+
+use std::process::Command;
+
+use kosmonaut_core::style::media::{
+    ColorScheme, ContrastPreference, ForcedColors, MotionPreference,
+};
+
+/// The color scheme of the OS's theme, or light if it can't be found.
+pub fn system_color_scheme() -> ColorScheme {
+    let is_dark = if cfg!(target_os = "macos") {
+        // `AppleInterfaceStyle` is only set in dark mode.
+        command_output("defaults", &["read", "-g", "AppleInterfaceStyle"])
+            .map_or(false, |style| style.trim() == "Dark")
+    } else if cfg!(windows) {
+        command_output(
+            "reg",
+            &[
+                "query",
+                r"HKCU\Software\Microsoft\Windows\CurrentVersion\Themes\Personalize",
+                "/v",
+                "AppsUseLightTheme",
+            ],
+        )
+        .map_or(false, |output| output.trim_end().ends_with("0x0"))
+    } else {
+        // Desktops following freedesktop.org's color scheme setting (like GNOME) report it as
+        // `prefer-dark`, while older ones only have dark themes, usually named as such.
+        ["color-scheme", "gtk-theme"].iter().any(|key| {
+            command_output("gsettings", &["get", "org.gnome.desktop.interface", key])
+                .map_or(false, |value| value.to_ascii_lowercase().contains("dark"))
+        }) || std::env::var("GTK_THEME")
+            .map_or(false, |theme| theme.to_ascii_lowercase().contains("dark"))
+    };
+    if is_dark {
+        ColorScheme::Dark
+    } else {
+        ColorScheme::Light
+    }
+}
+
+/// Whether the OS has been asked to reduce motion (or turn off animations), or no preference if
+/// it can't be found.
+pub fn system_motion_preference() -> MotionPreference {
+    let reduce = if cfg!(target_os = "macos") {
+        command_output(
+            "defaults",
+            &["read", "com.apple.universalaccess", "reduceMotion"],
+        )
+        .map_or(false, |value| value.trim() == "1")
+    } else if cfg!(windows) {
+        // Windows' "Show animations" setting turns off window animations, like minimizing.
+        command_output(
+            "reg",
+            &[
+                "query",
+                r"HKCU\Control Panel\Desktop\WindowMetrics",
+                "/v",
+                "MinAnimate",
+            ],
+        )
+        .map_or(false, |output| output.trim_end().ends_with('0'))
+    } else {
+        command_output(
+            "gsettings",
+            &["get", "org.gnome.desktop.interface", "enable-animations"],
+        )
+        .map_or(false, |value| value.trim() == "false")
+    };
+    if reduce {
+        MotionPreference::Reduce
+    } else {
+        MotionPreference::NoPreference
+    }
+}
+
+/// The contrast the OS has been asked for, or no preference if it can't be found.  A high
+/// contrast theme on Windows forces colors, which is a custom contrast preference.
+pub fn system_contrast_preference() -> ContrastPreference {
+    if cfg!(target_os = "macos") {
+        if command_output(
+            "defaults",
+            &["read", "com.apple.universalaccess", "increaseContrast"],
+        )
+        .map_or(false, |value| value.trim() == "1")
+        {
+            return ContrastPreference::More;
+        }
+    } else if cfg!(windows) {
+        if system_forced_colors() == ForcedColors::Active {
+            return ContrastPreference::Custom;
+        }
+    } else if command_output(
+        "gsettings",
+        &["get", "org.gnome.desktop.a11y.interface", "high-contrast"],
+    )
+    .map_or(false, |value| value.trim() == "true")
+    {
+        return ContrastPreference::More;
+    }
+    ContrastPreference::NoPreference
+}
+
+/// Whether the OS forces colors, which only Windows does, with its high contrast themes.
+pub fn system_forced_colors() -> ForcedColors {
+    // The first bit of the `Flags` of the high contrast setting is whether it's on.
+    let is_active = cfg!(windows)
+        && command_output(
+            "reg",
+            &[
+                "query",
+                r"HKCU\Control Panel\Accessibility\HighContrast",
+                "/v",
+                "Flags",
+            ],
+        )
+        .and_then(|output| {
+            output
+                .split_whitespace()
+                .last()
+                .and_then(|flags| flags.parse::<u32>().ok())
+        })
+        .map_or(false, |flags| flags & 1 == 1);
+    if is_active {
+        ForcedColors::Active
+    } else {
+        ForcedColors::None
+    }
+}
+
+/// Runs `program` with `args`, returning what it writes to stdout if it succeeds.
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
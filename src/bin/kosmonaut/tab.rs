@@ -0,0 +1,458 @@
+//! The tabs of the browser window, each of which shows its own document, with its own DOM,
+//! styles, box tree, zoom, and focus, while only the active tab is painted and receives input.
+//! Tabs live on the pipeline thread, while their scroll positions are kept by the compositor,
+//! keyed by their documents, so that scrolling doesn't wait on the pipeline.
+
+use std::fs::File;
+use std::io::{self, ErrorKind, Read};
+use std::path::{Path, PathBuf};
+
+use kosmonaut_core::dom::focus;
+use kosmonaut_core::dom::form::{self, FormControlKind};
+use kosmonaut_core::dom::mutation;
+use kosmonaut_core::dom::navigation::{self, IndicatedPart};
+use kosmonaut_core::dom::parser::is_xml_document;
+use kosmonaut_core::dom::selection::{BoundaryPoint, Selection};
+use kosmonaut_core::dom::tree::NodeRef;
+use kosmonaut_core::image::cache::ImageCache;
+use kosmonaut_core::layout::box_tree::build_box_tree;
+use kosmonaut_core::layout::layout_box::LayoutBox;
+use kosmonaut_core::layout::rect::Point;
+use kosmonaut_core::layout::scroll::ScrollTarget;
+use kosmonaut_core::page::{begin_loading_document, load_document, DocumentLoad, LoadedDocument};
+use kosmonaut_core::script::ScriptEngine;
+use kosmonaut_core::style::apply_styles;
+use kosmonaut_core::style::media::Device;
+use kosmonaut_core::style::stylesheet::Stylesheet;
+
+/// How much of a loading document's source is parsed between frames, so the start of a long
+/// document is shown before the rest of it has been parsed.
+const LOAD_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The load of a tab's document while its source is still being read.
+pub struct TabLoad {
+    load: DocumentLoad,
+    source: File,
+}
+
+/// A document open in a tab, and the state of the user's interaction with it.
+pub struct Tab {
+    pub dom: NodeRef,
+    /// The cache the document's images are loaded into.
+    pub image_cache: ImageCache,
+    /// The path of the file the document was loaded from.
+    pub path: PathBuf,
+    /// The engine the document's scripts run in, if it has any.
+    pub script_engine: Option<ScriptEngine>,
+    /// The device the document is styled for.  Background tabs aren't restyled when the window
+    /// changes, so this is brought up to date when the tab is activated.
+    pub device: Device,
+    /// The tree of boxes, which is laid out in place whenever it's painted (only boxes that
+    /// changed since, or whose containing block did, are laid out again).
+    pub box_tree: Option<LayoutBox>,
+    /// The page zoom, which scales CSS pixels on top of the scale factor (and so shrinks or grows
+    /// the viewport in CSS pixels).
+    pub zoom: f32,
+    /// The element keystrokes are sent to.
+    pub focused: Option<NodeRef>,
+    /// The node the primary mouse button was last pressed over, which a click on its release
+    /// targets.
+    pub pressed_target: Option<NodeRef>,
+    /// Whether the primary mouse button was pressed over the document's text and is still held,
+    /// so moving the mouse extends the selection.
+    pub selecting: bool,
+    /// Where to scroll the document once it's next laid out, after it was navigated to a
+    /// fragment.
+    pub pending_scroll: Option<ScrollTarget>,
+    /// The element highlighted while the page is inspected, which is the innermost element the
+    /// mouse was last over.
+    pub inspected: Option<NodeRef>,
+    /// The load of the document, while it's still being read (see `Tab::load_next_chunk`).
+    pub loading: Option<TabLoad>,
+}
+
+impl Tab {
+    /// Opens `document`, which is styled for `device`, in a new tab, focusing its autofocus
+    /// target (if any).
+    pub fn new(document: LoadedDocument, device: Device) -> Tab {
+        let box_tree = build_box_tree(document.dom.clone(), None);
+        let focused = form::autofocus_target(&document.dom);
+        focus::update_focus(None, focused.as_ref(), false);
+        Tab {
+            dom: document.dom,
+            image_cache: document.image_cache,
+            path: document.path,
+            script_engine: document.script_engine,
+            device,
+            box_tree,
+            zoom: 1.,
+            focused,
+            pressed_target: None,
+            selecting: false,
+            pending_scroll: None,
+            inspected: None,
+            loading: None,
+        }
+    }
+
+    /// Opens the document at `path` in a new tab, styled by the given stylesheets for `device`.
+    /// HTML documents are shown as they're parsed, so the tab starts out with an empty document
+    /// that's filled in by `load_next_chunk`, while XML documents are loaded all at once.
+    pub fn begin_loading(
+        path: &Path,
+        device: Device,
+        ua_sheets: &[Stylesheet],
+        author_sheets: &[Stylesheet],
+    ) -> io::Result<Tab> {
+        if is_xml_document(path) {
+            let document = load_document(path, &device, ua_sheets, author_sheets)?;
+            return Ok(Tab::new(document, device));
+        }
+        let source = File::open(path)?;
+        let (document, load) = begin_loading_document(path, &device, ua_sheets, author_sheets);
+        let mut tab = Tab::new(document, device);
+        tab.loading = Some(TabLoad { load, source });
+        Ok(tab)
+    }
+
+    /// Parses the next chunk of the document's source while it's loading, which records the
+    /// nodes it inserted as mutations, for the next frame to restyle and lay out.  Once all of
+    /// the source has been parsed, the document is finished, restyled from scratch, and has its
+    /// boxes built again (see `DocumentLoad::finish`), and its autofocus target is focused
+    /// unless something else already was.  Returns whether the document changed.
+    pub fn load_next_chunk(
+        &mut self,
+        ua_sheets: &[Stylesheet],
+        author_sheets: &[Stylesheet],
+    ) -> bool {
+        let loading = match &mut self.loading {
+            Some(loading) => loading,
+            None => return false,
+        };
+        let mut chunk = vec![0; LOAD_CHUNK_SIZE];
+        loop {
+            match loading.source.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(read) => {
+                    loading.load.feed(&chunk[..read]);
+                    return true;
+                }
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                // The document is finished with what was read of it.
+                Err(err) => {
+                    eprintln!("couldn't read '{}': {}", self.path.display(), err);
+                    break;
+                }
+            }
+        }
+        let loading = self.loading.take().expect("the document is loading");
+        self.script_engine = loading.load.finish(&self.device, ua_sheets);
+        mutation::take_mutations(&self.dom);
+        apply_styles(
+            self.dom.clone(),
+            &self.device,
+            ua_sheets,
+            &[],
+            author_sheets,
+        );
+        self.box_tree = build_box_tree(self.dom.clone(), None);
+        if self.focused.is_none() {
+            self.focused = form::autofocus_target(&self.dom);
+            focus::update_focus(None, self.focused.as_ref(), false);
+        }
+        true
+    }
+
+    /// Parses the rest of the document's source, if it's still loading.
+    pub fn finish_loading(&mut self, ua_sheets: &[Stylesheet], author_sheets: &[Stylesheet]) {
+        while self.loading.is_some() {
+            self.load_next_chunk(ua_sheets, author_sheets);
+        }
+    }
+
+    /// Tears down the tab's document and replaces it with the document open in `tab` (e.g. after
+    /// a link in it was followed).  The zoom is kept.
+    pub fn navigate(&mut self, tab: Tab) {
+        let zoom = self.zoom;
+        *self = Tab { zoom, ..tab };
+    }
+
+    /// Navigates the document to `fragment`, making the element it indicates `:target` and
+    /// scrolling to it once the document is next laid out.  Fragments that indicate nothing
+    /// leave the document where it's scrolled to.
+    ///
+    /// https://html.spec.whatwg.org/multipage/browsing-the-web.html#navigate-fragid
+    pub fn navigate_to_fragment(&mut self, fragment: &str) {
+        self.pending_scroll = match navigation::navigate_to_fragment(&self.dom, fragment) {
+            Some(IndicatedPart::TopOfDocument) => Some(ScrollTarget::Position(Point::default())),
+            Some(IndicatedPart::Element(element)) => Some(ScrollTarget::Element(element)),
+            None => self.pending_scroll.take(),
+        };
+    }
+
+    /// The document's selection, if any.
+    pub fn selection(&self) -> Option<Selection> {
+        self.dom.as_document()?.selection.borrow().clone()
+    }
+
+    /// Starts selecting from the text closest to `point` (in CSS pixels, relative to the
+    /// document), where the primary mouse button was pressed, replacing the previous selection.
+    /// Returns whether the selection changed.
+    pub fn start_selecting(&mut self, point: Point) -> bool {
+        self.selecting = true;
+        let selection = self.text_position_at(point).map(Selection::collapsed_at);
+        self.set_selection(selection)
+    }
+
+    /// Extends the selection to the text closest to `point`, which the mouse was dragged to.
+    /// Returns whether the selection changed.
+    pub fn extend_selection(&mut self, point: Point) -> bool {
+        let (mut selection, focus) = match (self.selection(), self.text_position_at(point)) {
+            (Some(selection), Some(focus)) => (selection, focus),
+            _ => return false,
+        };
+        selection.focus = focus;
+        self.set_selection(Some(selection))
+    }
+
+    /// The focused element, if it's a text control that keystrokes edit.
+    pub fn focused_text_control(&self) -> Option<NodeRef> {
+        self.focused.clone().filter(|node| {
+            FormControlKind::of(node).map_or(false, FormControlKind::is_text_control)
+        })
+    }
+
+    /// The text to copy to the clipboard: that selected in the focused text control, or if no
+    /// text control is focused, the document's selected text.  `None` if nothing is selected.
+    pub fn selected_text(&self) -> Option<String> {
+        let text = match self.focused_text_control() {
+            Some(control) => form::with_editable_text(&control, |editable_text| {
+                let selection = editable_text.selection();
+                editable_text
+                    .value()
+                    .chars()
+                    .skip(selection.start)
+                    .take(selection.len())
+                    .collect::<String>()
+            })?,
+            None => self.selection()?.text(),
+        };
+        Some(text).filter(|text| !text.is_empty())
+    }
+
+    fn text_position_at(&self, point: Point) -> Option<BoundaryPoint> {
+        self.box_tree.as_ref()?.text_position_at(point)
+    }
+
+    fn set_selection(&self, selection: Option<Selection>) -> bool {
+        let document = match self.dom.as_document() {
+            Some(document) => document,
+            None => return false,
+        };
+        let mut current_selection = document.selection.borrow_mut();
+        if *current_selection == selection {
+            return false;
+        }
+        *current_selection = selection;
+        true
+    }
+
+    /// Zooms the page one `step`, returning whether its zoom changed.
+    pub fn zoom_by(&mut self, step: ZoomStep) -> bool {
+        let zoom = match step {
+            ZoomStep::In => ZOOM_LEVELS.iter().copied().find(|level| *level > self.zoom),
+            ZoomStep::Out => ZOOM_LEVELS
+                .iter()
+                .rev()
+                .copied()
+                .find(|level| *level < self.zoom),
+            ZoomStep::Reset => Some(1.),
+        };
+        match zoom {
+            Some(zoom) if zoom != self.zoom => {
+                self.zoom = zoom;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The levels a page can be zoomed to, which are the same as Firefox's.
+const ZOOM_LEVELS: [f32; 16] = [
+    0.3, 0.5, 0.67, 0.8, 0.9, 1., 1.1, 1.2, 1.33, 1.5, 1.7, 2., 2.4, 3., 4., 5.,
+];
+
+/// A step through the zoom levels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ZoomStep {
+    In,
+    Out,
+    /// Back to 100%.
+    Reset,
+}
+
+/// A command of the tab switcher.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TabCommand {
+    /// Opens a new tab, showing the document the window was opened with.
+    New,
+    /// Closes the active tab, or the window if it's the last tab.
+    Close,
+    Switch(TabSwitch),
+}
+
+/// Which tab to switch to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TabSwitch {
+    /// The tab after the active one, wrapping around to the first.
+    Next,
+    /// The tab before the active one, wrapping around to the last.
+    Previous,
+    /// The tab at the given index, if there is one.
+    Index(usize),
+    Last,
+}
+
+/// The tabs open in a window, in the order they're shown, of which one is active.  There's always
+/// at least one tab.
+pub struct Tabs {
+    tabs: Vec<Tab>,
+    active: usize,
+}
+
+impl Tabs {
+    /// Opens the given tabs, the first of which is active.  Panics if there are none.
+    pub fn new(tabs: Vec<Tab>) -> Tabs {
+        assert!(!tabs.is_empty(), "a window must have at least one tab");
+        Tabs { tabs, active: 0 }
+    }
+
+    pub fn count(&self) -> usize {
+        self.tabs.len()
+    }
+
+    pub fn active_index(&self) -> usize {
+        self.active
+    }
+
+    pub fn active(&self) -> &Tab {
+        &self.tabs[self.active]
+    }
+
+    pub fn active_mut(&mut self) -> &mut Tab {
+        &mut self.tabs[self.active]
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Tab> {
+        self.tabs.iter_mut()
+    }
+
+    /// Opens `tab` after the active tab, activating it unless it's opened in the background.
+    pub fn open(&mut self, tab: Tab, background: bool) {
+        self.tabs.insert(self.active + 1, tab);
+        if !background {
+            self.active += 1;
+        }
+    }
+
+    /// Closes the active tab, activating the tab after it (or the one before, if it was last).
+    /// Returns `None`, closing nothing, if it's the only tab.
+    pub fn close_active(&mut self) -> Option<Tab> {
+        if self.tabs.len() == 1 {
+            return None;
+        }
+        let closed = self.tabs.remove(self.active);
+        self.active = self.active.min(self.tabs.len() - 1);
+        Some(closed)
+    }
+
+    /// Activates the tab `switch` picks, returning whether the active tab changed.
+    pub fn switch(&mut self, switch: TabSwitch) -> bool {
+        let len = self.tabs.len();
+        let index = match switch {
+            TabSwitch::Next => (self.active + 1) % len,
+            TabSwitch::Previous => (self.active + len - 1) % len,
+            TabSwitch::Index(index) if index < len => index,
+            TabSwitch::Index(_) => return false,
+            TabSwitch::Last => len - 1,
+        };
+        let switched = index != self.active;
+        self.active = index;
+        switched
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kosmonaut_core::dom::parser::parse_html;
+    use kosmonaut_core::dom::traits::TendrilSink;
+    use kosmonaut_core::style::media::MediaType;
+    use std::path::Path;
+
+    fn tab(name: &str) -> Tab {
+        Tab::new(
+            LoadedDocument {
+                dom: parse_html().one(""),
+                image_cache: ImageCache::new(""),
+                path: Path::new(name).to_path_buf(),
+                script_engine: None,
+            },
+            Device::new(MediaType::Screen, 800., 600.),
+        )
+    }
+
+    fn active_path(tabs: &Tabs) -> &str {
+        tabs.active().path.to_str().unwrap()
+    }
+
+    #[test]
+    fn opens_switches_between_and_closes_tabs() {
+        let mut tabs = Tabs::new(vec![tab("a"), tab("b")]);
+        assert_eq!(active_path(&tabs), "a");
+        tabs.open(tab("c"), false);
+        assert_eq!(
+            (tabs.count(), tabs.active_index(), active_path(&tabs)),
+            (3, 1, "c")
+        );
+        tabs.open(tab("d"), true);
+        assert_eq!(active_path(&tabs), "c");
+
+        // The tabs are now in the order a, c, d, b.
+        assert!(tabs.switch(TabSwitch::Next));
+        assert_eq!(active_path(&tabs), "d");
+        assert!(tabs.switch(TabSwitch::Last));
+        assert!(tabs.switch(TabSwitch::Next));
+        assert_eq!(active_path(&tabs), "a");
+        assert!(tabs.switch(TabSwitch::Previous));
+        assert_eq!(active_path(&tabs), "b");
+        assert!(!tabs.switch(TabSwitch::Index(4)));
+        assert!(!tabs.switch(TabSwitch::Index(3)));
+        assert!(tabs.switch(TabSwitch::Index(0)));
+
+        assert_eq!(tabs.close_active().unwrap().path, Path::new("a"));
+        assert_eq!(active_path(&tabs), "c");
+        tabs.switch(TabSwitch::Last);
+        tabs.close_active();
+        assert_eq!(active_path(&tabs), "d");
+        tabs.close_active();
+        assert_eq!(active_path(&tabs), "c");
+        assert!(tabs.close_active().is_none());
+        assert_eq!(tabs.count(), 1);
+    }
+
+    #[test]
+    fn zooms_through_the_zoom_levels() {
+        let mut tab = tab("a");
+        assert!(tab.zoom_by(ZoomStep::In));
+        assert_eq!(tab.zoom, 1.1);
+        assert!(tab.zoom_by(ZoomStep::Out));
+        assert!(tab.zoom_by(ZoomStep::Out));
+        assert_eq!(tab.zoom, 0.9);
+        assert!(tab.zoom_by(ZoomStep::Reset));
+        assert!(!tab.zoom_by(ZoomStep::Reset));
+        tab.zoom = 5.;
+        assert!(!tab.zoom_by(ZoomStep::In));
+    }
+}
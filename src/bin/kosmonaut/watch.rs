@@ -0,0 +1,86 @@
+//! Watching the files a window was opened with for changes, so that it can be used as a live
+//! preview of the HTML and CSS being edited.  Files are polled rather than watched with the
+//! platform's file system events, which is cheap for the handful of files passed on the command
+//! line and works the same everywhere.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// How often the pipeline checks the files it watches for changes.
+pub const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// What's known of a file when it was last checked: its modification time and length, or `None`
+/// if it didn't exist (or couldn't be read).  The length is compared too, since modification
+/// times are only precise to the second on some file systems.
+type FileState = Option<(SystemTime, u64)>;
+
+/// A set of files, and their states when they were last checked.
+#[derive(Debug, Default)]
+pub struct FileWatcher {
+    files: HashMap<PathBuf, FileState>,
+}
+
+impl FileWatcher {
+    pub fn new() -> FileWatcher {
+        FileWatcher::default()
+    }
+
+    /// Starts watching the file at `path`, unless it's watched already.  Changes to the file are
+    /// reported from now on.
+    pub fn watch(&mut self, path: &Path) {
+        if !self.files.contains_key(path) {
+            self.files.insert(path.to_owned(), file_state(path));
+        }
+    }
+
+    /// The watched files that were modified, created, or removed since they were last checked.
+    pub fn changed_files(&mut self) -> Vec<PathBuf> {
+        self.files
+            .iter_mut()
+            .filter_map(|(path, last_state)| {
+                let state = file_state(path);
+                if state == *last_state {
+                    return None;
+                }
+                *last_state = state;
+                Some(path.clone())
+            })
+            .collect()
+    }
+}
+
+fn file_state(path: &Path) -> FileState {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn reports_files_changed_since_they_were_last_checked() {
+        let dir = TempDir::new("watch").unwrap();
+        let css = dir.path().join("style.css");
+        let html = dir.path().join("index.html");
+        fs::write(&css, "div {}").unwrap();
+        let mut watcher = FileWatcher::new();
+        watcher.watch(&css);
+        watcher.watch(&html);
+        assert!(watcher.changed_files().is_empty());
+
+        fs::write(&css, "div { color: red }").unwrap();
+        assert_eq!(watcher.changed_files(), vec![css.clone()]);
+        assert!(watcher.changed_files().is_empty());
+
+        // Creating and removing files are changes too.
+        fs::write(&html, "<p>").unwrap();
+        fs::remove_file(&css).unwrap();
+        let mut changed = watcher.changed_files();
+        changed.sort();
+        assert_eq!(changed, vec![html, css]);
+    }
+}
@@ -0,0 +1,729 @@
+//! Running tests from a checkout of the Web Platform Tests, so that conformance can be tracked over
+//! time.
+//!
+//! Reftests (tests with a `<link rel=match>` or `<link rel=mismatch>`) are run like those of
+//! `reftest` manifests, and crashtests (tests in a `crashtests` directory, or whose names end in
+//! `-crash`) pass if they can be laid out and painted without panicking.  Testharness.js tests
+//! that only check the layout of their elements with check-layout-th.js are run by comparing the
+//! laid out geometry of the elements with what they expect, like layout dump tests (see
+//! `CheckLayoutTest`).  Results are reported in the wptreport JSON format that wpt.fyi and `wpt`
+//! tooling consume.
+//!
+//! Kosmonaut only loads local files, so rather than running the WPT server, the checkout is served
+//! by resolving wptserve URLs (those starting with a slash, or with the origin of the server,
+//! `http://web-platform.test:8000`) against its root, which is what the server would serve them
+//! from.  Tests that need more of the server than its files (e.g. `.sub.` or `.https.` tests) are
+//! skipped, as are testharness.js tests that check more than layout.
+//!
+//! https://web-platform-tests.org/writing-tests/reftests.html
+//! https://web-platform-tests.org/writing-tests/crashtest.html
+//! https://web-platform-tests.org/writing-tests/server-features.html
+
+use crate::headless::render_page;
+use crate::reftest::{compare_pixels, Fuzzy, Reftest, ReftestKind};
+use kosmonaut_core::dom::parser::{is_xml_document, parse_html, parse_xml};
+use kosmonaut_core::dom::traits::TendrilSink;
+use kosmonaut_core::dom::tree::NodeRef;
+use kosmonaut_core::image::load_document_images;
+use kosmonaut_core::layout::box_tree::build_box_tree;
+use kosmonaut_core::layout::geometry::box_geometry;
+use kosmonaut_core::layout::global_layout;
+use kosmonaut_core::layout::layout_box::LayoutBox;
+use kosmonaut_core::layout::rect::Point;
+use kosmonaut_core::page::load_document;
+use kosmonaut_core::style::media::Device;
+use kosmonaut_core::style::stylesheet::Stylesheet;
+use kosmonaut_core::style::values::computed::Position;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+/// The origin the WPT server serves the checkout from, with its default configuration.
+const WPT_SERVER_ORIGIN: &str = "http://web-platform.test:8000";
+
+/// A test the WPT runner can run.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WptTest {
+    Reftest(Reftest),
+    /// A page that passes if it can be rendered without crashing.
+    Crashtest(PathBuf),
+    CheckLayout(CheckLayoutTest),
+}
+
+impl WptTest {
+    /// The path of the test's page.
+    pub fn path(&self) -> &Path {
+        match self {
+            WptTest::Reftest(reftest) => &reftest.test,
+            WptTest::Crashtest(path) => path,
+            WptTest::CheckLayout(test) => &test.path,
+        }
+    }
+}
+
+/// A testharness.js test that uses check-layout-th.js to check that the elements matching each of
+/// `selectors` (and their descendants) are laid out where their `data-offset-*` attributes, and as
+/// large as their `data-expected-*` attributes, say.  Rather than running the test's scripts, the
+/// page is laid out and the geometry of the elements compared with what they expect, with a
+/// subtest for each element matching a selector, named as check-layout-th.js names them.
+///
+/// https://github.com/web-platform-tests/wpt/blob/master/resources/check-layout-th.js
+#[derive(Clone, Debug, PartialEq)]
+pub struct CheckLayoutTest {
+    pub path: PathBuf,
+    pub selectors: Vec<String>,
+}
+
+/// The outcome of running a test or subtest, as named in wptreports.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WptStatus {
+    Pass,
+    Fail,
+    /// The testharness.js test ran to completion, whatever the outcomes of its subtests.
+    Ok,
+    /// The test (or its reference) couldn't be loaded.
+    Error,
+    /// Kosmonaut panicked while running the test.
+    Crash,
+}
+
+impl WptStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WptStatus::Pass => "PASS",
+            WptStatus::Fail => "FAIL",
+            WptStatus::Ok => "OK",
+            WptStatus::Error => "ERROR",
+            WptStatus::Crash => "CRASH",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WptResult {
+    /// The URL path of the test, relative to the root of the checkout, e.g.
+    /// `/css/CSS2/box/border-001.html`.
+    pub test: String,
+    pub status: WptStatus,
+    /// Why the test didn't pass, if it didn't.
+    pub message: Option<String>,
+    /// The results of the subtests of testharness.js tests, which are empty for other tests.
+    pub subtests: Vec<WptSubtestResult>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WptSubtestResult {
+    pub name: String,
+    pub status: WptStatus,
+    /// Why the subtest didn't pass, if it didn't.
+    pub message: Option<String>,
+}
+
+/// Finds the tests under each of `include` (paths relative to the root of the WPT checkout at
+/// `checkout`, e.g. `css/CSS2`) that the runner can run, in path order.  The whole checkout is
+/// searched if `include` is empty.
+pub fn discover_tests(checkout: &Path, include: &[&str]) -> std::io::Result<Vec<WptTest>> {
+    let mut paths = Vec::new();
+    if include.is_empty() {
+        collect_test_paths(checkout, &mut paths)?;
+    }
+    for dir in include {
+        collect_test_paths(&checkout.join(dir), &mut paths)?;
+    }
+    paths.sort();
+    let mut tests = Vec::new();
+    for path in paths {
+        let document = if is_xml_document(&path) {
+            parse_xml().from_utf8().from_file(&path)?
+        } else {
+            parse_html().from_utf8().from_file(&path)?
+        };
+        if let Some(test) = test_for_document(checkout, path, &document) {
+            tests.push(test);
+        }
+    }
+    Ok(tests)
+}
+
+/// Collects the paths of the pages under `path` that might be tests, skipping support files,
+/// references, and tests that need the WPT server.
+fn collect_test_paths(path: &Path, paths: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let name = path
+        .file_name()
+        .map_or("".into(), |name| name.to_string_lossy());
+    if path.is_dir() {
+        if matches!(
+            &name[..],
+            ".git" | "resources" | "support" | "reference" | "tools"
+        ) {
+            return Ok(());
+        }
+        for entry in std::fs::read_dir(path)? {
+            collect_test_paths(&entry?.path(), paths)?;
+        }
+        return Ok(());
+    }
+    let is_page = [".html", ".htm", ".xht", ".xhtml"]
+        .iter()
+        .any(|extension| name.ends_with(extension));
+    let is_reference = ["-ref.", "-notref.", "-ref-"]
+        .iter()
+        .any(|marker| name.contains(marker));
+    let needs_server = [".sub.", ".https.", ".h2.", ".www."]
+        .iter()
+        .any(|flag| name.contains(flag));
+    if is_page && !is_reference && !needs_server {
+        paths.push(path.to_path_buf());
+    }
+    Ok(())
+}
+
+/// The test the page at `path`, parsed into `document`, is, if it's one the runner can run.
+fn test_for_document(checkout: &Path, path: PathBuf, document: &NodeRef) -> Option<WptTest> {
+    if let Some((kind, href)) = reference_link(document) {
+        return Some(WptTest::Reftest(Reftest {
+            kind,
+            reference: resolve_href(checkout, &path, &href),
+            test: path,
+            fuzzy: fuzzy_meta(document).unwrap_or_default(),
+        }));
+    }
+    let is_crashtest = path
+        .components()
+        .any(|component| component.as_os_str() == "crashtests")
+        || path
+            .file_stem()
+            .map_or(false, |stem| stem.to_string_lossy().ends_with("-crash"));
+    if is_crashtest {
+        return Some(WptTest::Crashtest(path));
+    }
+    let selectors = check_layout_selectors(document);
+    if !selectors.is_empty() {
+        return Some(WptTest::CheckLayout(CheckLayoutTest { path, selectors }));
+    }
+    None
+}
+
+/// The selectors `document` passes to `checkLayout` from its scripts and event handler attributes
+/// (e.g. `<body onload="checkLayout('.test')">`), if it uses check-layout-th.js.
+fn check_layout_selectors(document: &NodeRef) -> Vec<String> {
+    let mut uses_check_layout = false;
+    let mut scripts = Vec::new();
+    for node in document.descendants() {
+        let element = match node.as_element() {
+            Some(element) => element,
+            None => continue,
+        };
+        let attributes = element.attributes.borrow();
+        if element.name.local == local_name!("script") {
+            match attributes.get(local_name!("src")) {
+                Some(src) => uses_check_layout |= src.trim().ends_with("/check-layout-th.js"),
+                None => scripts.push(node.text_contents()),
+            }
+        }
+        scripts.extend(
+            attributes
+                .map
+                .iter()
+                .filter(|(name, _)| name.local.starts_with("on"))
+                .map(|(_, attribute)| attribute.value.clone()),
+        );
+    }
+    if !uses_check_layout {
+        return Vec::new();
+    }
+    let mut selectors = Vec::new();
+    for script in scripts {
+        for call in script.split("checkLayout(").skip(1) {
+            let call = call.trim_start();
+            let quote = match call.chars().next() {
+                Some(quote) if quote == '\'' || quote == '"' => quote,
+                _ => continue,
+            };
+            if let Some(selector) = call[1..].split(quote).next() {
+                selectors.push(selector.to_owned());
+            }
+        }
+    }
+    selectors
+}
+
+/// The kind and `href` of the first `<link rel=match>` or `<link rel=mismatch>` in `document`.
+/// Tests with several references only have the first compared with them.
+fn reference_link(document: &NodeRef) -> Option<(ReftestKind, String)> {
+    document.descendants().find_map(|node| {
+        let element = node.as_element()?;
+        if element.name.local != local_name!("link") {
+            return None;
+        }
+        let attributes = element.attributes.borrow();
+        let href = attributes.get(local_name!("href"))?.trim().to_owned();
+        let rel = attributes.get(local_name!("rel"))?;
+        rel.split_ascii_whitespace().find_map(|keyword| {
+            if keyword.eq_ignore_ascii_case("match") {
+                Some((ReftestKind::Match, href.clone()))
+            } else if keyword.eq_ignore_ascii_case("mismatch") {
+                Some((ReftestKind::Mismatch, href.clone()))
+            } else {
+                None
+            }
+        })
+    })
+}
+
+/// The tolerance given by the first `<meta name=fuzzy>` in `document`, e.g.
+/// `<meta name=fuzzy content="maxDifference=0-2;totalPixels=0-40">`.  Only the upper bound of each
+/// range is used, and the URL of the reference it applies to, if given, is ignored.
+///
+/// https://web-platform-tests.org/writing-tests/reftests.html#fuzzy-matching
+fn fuzzy_meta(document: &NodeRef) -> Option<Fuzzy> {
+    let content = document.descendants().find_map(|node| {
+        let element = node.as_element()?;
+        if element.name.local != local_name!("meta") {
+            return None;
+        }
+        let attributes = element.attributes.borrow();
+        if !attributes
+            .get(local_name!("name"))?
+            .eq_ignore_ascii_case("fuzzy")
+        {
+            return None;
+        }
+        attributes
+            .get(local_name!("content"))
+            .map(|content| content.to_owned())
+    })?;
+    parse_fuzzy_content(&content)
+}
+
+fn parse_fuzzy_content(content: &str) -> Option<Fuzzy> {
+    let ranges = content.rsplit(':').next()?;
+    let mut ranges = ranges.split(';').map(|range| {
+        let range = range.rsplit('=').next().unwrap_or("");
+        range.rsplit('-').next().unwrap_or("").trim()
+    });
+    let fuzzy = Fuzzy {
+        max_difference: ranges.next()?.parse().ok()?,
+        max_pixels: ranges.next()?.parse().ok()?,
+    };
+    match ranges.next() {
+        Some(_) => None,
+        None => Some(fuzzy),
+    }
+}
+
+/// Resolves the `href` of a test's reference against the test's path.  Hrefs starting with `/` or
+/// the origin of the WPT server are resolved against the root of the checkout, since that's what
+/// the server serves.
+fn resolve_href(checkout: &Path, test_path: &Path, href: &str) -> PathBuf {
+    let href = href.split(|ch| ch == '?' || ch == '#').next().unwrap_or("");
+    let href = href.strip_prefix(WPT_SERVER_ORIGIN).unwrap_or(href);
+    match href.strip_prefix('/') {
+        Some(href) => checkout.join(href),
+        None => test_path
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(href),
+    }
+}
+
+/// The URL path of the test at `path` within the checkout at `checkout`.
+pub fn test_url(checkout: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(checkout).unwrap_or(path);
+    let segments = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>();
+    format!("/{}", segments.join("/"))
+}
+
+/// A wptreport of `results`, for runs that started and ended at the given times, in milliseconds
+/// since the Unix epoch.
+///
+/// https://github.com/web-platform-tests/wpt.fyi/blob/main/api/README.md#results-creation
+pub fn wptreport(results: &[WptResult], time_start: u128, time_end: u128) -> Value {
+    let results = results
+        .iter()
+        .map(|result| {
+            json!({
+                "test": result.test,
+                "status": result.status.as_str(),
+                "message": result.message,
+                "subtests": result
+                    .subtests
+                    .iter()
+                    .map(|subtest| {
+                        json!({
+                            "name": subtest.name,
+                            "status": subtest.status.as_str(),
+                            "message": subtest.message,
+                        })
+                    })
+                    .collect::<Vec<_>>(),
+            })
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "run_info": {
+            "product": "kosmonaut",
+            "browser_version": env!("CARGO_PKG_VERSION"),
+            "os": std::env::consts::OS,
+            "headless": true,
+        },
+        "time_start": time_start as u64,
+        "time_end": time_end as u64,
+        "results": results,
+    })
+}
+
+/// Runs the tests under `include` in the Web Platform Tests checkout at `checkout`, rendering
+/// their pages headless on `device`, and returns a wptreport of their results.
+pub fn run_wpt(
+    checkout: &Path,
+    include: &[&str],
+    device: &Device,
+    ua_sheets: &[Stylesheet],
+) -> serde_json::Value {
+    let time_start = millis_since_epoch();
+    let tests = discover_tests(checkout, include).expect("couldn't read WPT checkout");
+    let results = tests
+        .iter()
+        .map(|test| {
+            // Kosmonaut panicking on a test is a result to report, not a reason to stop.
+            let outcome = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                run_wpt_test(test, checkout, device, ua_sheets)
+            }));
+            let (status, message, subtests) = outcome.unwrap_or_else(|panic| {
+                let message = panic
+                    .downcast_ref::<&str>()
+                    .map(|message| message.to_string())
+                    .or_else(|| panic.downcast_ref::<String>().cloned());
+                (WptStatus::Crash, message, Vec::new())
+            });
+            WptResult {
+                test: test_url(checkout, test.path()),
+                status,
+                message,
+                subtests,
+            }
+        })
+        .collect::<Vec<_>>();
+    wptreport(&results, time_start, millis_since_epoch())
+}
+
+fn run_wpt_test(
+    test: &WptTest,
+    checkout: &Path,
+    device: &Device,
+    ua_sheets: &[Stylesheet],
+) -> (WptStatus, Option<String>, Vec<WptSubtestResult>) {
+    let load_error = |err: std::io::Error| {
+        (
+            WptStatus::Error,
+            Some(format!("couldn't load page: {}", err)),
+            Vec::new(),
+        )
+    };
+    let render = |path: &Path| render_page(path, Some(checkout), device, ua_sheets);
+    match test {
+        WptTest::Crashtest(path) => match render(path) {
+            Ok(_) => (WptStatus::Pass, None, Vec::new()),
+            Err(err) => load_error(err),
+        },
+        WptTest::CheckLayout(test) => {
+            match run_check_layout_test(test, checkout, device, ua_sheets) {
+                Ok(subtests) => (WptStatus::Ok, None, subtests),
+                Err(err) => load_error(err),
+            }
+        }
+        WptTest::Reftest(reftest) => match (render(&reftest.test), render(&reftest.reference)) {
+            (Ok(test), Ok(reference)) => {
+                let comparison =
+                    compare_pixels(test.frame(0).pixels(), reference.frame(0).pixels());
+                if reftest.passes(&comparison) {
+                    (WptStatus::Pass, None, Vec::new())
+                } else {
+                    let message = format!(
+                        "{} pixels differ, by up to {}",
+                        comparison.differing_pixels, comparison.max_difference
+                    );
+                    (WptStatus::Fail, Some(message), Vec::new())
+                }
+            }
+            (Err(err), _) | (_, Err(err)) => load_error(err),
+        },
+    }
+}
+
+/// Lays out the page of `test` on `device` and checks the geometry of the elements it checks,
+/// returning a subtest result for each of them.
+fn run_check_layout_test(
+    test: &CheckLayoutTest,
+    checkout: &Path,
+    device: &Device,
+    ua_sheets: &[Stylesheet],
+) -> std::io::Result<Vec<WptSubtestResult>> {
+    let mut document = load_document(&test.path, device, ua_sheets, &[])?;
+    document.image_cache = document.image_cache.with_document_root(checkout);
+    load_document_images(
+        &document.dom,
+        &document.image_cache,
+        device.viewport_width(),
+        device.resolution(),
+    );
+    let mut box_tree = build_box_tree(document.dom.clone(), None);
+    if let Some(box_tree) = box_tree.as_mut() {
+        global_layout(
+            box_tree,
+            device.viewport_width() * device.resolution(),
+            device.viewport_height() * device.resolution(),
+            device.resolution(),
+        );
+    }
+    let mut subtests = Vec::new();
+    for selector in &test.selectors {
+        let elements = match document.dom.select_str(selector) {
+            Ok(elements) => elements,
+            Err(()) => {
+                subtests.push(WptSubtestResult {
+                    name: selector.clone(),
+                    status: WptStatus::Fail,
+                    message: Some(format!("couldn't parse selector '{}'", selector)),
+                });
+                continue;
+            }
+        };
+        for (index, element) in elements.enumerate() {
+            let mut failures = Vec::new();
+            for node in element.as_node().inclusive_descendants() {
+                failures.extend(check_expected_layout(box_tree.as_ref(), &node));
+            }
+            subtests.push(WptSubtestResult {
+                name: format!("{} {}", selector, index + 1),
+                status: if failures.is_empty() {
+                    WptStatus::Pass
+                } else {
+                    WptStatus::Fail
+                },
+                message: Some(failures.join("; ")).filter(|message| !message.is_empty()),
+            });
+        }
+    }
+    Ok(subtests)
+}
+
+/// How the layout of `node` differs from what its `data-expected-*` and `data-offset-*`
+/// attributes expect, laid out in `box_tree`.  Sizes and offsets are rounded to whole pixels
+/// before they're compared, as `offsetWidth` and the like are.
+///
+/// TODO: Check the expectations of check-layout-th.js other than the size, client size, and
+/// offset of the border box, which fail for now.
+fn check_expected_layout(box_tree: Option<&LayoutBox>, node: &NodeRef) -> Vec<String> {
+    let element = match node.as_element() {
+        Some(element) => element,
+        None => return Vec::new(),
+    };
+    let expectations = element
+        .attributes
+        .borrow()
+        .map
+        .iter()
+        .filter(|(name, _)| {
+            name.local.starts_with("data-expected-") || name.local.starts_with("data-offset-")
+        })
+        .map(|(name, attribute)| (name.local.to_string(), attribute.value.clone()))
+        .collect::<Vec<_>>();
+    if expectations.is_empty() {
+        return Vec::new();
+    }
+    let geometry = match box_tree.and_then(|box_tree| box_geometry(box_tree, node)) {
+        Some(geometry) => geometry,
+        None => return vec![format!("<{}> generated no boxes", element.name.local)],
+    };
+    let offset = offset_from_offset_parent(box_tree.unwrap(), node);
+    let mut failures = Vec::new();
+    for (name, expected) in expectations {
+        let actual = match &name[..] {
+            "data-expected-width" => geometry.border_box.width.px(),
+            "data-expected-height" => geometry.border_box.height.px(),
+            "data-expected-client-width" => geometry.padding_box.width.px(),
+            "data-expected-client-height" => geometry.padding_box.height.px(),
+            "data-offset-x" => offset.x,
+            "data-offset-y" => offset.y,
+            _ => {
+                failures.push(format!("{} isn't supported", name));
+                continue;
+            }
+        };
+        match expected.trim().parse::<f32>() {
+            Ok(expected) if actual.round() == expected.round() => {}
+            Ok(_) => failures.push(format!("{} was {}, not {}", name, actual.round(), expected)),
+            Err(_) => failures.push(format!("{} wasn't a number", name)),
+        }
+    }
+    failures
+}
+
+/// The offset of the border box of `node` from the padding box of its offset parent, like
+/// `offsetLeft` and `offsetTop`, as laid out in `box_tree`.  The offset parent is the nearest
+/// positioned ancestor, or table cell or table, or `<body>`; offsets from `<body>` (or from no
+/// offset parent) are from the top left of the initial containing block.
+///
+/// https://drafts.csswg.org/cssom-view/#extensions-to-the-htmlelement-interface
+fn offset_from_offset_parent(box_tree: &LayoutBox, node: &NodeRef) -> Point {
+    let border_box = match box_geometry(box_tree, node) {
+        Some(geometry) => geometry.border_box,
+        None => return Point::default(),
+    };
+    let offset_parent = if node.computed_values().position == Position::Fixed {
+        None
+    } else {
+        node.ancestors().find(|ancestor| {
+            ancestor.as_element().map_or(false, |element| {
+                matches!(
+                    element.name.local,
+                    local_name!("body")
+                        | local_name!("td")
+                        | local_name!("th")
+                        | local_name!("table")
+                ) || ancestor.computed_values().position != Position::Static
+            })
+        })
+    };
+    let origin = offset_parent
+        .filter(|offset_parent| {
+            offset_parent
+                .as_element()
+                .map_or(false, |element| element.name.local != local_name!("body"))
+        })
+        .and_then(|offset_parent| box_geometry(box_tree, &offset_parent))
+        .map_or(Point::default(), |geometry| Point {
+            x: geometry.padding_box.start_x,
+            y: geometry.padding_box.start_y,
+        });
+    Point {
+        x: border_box.start_x - origin.x,
+        y: border_box.start_y - origin.y,
+    }
+}
+
+fn millis_since_epoch() -> u128 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_millis())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_references_and_fuzziness() {
+        let document = parse_html().one(
+            "<link rel=author href=mailto:someone@example.com>\
+             <link rel=\"Match\" href=\"/css/reference/green-square.html?pipe=sub\">\
+             <meta name=fuzzy content=\"green-square.html:maxDifference=0-2;totalPixels=0-40\">",
+        );
+        let checkout = Path::new("wpt");
+        assert_eq!(
+            test_for_document(
+                checkout,
+                PathBuf::from("wpt/css/box/border.html"),
+                &document
+            ),
+            Some(WptTest::Reftest(Reftest {
+                kind: ReftestKind::Match,
+                test: PathBuf::from("wpt/css/box/border.html"),
+                reference: PathBuf::from("wpt/css/reference/green-square.html"),
+                fuzzy: Fuzzy {
+                    max_difference: 2,
+                    max_pixels: 40,
+                },
+            }))
+        );
+        let document = parse_html().one("<link rel=mismatch href=border-notref.html>");
+        assert_eq!(
+            reference_link(&document),
+            Some((ReftestKind::Mismatch, "border-notref.html".to_owned()))
+        );
+        assert_eq!(
+            parse_fuzzy_content("2;40"),
+            Some(Fuzzy {
+                max_difference: 2,
+                max_pixels: 40,
+            })
+        );
+    }
+
+    #[test]
+    fn finds_crashtests() {
+        let document = parse_html().one("<p>No references</p>");
+        let checkout = Path::new("wpt");
+        let crashtest = PathBuf::from("wpt/css/box/crashtests/nested.html");
+        assert_eq!(
+            test_for_document(checkout, crashtest.clone(), &document),
+            Some(WptTest::Crashtest(crashtest))
+        );
+        let crashtest = PathBuf::from("wpt/css/box/nested-crash.html");
+        assert_eq!(
+            test_for_document(checkout, crashtest.clone(), &document),
+            Some(WptTest::Crashtest(crashtest))
+        );
+        let testharness_test = PathBuf::from("wpt/css/box/parsing.html");
+        assert_eq!(
+            test_for_document(checkout, testharness_test, &document),
+            None
+        );
+    }
+
+    #[test]
+    fn finds_check_layout_tests() {
+        let checkout = Path::new("wpt");
+        let path = PathBuf::from("wpt/css/box/sizes.html");
+        let document = parse_html().one(
+            "<script src=\"/resources/check-layout-th.js\"></script>\
+             <body onload=\"checkLayout('.test')\">\
+             <script>checkLayout( \"#other > div\" );</script>",
+        );
+        assert_eq!(
+            test_for_document(checkout, path.clone(), &document),
+            Some(WptTest::CheckLayout(CheckLayoutTest {
+                path: path.clone(),
+                selectors: vec![".test".to_owned(), "#other > div".to_owned()],
+            }))
+        );
+        // Tests that don't use check-layout-th.js check more than layout.
+        let document = parse_html().one("<script>checkLayout('.test')</script>");
+        assert_eq!(test_for_document(checkout, path, &document), None);
+        assert_eq!(
+            resolve_href(
+                checkout,
+                Path::new("wpt/css/box/sizes.html"),
+                "http://web-platform.test:8000/css/reference/ref.html"
+            ),
+            PathBuf::from("wpt/css/reference/ref.html")
+        );
+    }
+
+    #[test]
+    fn reports_results_by_url() {
+        let checkout = Path::new("wpt");
+        let result = WptResult {
+            test: test_url(checkout, Path::new("wpt/css/box/border.html")),
+            status: WptStatus::Fail,
+            message: Some("12 pixels differ, by up to 255".to_owned()),
+            subtests: Vec::new(),
+        };
+        let report = wptreport(&[result], 1000, 2000);
+        assert_eq!(
+            report["results"],
+            json!([{
+                "test": "/css/box/border.html",
+                "status": "FAIL",
+                "message": "12 pixels differ, by up to 255",
+                "subtests": [],
+            }])
+        );
+        assert_eq!(report["run_info"]["product"], "kosmonaut");
+        assert_eq!(report["time_end"], 2000);
+    }
+}
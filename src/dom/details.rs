@@ -0,0 +1,85 @@
+//! The `<details>` element, a disclosure widget that shows its contents only while it's open.  Its
+//! `<summary>` is always shown, and clicking it opens or closes the element.
+//!
+//! https://html.spec.whatwg.org/multipage/interactive-elements.html#the-details-element
+
+use crate::dom::mutation::{self, Mutation};
+use crate::dom::tree::NodeRef;
+
+/// Whether `node` is an HTML element named `local_name`.
+fn is_html_element(node: &NodeRef, local_name: html5ever::LocalName) -> bool {
+    node.as_element().map_or(false, |element| {
+        element.name.ns == ns!(html) && element.name.local == local_name
+    })
+}
+
+/// Whether `details` is a `<details>` element with the `open` attribute.
+pub fn is_open(details: &NodeRef) -> bool {
+    is_html_element(details, local_name!("details"))
+        && details
+            .as_element()
+            .unwrap()
+            .attributes
+            .borrow()
+            .contains(local_name!("open"))
+}
+
+/// Whether `node` is the summary of its parent `<details>` element: the first `<summary>` child of
+/// it.
+///
+/// https://html.spec.whatwg.org/multipage/interactive-elements.html#summary-for-its-parent-details
+pub fn is_summary_for_parent_details(node: &NodeRef) -> bool {
+    if !is_html_element(node, local_name!("summary")) {
+        return false;
+    }
+    match node.parent() {
+        Some(parent) if is_html_element(&parent, local_name!("details")) => parent
+            .children()
+            .find(|child| is_html_element(child, local_name!("summary")))
+            .map_or(false, |summary| summary == *node),
+        _ => false,
+    }
+}
+
+/// Whether `child` is rendered, which every node is unless it's the child of a closed `<details>`
+/// element other than its summary.
+///
+/// https://html.spec.whatwg.org/multipage/rendering.html#the-details-and-summary-elements
+pub fn is_rendered_child(child: &NodeRef) -> bool {
+    match child.parent() {
+        Some(parent) if is_html_element(&parent, local_name!("details")) => {
+            is_open(&parent) || is_summary_for_parent_details(child)
+        }
+        _ => true,
+    }
+}
+
+/// The activation behavior of `<summary>` elements, which opens the parent `<details>` element
+/// of `summary` if it's closed and closes it if it's open.  Returns whether `summary` was the
+/// summary of its parent details, and so toggled it.
+///
+/// TODO: Fire the `toggle` event at the `<details>` element.
+///
+/// https://html.spec.whatwg.org/multipage/interactive-elements.html#the-summary-element
+pub fn toggle(summary: &NodeRef) -> bool {
+    if !is_summary_for_parent_details(summary) {
+        return false;
+    }
+    let details = summary.parent().unwrap();
+    let element = details.as_element().unwrap();
+    let mut attributes = element.attributes.borrow_mut();
+    let old_value = match attributes.remove(local_name!("open")) {
+        Some(attribute) => Some(attribute.value),
+        None => {
+            attributes.insert(local_name!("open"), String::new());
+            None
+        }
+    };
+    drop(attributes);
+    mutation::record(Mutation::Attribute {
+        element: details.clone(),
+        name: local_name!("open"),
+        old_value,
+    });
+    true
+}
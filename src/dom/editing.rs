@@ -0,0 +1,287 @@
+use std::ops::Range;
+
+/// The editable value of a text control (a text field `<input>` or a `<textarea>`), along with its
+/// caret and selection.  All positions are indices of characters in the value, not bytes.
+///
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#concept-fe-value
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#textFieldSelection
+#[derive(Clone, Debug, PartialEq)]
+pub struct EditableText {
+    value: String,
+    /// The position of the caret, which is also the end of the selection that moves as it's
+    /// extended.
+    caret: usize,
+    /// The end of the selection that stays put as it's extended.  Equal to `caret` when nothing is
+    /// selected.
+    anchor: usize,
+    /// Whether the value may contain line breaks, i.e. whether this is the value of a `<textarea>`.
+    multiline: bool,
+}
+
+/// An editing operation, usually the result of a keystroke.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EditCommand {
+    /// Replaces the selection with a character.
+    Insert(char),
+    /// Deletes the selection, or the character before the caret if nothing is selected.
+    DeleteBackward,
+    /// Deletes the selection, or the character after the caret if nothing is selected.
+    DeleteForward,
+    /// Moves the caret.  If `extend_selection` is true, the anchor of the selection stays where it
+    /// was, otherwise the selection collapses to the caret.
+    Move {
+        movement: CaretMovement,
+        extend_selection: bool,
+    },
+    SelectAll,
+}
+
+/// The ways the caret can be moved.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CaretMovement {
+    Left,
+    Right,
+    Up,
+    Down,
+    LineStart,
+    LineEnd,
+}
+
+impl EditableText {
+    /// Creates an editable value with the caret at its end.  Line breaks are removed from the
+    /// value unless it's `multiline`.
+    ///
+    /// https://html.spec.whatwg.org/multipage/input.html#text-(type=text)-state-and-search-state-(type=search)
+    pub fn new(value: &str, multiline: bool) -> EditableText {
+        let value = sanitize(value, multiline);
+        let end = value.chars().count();
+        EditableText {
+            value,
+            caret: end,
+            anchor: end,
+            multiline,
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn caret(&self) -> usize {
+        self.caret
+    }
+
+    /// The selected range of characters, which is empty if nothing is selected.
+    pub fn selection(&self) -> Range<usize> {
+        self.caret.min(self.anchor)..self.caret.max(self.anchor)
+    }
+
+    pub fn apply(&mut self, command: EditCommand) {
+        match command {
+            EditCommand::Insert(ch) => self.replace_selection(&ch.to_string()),
+            EditCommand::DeleteBackward => {
+                if self.caret == self.anchor {
+                    self.anchor = self.caret.saturating_sub(1);
+                }
+                self.replace_selection("");
+            }
+            EditCommand::DeleteForward => {
+                if self.caret == self.anchor {
+                    self.anchor = (self.caret + 1).min(self.len());
+                }
+                self.replace_selection("");
+            }
+            EditCommand::Move {
+                movement,
+                extend_selection,
+            } => {
+                let selection = self.selection();
+                self.caret = match movement {
+                    // Like other editors, collapse a selection to the side being moved towards
+                    // rather than moving past it.
+                    CaretMovement::Left if !extend_selection && !selection.is_empty() => {
+                        selection.start
+                    }
+                    CaretMovement::Right if !extend_selection && !selection.is_empty() => {
+                        selection.end
+                    }
+                    CaretMovement::Left => self.caret.saturating_sub(1),
+                    CaretMovement::Right => (self.caret + 1).min(self.len()),
+                    CaretMovement::Up | CaretMovement::Down => {
+                        let (line, column) = self.line_and_column(self.caret);
+                        match movement {
+                            CaretMovement::Up if line == 0 => 0,
+                            CaretMovement::Up => self.index_of(line - 1, column),
+                            _ if line + 1 == self.line_count() => self.len(),
+                            _ => self.index_of(line + 1, column),
+                        }
+                    }
+                    CaretMovement::LineStart => {
+                        let (line, _) = self.line_and_column(self.caret);
+                        self.index_of(line, 0)
+                    }
+                    CaretMovement::LineEnd => {
+                        let (line, _) = self.line_and_column(self.caret);
+                        self.index_of(line, usize::MAX)
+                    }
+                };
+                if !extend_selection {
+                    self.anchor = self.caret;
+                }
+            }
+            EditCommand::SelectAll => {
+                self.anchor = 0;
+                self.caret = self.len();
+            }
+        }
+    }
+
+    /// The line and column of the character at `index`, where lines are separated by line breaks.
+    pub fn line_and_column(&self, index: usize) -> (usize, usize) {
+        let mut line = 0;
+        let mut line_start = 0;
+        for (i, ch) in self.value.chars().take(index).enumerate() {
+            if ch == '\n' {
+                line += 1;
+                line_start = i + 1;
+            }
+        }
+        (line, index - line_start)
+    }
+
+    /// The selected columns of each line with selected characters, as `(line, columns)` pairs.
+    pub fn selected_line_ranges(&self) -> Vec<(usize, Range<usize>)> {
+        let selection = self.selection();
+        if selection.is_empty() {
+            return Vec::new();
+        }
+        let (start_line, start_column) = self.line_and_column(selection.start);
+        let (end_line, end_column) = self.line_and_column(selection.end);
+        (start_line..=end_line)
+            .map(|line| {
+                let start = if line == start_line { start_column } else { 0 };
+                let end = if line == end_line {
+                    end_column
+                } else {
+                    self.line_length(line)
+                };
+                (line, start..end)
+            })
+            .collect()
+    }
+
+    fn replace_selection(&mut self, replacement: &str) {
+        let replacement = sanitize(replacement, self.multiline);
+        let selection = self.selection();
+        let start = self.byte_index(selection.start);
+        let end = self.byte_index(selection.end);
+        self.value.replace_range(start..end, &replacement);
+        self.caret = selection.start + replacement.chars().count();
+        self.anchor = self.caret;
+    }
+
+    fn len(&self) -> usize {
+        self.value.chars().count()
+    }
+
+    fn line_count(&self) -> usize {
+        self.value.split('\n').count()
+    }
+
+    fn line_length(&self, line: usize) -> usize {
+        self.value
+            .split('\n')
+            .nth(line)
+            .map_or(0, |line| line.chars().count())
+    }
+
+    /// The index of the character at the given line and column, clamping the column to the length
+    /// of the line.
+    fn index_of(&self, line: usize, column: usize) -> usize {
+        let preceding_lines = self
+            .value
+            .split('\n')
+            .take(line)
+            .map(|line| line.chars().count() + 1)
+            .sum::<usize>();
+        preceding_lines + column.min(self.line_length(line))
+    }
+
+    fn byte_index(&self, index: usize) -> usize {
+        self.value
+            .char_indices()
+            .nth(index)
+            .map_or(self.value.len(), |(byte_index, _)| byte_index)
+    }
+}
+
+/// Normalizes line breaks to `\n`, removing them entirely from single-line values.
+///
+/// https://html.spec.whatwg.org/multipage/form-elements.html#textarea-wrapping-transformation
+fn sanitize(value: &str, multiline: bool) -> String {
+    let normalized = value.replace("\r\n", "\n").replace('\r', "\n");
+    if multiline {
+        normalized
+    } else {
+        normalized.replace('\n', "")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn move_caret(movement: CaretMovement, extend_selection: bool) -> EditCommand {
+        EditCommand::Move {
+            movement,
+            extend_selection,
+        }
+    }
+
+    #[test]
+    fn inserts_and_deletes_at_caret() {
+        let mut text = EditableText::new("héllo", false);
+        assert_eq!(text.caret(), 5);
+        text.apply(EditCommand::DeleteBackward);
+        text.apply(move_caret(CaretMovement::LineStart, false));
+        text.apply(EditCommand::DeleteForward);
+        text.apply(EditCommand::Insert('y'));
+        assert_eq!(text.value(), "yéll");
+        assert_eq!(text.caret(), 1);
+        // Single-line values never contain line breaks.
+        text.apply(EditCommand::Insert('\n'));
+        assert_eq!(text.value(), "yéll");
+    }
+
+    #[test]
+    fn typing_replaces_selection() {
+        let mut text = EditableText::new("abcdef", false);
+        text.apply(move_caret(CaretMovement::Left, false));
+        text.apply(move_caret(CaretMovement::Left, true));
+        text.apply(move_caret(CaretMovement::Left, true));
+        assert_eq!(text.selection(), 3..5);
+        text.apply(EditCommand::Insert('X'));
+        assert_eq!(text.value(), "abcXf");
+        assert_eq!(text.selection(), 4..4);
+        text.apply(EditCommand::SelectAll);
+        text.apply(EditCommand::DeleteBackward);
+        assert_eq!(text.value(), "");
+    }
+
+    #[test]
+    fn moves_between_lines() {
+        let mut text = EditableText::new("first\r\nab\nthird", true);
+        assert_eq!(text.value(), "first\nab\nthird");
+        assert_eq!(text.line_and_column(text.caret()), (2, 5));
+        text.apply(move_caret(CaretMovement::Up, false));
+        // The column is clamped to the length of the shorter line.
+        assert_eq!(text.line_and_column(text.caret()), (1, 2));
+        text.apply(move_caret(CaretMovement::Up, true));
+        assert_eq!(text.line_and_column(text.caret()), (0, 2));
+        assert_eq!(text.selected_line_ranges(), vec![(0, 2..5), (1, 0..2)]);
+        text.apply(move_caret(CaretMovement::Down, false));
+        text.apply(move_caret(CaretMovement::Down, false));
+        text.apply(move_caret(CaretMovement::Down, false));
+        assert_eq!(text.caret(), 14);
+    }
+}
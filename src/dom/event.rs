@@ -0,0 +1,381 @@
+use crate::dom::tree::NodeRef;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// An event dispatched to a node, e.g. in response to user input.
+///
+/// https://dom.spec.whatwg.org/#interface-event
+#[derive(Clone, Debug)]
+pub struct Event {
+    /// The type of the event, e.g. `click` or `keydown`.
+    event_type: String,
+    bubbles: bool,
+    cancelable: bool,
+    /// The data specific to the interface of the event.
+    kind: EventKind,
+    /// The node the event was dispatched to.  `None` until the event is dispatched.
+    target: Option<NodeRef>,
+    /// The node whose listeners are currently being invoked.  `None` outside of dispatch.
+    current_target: Option<NodeRef>,
+    phase: EventPhase,
+    stop_propagation: bool,
+    stop_immediate_propagation: bool,
+    canceled: bool,
+}
+
+/// The interfaces events can implement beyond `Event`, along with their data.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EventKind {
+    Plain,
+    Mouse(MouseEvent),
+    Keyboard(KeyboardEvent),
+}
+
+/// https://w3c.github.io/uievents/#interface-mouseevent
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MouseEvent {
+    /// The position of the pointer in CSS pixels, relative to the viewport.
+    pub client_x: f32,
+    pub client_y: f32,
+    pub button: MouseButton,
+    pub modifiers: Modifiers,
+}
+
+/// https://w3c.github.io/uievents/#interface-keyboardevent
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyboardEvent {
+    /// The key value of the key, e.g. `a`, `Enter`, or `ArrowLeft`.
+    /// https://w3c.github.io/uievents-key/
+    pub key: String,
+    pub modifiers: Modifiers,
+}
+
+/// https://w3c.github.io/uievents/#dom-mouseevent-button
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MouseButton {
+    Primary,
+    Auxiliary,
+    Secondary,
+}
+
+/// The modifier keys held when an input event occurred.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub meta: bool,
+}
+
+/// https://dom.spec.whatwg.org/#dom-event-eventphase
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EventPhase {
+    None,
+    Capturing,
+    AtTarget,
+    Bubbling,
+}
+
+impl Event {
+    pub fn new(event_type: &str, bubbles: bool, cancelable: bool, kind: EventKind) -> Event {
+        Event {
+            event_type: event_type.to_owned(),
+            bubbles,
+            cancelable,
+            kind,
+            target: None,
+            current_target: None,
+            phase: EventPhase::None,
+            stop_propagation: false,
+            stop_immediate_propagation: false,
+            canceled: false,
+        }
+    }
+
+    /// Creates a mouse event with the bubbling and cancelation behavior its type calls for.
+    ///
+    /// https://w3c.github.io/uievents/#events-mouse-types
+    pub fn mouse(event_type: &str, mouse_event: MouseEvent) -> Event {
+        let is_boundary_event = matches!(event_type, "mouseenter" | "mouseleave");
+        Event::new(
+            event_type,
+            !is_boundary_event,
+            !is_boundary_event,
+            EventKind::Mouse(mouse_event),
+        )
+    }
+
+    /// Creates a keyboard event, which always bubbles and is cancelable.
+    ///
+    /// https://w3c.github.io/uievents/#events-keyboard-types
+    pub fn keyboard(event_type: &str, keyboard_event: KeyboardEvent) -> Event {
+        Event::new(event_type, true, true, EventKind::Keyboard(keyboard_event))
+    }
+
+    pub fn event_type(&self) -> &str {
+        &self.event_type
+    }
+
+    pub fn bubbles(&self) -> bool {
+        self.bubbles
+    }
+
+    pub fn kind(&self) -> &EventKind {
+        &self.kind
+    }
+
+    pub fn target(&self) -> Option<&NodeRef> {
+        self.target.as_ref()
+    }
+
+    pub fn current_target(&self) -> Option<&NodeRef> {
+        self.current_target.as_ref()
+    }
+
+    pub fn phase(&self) -> EventPhase {
+        self.phase
+    }
+
+    /// Prevents the event from reaching any node after the current one.
+    pub fn stop_propagation(&mut self) {
+        self.stop_propagation = true;
+    }
+
+    /// Prevents the event from reaching any listener after the current one, including the
+    /// remaining listeners of the current node.
+    pub fn stop_immediate_propagation(&mut self) {
+        self.stop_propagation = true;
+        self.stop_immediate_propagation = true;
+    }
+
+    /// Cancels the event, so the default action of whatever caused it (like following a link) is
+    /// skipped.  Does nothing if the event isn't cancelable.
+    pub fn prevent_default(&mut self) {
+        if self.cancelable {
+            self.canceled = true;
+        }
+    }
+
+    pub fn default_prevented(&self) -> bool {
+        self.canceled
+    }
+}
+
+/// A callback registered with `add_event_listener`.
+pub type EventCallback = Rc<dyn Fn(&mut Event)>;
+
+/// Identifies a registered listener, so it can be removed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ListenerId(usize);
+
+static NEXT_LISTENER_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// https://dom.spec.whatwg.org/#concept-event-listener
+#[derive(Clone)]
+pub struct EventListener {
+    id: ListenerId,
+    event_type: String,
+    /// Whether the listener is invoked in the capture phase rather than the bubble phase.
+    capture: bool,
+    callback: EventCallback,
+}
+
+impl NodeRef {
+    /// Registers `callback` to be invoked when an event of type `event_type` reaches this node,
+    /// either during the capture phase (if `capture` is true) or the bubble phase.  Listeners are
+    /// invoked in the order they were added.
+    ///
+    /// Note that a callback holding a `NodeRef` to this node (or its descendants) forms a
+    /// reference cycle, keeping the node alive until the listener is removed.
+    ///
+    /// https://dom.spec.whatwg.org/#dom-eventtarget-addeventlistener
+    pub fn add_event_listener<F>(&self, event_type: &str, capture: bool, callback: F) -> ListenerId
+    where
+        F: Fn(&mut Event) + 'static,
+    {
+        let id = ListenerId(NEXT_LISTENER_ID.fetch_add(1, Ordering::Relaxed));
+        self.event_listeners().borrow_mut().push(EventListener {
+            id,
+            event_type: event_type.to_owned(),
+            capture,
+            callback: Rc::new(callback),
+        });
+        id
+    }
+
+    /// https://dom.spec.whatwg.org/#dom-eventtarget-removeeventlistener
+    pub fn remove_event_listener(&self, id: ListenerId) {
+        self.event_listeners()
+            .borrow_mut()
+            .retain(|listener| listener.id != id);
+    }
+
+    /// Dispatches `event` to this node: first down through its ancestors in the capture phase,
+    /// then to the node itself, then (if the event bubbles) back up through its ancestors.
+    /// Returns false if a listener canceled the event, true otherwise.
+    ///
+    /// https://dom.spec.whatwg.org/#concept-event-dispatch
+    pub fn dispatch_event(&self, event: &mut Event) -> bool {
+        event.target = Some(self.clone());
+        let ancestors = self.ancestors().collect::<Vec<_>>();
+
+        event.phase = EventPhase::Capturing;
+        for ancestor in ancestors.iter().rev() {
+            if event.stop_propagation {
+                break;
+            }
+            ancestor.invoke_listeners(event, true);
+        }
+        if !event.stop_propagation {
+            event.phase = EventPhase::AtTarget;
+            // Capturing listeners on the target are invoked before non-capturing ones.
+            self.invoke_listeners(event, true);
+            self.invoke_listeners(event, false);
+        }
+        if event.bubbles {
+            event.phase = EventPhase::Bubbling;
+            for ancestor in &ancestors {
+                if event.stop_propagation {
+                    break;
+                }
+                ancestor.invoke_listeners(event, false);
+            }
+        }
+
+        event.phase = EventPhase::None;
+        event.current_target = None;
+        event.stop_propagation = false;
+        event.stop_immediate_propagation = false;
+        !event.canceled
+    }
+
+    /// Invokes either the capturing or the non-capturing listeners of this node for `event`.
+    ///
+    /// https://dom.spec.whatwg.org/#concept-event-listener-inner-invoke
+    fn invoke_listeners(&self, event: &mut Event, capture: bool) {
+        // Listeners may add or remove listeners, so invoke a snapshot of them.  Listeners added
+        // during dispatch aren't invoked until the next dispatch.
+        let listeners = self
+            .event_listeners()
+            .borrow()
+            .iter()
+            .filter(|listener| {
+                listener.event_type == event.event_type && listener.capture == capture
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        event.current_target = Some(self.clone());
+        for listener in listeners {
+            if event.stop_immediate_propagation {
+                break;
+            }
+            // A listener removed by an earlier listener in the snapshot isn't invoked.
+            let still_registered = self
+                .event_listeners()
+                .borrow()
+                .iter()
+                .any(|registered| registered.id == listener.id);
+            if still_registered {
+                (listener.callback)(event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::parser::parse_html;
+    use crate::dom::traits::*;
+    use std::cell::RefCell;
+
+    fn node(document: &NodeRef, selector: &str) -> NodeRef {
+        document.select_first(selector).unwrap().as_node().clone()
+    }
+
+    /// Adds capturing and non-capturing listeners for `event_type` to `node` that record when
+    /// they're invoked in `log`.
+    fn log_listeners(node: &NodeRef, event_type: &str, name: &str, log: &Rc<RefCell<Vec<String>>>) {
+        for &capture in &[true, false] {
+            let log = log.clone();
+            let entry = format!("{} {}", name, if capture { "capture" } else { "bubble" });
+            node.add_event_listener(event_type, capture, move |_| {
+                log.borrow_mut().push(entry.clone())
+            });
+        }
+    }
+
+    #[test]
+    fn propagates_through_capture_target_and_bubble_phases() {
+        let document = parse_html().one("<div><p><span>hi</span></p></div>");
+        let log = Rc::new(RefCell::new(Vec::new()));
+        log_listeners(&node(&document, "div"), "click", "div", &log);
+        log_listeners(&node(&document, "span"), "click", "span", &log);
+        log_listeners(&node(&document, "p"), "keydown", "p", &log);
+
+        let mut event = Event::new("click", true, true, EventKind::Plain);
+        assert!(node(&document, "span").dispatch_event(&mut event));
+        assert_eq!(
+            *log.borrow(),
+            vec!["div capture", "span capture", "span bubble", "div bubble"]
+        );
+        assert!(event.target() == Some(&node(&document, "span")));
+        assert_eq!(event.phase(), EventPhase::None);
+
+        log.borrow_mut().clear();
+        let mut event = Event::new("click", false, true, EventKind::Plain);
+        node(&document, "span").dispatch_event(&mut event);
+        assert_eq!(
+            *log.borrow(),
+            vec!["div capture", "span capture", "span bubble"]
+        );
+    }
+
+    #[test]
+    fn stopping_propagation_and_canceling() {
+        let document = parse_html().one("<div><p></p></div>");
+        let (div, p) = (node(&document, "div"), node(&document, "p"));
+        let log = Rc::new(RefCell::new(Vec::new()));
+        p.add_event_listener("click", false, |event| {
+            event.prevent_default();
+            event.stop_propagation();
+        });
+        log_listeners(&p, "click", "p", &log);
+        log_listeners(&div, "click", "div", &log);
+
+        let mut event = Event::new("click", true, true, EventKind::Plain);
+        assert!(!p.dispatch_event(&mut event));
+        // The remaining listeners of the node that stopped propagation are still invoked.
+        assert_eq!(*log.borrow(), vec!["div capture", "p capture", "p bubble"]);
+
+        // Events that aren't cancelable can't be canceled.
+        let mut event = Event::new("click", true, false, EventKind::Plain);
+        assert!(p.dispatch_event(&mut event));
+    }
+
+    #[test]
+    fn removed_listeners_are_not_invoked() {
+        let document = parse_html().one("<p></p>");
+        let p = node(&document, "p");
+        let count = Rc::new(RefCell::new(0));
+        let counter = count.clone();
+        let id = p.add_event_listener("mousedown", false, move |event| {
+            *counter.borrow_mut() += 1;
+            event.stop_immediate_propagation();
+        });
+        let counter = count.clone();
+        p.add_event_listener("mousedown", false, move |_| *counter.borrow_mut() += 10);
+
+        let mouse_event = MouseEvent {
+            client_x: 1.,
+            client_y: 2.,
+            button: MouseButton::Primary,
+            modifiers: Modifiers::default(),
+        };
+        p.dispatch_event(&mut Event::mouse("mousedown", mouse_event));
+        assert_eq!(*count.borrow(), 1);
+        p.remove_event_listener(id);
+        p.dispatch_event(&mut Event::mouse("mousedown", mouse_event));
+        assert_eq!(*count.borrow(), 11);
+    }
+}
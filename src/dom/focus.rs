@@ -0,0 +1,326 @@
+//! Focus: which element of a document keystrokes are sent to, and moving it between elements with
+//! the keyboard (sequential focus navigation).
+//!
+//! https://html.spec.whatwg.org/multipage/interaction.html#focus
+
+use crate::dom::details;
+use crate::dom::event::{Event, EventKind};
+use crate::dom::form::{self, FormControlKind};
+use crate::dom::mutation::{self, Mutation};
+use crate::dom::navigation;
+use crate::dom::tree::{ElementData, NodeRef};
+use crate::layout::rect::Rect;
+
+/// Which way sequential focus navigation moves focus: forward with Tab, or backward with
+/// Shift+Tab.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FocusDirection {
+    Forward,
+    Backward,
+}
+
+/// The value of the given element's `tabindex` attribute, if it has a valid one.
+///
+/// https://html.spec.whatwg.org/multipage/interaction.html#attr-tabindex
+pub fn tabindex(element: &ElementData) -> Option<i32> {
+    let attributes = element.attributes.borrow();
+    let value = attributes
+        .get(local_name!("tabindex"))?
+        .trim_start_matches(|c: char| c.is_ascii_whitespace());
+    // https://html.spec.whatwg.org/multipage/common-microsyntaxes.html#rules-for-parsing-integers
+    let digits_start = if value.starts_with('-') || value.starts_with('+') {
+        1
+    } else {
+        0
+    };
+    let digits_end = value[digits_start..]
+        .find(|c: char| !c.is_ascii_digit())
+        .map_or(value.len(), |end| digits_start + end);
+    value[..digits_end]
+        .trim_start_matches('+')
+        .parse::<i32>()
+        .ok()
+}
+
+/// Whether `node` can be focused: it's a link, an enabled form control, the summary of a
+/// `<details>` element, or has a `tabindex`, and it's being rendered (see `rendered_rects`).
+///
+/// https://html.spec.whatwg.org/multipage/interaction.html#focusable-area
+pub fn is_focusable(node: &NodeRef) -> bool {
+    let element = match node.as_element() {
+        Some(element) => element,
+        None => return false,
+    };
+    let focusable = if FormControlKind::of(node).is_some() {
+        !form::is_disabled(node)
+    } else {
+        tabindex(element).is_some()
+            || navigation::hyperlink_href(node).is_some()
+            || details::is_summary_for_parent_details(node)
+    };
+    focusable && !rendered_rects(node).is_empty()
+}
+
+/// The rects `node` was last laid out in, which are empty if it isn't being rendered.  These are
+/// its client rects, or if it has none, those of its descendants, since inline elements don't
+/// record client rects of their own (only the text in them does).
+///
+/// TODO: Use the rects of inline boxes once they're laid out as boxes.
+pub fn rendered_rects(node: &NodeRef) -> Vec<Rect> {
+    let client_rects = node.client_rects();
+    if !client_rects.is_empty() {
+        return client_rects;
+    }
+    node.descendants()
+        .flat_map(|descendant| descendant.client_rects())
+        .collect()
+}
+
+/// The element that pressing the mouse over `node` focuses: its nearest focusable inclusive
+/// ancestor, if any.
+///
+/// https://html.spec.whatwg.org/multipage/interaction.html#focus-processing-model
+pub fn click_focus_target(node: &NodeRef) -> Option<NodeRef> {
+    node.inclusive_ancestors().find(is_focusable)
+}
+
+/// The focusable elements of `document` that sequential focus navigation moves through, in the
+/// order it moves through them: those with a positive `tabindex` in increasing order of it, then
+/// the rest in tree order.  Elements with a negative `tabindex` are skipped.
+///
+/// https://html.spec.whatwg.org/multipage/interaction.html#sequential-focus-navigation-order
+pub fn sequential_navigation_order(document: &NodeRef) -> Vec<NodeRef> {
+    let mut order = document
+        .shadow_including_inclusive_descendants()
+        .filter(is_focusable)
+        .filter_map(|node| {
+            let tabindex = tabindex(node.as_element().unwrap()).unwrap_or(0);
+            if tabindex < 0 {
+                None
+            } else {
+                Some((tabindex, node))
+            }
+        })
+        .collect::<Vec<_>>();
+    // The sort is stable, so elements with the same `tabindex` stay in tree order.
+    order.sort_by_key(|&(tabindex, _)| if tabindex == 0 { i32::MAX } else { tabindex });
+    order.into_iter().map(|(_, node)| node).collect()
+}
+
+/// The element sequential focus navigation moves focus to from `current` (or from the start or
+/// end of `document` if nothing is focused), wrapping around at either end.  `None` if nothing in
+/// the document can be focused with the keyboard.
+///
+/// https://html.spec.whatwg.org/multipage/interaction.html#sequential-focus-navigation
+pub fn next_focus_target(
+    document: &NodeRef,
+    current: Option<&NodeRef>,
+    direction: FocusDirection,
+) -> Option<NodeRef> {
+    let order = sequential_navigation_order(document);
+    if order.is_empty() {
+        return None;
+    }
+    let position = current.and_then(|current| order.iter().position(|node| node == current));
+    let index = match (position, direction) {
+        (Some(position), FocusDirection::Forward) => (position + 1) % order.len(),
+        (Some(position), FocusDirection::Backward) => (position + order.len() - 1) % order.len(),
+        (None, FocusDirection::Forward) => 0,
+        (None, FocusDirection::Backward) => order.len() - 1,
+    };
+    Some(order[index].clone())
+}
+
+/// Whether pressing the key with the given key value while `node` is focused clicks it, as a
+/// press of the primary mouse button would: Enter clicks links, buttons and the summaries of
+/// `<details>` elements, while Space clicks buttons, summaries, checkboxes and radio buttons.
+///
+/// https://html.spec.whatwg.org/multipage/interaction.html#activation
+pub fn is_activated_by_key(node: &NodeRef, key: &str) -> bool {
+    let kind = FormControlKind::of(node);
+    let is_button =
+        kind == Some(FormControlKind::Button) || details::is_summary_for_parent_details(node);
+    match key {
+        "Enter" => is_button || navigation::hyperlink_href(node).is_some(),
+        " " => {
+            is_button
+                || kind == Some(FormControlKind::Checkbox)
+                || kind == Some(FormControlKind::Radio)
+        }
+        _ => false,
+    }
+}
+
+/// The access key assigned to the given element: the first of the space-separated tokens of its
+/// `accesskey` attribute that's a single character.
+///
+/// https://html.spec.whatwg.org/multipage/interaction.html#the-accesskey-attribute
+pub fn assigned_access_key(element: &ElementData) -> Option<char> {
+    let attributes = element.attributes.borrow();
+    attributes
+        .get(local_name!("accesskey"))?
+        .split_ascii_whitespace()
+        .find_map(|token| {
+            let mut chars = token.chars();
+            match (chars.next(), chars.next()) {
+                (Some(key), None) => Some(key),
+                _ => None,
+            }
+        })
+}
+
+/// The element pressing the access key `key` focuses and clicks: the first in tree order whose
+/// assigned access key is `key` (ignoring ASCII case), that's being rendered and isn't disabled.
+///
+/// https://html.spec.whatwg.org/multipage/interaction.html#the-accesskey-attribute
+pub fn access_key_target(document: &NodeRef, key: char) -> Option<NodeRef> {
+    document
+        .shadow_including_inclusive_descendants()
+        .find(|node| {
+            node.as_element()
+                .and_then(assigned_access_key)
+                .map_or(false, |assigned| assigned.eq_ignore_ascii_case(&key))
+                && !form::is_disabled(node)
+                && !rendered_rects(node).is_empty()
+        })
+}
+
+/// Moves focus from `previously_focused` (if any) to `node` (if any), firing `blur` and `focus`
+/// at them.  Whether the newly focused element matches `:focus-visible` (and so is painted with a
+/// focus ring) depends on whether it was focused `by_pointer`: elements focused with the keyboard
+/// (or by the page) always do, while those clicked only do if they take text input.
+///
+/// https://html.spec.whatwg.org/multipage/interaction.html#focus-update-steps
+/// https://drafts.csswg.org/selectors-4/#the-focus-visible-pseudo
+pub fn update_focus(
+    previously_focused: Option<&NodeRef>,
+    node: Option<&NodeRef>,
+    by_pointer: bool,
+) {
+    if previously_focused == node {
+        return;
+    }
+    if let Some(previously_focused) = previously_focused {
+        if let Some(element) = previously_focused.as_element() {
+            element.focused.set(false);
+            element.focus_visible.set(false);
+            // Drop-down boxes close their popups when they lose focus.
+            element.dropdown_open.set(false);
+        }
+        mutation::record(Mutation::State {
+            element: previously_focused.clone(),
+        });
+        previously_focused.dispatch_event(&mut Event::new("blur", false, false, EventKind::Plain));
+    }
+    if let Some(node) = node {
+        if let Some(element) = node.as_element() {
+            let takes_text_input =
+                FormControlKind::of(node).map_or(false, FormControlKind::is_text_control);
+            element.focused.set(true);
+            element.focus_visible.set(!by_pointer || takes_text_input);
+        }
+        mutation::record(Mutation::State {
+            element: node.clone(),
+        });
+        node.dispatch_event(&mut Event::new("focus", false, false, EventKind::Plain));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::traits::*;
+    use crate::style::test_utils::laid_out_document;
+
+    fn ids(nodes: &[NodeRef]) -> Vec<String> {
+        nodes
+            .iter()
+            .map(|node| {
+                node.as_element()
+                    .unwrap()
+                    .attributes
+                    .borrow()
+                    .get("id")
+                    .unwrap_or("")
+                    .to_owned()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn orders_focusable_elements_by_tabindex() {
+        let (document, _) = laid_out_document(
+            "<a id=link href=a.html>Link</a><a id=anchor>Not a link</a>\
+             <input id=text><input id=disabled disabled><input id=second tabindex=2>\
+             <div id=div tabindex=0>Div</div><span id=first tabindex=' +1'>Span</span>\
+             <button id=skipped tabindex=-1>Skipped</button><input id=hidden style='display: none'>",
+            "",
+        );
+        assert_eq!(
+            ids(&sequential_navigation_order(&document)),
+            vec!["first", "second", "link", "text", "div"]
+        );
+        let select = |selector: &str| document.select_first(selector).unwrap().as_node().clone();
+        let skipped = select("#skipped");
+        assert!(is_focusable(&skipped));
+        assert_eq!(
+            click_focus_target(&skipped.first_child().unwrap()),
+            Some(skipped)
+        );
+        assert_eq!(click_focus_target(&select("#anchor")), None);
+
+        let forward = |current: Option<&NodeRef>| {
+            next_focus_target(&document, current, FocusDirection::Forward)
+        };
+        let backward = |current: Option<&NodeRef>| {
+            next_focus_target(&document, current, FocusDirection::Backward)
+        };
+        assert_eq!(forward(None), Some(select("#first")));
+        assert_eq!(forward(Some(&select("#div"))), Some(select("#first")));
+        assert_eq!(backward(None), Some(select("#div")));
+        assert_eq!(backward(Some(&select("#link"))), Some(select("#second")));
+    }
+
+    #[test]
+    fn finds_elements_activated_from_the_keyboard() {
+        let (document, _) = laid_out_document(
+            "<a href=a.html accesskey='ab e'>Link</a><button accesskey=c disabled>C</button>\
+             <label accesskey=C>Label</label><input type=checkbox><details><summary>More</summary>\
+             </details><input accesskey=d style='display: none'>",
+            "",
+        );
+        let select = |selector: &str| document.select_first(selector).unwrap().as_node().clone();
+        assert!(is_activated_by_key(&select("a"), "Enter"));
+        assert!(!is_activated_by_key(&select("a"), " "));
+        assert!(is_activated_by_key(&select("button"), " "));
+        assert!(is_activated_by_key(&select("summary"), "Enter"));
+        assert!(is_activated_by_key(&select("input"), " "));
+        assert!(!is_activated_by_key(&select("input"), "Enter"));
+
+        // Only the first single-character token of `accesskey` is assigned.
+        assert_eq!(access_key_target(&document, 'c'), Some(select("label")));
+        assert_eq!(access_key_target(&document, 'E'), Some(select("a")));
+        assert_eq!(access_key_target(&document, 'a'), None);
+        assert_eq!(access_key_target(&document, 'd'), None);
+    }
+
+    #[test]
+    fn focus_is_visible_unless_clicked() {
+        let (document, _) = laid_out_document("<button>Go</button><input>", "");
+        let button = document.select_first("button").unwrap();
+        let input = document.select_first("input").unwrap();
+        update_focus(None, Some(button.as_node()), true);
+        assert!(button.focused.get() && !button.focus_visible.get());
+        assert_eq!(document.select_str(":focus").unwrap().count(), 1);
+        assert_eq!(document.select_str(":focus-visible").unwrap().count(), 0);
+        update_focus(Some(button.as_node()), Some(input.as_node()), true);
+        assert!(!button.focused.get());
+        assert!(input.focus_visible.get());
+        update_focus(Some(input.as_node()), Some(button.as_node()), false);
+        assert!(button.focus_visible.get());
+        assert_eq!(
+            document.select_first(":focus-visible").unwrap().as_node(),
+            button.as_node()
+        );
+    }
+}
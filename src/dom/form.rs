@@ -0,0 +1,827 @@
+use crate::dom::editing::{EditCommand, EditableText};
+use crate::dom::mutation::{self, Mutation};
+use crate::dom::tree::{ElementData, NodeRef};
+use html5ever::LocalName;
+
+/// The kinds of form control Kosmonaut renders as widgets.  Each of these is rendered as a replaced
+/// element, since its appearance is determined by the control rather than by CSS.
+///
+/// https://html.spec.whatwg.org/multipage/rendering.html#form-controls
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FormControlKind {
+    /// A single-line text field, e.g. `<input type="text">` or `<input type="email">`.
+    TextField,
+    Checkbox,
+    Radio,
+    /// A push button, e.g. `<button>` or `<input type="submit">`.
+    Button,
+    /// A multi-line text field, i.e. `<textarea>`.
+    TextArea,
+    /// A drop-down box, i.e. `<select>`.
+    Select,
+}
+
+impl FormControlKind {
+    /// Determines which kind of form control widget `node` should be rendered as, or `None` if it
+    /// isn't rendered as one (e.g. because it isn't a form control, or is a hidden `<input>`).
+    pub fn of(node: &NodeRef) -> Option<FormControlKind> {
+        let element = node.as_element()?;
+        if element.name.ns != ns!(html) {
+            return None;
+        }
+        Some(match element.name.local {
+            local_name!("input") => match input_type(element).as_str() {
+                "hidden" => return None,
+                "checkbox" => FormControlKind::Checkbox,
+                "radio" => FormControlKind::Radio,
+                "submit" | "reset" | "button" => FormControlKind::Button,
+                // Every other type, including those Kosmonaut doesn't have a widget for yet (like
+                // `date` and `range`), is rendered as a text field so that it's at least visible.
+                _ => FormControlKind::TextField,
+            },
+            local_name!("button") => FormControlKind::Button,
+            local_name!("textarea") => FormControlKind::TextArea,
+            local_name!("select") => FormControlKind::Select,
+            _ => return None,
+        })
+    }
+
+    /// Whether controls of this kind are edited as text.
+    pub fn is_text_control(self) -> bool {
+        matches!(self, FormControlKind::TextField | FormControlKind::TextArea)
+    }
+}
+
+/// The current value of the given text control, or `None` if `node` isn't a text control.  This is
+/// the value the user has edited it to, or its default value if it hasn't been edited.
+///
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#concept-fe-value
+pub fn value(node: &NodeRef) -> Option<String> {
+    with_editable_text(node, |editable_text| editable_text.value().to_owned())
+}
+
+/// Applies an editing command to the given text control.  Does nothing if `node` isn't a text
+/// control.
+pub fn edit(node: &NodeRef, command: EditCommand) {
+    with_editable_text(node, |editable_text| editable_text.apply(command));
+}
+
+/// Calls `f` with the editable text of the given text control, creating it from the control's
+/// default value if it hasn't been edited before.  Returns `None` if `node` isn't a text control.
+pub fn with_editable_text<F, R>(node: &NodeRef, f: F) -> Option<R>
+where
+    F: FnOnce(&mut EditableText) -> R,
+{
+    let kind = FormControlKind::of(node).filter(|kind| kind.is_text_control())?;
+    let element = node.as_element()?;
+    let mut editable_text = element.editable_text.borrow_mut();
+    let editable_text = editable_text.get_or_insert_with(|| {
+        EditableText::new(&default_value(node), kind == FormControlKind::TextArea)
+    });
+    Some(f(editable_text))
+}
+
+/// The value of the given text control before it's edited: the `value` attribute of an `<input>`,
+/// or the text contents of a `<textarea>`.
+///
+/// https://html.spec.whatwg.org/multipage/form-elements.html#concept-textarea-raw-value
+fn default_value(node: &NodeRef) -> String {
+    match node.as_element() {
+        Some(element) if element.name.local == local_name!("textarea") => node.text_contents(),
+        Some(element) => element
+            .attributes
+            .borrow()
+            .get(local_name!("value"))
+            .unwrap_or("")
+            .to_owned(),
+        None => String::new(),
+    }
+}
+
+/// The form control that should be focused when the document is loaded: the first one with an
+/// `autofocus` attribute.
+///
+/// https://html.spec.whatwg.org/multipage/interaction.html#the-autofocus-attribute
+pub fn autofocus_target(document: &NodeRef) -> Option<NodeRef> {
+    document.descendants().find(|node| {
+        FormControlKind::of(node).is_some()
+            && node.as_element().map_or(false, |element| {
+                element
+                    .attributes
+                    .borrow()
+                    .contains(local_name!("autofocus"))
+            })
+    })
+}
+
+/// The state of the `type` attribute of the given `<input>`, lowercased.  Missing types default to
+/// `text`.
+///
+/// https://html.spec.whatwg.org/multipage/input.html#attr-input-type
+pub fn input_type(element: &ElementData) -> String {
+    element
+        .attributes
+        .borrow()
+        .get(local_name!("type"))
+        .map(|input_type| input_type.trim().to_ascii_lowercase())
+        .unwrap_or_else(|| "text".to_owned())
+}
+
+/// Whether the given checkbox or radio button is checked.  Its `checked` attribute only sets
+/// whether it's checked until the user checks or unchecks it.
+///
+/// https://html.spec.whatwg.org/multipage/input.html#concept-fe-checked
+pub fn is_checked(element: &ElementData) -> bool {
+    element
+        .checkedness
+        .get()
+        .unwrap_or_else(|| element.attributes.borrow().contains(local_name!("checked")))
+}
+
+/// The activation behavior of checkboxes and radio buttons: a checkbox toggles whether it's
+/// checked, while a radio button is checked, unchecking the other radio buttons in its group.
+/// Returns `None` if `node` isn't a checkbox or radio button that's enabled, or else whether
+/// clicking it changed its checkedness.
+///
+/// https://html.spec.whatwg.org/multipage/input.html#the-input-element:activation-behaviour
+pub fn toggle_checkedness(node: &NodeRef) -> Option<bool> {
+    let kind = FormControlKind::of(node)?;
+    let element = node.as_element()?;
+    if is_disabled(node) {
+        return None;
+    }
+    match kind {
+        FormControlKind::Checkbox => {
+            set_checkedness(node, !is_checked(element));
+            Some(true)
+        }
+        FormControlKind::Radio if is_checked(element) => Some(false),
+        FormControlKind::Radio => {
+            for other in radio_button_group(node) {
+                if other != *node {
+                    set_checkedness(&other, false);
+                }
+            }
+            set_checkedness(node, true);
+            Some(true)
+        }
+        _ => None,
+    }
+}
+
+/// Sets whether the given checkbox or radio button is checked, recording the change so that
+/// `:checked` is matched again.
+fn set_checkedness(node: &NodeRef, checked: bool) {
+    let element = node.as_element().unwrap();
+    if is_checked(element) != checked {
+        mutation::record(Mutation::State {
+            element: node.clone(),
+        });
+    }
+    element.checkedness.set(Some(checked));
+}
+
+/// The radio buttons in the same group as the given one (including itself): those in the same
+/// tree, with the same form owner, and with the same non-empty `name`.  A radio button without a
+/// name is in a group of its own.
+///
+/// https://html.spec.whatwg.org/multipage/input.html#radio-button-group
+fn radio_button_group(node: &NodeRef) -> Vec<NodeRef> {
+    let name = |node: &NodeRef| {
+        node.as_element()
+            .and_then(|element| {
+                element
+                    .attributes
+                    .borrow()
+                    .get(local_name!("name"))
+                    .map(str::to_owned)
+            })
+            .filter(|name| !name.is_empty())
+    };
+    let group_name = match name(node) {
+        Some(group_name) => group_name,
+        None => return vec![node.clone()],
+    };
+    let form = form_owner(node);
+    tree_root(node)
+        .descendants()
+        .filter(|other| {
+            FormControlKind::of(other) == Some(FormControlKind::Radio)
+                && name(other).as_ref() == Some(&group_name)
+                && form_owner(other) == form
+        })
+        .collect()
+}
+
+/// The `<form>` the given form control belongs to, if any: the one its `form` attribute names by
+/// id, or else its nearest `<form>` ancestor.
+///
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#form-owner
+pub fn form_owner(node: &NodeRef) -> Option<NodeRef> {
+    let form_id = node.as_element().and_then(|element| {
+        element
+            .attributes
+            .borrow()
+            .get(local_name!("form"))
+            .map(str::to_owned)
+    });
+    match form_id {
+        Some(form_id) => {
+            element_by_id(node, &form_id).filter(|form| is_html_element(form, local_name!("form")))
+        }
+        None => node
+            .ancestors()
+            .find(|ancestor| is_html_element(ancestor, local_name!("form"))),
+    }
+}
+
+/// The control the given `<label>` labels, if any: the element its `for` attribute names by id,
+/// or else its first descendant that's a form control.
+///
+/// TODO: `<meter>`, `<output>`, and `<progress>` can be labeled too, once they're supported.
+///
+/// https://html.spec.whatwg.org/multipage/forms.html#labeled-control
+pub fn labeled_control(label: &NodeRef) -> Option<NodeRef> {
+    if !is_html_element(label, local_name!("label")) {
+        return None;
+    }
+    let for_id = label
+        .as_element()
+        .unwrap()
+        .attributes
+        .borrow()
+        .get(local_name!("for"))
+        .map(str::to_owned);
+    match for_id {
+        Some(for_id) => {
+            element_by_id(label, &for_id).filter(|control| FormControlKind::of(control).is_some())
+        }
+        None => label
+            .descendants()
+            .find(|descendant| FormControlKind::of(descendant).is_some()),
+    }
+}
+
+/// Whether the given element is disabled, and so can't be interacted with.  Form controls and
+/// `<fieldset>`s are disabled by their `disabled` attribute or by a disabled `<fieldset>` they're
+/// in (unless they're in its first `<legend>`), and options by their own `disabled` attribute or
+/// that of their `<optgroup>`.
+///
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#concept-fe-disabled
+/// https://html.spec.whatwg.org/multipage/form-elements.html#concept-option-disabled
+pub fn is_disabled(node: &NodeRef) -> bool {
+    let element = match node.as_element() {
+        Some(element) if element.name.ns == ns!(html) => element,
+        _ => return false,
+    };
+    match element.name.local {
+        local_name!("button")
+        | local_name!("input")
+        | local_name!("select")
+        | local_name!("textarea")
+        | local_name!("fieldset") => has_disabled_attribute(node) || is_in_disabled_fieldset(node),
+        local_name!("optgroup") => has_disabled_attribute(node),
+        local_name!("option") => {
+            has_disabled_attribute(node)
+                || node.parent().map_or(false, |parent| {
+                    is_html_element(&parent, local_name!("optgroup"))
+                        && has_disabled_attribute(&parent)
+                })
+        }
+        _ => false,
+    }
+}
+
+/// Whether the given element can be disabled, so it matches `:enabled` whenever it isn't.
+///
+/// https://html.spec.whatwg.org/multipage/semantics-other.html#selector-enabled
+pub fn can_be_disabled(node: &NodeRef) -> bool {
+    node.as_element().map_or(false, |element| {
+        element.name.ns == ns!(html)
+            && matches!(
+                element.name.local,
+                local_name!("button")
+                    | local_name!("input")
+                    | local_name!("select")
+                    | local_name!("textarea")
+                    | local_name!("fieldset")
+                    | local_name!("optgroup")
+                    | local_name!("option")
+            )
+    })
+}
+
+fn has_disabled_attribute(node: &NodeRef) -> bool {
+    node.as_element().map_or(false, |element| {
+        element
+            .attributes
+            .borrow()
+            .contains(local_name!("disabled"))
+    })
+}
+
+/// Whether `node` is a descendant of a disabled `<fieldset>`, other than one in its first
+/// `<legend>`.
+fn is_in_disabled_fieldset(node: &NodeRef) -> bool {
+    let mut child = node.clone();
+    for ancestor in node.ancestors() {
+        if is_html_element(&ancestor, local_name!("fieldset")) && has_disabled_attribute(&ancestor)
+        {
+            let first_legend = ancestor
+                .children()
+                .find(|child| is_html_element(child, local_name!("legend")));
+            if first_legend.as_ref() != Some(&child) {
+                return true;
+            }
+        }
+        child = ancestor;
+    }
+    false
+}
+
+/// The root of the tree `node` is in: its shadow root if it's in a shadow tree, or else its
+/// document (or the root of the subtree it's in, if it isn't in one).
+pub(crate) fn tree_root(node: &NodeRef) -> NodeRef {
+    node.containing_shadow_root()
+        .or_else(|| node.inclusive_ancestors().last())
+        .unwrap()
+}
+
+/// The first element in the tree `node` is in whose id is `id`.
+fn element_by_id(node: &NodeRef, id: &str) -> Option<NodeRef> {
+    tree_root(node).descendants().find(|descendant| {
+        descendant.as_element().map_or(false, |element| {
+            element.attributes.borrow().get(local_name!("id")) == Some(id)
+        })
+    })
+}
+
+fn is_html_element(node: &NodeRef, local_name: LocalName) -> bool {
+    node.as_element().map_or(false, |element| {
+        element.name.ns == ns!(html) && element.name.local == local_name
+    })
+}
+
+/// The width of the given text field in characters, from its `size` attribute.
+///
+/// https://html.spec.whatwg.org/multipage/input.html#attr-input-size
+pub fn input_size(element: &ElementData) -> u32 {
+    positive_integer_attribute(element, local_name!("size")).unwrap_or(20)
+}
+
+/// The width of the given `<textarea>` in characters, from its `cols` attribute.
+///
+/// https://html.spec.whatwg.org/multipage/form-elements.html#attr-textarea-cols
+pub fn textarea_cols(element: &ElementData) -> u32 {
+    positive_integer_attribute(element, local_name!("cols")).unwrap_or(20)
+}
+
+/// The height of the given `<textarea>` in lines, from its `rows` attribute.
+///
+/// https://html.spec.whatwg.org/multipage/form-elements.html#attr-textarea-rows
+pub fn textarea_rows(element: &ElementData) -> u32 {
+    positive_integer_attribute(element, local_name!("rows")).unwrap_or(2)
+}
+
+/// The label the given button displays.  A `<button>` displays its contents, while an `<input>`
+/// button displays its value, falling back to a default label for submit and reset buttons.
+///
+/// https://html.spec.whatwg.org/multipage/input.html#submit-button-state-(type=submit)
+pub fn button_label(node: &NodeRef) -> String {
+    let element = match node.as_element() {
+        Some(element) => element,
+        None => return String::new(),
+    };
+    if element.name.local == local_name!("button") {
+        return collapse_whitespace(&node.text_contents());
+    }
+    if let Some(value) = element.attributes.borrow().get(local_name!("value")) {
+        return value.to_owned();
+    }
+    match input_type(element).as_str() {
+        "submit" => "Submit".to_owned(),
+        "reset" => "Reset".to_owned(),
+        _ => String::new(),
+    }
+}
+
+/// The labels of the options of the given `<select>`, in tree order.
+pub fn select_option_labels(node: &NodeRef) -> Vec<String> {
+    select_options(node).iter().map(option_label).collect()
+}
+
+/// The list of options of the given `<select>`: its `<option>` descendants, in tree order.
+///
+/// https://html.spec.whatwg.org/multipage/form-elements.html#concept-select-option-list
+pub fn select_options(node: &NodeRef) -> Vec<NodeRef> {
+    node.descendants()
+        .filter(|descendant| {
+            descendant
+                .as_element()
+                .map_or(false, |element| element.name.local == local_name!("option"))
+        })
+        .collect()
+}
+
+/// The label of the given `<option>`: its `label` attribute, or else its text.
+///
+/// https://html.spec.whatwg.org/multipage/form-elements.html#concept-option-label
+pub fn option_label(option: &NodeRef) -> String {
+    let label = option.as_element().and_then(|element| {
+        element
+            .attributes
+            .borrow()
+            .get(local_name!("label"))
+            .map(|label| label.to_owned())
+    });
+    label.unwrap_or_else(|| collapse_whitespace(&option.text_contents()))
+}
+
+/// The value of the given `<option>`: its `value` attribute, or else its text.
+///
+/// https://html.spec.whatwg.org/multipage/form-elements.html#concept-option-value
+pub fn option_value(option: &NodeRef) -> String {
+    let value = option.as_element().and_then(|element| {
+        element
+            .attributes
+            .borrow()
+            .get(local_name!("value"))
+            .map(|value| value.to_owned())
+    });
+    value.unwrap_or_else(|| collapse_whitespace(&option.text_contents()))
+}
+
+/// Whether the given `<select>` lets more than one option be selected at once.
+///
+/// https://html.spec.whatwg.org/multipage/form-elements.html#attr-select-multiple
+pub fn is_multiple(element: &ElementData) -> bool {
+    element
+        .attributes
+        .borrow()
+        .contains(local_name!("multiple"))
+}
+
+/// The number of options the given `<select>` shows at once, from its `size` attribute.
+///
+/// https://html.spec.whatwg.org/multipage/form-elements.html#concept-select-size
+pub fn select_display_size(element: &ElementData) -> u32 {
+    positive_integer_attribute(element, local_name!("size")).unwrap_or_else(|| {
+        if is_multiple(element) {
+            4
+        } else {
+            1
+        }
+    })
+}
+
+/// Whether the given `<select>` is rendered as a drop-down box, which shows only its selected
+/// option until it's opened, rather than as a list box showing several options.
+///
+/// https://html.spec.whatwg.org/multipage/rendering.html#the-select-element-2
+pub fn is_drop_down_box(element: &ElementData) -> bool {
+    !is_multiple(element) && select_display_size(element) == 1
+}
+
+/// The options of the given `<select>` that are selected, in tree order.  Only the last option
+/// marked as selected counts unless the `<select>` is multiple, and a drop-down box with no
+/// option marked as selected selects its first option that isn't disabled.
+///
+/// https://html.spec.whatwg.org/multipage/form-elements.html#selectedness-setting-algorithm
+pub fn selected_options(node: &NodeRef) -> Vec<NodeRef> {
+    let element = match node.as_element() {
+        Some(element) => element,
+        None => return Vec::new(),
+    };
+    let options = select_options(node);
+    let mut selected = options
+        .iter()
+        .filter(|option| is_option_selected(option))
+        .cloned()
+        .collect::<Vec<_>>();
+    if is_multiple(element) {
+        return selected;
+    }
+    match selected.pop() {
+        Some(option) => vec![option],
+        None if is_drop_down_box(element) => options
+            .into_iter()
+            .find(|option| !is_disabled(option))
+            .into_iter()
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Whether the given `<option>` is marked as selected, by the user or by its `selected` attribute.
+fn is_option_selected(option: &NodeRef) -> bool {
+    option.as_element().map_or(false, |element| {
+        element.selectedness.get().unwrap_or_else(|| {
+            element
+                .attributes
+                .borrow()
+                .contains(local_name!("selected"))
+        })
+    })
+}
+
+/// The `<select>` the given `<option>` belongs to, if any: its parent, or the parent of its
+/// `<optgroup>`.
+///
+/// https://html.spec.whatwg.org/multipage/form-elements.html#concept-option-select
+fn option_select(option: &NodeRef) -> Option<NodeRef> {
+    let is_named = |node: &NodeRef, local_name: LocalName| {
+        node.as_element()
+            .map_or(false, |element| element.name.local == local_name)
+    };
+    if !is_named(option, local_name!("option")) {
+        return None;
+    }
+    let parent = option.parent()?;
+    if is_named(&parent, local_name!("select")) {
+        return Some(parent);
+    }
+    let grandparent = parent.parent()?;
+    if is_named(&parent, local_name!("optgroup")) && is_named(&grandparent, local_name!("select")) {
+        Some(grandparent)
+    } else {
+        None
+    }
+}
+
+/// Selects the given option of `select` as if the user picked it: a multiple `<select>` toggles
+/// it, while any other selects it in place of the previously selected option.  Disabled options
+/// can't be picked.
+pub fn pick_option(select: &NodeRef, option: &NodeRef) {
+    let element = match select.as_element() {
+        Some(element) => element,
+        None => return,
+    };
+    if is_disabled(option) {
+        return;
+    }
+    let previously_selected = selected_options(select);
+    if is_multiple(element) {
+        let option_element = option.as_element().unwrap();
+        option_element
+            .selectedness
+            .set(Some(!is_option_selected(option)));
+    } else {
+        for other in select_options(select) {
+            other
+                .as_element()
+                .unwrap()
+                .selectedness
+                .set(Some(other == *option));
+        }
+    }
+    // Record the options whose selectedness changed, so that `:checked` is matched again.
+    let selected = selected_options(select);
+    for other in select_options(select) {
+        if selected.contains(&other) != previously_selected.contains(&other) {
+            mutation::record(Mutation::State { element: other });
+        }
+    }
+}
+
+/// Whether the given `<option>` is selected, in the `<select>` it belongs to if any.
+pub fn is_selected(option: &NodeRef) -> bool {
+    match option_select(option) {
+        Some(select) => selected_options(&select).contains(option),
+        None => is_option_selected(option),
+    }
+}
+
+/// The activation behavior of an option the user clicked, in the popup of an open drop-down box
+/// or in a list box, which picks it and closes the popup.  Returns the `<select>` the option was
+/// picked in, or `None` if `node` isn't a shown option.
+pub fn click_option(node: &NodeRef) -> Option<NodeRef> {
+    let select = option_select(node)?;
+    let element = select.as_element()?;
+    if is_disabled(&select) || (is_drop_down_box(element) && !element.dropdown_open.get()) {
+        return None;
+    }
+    pick_option(&select, node);
+    element.dropdown_open.set(false);
+    Some(select)
+}
+
+/// The activation behavior of a drop-down box, which opens the popup listing its options if it's
+/// closed and closes it if it's open.  Returns whether `node` was an enabled drop-down box.
+pub fn toggle_dropdown(node: &NodeRef) -> bool {
+    match node.as_element() {
+        Some(element)
+            if FormControlKind::of(node) == Some(FormControlKind::Select)
+                && is_drop_down_box(element)
+                && !is_disabled(node) =>
+        {
+            element.dropdown_open.set(!element.dropdown_open.get());
+            true
+        }
+        _ => false,
+    }
+}
+
+/// https://html.spec.whatwg.org/multipage/common-microsyntaxes.html#rules-for-parsing-non-negative-integers
+fn positive_integer_attribute(element: &ElementData, local_name: LocalName) -> Option<u32> {
+    element
+        .attributes
+        .borrow()
+        .get(local_name)
+        .and_then(|value| value.trim().parse::<u32>().ok())
+        .filter(|value| *value > 0)
+}
+
+/// https://infra.spec.whatwg.org/#strip-and-collapse-ascii-whitespace
+pub(crate) fn collapse_whitespace(text: &str) -> String {
+    text.split_ascii_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::parser::parse_html;
+    use crate::dom::traits::*;
+
+    fn first_match(html: &str, selector: &str) -> NodeRef {
+        let document = parse_html().one(html);
+        document.select_first(selector).unwrap().as_node().clone()
+    }
+
+    #[test]
+    fn classifies_form_controls() {
+        let kind_of = |html: &str| {
+            FormControlKind::of(&first_match(html, "input, button, textarea, select, div"))
+        };
+        assert_eq!(kind_of("<input>"), Some(FormControlKind::TextField));
+        assert_eq!(
+            kind_of("<input type=EMAIL>"),
+            Some(FormControlKind::TextField)
+        );
+        assert_eq!(
+            kind_of("<input type=checkbox>"),
+            Some(FormControlKind::Checkbox)
+        );
+        assert_eq!(kind_of("<input type=radio>"), Some(FormControlKind::Radio));
+        assert_eq!(kind_of("<input type=reset>"), Some(FormControlKind::Button));
+        assert_eq!(kind_of("<input type=hidden>"), None);
+        assert_eq!(
+            kind_of("<button>Go</button>"),
+            Some(FormControlKind::Button)
+        );
+        assert_eq!(
+            kind_of("<textarea></textarea>"),
+            Some(FormControlKind::TextArea)
+        );
+        assert_eq!(kind_of("<select></select>"), Some(FormControlKind::Select));
+        assert_eq!(kind_of("<div></div>"), None);
+    }
+
+    #[test]
+    fn reads_control_labels_and_sizes() {
+        let submit = first_match("<input type=submit>", "input");
+        assert_eq!(button_label(&submit), "Submit");
+        let button = first_match("<button>  Save\n draft </button>", "button");
+        assert_eq!(button_label(&button), "Save draft");
+        let select = first_match(
+            "<select><option>One</option><option label=Two>2</option></select>",
+            "select",
+        );
+        assert_eq!(select_option_labels(&select), vec!["One", "Two"]);
+        let input = first_match("<input size=0>", "input");
+        assert_eq!(input_size(input.as_element().unwrap()), 20);
+        let textarea = first_match("<textarea cols=40 rows=5></textarea>", "textarea");
+        assert_eq!(textarea_cols(textarea.as_element().unwrap()), 40);
+        assert_eq!(textarea_rows(textarea.as_element().unwrap()), 5);
+    }
+
+    #[test]
+    fn selects_options() {
+        let selected_labels = |select: &NodeRef| {
+            selected_options(select)
+                .iter()
+                .map(option_label)
+                .collect::<Vec<_>>()
+        };
+        // Only the last option marked as selected is, and drop-down boxes fall back to their
+        // first enabled option.
+        let select = first_match(
+            "<select><option>A<option selected>B<option selected>C</select>",
+            "select",
+        );
+        assert_eq!(selected_labels(&select), vec!["C"]);
+        let select = first_match(
+            "<select><optgroup disabled><option>A</optgroup><option>B</select>",
+            "select",
+        );
+        assert_eq!(selected_labels(&select), vec!["B"]);
+        let options = select_options(&select);
+        pick_option(&select, &options[0]);
+        assert_eq!(selected_labels(&select), vec!["B"]);
+
+        // List boxes can have nothing selected.
+        let select = first_match("<select size=3><option>A<option>B</select>", "select");
+        assert!(!is_drop_down_box(select.as_element().unwrap()));
+        assert!(selected_labels(&select).is_empty());
+        assert_eq!(
+            click_option(&select_options(&select)[1]),
+            Some(select.clone())
+        );
+        assert_eq!(selected_labels(&select), vec!["B"]);
+
+        // The options of a closed drop-down box can't be clicked.
+        let select = first_match("<select><option>A<option>B</select>", "select");
+        let options = select_options(&select);
+        assert_eq!(click_option(&options[1]), None);
+        assert!(toggle_dropdown(&select));
+        assert_eq!(click_option(&options[1]), Some(select.clone()));
+        assert_eq!(selected_labels(&select), vec!["B"]);
+        assert!(!select.as_element().unwrap().dropdown_open.get());
+
+        let select = first_match(
+            "<select multiple><option selected>A<option>B<option selected>C</select>",
+            "select",
+        );
+        assert_eq!(select_display_size(select.as_element().unwrap()), 4);
+        assert!(!toggle_dropdown(&select));
+        let options = select_options(&select);
+        pick_option(&select, &options[0]);
+        pick_option(&select, &options[1]);
+        assert_eq!(selected_labels(&select), vec!["B", "C"]);
+    }
+
+    #[test]
+    fn toggles_checkboxes_and_radio_buttons() {
+        let document = parse_html().one(
+            "<form><input type=checkbox id=box checked>\
+             <input type=radio name=r id=r1 checked><input type=radio name=r id=r2></form>\
+             <input type=radio name=r id=r3 checked>\
+             <fieldset disabled><input type=checkbox id=disabled></fieldset>",
+        );
+        let select = |selector: &str| document.select_first(selector).unwrap().as_node().clone();
+        let checked_ids = || {
+            document
+                .select_str("input:checked")
+                .unwrap()
+                .map(|input| input.attributes.borrow().get("id").unwrap().to_owned())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(checked_ids(), vec!["box", "r1", "r3"]);
+        assert_eq!(toggle_checkedness(&select("#box")), Some(true));
+        // Clicking a checked radio button leaves it checked.
+        assert_eq!(toggle_checkedness(&select("#r1")), Some(false));
+        // Radio buttons in another form are in another group.
+        assert_eq!(toggle_checkedness(&select("#r2")), Some(true));
+        assert_eq!(checked_ids(), vec!["r2", "r3"]);
+        // The `checked` attribute is only the default.
+        assert!(select("#box")
+            .as_element()
+            .unwrap()
+            .attributes
+            .borrow()
+            .contains("checked"));
+
+        assert!(is_disabled(&select("#disabled")));
+        assert_eq!(toggle_checkedness(&select("#disabled")), None);
+        assert_eq!(
+            document.select_str("fieldset, :disabled").unwrap().count(),
+            2,
+        );
+        assert_eq!(document.select_str(":enabled").unwrap().count(), 4);
+        assert_eq!(toggle_checkedness(&select("form")), None);
+    }
+
+    #[test]
+    fn finds_labeled_controls_and_form_owners() {
+        let document = parse_html().one(
+            "<label id=wrapping>Name <input id=name></label>\
+             <label id=pointing for=email>Email</label><input id=email form=f>\
+             <label id=unlabeled for=nothing></label><form id=f></form>",
+        );
+        let select = |selector: &str| document.select_first(selector).unwrap().as_node().clone();
+        assert_eq!(labeled_control(&select("#wrapping")), Some(select("#name")));
+        assert_eq!(
+            labeled_control(&select("#pointing")),
+            Some(select("#email"))
+        );
+        assert_eq!(labeled_control(&select("#unlabeled")), None);
+        assert_eq!(form_owner(&select("#email")), Some(select("#f")));
+        assert_eq!(form_owner(&select("#name")), None);
+    }
+
+    #[test]
+    fn edits_start_from_default_value() {
+        let input = first_match("<input value=abc>", "input");
+        assert_eq!(value(&input), Some("abc".to_owned()));
+        edit(&input, EditCommand::DeleteBackward);
+        edit(&input, EditCommand::Insert('!'));
+        assert_eq!(value(&input), Some("ab!".to_owned()));
+        // The default value is left untouched.
+        let element = input.as_element().unwrap();
+        assert_eq!(element.attributes.borrow().get("value"), Some("abc"));
+
+        let textarea = first_match("<textarea>one\ntwo</textarea>", "textarea");
+        assert_eq!(value(&textarea), Some("one\ntwo".to_owned()));
+        let checkbox = first_match("<input type=checkbox value=on>", "input");
+        assert_eq!(value(&checkbox), None);
+    }
+}
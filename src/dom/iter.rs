@@ -157,6 +157,22 @@ impl NodeRef {
         }
     }
 
+    /// Like `traverse_inclusive`, but also traverses the shadow trees of shadow hosts, in
+    /// shadow-including tree order.
+    #[inline]
+    pub fn shadow_including_traverse_inclusive(&self) -> ShadowIncludingTraverse {
+        ShadowIncludingTraverse(vec![self.traverse_inclusive()])
+    }
+
+    /// Like `inclusive_descendants`, but also includes the shadow-including descendants of shadow
+    /// hosts: their shadow roots, and the nodes of their shadow trees.
+    ///
+    /// https://dom.spec.whatwg.org/#concept-shadow-including-inclusive-descendant
+    #[inline]
+    pub fn shadow_including_inclusive_descendants(&self) -> ShadowIncludingDescendants {
+        ShadowIncludingDescendants(self.shadow_including_traverse_inclusive())
+    }
+
     /// Return an iterator of the inclusive descendants element that match the given selector list.
     #[inline]
     pub fn select(&self, selectors: &Selectors) -> Select<Elements<Descendants>> {
@@ -331,6 +347,55 @@ impl DoubleEndedIterator for Traverse {
     traverse_next!(next_back, next, last_child, previous_sibling, End, Start);
 }
 
+/// An iterator of the start and end edges of the nodes in a given subtree and in the shadow trees
+/// of its shadow hosts.  The edges of a host's shadow tree (starting and ending with its shadow
+/// root) come right after the start of the host, before those of its children.
+///
+/// https://dom.spec.whatwg.org/#concept-shadow-including-tree-order
+#[derive(Debug, Clone)]
+pub struct ShadowIncludingTraverse(Vec<Traverse>);
+
+impl Iterator for ShadowIncludingTraverse {
+    type Item = NodeEdge<NodeRef>;
+
+    fn next(&mut self) -> Option<NodeEdge<NodeRef>> {
+        loop {
+            let edge = match self.0.last_mut()?.next() {
+                Some(edge) => edge,
+                None => {
+                    // Done with a shadow tree, so carry on with its host's children.
+                    self.0.pop();
+                    continue;
+                }
+            };
+            if let NodeEdge::Start(ref node) = edge {
+                if let Some(shadow_root) = node.shadow_root() {
+                    self.0.push(shadow_root.traverse_inclusive());
+                }
+            }
+            return Some(edge);
+        }
+    }
+}
+
+/// An iterator of references to a given node and its shadow-including descendants, in
+/// shadow-including tree order.
+#[derive(Debug, Clone)]
+pub struct ShadowIncludingDescendants(ShadowIncludingTraverse);
+
+impl Iterator for ShadowIncludingDescendants {
+    type Item = NodeRef;
+
+    fn next(&mut self) -> Option<NodeRef> {
+        loop {
+            match self.0.next()? {
+                NodeEdge::Start(node) => return Some(node),
+                NodeEdge::End(_) => {}
+            }
+        }
+    }
+}
+
 macro_rules! filter_map_like_iterator {
     (#[$doc: meta] $name: ident: $f: expr, $from: ty => $to: ty) => {
         #[$doc]
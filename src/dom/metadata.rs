@@ -0,0 +1,85 @@
+use crate::dom::iter::NodeIterator;
+use crate::dom::tree::NodeRef;
+
+/// The title of `document`: the child text of its first `<title>` element, with leading and
+/// trailing whitespace stripped and runs of whitespace collapsed into single spaces.  `None` if
+/// the document has no `<title>`.
+///
+/// https://html.spec.whatwg.org/multipage/dom.html#document.title
+pub fn document_title(document: &NodeRef) -> Option<String> {
+    let title = document.descendants().find(|node| {
+        node.as_element().map_or(false, |element| {
+            element.name.ns == ns!(html) && element.name.local == local_name!("title")
+        })
+    })?;
+    let child_text = title
+        .children()
+        .text_nodes()
+        .map(|text| text.borrow().clone())
+        .collect::<String>();
+    Some(
+        child_text
+            .split_ascii_whitespace()
+            .collect::<Vec<_>>()
+            .join(" "),
+    )
+}
+
+/// The `href` of the icon `document` declares with `<link rel=icon>`, if any.  When there are
+/// several, the last one in tree order is used.
+///
+/// https://html.spec.whatwg.org/multipage/links.html#rel-icon
+pub fn icon_href(document: &NodeRef) -> Option<String> {
+    document
+        .descendants()
+        .filter_map(|node| {
+            let element = node.as_element()?;
+            if element.name.ns != ns!(html) || element.name.local != local_name!("link") {
+                return None;
+            }
+            let attributes = element.attributes.borrow();
+            // `rel` is a set of space-separated, ASCII case-insensitive keywords.
+            let is_icon = attributes.get(local_name!("rel")).map_or(false, |rel| {
+                rel.split_ascii_whitespace()
+                    .any(|keyword| keyword.eq_ignore_ascii_case("icon"))
+            });
+            if !is_icon {
+                return None;
+            }
+            attributes
+                .get(local_name!("href"))
+                .map(|href| href.trim().to_owned())
+                .filter(|href| !href.is_empty())
+        })
+        .last()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::parser::parse_html;
+    use crate::dom::traits::*;
+
+    #[test]
+    fn title_collapses_whitespace() {
+        let document = parse_html().one("<title>\n  A   page\tabout\n  things </title>");
+        assert_eq!(
+            document_title(&document),
+            Some("A page about things".to_owned())
+        );
+        let svg_title = parse_html().one("<body><svg><title>Not a page title</title></svg>");
+        assert_eq!(document_title(&svg_title), None);
+    }
+
+    #[test]
+    fn last_icon_link_wins() {
+        let document = parse_html().one(
+            "<link rel=stylesheet href=style.css>\
+             <link rel=\"shortcut ICON\" href=old.ico>\
+             <link rel=icon href=\" new.png \">\
+             <link rel=icon>",
+        );
+        assert_eq!(icon_href(&document), Some("new.png".to_owned()));
+        assert_eq!(icon_href(&parse_html().one("<p>No icons</p>")), None);
+    }
+}
@@ -5,10 +5,21 @@
 /// Kuchiki where it makes sense.  Thanks to the authors of Kuchiki for their work.
 pub mod attributes;
 pub mod cell_extras;
+pub mod details;
+pub mod editing;
+pub mod event;
+pub mod focus;
+pub mod form;
 pub mod iter;
+pub mod metadata;
+pub mod mutation;
+pub mod navigation;
 pub mod node_data_ref;
 pub mod parser;
+pub mod selection;
 pub mod serializer;
+pub mod shadow;
+pub mod submission;
 #[cfg(test)]
 pub mod tests;
 pub mod tree;
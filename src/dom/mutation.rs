@@ -0,0 +1,103 @@
+use html5ever::LocalName;
+
+use crate::dom::tree::NodeRef;
+
+/// A change to a document's tree, which may affect the styles of the nodes in it.  Changes are
+/// recorded as they're made (whether by scripts or the browser itself), and handled in a batch
+/// before the document is next painted.
+///
+/// https://dom.spec.whatwg.org/#interface-mutationrecord
+#[derive(Clone, Debug, PartialEq)]
+pub enum Mutation {
+    /// The attribute `name` of `element` was set, changed, or removed.
+    Attribute {
+        element: NodeRef,
+        name: LocalName,
+        /// The value of the attribute before it changed, if it was set.
+        old_value: Option<String>,
+    },
+    /// Children were inserted into or removed from `parent`.
+    ChildList {
+        parent: NodeRef,
+        /// The nodes that were inserted, which are yet to be styled.
+        added: Vec<NodeRef>,
+    },
+    /// The data of a text, comment, or processing instruction node changed.
+    CharacterData { node: NodeRef },
+    /// State of `element` that isn't in its attributes, but that pseudo-classes match (e.g.
+    /// whether a checkbox is checked), changed.
+    State { element: NodeRef },
+}
+
+impl Mutation {
+    /// The node that changed.
+    pub fn target(&self) -> &NodeRef {
+        match self {
+            Mutation::Attribute { element, .. } => element,
+            Mutation::ChildList { parent, .. } => parent,
+            Mutation::CharacterData { node } => node,
+            Mutation::State { element } => element,
+        }
+    }
+}
+
+/// Records `mutation` with the document its target is in.  Changes to nodes that aren't in a
+/// document are dropped, since the nodes will be styled from scratch if they're ever inserted into
+/// one.
+pub fn record(mutation: Mutation) {
+    let root = mutation
+        .target()
+        .inclusive_ancestors()
+        .last()
+        .expect("a node is always its own inclusive ancestor");
+    if let Some(document) = root.as_document() {
+        document.mutations.borrow_mut().push(mutation);
+    }
+}
+
+/// Takes the mutations recorded in `document` since they were last taken, in the order they were
+/// made.
+pub fn take_mutations(document: &NodeRef) -> Vec<Mutation> {
+    document
+        .as_document()
+        .map(|document| document.mutations.borrow_mut().drain(..).collect())
+        .unwrap_or_default()
+}
+
+/// Whether any mutations have been recorded in `document` since they were last taken.
+pub fn has_mutations(document: &NodeRef) -> bool {
+    document
+        .as_document()
+        .map_or(false, |document| !document.mutations.borrow().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::parser::parse_html;
+    use crate::dom::traits::*;
+
+    #[test]
+    fn only_records_mutations_of_connected_nodes() {
+        let document = parse_html().one("<p>Hello</p>");
+        let paragraph = document.select_first("p").unwrap().as_node().clone();
+        record(Mutation::CharacterData {
+            node: paragraph.first_child().unwrap(),
+        });
+        paragraph.detach();
+        record(Mutation::Attribute {
+            element: paragraph.clone(),
+            name: local_name!("id"),
+            old_value: None,
+        });
+        assert!(has_mutations(&document));
+        assert_eq!(
+            take_mutations(&document),
+            vec![Mutation::CharacterData {
+                node: paragraph.first_child().unwrap()
+            }]
+        );
+        assert!(!has_mutations(&document));
+        assert!(take_mutations(&document).is_empty());
+    }
+}
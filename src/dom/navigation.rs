@@ -0,0 +1,376 @@
+use crate::dom::mutation::{self, Mutation};
+use crate::dom::tree::{ElementData, NodeRef};
+use html5ever::LocalName;
+use std::path::{Path, PathBuf};
+
+/// Where following a hyperlink leads.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LinkTarget {
+    /// Another document (or a reload of the current one), at the given local path.
+    Document(PathBuf),
+    /// A fragment of the current document, e.g. `#top`.  Navigating to a fragment doesn't load a
+    /// new document.
+    Fragment(String),
+}
+
+/// The part of a document a fragment (e.g. `#intro`) indicates, which navigating to the fragment
+/// scrolls to.
+///
+/// https://html.spec.whatwg.org/multipage/browsing-the-web.html#the-indicated-part-of-the-document
+#[derive(Clone, Debug, PartialEq)]
+pub enum IndicatedPart {
+    /// The top of the document, which empty fragments and `#top` indicate.
+    TopOfDocument,
+    Element(NodeRef),
+}
+
+#[derive(Debug)]
+pub enum NavigationError {
+    /// The link's URL uses a scheme we can't navigate to, such as `https:` or `javascript:`.
+    UnsupportedScheme(String),
+}
+
+/// The `href` of `node` if it's a hyperlink, i.e. an `<a>` or `<area>` with an `href` attribute.
+///
+/// https://html.spec.whatwg.org/multipage/links.html#hyperlink
+pub fn hyperlink_href(node: &NodeRef) -> Option<String> {
+    let element = node.as_element()?;
+    if element.name.ns != ns!(html)
+        || !matches!(element.name.local, local_name!("a") | local_name!("area"))
+    {
+        return None;
+    }
+    element
+        .attributes
+        .borrow()
+        .get(local_name!("href"))
+        .map(|href| href.to_owned())
+}
+
+/// Resolves `href` against the base URL of `document`, which was loaded from `document_path`.
+/// The base URL is the `href` of the document's first `<base>` element, if it has one, or else
+/// the document's own path.
+///
+/// Only local documents are supported for now, so URLs are resolved to file paths.  Query strings
+/// are ignored, since there is nothing to send them to.
+///
+/// https://html.spec.whatwg.org/multipage/links.html#following-hyperlinks-2
+/// https://html.spec.whatwg.org/multipage/urls-and-fetching.html#document-base-url
+pub fn resolve_link(
+    document: &NodeRef,
+    document_path: &Path,
+    href: &str,
+) -> Result<LinkTarget, NavigationError> {
+    let href = href.trim_matches(|ch: char| ch.is_ascii_whitespace());
+    let (url, fragment) = match href.find('#') {
+        Some(fragment_start) => (&href[..fragment_start], Some(&href[fragment_start + 1..])),
+        None => (href, None),
+    };
+    let url = url.split('?').next().unwrap_or("");
+    if url.is_empty() {
+        return Ok(match fragment {
+            Some(fragment) => LinkTarget::Fragment(fragment.to_owned()),
+            None => LinkTarget::Document(document_path.to_path_buf()),
+        });
+    }
+    let base_path = match base_href(document) {
+        Some(base_href) => resolve_path(document_path, &base_href)?,
+        None => document_path.to_path_buf(),
+    };
+    let mut path = resolve_path(&base_path, url)?;
+    if url.ends_with('/') {
+        // A link to a directory loads its index, as file servers conventionally do.
+        path.push("index.html");
+    }
+    Ok(match fragment {
+        Some(fragment) if path == document_path => LinkTarget::Fragment(fragment.to_owned()),
+        _ => LinkTarget::Document(path),
+    })
+}
+
+/// The part of `document` that `fragment` indicates: the first element whose ID is `fragment`
+/// (before or after percent-decoding it), or failing that, the first `<a>` whose `name` is, or
+/// the top of the document if `fragment` is empty or `top`.  `None` if `fragment` indicates no
+/// part of the document.
+///
+/// https://html.spec.whatwg.org/multipage/browsing-the-web.html#find-a-potential-indicated-element
+pub fn indicated_part(document: &NodeRef, fragment: &str) -> Option<IndicatedPart> {
+    if fragment.is_empty() {
+        return Some(IndicatedPart::TopOfDocument);
+    }
+    let find_element = |matches: &dyn Fn(&ElementData) -> bool| {
+        document
+            .descendants()
+            .find(|node| node.as_element().map_or(false, |element| matches(element)))
+            .map(IndicatedPart::Element)
+    };
+    let attribute_is = |element: &ElementData, name: LocalName, value: &str| {
+        element.attributes.borrow().get(name) == Some(value)
+    };
+    let decoded_fragment = percent_decode(fragment);
+    find_element(&|element| attribute_is(element, local_name!("id"), fragment))
+        .or_else(|| {
+            find_element(&|element| attribute_is(element, local_name!("id"), &decoded_fragment))
+        })
+        .or_else(|| {
+            find_element(&|element| {
+                element.name.expanded() == expanded_name!(html "a")
+                    && attribute_is(element, local_name!("name"), &decoded_fragment)
+            })
+        })
+        .or_else(|| {
+            if decoded_fragment.eq_ignore_ascii_case("top") {
+                Some(IndicatedPart::TopOfDocument)
+            } else {
+                None
+            }
+        })
+}
+
+/// The fragment of `url` (after its `#`), if it has one.
+pub fn url_fragment(url: &str) -> Option<&str> {
+    let url = url.trim_matches(|ch: char| ch.is_ascii_whitespace());
+    url.find('#')
+        .map(|fragment_start| &url[fragment_start + 1..])
+}
+
+/// Navigates `document` to `fragment`, making the element it indicates (if any) the document's
+/// target element, and returns the part of the document it indicates, which should be scrolled
+/// to once the document is next laid out.
+///
+/// https://html.spec.whatwg.org/multipage/browsing-the-web.html#scroll-to-the-fragment-identifier
+pub fn navigate_to_fragment(document: &NodeRef, fragment: &str) -> Option<IndicatedPart> {
+    let indicated_part = indicated_part(document, fragment);
+    let target_element = match &indicated_part {
+        Some(IndicatedPart::Element(element)) => Some(element.clone()),
+        _ => None,
+    };
+    set_target_element(document, target_element);
+    indicated_part
+}
+
+/// Sets the target element of `document`, which matches `:target`, recording the change of state
+/// of the previous and new target elements.
+fn set_target_element(document: &NodeRef, element: Option<NodeRef>) {
+    let document_data = match document.as_document() {
+        Some(document_data) => document_data,
+        None => return,
+    };
+    let previous = document_data.target_element.replace(element.clone());
+    if previous == element {
+        return;
+    }
+    for changed in previous.into_iter().chain(element) {
+        mutation::record(Mutation::State { element: changed });
+    }
+}
+
+/// Whether `node` is the target element of its document, i.e. whether it matches `:target`.
+///
+/// https://drafts.csswg.org/selectors-4/#the-target-pseudo
+pub fn is_target_element(node: &NodeRef) -> bool {
+    let root = node
+        .inclusive_ancestors()
+        .last()
+        .expect("a node is always its own inclusive ancestor");
+    root.as_document().map_or(false, |document| {
+        document.target_element.borrow().as_ref() == Some(node)
+    })
+}
+
+/// Decodes the percent-encoded bytes of `input` (e.g. `%20` for a space), leaving any `%` not
+/// followed by two hexadecimal digits as it is.  Bytes that aren't valid UTF-8 once decoded are
+/// replaced with U+FFFD.
+///
+/// https://url.spec.whatwg.org/#percent-decode
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex_digits = bytes
+            .get(i + 1..i + 3)
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .filter(|digits| digits.chars().all(|c| c.is_ascii_hexdigit()));
+        match (bytes[i], hex_digits) {
+            (b'%', Some(digits)) => {
+                decoded.push(u8::from_str_radix(digits, 16).unwrap());
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// The `href` of the first `<base>` element with one.
+///
+/// https://html.spec.whatwg.org/multipage/semantics.html#frozen-base-url
+fn base_href(document: &NodeRef) -> Option<String> {
+    document.descendants().find_map(|node| {
+        let element = node.as_element()?;
+        if element.name.ns != ns!(html) || element.name.local != local_name!("base") {
+            return None;
+        }
+        element
+            .attributes
+            .borrow()
+            .get(local_name!("href"))
+            .map(|href| href.trim().to_owned())
+    })
+}
+
+/// Resolves the relative or `file:` URL `url` against `base_path`.  A base path ending in a slash
+/// names a directory; otherwise `url` is resolved against the directory containing it.
+fn resolve_path(base_path: &Path, url: &str) -> Result<PathBuf, NavigationError> {
+    if let Some(file_path) = url.strip_prefix("file://") {
+        return Ok(PathBuf::from(file_path));
+    }
+    if let Some(scheme_end) = url.find(':') {
+        return Err(NavigationError::UnsupportedScheme(
+            url[..scheme_end].to_owned(),
+        ));
+    }
+    let base_dir = if base_path.to_string_lossy().ends_with('/') {
+        base_path
+    } else {
+        base_path.parent().unwrap_or_else(|| Path::new(""))
+    };
+    Ok(base_dir.join(url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::parser::parse_html;
+    use crate::dom::traits::*;
+
+    fn resolve(html: &str, href: &str) -> Result<LinkTarget, NavigationError> {
+        let document = parse_html().one(html);
+        resolve_link(&document, Path::new("site/docs/page.html"), href)
+    }
+
+    fn document(path: &str) -> LinkTarget {
+        LinkTarget::Document(PathBuf::from(path))
+    }
+
+    #[test]
+    fn resolves_links_against_document_path() {
+        assert_eq!(
+            resolve("", "other.html?q=1#top").unwrap(),
+            document("site/docs/other.html")
+        );
+        assert_eq!(
+            resolve("", "../index.html").unwrap(),
+            document("site/docs/../index.html")
+        );
+        assert_eq!(
+            resolve("", "sub/").unwrap(),
+            document("site/docs/sub/index.html")
+        );
+        assert_eq!(
+            resolve("", "file:///tmp/a.html").unwrap(),
+            document("/tmp/a.html")
+        );
+        assert!(matches!(
+            resolve("", "https://example.com"),
+            Err(NavigationError::UnsupportedScheme(scheme)) if scheme == "https"
+        ));
+    }
+
+    #[test]
+    fn fragments_of_current_document_do_not_load_documents() {
+        assert_eq!(
+            resolve("", "#intro").unwrap(),
+            LinkTarget::Fragment("intro".to_owned())
+        );
+        assert_eq!(
+            resolve("", "page.html#intro").unwrap(),
+            LinkTarget::Fragment("intro".to_owned())
+        );
+        assert_eq!(resolve("", " ").unwrap(), document("site/docs/page.html"));
+    }
+
+    #[test]
+    fn finds_the_indicated_part_of_the_document() {
+        let document = parse_html().one(
+            "<p id=top>Top</p><a name=intro>Named</a><div id='a b'></div>\
+             <section id=%41></section><section id=A></section><p id=intro>Intro</p>",
+        );
+        let element = |selector: &str| {
+            Some(IndicatedPart::Element(
+                document.select_first(selector).unwrap().as_node().clone(),
+            ))
+        };
+        assert_eq!(
+            indicated_part(&document, ""),
+            Some(IndicatedPart::TopOfDocument)
+        );
+        // IDs take precedence over the names of anchors, and over `#top`.
+        assert_eq!(indicated_part(&document, "intro"), element("p:last-child"));
+        assert_eq!(indicated_part(&document, "top"), element("p"));
+        assert_eq!(indicated_part(&document, "a%20b"), element("div"));
+        // Fragments are only decoded if they don't match an ID as they are.
+        assert_eq!(indicated_part(&document, "%41"), element("section"));
+        assert_eq!(indicated_part(&document, "%4"), None);
+        assert_eq!(indicated_part(&document, "TOP%"), None);
+    }
+
+    #[test]
+    fn fragments_make_the_elements_they_indicate_targets() {
+        let document = parse_html().one("<p id=a>A</p><p id=b>B</p>");
+        let paragraph = |id| document.select_first(id).unwrap().as_node().clone();
+        assert_eq!(url_fragment("page.html#b"), Some("b"));
+        assert_eq!(url_fragment("page.html"), None);
+
+        navigate_to_fragment(&document, "a");
+        assert!(is_target_element(&paragraph("#a")));
+        assert_eq!(document.select_str(":target").unwrap().count(), 1);
+        mutation::take_mutations(&document);
+        navigate_to_fragment(&document, "b");
+        assert!(!is_target_element(&paragraph("#a")));
+        assert_eq!(
+            document.select_first(":target").unwrap().as_node(),
+            &paragraph("#b")
+        );
+        // Both the previous and the new target changed state.
+        assert_eq!(mutation::take_mutations(&document).len(), 2);
+        assert_eq!(
+            navigate_to_fragment(&document, "top"),
+            Some(IndicatedPart::TopOfDocument)
+        );
+        assert_eq!(document.select_str(":target").unwrap().count(), 0);
+    }
+
+    #[test]
+    fn resolves_links_against_base_element() {
+        let html = "<head><base target=_blank><base href=../assets/></head>";
+        assert_eq!(
+            resolve(html, "next.html").unwrap(),
+            document("site/docs/../assets/next.html")
+        );
+        let html = "<head><base href=/srv/root.html></head>";
+        assert_eq!(
+            resolve(html, "next.html").unwrap(),
+            document("/srv/next.html")
+        );
+    }
+
+    #[test]
+    fn only_anchors_and_areas_with_href_are_hyperlinks() {
+        let document =
+            parse_html().one("<a href=x.html>x</a><a>y</a><link href=z.css><area href=w.html>");
+        let hrefs = document
+            .descendants()
+            .map(|node| hyperlink_href(&node))
+            .filter(Option::is_some)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            hrefs,
+            vec![Some("x.html".to_owned()), Some("w.html".to_owned())]
+        );
+    }
+}
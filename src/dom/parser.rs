@@ -1,12 +1,19 @@
+use html5ever::tendril::fmt::UTF8;
 /// This file is a direct copy-paste from [Kuchiki](https://github.com/kuchiki-rs/kuchiki/blob/master/src/parser.rs).
 /// Thanks to the authors of Kuchiki for their work.
-use html5ever::tendril::StrTendril;
-use html5ever::tree_builder::{ElementFlags, NodeOrText, QuirksMode, TreeSink};
+use html5ever::tendril::stream::Utf8LossyDecoder;
+use html5ever::tendril::{ByteTendril, StrTendril, TendrilSink};
+use html5ever::tokenizer::TokenizerResult;
+use html5ever::tree_builder::{ElementFlags, NextParserState, NodeOrText, QuirksMode, TreeSink};
 use html5ever::{self, Attribute, ExpandedName, QualName};
 use std::borrow::Cow;
+use std::path::Path;
 
 use crate::dom::attributes;
+use crate::dom::mutation::{self, Mutation};
+use crate::dom::shadow::attach_declarative_shadow_roots;
 use crate::dom::tree::NodeRef;
+use crate::script::{ScriptEngine, ScriptEngineSlot};
 
 /// Options for the HTML parser.
 #[derive(Default)]
@@ -19,35 +26,215 @@ pub struct ParseOpts {
 
     /// A callback for HTML parse errors (which are never fatal).
     pub on_parse_error: Option<Box<dyn FnMut(Cow<'static, str>)>>,
+
+    /// Where to keep the engine classic `<script>` elements are executed with as they're parsed,
+    /// so that it outlives parsing.  Scripts aren't executed if this is `None`.
+    pub script_engine: Option<ScriptEngineSlot>,
+
+    /// Whether to record the changes the parser makes to the document as it builds it (see
+    /// `mutation::record`), so that what it parses can be restyled and laid out incrementally,
+    /// like the changes scripts make.
+    pub record_mutations: bool,
 }
 
 /// Parse an HTML document with html5ever and the default configuration.
-pub fn parse_html() -> html5ever::Parser<Sink> {
+pub fn parse_html() -> Parser {
     parse_html_with_options(ParseOpts::default())
 }
 
 /// Parse an HTML document with html5ever with custom configuration.
-pub fn parse_html_with_options(opts: ParseOpts) -> html5ever::Parser<Sink> {
+pub fn parse_html_with_options(opts: ParseOpts) -> Parser {
     let sink = Sink {
         document_node: NodeRef::new_document(),
         on_parse_error: opts.on_parse_error,
+        script_engine: opts.script_engine,
+        record_mutations: opts.record_mutations,
     };
     let html5opts = html5ever::ParseOpts {
         tokenizer: opts.tokenizer,
         tree_builder: opts.tree_builder,
     };
-    html5ever::parse_document(sink, html5opts)
+    Parser {
+        inner: html5ever::parse_document(sink, html5opts),
+    }
+}
+
+/// Parse an XML document (e.g. an XHTML one) with xml5ever.  Elements are in the namespaces
+/// their `xmlns` attributes declare, so those of XHTML documents are only HTML elements if
+/// they're in the XHTML namespace.
+///
+/// TODO: Scripts aren't run in XML documents.
+pub fn parse_xml() -> xml5ever::driver::XmlParser<Sink> {
+    let sink = Sink {
+        document_node: NodeRef::new_document(),
+        on_parse_error: None,
+        script_engine: None,
+        record_mutations: false,
+    };
+    xml5ever::driver::parse_document(sink, xml5ever::driver::XmlParseOpts::default())
+}
+
+/// Whether the file at `path` is an XML document, and so should be parsed with `parse_xml`
+/// rather than the HTML parser, judging by its extension.  Documents are loaded from files rather
+/// than served with a MIME type, so this is how `application/xhtml+xml` documents are recognized.
+pub fn is_xml_document(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .map_or(false, |extension| {
+            matches!(
+                extension.to_ascii_lowercase().as_str(),
+                "xhtml" | "xht" | "xml"
+            )
+        })
+}
+
+/// An HTML parser, which is fed the source of a document through its `TendrilSink` methods (e.g.
+/// `one` or `from_utf8().read_from(...)`).
+///
+/// When scripts are enabled, the parser pauses at the end of each `<script>` element to run it,
+/// then parses the markup the script inserted with `document.write` before the rest of its input.
+///
+/// https://html.spec.whatwg.org/multipage/parsing.html#scriptEndTag
+pub struct Parser {
+    inner: html5ever::Parser<Sink>,
+}
+
+impl Parser {
+    /// Tokenizes the input fed so far, running each script the tree builder pauses at.
+    fn run(&mut self) {
+        let html5ever::Parser {
+            tokenizer,
+            input_buffer,
+            ..
+        } = &mut self.inner;
+        while let TokenizerResult::Script(script) = tokenizer.feed(input_buffer) {
+            let written = tokenizer.sink.sink.run_script(&script);
+            // The markup is inserted at the insertion point, which is right after the script's
+            // end tag, so it's parsed before anything that follows.
+            if !written.is_empty() {
+                input_buffer.push_front(StrTendril::from(written));
+            }
+        }
+    }
+
+    fn document(&self) -> &NodeRef {
+        &self.inner.tokenizer.sink.sink.document_node
+    }
+}
+
+impl TendrilSink<UTF8> for Parser {
+    type Output = NodeRef;
+
+    fn process(&mut self, t: StrTendril) {
+        self.inner.input_buffer.push_back(t);
+        self.run();
+    }
+
+    fn error(&mut self, desc: Cow<'static, str>) {
+        self.inner.tokenizer.sink.sink.parse_error(desc)
+    }
+
+    fn finish(mut self) -> NodeRef {
+        self.run();
+        self.inner.tokenizer.end();
+        self.inner.tokenizer.sink.sink.finish()
+    }
+}
+
+/// An HTML parser that's fed the bytes of a document as they arrive (e.g. over a slow
+/// connection), rather than all at once.  The document is built as it's parsed, so what's been
+/// parsed so far can be styled, laid out, and painted before the rest arrives.  The changes made
+/// to the document are recorded as it's built (see `ParseOpts::record_mutations`), so only what
+/// each chunk added needs to be restyled and laid out.
+pub struct IncrementalParser {
+    document: NodeRef,
+    decoder: Utf8LossyDecoder<Parser>,
+}
+
+impl IncrementalParser {
+    pub fn new(opts: ParseOpts) -> IncrementalParser {
+        let parser = parse_html_with_options(ParseOpts {
+            record_mutations: true,
+            ..opts
+        });
+        IncrementalParser {
+            document: parser.document().clone(),
+            decoder: parser.from_utf8(),
+        }
+    }
+
+    /// The document, as parsed so far.  Text cut off at the end of what's been fed so far may not
+    /// be in it yet, and neither may elements whose start tags were cut off.
+    pub fn document(&self) -> &NodeRef {
+        &self.document
+    }
+
+    /// Parses the next `bytes` of the document, which are decoded as UTF-8.  A character whose
+    /// encoding is split between two calls is decoded once the rest of it is fed.
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.decoder.process(ByteTendril::from_slice(bytes));
+    }
+
+    /// Parses whatever is left once the end of the document is reached, returning the document.
+    pub fn finish(self) -> NodeRef {
+        self.decoder.finish()
+    }
 }
 
 pub struct Sink {
     document_node: NodeRef,
     on_parse_error: Option<Box<dyn FnMut(Cow<'static, str>)>>,
+    /// Where the engine scripts are executed with is kept.  The engine is created when the first
+    /// script is parsed.
+    script_engine: Option<ScriptEngineSlot>,
+    record_mutations: bool,
+}
+
+impl Sink {
+    fn record(&self, mutation: Mutation) {
+        if self.record_mutations {
+            mutation::record(mutation);
+        }
+    }
+
+    /// Records that `node` is about to be removed from its parent, if it has one.
+    fn record_removal(&self, node: &NodeRef) {
+        if let Some(parent) = node.parent() {
+            self.record(Mutation::ChildList {
+                parent,
+                added: Vec::new(),
+            });
+        }
+    }
+
+    fn record_insertion(&self, parent: NodeRef, node: NodeRef) {
+        self.record(Mutation::ChildList {
+            parent,
+            added: vec![node],
+        });
+    }
+
+    /// Runs the `<script>` element the parser paused at, so it sees the document as parsed so
+    /// far, returning the markup it inserted with `document.write`.
+    fn run_script(&mut self, script: &NodeRef) -> String {
+        match &self.script_engine {
+            Some(script_engine) => {
+                let document = self.document_node.clone();
+                script_engine
+                    .borrow_mut()
+                    .get_or_insert_with(|| ScriptEngine::new(document))
+                    .run_parser_inserted_script(script)
+            }
+            None => String::new(),
+        }
+    }
 }
 
 impl TreeSink for Sink {
     type Output = NodeRef;
 
     fn finish(self) -> NodeRef {
+        attach_declarative_shadow_roots(&self.document_node);
         self.document_node
     }
 
@@ -119,33 +306,49 @@ impl TreeSink for Sink {
 
     #[inline]
     fn append(&mut self, parent: &NodeRef, child: NodeOrText<NodeRef>) {
-        match child {
-            NodeOrText::AppendNode(node) => parent.append(node),
+        let node = match child {
+            NodeOrText::AppendNode(node) => {
+                self.record_removal(&node);
+                node
+            }
             NodeOrText::AppendText(text) => {
                 if let Some(last_child) = parent.last_child() {
                     if let Some(existing) = last_child.as_text() {
                         existing.borrow_mut().push_str(&text);
+                        self.record(Mutation::CharacterData { node: last_child });
                         return;
                     }
                 }
-                parent.append(NodeRef::new_text(text))
+                NodeRef::new_text(text)
             }
-        }
+        };
+        parent.append(node.clone());
+        self.record_insertion(parent.clone(), node);
     }
 
     #[inline]
     fn append_before_sibling(&mut self, sibling: &NodeRef, child: NodeOrText<NodeRef>) {
-        match child {
-            NodeOrText::AppendNode(node) => sibling.insert_before(node),
+        let node = match child {
+            NodeOrText::AppendNode(node) => {
+                self.record_removal(&node);
+                node
+            }
             NodeOrText::AppendText(text) => {
                 if let Some(previous_sibling) = sibling.previous_sibling() {
                     if let Some(existing) = previous_sibling.as_text() {
                         existing.borrow_mut().push_str(&text);
+                        self.record(Mutation::CharacterData {
+                            node: previous_sibling,
+                        });
                         return;
                     }
                 }
-                sibling.insert_before(NodeRef::new_text(text))
+                NodeRef::new_text(text)
             }
+        };
+        sibling.insert_before(node.clone());
+        if let Some(parent) = sibling.parent() {
+            self.record_insertion(parent, node);
         }
     }
 
@@ -170,18 +373,29 @@ impl TreeSink for Sink {
             value,
         } in attrs
         {
+            let name = local.clone();
+            let mut added = false;
             attributes
                 .map
                 .entry(attributes::ExpandedName { ns, local })
                 .or_insert_with(|| {
+                    added = true;
                     let value = String::from(value);
                     attributes::Attribute { prefix, value }
                 });
+            if added {
+                self.record(Mutation::Attribute {
+                    element: target.clone(),
+                    name,
+                    old_value: None,
+                });
+            }
         }
     }
 
     #[inline]
     fn remove_from_parent(&mut self, target: &NodeRef) {
+        self.record_removal(target);
         target.detach()
     }
 
@@ -189,8 +403,19 @@ impl TreeSink for Sink {
     fn reparent_children(&mut self, node: &NodeRef, new_parent: &NodeRef) {
         // TODO: Can this be done more efficiently in rctree, by moving the whole linked list of
         // children at once?
-        for child in node.children() {
-            new_parent.append(child)
+        let children = node.children().collect::<Vec<_>>();
+        for child in &children {
+            new_parent.append(child.clone())
+        }
+        if !children.is_empty() {
+            self.record(Mutation::ChildList {
+                parent: node.clone(),
+                added: Vec::new(),
+            });
+            self.record(Mutation::ChildList {
+                parent: new_parent.clone(),
+                added: children,
+            });
         }
     }
 
@@ -199,6 +424,19 @@ impl TreeSink for Sink {
         // TODO: Is this useful outside of a browser?
     }
 
+    /// Called after a `<script>` element and its contents have been parsed.  When scripts are
+    /// enabled, the parser pauses here so that `Parser` can run the script (see `run_script`)
+    /// before parsing anything after it.
+    ///
+    /// https://html.spec.whatwg.org/multipage/parsing.html#scriptEndTag
+    fn complete_script(&mut self, _node: &NodeRef) -> NextParserState {
+        if self.script_engine.is_some() {
+            NextParserState::Suspend
+        } else {
+            NextParserState::Continue
+        }
+    }
+
     #[inline]
     fn get_template_contents(&mut self, target: &NodeRef) -> NodeRef {
         target
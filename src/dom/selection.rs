@@ -0,0 +1,298 @@
+//! The selection of a document: the text the user has selected by dragging the mouse over it,
+//! which is highlighted and can be copied to the clipboard.
+//!
+//! https://w3c.github.io/selection-api/
+
+use std::cmp::Ordering;
+use std::ops::Range;
+
+use crate::dom::iter::{NodeEdge, NodeIterator};
+use crate::dom::tree::{NodeData, NodeRef};
+use crate::style::values::computed::display::{DisplayBox, OuterDisplay};
+use crate::style::values::computed::Display;
+
+/// A position in the text of a document.  Kosmonaut only selects text, so unlike the boundary
+/// points of DOM ranges, these are always in text nodes.
+///
+/// https://dom.spec.whatwg.org/#concept-range-bp
+#[derive(Clone, Debug, PartialEq)]
+pub struct BoundaryPoint {
+    /// The text node the point is in.
+    pub node: NodeRef,
+    /// The index of the character (not byte) of the node's text the point is before.
+    pub offset: usize,
+}
+
+impl BoundaryPoint {
+    /// Orders this point and `other` by where they are in the document.
+    ///
+    /// https://dom.spec.whatwg.org/#concept-range-bp-position
+    fn compare(&self, other: &BoundaryPoint) -> Ordering {
+        if self.node == other.node {
+            self.offset.cmp(&other.offset)
+        } else if precedes(&self.node, &other.node) {
+            Ordering::Less
+        } else {
+            Ordering::Greater
+        }
+    }
+}
+
+/// The selection of a document, which runs from where the user started selecting (the anchor) to
+/// where they are selecting to (the focus).  The focus may be before the anchor.
+///
+/// https://w3c.github.io/selection-api/#dfn-selection
+#[derive(Clone, Debug, PartialEq)]
+pub struct Selection {
+    pub anchor: BoundaryPoint,
+    pub focus: BoundaryPoint,
+}
+
+impl Selection {
+    /// A selection with nothing selected yet, at `point`.
+    pub fn collapsed_at(point: BoundaryPoint) -> Selection {
+        Selection {
+            anchor: point.clone(),
+            focus: point,
+        }
+    }
+
+    /// Whether nothing is selected, i.e. the anchor and focus are the same.
+    pub fn is_collapsed(&self) -> bool {
+        self.anchor == self.focus
+    }
+
+    /// The start and end of the selection, in document order.
+    pub fn range(&self) -> (&BoundaryPoint, &BoundaryPoint) {
+        match self.anchor.compare(&self.focus) {
+            Ordering::Greater => (&self.focus, &self.anchor),
+            Ordering::Less | Ordering::Equal => (&self.anchor, &self.focus),
+        }
+    }
+
+    /// The range of the characters of the text node `node` that are selected, or `None` if none
+    /// of them are.
+    pub fn selected_chars(&self, node: &NodeRef) -> Option<Range<usize>> {
+        let text = node.as_text()?;
+        let (start, end) = self.range();
+        if node != &start.node && !precedes(&start.node, node) {
+            return None;
+        }
+        if node != &end.node && !precedes(node, &end.node) {
+            return None;
+        }
+        let start_offset = if node == &start.node { start.offset } else { 0 };
+        let end_offset = if node == &end.node {
+            end.offset
+        } else {
+            text.borrow().chars().count()
+        };
+        Some(start_offset..end_offset).filter(|chars| !chars.is_empty())
+    }
+
+    /// The selected text, as it's rendered, for copying to the clipboard.  Like `innerText`,
+    /// whitespace is collapsed, blocks are put on lines of their own, and paragraphs are separated
+    /// by a blank line.
+    ///
+    /// https://html.spec.whatwg.org/multipage/dom.html#rendered-text-collection-steps
+    ///
+    /// TODO: Preserve whitespace that `white-space` says to keep.
+    pub fn text(&self) -> String {
+        let (start, end) = self.range();
+        let root = start.node.inclusive_ancestors().last().unwrap();
+        let mut text = String::new();
+        // The number of line breaks required before the next text, which are only written once
+        // there is next text, so there are none at the start or end.
+        let mut required_line_breaks = 0;
+        let mut selecting = false;
+        for edge in root.shadow_including_traverse_inclusive() {
+            let (node, is_start) = match edge {
+                NodeEdge::Start(node) => (node, true),
+                NodeEdge::End(node) => (node, false),
+            };
+            if let Some(node_text) = node.as_text() {
+                if !is_start {
+                    continue;
+                }
+                if node == start.node {
+                    selecting = true;
+                }
+                if selecting && is_rendered(&node) {
+                    let chars = self.selected_chars(&node).unwrap_or(0..0);
+                    let selected = node_text
+                        .borrow()
+                        .chars()
+                        .skip(chars.start)
+                        .take(chars.len())
+                        .collect::<String>();
+                    push_collapsed(&mut text, &selected, &mut required_line_breaks);
+                }
+                if node == end.node {
+                    break;
+                }
+                continue;
+            }
+            if !selecting || !is_rendered(&node) {
+                continue;
+            }
+            let element = match node.as_element() {
+                Some(element) => element,
+                None => continue,
+            };
+            if is_start && element.name.ns == ns!(html) && element.name.local == local_name!("br") {
+                text.push('\n');
+                required_line_breaks = 0;
+            } else if element.name.ns == ns!(html) && element.name.local == local_name!("p") {
+                required_line_breaks = required_line_breaks.max(2);
+            } else if let Display::Full(full_display) = node.computed_values().display {
+                if full_display.outer() == OuterDisplay::Block {
+                    required_line_breaks = required_line_breaks.max(1);
+                }
+            }
+        }
+        text.trim_end_matches(' ').to_owned()
+    }
+}
+
+/// Appends `selected` to `text` with its whitespace collapsed, after the line breaks required
+/// before it (unless it's all whitespace, or is the first text).
+fn push_collapsed(text: &mut String, selected: &str, required_line_breaks: &mut usize) {
+    let starts_with_space = selected.starts_with(|ch: char| ch.is_ascii_whitespace());
+    let ends_with_space = selected.ends_with(|ch: char| ch.is_ascii_whitespace());
+    let words = selected.split_ascii_whitespace().collect::<Vec<_>>();
+    if words.is_empty() {
+        if !selected.is_empty() && !text.is_empty() && !text.ends_with(&[' ', '\n'][..]) {
+            text.push(' ');
+        }
+        return;
+    }
+    if !text.is_empty() && *required_line_breaks > 0 {
+        let trimmed_len = text.trim_end_matches(' ').len();
+        text.truncate(trimmed_len);
+        for _ in 0..*required_line_breaks {
+            text.push('\n');
+        }
+    } else if starts_with_space && !text.is_empty() && !text.ends_with(&[' ', '\n'][..]) {
+        text.push(' ');
+    }
+    *required_line_breaks = 0;
+    text.push_str(&words.join(" "));
+    if ends_with_space {
+        text.push(' ');
+    }
+}
+
+/// Whether `node` is rendered, i.e. neither it nor any of its ancestors is `display: none`.
+fn is_rendered(node: &NodeRef) -> bool {
+    node.inclusive_ancestors()
+        .all(|ancestor| match ancestor.as_element() {
+            // Scripts aren't rendered, but the UA stylesheet doesn't hide them yet.
+            Some(element) => {
+                !(element.name.ns == ns!(html) && element.name.local == local_name!("script"))
+                    && ancestor.computed_values().display != Display::Box(DisplayBox::None)
+            }
+            None => true,
+        })
+}
+
+/// Whether `node` comes before `other` in shadow-including tree order, in which the shadow root
+/// of a host comes before the host's children.  Neither may be an inclusive ancestor of the
+/// other.
+///
+/// https://dom.spec.whatwg.org/#concept-shadow-including-tree-order
+fn precedes(node: &NodeRef, other: &NodeRef) -> bool {
+    let ancestors = node.inclusive_ancestors().collect::<Vec<_>>();
+    let other_ancestors = other.inclusive_ancestors().collect::<Vec<_>>();
+    // Find the children of the closest common ancestor the two nodes are (or are in).
+    let common = ancestors
+        .iter()
+        .rev()
+        .zip(other_ancestors.iter().rev())
+        .take_while(|(ancestor, other_ancestor)| ancestor == other_ancestor)
+        .count();
+    let (child, other_child) = match (
+        ancestors.get(ancestors.len().wrapping_sub(common + 1)),
+        other_ancestors.get(other_ancestors.len().wrapping_sub(common + 1)),
+    ) {
+        (Some(child), Some(other_child)) => (child, other_child),
+        // The nodes aren't in the same tree, or one contains the other.
+        _ => return false,
+    };
+    match (child.data(), other_child.data()) {
+        (NodeData::ShadowRoot(_), _) => true,
+        (_, NodeData::ShadowRoot(_)) => false,
+        _ => child
+            .following_siblings()
+            .any(|sibling| sibling == *other_child),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::traits::*;
+    use crate::style::test_utils::styled_document;
+
+    /// The point `offset` characters into the first text node of the element matching `selector`.
+    fn point(document: &NodeRef, selector: &str, offset: usize) -> BoundaryPoint {
+        let element = document.select_first(selector).unwrap().as_node().clone();
+        BoundaryPoint {
+            node: element
+                .descendants()
+                .find(|node| node.as_text().is_some())
+                .unwrap(),
+            offset,
+        }
+    }
+
+    #[test]
+    fn orders_the_anchor_and_focus() {
+        let document = styled_document("<p>one <b>two</b> three</p>", "");
+        let selection = Selection {
+            anchor: point(&document, "b", 2),
+            focus: point(&document, "p", 1),
+        };
+        let (start, end) = selection.range();
+        assert_eq!((start, end), (&selection.focus, &selection.anchor));
+        assert!(!selection.is_collapsed());
+
+        let p_text = point(&document, "p", 0).node;
+        let b_text = point(&document, "b", 0).node;
+        let last_text = p_text.parent().unwrap().last_child().unwrap();
+        assert_eq!(selection.selected_chars(&p_text), Some(1..4));
+        assert_eq!(selection.selected_chars(&b_text), Some(0..2));
+        assert_eq!(selection.selected_chars(&last_text), None);
+        assert_eq!(selection.text(), "ne tw");
+    }
+
+    #[test]
+    fn copies_blocks_and_paragraphs_on_lines_of_their_own() {
+        let document = styled_document(
+            "<div id=first>Some   <i>inline</i>\n text</div>\
+             <p>A paragraph<br>broken</p>\
+             <div style='display: none'>Hidden</div>\
+             <div>Item</div><div id=last>Last item</div>",
+            "",
+        );
+        let selection = Selection {
+            anchor: point(&document, "#first", 2),
+            focus: point(&document, "#last", 4),
+        };
+        assert_eq!(
+            selection.text(),
+            "me inline text\n\nA paragraph\nbroken\n\nItem\nLast"
+        );
+    }
+
+    #[test]
+    fn collapsed_selections_select_nothing() {
+        let document = styled_document("<p>text</p>", "");
+        let selection = Selection::collapsed_at(point(&document, "p", 2));
+        assert!(selection.is_collapsed());
+        assert_eq!(
+            selection.selected_chars(&point(&document, "p", 0).node),
+            None
+        );
+        assert_eq!(selection.text(), "");
+    }
+}
@@ -53,7 +53,9 @@ impl Serialize for NodeRef {
                 Ok(())
             }
 
-            (_, &NodeData::DocumentFragment) | (_, &NodeData::Document(_)) => {
+            (_, &NodeData::DocumentFragment)
+            | (_, &NodeData::Document(_))
+            | (_, &NodeData::ShadowRoot(_)) => {
                 for child in self.children() {
                     Serialize::serialize(&child, serializer, IncludeNode)?
                 }
@@ -0,0 +1,245 @@
+//! Shadow trees, which are attached to shadow host elements and rendered in place of their
+//! children, and slots, which render the host's children within them.
+//!
+//! Shadow roots are created declaratively, with `<template shadowrootmode>` elements.
+//!
+//! https://dom.spec.whatwg.org/#shadow-trees
+//! https://html.spec.whatwg.org/multipage/scripting.html#attr-template-shadowrootmode
+
+use crate::dom::tree::{NodeRef, ShadowRootMode};
+
+impl NodeRef {
+    /// The root of the shadow tree this node is in, or `None` if it's in a document tree.
+    ///
+    /// https://dom.spec.whatwg.org/#concept-shadow-root
+    pub fn containing_shadow_root(&self) -> Option<NodeRef> {
+        self.ancestors()
+            .find(|ancestor| ancestor.as_shadow_root().is_some())
+    }
+
+    /// The host of the shadow tree this node is in, or `None` if it's in a document tree.
+    ///
+    /// https://dom.spec.whatwg.org/#concept-documentfragment-host
+    pub fn containing_shadow_host(&self) -> Option<NodeRef> {
+        self.containing_shadow_root()?.parent()
+    }
+
+    /// The shadow roots of the shadow hosts in this node's subtree, not including those of hosts in
+    /// their shadow trees, in tree order.
+    pub fn shadow_roots(&self) -> Vec<NodeRef> {
+        self.inclusive_descendants()
+            .filter_map(|node| node.shadow_root())
+            .collect()
+    }
+
+    /// The children of this node in the flat tree, which is the tree that's rendered.  The children
+    /// of a shadow host are those of its shadow root, and the `<slot>`s of shadow trees are
+    /// replaced by the nodes assigned to them, or if none are, by their own children (their
+    /// fallback content).
+    ///
+    /// TODO: Slots should stay in the flat tree, with `display: contents`, so that the nodes
+    /// assigned to them inherit from them rather than from the host.
+    ///
+    /// https://drafts.csswg.org/css-scoping/#flat-tree
+    pub fn flat_tree_children(&self) -> Vec<NodeRef> {
+        let parent = self.shadow_root().unwrap_or_else(|| self.clone());
+        let mut flat_tree_children = Vec::new();
+        for child in parent.children() {
+            push_flattened(child, &mut flat_tree_children);
+        }
+        flat_tree_children
+    }
+
+    /// If this is a `<slot>` in a shadow tree, the children of the tree's host assigned to it: those
+    /// whose `slot` attribute is the slot's name, if this is the first slot with that name in the
+    /// tree.  Text nodes are assigned to the slot without a name.
+    ///
+    /// https://dom.spec.whatwg.org/#find-slotables
+    pub fn assigned_nodes(&self) -> Vec<NodeRef> {
+        let shadow_root = match self.containing_shadow_root() {
+            Some(shadow_root) if is_slot(self) => shadow_root,
+            _ => return Vec::new(),
+        };
+        let name = slot_name(self);
+        let first_slot = shadow_root
+            .descendants()
+            .find(|node| is_slot(node) && slot_name(node) == name);
+        if first_slot.as_ref() != Some(self) {
+            return Vec::new();
+        }
+        match shadow_root.parent() {
+            Some(host) => host
+                .children()
+                .filter(|child| slottable_name(child).as_deref() == Some(&*name))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Pushes `node` onto `flat_tree_children`, or if it's a slot of a shadow tree, what replaces it.
+fn push_flattened(node: NodeRef, flat_tree_children: &mut Vec<NodeRef>) {
+    if !is_slot(&node) || node.containing_shadow_root().is_none() {
+        flat_tree_children.push(node);
+        return;
+    }
+    let mut replacements = node.assigned_nodes();
+    if replacements.is_empty() {
+        replacements = node.children().collect();
+    }
+    for replacement in replacements {
+        push_flattened(replacement, flat_tree_children);
+    }
+}
+
+fn is_slot(node: &NodeRef) -> bool {
+    node.as_element().map_or(false, |element| {
+        element.name.ns == ns!(html) && element.name.local == local_name!("slot")
+    })
+}
+
+/// https://dom.spec.whatwg.org/#slot-name
+fn slot_name(slot: &NodeRef) -> String {
+    slot.as_element()
+        .and_then(|element| {
+            element
+                .attributes
+                .borrow()
+                .get(local_name!("name"))
+                .map(str::to_owned)
+        })
+        .unwrap_or_default()
+}
+
+/// The name of the slot `node` is assigned to, if it can be assigned to a slot at all.
+///
+/// https://dom.spec.whatwg.org/#slotable-name
+fn slottable_name(node: &NodeRef) -> Option<String> {
+    if node.as_text().is_some() {
+        return Some(String::new());
+    }
+    let element = node.as_element()?;
+    let attributes = element.attributes.borrow();
+    Some(
+        attributes
+            .get(local_name!("slot"))
+            .unwrap_or_default()
+            .to_owned(),
+    )
+}
+
+/// Turns each `<template shadowrootmode>` in `node`'s subtree (and in the contents of templates)
+/// into a shadow root of its parent, moving the template's contents into the shadow root and
+/// removing the template.  Templates whose parent is already a shadow host are left alone, as are
+/// those with an invalid mode.
+///
+/// The HTML parser does this when it pops the template off the stack of open elements, but doing
+/// it once the document is parsed gets the same tree, except as seen by scripts run while parsing.
+///
+/// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inhead:attach-a-shadow-root
+pub fn attach_declarative_shadow_roots(node: &NodeRef) {
+    let templates = node
+        .descendants()
+        .filter(|node| declarative_shadow_root_mode(node).is_some())
+        .collect::<Vec<_>>();
+    for template in templates {
+        let contents = match &template.as_element().unwrap().template_contents {
+            Some(contents) => contents.clone(),
+            None => continue,
+        };
+        attach_declarative_shadow_roots(&contents);
+        let mode = declarative_shadow_root_mode(&template).unwrap();
+        let shadow_root = template
+            .parent()
+            .and_then(|parent| parent.attach_shadow(mode));
+        if let Some(shadow_root) = shadow_root {
+            for child in contents.children() {
+                shadow_root.append(child);
+            }
+            template.detach();
+        }
+    }
+}
+
+/// The mode of the shadow root `node` declares, if it's a `<template>` with a valid
+/// `shadowrootmode`.
+fn declarative_shadow_root_mode(node: &NodeRef) -> Option<ShadowRootMode> {
+    let element = node.as_element()?;
+    if element.name.ns != ns!(html) || element.name.local != local_name!("template") {
+        return None;
+    }
+    let attributes = element.attributes.borrow();
+    let mode = attributes.get("shadowrootmode")?;
+    if mode.eq_ignore_ascii_case("open") {
+        Some(ShadowRootMode::Open)
+    } else if mode.eq_ignore_ascii_case("closed") {
+        Some(ShadowRootMode::Closed)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::parser::parse_html;
+    use crate::dom::traits::*;
+
+    fn local_names(nodes: &[NodeRef]) -> Vec<String> {
+        nodes
+            .iter()
+            .map(|node| match node.as_element() {
+                Some(element) => element.name.local.to_string(),
+                None => format!("#{}", node.text_contents().trim()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn declarative_shadow_roots_replace_their_templates() {
+        let document = parse_html().one(
+            "<div id=host><template shadowrootmode=open><p>shadow</p>\
+             <template shadowrootmode=closed></template></template>light</div>\
+             <section><template shadowrootmode=bogus></template></section>",
+        );
+        let host = document.select_first("#host").unwrap().as_node().clone();
+        let shadow_root = host.shadow_root().unwrap();
+        assert_eq!(
+            shadow_root.as_shadow_root().unwrap().mode,
+            ShadowRootMode::Open
+        );
+        assert_eq!(shadow_root.parent(), Some(host.clone()));
+        // The template is gone from the host's children, which are its light tree.
+        assert_eq!(
+            local_names(&host.children().collect::<Vec<_>>()),
+            ["#light"]
+        );
+        let p = shadow_root.first_child().unwrap();
+        assert_eq!(p.containing_shadow_host(), Some(host));
+        // Nested templates without a parent element in the shadow tree stay templates.
+        assert_eq!(
+            local_names(&shadow_root.children().collect::<Vec<_>>()),
+            ["p", "template"]
+        );
+        assert!(document.select_first("section template").is_ok());
+        // The shadow tree is found by shadow-including traversals only.
+        assert!(document.descendants().all(|node| node != p));
+        assert!(document
+            .shadow_including_inclusive_descendants()
+            .any(|node| node == p));
+    }
+
+    #[test]
+    fn slots_are_replaced_by_their_assigned_nodes_in_the_flat_tree() {
+        let document = parse_html().one(
+            "<div id=host><template shadowrootmode=open><header></header>\
+             <slot name=title><i>fallback</i></slot><slot></slot><slot name=empty><b>x</b></slot>\
+             </template><h1 slot=title>title</h1><span>default</span>text</div>",
+        );
+        let host = document.select_first("#host").unwrap().as_node().clone();
+        assert_eq!(
+            local_names(&host.flat_tree_children()),
+            ["header", "h1", "span", "#text", "b"]
+        );
+    }
+}
@@ -0,0 +1,385 @@
+//! Form submission: collecting the names and values of a `<form>`'s controls, encoding them, and
+//! working out where the browser should navigate to send them.
+//!
+//! Documents are only loaded from files, so there's nowhere to send a request body to.  Submitting
+//! a form navigates to its action, with the entries in the query of `GET` submissions, which local
+//! navigation ignores.
+//!
+//! https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#form-submission-2
+
+use crate::dom::form::{self, FormControlKind};
+use crate::dom::tree::NodeRef;
+
+/// The HTTP method a form is submitted with.
+///
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#attr-fs-method
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FormMethod {
+    Get,
+    Post,
+}
+
+/// How the entries of a form submitted with `POST` are encoded in the request body.
+///
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#attr-fs-enctype
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FormEnctype {
+    UrlEncoded,
+    /// TODO: Entries are encoded as `application/x-www-form-urlencoded` instead, until file inputs
+    /// are supported.
+    Multipart,
+    TextPlain,
+}
+
+/// Where and how a form is to be submitted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FormSubmission {
+    pub method: FormMethod,
+    /// The URL to navigate to, which is the form's action, with the encoded entries as its query
+    /// if the form is submitted with `GET`.
+    pub url: String,
+    /// The encoded entries, if the form is submitted with `POST`.
+    pub body: Option<String>,
+}
+
+/// Whether `node` is a submit button, which submits its form owner when it's activated.
+///
+/// https://html.spec.whatwg.org/multipage/forms.html#concept-submit-button
+pub fn is_submit_button(node: &NodeRef) -> bool {
+    let element = match node.as_element() {
+        Some(element) if element.name.ns == ns!(html) => element,
+        _ => return false,
+    };
+    match element.name.local {
+        local_name!("input") => form::input_type(element) == "submit",
+        // Buttons without a valid `type` are submit buttons.
+        local_name!("button") => !matches!(
+            element
+                .attributes
+                .borrow()
+                .get(local_name!("type"))
+                .map(|button_type| button_type.trim().to_ascii_lowercase())
+                .as_deref(),
+            Some("reset") | Some("button")
+        ),
+        _ => false,
+    }
+}
+
+/// The form the given submit button submits when it's activated, if it's an enabled submit button
+/// with a form owner.
+pub fn submit_button_form(node: &NodeRef) -> Option<NodeRef> {
+    if !is_submit_button(node) || form::is_disabled(node) {
+        return None;
+    }
+    form::form_owner(node)
+}
+
+/// The default button of `form`: its first submit button in tree order.
+///
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#default-button
+pub fn default_button(form: &NodeRef) -> Option<NodeRef> {
+    form::tree_root(form)
+        .descendants()
+        .find(|node| is_submit_button(node) && form::form_owner(node).as_ref() == Some(form))
+}
+
+/// The form that pressing Enter in the given text field implicitly submits, if any.  That's the
+/// field's form owner, if the form has a default button to submit it with (which is what should be
+/// clicked), or if the field is the only one in the form that blocks implicit submission.
+///
+/// Returns the form and its default button, if any.
+///
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#implicit-submission
+pub fn implicit_submission(field: &NodeRef) -> Option<(NodeRef, Option<NodeRef>)> {
+    if FormControlKind::of(field) != Some(FormControlKind::TextField) {
+        return None;
+    }
+    let form = form::form_owner(field)?;
+    if let Some(default_button) = default_button(&form) {
+        return Some((form, Some(default_button)));
+    }
+    let blocking_fields = form::tree_root(&form)
+        .descendants()
+        .filter(|node| {
+            FormControlKind::of(node) == Some(FormControlKind::TextField)
+                && form::form_owner(node).as_ref() == Some(&form)
+        })
+        .count();
+    if blocking_fields == 1 {
+        Some((form, None))
+    } else {
+        None
+    }
+}
+
+/// Works out how to submit `form`, with `submitter` being the submit button that submitted it, if
+/// any.  The submitter's `formaction`, `formmethod`, and `formenctype` attributes override those of
+/// the form.
+///
+/// TODO: Support the `dialog` method, and `formtarget`.
+///
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#form-submission-algorithm
+pub fn plan_submission(form: &NodeRef, submitter: Option<&NodeRef>) -> FormSubmission {
+    let attribute = |form_attribute: &str, submitter_attribute: &str| {
+        let from = |node: &NodeRef, name: &str| {
+            node.as_element()
+                .and_then(|element| element.attributes.borrow().get(name).map(str::to_owned))
+        };
+        submitter
+            .and_then(|submitter| from(submitter, submitter_attribute))
+            .or_else(|| from(form, form_attribute))
+    };
+    let method = match attribute("method", "formmethod") {
+        Some(method) if method.trim().eq_ignore_ascii_case("post") => FormMethod::Post,
+        _ => FormMethod::Get,
+    };
+    let enctype = match attribute("enctype", "formenctype")
+        .map(|enctype| enctype.trim().to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("multipart/form-data") => FormEnctype::Multipart,
+        Some("text/plain") => FormEnctype::TextPlain,
+        _ => FormEnctype::UrlEncoded,
+    };
+    // An empty action submits the form to the document itself.
+    let action = attribute("action", "formaction").unwrap_or_default();
+    let action = action.trim();
+    let entries = entry_list(form, submitter);
+    match method {
+        FormMethod::Get => {
+            let (url, fragment) = match action.find('#') {
+                Some(fragment_start) => action.split_at(fragment_start),
+                None => (action, ""),
+            };
+            let url = url.split('?').next().unwrap_or("");
+            FormSubmission {
+                method,
+                url: format!("{}?{}{}", url, url_encode(&entries), fragment),
+                body: None,
+            }
+        }
+        FormMethod::Post => FormSubmission {
+            method,
+            url: action.to_owned(),
+            body: Some(match enctype {
+                FormEnctype::UrlEncoded | FormEnctype::Multipart => url_encode(&entries),
+                FormEnctype::TextPlain => text_plain_encode(&entries),
+            }),
+        },
+    }
+}
+
+/// The names and values `form` is submitted with, in tree order: those of its enabled controls
+/// that have a name, except for unchecked checkboxes and radio buttons, and buttons other than the
+/// submitter.  Line breaks are normalized to CRLF.
+///
+/// TODO: Support file inputs and `dirname`.
+///
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#constructing-the-form-data-set
+pub fn entry_list(form: &NodeRef, submitter: Option<&NodeRef>) -> Vec<(String, String)> {
+    let mut entries = Vec::new();
+    for node in form::tree_root(form).descendants() {
+        let element = match node.as_element() {
+            Some(element) if element.name.ns == ns!(html) => element,
+            _ => continue,
+        };
+        let is_submittable = matches!(
+            element.name.local,
+            local_name!("button")
+                | local_name!("input")
+                | local_name!("select")
+                | local_name!("textarea")
+        );
+        if !is_submittable
+            || form::form_owner(&node).as_ref() != Some(form)
+            || form::is_disabled(&node)
+            || node.ancestors().any(|ancestor| {
+                ancestor.as_element().map_or(false, |element| {
+                    element.name.local == local_name!("datalist")
+                })
+            })
+        {
+            continue;
+        }
+        let name = match element.attributes.borrow().get(local_name!("name")) {
+            Some(name) if !name.is_empty() => name.to_owned(),
+            _ => continue,
+        };
+        let mut push = |value: String| {
+            entries.push((normalize_line_breaks(&name), normalize_line_breaks(&value)))
+        };
+        let value_attribute = || {
+            element
+                .attributes
+                .borrow()
+                .get(local_name!("value"))
+                .map(str::to_owned)
+        };
+        match FormControlKind::of(&node) {
+            Some(FormControlKind::Button) => {
+                if submitter == Some(&node) {
+                    push(value_attribute().unwrap_or_default());
+                }
+            }
+            Some(FormControlKind::Checkbox) | Some(FormControlKind::Radio) => {
+                if form::is_checked(element) {
+                    push(value_attribute().unwrap_or_else(|| "on".to_owned()));
+                }
+            }
+            Some(FormControlKind::Select) => {
+                for option in form::selected_options(&node) {
+                    if !form::is_disabled(&option) {
+                        push(form::option_value(&option));
+                    }
+                }
+            }
+            Some(FormControlKind::TextField) | Some(FormControlKind::TextArea) => {
+                push(form::value(&node).unwrap_or_default());
+            }
+            // Hidden inputs aren't rendered, but are submitted with their `value`.
+            None => push(value_attribute().unwrap_or_default()),
+        }
+    }
+    entries
+}
+
+/// Encodes `entries` as `application/x-www-form-urlencoded`.
+///
+/// https://url.spec.whatwg.org/#concept-urlencoded-serializer
+pub fn url_encode(entries: &[(String, String)]) -> String {
+    entries
+        .iter()
+        .map(|(name, value)| format!("{}={}", url_encode_string(name), url_encode_string(value)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Percent-encodes the UTF-8 bytes of `string` with the `application/x-www-form-urlencoded`
+/// percent-encode set, encoding spaces as `+`.
+///
+/// https://url.spec.whatwg.org/#urlencoded-serializing
+fn url_encode_string(string: &str) -> String {
+    let mut encoded = String::new();
+    for byte in string.bytes() {
+        match byte {
+            b' ' => encoded.push('+'),
+            b'*' | b'-' | b'.' | b'_' => encoded.push(byte as char),
+            _ if byte.is_ascii_alphanumeric() => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Encodes `entries` as `text/plain`, one `name=value` pair per line.
+///
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#text/plain-encoding-algorithm
+fn text_plain_encode(entries: &[(String, String)]) -> String {
+    entries
+        .iter()
+        .map(|(name, value)| format!("{}={}\r\n", name, value))
+        .collect()
+}
+
+/// Replaces every line break (a CR, LF, or CRLF) in `string` with a CRLF.
+fn normalize_line_breaks(string: &str) -> String {
+    string
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .replace('\n', "\r\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::editing::EditCommand;
+    use crate::dom::parser::parse_html;
+    use crate::dom::traits::*;
+
+    fn entries(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|&(name, value)| (name.to_owned(), value.to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn constructs_entry_lists() {
+        let document = parse_html().one(
+            "<form id=f><input name=q value=old><input type=hidden name=h value=1>\
+             <input type=checkbox name=c><input type=checkbox name=c2 checked>\
+             <input name=disabled disabled><input value=nameless>\
+             <textarea name=t>a\nb</textarea>\
+             <select name=s><option>One<option value=2 selected>Two</select>\
+             <button name=b value=pressed>Go</button><button name=other>No</button></form>\
+             <input name=outside form=f value=x>",
+        );
+        let select = |selector: &str| document.select_first(selector).unwrap().as_node().clone();
+        let form = select("form");
+        form::edit(&select("input[name=q]"), EditCommand::Insert('!'));
+        let button = select("button");
+        assert_eq!(
+            entry_list(&form, Some(&button)),
+            entries(&[
+                ("q", "old!"),
+                ("h", "1"),
+                ("c2", "on"),
+                ("t", "a\r\nb"),
+                ("s", "2"),
+                ("b", "pressed"),
+                ("outside", "x"),
+            ])
+        );
+    }
+
+    #[test]
+    fn plans_get_and_post_submissions() {
+        let document = parse_html().one(
+            "<form action='results.html?old#top'><input name=q value='a b&c'>\
+             <button formmethod=post formenctype=text/plain>Post</button>\
+             <button type=button>Not a submit button</button></form>",
+        );
+        let form = document.select_first("form").unwrap().as_node().clone();
+        assert_eq!(
+            plan_submission(&form, None),
+            FormSubmission {
+                method: FormMethod::Get,
+                url: "results.html?q=a+b%26c#top".to_owned(),
+                body: None,
+            }
+        );
+        let buttons = document.select_str("button").unwrap().collect::<Vec<_>>();
+        let post_button = buttons[0].as_node();
+        assert_eq!(submit_button_form(post_button), Some(form.clone()));
+        assert_eq!(submit_button_form(buttons[1].as_node()), None);
+        assert_eq!(
+            plan_submission(&form, Some(post_button)),
+            FormSubmission {
+                method: FormMethod::Post,
+                url: "results.html?old#top".to_owned(),
+                body: Some("q=a b&c\r\n".to_owned()),
+            }
+        );
+        assert_eq!(url_encode(&entries(&[("é", "*~")])), "%C3%A9=*%7E");
+    }
+
+    #[test]
+    fn submits_implicitly_from_text_fields() {
+        let document = parse_html().one(
+            "<form id=one><input id=a></form>\
+             <form id=two><input id=b><input id=c></form>\
+             <form id=three><input id=d><input id=e><input type=submit></form>",
+        );
+        let select = |selector: &str| document.select_first(selector).unwrap().as_node().clone();
+        assert_eq!(
+            implicit_submission(&select("#a")),
+            Some((select("#one"), None))
+        );
+        assert_eq!(implicit_submission(&select("#b")), None);
+        assert_eq!(
+            implicit_submission(&select("#d")),
+            Some((select("#three"), Some(select("input[type=submit]"))))
+        );
+    }
+}
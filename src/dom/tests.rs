@@ -5,9 +5,12 @@ use std::path::Path;
 
 use tempdir::TempDir;
 
-use crate::dom::parser::parse_html;
+use crate::dom::parser::{is_xml_document, parse_html, parse_xml, IncrementalParser, ParseOpts};
 use crate::dom::traits::*;
+use crate::dom::tree::NodeRef;
+use crate::layout::rect::Rect;
 use crate::style::select::*;
+use crate::style::values::computed::length::CSSPixelLength;
 
 #[test]
 fn text_nodes() {
@@ -39,6 +42,118 @@ fn text_nodes() {
     assert_eq!(&*texts[0].borrow(), "Content doesn't contain ");
 }
 
+#[test]
+fn parse_quirks_modes() {
+    let quirks_mode = |html| parse_html().one(html).as_document().unwrap().quirks_mode();
+    assert_eq!(quirks_mode("<p>No doctype"), QuirksMode::Quirks);
+    assert_eq!(
+        quirks_mode(
+            r#"<!DOCTYPE html PUBLIC "-//W3C//DTD HTML 4.01 Transitional//EN"
+            "http://www.w3.org/TR/html4/loose.dtd"><p>Transitional"#
+        ),
+        QuirksMode::LimitedQuirks
+    );
+    assert_eq!(
+        quirks_mode("<!DOCTYPE html><p>Standards"),
+        QuirksMode::NoQuirks
+    );
+
+    let document = parse_html().one("<p>No doctype");
+    let p = document.select_first("p").unwrap();
+    assert_eq!(p.as_node().quirks_mode(), QuirksMode::Quirks);
+    assert_eq!(NodeRef::new_text("").quirks_mode(), QuirksMode::NoQuirks);
+}
+
+#[test]
+fn parse_incrementally() {
+    let html = "<!DOCTYPE html><title>Café</title><p id=first>Ça va<p id=second>Très bien";
+    let mut parser = IncrementalParser::new(ParseOpts::default());
+    // Split the `é` of the title between two chunks.
+    let split_at = html.find("é").unwrap() + 1;
+    parser.feed(&html.as_bytes()[..split_at]);
+    assert!(parser.document().select_first("title").is_ok());
+    assert!(parser.document().select_first("p").is_err());
+    // Cut the start tag of the second paragraph off.
+    let cut_at = html.find("id=second").unwrap();
+    parser.feed(&html.as_bytes()[split_at..cut_at]);
+    // The partial document is there to be read (and styled) before the rest arrives.
+    assert!(parser.document().select_first("#first").is_ok());
+    assert!(parser.document().select_first("#second").is_err());
+    parser.feed(&html.as_bytes()[cut_at..]);
+    let document = parser.finish();
+    assert_eq!(document.to_string(), parse_html().one(html).to_string());
+    assert_eq!(
+        document.select_first("title").unwrap().text_contents(),
+        "Café"
+    );
+}
+
+#[test]
+fn parse_xhtml() {
+    let document = parse_xml().one(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:svg="http://www.w3.org/2000/svg">
+<head><title>XHTML</title></head>
+<body><div id="empty"/><p>After</p><svg:svg><svg:rect/></svg:svg><Custom xmlns=""/></body>
+</html>"#,
+    );
+    let html = document.select_first("html").unwrap();
+    assert_eq!(html.name.ns, ns!(html));
+    // Elements can be self-closing, unlike in HTML, where `<div/>` would contain the paragraph.
+    let empty = document.select_first("#empty").unwrap();
+    assert!(empty.as_node().first_child().is_none());
+    assert_eq!(
+        empty
+            .as_node()
+            .next_sibling()
+            .unwrap()
+            .as_element()
+            .unwrap()
+            .name
+            .local,
+        local_name!("p")
+    );
+    // Names are case-sensitive, and elements take the namespace declared for their prefix.
+    let rect = document.select_first("rect").unwrap();
+    assert_eq!(rect.name.ns, ns!(svg));
+    let custom = document
+        .as_node()
+        .descendants()
+        .elements()
+        .find(|element| &*element.name.local == "Custom")
+        .unwrap();
+    assert_eq!(custom.name.ns, ns!());
+    assert_eq!(
+        document.as_document().unwrap().quirks_mode(),
+        QuirksMode::NoQuirks
+    );
+
+    assert!(is_xml_document(Path::new("test.xhtml")));
+    assert!(is_xml_document(Path::new("test.XHT")));
+    assert!(!is_xml_document(Path::new("test.html")));
+}
+
+#[test]
+fn bounding_client_rects() {
+    let rect = |start_x, start_y, width, height| Rect {
+        start_x,
+        start_y,
+        width: CSSPixelLength::new(width),
+        height: CSSPixelLength::new(height),
+    };
+    let text = NodeRef::new_text("Hello");
+    assert_eq!(text.bounding_client_rect(), Rect::default());
+    // Empty rects are only used when they all are.
+    text.set_client_rects(vec![rect(5., 5., 0., 10.), rect(8., 8., 0., 0.)]);
+    assert_eq!(text.bounding_client_rect(), rect(5., 5., 0., 10.));
+    text.set_client_rects(vec![
+        rect(0., 0., 0., 10.),
+        rect(10., 20., 30., 10.),
+        rect(5., 30., 10., 20.),
+    ]);
+    assert_eq!(text.bounding_client_rect(), rect(5., 20., 35., 30.));
+}
+
 #[test]
 fn parse_and_serialize() {
     let html = r"
@@ -1,5 +1,6 @@
 use std::cell::Cell;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::ops::Deref;
 use std::rc::{Rc, Weak};
 
@@ -10,10 +11,19 @@ use html5ever::QualName;
 
 use crate::dom::attributes::{Attribute, Attributes, ExpandedName};
 use crate::dom::cell_extras::*;
+use crate::dom::editing::EditableText;
+use crate::dom::event::EventListener;
 use crate::dom::iter::NodeIterator;
-use crate::style::properties::{ContextualPropertyDeclaration, ContextualPropertyDeclarations};
+use crate::dom::mutation::Mutation;
+use crate::dom::selection::Selection;
+use crate::iframe::NestedBrowsingContext;
+use crate::image::ImageRequest;
+use crate::layout::rect::{Point, Rect};
+use crate::style::container::QueryContainer;
+use crate::style::rule_tree::{ApplicableDeclarationBlock, RuleNode, RuleTree};
 use crate::style::values::computed::ComputedValues;
 use accountable_refcell::{Ref, RefCell, RefMut};
+use cssparser::RGBA;
 
 /// The type of DOM node.
 /// https://html.spec.whatwg.org/#a-quick-introduction-to-html
@@ -42,6 +52,8 @@ pub enum NodeData {
     Text(RefCell<String>),
     /// Processing instruction node
     ProcessingInstruction(RefCell<(String, String)>),
+    /// Shadow root node, the root of the shadow tree of its host element.
+    ShadowRoot(ShadowRootData),
 }
 
 /// Data specific to doctype nodes.
@@ -57,6 +69,20 @@ pub struct Doctype {
     pub system_id: String,
 }
 
+/// Whether a shadow root's tree is exposed to scripts through its host's `shadowRoot`.
+/// https://dom.spec.whatwg.org/#shadowroot-mode
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ShadowRootMode {
+    Open,
+    Closed,
+}
+
+/// Data specific to shadow root nodes.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ShadowRootData {
+    pub mode: ShadowRootMode,
+}
+
 /// Data specific to element nodes.
 #[derive(Debug, PartialEq, Clone)]
 pub struct ElementData {
@@ -69,6 +95,62 @@ pub struct ElementData {
     /// If the element is an HTML `<template>` element,
     /// the document fragment node that is the root of template contents.
     pub template_contents: Option<NodeRef>,
+
+    /// If the element is an HTML `<img>` element, the image most recently selected and loaded from
+    /// its `src` and `srcset`.
+    /// https://html.spec.whatwg.org/multipage/images.html#current-request
+    pub current_request: RefCell<Option<ImageRequest>>,
+
+    /// If the element is an HTML `<iframe>` element whose document has been loaded, the browsing
+    /// context it displays that document in.
+    /// https://html.spec.whatwg.org/multipage/document-sequences.html#nav-content-document
+    pub nested_browsing_context: RefCell<Option<NestedBrowsingContext>>,
+
+    /// If the element is a text control (a text field `<input>` or `<textarea>`) that has been
+    /// edited, its edited value, caret, and selection.  Controls that haven't been edited yet take
+    /// their value from their default value.
+    /// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#concept-fe-dirty
+    pub editable_text: RefCell<Option<EditableText>>,
+
+    /// Whether the element is the focused element of its document.
+    /// https://html.spec.whatwg.org/multipage/interaction.html#focused
+    pub focused: Cell<bool>,
+
+    /// Whether the element, if focused, should show that it is, i.e. match `:focus-visible`.
+    /// https://drafts.csswg.org/selectors-4/#the-focus-visible-pseudo
+    pub focus_visible: Cell<bool>,
+
+    /// If the element is a checkbox or radio button the user has checked or unchecked, whether
+    /// it's checked.  Controls the user hasn't interacted with take their checkedness from their
+    /// `checked` attribute.
+    /// https://html.spec.whatwg.org/multipage/input.html#concept-input-checked-dirty-flag
+    pub checkedness: Cell<Option<bool>>,
+
+    /// If the element is an `<option>` the user has selected or deselected, whether it's selected.
+    /// Options the user hasn't interacted with take their selectedness from their `selected`
+    /// attribute.
+    /// https://html.spec.whatwg.org/multipage/form-elements.html#concept-option-selectedness
+    pub selectedness: Cell<Option<bool>>,
+
+    /// If the element is a `<select>` drop-down box, whether the popup listing its options is open.
+    pub dropdown_open: Cell<bool>,
+
+    /// If the element is a shadow host, the root of its shadow tree.  The shadow root isn't one of
+    /// the element's children, but its parent is the element, so that the nodes of the shadow tree
+    /// have the host (and its ancestors) as ancestors.
+    /// https://dom.spec.whatwg.org/#concept-element-shadow-root
+    pub shadow_root: RefCell<Option<NodeRef>>,
+
+    /// Whether the element was relevant to the user (e.g. on or near the screen) when its
+    /// document was last laid out, which decides whether it skips its contents if it has
+    /// `content-visibility: auto`.
+    /// https://drafts.csswg.org/css-contain-2/#relevant-to-the-user
+    pub relevant_to_user: Cell<bool>,
+
+    /// What the element looked like as a query container (e.g. its size) when its document was
+    /// last laid out, which `@container` rules are evaluated against, or `None` if it wasn't one.
+    /// https://drafts.csswg.org/css-contain-3/#query-container
+    pub query_container: RefCell<Option<QueryContainer>>,
 }
 
 /// Data specific to document nodes.
@@ -76,6 +158,25 @@ pub struct ElementData {
 pub struct DocumentData {
     #[doc(hidden)]
     pub _quirks_mode: Cell<QuirksMode>,
+    /// Changes to the document that haven't been restyled yet, in the order they were made.
+    pub(crate) mutations: RefCell<Vec<Mutation>>,
+    /// The rule tree shared by the nodes of the document.
+    pub(crate) rule_tree: RuleTree,
+    /// The text the user has selected, if any.
+    /// https://w3c.github.io/selection-api/#dfn-selection
+    pub selection: RefCell<Option<Selection>>,
+    /// The color of the canvas where neither the root element nor `<body>` have a background to
+    /// propagate to it: the `Canvas` system color of the root element's used color scheme.
+    /// https://drafts.csswg.org/css-color-adjust-1/#color-scheme-effect
+    pub canvas_color: Cell<RGBA>,
+    /// The element the fragment the document was last navigated to indicates, which matches
+    /// `:target`.
+    /// https://html.spec.whatwg.org/multipage/browsing-the-web.html#target-element
+    pub(crate) target_element: RefCell<Option<NodeRef>>,
+    /// Where the document's viewport is scrolled to, as of the last time the compositor told the
+    /// pipeline, which decides which `content-visibility: auto` elements are relevant to the user
+    /// when the document is laid out.
+    pub scroll_position: Cell<Point>,
 }
 
 impl DocumentData {
@@ -120,6 +221,15 @@ impl Deref for NodeRef {
 
 impl Eq for NodeRef {}
 
+/// Nodes hash by identity, consistent with `PartialEq`.
+impl Hash for NodeRef {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let node: *const Node = &*self.0;
+        node.hash(state)
+    }
+}
+
 impl PartialEq for NodeRef {
     #[inline]
     fn eq(&self, other: &NodeRef) -> bool {
@@ -129,6 +239,34 @@ impl PartialEq for NodeRef {
     }
 }
 
+/// Identifies a node without keeping it alive, so that, unlike a `NodeRef`, it can be sent to
+/// other threads (e.g. in display lists sent to the compositor).  It can only be turned back into
+/// the node on the thread that owns the node's tree, with `NodeRef::find_opaque`.
+///
+/// Like Servo's `OpaqueNode`, this is the address of the node, so once a node is dropped another
+/// node may be given the same identifier.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct OpaqueNode(usize);
+
+impl OpaqueNode {
+    /// The number identifying the node, e.g. to devtools clients.
+    pub fn id(self) -> usize {
+        self.0
+    }
+}
+
+impl NodeRef {
+    pub fn opaque(&self) -> OpaqueNode {
+        OpaqueNode(Rc::as_ptr(&self.0) as usize)
+    }
+
+    /// Finds the node `opaque` identifies among this node and its shadow-including descendants.
+    pub fn find_opaque(&self, opaque: OpaqueNode) -> Option<NodeRef> {
+        self.shadow_including_inclusive_descendants()
+            .find(|node| node.opaque() == opaque)
+    }
+}
+
 /// A node inside a DOM-like tree.
 pub struct Node {
     parent: Cell<Option<Weak<Node>>>,
@@ -137,14 +275,24 @@ pub struct Node {
     first_child: Cell<Option<Rc<Node>>>,
     last_child: Cell<Option<Weak<Node>>>,
     data: NodeData,
-    /// Property declarations with sufficient context (e.g. origin, specificity, importance) to
-    /// perform a cascade, getting us through step 2 of the value stages algorithm.
+    /// The blocks of declarations matched to this node that are yet to be cascaded, in order of
+    /// appearance.
+    applicable_declarations: RefCell<Vec<ApplicableDeclarationBlock>>,
+    /// The node of the document's rule tree holding the declarations that apply to this node,
+    /// which gets us through step 2 of the value stages algorithm.  This is `None` until the node
+    /// goes through the cascade.
     /// https://www.w3.org/TR/2018/CR-css-cascade-3-20180828/#value-stages
-    contextual_decls: RefCell<ContextualPropertyDeclarations>,
+    rule_node: RefCell<Option<Rc<RuleNode>>>,
     /// The result of step 3 and 4 of the CSS value processing stages.  Until the node goes through
     /// the cascade and computed value calculation phases, this will be something like
     /// `ComputedValues::default()`.
     computed_values: RefCell<ComputedValues>,
+    /// The listeners registered on this node, in the order they were added.
+    /// https://dom.spec.whatwg.org/#eventtarget-event-listener-list
+    event_listeners: RefCell<Vec<EventListener>>,
+    /// The border boxes of the boxes this node generated (or, for text, of the fragments of its
+    /// text on each line) when its document was last laid out.
+    client_rects: RefCell<Vec<Rect>>,
 }
 
 impl fmt::Debug for Node {
@@ -153,7 +301,7 @@ impl fmt::Debug for Node {
         write!(
             f,
             "{:?} @ {:?}, rules: {:?}",
-            self.data, self as *const Node, self.contextual_decls
+            self.data, self as *const Node, self.rule_node
         )
     }
 }
@@ -231,8 +379,11 @@ impl NodeRef {
             previous_sibling: Cell::new(None),
             next_sibling: Cell::new(None),
             data,
-            contextual_decls: RefCell::new(ContextualPropertyDeclarations::new()),
+            applicable_declarations: RefCell::new(Vec::new()),
+            rule_node: RefCell::new(None),
             computed_values: RefCell::new(ComputedValues::default()),
+            event_listeners: RefCell::new(Vec::new()),
+            client_rects: RefCell::new(Vec::new()),
         }))
     }
 
@@ -252,6 +403,17 @@ impl NodeRef {
             attributes: RefCell::new(Attributes {
                 map: attributes.into_iter().collect(),
             }),
+            current_request: RefCell::new(None),
+            nested_browsing_context: RefCell::new(None),
+            editable_text: RefCell::new(None),
+            focused: Cell::new(false),
+            focus_visible: Cell::new(false),
+            checkedness: Cell::new(None),
+            selectedness: Cell::new(None),
+            dropdown_open: Cell::new(false),
+            shadow_root: RefCell::new(None),
+            relevant_to_user: Cell::new(false),
+            query_container: RefCell::new(None),
         }))
     }
 
@@ -300,9 +462,41 @@ impl NodeRef {
     pub fn new_document() -> NodeRef {
         NodeRef::new(NodeData::Document(DocumentData {
             _quirks_mode: Cell::new(QuirksMode::NoQuirks),
+            mutations: RefCell::new(Vec::new()),
+            rule_tree: RuleTree::new(),
+            selection: RefCell::new(None),
+            canvas_color: Cell::new(RGBA::new(255, 255, 255, 255)),
+            target_element: RefCell::new(None),
+            scroll_position: Cell::new(Point::default()),
         }))
     }
 
+    /// Attaches a new, empty shadow root to this element, and returns it.  Returns `None` if this
+    /// isn't an element, or is already a shadow host.
+    ///
+    /// TODO: Elements that can't be shadow hosts (e.g. `<img>`) aren't rejected.
+    ///
+    /// https://dom.spec.whatwg.org/#concept-attach-a-shadow-root
+    pub fn attach_shadow(&self, mode: ShadowRootMode) -> Option<NodeRef> {
+        let element = self.as_element()?;
+        let mut shadow_root = element.shadow_root.borrow_mut();
+        if shadow_root.is_some() {
+            return None;
+        }
+        let new_shadow_root = NodeRef::new(NodeData::ShadowRoot(ShadowRootData { mode }));
+        new_shadow_root.parent.replace(Some(Rc::downgrade(&self.0)));
+        *shadow_root = Some(new_shadow_root.clone());
+        Some(new_shadow_root)
+    }
+
+    /// The quirks mode of the document this node is in, which is no-quirks if it isn't in one.
+    pub fn quirks_mode(&self) -> QuirksMode {
+        self.inclusive_ancestors()
+            .last()
+            .and_then(|root| root.as_document().map(DocumentData::quirks_mode))
+            .unwrap_or(QuirksMode::NoQuirks)
+    }
+
     /// Return the concatenation of all text nodes in this subtree.
     pub fn text_contents(&self) -> String {
         let mut s = String::new();
@@ -320,16 +514,15 @@ impl Node {
         &self.data
     }
 
-    /// Return a reference to this node’s list of contextual property declarations.
+    /// Return this node’s rule node, if it has been through the cascade.
     #[inline]
-    pub fn contextual_decls(&self) -> Ref<ContextualPropertyDeclarations> {
-        self.contextual_decls.borrow()
+    pub fn rule_node(&self) -> Option<Rc<RuleNode>> {
+        self.rule_node.borrow().clone()
     }
 
-    /// Return a mutable reference to this node’s list of contextual property declarations.
     #[inline]
-    pub fn contextual_decls_mut(&self) -> RefMut<ContextualPropertyDeclarations> {
-        self.contextual_decls.borrow_mut()
+    pub fn set_rule_node(&self, rule_node: Rc<RuleNode>) {
+        *self.rule_node.borrow_mut() = Some(rule_node);
     }
 
     /// Return a reference to this node’s computed values.
@@ -344,9 +537,62 @@ impl Node {
         self.computed_values.borrow_mut()
     }
 
+    /// The border boxes of the boxes this node generated when its document was last laid out, in
+    /// tree order, or those of the fragments of its text on each line if this is a text node.
+    /// Empty if the node generated no boxes, or hasn't been laid out.
+    ///
+    /// TODO: Layout doesn't know how far the document is scrolled, so the rects are relative to
+    /// the initial containing block rather than the viewport, as if the document wasn't scrolled.
+    ///
+    /// https://drafts.csswg.org/cssom-view/#dom-element-getclientrects
+    pub fn client_rects(&self) -> Vec<Rect> {
+        self.client_rects.borrow().clone()
+    }
+
+    /// The smallest rect containing the client rects of this node (see `client_rects`), ignoring
+    /// those that are empty unless they all are, in which case it's the first of them.  If the
+    /// node has no client rects, this is an empty rect at the origin.
+    ///
+    /// https://drafts.csswg.org/cssom-view/#dom-element-getboundingclientrect
+    pub fn bounding_client_rect(&self) -> Rect {
+        let client_rects = self.client_rects.borrow();
+        let is_empty = |rect: &&Rect| rect.width.px() == 0. || rect.height.px() == 0.;
+        let mut non_empty_rects = client_rects.iter().filter(|rect| !is_empty(rect));
+        match non_empty_rects.next() {
+            Some(first) => non_empty_rects.fold(*first, |bounds, rect| bounds.union(*rect)),
+            None => client_rects.first().copied().unwrap_or_default(),
+        }
+    }
+
+    /// Replaces the client rects of this node (see `client_rects`), which layout does once it's
+    /// done.
+    pub(crate) fn set_client_rects(&self, client_rects: Vec<Rect>) {
+        *self.client_rects.borrow_mut() = client_rects;
+    }
+
+    /// Adds a client rect to the end of this node's (see `client_rects`).
+    pub(crate) fn push_client_rect(&self, client_rect: Rect) {
+        self.client_rects.borrow_mut().push(client_rect);
+    }
+
+    /// Return this node's event listeners.
+    #[inline]
+    pub fn event_listeners(&self) -> &RefCell<Vec<EventListener>> {
+        &self.event_listeners
+    }
+
+    #[inline]
+    pub fn add_declaration_block(&self, block: ApplicableDeclarationBlock) {
+        self.applicable_declarations.borrow_mut().push(block);
+    }
+
+    /// Takes the declaration blocks matched to this node since they were last taken.
     #[inline]
-    pub fn add_decl(&self, new_decl: ContextualPropertyDeclaration) {
-        self.contextual_decls.borrow_mut().add(new_decl);
+    pub fn take_applicable_declarations(&self) -> Vec<ApplicableDeclarationBlock> {
+        self.applicable_declarations
+            .borrow_mut()
+            .drain(..)
+            .collect()
     }
 
     /// If this node is an element, return a reference to element-specific data.
@@ -394,6 +640,21 @@ impl Node {
         }
     }
 
+    /// If this node is a shadow root, return a reference to shadow-root-specific data.
+    #[inline]
+    pub fn as_shadow_root(&self) -> Option<&ShadowRootData> {
+        match self.data {
+            NodeData::ShadowRoot(ref value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// If this node is a shadow host, return the root of its shadow tree.
+    #[inline]
+    pub fn shadow_root(&self) -> Option<NodeRef> {
+        self.as_element()?.shadow_root.borrow().clone()
+    }
+
     /// Return a reference to the parent node, unless this node is the root of the tree.
     #[inline]
     pub fn parent(&self) -> Option<NodeRef> {
@@ -428,6 +689,10 @@ impl Node {
     ///
     /// To remove a node and its descendants, detach it and drop any strong reference to it.
     pub fn detach(&self) {
+        // Shadow roots stay attached to their hosts, and aren't among their hosts' children.
+        if self.as_shadow_root().is_some() {
+            return;
+        }
         let parent_weak = self.parent.take();
         let previous_sibling_weak = self.previous_sibling.take();
         let next_sibling_strong = self.next_sibling.take();
@@ -0,0 +1,295 @@
+//! A C ABI for embedding Kosmonaut in programs that aren't written in Rust (e.g. game engines or
+//! GUI toolkits), wrapping `Page`.  The header declaring it, `include/kosmonaut.h`, is generated
+//! from this module by cbindgen:
+//!
+//! ```text
+//! cbindgen --config cbindgen.toml --output include/kosmonaut.h
+//! ```
+//!
+//! Pages and nodes aren't thread-safe, so each must only be used on the thread that created it.
+//! Strings passed in must be NUL-terminated UTF-8.  Panics are caught at the boundary, and reported
+//! like any other failure.
+
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::Path;
+use std::ptr;
+
+use crate::dom::tree::NodeRef;
+use crate::layout::rect::Point;
+use crate::page::Page;
+use crate::style::stylesheet::parse_css_to_stylesheet;
+
+/// A page loaded with `kosmonaut_load_html`.
+pub struct KosmonautPage {
+    page: Page,
+}
+
+/// An element found with `kosmonaut_hit_test`.
+pub struct KosmonautNode {
+    node: NodeRef,
+}
+
+/// Runs `f`, returning `failed` instead if it panics, since unwinding into C is undefined.
+fn catch_panic<T>(failed: T, f: impl FnOnce() -> T) -> T {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or(failed)
+}
+
+/// The string `ptr` points to, or `None` if it's null or not UTF-8.
+///
+/// # Safety
+///
+/// `ptr` must be null or point to a NUL-terminated string.
+unsafe fn str_from_ptr<'a>(ptr: *const c_char) -> Option<&'a str> {
+    if ptr.is_null() {
+        return None;
+    }
+    CStr::from_ptr(ptr).to_str().ok()
+}
+
+/// Returns a new string, to be freed with `kosmonaut_string_free`, or null if `string` contains a
+/// NUL.
+fn string_to_ptr(string: String) -> *mut c_char {
+    CString::new(string).map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/// Loads the document `html` is the source of, styled by the user agent stylesheet and the
+/// author stylesheet `css` (if it isn't null), resolving the relative URLs of its images against
+/// the directory `base_dir` (or the current directory, if it's null).  The page's viewport is
+/// the default window size, at one device pixel per CSS pixel, until it's set with
+/// `kosmonaut_set_viewport`.
+///
+/// Returns null if `html` is null or any of the strings aren't UTF-8.  The returned page must be
+/// freed with `kosmonaut_free`.
+///
+/// # Safety
+///
+/// Each string must be null or NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn kosmonaut_load_html(
+    html: *const c_char,
+    css: *const c_char,
+    base_dir: *const c_char,
+) -> *mut KosmonautPage {
+    let html = match str_from_ptr(html) {
+        Some(html) => html,
+        None => return ptr::null_mut(),
+    };
+    let author_sheets = match (css.is_null(), str_from_ptr(css)) {
+        (true, _) => Vec::new(),
+        (false, Some(css)) => vec![parse_css_to_stylesheet(None, &mut css.to_owned())],
+        (false, None) => return ptr::null_mut(),
+    };
+    let base_dir = match (base_dir.is_null(), str_from_ptr(base_dir)) {
+        (true, _) => "",
+        (false, Some(base_dir)) => base_dir,
+        (false, None) => return ptr::null_mut(),
+    };
+    catch_panic(ptr::null_mut(), || {
+        let page = Page::load_html(html, Path::new(base_dir), author_sheets);
+        Box::into_raw(Box::new(KosmonautPage { page }))
+    })
+}
+
+/// Frees `page`, which may be null.
+///
+/// # Safety
+///
+/// `page` must be null or have been returned by `kosmonaut_load_html`, and not freed already.
+#[no_mangle]
+pub unsafe extern "C" fn kosmonaut_free(page: *mut KosmonautPage) {
+    if !page.is_null() {
+        drop(Box::from_raw(page));
+    }
+}
+
+/// Sets the viewport of `page` to `width` by `height` device pixels, at `scale_factor` device
+/// pixels per CSS pixel.
+///
+/// # Safety
+///
+/// `page` must be a live page returned by `kosmonaut_load_html`.
+#[no_mangle]
+pub unsafe extern "C" fn kosmonaut_set_viewport(
+    page: *mut KosmonautPage,
+    width: f32,
+    height: f32,
+    scale_factor: f32,
+) {
+    let page = &mut (*page).page;
+    catch_panic((), || page.set_viewport(width, height, scale_factor))
+}
+
+/// Lays out `page` in its viewport.  Returns false if layout failed.
+///
+/// Rendering and hit testing lay pages out as needed, so this only needs calling to control when
+/// that work happens.
+///
+/// # Safety
+///
+/// `page` must be a live page returned by `kosmonaut_load_html`.
+#[no_mangle]
+pub unsafe extern "C" fn kosmonaut_layout(page: *mut KosmonautPage) -> bool {
+    let page = &mut (*page).page;
+    catch_panic(false, || {
+        page.layout();
+        true
+    })
+}
+
+/// Renders `page` into `pixels`, as rows of straight (not premultiplied) RGBA pixels the size of
+/// its viewport in device pixels, from top to bottom.  Nothing is written unless `len` is at
+/// least the size of the image (i.e. width × height × 4 bytes).
+///
+/// Returns the size of the image in bytes, or 0 if rendering failed, so the size of the buffer
+/// needed can be found by passing a null `pixels` and a `len` of 0.
+///
+/// # Safety
+///
+/// `page` must be a live page returned by `kosmonaut_load_html`, and `pixels` must be valid for
+/// writes of `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn kosmonaut_render_rgba(
+    page: *mut KosmonautPage,
+    pixels: *mut u8,
+    len: usize,
+) -> usize {
+    let page = &mut (*page).page;
+    catch_panic(0, || {
+        let image = page.render();
+        let rendered = image.frame(0).pixels();
+        if !pixels.is_null() && len >= rendered.len() {
+            ptr::copy_nonoverlapping(rendered.as_ptr(), pixels, rendered.len());
+        }
+        rendered.len()
+    })
+}
+
+/// Finds the element painted at (`x`, `y`) in `page`, in CSS pixels relative to its viewport.
+/// Returns null if nothing was painted there.  The returned node must be freed with
+/// `kosmonaut_node_free`.
+///
+/// # Safety
+///
+/// `page` must be a live page returned by `kosmonaut_load_html`.
+#[no_mangle]
+pub unsafe extern "C" fn kosmonaut_hit_test(
+    page: *mut KosmonautPage,
+    x: f32,
+    y: f32,
+) -> *mut KosmonautNode {
+    let page = &mut (*page).page;
+    catch_panic(ptr::null_mut(), || {
+        match page.hit_test(Point { x, y }).into_iter().next() {
+            Some(node) => Box::into_raw(Box::new(KosmonautNode { node })),
+            None => ptr::null_mut(),
+        }
+    })
+}
+
+/// The local name of the element `node` (e.g. `div`), which must be freed with
+/// `kosmonaut_string_free`.
+///
+/// # Safety
+///
+/// `node` must be a live node returned by `kosmonaut_hit_test`.
+#[no_mangle]
+pub unsafe extern "C" fn kosmonaut_node_local_name(node: *const KosmonautNode) -> *mut c_char {
+    let node = &(*node).node;
+    match node.as_element() {
+        Some(element) => string_to_ptr(element.name.local.to_string()),
+        None => ptr::null_mut(),
+    }
+}
+
+/// The value of the attribute `name` of the element `node`, which must be freed with
+/// `kosmonaut_string_free`, or null if it has no such attribute.
+///
+/// # Safety
+///
+/// `node` must be a live node returned by `kosmonaut_hit_test`, and `name` must be
+/// NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn kosmonaut_node_attribute(
+    node: *const KosmonautNode,
+    name: *const c_char,
+) -> *mut c_char {
+    let node = &(*node).node;
+    let name = match str_from_ptr(name) {
+        Some(name) => name,
+        None => return ptr::null_mut(),
+    };
+    node.as_element()
+        .and_then(|element| element.attributes.borrow().get(name).map(str::to_owned))
+        .map_or(ptr::null_mut(), string_to_ptr)
+}
+
+/// Frees `node`, which may be null.
+///
+/// # Safety
+///
+/// `node` must be null or have been returned by `kosmonaut_hit_test`, and not freed already.
+#[no_mangle]
+pub unsafe extern "C" fn kosmonaut_node_free(node: *mut KosmonautNode) {
+    if !node.is_null() {
+        drop(Box::from_raw(node));
+    }
+}
+
+/// Frees `string`, which may be null.
+///
+/// # Safety
+///
+/// `string` must be null or have been returned by Kosmonaut, and not freed already.
+#[no_mangle]
+pub unsafe extern "C" fn kosmonaut_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_and_hit_tests_through_the_c_abi() {
+        let html = CString::new(
+            "<div id=box style=\"width: 100px; height: 50px; background-color: red\"></div>",
+        )
+        .unwrap();
+        let css = CString::new(
+            "body { margin-top: 0px; margin-right: 0px; margin-bottom: 0px; margin-left: 0px }",
+        )
+        .unwrap();
+        unsafe {
+            let page = kosmonaut_load_html(html.as_ptr(), css.as_ptr(), ptr::null());
+            assert!(!page.is_null());
+            kosmonaut_set_viewport(page, 200., 100., 1.);
+            assert!(kosmonaut_layout(page));
+
+            let len = kosmonaut_render_rgba(page, ptr::null_mut(), 0);
+            assert_eq!(len, 200 * 100 * 4);
+            let mut pixels = vec![0; len];
+            assert_eq!(kosmonaut_render_rgba(page, pixels.as_mut_ptr(), len), len);
+            assert_eq!(pixels[..4], [255, 0, 0, 255]);
+
+            let node = kosmonaut_hit_test(page, 10., 10.);
+            assert!(!node.is_null());
+            let local_name = kosmonaut_node_local_name(node);
+            assert_eq!(CStr::from_ptr(local_name).to_str(), Ok("div"));
+            kosmonaut_string_free(local_name);
+            let id_name = CString::new("id").unwrap();
+            let id = kosmonaut_node_attribute(node, id_name.as_ptr());
+            assert_eq!(CStr::from_ptr(id).to_str(), Ok("box"));
+            kosmonaut_string_free(id);
+            let class_name = CString::new("class").unwrap();
+            assert!(kosmonaut_node_attribute(node, class_name.as_ptr()).is_null());
+            kosmonaut_node_free(node);
+
+            kosmonaut_free(page);
+        }
+        assert!(unsafe { kosmonaut_load_html(ptr::null(), ptr::null(), ptr::null()) }.is_null());
+    }
+}
@@ -0,0 +1,145 @@
+//! Packing of rasterized glyphs into texture atlases: large textures holding many glyphs each, so
+//! that a line of text is painted from one texture in a single draw call rather than binding a
+//! texture per glyph.
+//!
+//! https://en.wikipedia.org/wiki/Texture_atlas
+
+/// The width and height of each atlas, in pixels.
+pub const ATLAS_SIZE: u32 = 1024;
+
+/// The number of horizontal positions within a pixel that glyphs are rasterized at.  Glyphs are
+/// positioned with fractional advances, so rasterizing them only at whole pixels would make the
+/// spacing between them uneven.
+pub const SUBPIXEL_POSITIONS: u8 = 4;
+
+/// Transparent pixels left between glyphs, so that sampling the edge of one glyph with linear
+/// filtering doesn't bleed in its neighbours.
+const GLYPH_PADDING: u32 = 1;
+
+/// Splits a horizontal position, in device pixels, into a whole pixel and the index of the
+/// nearest of the `SUBPIXEL_POSITIONS` offsets within it.  A glyph rasterized at that offset and
+/// painted at the whole pixel lands as close to `x` as glyphs get.
+pub fn subpixel_offset(x: f32) -> (f32, u8) {
+    let positions = f32::from(SUBPIXEL_POSITIONS);
+    let steps = (x * positions).round();
+    let whole = (steps / positions).floor();
+    (whole, (steps - whole * positions) as u8)
+}
+
+/// The horizontal offset, in pixels, of the subpixel position `index`.
+pub fn subpixel_x(index: u8) -> f32 {
+    f32::from(index) / f32::from(SUBPIXEL_POSITIONS)
+}
+
+/// A rectangle of an atlas, in pixels from its top left corner.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct AtlasRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Allocates rectangles of a square atlas in shelves: rows as tall as the tallest rectangle in
+/// them, filled from left to right.  Glyphs of a run are of similar heights, so little of each
+/// shelf goes to waste.  Rectangles are never freed; a full atlas is replaced by a new one.
+#[derive(Clone, Debug)]
+pub struct ShelfAllocator {
+    size: u32,
+    shelves: Vec<Shelf>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Shelf {
+    y: u32,
+    height: u32,
+    used_width: u32,
+}
+
+impl ShelfAllocator {
+    pub fn new(size: u32) -> ShelfAllocator {
+        ShelfAllocator {
+            size,
+            shelves: Vec::new(),
+        }
+    }
+
+    /// Allocates a `width` by `height` rectangle, or returns `None` if the atlas has no room left
+    /// for it.
+    pub fn allocate(&mut self, width: u32, height: u32) -> Option<AtlasRect> {
+        let padded_width = width + GLYPH_PADDING;
+        let padded_height = height + GLYPH_PADDING;
+        if padded_width > self.size || padded_height > self.size {
+            return None;
+        }
+        // Use the shortest shelf the rectangle fits in, to keep taller shelves for taller glyphs.
+        let size = self.size;
+        let best_shelf = self
+            .shelves
+            .iter_mut()
+            .filter(|shelf| {
+                shelf.height >= padded_height && size - shelf.used_width >= padded_width
+            })
+            .min_by_key(|shelf| shelf.height);
+        let shelf = match best_shelf {
+            Some(shelf) => shelf,
+            None => {
+                let y = self
+                    .shelves
+                    .last()
+                    .map_or(0, |shelf| shelf.y + shelf.height);
+                if size - y < padded_height {
+                    return None;
+                }
+                self.shelves.push(Shelf {
+                    y,
+                    height: padded_height,
+                    used_width: 0,
+                });
+                self.shelves.last_mut().unwrap()
+            }
+        };
+        let rect = AtlasRect {
+            x: shelf.used_width,
+            y: shelf.y,
+            width,
+            height,
+        };
+        shelf.used_width += padded_width;
+        Some(rect)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions_snap_to_the_nearest_subpixel_offset() {
+        assert_eq!(subpixel_offset(10.), (10., 0));
+        assert_eq!(subpixel_offset(10.3), (10., 1));
+        assert_eq!(subpixel_offset(10.5), (10., 2));
+        assert_eq!(subpixel_offset(10.9), (11., 0));
+        assert_eq!(subpixel_offset(-0.25), (-1., 3));
+        assert_eq!(subpixel_x(2), 0.5);
+    }
+
+    #[test]
+    fn allocates_rects_in_shelves() {
+        let mut allocator = ShelfAllocator::new(16);
+        let rect = |x, y, width, height| AtlasRect {
+            x,
+            y,
+            width,
+            height,
+        };
+        assert_eq!(allocator.allocate(6, 7), Some(rect(0, 0, 6, 7)));
+        assert_eq!(allocator.allocate(6, 5), Some(rect(7, 0, 6, 5)));
+        // Too wide for the rest of the first shelf, so it starts a second one.
+        assert_eq!(allocator.allocate(4, 3), Some(rect(0, 8, 4, 3)));
+        // Short enough for the second shelf, which is shorter than the first.
+        assert_eq!(allocator.allocate(2, 2), Some(rect(5, 8, 2, 2)));
+        assert_eq!(allocator.allocate(16, 1), None);
+        assert_eq!(allocator.allocate(8, 8), None);
+    }
+}
@@ -0,0 +1,67 @@
+use crate::gfx::backend::opengl::OpenGlBackend;
+use crate::gfx::backend::webgpu::WgpuBackend;
+use crate::gfx::display::DisplayList;
+use glutin::dpi::PhysicalSize;
+use glutin::event_loop::EventLoop;
+use glutin::window::Window;
+
+pub mod opengl;
+pub mod webgpu;
+
+/// The backends Kosmonaut can paint the window with.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Renderer {
+    /// Paints with OpenGL 3.3.
+    OpenGl,
+    /// Paints with `wgpu`, which runs on Vulkan, Metal, or DX12.
+    Wgpu,
+}
+
+impl Renderer {
+    pub fn to_cli_string(&self) -> &'static str {
+        match self {
+            Renderer::OpenGl => "opengl",
+            Renderer::Wgpu => "wgpu",
+        }
+    }
+}
+
+/// A rendering backend, which owns the main window and paints display lists into it.
+///
+/// Display lists are renderer-agnostic, so everything up to and including building them is shared
+/// between backends.
+pub trait Backend {
+    /// The window painted into.
+    fn window(&self) -> &Window;
+
+    /// Resizes the surface painted into, after the window was resized to `size` physical pixels.
+    fn resize(&mut self, size: PhysicalSize<u32>);
+
+    /// Paints `display_list` to the window, at `scale_factor` device pixels per CSS pixel.
+    fn paint(&mut self, display_list: DisplayList, scale_factor: f32);
+
+    /// The display list that was last painted, which is what's currently shown in the window.
+    fn display_list(&self) -> &DisplayList;
+}
+
+/// Opens the main window, which is `inner_width_opt` by `inner_height_opt` physical pixels (or a
+/// default size), and sets up the given `renderer` to paint into it.
+pub fn init_backend<T>(
+    renderer: Renderer,
+    event_loop: &EventLoop<T>,
+    inner_width_opt: Option<f32>,
+    inner_height_opt: Option<f32>,
+) -> Box<dyn Backend> {
+    match renderer {
+        Renderer::OpenGl => Box::new(OpenGlBackend::new(
+            event_loop,
+            inner_width_opt,
+            inner_height_opt,
+        )),
+        Renderer::Wgpu => Box::new(WgpuBackend::new(
+            event_loop,
+            inner_width_opt,
+            inner_height_opt,
+        )),
+    }
+}
@@ -0,0 +1,54 @@
+use crate::gfx::backend::Backend;
+use crate::gfx::display::DisplayList;
+use crate::gfx::paint::MasterPainter;
+use crate::gfx::{init_main_window_and_gl, print_gl_info, resize_window};
+use gl::Gl;
+use glutin::dpi::PhysicalSize;
+use glutin::event_loop::EventLoop;
+use glutin::window::Window;
+use glutin::{PossiblyCurrent, WindowedContext};
+use tracing::info_span;
+
+/// Paints with OpenGL 3.3, through a context made current on the main window.
+pub struct OpenGlBackend {
+    gl: Gl,
+    master_painter: MasterPainter,
+    windowed_context: WindowedContext<PossiblyCurrent>,
+}
+
+impl OpenGlBackend {
+    pub fn new<T>(
+        event_loop: &EventLoop<T>,
+        inner_width_opt: Option<f32>,
+        inner_height_opt: Option<f32>,
+    ) -> OpenGlBackend {
+        let (windowed_context, gl) =
+            init_main_window_and_gl(event_loop, inner_width_opt, inner_height_opt);
+        print_gl_info(&windowed_context, &gl);
+        OpenGlBackend {
+            master_painter: MasterPainter::new(&gl).unwrap(),
+            gl,
+            windowed_context,
+        }
+    }
+}
+
+impl Backend for OpenGlBackend {
+    fn window(&self) -> &Window {
+        self.windowed_context.window()
+    }
+
+    fn resize(&mut self, size: PhysicalSize<u32>) {
+        resize_window(&self.gl, &self.windowed_context, &size);
+    }
+
+    fn paint(&mut self, display_list: DisplayList, scale_factor: f32) {
+        let _span = info_span!("paint").entered();
+        self.master_painter
+            .paint(&self.windowed_context, display_list, scale_factor);
+    }
+
+    fn display_list(&self) -> &DisplayList {
+        self.master_painter.display_list()
+    }
+}
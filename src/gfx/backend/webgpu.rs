@@ -0,0 +1,469 @@
+use crate::gfx::atlas::{AtlasRect, ATLAS_SIZE};
+use crate::gfx::backend::Backend;
+use crate::gfx::char::{AtlasTexture, CharHandle};
+use crate::gfx::display::{DisplayCommand, DisplayList};
+use crate::gfx::main_window_builder;
+use crate::gfx::paint::ToVertices;
+use glutin::dpi::PhysicalSize;
+use glutin::event_loop::EventLoop;
+use glutin::window::Window;
+use image::RgbaImage;
+use std::num::NonZeroU32;
+use std::ops::Range;
+use std::rc::Rc;
+use tracing::info_span;
+use wgpu::util::DeviceExt;
+
+/// The format of the frames painted into.  Like the OpenGL backend, colors are written as-is
+/// rather than converted to sRGB.
+const SWAP_CHAIN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Bgra8Unorm;
+/// Each vertex of a rect has a position (x, y, and z) and a color (r, g, b, and a).
+const RECT_VERTEX_FLOATS: usize = 7;
+/// Each vertex of a glyph's quad has a position (x and y), texture coordinates (s and t), and the
+/// color to tint the glyph with (r, g, b, and a).
+const TEXT_VERTEX_FLOATS: usize = 8;
+
+/// Paints with `wgpu`, which runs on Vulkan, Metal, or DX12 (whichever the platform supports).
+///
+/// TODO: Paint images, and only repaint damaged layers like the OpenGL backend does.
+pub struct WgpuBackend {
+    /// Packs the glyphs of painted text into atlases, whose textures hold the device and queue.
+    char_handle: CharHandle<WgpuAtlasTexture>,
+    device: Rc<wgpu::Device>,
+    /// The display list that was last painted.
+    display_list: DisplayList,
+    queue: Rc<wgpu::Queue>,
+    rect_pipeline: wgpu::RenderPipeline,
+    text_pipeline: wgpu::RenderPipeline,
+    swap_chain_descriptor: wgpu::SwapChainDescriptor,
+    // The swap chain must be dropped before the surface it presents to, which must be dropped
+    // before the window, so these are declared (and thus dropped) in that order.
+    swap_chain: wgpu::SwapChain,
+    surface: wgpu::Surface,
+    window: Window,
+}
+
+impl WgpuBackend {
+    pub fn new<T>(
+        event_loop: &EventLoop<T>,
+        inner_width_opt: Option<f32>,
+        inner_height_opt: Option<f32>,
+    ) -> WgpuBackend {
+        let window = main_window_builder(inner_width_opt, inner_height_opt)
+            .build(event_loop)
+            .expect("couldn't create the main window");
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        // The surface is dropped before the window (see the field order of `WgpuBackend`), so
+        // it never outlives it.
+        let surface = unsafe { instance.create_surface(&window) };
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            compatible_surface: Some(&surface),
+        }))
+        .expect("couldn't find a graphics adapter wgpu supports");
+        println!("-------------------------------------------------");
+        println!("wgpu adapter in use: {:?}", adapter.get_info());
+        println!("-------------------------------------------------");
+        let (device, queue) = pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                features: wgpu::Features::empty(),
+                limits: wgpu::Limits::default(),
+            },
+            None,
+        ))
+        .expect("couldn't open the graphics device");
+        let size = window.inner_size();
+        let swap_chain_descriptor = wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+            format: SWAP_CHAIN_FORMAT,
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        let swap_chain = device.create_swap_chain(&surface, &swap_chain_descriptor);
+        let (device, queue) = (Rc::new(device), Rc::new(queue));
+        let atlas_layout = Rc::new(build_atlas_bind_group_layout(&device));
+        let atlas_sampler = Rc::new(device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("glyph atlas sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..wgpu::SamplerDescriptor::default()
+        }));
+        let char_handle = {
+            let (device, queue) = (device.clone(), queue.clone());
+            let layout = atlas_layout.clone();
+            CharHandle::new(move |index| {
+                WgpuAtlasTexture::new(index, &device, &queue, &layout, &atlas_sampler)
+            })
+        };
+        WgpuBackend {
+            char_handle,
+            rect_pipeline: build_rect_pipeline(&device),
+            text_pipeline: build_text_pipeline(&device, &atlas_layout),
+            device,
+            display_list: DisplayList::new(),
+            queue,
+            swap_chain_descriptor,
+            swap_chain,
+            surface,
+            window,
+        }
+    }
+}
+
+impl Backend for WgpuBackend {
+    fn window(&self) -> &Window {
+        &self.window
+    }
+
+    fn resize(&mut self, size: PhysicalSize<u32>) {
+        self.swap_chain_descriptor.width = size.width;
+        self.swap_chain_descriptor.height = size.height;
+        self.swap_chain = self
+            .device
+            .create_swap_chain(&self.surface, &self.swap_chain_descriptor);
+    }
+
+    fn paint(&mut self, display_list: DisplayList, scale_factor: f32) {
+        let _span = info_span!("paint").entered();
+        let viewport_width = self.swap_chain_descriptor.width as f32;
+        let viewport_height = self.swap_chain_descriptor.height as f32;
+        let mut background = wgpu::Color::TRANSPARENT;
+        let mut rect_vertices = Vec::new();
+        let mut text_vertices = Vec::new();
+        // Each stretch of glyphs packed into the same atlas is drawn in a single call, so these
+        // are the atlas and the range of `text_vertices` of each stretch.
+        let mut text_batches: Vec<(usize, Range<u32>)> = Vec::new();
+        for command in display_list.commands() {
+            match command {
+                DisplayCommand::RectSolidColor(rgba, rect) | DisplayCommand::Border(rgba, rect) => {
+                    rect_vertices.extend((rect, rgba).to_vertices(
+                        viewport_width,
+                        viewport_height,
+                        scale_factor,
+                    ))
+                }
+                DisplayCommand::ViewportBackground(rgba) => {
+                    background = wgpu::Color {
+                        r: rgba.red_f32() as f64,
+                        g: rgba.green_f32() as f64,
+                        b: rgba.blue_f32() as f64,
+                        a: rgba.alpha_f32() as f64,
+                    }
+                }
+                DisplayCommand::Text(text_command) => {
+                    let char_commands =
+                        match self.char_handle.prepare_text(text_command, scale_factor) {
+                            Ok(char_commands) => char_commands,
+                            Err(err) => {
+                                eprintln!("couldn't prepare text to paint: {:?}", err);
+                                continue;
+                            }
+                        };
+                    for char_command in char_commands {
+                        let start = (text_vertices.len() / TEXT_VERTEX_FLOATS) as u32;
+                        text_vertices.extend(char_command.to_vertices(
+                            viewport_width,
+                            viewport_height,
+                            scale_factor,
+                        ));
+                        let end = (text_vertices.len() / TEXT_VERTEX_FLOATS) as u32;
+                        match text_batches.last_mut() {
+                            Some((atlas, vertices)) if *atlas == char_command.texture_id() => {
+                                vertices.end = end
+                            }
+                            _ => text_batches.push((char_command.texture_id(), start..end)),
+                        }
+                    }
+                }
+                DisplayCommand::HitTestArea(..)
+                | DisplayCommand::Image(_)
+                | DisplayCommand::LayerBoundary => {}
+            }
+        }
+
+        let frame = match self.swap_chain.get_current_frame() {
+            Ok(frame) => frame,
+            Err(err) => {
+                // The swap chain is usually lost or outdated because the window changed, so make
+                // it again for the next paint.
+                eprintln!("couldn't get a frame to paint: {:?}", err);
+                self.resize(self.window.inner_size());
+                return;
+            }
+        };
+        let rect_buffer = self.vertex_buffer("rect vertices", &rect_vertices);
+        let text_buffer = self.vertex_buffer("text vertices", &text_vertices);
+        let atlases = self.char_handle.atlases();
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[wgpu::RenderPassColorAttachment {
+                    view: &frame.output.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(background),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+            if let Some(rect_buffer) = &rect_buffer {
+                render_pass.set_pipeline(&self.rect_pipeline);
+                render_pass.set_vertex_buffer(0, rect_buffer.slice(..));
+                render_pass.draw(0..(rect_vertices.len() / RECT_VERTEX_FLOATS) as u32, 0..1);
+            }
+            // Like the OpenGL backend, text is painted over rects.
+            if let Some(text_buffer) = &text_buffer {
+                render_pass.set_pipeline(&self.text_pipeline);
+                render_pass.set_vertex_buffer(0, text_buffer.slice(..));
+                for (atlas, vertices) in text_batches {
+                    render_pass.set_bind_group(0, &atlases[atlas].texture().bind_group, &[]);
+                    render_pass.draw(vertices, 0..1);
+                }
+            }
+        }
+        self.queue.submit(std::iter::once(encoder.finish()));
+        // The frame is presented once it's dropped.
+        drop(frame);
+        self.display_list = display_list;
+    }
+
+    fn display_list(&self) -> &DisplayList {
+        &self.display_list
+    }
+}
+
+impl WgpuBackend {
+    /// Makes a vertex buffer holding `vertices`, or returns `None` if there are none.
+    fn vertex_buffer(&self, label: &str, vertices: &[f32]) -> Option<wgpu::Buffer> {
+        if vertices.is_empty() {
+            return None;
+        }
+        Some(
+            self.device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(label),
+                    contents: bytemuck::cast_slice(vertices),
+                    usage: wgpu::BufferUsage::VERTEX,
+                }),
+        )
+    }
+}
+
+/// A `wgpu` texture glyphs are packed into, bound along with the sampler glyphs are painted with.
+struct WgpuAtlasTexture {
+    bind_group: wgpu::BindGroup,
+    /// The index of the atlas, which identifies it to the painter.
+    index: usize,
+    queue: Rc<wgpu::Queue>,
+    texture: wgpu::Texture,
+}
+
+impl WgpuAtlasTexture {
+    fn new(
+        index: usize,
+        device: &wgpu::Device,
+        queue: &Rc<wgpu::Queue>,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+    ) -> WgpuAtlasTexture {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("glyph atlas"),
+            size: wgpu::Extent3d {
+                width: ATLAS_SIZE,
+                height: ATLAS_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("glyph atlas bind group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+        });
+        let atlas_texture = WgpuAtlasTexture {
+            bind_group,
+            index,
+            queue: queue.clone(),
+            texture,
+        };
+        let all = AtlasRect {
+            x: 0,
+            y: 0,
+            width: ATLAS_SIZE,
+            height: ATLAS_SIZE,
+        };
+        atlas_texture.write(all, &vec![0u8; (ATLAS_SIZE * ATLAS_SIZE * 4) as usize]);
+        atlas_texture
+    }
+
+    /// Copies the RGBA `pixels` of `rect`, row by row, into the texture.
+    fn write(&self, rect: AtlasRect, pixels: &[u8]) {
+        self.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: rect.x,
+                    y: rect.y,
+                    z: 0,
+                },
+            },
+            pixels,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(rect.width * 4),
+                rows_per_image: NonZeroU32::new(rect.height),
+            },
+            wgpu::Extent3d {
+                width: rect.width,
+                height: rect.height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+}
+
+impl AtlasTexture for WgpuAtlasTexture {
+    type Id = usize;
+
+    fn id(&self) -> usize {
+        self.index
+    }
+
+    fn upload(&self, rect: AtlasRect, image: &RgbaImage) {
+        self.write(rect, image.as_raw());
+    }
+}
+
+/// Lays out the bindings of the text pipeline: a glyph atlas, and the sampler it's sampled with.
+fn build_atlas_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("glyph atlas bind group layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::Sampler {
+                    filtering: true,
+                    comparison: false,
+                },
+                count: None,
+            },
+        ],
+    })
+}
+
+fn build_rect_pipeline(device: &wgpu::Device) -> wgpu::RenderPipeline {
+    let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+        label: Some("rect shader"),
+        source: wgpu::ShaderSource::Wgsl(
+            include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/shader_src/rect.wgsl")).into(),
+        ),
+        flags: wgpu::ShaderFlags::all(),
+    });
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("rect pipeline layout"),
+        bind_group_layouts: &[],
+        push_constant_ranges: &[],
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("rect pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[wgpu::VertexBufferLayout {
+                array_stride: (RECT_VERTEX_FLOATS * std::mem::size_of::<f32>())
+                    as wgpu::BufferAddress,
+                step_mode: wgpu::InputStepMode::Vertex,
+                attributes: &wgpu::vertex_attr_array![0 => Float3, 1 => Float4],
+            }],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[wgpu::ColorTargetState {
+                format: SWAP_CHAIN_FORMAT,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrite::ALL,
+            }],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+    })
+}
+
+fn build_text_pipeline(
+    device: &wgpu::Device,
+    atlas_layout: &wgpu::BindGroupLayout,
+) -> wgpu::RenderPipeline {
+    let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+        label: Some("text shader"),
+        source: wgpu::ShaderSource::Wgsl(
+            include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/shader_src/text.wgsl")).into(),
+        ),
+        flags: wgpu::ShaderFlags::all(),
+    });
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("text pipeline layout"),
+        bind_group_layouts: &[atlas_layout],
+        push_constant_ranges: &[],
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("text pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[wgpu::VertexBufferLayout {
+                array_stride: (TEXT_VERTEX_FLOATS * std::mem::size_of::<f32>())
+                    as wgpu::BufferAddress,
+                step_mode: wgpu::InputStepMode::Vertex,
+                attributes: &wgpu::vertex_attr_array![0 => Float2, 1 => Float2, 2 => Float4],
+            }],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[wgpu::ColorTargetState {
+                format: SWAP_CHAIN_FORMAT,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrite::ALL,
+            }],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+    })
+}
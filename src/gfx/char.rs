@@ -1,221 +1,344 @@
-use crate::gfx::display::{CharCommand, DisplayCommand, DisplayList};
-use crate::gfx::font::{FontError, PostscriptName};
-use crate::style::values::CSSFloat;
+use crate::gfx::atlas::{AtlasRect, ShelfAllocator, ATLAS_SIZE};
+use crate::gfx::display::TextCommand;
+use crate::gfx::glyph_raster::{
+    place_glyphs, GlyphBitmap, GlyphKey, GlyphRasterError, GlyphRasterizer,
+};
 use accountable_refcell::{Ref, RefCell};
-use app_units::Au;
 use cssparser::RGBA;
-use font_kit::canvas::{Canvas, Format, RasterizationOptions};
-use font_kit::error::GlyphLoadingError;
-use font_kit::font::Font;
-use font_kit::hinting::HintingOptions;
-use gl::texture::{Texture, TextureKind};
+use gl::texture::{Texture, TextureId, TextureKind};
 use gl::types::GLint;
 use gl::{
-    Gl, CLAMP_TO_EDGE, LINEAR, RED, TEXTURE_2D, TEXTURE_MAG_FILTER, TEXTURE_MIN_FILTER,
-    TEXTURE_WRAP_S, TEXTURE_WRAP_T, UNPACK_ALIGNMENT, UNSIGNED_BYTE,
+    Gl, CLAMP_TO_EDGE, LINEAR, RGBA as GL_RGBA, TEXTURE_2D, TEXTURE_MAG_FILTER, TEXTURE_MIN_FILTER,
+    TEXTURE_WRAP_S, TEXTURE_WRAP_T, UNSIGNED_BYTE,
 };
-use pathfinder_geometry::transform2d::Transform2F;
-use pathfinder_geometry::vector::{Vector2F, Vector2I};
+use image::RgbaImage;
+use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::vector::Vector2F;
 use std::collections::HashMap;
+use std::fmt::Debug;
 use std::os::raw::c_void;
 
-#[derive(Debug)]
-pub struct OpenglChar {
-    /// Offset to advance to next glyph.
-    advance: Vector2F,
-    /// Offset from baseline to left/top of glyph.
+/// A glyph rasterized into a glyph atlas at a particular font, size, and subpixel offset.
+#[derive(Clone, Copy, Debug)]
+pub struct PackedChar<Id> {
+    /// Offset from the glyph's origin, on the baseline, to the top left of the rasterized glyph,
+    /// in device pixels.
     bearing: Vector2F,
-    /// The size of the character in pixels.
-    size_px: CSSFloat,
-    /// The OpenGL texture associated with this character.
-    texture: Texture,
-    /// The actual character value.
-    val: char,
+    /// The size of the rasterized glyph, in device pixels.
+    size: Vector2F,
+    /// Where the rasterized glyph is in the atlas it was packed into.
+    texture: GlyphTexture<Id>,
 }
 
-impl OpenglChar {
-    pub fn new(ch: char, size_px: i32, font: &Font, gl: &Gl) -> Result<OpenglChar, CharError> {
-        let glyph_id = match font.glyph_for_char(ch) {
-            Some(id) => id,
-            None => return Err(CharError::NoIdForChar),
-        };
-        let mut canvas = Canvas::new(Vector2I::splat(size_px), Format::A8);
-        font.rasterize_glyph(
-            &mut canvas,
-            glyph_id,
-            size_px as f32,
-            Transform2F::from_translation(Vector2F::new(0.0, size_px as f32)),
-            HintingOptions::None,
-            RasterizationOptions::GrayscaleAa,
-        )?;
-        // TODO: Implement texture packing.  Currently, for each font and size, a new OpenGL texture
-        // is generated along with each character and cached.  It would be better if we used one
-        // texture for each font and size, packing all rasterizations of each character into this
-        // singular texture.  This avoids rapid texture swapping when rendering lines of text,
-        // which can cause serious perf issues.
-        let texture = OpenglChar::setup_texture(gl, &canvas);
-
-        // Divide by 64 because these are represented as FreeType font units, which are 1/64th
-        // of a pixel.  We want pixel units.
-        // TODO: Represent font units as a type to avoid confusion and ease conversion?
-        let advance_font_units = font.advance(glyph_id)?;
-        let advance = Vector2F::new(advance_font_units.x() / 64.0, advance_font_units.y() / 64.0);
-        let bearing_font_units = font.origin(glyph_id)?;
-        let bearing = Vector2F::new(bearing_font_units.x() / 64.0, bearing_font_units.y() / 64.0);
-
-        Ok(OpenglChar {
-            advance,
-            bearing,
-            size_px: size_px as f32,
-            texture,
-            val: ch,
-        })
+impl<Id: Copy> PackedChar<Id> {
+    pub fn bearing(&self) -> Vector2F {
+        self.bearing
     }
 
-    pub fn advance(&self) -> Vector2F {
-        self.advance
+    pub fn size(&self) -> Vector2F {
+        self.size
     }
 
-    pub fn bearing(&self) -> Vector2F {
-        self.bearing
+    pub fn texture(&self) -> GlyphTexture<Id> {
+        self.texture
     }
+}
 
-    pub fn size_px(&self) -> CSSFloat {
-        self.size_px
-    }
+/// A texture of whichever graphics API paints glyphs, `ATLAS_SIZE` pixels square, that glyphs are
+/// packed into.  Atlases start out transparent, since the padding between glyphs is never written
+/// to.
+pub trait AtlasTexture {
+    /// Identifies the texture to the painter, so that it can paint glyphs from it.
+    type Id: Copy + Debug + PartialEq;
+
+    fn id(&self) -> Self::Id;
+
+    /// Copies `image` into `rect` of the texture.
+    fn upload(&self, rect: AtlasRect, image: &RgbaImage);
+}
 
-    fn setup_texture(gl: &Gl, rasterized_canvas: &Canvas) -> Texture {
+/// An OpenGL texture glyphs are packed into.
+pub struct OpenglAtlasTexture {
+    gl: Gl,
+    texture: Texture,
+}
+
+impl OpenglAtlasTexture {
+    pub fn new(gl: &Gl) -> OpenglAtlasTexture {
         let texture = Texture::new(TextureKind::TwoDimensional, gl);
+        let pixels = vec![0u8; (ATLAS_SIZE * ATLAS_SIZE * 4) as usize];
         unsafe {
-            // Set alignment to a single byte since we only use one byte per pixel.
-            gl.PixelStorei(UNPACK_ALIGNMENT, 1);
             gl.BindTexture(TEXTURE_2D, texture.id());
             gl.TexImage2D(
                 TEXTURE_2D,
                 0,
-                RED as GLint,
-                rasterized_canvas.size.x(),
-                rasterized_canvas.size.y(),
+                GL_RGBA as GLint,
+                ATLAS_SIZE as GLint,
+                ATLAS_SIZE as GLint,
                 0,
-                RED,
+                GL_RGBA,
                 UNSIGNED_BYTE,
-                rasterized_canvas.pixels.as_ptr() as *const c_void,
+                pixels.as_ptr() as *const c_void,
             );
             gl.TexParameteri(TEXTURE_2D, TEXTURE_WRAP_S, CLAMP_TO_EDGE as GLint);
             gl.TexParameteri(TEXTURE_2D, TEXTURE_WRAP_T, CLAMP_TO_EDGE as GLint);
             gl.TexParameteri(TEXTURE_2D, TEXTURE_MIN_FILTER, LINEAR as GLint);
             gl.TexParameteri(TEXTURE_2D, TEXTURE_MAG_FILTER, LINEAR as GLint);
-            // Reset byte-alignment to default value of 4.
-            gl.PixelStorei(UNPACK_ALIGNMENT, 4);
+            gl.BindTexture(TEXTURE_2D, 0);
+        }
+        OpenglAtlasTexture {
+            gl: gl.clone(),
+            texture,
         }
-        texture
+    }
+}
+
+impl AtlasTexture for OpenglAtlasTexture {
+    type Id = TextureId;
+
+    fn id(&self) -> TextureId {
+        self.texture.id()
+    }
+
+    fn upload(&self, rect: AtlasRect, image: &RgbaImage) {
+        unsafe {
+            self.gl.BindTexture(TEXTURE_2D, self.texture.id());
+            self.gl.TexSubImage2D(
+                TEXTURE_2D,
+                0,
+                rect.x as GLint,
+                rect.y as GLint,
+                rect.width as GLint,
+                rect.height as GLint,
+                GL_RGBA,
+                UNSIGNED_BYTE,
+                image.as_raw().as_ptr() as *const c_void,
+            );
+            self.gl.BindTexture(TEXTURE_2D, 0);
+        }
+    }
+}
+
+/// A texture glyphs are packed into, and the room it has left.
+pub struct Atlas<T> {
+    allocator: ShelfAllocator,
+    texture: T,
+}
+
+impl<T> Atlas<T> {
+    pub fn texture(&self) -> &T {
+        &self.texture
     }
 }
 
 #[derive(Debug)]
 pub enum CharError {
-    Font(FontError),
-    Loading(GlyphLoadingError),
-    NoIdForChar,
+    Raster(GlyphRasterError),
+    /// The rasterized glyph is too large to fit in an atlas.
+    TooLarge,
 }
 
-impl From<GlyphLoadingError> for CharError {
-    fn from(err: GlyphLoadingError) -> Self {
-        CharError::Loading(err)
+impl From<GlyphRasterError> for CharError {
+    fn from(err: GlyphRasterError) -> Self {
+        CharError::Raster(err)
     }
 }
 
-impl From<FontError> for CharError {
-    fn from(err: FontError) -> Self {
-        CharError::Font(err)
-    }
+/// Packs rasterized glyphs into atlases, so that each glyph is uploaded only once per font, size,
+/// and subpixel offset.  Each backend packs glyphs into textures of its own graphics API.
+pub struct CharHandle<T: AtlasTexture> {
+    /// The glyphs packed so far, or `None` for those with nothing to paint.
+    cached_chars: RefCell<HashMap<GlyphKey, Option<PackedChar<T::Id>>>>,
+    /// The atlases glyphs have been packed into.  Only the last one still has room for more.
+    atlases: RefCell<Vec<Atlas<T>>>,
+    /// Makes the texture of a new atlas, given the index of the atlas.
+    new_texture: Box<dyn Fn(usize) -> T>,
+    rasterizer: GlyphRasterizer,
 }
 
-pub struct CharHandle {
-    cached_chars: RefCell<HashMap<(PostscriptName, Au), HashMap<char, OpenglChar>>>,
-    gl: Gl,
+impl CharHandle<OpenglAtlasTexture> {
+    pub fn new_opengl(gl: &Gl) -> Self {
+        let gl = gl.clone();
+        CharHandle::new(move |_| OpenglAtlasTexture::new(&gl))
+    }
 }
 
-impl CharHandle {
-    pub fn new(gl: &Gl) -> Self {
+impl<T: AtlasTexture> CharHandle<T> {
+    pub fn new(new_texture: impl Fn(usize) -> T + 'static) -> Self {
         CharHandle {
             cached_chars: RefCell::new(HashMap::new()),
-            gl: gl.clone(),
+            atlases: RefCell::new(Vec::new()),
+            new_texture: Box::new(new_texture),
+            rasterizer: GlyphRasterizer::new(),
         }
     }
 
-    pub fn get_char(
-        &self,
-        font: &Font,
-        font_size: Au,
-        ch: char,
-    ) -> Result<Ref<OpenglChar>, CharError> {
-        let postscript_name = font.postscript_name().unwrap_or_else(|| {
-            panic!(
-                "couldn't get font postscript name for font with `full_name`: {}",
-                font.full_name()
-            )
-        });
-        let font_and_size_key = (postscript_name, font_size);
+    /// The atlases glyphs have been packed into so far.
+    pub fn atlases(&self) -> Ref<Vec<Atlas<T>>> {
+        self.atlases.borrow()
+    }
+
+    /// Returns the glyph identified by `key` packed into an atlas, rasterizing it if it hasn't
+    /// been yet.  Returns `None` for glyphs with nothing to paint, such as spaces.
+    pub fn get_char(&self, key: &GlyphKey) -> Result<Option<PackedChar<T::Id>>, CharError> {
+        if let Some(packed_char) = self.cached_chars.borrow().get(key) {
+            return Ok(*packed_char);
+        }
+        let packed_char = match self.rasterizer.rasterize(key)? {
+            Some(bitmap) => Some(self.pack(&bitmap)?),
+            None => None,
+        };
+        self.cached_chars
+            .borrow_mut()
+            .insert(key.clone(), packed_char);
+        Ok(packed_char)
+    }
+
+    /// Packs `bitmap` into the last atlas, or into a new one if the last is full.
+    fn pack(&self, bitmap: &GlyphBitmap) -> Result<PackedChar<T::Id>, CharError> {
+        let (width, height) = bitmap.image.dimensions();
+        let mut atlases = self.atlases.borrow_mut();
+        let rect = match atlases
+            .last_mut()
+            .and_then(|atlas| atlas.allocator.allocate(width, height))
         {
-            let mut font_to_chars = self.cached_chars.borrow_mut();
-            let chars = match font_to_chars.get_mut(&font_and_size_key) {
-                Some(chars) => chars,
-                None => {
-                    font_to_chars.insert(font_and_size_key.clone(), HashMap::new());
-                    font_to_chars.get_mut(&font_and_size_key).unwrap()
-                }
-            };
-            if !chars.contains_key(&ch) {
-                chars.insert(ch, OpenglChar::new(ch, font_size.to_px(), font, &self.gl)?);
+            Some(rect) => rect,
+            None => {
+                let mut allocator = ShelfAllocator::new(ATLAS_SIZE);
+                let rect = allocator
+                    .allocate(width, height)
+                    .ok_or(CharError::TooLarge)?;
+                let texture = (self.new_texture)(atlases.len());
+                atlases.push(Atlas { allocator, texture });
+                rect
             }
-        }
-        let cached_chars = self.cached_chars.borrow();
-        let char_ref = Ref::map(cached_chars, |font_to_chars| {
-            let chars = font_to_chars.get(&font_and_size_key).unwrap();
-            chars.get(&ch).unwrap()
-        });
-        Ok(char_ref)
+        };
+        let atlas = atlases.last().unwrap();
+        atlas.texture.upload(rect, &bitmap.image);
+        let size = Vector2F::new(width as f32, height as f32);
+        let tex_coords_per_px = 1.0 / ATLAS_SIZE as f32;
+        let tex_coords = RectF::new(
+            Vector2F::new(rect.x as f32, rect.y as f32) * tex_coords_per_px,
+            size * tex_coords_per_px,
+        );
+        Ok(PackedChar {
+            bearing: bitmap.origin.to_f32(),
+            size,
+            texture: GlyphTexture {
+                id: atlas.texture.id(),
+                is_color: bitmap.is_color,
+                tex_coords,
+            },
+        })
     }
 
-    // TODO: Not sure these `prepare` functions belong here in CharHandle...
-    pub fn prepare_char(
-        &self,
-        display_list: &mut DisplayList,
-        ch: char,
-        color: RGBA,
-        font: &Font,
-        size: Au,
-        scale_factor: f32,
-    ) -> Result<(), CharError> {
-        let opengl_char = self.get_char(font, size, ch)?;
-        display_list.push(DisplayCommand::Char(CharCommand::new(
-            opengl_char.advance(),
-            opengl_char.bearing(),
-            ch,
-            color,
-            Vector2F::new(size.to_f32_px(), size.to_f32_px()) * scale_factor,
-            // TODO: These starting x and y coordinates need to be determined by layout once inline
-            // layout is implemented.  For now, all characters will be painted over the top of  each
-            // other at 0,0.
-            Vector2F::new(300., 300.),
-            opengl_char.texture.id(),
-        )));
-        Ok(())
-    }
-
-    pub fn prepare_str(
+    /// Prepares the glyphs of `text_command` to be painted at `scale_factor` device pixels per CSS
+    /// pixel.  Glyphs that fall partly outside of the command's clip are cropped to it.
+    pub fn prepare_text(
         &self,
-        display_list: &mut DisplayList,
-        color: RGBA,
-        font: &Font,
-        str: &str,
-        size: Au,
+        text_command: &TextCommand,
         scale_factor: f32,
-    ) -> Result<(), CharError> {
-        for char in str.chars() {
-            self.prepare_char(display_list, char, color, font, size, scale_factor)?;
+    ) -> Result<Vec<CharCommand<T::Id>>, CharError> {
+        let clip = text_command.clip().map(|clip| {
+            let clip = clip.scaled_by(scale_factor);
+            RectF::new(
+                Vector2F::new(clip.start_x, clip.start_y),
+                Vector2F::new(clip.width.px(), clip.height.px()),
+            )
+        });
+        let mut char_commands = Vec::new();
+        for glyph in place_glyphs(text_command, scale_factor) {
+            let packed_char = match self.get_char(&glyph.key)? {
+                Some(packed_char) => packed_char,
+                None => continue,
+            };
+            let quad = RectF::new(
+                glyph.origin.to_f32() + packed_char.bearing(),
+                packed_char.size(),
+            );
+            let visible = match clip {
+                Some(clip) => match quad.intersection(clip) {
+                    Some(visible) => visible,
+                    None => continue,
+                },
+                None => quad,
+            };
+            // Crop the texture coordinates by as much as the quad was cropped.
+            let tex_coords = packed_char.texture().tex_coords;
+            let tex_coords_per_px = tex_coords.size() / quad.size();
+            let texture = GlyphTexture {
+                tex_coords: RectF::new(
+                    tex_coords.origin() + (visible.origin() - quad.origin()) * tex_coords_per_px,
+                    visible.size() * tex_coords_per_px,
+                ),
+                ..packed_char.texture()
+            };
+            char_commands.push(CharCommand {
+                bearing: visible.origin() - glyph.origin.to_f32(),
+                color: text_command.color(),
+                size: visible.size(),
+                start_coords: glyph.origin.to_f32() * (1.0 / scale_factor),
+                texture,
+            });
         }
-        Ok(())
+        Ok(char_commands)
     }
 }
+
+/// A glyph ready to be painted from the atlas it was packed into.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CharCommand<Id> {
+    /// Offset from the glyph's origin, on the baseline, to the top left of the part of the
+    /// rasterized glyph to paint, in device pixels.
+    bearing: Vector2F,
+    /// The color to render the char as.
+    color: RGBA,
+    /// The size of the part of the rasterized glyph to paint, in device pixels.
+    size: Vector2F,
+    /// The x and y coordinates, in CSS pixels, of where the glyph origin should be placed on the
+    /// viewport.
+    start_coords: Vector2F,
+    /// Where the part of the rasterized glyph to paint is in the atlas it was packed into.
+    texture: GlyphTexture<Id>,
+}
+
+impl<Id: Copy> CharCommand<Id> {
+    pub fn bearing(&self) -> Vector2F {
+        self.bearing
+    }
+
+    pub fn color(&self) -> RGBA {
+        self.color
+    }
+
+    pub fn size(&self) -> Vector2F {
+        self.size
+    }
+
+    pub fn start_coords(&self) -> Vector2F {
+        self.start_coords
+    }
+
+    pub fn texture_id(&self) -> Id {
+        self.texture.id
+    }
+
+    pub fn is_color(&self) -> bool {
+        self.texture.is_color
+    }
+
+    pub fn tex_coords(&self) -> RectF {
+        self.texture.tex_coords
+    }
+}
+
+/// Where a rasterized glyph is in the texture (a glyph atlas) it was packed into.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GlyphTexture<Id> {
+    pub id: Id,
+    /// Whether the glyph is in its own colors (e.g. for emoji), rather than white to be tinted with
+    /// the text color.
+    pub is_color: bool,
+    /// The texture coordinates of the glyph's top left corner and size.
+    pub tex_coords: RectF,
+}
@@ -0,0 +1,246 @@
+//! Rasterization of color glyphs, such as those of color emoji fonts, which are painted in their
+//! own colors rather than in the color of the text.
+//!
+//! Two kinds of color glyphs are supported: layered glyphs (`COLR`/`CPAL` tables), made up of
+//! other glyphs each filled with a color, and bitmap glyphs (`CBDT` and `sbix` tables), which are
+//! PNG images.
+//!
+//! https://docs.microsoft.com/en-us/typography/opentype/spec/colr
+
+use cssparser::RGBA;
+use font_kit::canvas::{Canvas, Format, RasterizationOptions};
+use font_kit::font::Font;
+use font_kit::hinting::HintingOptions;
+use image::imageops::FilterType;
+use image::{ImageFormat, RgbaImage};
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::{Vector2F, Vector2I};
+use std::convert::TryFrom;
+use ttf_parser::{Face, GlyphId, RasterImageFormat, Tag};
+
+/// Rasterizes the glyph `glyph_id` of `font` in color into a square image `size_px` pixels wide,
+/// with the glyph's origin at the bottom left, like glyphs rasterized in the text color.  Returns
+/// `None` if the glyph has no color.
+///
+/// `font_data` and `font_index` are the data of `font`, and the index of `font` within it.
+pub fn rasterize_color_glyph(
+    font_data: &[u8],
+    font_index: u32,
+    font: &Font,
+    glyph_id: u32,
+    size_px: i32,
+) -> Option<RgbaImage> {
+    let face = Face::from_slice(font_data, font_index).ok()?;
+    let glyph_id = u16::try_from(glyph_id).ok()?;
+    let size = size_px.max(1) as u32;
+    let mut image = RgbaImage::new(size, size);
+
+    let colr = face.table_data(Tag::from_bytes(b"COLR"));
+    let cpal = face.table_data(Tag::from_bytes(b"CPAL"));
+    if let Some(layers) = colr.and_then(|colr| color_layers(colr, cpal?, glyph_id)) {
+        for layer in layers {
+            let mut coverage = Canvas::new(Vector2I::splat(size_px), Format::A8);
+            font.rasterize_glyph(
+                &mut coverage,
+                u32::from(layer.glyph_id),
+                size_px as f32,
+                Transform2F::from_translation(Vector2F::new(0.0, size_px as f32)),
+                HintingOptions::None,
+                RasterizationOptions::GrayscaleAa,
+            )
+            .ok()?;
+            // TODO: Paint layers in the text's color rather than black once color glyphs are
+            // cached by color.
+            let color = layer.color.unwrap_or_else(|| RGBA::new(0, 0, 0, 255));
+            paint_coverage(&mut image, &coverage.pixels, coverage.stride, color);
+        }
+        return Some(image);
+    }
+
+    let raster =
+        face.glyph_raster_image(GlyphId(glyph_id), size.min(u32::from(u16::MAX)) as u16)?;
+    if raster.format != RasterImageFormat::PNG {
+        return None;
+    }
+    let bitmap = image::load_from_memory_with_format(raster.data, ImageFormat::Png)
+        .ok()?
+        .to_rgba8();
+    // Bitmaps come in a few sizes (strikes), so scale the nearest one to the size being painted.
+    let scale = size_px as f32 / f32::from(raster.pixels_per_em.max(1));
+    let width = (f32::from(raster.width) * scale).round().max(1.) as u32;
+    let height = (f32::from(raster.height) * scale).round().max(1.) as u32;
+    let bitmap = image::imageops::resize(&bitmap, width, height, FilterType::Triangle);
+    // The bitmap's offset is from the glyph's origin to its bottom left corner.
+    let left = (f32::from(raster.x) * scale).round() as i64;
+    let top = size_px as i64 - (f32::from(raster.y) * scale).round() as i64 - height as i64;
+    paint_image(&mut image, &bitmap, left, top);
+    Some(image)
+}
+
+/// A layer of a layered color glyph: another glyph, filled with a color.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct ColorLayer {
+    glyph_id: u16,
+    /// The color to fill the layer with, or `None` to fill it with the text's color.
+    color: Option<RGBA>,
+}
+
+/// Returns the layers of `glyph_id`, from bottom to top, in the first palette of a font with the
+/// given `COLR` (version 0) and `CPAL` tables.  Returns `None` if the glyph has no layers.
+///
+/// https://docs.microsoft.com/en-us/typography/opentype/spec/cpal
+fn color_layers(colr: &[u8], cpal: &[u8], glyph_id: u16) -> Option<Vec<ColorLayer>> {
+    let base_glyph_count = read_u16(colr, 2)? as usize;
+    let base_glyphs_offset = read_u32(colr, 4)? as usize;
+    let layers_offset = read_u32(colr, 8)? as usize;
+    // Base glyph records are six bytes each: glyph id, index of the first layer, layer count.
+    let (first_layer, layer_count) = (0..base_glyph_count).find_map(|index| {
+        let record = base_glyphs_offset + index * 6;
+        if read_u16(colr, record)? != glyph_id {
+            return None;
+        }
+        Some((read_u16(colr, record + 2)?, read_u16(colr, record + 4)?))
+    })?;
+
+    let palette_entry_count = read_u16(cpal, 2)?;
+    let color_records_offset = read_u32(cpal, 8)? as usize;
+    let first_palette_color = read_u16(cpal, 12)? as usize;
+    (first_layer..first_layer.checked_add(layer_count)?)
+        .map(|layer| {
+            // Layer records are four bytes each: glyph id, palette entry index.
+            let record = layers_offset + layer as usize * 4;
+            let palette_index = read_u16(colr, record + 2)?;
+            // Palette index 0xFFFF means the text's color.
+            let color = if palette_index == 0xFFFF {
+                None
+            } else if palette_index < palette_entry_count {
+                // Color records are four bytes each, in BGRA order.
+                let color =
+                    color_records_offset + (first_palette_color + palette_index as usize) * 4;
+                let bgra = cpal.get(color..color + 4)?;
+                Some(RGBA::new(bgra[2], bgra[1], bgra[0], bgra[3]))
+            } else {
+                return None;
+            };
+            Some(ColorLayer {
+                glyph_id: read_u16(colr, record)?,
+                color,
+            })
+        })
+        .collect()
+}
+
+pub fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Paints `color` over `image` wherever `coverage`, an 8-bit coverage mask the size of `image`
+/// whose rows are `stride` bytes apart, covers it.
+fn paint_coverage(image: &mut RgbaImage, coverage: &[u8], stride: usize, color: RGBA) {
+    let width = image.width() as usize;
+    for (index, pixel) in image.pixels_mut().enumerate() {
+        let (x, y) = (index % width, index / width);
+        let covered = coverage.get(y * stride + x).copied().unwrap_or(0);
+        blend(
+            &mut pixel.0,
+            [color.red, color.green, color.blue],
+            (u32::from(color.alpha) * u32::from(covered) / 255) as u8,
+        );
+    }
+}
+
+/// Paints `source` over `image`, with its top left corner at `left`, `top`.
+fn paint_image(image: &mut RgbaImage, source: &RgbaImage, left: i64, top: i64) {
+    for (x, y, source_pixel) in source.enumerate_pixels() {
+        let (image_x, image_y) = (left + i64::from(x), top + i64::from(y));
+        if image_x < 0
+            || image_y < 0
+            || image_x >= i64::from(image.width())
+            || image_y >= i64::from(image.height())
+        {
+            continue;
+        }
+        let [red, green, blue, alpha] = source_pixel.0;
+        blend(
+            &mut image.get_pixel_mut(image_x as u32, image_y as u32).0,
+            [red, green, blue],
+            alpha,
+        );
+    }
+}
+
+/// Composites `color` at `alpha` over `pixel`, whose color isn't premultiplied by its alpha.
+fn blend(pixel: &mut [u8; 4], color: [u8; 3], alpha: u8) {
+    let source_alpha = f32::from(alpha) / 255.;
+    let dest_alpha = f32::from(pixel[3]) / 255.;
+    let out_alpha = source_alpha + dest_alpha * (1. - source_alpha);
+    if out_alpha <= 0. {
+        return;
+    }
+    for (channel, source) in pixel.iter_mut().zip(color.iter()) {
+        let source = f32::from(*source) * source_alpha;
+        let dest = f32::from(*channel) * dest_alpha * (1. - source_alpha);
+        *channel = ((source + dest) / out_alpha).round() as u8;
+    }
+    pixel[3] = (out_alpha * 255.).round() as u8;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_layers_from_the_first_palette() {
+        // One base glyph (5) with two layers: glyph 6 in palette entry 1, and glyph 7 in the text
+        // color.
+        let colr = [
+            0, 0, // version
+            0, 1, // base glyph count
+            0, 0, 0, 14, // base glyph records offset
+            0, 0, 0, 20, // layer records offset
+            0, 2, // layer count
+            0, 5, 0, 0, 0, 2, // base glyph 5: first layer 0, two layers
+            0, 6, 0, 1, // layer: glyph 6, palette entry 1
+            0, 7, 0xFF, 0xFF, // layer: glyph 7, text color
+        ];
+        let cpal = [
+            0, 0, // version
+            0, 2, // palette entry count
+            0, 1, // palette count
+            0, 2, // color record count
+            0, 0, 0, 14, // color records offset
+            0, 0, // first color of palette 0
+            10, 20, 30, 255, // entry 0, BGRA
+            40, 50, 60, 128, // entry 1, BGRA
+        ];
+        assert_eq!(
+            color_layers(&colr, &cpal, 5),
+            Some(vec![
+                ColorLayer {
+                    glyph_id: 6,
+                    color: Some(RGBA::new(60, 50, 40, 128)),
+                },
+                ColorLayer {
+                    glyph_id: 7,
+                    color: None,
+                },
+            ])
+        );
+        assert_eq!(color_layers(&colr, &cpal, 6), None);
+    }
+
+    #[test]
+    fn layers_are_composited_over_each_other() {
+        let mut image = RgbaImage::new(2, 1);
+        paint_coverage(&mut image, &[255, 0], 2, RGBA::new(255, 0, 0, 255));
+        paint_coverage(&mut image, &[128, 255], 2, RGBA::new(0, 0, 255, 255));
+        assert_eq!(image.get_pixel(0, 0).0, [127, 0, 128, 255]);
+        assert_eq!(image.get_pixel(1, 0).0, [0, 0, 255, 255]);
+    }
+}
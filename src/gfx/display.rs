@@ -1,37 +1,68 @@
-use crate::gfx::char::CharHandle;
+use crate::dom::details;
+use crate::dom::focus;
+use crate::dom::form::{self, FormControlKind};
+use crate::dom::tree::{NodeRef, OpaqueNode};
+use crate::gfx::glyph::GlyphRun;
+use crate::image::cache::ImageCache;
+use crate::image::LoadedImage;
 use crate::layout::behavior::BaseLayoutBoxBehavior;
-use crate::layout::layout_box::LayoutBox;
-use crate::layout::rect::Rect;
-use crate::style::values::computed::LineStyle;
+use crate::layout::dimensions::Dimensions;
+use crate::layout::flow::block::BlockLevelBox;
+use crate::layout::flow::inline::{AnonymousInlineBox, InlineLevelBox, InlineLevelContent};
+use crate::layout::layout_box::{is_root_body, LayoutBox};
+use crate::layout::rect::{Point, Rect};
+use crate::layout::replaced::{ReplacedBox, LINE_HEIGHT_EM};
+use crate::layout::text::shape_text;
+use crate::layout::{dump_layout_dom_path, DumpLayoutFormat};
+use crate::style::values::computed::length::CSSPixelLength;
+use crate::style::values::computed::{BackgroundImage, ComputedValues, LineStyle};
 use crate::Side;
 use cssparser::RGBA;
-use gl::texture::TextureId;
 use pathfinder_geometry::vector::Vector2F;
+use std::io::Write;
+use std::time::Duration;
+use tracing::info_span;
 
 /// Builds list of display commands that should be used to paint the output.
 pub fn build_display_list(
     layout_box: &LayoutBox,
-    _char_handle: &CharHandle,
-    _scale_factor: f32,
+    image_cache: &ImageCache,
+    animation_time: Duration,
+    scale_factor: f32,
 ) -> DisplayList {
-    let mut display_list = Vec::new();
-    // TODO: Remove the three preceeding statements once text rendering is fixed.
-    // let font_handle = FontHandle::new();
-    // let font = font_handle.get_font("Helvetica").unwrap();
-    // char_handle
-    //     .prepare_char(
-    //         &mut display_list,
-    //         'A',
-    //         RGBA::new(0, 0, 0, 1),
-    //         &font,
-    //         Au::from_f32_px(12.),
-    //         scale_factor,
-    //     )
-    //     .unwrap();
-    prepare_layout_box(&mut display_list, &layout_box);
+    let _span = info_span!("build_display_list").entered();
+    let mut display_list = DisplayList::new();
+    let images = ImageContext {
+        cache: image_cache,
+        animation_time,
+        scale_factor,
+    };
+    prepare_stacking_context(&mut display_list, &layout_box, &images);
     display_list
 }
 
+/// Builds the display list for a page with the given (laid out) box tree.
+pub fn build_page_display_list(
+    box_tree_opt: Option<&LayoutBox>,
+    image_cache: &ImageCache,
+    animation_time: Duration,
+    scale_factor: f32,
+) -> DisplayList {
+    if let Some(box_tree) = box_tree_opt {
+        build_display_list(box_tree, image_cache, animation_time, scale_factor)
+    } else {
+        // There is no box tree to paint (e.g. in the case of `html { display: none }`, so paint
+        // only the viewport background.
+        // TODO: The viewport background color should come from system colors, not be hardcoded
+        // to white.
+        let mut display_list = DisplayList::new();
+        display_list.push(DisplayCommand::ViewportBackground(RGBA::new(
+            255, 255, 255, 0,
+        )));
+        display_list
+    }
+}
+
 /// Represents a display list, which is a list of graphics operations Kosmonaut should perform to
 /// paint output to the screen.
 ///
@@ -43,128 +74,859 @@ pub fn build_display_list(
 /// output, such as making pixels for display on a screen or vector graphics for sending to a
 /// printer.
 ///
+/// The display list is retained between paints, and holds the areas of boxes that can be hit by
+/// the pointer alongside the commands that paint them, so hit testing sees exactly what was last
+/// painted.  Nodes are referred to by `OpaqueNode`s rather than `NodeRef`s, so that display lists
+/// can be sent from the thread that builds them to the compositor.
+///
 /// For now, our display list is OpenGL specific, but this could be made more dynamic should we
 /// implement other types of rendering backends.
 ///
 /// https://en.wikipedia.org/wiki/Display_list
-pub type DisplayList = Vec<DisplayCommand>;
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DisplayList {
+    /// The commands to perform, in painting order.
+    commands: Vec<DisplayCommand>,
+}
+
+impl DisplayList {
+    pub fn new() -> DisplayList {
+        DisplayList::default()
+    }
+
+    pub fn push(&mut self, command: DisplayCommand) {
+        self.commands.push(command);
+    }
+
+    /// The commands of the display list, in painting order.
+    pub fn commands(&self) -> &[DisplayCommand] {
+        &self.commands
+    }
+
+    /// Writes the commands that paint something, one per line in painting order, with the area
+    /// they paint and the color they paint it in.  Used by `dump-display-list` to test painting
+    /// decisions without comparing pixels.  Hit test areas aren't painted, so they're left out.
+    pub fn dump<W: Write>(&self, write_to: &mut W) {
+        for command in &self.commands {
+            let line = match command {
+                DisplayCommand::Border(color, rect) => {
+                    format!("Border {} {}", dump_color(*color), dump_rect(*rect))
+                }
+                DisplayCommand::HitTestArea(..) => continue,
+                DisplayCommand::LayerBoundary => "LayerBoundary".to_owned(),
+                DisplayCommand::Image(image_command) => format!(
+                    "Image \"{}\" frame {} {} clip {}",
+                    image_command.image.url(),
+                    image_command.frame_index,
+                    dump_rect(image_command.rect),
+                    dump_rect(image_command.clip),
+                ),
+                DisplayCommand::RectSolidColor(color, rect) => {
+                    format!("RectSolidColor {} {}", dump_color(*color), dump_rect(*rect))
+                }
+                DisplayCommand::Text(text_command) => format!(
+                    "Text \"{}\" {} {}",
+                    text_command.text,
+                    dump_color(text_command.color),
+                    dump_rect(text_command.rect)
+                ),
+                DisplayCommand::ViewportBackground(color) => {
+                    format!("ViewportBackground {}", dump_color(*color))
+                }
+            };
+            writeln!(write_to, "{}", line).expect("could not write display list dump");
+        }
+    }
+
+    /// Moves everything painted by this list `x` pixels right and `y` pixels down, except for the
+    /// viewport background, which always covers the whole viewport.  This is how the document is
+    /// scrolled, and since hit test areas move too, hit testing stays relative to the viewport.
+    pub fn translate(&mut self, x: f32, y: f32) {
+        for command in &mut self.commands {
+            match command {
+                DisplayCommand::Border(_, rect)
+                | DisplayCommand::HitTestArea(_, rect)
+                | DisplayCommand::RectSolidColor(_, rect) => *rect = rect.translated(x, y),
+                DisplayCommand::Text(text_command) => *text_command = text_command.translated(x, y),
+                DisplayCommand::Image(image_command) => {
+                    image_command.rect = image_command.rect.translated(x, y);
+                    image_command.clip = image_command.clip.translated(x, y);
+                }
+                DisplayCommand::LayerBoundary | DisplayCommand::ViewportBackground(_) => {}
+            }
+        }
+    }
+
+    /// Appends the commands of `nested`, the display list of a nested browsing context, moved so
+    /// the top-left corner of its viewport is at that of `viewport`, and clipped to `viewport`.
+    /// Its viewport background becomes a solid color filling `viewport`.
+    ///
+    /// The nested list's hit test areas are dropped, since its nodes aren't in this list's
+    /// document, so the nested document is hit as part of the iframe displaying it.
+    ///
+    /// TODO: Hit test into nested documents.
+    pub fn push_nested(&mut self, mut nested: DisplayList, viewport: Rect) {
+        nested.translate(viewport.start_x, viewport.start_y);
+        for command in nested.commands {
+            let command = match command {
+                // The nested list is painted into the layer it's pushed into.
+                DisplayCommand::HitTestArea(..) | DisplayCommand::LayerBoundary => None,
+                DisplayCommand::ViewportBackground(color) if color == RGBA::transparent() => None,
+                DisplayCommand::ViewportBackground(color) => {
+                    Some(DisplayCommand::RectSolidColor(color, viewport))
+                }
+                command => command.clipped_to(viewport),
+            };
+            self.commands.extend(command);
+        }
+    }
+
+    /// Clips the commands from the `start`th onwards to `clip`, dropping those that would paint
+    /// nothing.
+    fn clip_from(&mut self, start: usize, clip: Rect) {
+        let clipped = self
+            .commands
+            .drain(start..)
+            .filter_map(|command| command.clipped_to(clip))
+            .collect::<Vec<_>>();
+        self.commands.extend(clipped);
+    }
+
+    /// Finds the node of `document` painted at `point` (in CSS pixels, relative to the viewport),
+    /// returning the element it is or is in followed by each of that element's ancestors, up to
+    /// and including the document.  The returned chain is empty if nothing was painted at `point`,
+    /// or if what was painted there has since been removed from `document`.
+    ///
+    /// Hit test areas are tested in the reverse of the order they're painted in, so the topmost
+    /// box at `point` is the one that's hit.
+    ///
+    /// https://drafts.csswg.org/cssom-view/#dom-document-elementsfrompoint
+    ///
+    /// TODO: Once Kosmonaut supports them, account for transforms, overflow clipping,
+    /// `visibility`, and `pointer-events`.
+    pub fn hit_test(&self, document: &NodeRef, point: Point) -> Vec<NodeRef> {
+        let hit_node = self
+            .commands
+            .iter()
+            .rev()
+            .find_map(|command| match command {
+                DisplayCommand::HitTestArea(node, rect) if rect.contains(point) => Some(*node),
+                _ => None,
+            })
+            .and_then(|node| document.find_opaque(node));
+        match hit_node {
+            // Text is hit as part of the element it's in.
+            Some(node) => node
+                .inclusive_ancestors()
+                .skip_while(|node| node.as_element().is_none())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Finds the area of the viewport that must be repainted for it to go from showing `previous`
+    /// to showing this display list.
+    ///
+    /// Style and layout changes usually only change the commands of a few boxes, so the commands
+    /// both lists start and end with are skipped, and the area is that painted by the commands in
+    /// between, in either list.
+    pub fn damage_since(&self, previous: &DisplayList) -> Damage {
+        let common_prefix = self
+            .commands
+            .iter()
+            .zip(&previous.commands)
+            .take_while(|(command, previous_command)| command == previous_command)
+            .count();
+        let common_suffix = self.commands[common_prefix..]
+            .iter()
+            .rev()
+            .zip(previous.commands[common_prefix..].iter().rev())
+            .take_while(|(command, previous_command)| command == previous_command)
+            .count();
+        let changed = &self.commands[common_prefix..self.commands.len() - common_suffix];
+        let previously_changed =
+            &previous.commands[common_prefix..previous.commands.len() - common_suffix];
+        changed
+            .iter()
+            .chain(previously_changed)
+            .fold(Damage::None, |damage, command| {
+                damage.union(command.painted_area())
+            })
+    }
+}
+
+fn dump_color(color: RGBA) -> String {
+    format!(
+        "rgba({}, {}, {}, {})",
+        color.red,
+        color.green,
+        color.blue,
+        color.alpha_f32().dump_layout_format()
+    )
+}
+
+fn dump_rect(rect: Rect) -> String {
+    format!(
+        "(x: {}, y: {}, width: {}, height: {})",
+        rect.start_x.dump_layout_format(),
+        rect.start_y.dump_layout_format(),
+        rect.width.dump_layout_format(),
+        rect.height.dump_layout_format()
+    )
+}
+
+/// Writes the stacking context tree of the laid out box tree in `root`, one stacking context per
+/// line (indented under the one it's painted in) along with what establishes it, and under each,
+/// the boxes painted as part of it in painting order, with the step of painting order each is
+/// painted in.  Used by `dump-stacking` to test painting order without comparing pixels.
+///
+/// This mirrors how `prepare_stacking_context` walks the box tree, so boxes are listed in exactly
+/// the order their commands are in the display list.
+pub fn dump_stacking_contexts<W: Write>(root: &LayoutBox, write_to: &mut W) {
+    dump_stacking_context(root, 0, write_to);
+}
+
+fn dump_stacking_context<W: Write>(layout_box: &LayoutBox, depth: usize, write_to: &mut W) {
+    let indent = "  ".repeat(depth);
+    let established_by = if layout_box.is_root() {
+        "root"
+    } else if layout_box
+        .computed_values()
+        .will_change
+        .creates_stacking_context()
+    {
+        "will-change"
+    } else {
+        "contain"
+    };
+    writeln!(
+        write_to,
+        "{}StackingContext {} ({})",
+        indent,
+        dump_box(layout_box),
+        established_by
+    )
+    .expect("could not write stacking context dump");
+
+    let mut block_level_boxes = Vec::new();
+    collect_block_level_boxes(layout_box, &mut block_level_boxes);
+    let mut content = Vec::new();
+    collect_content(layout_box, &mut content);
+    for (step, painted_box) in block_level_boxes
+        .into_iter()
+        .map(|painted_box| (4, painted_box))
+        .chain(content.into_iter().map(|painted_box| (7, painted_box)))
+    {
+        writeln!(
+            write_to,
+            "{}  Step {} {}",
+            indent,
+            step,
+            dump_box(painted_box)
+        )
+        .expect("could not write stacking context dump");
+    }
+
+    let mut child_stacking_contexts = Vec::new();
+    collect_child_stacking_contexts(layout_box, &mut child_stacking_contexts);
+    for child_stacking_context in child_stacking_contexts {
+        dump_stacking_context(child_stacking_context, depth + 1, write_to);
+    }
+}
+
+/// The type of `layout_box`, the path to its element (see `dump_layout_dom_path`), and for text
+/// runs, their text.
+fn dump_box(layout_box: &LayoutBox) -> String {
+    let mut dump = format!(
+        "{} \"{}\"",
+        layout_box.box_type(),
+        dump_layout_dom_path(&layout_box.node())
+    );
+    if let LayoutBox::InlineLevel(InlineLevelContent::TextRun(text_run)) = layout_box {
+        dump.push_str(&format!(" text \"{}\"", text_run.contents()));
+    }
+    dump
+}
+
+/// Collects the boxes `prepare_block_level_boxes` paints, in the order it paints them.
+fn collect_block_level_boxes<'a>(layout_box: &'a LayoutBox, painted: &mut Vec<&'a LayoutBox>) {
+    if let LayoutBox::BlockLevel(_) = layout_box {
+        painted.push(layout_box);
+    }
+    if let Some(children) = painted_children(layout_box) {
+        for child in children {
+            if !establishes_stacking_context(child) {
+                collect_block_level_boxes(child, painted);
+            }
+        }
+    }
+}
+
+/// Collects the boxes `prepare_content` paints something for, in the order it paints them.
+fn collect_content<'a>(layout_box: &'a LayoutBox, painted: &mut Vec<&'a LayoutBox>) {
+    let paints_content = match layout_box {
+        LayoutBox::BlockLevel(BlockLevelBox::Replaced(_)) => true,
+        LayoutBox::BlockLevel(block_level_box) => {
+            !block_level_box.is_anonymous_block()
+                && details::is_summary_for_parent_details(&layout_box.node())
+        }
+        LayoutBox::InlineLevel(_) => true,
+    };
+    if paints_content {
+        painted.push(layout_box);
+    }
+    if let Some(children) = painted_children(layout_box) {
+        for child in children {
+            if !establishes_stacking_context(child) {
+                collect_content(child, painted);
+            }
+        }
+    }
+}
+
+/// An area of the viewport (in CSS pixels) whose painted contents are out of date.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Damage {
+    None,
+    Area(Rect),
+    /// The whole viewport.
+    Full,
+}
+
+impl Damage {
+    pub fn union(self, other: Damage) -> Damage {
+        match (self, other) {
+            (Damage::Full, _) | (_, Damage::Full) => Damage::Full,
+            (Damage::None, damage) | (damage, Damage::None) => damage,
+            (Damage::Area(rect), Damage::Area(other_rect)) => Damage::Area(rect.union(other_rect)),
+        }
+    }
+}
 
 /// A command to perform a graphics operation.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum DisplayCommand {
-    Char(CharCommand),
+    /// A side of a box's border.  Borders are painted as solid rectangles for now, but are kept
+    /// apart from them so that other border styles (e.g. `dashed`) can be painted.
+    Border(RGBA, Rect),
+    /// The area of a box that's hit by the pointer (its border box), which isn't painted.
+    HitTestArea(OpaqueNode, Rect),
+    Image(ImageCommand),
+    /// Where a stacking context painted into a layer of its own begins or ends, so that the
+    /// commands on either side of it are painted into different layers.  Paints nothing.
+    LayerBoundary,
     RectSolidColor(RGBA, Rect),
+    /// Glyphs of text laid out on a line.
+    Text(TextCommand),
     /// This _could_ be represented as [`RectSolidColor`], but graphics APIs sometimes have a
     /// special background painting capabilities that are more idiomatic, such as OpenGL's
     /// `Clear(COLOR_BUFFER_BIT)` and `ClearColor(r, g, b, a)` APIs.
     ViewportBackground(RGBA),
 }
 
-#[derive(Clone, Debug)]
-pub struct CharCommand {
-    /// The horizontal and vertical distance to the next glyph.
-    advance: Vector2F,
-    /// Offset from baseline to left/top of glyph.
-    bearing: Vector2F,
-    /// The char to be rendered.
-    ch: char,
-    /// The color to render the char as.
+impl DisplayCommand {
+    /// This command, painting (or hit testing) only the part of its area inside `clip`, or `None`
+    /// if none of it is.  The viewport background and layer boundaries are kept as they are.
+    fn clipped_to(self, clip: Rect) -> Option<DisplayCommand> {
+        match self {
+            DisplayCommand::Border(color, rect) => rect
+                .intersection(clip)
+                .map(|rect| DisplayCommand::Border(color, rect)),
+            DisplayCommand::HitTestArea(node, rect) => rect
+                .intersection(clip)
+                .map(|rect| DisplayCommand::HitTestArea(node, rect)),
+            DisplayCommand::Image(mut image_command) => {
+                image_command.clip.intersection(clip).map(|clip| {
+                    image_command.clip = clip;
+                    DisplayCommand::Image(image_command)
+                })
+            }
+            DisplayCommand::RectSolidColor(color, rect) => rect
+                .intersection(clip)
+                .map(|rect| DisplayCommand::RectSolidColor(color, rect)),
+            DisplayCommand::Text(mut text_command) => text_command
+                .clip
+                .map_or(Some(clip), |text_clip| text_clip.intersection(clip))
+                .and_then(|clip| {
+                    text_command.rect.intersection(clip)?;
+                    text_command.clip = Some(clip);
+                    Some(DisplayCommand::Text(text_command))
+                }),
+            DisplayCommand::LayerBoundary | DisplayCommand::ViewportBackground(_) => Some(self),
+        }
+    }
+
+    /// The area of the viewport this command paints.
+    pub fn painted_area(&self) -> Damage {
+        match self {
+            DisplayCommand::Border(_, rect) | DisplayCommand::RectSolidColor(_, rect) => {
+                Damage::Area(*rect)
+            }
+            DisplayCommand::Text(text_command) => text_command
+                .clip
+                .map_or(Some(text_command.rect), |clip| {
+                    text_command.rect.intersection(clip)
+                })
+                .map_or(Damage::None, Damage::Area),
+            DisplayCommand::Image(image_command) => image_command
+                .rect
+                .intersection(image_command.clip)
+                .map_or(Damage::None, Damage::Area),
+            DisplayCommand::HitTestArea(..) | DisplayCommand::LayerBoundary => Damage::None,
+            DisplayCommand::ViewportBackground(_) => Damage::Full,
+        }
+    }
+}
+
+/// The glyphs of a text fragment, painted in a single color.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextCommand {
+    /// The text the glyphs were shaped from, which the clusters of the glyphs index into.
+    text: String,
+    /// The glyphs to paint, placed relative to `origin`.
+    runs: Vec<GlyphRun>,
     color: RGBA,
-    /// The size to render the char as.
-    size: Vector2F,
-    /// The x and y coordinates of where the glyph origin should be placed on the viewport.
-    start_coords: Vector2F,
-    // This is the only OpenGL-specific part of state associated with the command.  Kosmonaut is
-    // pretty hardcoded to OpenGL, but I _really_ wanted to keep the DisplayCommands renderer-agnostic...
-    /// The OpenGL texture associated with this character.
-    texture_id: TextureId,
+    /// The start of the baseline the glyphs are placed on, in CSS pixels.
+    origin: Vector2F,
+    /// The area the text was laid out in.  Glyphs may stick out of it a little, e.g. the tail of
+    /// an italic letter.
+    rect: Rect,
+    /// The area outside of which no part of the glyphs should be painted, if any.
+    clip: Option<Rect>,
 }
 
-impl CharCommand {
+impl TextCommand {
     pub fn new(
-        advance: Vector2F,
-        bearing: Vector2F,
-        ch: char,
+        text: String,
+        runs: Vec<GlyphRun>,
         color: RGBA,
-        size: Vector2F,
-        start_coords: Vector2F,
-        texture_id: TextureId,
+        origin: Vector2F,
+        rect: Rect,
     ) -> Self {
-        CharCommand {
-            advance,
-            bearing,
-            ch,
+        TextCommand {
+            text,
+            runs,
             color,
-            size,
-            start_coords,
-            texture_id,
+            origin,
+            rect,
+            clip: None,
         }
     }
 
-    pub fn advance(&self) -> Vector2F {
-        self.advance
+    pub fn text(&self) -> &str {
+        &self.text
     }
 
-    pub fn bearing(&self) -> Vector2F {
-        self.bearing
-    }
-
-    pub fn ch(&self) -> char {
-        self.ch
+    pub fn runs(&self) -> &[GlyphRun] {
+        &self.runs
     }
 
     pub fn color(&self) -> RGBA {
         self.color
     }
 
-    pub fn size(&self) -> Vector2F {
-        self.size
+    pub fn origin(&self) -> Vector2F {
+        self.origin
+    }
+
+    pub fn rect(&self) -> Rect {
+        self.rect
     }
 
-    pub fn start_coords(&self) -> Vector2F {
-        self.start_coords
+    pub fn clip(&self) -> Option<Rect> {
+        self.clip
     }
 
-    pub fn texture_id(&self) -> TextureId {
-        self.texture_id
+    /// This command, painting its glyphs `x` pixels further right and `y` pixels further down.
+    pub fn translated(&self, x: f32, y: f32) -> TextCommand {
+        TextCommand {
+            origin: self.origin + Vector2F::new(x, y),
+            rect: self.rect.translated(x, y),
+            clip: self.clip.map(|clip| clip.translated(x, y)),
+            ..self.clone()
+        }
     }
 }
 
-/// Prepares a layout box for display in the correct order.  The order in which each part of a box
-/// is painted is defined here: https://www.w3.org/TR/CSS22/zindex.html
-fn prepare_layout_box(display_list: &mut DisplayList, layout_box: &LayoutBox) {
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageCommand {
+    /// The image to paint.
+    image: LoadedImage,
+    /// The frame of the image to paint.  Always zero for still images.
+    frame_index: usize,
+    /// The area the image should be painted into.  The image is scaled to fill this area.
+    rect: Rect,
+    /// The area outside of which no part of the image should be painted.
+    clip: Rect,
+}
+
+impl ImageCommand {
+    pub fn new(image: LoadedImage, frame_index: usize, rect: Rect, clip: Rect) -> Self {
+        ImageCommand {
+            image,
+            frame_index,
+            rect,
+            clip,
+        }
+    }
+
+    pub fn image(&self) -> &LoadedImage {
+        &self.image
+    }
+
+    pub fn frame_index(&self) -> usize {
+        self.frame_index
+    }
+
+    pub fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    pub fn clip(&self) -> Rect {
+        self.clip
+    }
+}
+
+/// The state needed to turn images into display commands.
+struct ImageContext<'a> {
+    cache: &'a ImageCache,
+    /// How far into the animation timeline we are, used to pick frames of animated images.
+    animation_time: Duration,
+    /// The number of device pixels per CSS pixel, used to pick the resolution vector images are
+    /// rasterized at.
+    scale_factor: f32,
+}
+
+impl ImageContext<'_> {
+    fn image_command(&self, image: LoadedImage, rect: Rect, clip: Rect) -> DisplayCommand {
+        let image = self.cache.rasterize(
+            &image,
+            rect.width.px() * self.scale_factor,
+            rect.height.px() * self.scale_factor,
+        );
+        let frame_index = image.image().frame_index_at(self.animation_time);
+        DisplayCommand::Image(ImageCommand::new(image, frame_index, rect, clip))
+    }
+}
+
+/// Prepares `layout_box` and its descendants for display in the painting order of a stacking
+/// context: https://www.w3.org/TR/CSS22/zindex.html
+///
+/// Kosmonaut doesn't support positioning, floats, or `z-index` yet, so every box is in-flow, and
+/// the only stacking contexts other than the root element's are those created by `will-change`
+/// and `contain`.  Those are painted as if they were positioned with `z-index: 0`, each into a
+/// layer of its own, so that changes inside them only repaint their own layer.
+fn prepare_stacking_context(
+    display_list: &mut DisplayList,
+    layout_box: &LayoutBox,
+    images: &ImageContext,
+) {
     // Step 1 of painting order
     if layout_box.is_root() {
         // Step 1.1
-        let cvs = layout_box.computed_values();
-        display_list.push(DisplayCommand::ViewportBackground(
-            cvs.background_color.rgba(),
-        ));
-        // TODO: Step 1.2, painting background images
+        let background_box = canvas_background_box(layout_box);
+        let background_color = match background_box.computed_values().background_color.rgba() {
+            color if color == RGBA::transparent() => canvas_color(layout_box),
+            color => color,
+        };
+        display_list.push(DisplayCommand::ViewportBackground(background_color));
+        // Step 1.2
+        // TODO: Per spec, the canvas's background image should cover the entire canvas rather
+        // than just the box it's propagated from.
+        prepare_background_image(display_list, background_box, images);
+    }
+    // Step 4
+    prepare_block_level_boxes(display_list, layout_box, images);
+    // Step 7
+    prepare_content(display_list, layout_box, images);
+    // Step 8
+    let mut child_stacking_contexts = Vec::new();
+    collect_child_stacking_contexts(layout_box, &mut child_stacking_contexts);
+    let start = display_list.commands.len();
+    for child_stacking_context in child_stacking_contexts {
+        display_list.push(DisplayCommand::LayerBoundary);
+        prepare_stacking_context(display_list, child_stacking_context, images);
+        display_list.push(DisplayCommand::LayerBoundary);
+    }
+    clip_contents(display_list, layout_box, start);
+    if layout_box.is_root() {
+        prepare_dropdown_popups(display_list, layout_box);
+        prepare_focus_ring(display_list, layout_box);
+    }
+}
+
+/// Collects the descendants of `layout_box` that establish stacking contexts, in tree order,
+/// without descending into them (their descendants belong to their own stacking contexts).
+fn collect_child_stacking_contexts<'a>(
+    layout_box: &'a LayoutBox,
+    stacking_contexts: &mut Vec<&'a LayoutBox>,
+) {
+    if let Some(children) = painted_children(layout_box) {
+        for child in children {
+            if establishes_stacking_context(child) {
+                stacking_contexts.push(child);
+            } else {
+                collect_child_stacking_contexts(child, stacking_contexts);
+            }
+        }
     }
+}
+
+/// The children of `layout_box` to paint, which are none if it skips its contents (see
+/// `LayoutBox::skips_contents`).
+fn painted_children(layout_box: &LayoutBox) -> Option<&Vec<LayoutBox>> {
+    layout_box
+        .children()
+        .filter(|_| !layout_box.skips_contents())
+}
+
+/// Whether `layout_box` establishes a stacking context of its own, other than the root element's.
+///
+/// https://drafts.csswg.org/css-will-change/#valdef-will-change-custom-ident
+/// https://drafts.csswg.org/css-contain-2/#containment-layout
+fn establishes_stacking_context(layout_box: &LayoutBox) -> bool {
+    if layout_box.is_root() || is_anonymous(layout_box) || layout_box.node().as_element().is_none()
+    {
+        return false;
+    }
+    layout_box
+        .computed_values()
+        .will_change
+        .creates_stacking_context()
+        || layout_box.containment().creates_stacking_context()
+}
+
+/// Clips what was painted inside `layout_box` since the display list had `start` commands to its
+/// padding box, if it has paint containment.  Since paint containment establishes a stacking
+/// context, everything inside the box is painted as part of it.
+///
+/// https://drafts.csswg.org/css-contain-2/#containment-paint
+fn clip_contents(display_list: &mut DisplayList, layout_box: &LayoutBox, start: usize) {
+    if layout_box.containment().paint {
+        display_list.clip_from(start, layout_box.dimensions().padding_box());
+    }
+}
+
+/// Prepares the block-level boxes among `layout_box` and its descendants for display, in tree
+/// order, except for those in stacking contexts of their own.  This is step 4 of painting order.
+fn prepare_block_level_boxes(
+    display_list: &mut DisplayList,
+    layout_box: &LayoutBox,
+    images: &ImageContext,
+) {
+    if let LayoutBox::BlockLevel(_) = layout_box {
+        prepare_hit_test_area(display_list, layout_box);
+        prepare_block_listitem_block_equiv(display_list, layout_box, images);
+    }
+    if let Some(children) = painted_children(layout_box) {
+        let start = display_list.commands.len();
+        for child in children {
+            if !establishes_stacking_context(child) {
+                prepare_block_level_boxes(display_list, child, images);
+            }
+        }
+        clip_contents(display_list, layout_box, start);
+    }
+}
 
+/// Prepares the content of `layout_box` and its descendants for display, in tree order: the
+/// replaced content of block-level boxes, and inline-level boxes, except for those in stacking
+/// contexts of their own.  This is step 7 of painting order.
+fn prepare_content(display_list: &mut DisplayList, layout_box: &LayoutBox, images: &ImageContext) {
     match layout_box {
-        LayoutBox::BlockLevel(_) => prepare_block_listitem_block_equiv(display_list, layout_box),
+        LayoutBox::BlockLevel(BlockLevelBox::Replaced(replaced_box)) => {
+            // Step 7.1
+            prepare_replaced_content(display_list, replaced_box, images);
+        }
+        LayoutBox::BlockLevel(block_level_box) => {
+            // The summary of a `<details>` element is painted with a disclosure triangle in place
+            // of a list item marker.
+            if !block_level_box.is_anonymous_block()
+                && details::is_summary_for_parent_details(&layout_box.node())
+            {
+                prepare_disclosure_triangle(display_list, layout_box);
+            }
+        }
+        LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(InlineLevelBox::Replaced(
+            replaced_box,
+        ))) => {
+            // Step 7.2.1.4: Inline-level replaced elements are painted atomically.
+            prepare_hit_test_area(display_list, layout_box);
+            prepare_background(display_list, layout_box);
+            prepare_background_image(display_list, layout_box, images);
+            prepare_borders(display_list, layout_box);
+            prepare_replaced_content(display_list, replaced_box, images);
+        }
+        LayoutBox::InlineLevel(InlineLevelContent::TextRun(text_run)) => {
+            // Step 7.2.1.4.1.3: Each fragment of a text run (its part on each line) is painted
+            // where it was laid out.
+            let color = layout_box.computed_values().color.rgba();
+            for fragment in text_run.fragments() {
+                display_list.push(DisplayCommand::HitTestArea(
+                    layout_box.node().opaque(),
+                    fragment.rect(),
+                ));
+                let (x, y) = fragment.baseline_start();
+                display_list.push(DisplayCommand::Text(TextCommand::new(
+                    fragment.text().to_owned(),
+                    fragment.runs().to_vec(),
+                    color,
+                    Vector2F::new(x, y),
+                    fragment.rect(),
+                )));
+            }
+        }
+        LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(
+            InlineLevelBox::AnonymousInline(root_inline_box),
+        )) => {
+            prepare_hit_test_area(display_list, layout_box);
+            prepare_selection(display_list, root_inline_box);
+        }
+        LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(InlineLevelBox::InlineBox(
+            inline_box,
+        ))) => {
+            // Step 7.2.1.1 -> 7.2.1.3: Each fragment of an inline box (its part on each line) is
+            // painted before its contents, with its borders and padding whether or not they have
+            // any.
+            //
+            // TODO: The background image should continue across fragments, rather than being
+            // painted once in the first (whose dimensions are the box's).
+            prepare_background_image(display_list, layout_box, images);
+            for fragment in inline_box.fragments() {
+                display_list.push(DisplayCommand::HitTestArea(
+                    layout_box.node().opaque(),
+                    fragment.rect(),
+                ));
+                let bg_color = layout_box.computed_values().background_color.rgba();
+                if bg_color != RGBA::transparent() {
+                    display_list.push(DisplayCommand::RectSolidColor(bg_color, fragment.rect()));
+                }
+                for &side in &[Side::Bottom, Side::Left, Side::Right, Side::Top] {
+                    prepare_border(display_list, layout_box, fragment.dimensions(), side);
+                }
+            }
+        }
         LayoutBox::InlineLevel(_) => {
-            // TODO: Implement other steps of painting order, 3 -> 10
-            // println!("skipping render of non-block box")
+            prepare_hit_test_area(display_list, layout_box);
         }
     }
 
-    if let Some(children) = layout_box.children() {
+    if let Some(children) = painted_children(layout_box) {
+        let start = display_list.commands.len();
         for child in children {
-            prepare_layout_box(display_list, child);
+            if !establishes_stacking_context(child) {
+                prepare_content(display_list, child, images);
+            }
+        }
+        clip_contents(display_list, layout_box, start);
+    }
+}
+
+/// Prepares the highlight behind the text of the document's selection on the lines of a root
+/// inline box, which is painted before the text itself.
+fn prepare_selection(display_list: &mut DisplayList, root_inline_box: &AnonymousInlineBox) {
+    let document = root_inline_box.node().inclusive_ancestors().last().unwrap();
+    let selection = match document.as_document() {
+        Some(document_data) => document_data.selection.borrow(),
+        None => return,
+    };
+    let selection = match &*selection {
+        Some(selection) => selection,
+        None => return,
+    };
+    for fragment in root_inline_box
+        .lines()
+        .iter()
+        .flat_map(|line| line.text_fragments())
+    {
+        let fragment_chars = fragment.char_range();
+        if let Some(chars) = selection.selected_chars(fragment.node()) {
+            let selected = chars.start.max(fragment_chars.start)..chars.end.min(fragment_chars.end);
+            if !selected.is_empty() {
+                display_list.push(DisplayCommand::RectSolidColor(
+                    SELECTION_COLOR,
+                    fragment.chars_rect(selected),
+                ));
+            }
         }
     }
 }
 
+/// Prepares the area of `layout_box` that's hit by the pointer, which is its border box (so
+/// margins are never hit).
+fn prepare_hit_test_area(display_list: &mut DisplayList, layout_box: &LayoutBox) {
+    // Anonymous boxes have no node of their own, so they can't be hit themselves.  Their node is
+    // that of their parent, which is hit by its own (encompassing) box instead.
+    if !is_anonymous(layout_box) {
+        display_list.push(DisplayCommand::HitTestArea(
+            layout_box.node().opaque(),
+            layout_box.dimensions().border_box(),
+        ));
+    }
+}
+
+/// Whether `layout_box` is anonymous, rather than generated by its node.
+fn is_anonymous(layout_box: &LayoutBox) -> bool {
+    match layout_box {
+        LayoutBox::BlockLevel(blb) => blb.is_anonymous_block(),
+        LayoutBox::InlineLevel(_) => layout_box.is_anonymous_inline(),
+    }
+}
+
 /// Preparation for step 2 from: https://www.w3.org/TR/CSS22/zindex.html
-fn prepare_block_listitem_block_equiv(display_list: &mut DisplayList, layout_box: &LayoutBox) {
-    prepare_background(display_list, layout_box);
+fn prepare_block_listitem_block_equiv(
+    display_list: &mut DisplayList,
+    layout_box: &LayoutBox,
+    images: &ImageContext,
+) {
+    // The background of the root, or of `<body>` if it was propagated to the root, was already
+    // painted over the canvas in step 1.
+    if !paints_canvas_background(layout_box) {
+        prepare_background(display_list, layout_box);
+        prepare_background_image(display_list, layout_box, images);
+    }
     prepare_borders(display_list, layout_box);
     // TODO: Render text - https://learnopengl.com/In-Practice/Text-Rendering
 }
 
+/// The box whose background is painted over the canvas: the root box, or the box of `<body>` if the
+/// root element has no background of its own to paint.
+///
+/// https://drafts.csswg.org/css-backgrounds/#special-backgrounds
+fn canvas_background_box(root: &LayoutBox) -> &LayoutBox {
+    if has_background(&root.computed_values()) {
+        return root;
+    }
+    root.root_body_box().unwrap_or(root)
+}
+
+/// Whether `layout_box` is the box whose background is painted over the canvas.
+fn paints_canvas_background(layout_box: &LayoutBox) -> bool {
+    let node = layout_box.node();
+    layout_box.is_root()
+        || (is_root_body(&node)
+            && node
+                .parent()
+                .map_or(false, |root| !has_background(&root.computed_values())))
+}
+
+/// Whether the background of a box with `computed_values` paints anything.
+fn has_background(computed_values: &ComputedValues) -> bool {
+    computed_values.background_color.rgba() != RGBA::transparent()
+        || computed_values.background_image != BackgroundImage::None
+}
+
+/// The color of the canvas of the document laid out in `root` where no background propagates to
+/// it, which depends on the color scheme used for the root element.
+fn canvas_color(root: &LayoutBox) -> RGBA {
+    let document = root.node().inclusive_ancestors().last().unwrap();
+    document
+        .as_document()
+        .map_or(RGBA::new(255, 255, 255, 255), |document_data| {
+            document_data.canvas_color.get()
+        })
+}
+
 /// Prepares the background of `layout_box` for display by converting it to display command(s).
 fn prepare_background(display_list: &mut DisplayList, layout_box: &LayoutBox) {
     let bg_color = layout_box.computed_values().background_color.rgba();
@@ -176,18 +938,473 @@ fn prepare_background(display_list: &mut DisplayList, layout_box: &LayoutBox) {
     }
 }
 
+/// Prepares the background image of `layout_box` for display.  Images are painted once, at their
+/// natural size, starting at the top-left corner of the padding box.
+///
+/// TODO: Implement `background-repeat`, `background-size`, `background-position`, and
+/// `background-clip`.  Images currently aren't clipped to the border box.
+fn prepare_background_image(
+    display_list: &mut DisplayList,
+    layout_box: &LayoutBox,
+    images: &ImageContext,
+) {
+    let image = match &layout_box.computed_values().background_image {
+        BackgroundImage::Url(url) => images.cache.get(url),
+        BackgroundImage::None => None,
+    };
+    if let Some(image) = image {
+        let padding_box = layout_box.dimensions().padding_box();
+        let rect = Rect {
+            start_x: padding_box.start_x,
+            start_y: padding_box.start_y,
+            width: CSSPixelLength::new(image.natural_width() as f32),
+            height: CSSPixelLength::new(image.natural_height() as f32),
+        };
+        display_list.push(images.image_command(image, rect, rect));
+    }
+}
+
+/// Prepares the content of a replaced box (e.g. the image of an `<img>`) for display.  The content
+/// is sized and positioned according to `object-fit` and `object-position`, and clipped to the
+/// content box.  The document of an `<iframe>` fills its content box, with its viewport there.
+fn prepare_replaced_content(
+    display_list: &mut DisplayList,
+    replaced_box: &ReplacedBox,
+    images: &ImageContext,
+) {
+    if let Some(kind) = replaced_box.form_control() {
+        prepare_form_control(display_list, replaced_box, kind);
+        return;
+    }
+    if let Some(context) = replaced_box.nested_browsing_context() {
+        display_list.push_nested(
+            context.display_list(images.animation_time, images.scale_factor),
+            replaced_box.dimensions().content,
+        );
+        return;
+    }
+    if let Some(image) = replaced_box.image() {
+        display_list.push(images.image_command(
+            image,
+            replaced_box.object_rect(),
+            replaced_box.dimensions().content,
+        ));
+    }
+}
+
+/// Prepares the parts of a form control widget that aren't drawn by its CSS background and borders,
+/// i.e. checkboxes, radio buttons, the selected option and arrow of drop-down boxes, and the
+/// options of list boxes.  Widgets are drawn in the content box using the control's `color`.
+///
+/// TODO: Paint the text of text fields and buttons once text painting works.
+fn prepare_form_control(
+    display_list: &mut DisplayList,
+    replaced_box: &ReplacedBox,
+    kind: FormControlKind,
+) {
+    let content_box = replaced_box.dimensions().content;
+    let color = replaced_box.computed_values().color.rgba();
+    let node = replaced_box.node();
+    let checked = node.as_element().map_or(false, form::is_checked);
+    let focused = node
+        .as_element()
+        .map_or(false, |element| element.focused.get());
+    match kind {
+        FormControlKind::Checkbox => {
+            display_list.push(DisplayCommand::RectSolidColor(
+                WIDGET_FRAME_COLOR,
+                content_box,
+            ));
+            display_list.push(DisplayCommand::RectSolidColor(
+                WIDGET_FILL_COLOR,
+                inset_rect(content_box, 1.),
+            ));
+            if checked {
+                display_list.push(DisplayCommand::RectSolidColor(
+                    color,
+                    inset_rect(content_box, 3.),
+                ));
+            }
+        }
+        FormControlKind::Radio => {
+            let rings = [
+                (WIDGET_FRAME_COLOR, 0.),
+                (WIDGET_FILL_COLOR, 1.),
+                (color, 3.),
+            ];
+            let ring_count = if checked { 3 } else { 2 };
+            for &(ring_color, inset) in &rings[..ring_count] {
+                for strip in circle_strips(inset_rect(content_box, inset)) {
+                    display_list.push(DisplayCommand::RectSolidColor(ring_color, strip));
+                }
+            }
+        }
+        FormControlKind::Select if !node.as_element().map_or(true, form::is_drop_down_box) => {
+            // List boxes show their first options, one per line.
+            // TODO: Scroll list boxes with more options than they show at once.
+            let computed_values = replaced_box.computed_values();
+            let row_height = computed_values.font_size.size.px() * LINE_HEIGHT_EM;
+            prepare_option_rows(
+                display_list,
+                &node,
+                content_box,
+                row_height,
+                &computed_values,
+            );
+        }
+        FormControlKind::Select => {
+            // The selected option, followed by a downwards-pointing arrow centered in a square at
+            // the inline-end of the box.
+            // TODO: Place the arrow on the left for right-to-left content.
+            if let Some(selected) = form::selected_options(&node).first() {
+                let label_box = Rect {
+                    width: CSSPixelLength::new(
+                        (content_box.width.px() - content_box.height.px()).max(0.),
+                    ),
+                    ..content_box
+                };
+                display_list.push(label_text(
+                    &form::option_label(selected),
+                    &replaced_box.computed_values(),
+                    label_box,
+                ));
+            }
+            let arrow_size = content_box.height.px() / 2.;
+            let arrow_height = (arrow_size / 2.).ceil();
+            let start_x = content_box.start_x + content_box.width.px()
+                - (content_box.height.px() + arrow_size) / 2.;
+            let start_y = content_box.start_y + (content_box.height.px() - arrow_height) / 2.;
+            for strip in arrow_strips(start_x, start_y, arrow_size, ArrowDirection::Down) {
+                display_list.push(DisplayCommand::RectSolidColor(color, strip));
+            }
+        }
+        FormControlKind::TextField | FormControlKind::TextArea if focused => {
+            prepare_caret_and_selection(display_list, replaced_box, color)
+        }
+        FormControlKind::TextField | FormControlKind::TextArea | FormControlKind::Button => {}
+    }
+}
+
+/// Prepares a row for each option of `select` that fits in `area`, from the top, each with the
+/// option's label and hit test area, and highlighted if the option is selected.  Labels are
+/// styled like `select`, whose computed values are `style`.
+fn prepare_option_rows(
+    display_list: &mut DisplayList,
+    select: &NodeRef,
+    area: Rect,
+    row_height: f32,
+    style: &ComputedValues,
+) {
+    let selected = form::selected_options(select);
+    for (index, option) in form::select_options(select).iter().enumerate() {
+        let row = Rect {
+            start_y: area.start_y + index as f32 * row_height,
+            height: CSSPixelLength::new(row_height),
+            ..area
+        };
+        let row = match row.intersection(area) {
+            Some(row) => row,
+            None => break,
+        };
+        display_list.push(DisplayCommand::HitTestArea(option.opaque(), row));
+        if selected.contains(option) {
+            display_list.push(DisplayCommand::RectSolidColor(SELECTION_COLOR, row));
+        }
+        display_list.push(label_text(&form::option_label(option), style, row));
+    }
+}
+
+/// A command painting `text`, laid out on a single line styled by `style`, at the inline-start of
+/// `area` and centered in it vertically.  The text is clipped to `area`.
+///
+/// TODO: Start the text on the right of `area` for right-to-left content.
+fn label_text(text: &str, style: &ComputedValues, area: Rect) -> DisplayCommand {
+    let shaped = shape_text(text, style, 0.);
+    let height = shaped.ascent + shaped.descent;
+    let start_y = area.start_y + (area.height.px() - height) / 2.;
+    let mut text_command = TextCommand::new(
+        text.to_owned(),
+        shaped.runs,
+        style.color.rgba(),
+        Vector2F::new(area.start_x, start_y + shaped.ascent),
+        Rect {
+            start_x: area.start_x,
+            start_y,
+            width: CSSPixelLength::new(shaped.advance),
+            height: CSSPixelLength::new(height),
+        },
+    );
+    text_command.clip = Some(area);
+    DisplayCommand::Text(text_command)
+}
+
+/// Prepares the popups of the open drop-down boxes among `layout_box` and its descendants, which
+/// list their options below the box.  Popups are painted over the whole page, after everything
+/// else.
+fn prepare_dropdown_popups(display_list: &mut DisplayList, layout_box: &LayoutBox) {
+    let node = layout_box.node();
+    let is_open = match layout_box {
+        LayoutBox::BlockLevel(BlockLevelBox::Replaced(_))
+        | LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(InlineLevelBox::Replaced(_))) => {
+            node.as_element()
+                .map_or(false, |element| element.dropdown_open.get())
+        }
+        _ => false,
+    };
+    if is_open {
+        let computed_values = layout_box.computed_values();
+        let row_height = computed_values.font_size.size.px() * LINE_HEIGHT_EM;
+        let border_box = layout_box.dimensions().border_box();
+        let row_count = form::select_options(&node).len();
+        let popup = Rect {
+            start_y: border_box.start_y + border_box.height.px(),
+            height: CSSPixelLength::new(row_count as f32 * row_height + 2.),
+            ..border_box
+        };
+        display_list.push(DisplayCommand::RectSolidColor(WIDGET_FRAME_COLOR, popup));
+        display_list.push(DisplayCommand::RectSolidColor(
+            WIDGET_FILL_COLOR,
+            inset_rect(popup, 1.),
+        ));
+        prepare_option_rows(
+            display_list,
+            &node,
+            inset_rect(popup, 1.),
+            row_height,
+            &computed_values,
+        );
+    }
+    if let Some(children) = painted_children(layout_box) {
+        for child in children {
+            prepare_dropdown_popups(display_list, child);
+        }
+    }
+}
+
+/// Prepares the ring around the focused element of the document laid out in `root_box`, if it
+/// matches `:focus-visible`, which is painted around each of its rects, over everything else.
+///
+/// TODO: Paint the `outline` of `:focus-visible` elements instead once it's supported.
+fn prepare_focus_ring(display_list: &mut DisplayList, root_box: &LayoutBox) {
+    let document = root_box.node().inclusive_ancestors().last().unwrap();
+    let focused = document
+        .shadow_including_inclusive_descendants()
+        .find(|node| {
+            node.as_element().map_or(false, |element| {
+                element.focused.get() && element.focus_visible.get()
+            })
+        });
+    let focused = match focused {
+        Some(focused) => focused,
+        None => return,
+    };
+    let width = FOCUS_RING_WIDTH_PX;
+    for rect in focus::rendered_rects(&focused) {
+        let ring = inset_rect(rect, -width);
+        let sides = [
+            Rect {
+                height: CSSPixelLength::new(width),
+                ..ring
+            },
+            Rect {
+                start_y: ring.start_y + ring.height.px() - width,
+                height: CSSPixelLength::new(width),
+                ..ring
+            },
+            Rect {
+                width: CSSPixelLength::new(width),
+                ..ring
+            },
+            Rect {
+                start_x: ring.start_x + ring.width.px() - width,
+                width: CSSPixelLength::new(width),
+                ..ring
+            },
+        ];
+        for side in sides.iter() {
+            display_list.push(DisplayCommand::RectSolidColor(FOCUS_RING_COLOR, *side));
+        }
+    }
+}
+
+/// Prepares the disclosure triangle of the summary of a `<details>` element, which points down
+/// while the element is open and to the right while it's closed.  It's painted in the summary's
+/// left padding, centered on its first line, using its `color`.
+///
+/// TODO: Point the closed triangle to the left for right-to-left content.
+///
+/// https://html.spec.whatwg.org/multipage/rendering.html#the-details-and-summary-elements
+fn prepare_disclosure_triangle(display_list: &mut DisplayList, summary_box: &LayoutBox) {
+    let dimensions = summary_box.dimensions();
+    let computed_values = summary_box.computed_values();
+    let font_size = computed_values.font_size.size.px();
+    let size = (font_size / 2.).round();
+    let depth = (size / 2.).ceil();
+    let (direction, width, height) = if details::is_open(&summary_box.node().parent().unwrap()) {
+        (ArrowDirection::Down, size, depth)
+    } else {
+        (ArrowDirection::Right, depth, size)
+    };
+    let start_x = dimensions.padding_box().start_x + (dimensions.padding.left.px() - width) / 2.;
+    let start_y = dimensions.content.start_y + (font_size * LINE_HEIGHT_EM - height) / 2.;
+    for strip in arrow_strips(start_x, start_y, size, direction) {
+        display_list.push(DisplayCommand::RectSolidColor(
+            computed_values.color.rgba(),
+            strip,
+        ));
+    }
+}
+
+/// Prepares the selection highlight and caret of a focused text control, clipped to its content box.
+///
+/// TODO: Scroll the text so the caret stays visible once the text itself is painted.
+fn prepare_caret_and_selection(
+    display_list: &mut DisplayList,
+    replaced_box: &ReplacedBox,
+    color: RGBA,
+) {
+    let editable_text = match form::with_editable_text(&replaced_box.node(), |text| text.clone()) {
+        Some(editable_text) => editable_text,
+        None => return,
+    };
+    let content_box = replaced_box.dimensions().content;
+    for (line, columns) in editable_text.selected_line_ranges() {
+        if let Some(rect) = replaced_box
+            .text_rect(line, columns)
+            .intersection(content_box)
+        {
+            display_list.push(DisplayCommand::RectSolidColor(SELECTION_COLOR, rect));
+        }
+    }
+    let (line, column) = editable_text.line_and_column(editable_text.caret());
+    let caret_position = replaced_box.text_rect(line, column..column);
+    let caret = Rect {
+        // Keep a caret at the very end of a full text field inside the content box.
+        start_x: caret_position
+            .start_x
+            .min(content_box.start_x + content_box.width.px() - 1.),
+        width: CSSPixelLength::new(1.),
+        ..caret_position
+    };
+    if let Some(caret) = caret.intersection(content_box) {
+        display_list.push(DisplayCommand::RectSolidColor(color, caret));
+    }
+}
+
+/// The color of the highlight behind selected text, in text controls and the document.
+const SELECTION_COLOR: RGBA = RGBA {
+    red: 0,
+    green: 120,
+    blue: 215,
+    alpha: 96,
+};
+/// The color of the ring painted around the focused element when focus is visible.
+const FOCUS_RING_COLOR: RGBA = RGBA {
+    red: 0,
+    green: 95,
+    blue: 204,
+    alpha: 255,
+};
+/// The width of the ring painted around the focused element when focus is visible.
+const FOCUS_RING_WIDTH_PX: f32 = 2.;
+/// The color of the outline of checkboxes and radio buttons.
+const WIDGET_FRAME_COLOR: RGBA = RGBA {
+    red: 118,
+    green: 118,
+    blue: 118,
+    alpha: 255,
+};
+/// The color of the inside of checkboxes and radio buttons.
+const WIDGET_FILL_COLOR: RGBA = RGBA {
+    red: 255,
+    green: 255,
+    blue: 255,
+    alpha: 255,
+};
+
+/// Shrinks `rect` by `inset` pixels on every side.
+fn inset_rect(rect: Rect, inset: f32) -> Rect {
+    Rect {
+        start_x: rect.start_x + inset,
+        start_y: rect.start_y + inset,
+        width: CSSPixelLength::new((rect.width.px() - 2. * inset).max(0.)),
+        height: CSSPixelLength::new((rect.height.px() - 2. * inset).max(0.)),
+    }
+}
+
+/// Approximates the ellipse inscribed in `rect` with one-pixel-high horizontal strips, since the
+/// display list can only draw rectangles.
+fn circle_strips(rect: Rect) -> Vec<Rect> {
+    let radius_x = rect.width.px() / 2.;
+    let radius_y = rect.height.px() / 2.;
+    let center_x = rect.start_x + radius_x;
+    (0..rect.height.px().ceil() as usize)
+        .filter_map(|row| {
+            // Size each strip by the ellipse's width at the strip's vertical center.
+            let dy = (row as f32 + 0.5 - radius_y) / radius_y;
+            let half_width = radius_x * (1. - dy * dy).max(0.).sqrt();
+            if half_width <= 0. {
+                return None;
+            }
+            Some(Rect {
+                start_x: center_x - half_width,
+                start_y: rect.start_y + row as f32,
+                width: CSSPixelLength::new(2. * half_width),
+                height: CSSPixelLength::new(1.),
+            })
+        })
+        .collect()
+}
+
+/// Which way an arrow drawn with `arrow_strips` points.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ArrowDirection {
+    Down,
+    Right,
+}
+
+/// Approximates an arrowhead (an isosceles triangle) whose base is `size` pixels long with
+/// one-pixel-wide strips, since the display list can only draw rectangles.  The base starts at
+/// (`start_x`, `start_y`), and runs along the top of the arrow if it points down, or along its left
+/// if it points right.
+fn arrow_strips(start_x: f32, start_y: f32, size: f32, direction: ArrowDirection) -> Vec<Rect> {
+    (0..(size / 2.).ceil() as usize)
+        .map(|step| {
+            let step = step as f32;
+            let length = CSSPixelLength::new((size - 2. * step).max(1.));
+            let one = CSSPixelLength::new(1.);
+            let (width, height) = match direction {
+                ArrowDirection::Down => (length, one),
+                ArrowDirection::Right => (one, length),
+            };
+            Rect {
+                start_x: start_x + step,
+                start_y: start_y + step,
+                width,
+                height,
+            }
+        })
+        .collect()
+}
+
 /// Prepares the borders of `layout_box` for display by converting them to display commands.
 fn prepare_borders(display_list: &mut DisplayList, layout_box: &LayoutBox) {
-    prepare_border(display_list, layout_box, Side::Bottom);
-    prepare_border(display_list, layout_box, Side::Left);
-    prepare_border(display_list, layout_box, Side::Right);
-    prepare_border(display_list, layout_box, Side::Top);
+    let d = layout_box.dimensions();
+    prepare_border(display_list, layout_box, d, Side::Bottom);
+    prepare_border(display_list, layout_box, d, Side::Left);
+    prepare_border(display_list, layout_box, d, Side::Right);
+    prepare_border(display_list, layout_box, d, Side::Top);
 }
 
-/// Prepares the border `side` of `layout_box` for display by converting it to a display command.
-fn prepare_border(display_list: &mut DisplayList, layout_box: &LayoutBox, side: Side) {
+/// Prepares the border `side` of `layout_box`, whose edges are given by `d` (which are those of
+/// one of its fragments for inline boxes), for display by converting it to a display command.
+fn prepare_border(
+    display_list: &mut DisplayList,
+    layout_box: &LayoutBox,
+    d: Dimensions,
+    side: Side,
+) {
     let cvs = layout_box.computed_values();
-    let d = layout_box.dimensions();
     let border_style = cvs.border_style(side);
     let border_color_rgba = cvs.border_color_rgba(side);
     // The border size has already been calculated during layout, so we don't need to get it from
@@ -228,5 +1445,540 @@ fn prepare_border(display_list: &mut DisplayList, layout_box: &LayoutBox, side:
             height: border_size_px,
         },
     };
-    display_list.push(DisplayCommand::RectSolidColor(border_color_rgba, rect));
+    display_list.push(DisplayCommand::Border(border_color_rgba, rect));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::parser::parse_html;
+    use crate::dom::traits::*;
+    use crate::style::test_utils::{laid_out_box_tree, laid_out_document, style};
+
+    fn display_list(html: &str, css: &str) -> (NodeRef, DisplayList) {
+        display_list_of(&parse_html().one(html), css)
+    }
+
+    /// Styles, lays out, and paints `document` (again).
+    fn display_list_of(document: &NodeRef, css: &str) -> (NodeRef, DisplayList) {
+        style(document, css);
+        let box_tree = laid_out_box_tree(document);
+        let images = ImageContext {
+            cache: &ImageCache::new("."),
+            animation_time: Duration::default(),
+            scale_factor: 1.,
+        };
+        let mut display_list = DisplayList::new();
+        prepare_stacking_context(&mut display_list, &box_tree, &images);
+        (document.clone(), display_list)
+    }
+
+    fn hit_element_names(
+        document: &NodeRef,
+        display_list: &DisplayList,
+        x: f32,
+        y: f32,
+    ) -> Vec<String> {
+        display_list
+            .hit_test(document, Point { x, y })
+            .iter()
+            .filter_map(|node| {
+                node.as_element()
+                    .map(|element| element.name.local.to_string())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn paints_block_backgrounds_before_content() {
+        let (_, display_list) = display_list(
+            "<div id=a><input type=checkbox></div><div id=b></div>",
+            "#a { height: 10px; background-color: red } \
+             #b { height: 10px; background-color: blue } \
+             #b { border-top-width: 1px; border-top-style: solid; border-top-color: black }",
+        );
+        let painted = display_list
+            .commands()
+            .iter()
+            .filter_map(|command| match command {
+                DisplayCommand::ViewportBackground(_) => Some("viewport"),
+                DisplayCommand::RectSolidColor(..) => Some("rect"),
+                DisplayCommand::Border(..) => Some("border"),
+                DisplayCommand::HitTestArea(..) | DisplayCommand::Text(..) => None,
+                _ => Some("other"),
+            })
+            .collect::<Vec<_>>();
+        // The checkbox in `#a` is painted after the background and border of `#b`.
+        assert_eq!(
+            painted,
+            vec!["viewport", "rect", "rect", "border", "rect", "rect"]
+        );
+    }
+
+    #[test]
+    fn paints_will_change_stacking_contexts_after_the_rest_in_layers_of_their_own() {
+        let (_, display_list) = display_list(
+            "<div id=a><div id=b></div></div><div id=c></div>",
+            "#a { height: 10px; background-color: red; will-change: opacity } \
+             #b { height: 10px; background-color: lime; will-change: transform } \
+             #c { height: 10px; background-color: blue; will-change: contents }",
+        );
+        let (red, lime, blue) = (
+            RGBA::new(255, 0, 0, 255),
+            RGBA::new(0, 255, 0, 255),
+            RGBA::new(0, 0, 255, 255),
+        );
+        let painted = display_list
+            .commands()
+            .iter()
+            .filter_map(|command| match command {
+                DisplayCommand::RectSolidColor(color, _) if *color == red => Some("a"),
+                DisplayCommand::RectSolidColor(color, _) if *color == lime => Some("b"),
+                DisplayCommand::RectSolidColor(color, _) if *color == blue => Some("c"),
+                DisplayCommand::LayerBoundary => Some("|"),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        // `#c` doesn't hint at anything that creates a stacking context, so it's painted with the
+        // root's, before `#a` and the stacking context nested in it.
+        assert_eq!(painted, vec!["c", "|", "a", "|", "b", "|", "|"]);
+    }
+
+    #[test]
+    fn paints_each_fragment_of_inline_boxes_sliced_at_line_breaks() {
+        let (document, display_list) = display_list(
+            "<p><span><img><img></span></p>",
+            "p { margin-top: 0px; margin-bottom: 0px } img { width: 500px; height: 10px } \
+             span { background-color: red; padding-left: 3px; padding-right: 3px } \
+             span { border-left-width: 2px; border-left-style: solid; border-left-color: blue } \
+             span { border-right-width: 2px; border-right-style: solid; border-right-color: blue }",
+        );
+        let painted_in = |color| {
+            display_list
+                .commands()
+                .iter()
+                .filter_map(|command| match command {
+                    DisplayCommand::RectSolidColor(rect_color, rect)
+                    | DisplayCommand::Border(rect_color, rect)
+                        if *rect_color == color =>
+                    {
+                        Some(*rect)
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        // The second image doesn't fit on the first line, so the span is split around the break,
+        // with its inline-start edges on its first fragment and its inline-end ones on its last.
+        assert_eq!(
+            painted_in(RGBA::new(255, 0, 0, 255)),
+            vec![rect(8., 8., 505., 10.), rect(8., 18., 505., 10.)]
+        );
+        assert_eq!(
+            painted_in(RGBA::new(0, 0, 255, 255)),
+            vec![rect(8., 8., 2., 10.), rect(511., 18., 2., 10.)]
+        );
+        assert_eq!(
+            hit_element_names(&document, &display_list, 510., 20.)[0],
+            "span"
+        );
+        assert_eq!(
+            hit_element_names(&document, &display_list, 600., 20.)[0],
+            "p"
+        );
+    }
+
+    #[test]
+    fn clips_the_contents_of_boxes_with_paint_containment() {
+        let (document, display_list) = display_list(
+            "<div id=a><div id=b></div></div>",
+            "#a { height: 10px; contain: strict; border-bottom-width: 2px } \
+             #a { border-bottom-style: solid; border-bottom-color: blue } \
+             #b { height: 30px; background-color: red }",
+        );
+        let painted_in = |color| {
+            display_list
+                .commands()
+                .iter()
+                .filter_map(|command| match command {
+                    DisplayCommand::RectSolidColor(rect_color, rect)
+                    | DisplayCommand::Border(rect_color, rect)
+                        if *rect_color == color =>
+                    {
+                        Some(*rect)
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        // The border of `#a` itself is outside its padding box, but isn't clipped.
+        assert_eq!(
+            painted_in(RGBA::new(255, 0, 0, 255)),
+            vec![rect(8., 8., 784., 10.)]
+        );
+        assert_eq!(
+            painted_in(RGBA::new(0, 0, 255, 255)),
+            vec![rect(8., 18., 784., 2.)]
+        );
+        assert_eq!(
+            hit_element_names(&document, &display_list, 20., 12.)[0],
+            "div"
+        );
+        assert_eq!(
+            hit_element_names(&document, &display_list, 20., 24.)[0],
+            "html"
+        );
+    }
+
+    #[test]
+    fn dumps_painted_commands_in_painting_order() {
+        // Neither the root nor `<body>` have a background, so the canvas is painted in `Canvas`.
+        let (_, display_list) = display_list(
+            "<div></div>",
+            "html, body { margin-top: 0px; margin-right: 0px } \
+             html, body { margin-bottom: 0px; margin-left: 0px } \
+             div { height: 10px; background-color: red } \
+             div { border-top-width: 2px; border-top-style: solid } \
+             div { border-top-color: rgba(0, 0, 255, 0.5) }",
+        );
+        let mut dump = Vec::new();
+        display_list.dump(&mut dump);
+        assert_eq!(
+            String::from_utf8(dump).unwrap(),
+            "ViewportBackground rgba(255, 255, 255, 1)\n\
+             RectSolidColor rgba(255, 0, 0, 1) (x: 0, y: 0, width: 800, height: 12)\n\
+             Border rgba(0, 0, 255, 0.5) (x: 0, y: 0, width: 800, height: 2)\n"
+        );
+    }
+
+    #[test]
+    fn paints_the_glyphs_of_each_line_of_text_where_it_was_laid_out() {
+        let (_, display_list) = display_list(
+            "<div>Hello there</div>",
+            "body { margin-top: 0px; margin-right: 0px; margin-bottom: 0px } \
+             body { margin-left: 0px } div { width: 100px }",
+        );
+        let mut dump = Vec::new();
+        display_list.dump(&mut dump);
+        // Every glyph of the test font is a 16px box, so only one word fits on each line.
+        assert_eq!(
+            String::from_utf8(dump).unwrap(),
+            "ViewportBackground rgba(255, 255, 255, 1)\n\
+             Text \"Hello\" rgba(0, 0, 0, 1) (x: 0, y: 0, width: 80, height: 16)\n\
+             Text \"there\" rgba(0, 0, 0, 1) (x: 0, y: 16, width: 80, height: 16)\n"
+        );
+        let texts: Vec<&TextCommand> = display_list
+            .commands()
+            .iter()
+            .filter_map(|command| match command {
+                DisplayCommand::Text(text_command) => Some(text_command),
+                _ => None,
+            })
+            .collect();
+        // Glyphs are placed on the baseline, 0.8em below the top of the second line.
+        assert_eq!(texts[1].origin().x(), 0.);
+        assert_eq!(texts[1].origin().y().dump_layout_format(), "28.8");
+        let glyph_xs: Vec<f32> = texts[1].runs()[0]
+            .glyphs
+            .iter()
+            .map(|glyph| glyph.position.x())
+            .collect();
+        assert_eq!(glyph_xs, vec![0., 16., 32., 48., 64.]);
+    }
+
+    #[test]
+    fn dumps_stacking_contexts_and_the_order_their_boxes_are_painted_in() {
+        let (_, box_tree) = laid_out_document(
+            "<div id=a>hi<div class=b></div></div><p>there</p>",
+            "#a { will-change: opacity } .b { contain: paint }",
+        );
+        let mut dump = Vec::new();
+        dump_stacking_contexts(&box_tree, &mut dump);
+        assert_eq!(
+            String::from_utf8(dump).unwrap(),
+            "StackingContext BlockContainer \"html\" (root)\n\
+             \x20 Step 4 BlockContainer \"html\"\n\
+             \x20 Step 4 BlockContainer \"html > body\"\n\
+             \x20 Step 4 BlockContainer \"html > body > p\"\n\
+             \x20 Step 4 AnonymousBlockBox \"html > body > p\"\n\
+             \x20 Step 7 AnonymousInlineBox \"html > body > p\"\n\
+             \x20 Step 7 TextRun \"html > body > p\" text \"there\"\n\
+             \x20 StackingContext BlockContainer \"html > body > div#a\" (will-change)\n\
+             \x20   Step 4 BlockContainer \"html > body > div#a\"\n\
+             \x20   Step 4 AnonymousBlockBox \"html > body > div#a\"\n\
+             \x20   Step 7 AnonymousInlineBox \"html > body > div#a\"\n\
+             \x20   Step 7 TextRun \"html > body > div#a\" text \"hi\"\n\
+             \x20   StackingContext BlockContainer \"html > body > div#a > div.b\" (contain)\n\
+             \x20     Step 4 BlockContainer \"html > body > div#a > div.b\"\n"
+        );
+    }
+
+    #[test]
+    fn propagates_body_background_to_the_canvas() {
+        let dump = |css| {
+            let (_, display_list) = display_list("<div></div>", css);
+            let mut dump = Vec::new();
+            display_list.dump(&mut dump);
+            String::from_utf8(dump).unwrap()
+        };
+        // `<body>`'s background is painted over the whole canvas, rather than its own box.
+        assert_eq!(
+            dump("body { background-color: red }"),
+            "ViewportBackground rgba(255, 0, 0, 1)\n"
+        );
+        // The root's background takes precedence, leaving `<body>` to paint its own (which fills
+        // the viewport, since the document is in quirks mode).
+        assert_eq!(
+            dump("html { background-color: blue } body { background-color: red }"),
+            "ViewportBackground rgba(0, 0, 255, 1)\n\
+             RectSolidColor rgba(255, 0, 0, 1) (x: 8, y: 8, width: 784, height: 584)\n"
+        );
+    }
+
+    #[test]
+    fn hits_innermost_box_and_its_ancestors() {
+        let (document, display_list) = display_list(
+            "<div id=outer><p id=inner></p></div>",
+            "body { margin-top: 0px; margin-right: 0px; margin-bottom: 0px; margin-left: 0px } \
+             #outer { height: 100px; padding-top: 10px } \
+             #inner { height: 20px; margin-top: 10px }",
+        );
+        let chain = display_list.hit_test(&document, Point { x: 5., y: 25. });
+        let inner = document.select_first("#inner").unwrap();
+        assert!(chain[0] == *inner.as_node());
+        assert!(*chain.last().unwrap() == document);
+        assert_eq!(
+            hit_element_names(&document, &display_list, 5., 25.),
+            vec!["p", "div", "body", "html"]
+        );
+        // The inner box's margin belongs to the outer box.
+        assert_eq!(
+            hit_element_names(&document, &display_list, 5., 15.),
+            vec!["div", "body", "html"]
+        );
+    }
+
+    #[test]
+    fn lists_options_of_open_drop_down_boxes() {
+        let html = "<select><option>One<option selected>Two</select><div></div>";
+        let css = "body { margin-top: 0px; margin-right: 0px; margin-bottom: 0px } \
+                   body { margin-left: 0px } div { height: 100px }";
+        let (document, display_list) = display_list(html, css);
+        let select = document.select_first("select").unwrap().as_node().clone();
+        let select_box = display_list
+            .commands()
+            .iter()
+            .find_map(|command| match command {
+                DisplayCommand::HitTestArea(node, rect) if *node == select.opaque() => Some(*rect),
+                _ => None,
+            })
+            .unwrap();
+        // A closed drop-down box shows the selected option.
+        assert!(display_list.commands().iter().any(|command| matches!(
+            command,
+            DisplayCommand::Text(text_command) if text_command.text() == "Two"
+        )));
+        let row_height = select.computed_values().font_size.size.px() * LINE_HEIGHT_EM;
+        let x = select_box.start_x + 5.;
+        let second_row_y = select_box.start_y + select_box.height.px() + 1.5 * row_height;
+        assert_eq!(
+            hit_element_names(&document, &display_list, x, second_row_y),
+            vec!["div", "body", "html"]
+        );
+
+        assert!(form::toggle_dropdown(&select));
+        let (_, display_list) = display_list_of(&document, css);
+        assert_eq!(
+            hit_element_names(&document, &display_list, x, second_row_y),
+            vec!["option", "select", "body", "html"]
+        );
+        let hit_option = display_list.hit_test(&document, Point { x, y: second_row_y })[0].clone();
+        assert_eq!(form::option_label(&hit_option), "Two");
+    }
+
+    #[test]
+    fn rings_visibly_focused_elements() {
+        let css = "button { width: 20px; height: 10px }";
+        let (document, _) = display_list("<button></button>", css);
+        let button = document.select_first("button").unwrap().as_node().clone();
+        let ring_sides = |display_list: &DisplayList| {
+            display_list
+                .commands()
+                .iter()
+                .filter_map(|command| match command {
+                    DisplayCommand::RectSolidColor(color, rect) if *color == FOCUS_RING_COLOR => {
+                        Some(*rect)
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        // Clicking a button focuses it without showing that it is.
+        focus::update_focus(None, Some(&button), true);
+        let (_, display_list) = display_list_of(&document, css);
+        assert!(ring_sides(&display_list).is_empty());
+
+        focus::update_focus(Some(&button), None, false);
+        focus::update_focus(None, Some(&button), false);
+        let (_, display_list) = display_list_of(&document, css);
+        let border_box = button.bounding_client_rect();
+        let top = ring_sides(&display_list)[0];
+        assert_eq!(top.start_x, border_box.start_x - FOCUS_RING_WIDTH_PX);
+        assert_eq!(top.start_y, border_box.start_y - FOCUS_RING_WIDTH_PX);
+        assert_eq!(
+            top.width.px(),
+            border_box.width.px() + 2. * FOCUS_RING_WIDTH_PX
+        );
+        assert_eq!(ring_sides(&display_list).len(), 4);
+    }
+
+    #[test]
+    fn misses_outside_every_box() {
+        let (document, display_list) = display_list(
+            "<div></div>",
+            "html, body { margin-top: 0px; margin-right: 0px; margin-bottom: 0px } \
+             html, body { margin-left: 0px; height: 50px } div { height: 10px }",
+        );
+        assert_eq!(
+            hit_element_names(&document, &display_list, 5., 40.),
+            vec!["body", "html"]
+        );
+        assert!(display_list
+            .hit_test(&document, Point { x: 5., y: 60. })
+            .is_empty());
+    }
+
+    #[test]
+    fn hit_tests_translated_lists_relative_to_the_viewport() {
+        let (document, mut display_list) = display_list(
+            "<div></div>",
+            "html, body { margin-top: 0px; margin-right: 0px; margin-bottom: 0px } \
+             html, body { margin-left: 0px; height: 50px } div { height: 10px }",
+        );
+        // Scroll the document down by 20px.
+        display_list.translate(0., -20.);
+        assert_eq!(
+            hit_element_names(&document, &display_list, 5., 25.),
+            vec!["body", "html"]
+        );
+        assert!(display_list
+            .hit_test(&document, Point { x: 5., y: 35. })
+            .is_empty());
+        assert!(matches!(
+            display_list.commands()[0],
+            DisplayCommand::ViewportBackground(_)
+        ));
+    }
+
+    fn rect(start_x: f32, start_y: f32, width: f32, height: f32) -> Rect {
+        Rect {
+            start_x,
+            start_y,
+            width: CSSPixelLength::new(width),
+            height: CSSPixelLength::new(height),
+        }
+    }
+
+    fn solid_color_list(rects: &[(RGBA, Rect)]) -> DisplayList {
+        let mut display_list = DisplayList::new();
+        for &(color, rect) in rects {
+            display_list.push(DisplayCommand::RectSolidColor(color, rect));
+        }
+        display_list
+    }
+
+    #[test]
+    fn damage_covers_old_and_new_areas_of_changed_commands() {
+        let red = RGBA::new(255, 0, 0, 255);
+        let blue = RGBA::new(0, 0, 255, 255);
+        let previous = solid_color_list(&[
+            (red, rect(0., 0., 10., 10.)),
+            (red, rect(0., 20., 10., 10.)),
+            (red, rect(0., 40., 10., 10.)),
+        ]);
+        let moved = solid_color_list(&[
+            (red, rect(0., 0., 10., 10.)),
+            (red, rect(5., 25., 10., 10.)),
+            (red, rect(0., 40., 10., 10.)),
+        ]);
+        assert_eq!(
+            moved.damage_since(&previous),
+            Damage::Area(rect(0., 20., 15., 15.))
+        );
+        let recolored = solid_color_list(&[
+            (red, rect(0., 0., 10., 10.)),
+            (red, rect(0., 20., 10., 10.)),
+            (blue, rect(0., 40., 10., 10.)),
+        ]);
+        assert_eq!(
+            recolored.damage_since(&previous),
+            Damage::Area(rect(0., 40., 10., 10.))
+        );
+        // Removed commands damage the area they used to paint.
+        let removed = solid_color_list(&[(red, rect(0., 0., 10., 10.))]);
+        assert_eq!(
+            removed.damage_since(&previous),
+            Damage::Area(rect(0., 20., 10., 30.))
+        );
+        assert_eq!(previous.damage_since(&previous.clone()), Damage::None);
+    }
+
+    #[test]
+    fn changing_viewport_background_damages_everything() {
+        let mut previous = DisplayList::new();
+        previous.push(DisplayCommand::ViewportBackground(RGBA::new(0, 0, 0, 255)));
+        let mut current = DisplayList::new();
+        current.push(DisplayCommand::ViewportBackground(RGBA::new(
+            255, 255, 255, 255,
+        )));
+        assert_eq!(current.damage_since(&previous), Damage::Full);
+        assert_eq!(current.damage_since(&DisplayList::new()), Damage::Full);
+    }
+
+    #[test]
+    fn nested_lists_are_moved_into_and_clipped_to_their_viewport() {
+        let white = RGBA::new(255, 255, 255, 255);
+        let red = RGBA::new(255, 0, 0, 255);
+        let mut nested = DisplayList::new();
+        nested.push(DisplayCommand::ViewportBackground(white));
+        nested.push(DisplayCommand::HitTestArea(
+            NodeRef::new_text("nested").opaque(),
+            rect(0., 0., 10., 10.),
+        ));
+        nested.push(DisplayCommand::RectSolidColor(
+            red,
+            rect(-10., 0., 50., 20.),
+        ));
+        nested.push(DisplayCommand::RectSolidColor(
+            red,
+            rect(200., 0., 10., 10.),
+        ));
+        let mut display_list = DisplayList::new();
+        display_list.push_nested(nested, rect(100., 50., 100., 40.));
+        assert_eq!(
+            display_list,
+            solid_color_list(&[
+                (white, rect(100., 50., 100., 40.)),
+                (red, rect(100., 50., 40., 20.)),
+            ])
+        );
+    }
+
+    #[test]
+    fn circle_strips_fill_inscribed_circle() {
+        let strips = circle_strips(Rect {
+            start_x: 10.,
+            start_y: 20.,
+            width: CSSPixelLength::new(4.),
+            height: CSSPixelLength::new(4.),
+        });
+        assert_eq!(strips.len(), 4);
+        // Strips are symmetric about the center of the circle, and widest in the middle.
+        for (strip, mirrored) in strips.iter().zip(strips.iter().rev()) {
+            assert_eq!(strip.width, mirrored.width);
+            assert!((strip.start_x + strip.width.px() / 2. - 12.).abs() < 1e-4);
+        }
+        assert!(strips[0].width < strips[1].width);
+        assert_eq!(strips[3].start_y, 23.);
+    }
 }
@@ -0,0 +1,264 @@
+//! Detection of emoji in text, so that they can be rendered with a color emoji font rather than
+//! whatever font renders the text around them.
+//!
+//! https://www.unicode.org/reports/tr51/
+
+use std::ops::Range;
+
+/// The families of color emoji fonts, across platforms, in order of preference.
+pub static EMOJI_FAMILIES: &[&str] = &[
+    "Noto Color Emoji",
+    "Apple Color Emoji",
+    "Segoe UI Emoji",
+    "Twemoji Mozilla",
+    "EmojiOne Color",
+];
+
+const ZERO_WIDTH_JOINER: char = '\u{200D}';
+const TEXT_PRESENTATION_SELECTOR: char = '\u{FE0E}';
+const EMOJI_PRESENTATION_SELECTOR: char = '\u{FE0F}';
+const COMBINING_ENCLOSING_KEYCAP: char = '\u{20E3}';
+
+/// Splits `text` into segments of emoji and of other text, returning the byte range of each
+/// segment along with whether it's emoji.  Each emoji segment holds one or more whole emoji
+/// sequences, e.g. flags, keycaps, emoji with skin tone modifiers, and ZWJ sequences (emoji
+/// joined by zero width joiners, such as family emoji).
+///
+/// https://www.unicode.org/reports/tr51/#Emoji_Sequences
+pub fn emoji_segments(text: &str) -> Vec<(Range<usize>, bool)> {
+    let mut segments: Vec<(Range<usize>, bool)> = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, ch)) = chars.next() {
+        let next = chars.peek().map(|(_, next)| *next);
+        let mut end = start + ch.len_utf8();
+        let starts_emoji = match next {
+            Some(TEXT_PRESENTATION_SELECTOR) => false,
+            Some(EMOJI_PRESENTATION_SELECTOR) | Some(COMBINING_ENCLOSING_KEYCAP) => is_emoji(ch),
+            _ => has_emoji_presentation(ch),
+        };
+        if starts_emoji {
+            // Flags are pairs of regional indicators.
+            if is_regional_indicator(ch) {
+                if let Some((index, next)) = chars.peek().copied() {
+                    if is_regional_indicator(next) {
+                        chars.next();
+                        end = index + next.len_utf8();
+                    }
+                }
+            }
+            while let Some((index, next)) = chars.peek().copied() {
+                if is_emoji_modifier(next) {
+                    chars.next();
+                    end = index + next.len_utf8();
+                    continue;
+                }
+                // Only join the next emoji onto this sequence if there is one after the joiner.
+                let mut lookahead = chars.clone();
+                lookahead.next();
+                match lookahead.peek().copied() {
+                    Some((joined_index, joined))
+                        if next == ZERO_WIDTH_JOINER && is_emoji(joined) =>
+                    {
+                        chars.next();
+                        chars.next();
+                        end = joined_index + joined.len_utf8();
+                    }
+                    _ => break,
+                }
+            }
+        }
+        match segments.last_mut() {
+            Some((range, is_emoji_segment)) if *is_emoji_segment == starts_emoji => {
+                range.end = end;
+            }
+            _ => segments.push((start..end, starts_emoji)),
+        }
+    }
+    segments
+}
+
+/// Whether `ch` is an emoji modifier or another character that continues the emoji sequence
+/// before it: skin tone modifiers, the emoji presentation selector, the keycap mark, and tags
+/// (used by subdivision flags).
+fn is_emoji_modifier(ch: char) -> bool {
+    matches!(
+        ch,
+        '\u{1F3FB}'..='\u{1F3FF}'
+            | EMOJI_PRESENTATION_SELECTOR
+            | COMBINING_ENCLOSING_KEYCAP
+            | '\u{E0020}'..='\u{E007F}'
+    )
+}
+
+fn is_regional_indicator(ch: char) -> bool {
+    matches!(ch, '\u{1F1E6}'..='\u{1F1FF}')
+}
+
+/// Whether `ch` is rendered as an emoji by default, rather than only when followed by the emoji
+/// presentation selector.  This covers the emoji blocks along with the most common emoji among
+/// the symbol blocks.
+///
+/// https://www.unicode.org/reports/tr51/#Presentation_Style
+fn has_emoji_presentation(ch: char) -> bool {
+    matches!(
+        ch,
+        '\u{231A}'..='\u{231B}'
+            | '\u{23E9}'..='\u{23EC}'
+            | '\u{23F0}'
+            | '\u{23F3}'
+            | '\u{25FD}'..='\u{25FE}'
+            | '\u{2614}'..='\u{2615}'
+            | '\u{2648}'..='\u{2653}'
+            | '\u{267F}'
+            | '\u{2693}'
+            | '\u{26A1}'
+            | '\u{26AA}'..='\u{26AB}'
+            | '\u{26BD}'..='\u{26BE}'
+            | '\u{26C4}'..='\u{26C5}'
+            | '\u{26CE}'
+            | '\u{26D4}'
+            | '\u{26EA}'
+            | '\u{26F2}'..='\u{26F3}'
+            | '\u{26F5}'
+            | '\u{26FA}'
+            | '\u{26FD}'
+            | '\u{2705}'
+            | '\u{270A}'..='\u{270B}'
+            | '\u{2728}'
+            | '\u{274C}'
+            | '\u{274E}'
+            | '\u{2753}'..='\u{2755}'
+            | '\u{2757}'
+            | '\u{2795}'..='\u{2797}'
+            | '\u{27B0}'
+            | '\u{27BF}'
+            | '\u{2B1B}'..='\u{2B1C}'
+            | '\u{2B50}'
+            | '\u{2B55}'
+            | '\u{1F004}'
+            | '\u{1F0CF}'
+            | '\u{1F18E}'
+            | '\u{1F191}'..='\u{1F19A}'
+            | '\u{1F1E6}'..='\u{1F1FF}'
+            | '\u{1F201}'
+            | '\u{1F21A}'
+            | '\u{1F22F}'
+            | '\u{1F232}'..='\u{1F236}'
+            | '\u{1F238}'..='\u{1F23A}'
+            | '\u{1F250}'..='\u{1F251}'
+            | '\u{1F300}'..='\u{1F320}'
+            | '\u{1F32D}'..='\u{1F335}'
+            | '\u{1F337}'..='\u{1F37C}'
+            | '\u{1F37E}'..='\u{1F393}'
+            | '\u{1F3A0}'..='\u{1F3CA}'
+            | '\u{1F3CF}'..='\u{1F3D3}'
+            | '\u{1F3E0}'..='\u{1F3F0}'
+            | '\u{1F3F4}'
+            | '\u{1F3F8}'..='\u{1F43E}'
+            | '\u{1F440}'
+            | '\u{1F442}'..='\u{1F4FC}'
+            | '\u{1F4FF}'..='\u{1F53D}'
+            | '\u{1F54B}'..='\u{1F54E}'
+            | '\u{1F550}'..='\u{1F567}'
+            | '\u{1F57A}'
+            | '\u{1F595}'..='\u{1F596}'
+            | '\u{1F5A4}'
+            | '\u{1F5FB}'..='\u{1F64F}'
+            | '\u{1F680}'..='\u{1F6C5}'
+            | '\u{1F6CC}'
+            | '\u{1F6D0}'..='\u{1F6D2}'
+            | '\u{1F6D5}'..='\u{1F6D7}'
+            | '\u{1F6EB}'..='\u{1F6EC}'
+            | '\u{1F6F4}'..='\u{1F6FC}'
+            | '\u{1F7E0}'..='\u{1F7EB}'
+            | '\u{1F90C}'..='\u{1F93A}'
+            | '\u{1F93C}'..='\u{1F945}'
+            | '\u{1F947}'..='\u{1F9FF}'
+            | '\u{1FA70}'..='\u{1FAFF}'
+    )
+}
+
+/// Whether `ch` can be rendered as an emoji, which it is when it has emoji presentation by
+/// default or is followed by the emoji presentation selector.
+fn is_emoji(ch: char) -> bool {
+    has_emoji_presentation(ch)
+        || matches!(
+            ch,
+            '#' | '*'
+                | '0'..='9'
+                | '\u{A9}'
+                | '\u{AE}'
+                | '\u{203C}'
+                | '\u{2049}'
+                | '\u{2122}'
+                | '\u{2139}'
+                | '\u{2194}'..='\u{21AA}'
+                | '\u{2300}'..='\u{23FF}'
+                | '\u{24C2}'
+                | '\u{25AA}'..='\u{25FE}'
+                | '\u{2600}'..='\u{27BF}'
+                | '\u{2934}'..='\u{2935}'
+                | '\u{2B05}'..='\u{2B55}'
+                | '\u{3030}'
+                | '\u{303D}'
+                | '\u{3297}'
+                | '\u{3299}'
+                | '\u{1F000}'..='\u{1FAFF}'
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segments(text: &str) -> Vec<(&str, bool)> {
+        emoji_segments(text)
+            .into_iter()
+            .map(|(range, is_emoji)| (&text[range], is_emoji))
+            .collect()
+    }
+
+    #[test]
+    fn finds_emoji_sequences() {
+        // A family (man, ZWJ, woman, ZWJ, girl), a thumbs up with a skin tone, and a flag.
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        let thumbs_up = "\u{1F44D}\u{1F3FD}";
+        let flag = "\u{1F1EB}\u{1F1F7}";
+        let text = format!("Hi {}{} and {}!", family, thumbs_up, flag);
+        assert_eq!(
+            segments(&text),
+            vec![
+                ("Hi ", false),
+                (&*format!("{}{}", family, thumbs_up), true),
+                (" and ", false),
+                (flag, true),
+                ("!", false),
+            ]
+        );
+    }
+
+    #[test]
+    fn variation_selectors_choose_presentation() {
+        // Hearts are text by default, but emoji when followed by the emoji presentation selector.
+        assert_eq!(segments("\u{2764}"), vec![("\u{2764}", false)]);
+        assert_eq!(
+            segments("\u{2764}\u{FE0F}"),
+            vec![("\u{2764}\u{FE0F}", true)]
+        );
+        // Umbrellas with rain drops are emoji by default, but text before the text selector.
+        assert_eq!(
+            segments("\u{2614}\u{FE0E}"),
+            vec![("\u{2614}\u{FE0E}", false)]
+        );
+        // Keycaps are digits followed by the emoji presentation selector and the keycap mark.
+        assert_eq!(
+            segments("1\u{FE0F}\u{20E3}2"),
+            vec![("1\u{FE0F}\u{20E3}", true), ("2", false)]
+        );
+        // A joiner without an emoji after it isn't part of the sequence.
+        assert_eq!(
+            segments("\u{1F600}\u{200D}a"),
+            vec![("\u{1F600}", true), ("\u{200D}a", false)]
+        );
+    }
+}
@@ -0,0 +1,218 @@
+//! Font fallback: choosing, for each character of some text, a font that can render it, and
+//! splitting the text into runs wherever the chosen font changes.
+//!
+//! Fonts are tried in the order of the `font-family` list, then the system fonts known to cover
+//! the character's script, so that mixed-script text renders rather than showing "tofu" (the
+//! boxes painted for missing glyphs).
+//!
+//! https://drafts.csswg.org/css-fonts-4/#font-matching-algorithm
+
+use std::ops::Range;
+
+/// The writing system a character belongs to, as far as choosing a fallback font is concerned.
+/// This is a coarse approximation of the Unicode `Script` property.
+///
+/// https://www.unicode.org/reports/tr24/
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Script {
+    Arabic,
+    Bengali,
+    /// Characters used with many scripts, such as spaces, digits, and punctuation.
+    Common,
+    Cyrillic,
+    Devanagari,
+    Greek,
+    Han,
+    Hangul,
+    Hebrew,
+    /// Characters that take the script of the character they follow, such as combining marks,
+    /// joiners, and variation selectors.
+    Inherited,
+    /// Hiragana and katakana.
+    Kana,
+    Latin,
+    Thai,
+    Other,
+}
+
+impl Script {
+    /// Whether characters of this script should stay in the run of the characters before them,
+    /// as long as that run's font can render them.  This keeps combining marks with their base
+    /// characters, and avoids splitting runs at spaces and punctuation.
+    fn continues_run(self) -> bool {
+        matches!(self, Script::Common | Script::Inherited)
+    }
+
+    /// The families of system fonts, across platforms, that cover this script, in order of
+    /// preference.
+    pub fn fallback_families(self) -> &'static [&'static str] {
+        match self {
+            Script::Arabic => &[
+                "Noto Sans Arabic",
+                "Noto Naskh Arabic",
+                "Geeza Pro",
+                "Segoe UI",
+                "Arial",
+            ],
+            Script::Bengali => &[
+                "Noto Sans Bengali",
+                "Kohinoor Bangla",
+                "Bangla Sangam MN",
+                "Nirmala UI",
+                "Vrinda",
+            ],
+            Script::Devanagari => &[
+                "Noto Sans Devanagari",
+                "Kohinoor Devanagari",
+                "Devanagari Sangam MN",
+                "Nirmala UI",
+                "Mangal",
+            ],
+            Script::Han => &[
+                "Noto Sans CJK SC",
+                "PingFang SC",
+                "Hiragino Sans",
+                "Microsoft YaHei",
+                "SimSun",
+            ],
+            Script::Hangul => &["Noto Sans CJK KR", "Apple SD Gothic Neo", "Malgun Gothic"],
+            Script::Hebrew => &["Noto Sans Hebrew", "Arial Hebrew", "Segoe UI", "Arial"],
+            Script::Kana => &[
+                "Noto Sans CJK JP",
+                "Hiragino Sans",
+                "Yu Gothic",
+                "Meiryo",
+                "MS Gothic",
+            ],
+            Script::Thai => &["Noto Sans Thai", "Thonburi", "Leelawadee UI", "Tahoma"],
+            Script::Cyrillic | Script::Greek | Script::Latin => {
+                &["Noto Sans", "DejaVu Sans", "Helvetica", "Arial"]
+            }
+            Script::Common | Script::Inherited | Script::Other => &[
+                "Noto Sans",
+                "DejaVu Sans",
+                "Noto Sans Symbols",
+                "Noto Sans Symbols2",
+                "Segoe UI Symbol",
+                "Arial Unicode MS",
+            ],
+        }
+    }
+}
+
+/// Returns the script `ch` belongs to.
+pub fn script_of(ch: char) -> Script {
+    match ch as u32 {
+        0x0300..=0x036F
+        | 0x1AB0..=0x1AFF
+        | 0x1DC0..=0x1DFF
+        | 0x200C..=0x200D
+        | 0x20D0..=0x20FF
+        | 0xFE00..=0xFE0F
+        | 0xFE20..=0xFE2F
+        | 0xE0100..=0xE01EF => Script::Inherited,
+        0x0000..=0x0040 | 0x005B..=0x0060 | 0x007B..=0x00BF | 0x00D7 | 0x00F7 => Script::Common,
+        0x0041..=0x005A | 0x0061..=0x007A | 0x00C0..=0x00D6 | 0x00D8..=0x00F6 => Script::Latin,
+        0x00F8..=0x024F | 0x1E00..=0x1EFF => Script::Latin,
+        0x0370..=0x03FF | 0x1F00..=0x1FFF => Script::Greek,
+        0x0400..=0x052F => Script::Cyrillic,
+        0x0590..=0x05FF => Script::Hebrew,
+        0x0600..=0x06FF | 0x0750..=0x077F | 0x08A0..=0x08FF | 0xFB50..=0xFDFF | 0xFE70..=0xFEFF => {
+            Script::Arabic
+        }
+        0x0900..=0x097F => Script::Devanagari,
+        0x0980..=0x09FF => Script::Bengali,
+        0x0E00..=0x0E7F => Script::Thai,
+        0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => Script::Hangul,
+        0x2000..=0x200B | 0x200E..=0x20CF | 0x2100..=0x2BFF => Script::Common,
+        0x3000..=0x303F | 0xFF01..=0xFF20 => Script::Common,
+        0x3040..=0x30FF | 0x31F0..=0x31FF => Script::Kana,
+        0x2E80..=0x2FDF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF => Script::Han,
+        0x20000..=0x2FFFF => Script::Han,
+        _ => Script::Other,
+    }
+}
+
+/// Splits `text` into runs that are each rendered with a single font, returning the byte range of
+/// each run along with its font.
+///
+/// `font_for_char` chooses the font for a character on its own, typically the first in its
+/// fallback chain that covers it.  Characters of the common and inherited scripts
+/// (e.g. spaces and combining marks) instead stay in the run before them whenever `covers` says that run's font can render them.
+pub fn split_runs<F: Clone + PartialEq>(
+    text: &str,
+    mut font_for_char: impl FnMut(char) -> F,
+    mut covers: impl FnMut(&F, char) -> bool,
+) -> Vec<(Range<usize>, F)> {
+    let mut runs: Vec<(Range<usize>, F)> = Vec::new();
+    for (index, ch) in text.char_indices() {
+        let end = index + ch.len_utf8();
+        if let Some((range, font)) = runs.last_mut() {
+            if script_of(ch).continues_run() && covers(font, ch) {
+                range.end = end;
+                continue;
+            }
+            let font_for_ch = font_for_char(ch);
+            if *font == font_for_ch {
+                range.end = end;
+            } else {
+                runs.push((index..end, font_for_ch));
+            }
+        } else {
+            runs.push((index..end, font_for_char(ch)));
+        }
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_scripts() {
+        assert_eq!(script_of('a'), Script::Latin);
+        assert_eq!(script_of('é'), Script::Latin);
+        assert_eq!(script_of(' '), Script::Common);
+        assert_eq!(script_of('7'), Script::Common);
+        assert_eq!(script_of('\u{301}'), Script::Inherited);
+        assert_eq!(script_of('م'), Script::Arabic);
+        assert_eq!(script_of('ह'), Script::Devanagari);
+        assert_eq!(script_of('漢'), Script::Han);
+        assert_eq!(script_of('か'), Script::Kana);
+        assert_eq!(script_of('한'), Script::Hangul);
+    }
+
+    #[test]
+    fn splits_runs_where_the_font_changes() {
+        // A "Latin" font covering ASCII and combining marks, and an "Arabic" font covering Arabic
+        // and spaces.
+        let covers = |font: &&str, ch: char| match *font {
+            "Latin" => ch.is_ascii() || script_of(ch) == Script::Inherited,
+            "Arabic" => script_of(ch) == Script::Arabic || ch == ' ',
+            _ => false,
+        };
+        let font_for_char = |ch| {
+            ["Latin", "Arabic"]
+                .iter()
+                .copied()
+                .find(|font| covers(font, ch))
+                .unwrap_or("Latin")
+        };
+        let text = "Hi مرحبا بك e\u{301}!";
+        let runs = split_runs(text, font_for_char, covers);
+        let runs = runs
+            .iter()
+            .map(|(range, font)| (&text[range.clone()], *font))
+            .collect::<Vec<_>>();
+        // Spaces stay in the run before them, as does the combining accent.
+        assert_eq!(
+            runs,
+            vec![
+                ("Hi ", "Latin"),
+                ("مرحبا بك ", "Arabic"),
+                ("e\u{301}!", "Latin"),
+            ]
+        );
+    }
+}
@@ -1,23 +1,183 @@
+use crate::gfx::emoji::{emoji_segments, EMOJI_FAMILIES};
+use crate::gfx::fallback::{script_of, split_runs};
+use crate::gfx::glyph::{PostscriptName, Synthesis};
+use crate::style::values::computed::font::{
+    FontFamily, FontStyle, FontWeight, GenericFontFamily, SingleFontFamily,
+};
+use crate::style::values::computed::ComputedValues;
 use accountable_refcell::{Ref, RefCell};
 use font_kit::error::{FontLoadingError, SelectionError};
+use font_kit::family_name::FamilyName;
 use font_kit::loaders::default::Font;
+use font_kit::properties::{Properties, Style, Weight};
 use font_kit::source::SystemSource;
 use std::collections::HashMap;
+use std::iter;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// The bundled test font, an Ahem-style font in which every glyph but the space is a box one em
+/// wide and tall, 0.8em above the baseline and 0.2em below it.
+///
+/// https://web-platform-tests.org/writing-tests/ahem.html
+pub(crate) static TEST_FONT: &[u8] = include_bytes!("../../fonts/ahem.ttf");
+const TEST_FONT_POSTSCRIPT_NAME: &str = "Ahem";
+
+/// Unit tests always lay text out with the test font, so that what they measure doesn't depend on
+/// the fonts installed.
+static TEST_FONTS_ENABLED: AtomicBool = AtomicBool::new(cfg!(test));
+
+/// Matches every family to the bundled test font from now on, so that text is laid out and
+/// painted the same on every machine, whatever fonts are installed.  Meant for layout dumps and
+/// screenshots that are compared across platforms.
+pub fn use_test_fonts() {
+    TEST_FONTS_ENABLED.store(true, Ordering::Relaxed);
+}
 
 /// Provides a handle for loading and caching fonts that abstracts over all different font loaders
 /// and sources.
 #[derive(Default)]
 pub struct FontHandle {
     cached_fonts: RefCell<HashMap<String, Font>>,
+    /// The font each family, weight (as the bits of its `f32`), and style were matched to, or
+    /// `None` if no installed font is of the family.
+    matched_fonts: RefCell<HashMap<(SingleFontFamily, u32, FontStyle), Option<MatchedFont>>>,
 }
 
-pub type PostscriptName = String;
+/// An installed font matched to a family, weight, and style, and how it needs to be altered to
+/// look like that weight and style.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MatchedFont {
+    pub postscript_name: PostscriptName,
+    pub synthesis: Synthesis,
+}
+
+/// A run of text rendered with a single font.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FontRun {
+    /// The byte range of the run within the text it was split from.
+    pub range: Range<usize>,
+    pub font: MatchedFont,
+}
 
 impl FontHandle {
     pub fn new() -> FontHandle {
         FontHandle {
             cached_fonts: RefCell::new(HashMap::new()),
+            matched_fonts: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the installed font of `family` that best matches `weight` and `style`, if the
+    /// family is installed, along with how it needs to be synthesized to match them.
+    ///
+    /// https://drafts.csswg.org/css-fonts-4/#font-style-matching
+    pub fn match_font(
+        &self,
+        family: &SingleFontFamily,
+        weight: FontWeight,
+        style: FontStyle,
+    ) -> Option<MatchedFont> {
+        let key = (family.clone(), weight.0.to_bits(), style);
+        if let Some(matched) = self.matched_fonts.borrow().get(&key) {
+            return matched.clone();
+        }
+        let test_fonts = TEST_FONTS_ENABLED.load(Ordering::Relaxed);
+        let font = if test_fonts {
+            load_test_font()
+        } else {
+            load_family(family, weight, style)
+        };
+        let matched = font.ok().and_then(|font| {
+            let postscript_name = font.postscript_name()?;
+            let properties = font.properties();
+            // Faces are only synthesized when the family has nothing closer: a bold face is
+            // synthesized when the closest weight is too light to be bold, and an oblique one
+            // when the family has no italic or oblique faces at all.  The test font is never
+            // synthesized, so that its glyphs stay exact boxes.
+            let synthesis = if test_fonts {
+                Synthesis::default()
+            } else {
+                Synthesis {
+                    bold: weight.is_bold() && !FontWeight(properties.weight.0).is_bold(),
+                    oblique: style != FontStyle::Normal && properties.style == Style::Normal,
+                }
+            };
+            self.cached_fonts
+                .borrow_mut()
+                .entry(postscript_name.clone())
+                .or_insert(font);
+            Some(MatchedFont {
+                postscript_name,
+                synthesis,
+            })
+        });
+        self.matched_fonts.borrow_mut().insert(key, matched.clone());
+        matched
+    }
+
+    /// Splits `text` into runs that are each rendered with a single font of the weight and style
+    /// in `style`.  Each character is rendered with the first font that has a glyph for it, trying
+    /// the families of `font-family` in order, then the system fonts that cover the character's
+    /// script.  Emoji sequences are instead rendered with a color emoji font if one is installed,
+    /// so that they're painted in color and shaped as a whole, e.g. into a single family glyph.
+    ///
+    /// Text that no installed font can render is rendered with the first font that's installed,
+    /// and text is left out of the runs altogether if no fonts are installed.
+    pub fn font_runs(&self, style: &ComputedValues, text: &str) -> Vec<FontRun> {
+        let has_glyph = |postscript_name: &str, ch: char| {
+            self.get_font(postscript_name)
+                .map_or(false, |font| font.glyph_for_char(ch).is_some())
+        };
+        let font_for_char = |ch: char, is_emoji: bool| {
+            let mut installed = fallback_chain(&style.font_family, ch, is_emoji)
+                .filter_map(|family| self.match_font(&family, style.font_weight, style.font_style));
+            let first_installed = installed.next()?;
+            if has_glyph(&first_installed.postscript_name, ch) {
+                return Some(first_installed);
+            }
+            installed
+                .find(|matched| has_glyph(&matched.postscript_name, ch))
+                .or(Some(first_installed))
+        };
+        let covers = |matched: &Option<MatchedFont>, ch: char| {
+            matched
+                .as_ref()
+                .map_or(false, |matched| has_glyph(&matched.postscript_name, ch))
+        };
+        let mut runs: Vec<FontRun> = Vec::new();
+        for (segment, is_emoji) in emoji_segments(text) {
+            let segment_runs = split_runs(
+                &text[segment.clone()],
+                |ch| font_for_char(ch, is_emoji),
+                covers,
+            );
+            for (range, font) in segment_runs {
+                let font = match font {
+                    Some(font) => font,
+                    None => continue,
+                };
+                let range = segment.start + range.start..segment.start + range.end;
+                // Runs on either side of a segment boundary may well have the same font, e.g. when
+                // there's no emoji font installed.
+                match runs.last_mut() {
+                    Some(last) if last.range.end == range.start && last.font == font => {
+                        last.range.end = range.end;
+                    }
+                    _ => runs.push(FontRun { range, font }),
+                }
+            }
         }
+        runs
+    }
+
+    /// Makes `font` the font loaded for its postscript name, in place of any installed font of the
+    /// same name.
+    #[cfg(test)]
+    pub fn add_font(&self, font: Font) {
+        let postscript_name = font.postscript_name().expect("font has no postscript name");
+        self.cached_fonts.borrow_mut().insert(postscript_name, font);
     }
 
     pub fn get_font(&self, postscript_name: &str) -> Result<Ref<Font>, FontError> {
@@ -25,7 +185,11 @@ impl FontHandle {
         {
             let mut cached_fonts = self.cached_fonts.borrow_mut();
             if !cached_fonts.contains_key(&key) {
-                let font = load_font(postscript_name)?;
+                let font = if postscript_name == TEST_FONT_POSTSCRIPT_NAME {
+                    load_test_font()?
+                } else {
+                    load_font(postscript_name)?
+                };
                 cached_fonts.insert(key.clone(), font);
             }
         }
@@ -41,6 +205,61 @@ fn load_font(postscript_name: &str) -> Result<Font, FontError> {
         .load()?)
 }
 
+fn load_test_font() -> Result<Font, FontError> {
+    Ok(Font::from_bytes(Arc::new(TEST_FONT.to_vec()), 0)?)
+}
+
+fn load_family(
+    family: &SingleFontFamily,
+    weight: FontWeight,
+    style: FontStyle,
+) -> Result<Font, FontError> {
+    let family_name = match family {
+        SingleFontFamily::FamilyName(name) => FamilyName::Title(name.clone()),
+        SingleFontFamily::Generic(GenericFontFamily::Cursive) => FamilyName::Cursive,
+        SingleFontFamily::Generic(GenericFontFamily::Fantasy) => FamilyName::Fantasy,
+        SingleFontFamily::Generic(GenericFontFamily::Monospace) => FamilyName::Monospace,
+        SingleFontFamily::Generic(GenericFontFamily::SansSerif)
+        | SingleFontFamily::Generic(GenericFontFamily::SystemUi) => FamilyName::SansSerif,
+        SingleFontFamily::Generic(GenericFontFamily::Serif) => FamilyName::Serif,
+    };
+    let mut properties = Properties::new();
+    properties.weight(Weight(weight.0)).style(match style {
+        FontStyle::Normal => Style::Normal,
+        FontStyle::Italic => Style::Italic,
+        FontStyle::Oblique => Style::Oblique,
+    });
+    // font-kit selects the face of the family nearest to `properties` by the CSS font matching
+    // algorithm, e.g. falling back from italic to oblique faces and to the nearest weight.
+    Ok(SystemSource::new()
+        .select_best_match(&[family_name], &properties)?
+        .load()?)
+}
+
+/// The families to try rendering `ch` with, in order: the color emoji fonts if `ch` is part of an
+/// emoji sequence, those of `font_family`, then the system fonts that cover the script of `ch`, and
+/// finally the system's default sans-serif font.
+fn fallback_chain<'a>(
+    font_family: &'a FontFamily,
+    ch: char,
+    is_emoji: bool,
+) -> impl Iterator<Item = SingleFontFamily> + 'a {
+    let emoji_families = EMOJI_FAMILIES
+        .iter()
+        .filter(move |_| is_emoji)
+        .map(|name| SingleFontFamily::FamilyName((*name).to_owned()));
+    let script_families = script_of(ch)
+        .fallback_families()
+        .iter()
+        .map(|name| SingleFontFamily::FamilyName((*name).to_owned()));
+    emoji_families
+        .chain(font_family.0.iter().cloned())
+        .chain(script_families)
+        .chain(iter::once(SingleFontFamily::Generic(
+            GenericFontFamily::SansSerif,
+        )))
+}
+
 // TODO: This error type seems a bit too general.  May want to refactor as this module evolves.
 #[derive(Debug)]
 pub enum FontError {
@@ -59,3 +278,25 @@ impl From<SelectionError> for FontError {
         FontError::Selection(err)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_font_renders_chars_as_em_boxes() {
+        let font = load_test_font().unwrap();
+        assert_eq!(
+            font.postscript_name().as_deref(),
+            Some(TEST_FONT_POSTSCRIPT_NAME)
+        );
+        let metrics = font.metrics();
+        assert_eq!(metrics.units_per_em, 1000);
+        assert_eq!(metrics.ascent, 800.);
+        assert_eq!(metrics.descent, -200.);
+        for ch in &['x', 'É', ' '] {
+            let glyph_id = font.glyph_for_char(*ch).unwrap();
+            assert_eq!(font.advance(glyph_id).unwrap().x(), 1000.);
+        }
+    }
+}
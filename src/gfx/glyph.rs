@@ -0,0 +1,61 @@
+//! Glyphs as placed by text layout, ready to be painted.  Unlike shaped runs, which hold on to the
+//! fonts they were shaped with, these are plain data: they go into display lists, which are sent
+//! to the compositor's thread, and exist in builds without the system fonts text is shaped with.
+
+use pathfinder_geometry::vector::Vector2F;
+
+pub type PostscriptName = String;
+
+/// How a font is altered to stand in for a bold or slanted face its family doesn't have.
+///
+/// https://drafts.csswg.org/css-fonts-4/#font-synthesis
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Synthesis {
+    /// Glyphs are made bolder by painting them twice, one slightly to the right of the other.
+    pub bold: bool,
+    /// Glyphs are slanted (skewed) to the right.
+    pub oblique: bool,
+}
+
+impl Synthesis {
+    /// The angle, in degrees, synthesized oblique glyphs are slanted by, which is the default
+    /// angle of `font-style: oblique`.
+    pub const OBLIQUE_ANGLE_DEG: f32 = 14.;
+
+    /// How far to the right, in pixels, the second copy of a glyph is painted to embolden it, and
+    /// so how much wider each glyph becomes, at `size_px`.
+    pub fn bold_offset(self, size_px: f32) -> f32 {
+        if self.bold {
+            (size_px / 24.).max(1.)
+        } else {
+            0.
+        }
+    }
+}
+
+/// Glyphs of a single font, in visual order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GlyphRun {
+    /// The font the glyphs are of.
+    pub font: PostscriptName,
+    /// How the font is altered to match the style the glyphs were shaped for.
+    pub synthesis: Synthesis,
+    pub glyphs: Vec<PositionedGlyph>,
+}
+
+/// A glyph placed by text layout.  Distances are in CSS pixels, with y increasing downwards.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PositionedGlyph {
+    /// The id of the glyph in its run's font.
+    pub glyph_id: u32,
+    /// The byte index, into the text the glyph was laid out from, of the first char it was shaped
+    /// from.
+    pub cluster: usize,
+    /// Where the glyph's origin is, relative to the start of the baseline of the text it was laid
+    /// out in.
+    pub position: Vector2F,
+    /// How far the glyph moves the pen horizontally.
+    pub advance: f32,
+    /// The font size the glyph is painted at.
+    pub size_px: f32,
+}
@@ -0,0 +1,342 @@
+//! Rasterization of the glyphs of display lists on the CPU.  Glyphs are rasterized the same way
+//! for every kind of output: the OpenGL backend packs them into glyph atlases (see `char`), while
+//! the software rasterizer (see `raster`) blends them straight into its pixmap.
+
+use crate::gfx::atlas::{subpixel_offset, subpixel_x};
+use crate::gfx::color_glyph::rasterize_color_glyph;
+use crate::gfx::display::TextCommand;
+use crate::gfx::font::{FontError, FontHandle};
+use crate::gfx::glyph::{PostscriptName, Synthesis};
+use crate::gfx::shape::Shaper;
+use accountable_refcell::RefCell;
+use app_units::Au;
+use font_kit::canvas::{Canvas, Format, RasterizationOptions};
+use font_kit::error::GlyphLoadingError;
+use font_kit::font::Font;
+use font_kit::hinting::HintingOptions;
+use image::{Rgba, RgbaImage};
+use pathfinder_geometry::transform2d::{Matrix2x2F, Transform2F};
+use pathfinder_geometry::vector::{Vector2F, Vector2I};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// A glyph rasterized into an RGBA image.
+pub struct GlyphBitmap {
+    /// Offset from the glyph's origin, on the baseline, to the top left of `image`, in device
+    /// pixels.
+    pub origin: Vector2I,
+    pub image: RgbaImage,
+    /// Whether `image` holds the glyph's own colors, rather than white to be tinted with the text
+    /// color.
+    pub is_color: bool,
+}
+
+/// Identifies a rasterization of a glyph.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct GlyphKey {
+    postscript_name: PostscriptName,
+    /// The size the glyph is rasterized at, in device pixels.
+    size: Au,
+    synthesis: Synthesis,
+    glyph_id: u32,
+    /// The index of the subpixel offset the glyph is rasterized at.
+    subpixel: u8,
+}
+
+/// A glyph of a text command placed in device pixels, ready to be rasterized and painted.
+#[derive(Clone, Debug)]
+pub struct PlacedGlyph {
+    pub key: GlyphKey,
+    /// Where the glyph's origin goes, in whole device pixels.  The fraction of a pixel it was
+    /// placed at is made up for by the subpixel offset it's rasterized at.
+    pub origin: Vector2I,
+}
+
+/// Places the glyphs of `text_command`, at `scale_factor` device pixels per CSS pixel.  Glyphs
+/// are rasterized at the size they're painted at in device pixels, so that they're as sharp on
+/// high-DPI displays as anywhere else.
+pub fn place_glyphs(text_command: &TextCommand, scale_factor: f32) -> Vec<PlacedGlyph> {
+    let origin = text_command.origin() * scale_factor;
+    text_command
+        .runs()
+        .iter()
+        .flat_map(|run| {
+            run.glyphs.iter().map(move |glyph| {
+                let position = origin + glyph.position * scale_factor;
+                // Glyphs are painted at whole pixels, having been rasterized at whichever subpixel
+                // offset lands them closest to where they were laid out.
+                let (x, subpixel) = subpixel_offset(position.x());
+                PlacedGlyph {
+                    key: GlyphKey {
+                        postscript_name: run.font.clone(),
+                        size: Au::from_f32_px(glyph.size_px * scale_factor),
+                        synthesis: run.synthesis,
+                        glyph_id: glyph.glyph_id,
+                        subpixel,
+                    },
+                    origin: Vector2F::new(x, position.y().round()).to_i32(),
+                }
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+pub enum GlyphRasterError {
+    Font(FontError),
+    Loading(GlyphLoadingError),
+}
+
+impl From<FontError> for GlyphRasterError {
+    fn from(err: FontError) -> Self {
+        GlyphRasterError::Font(err)
+    }
+}
+
+impl From<GlyphLoadingError> for GlyphRasterError {
+    fn from(err: GlyphLoadingError) -> Self {
+        GlyphRasterError::Loading(err)
+    }
+}
+
+/// Rasterizes glyphs, so that each glyph is rasterized only once per font, size, and subpixel
+/// offset.
+#[derive(Default)]
+pub struct GlyphRasterizer {
+    fonts: FontHandle,
+    /// Holds the data of the fonts color glyphs are read from.
+    shaper: Shaper,
+    /// The glyphs rasterized so far, or `None` for those with nothing to paint.
+    bitmaps: RefCell<HashMap<GlyphKey, Option<Rc<GlyphBitmap>>>>,
+}
+
+impl GlyphRasterizer {
+    pub fn new() -> GlyphRasterizer {
+        GlyphRasterizer {
+            fonts: FontHandle::new(),
+            shaper: Shaper::new(),
+            bitmaps: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the glyph identified by `key` rasterized, rasterizing it if it hasn't been yet.
+    /// Returns `None` for glyphs with nothing to paint, such as spaces.
+    pub fn rasterize(&self, key: &GlyphKey) -> Result<Option<Rc<GlyphBitmap>>, GlyphRasterError> {
+        if let Some(bitmap) = self.bitmaps.borrow().get(key) {
+            return Ok(bitmap.clone());
+        }
+        let font = self.fonts.get_font(&key.postscript_name)?;
+        // Color glyphs, such as emoji, are painted in their own colors and never synthesized bold
+        // or oblique.
+        let bitmap = match self.rasterize_color(&font, key.glyph_id, key.size.to_px()) {
+            Some(image) => Some(GlyphBitmap {
+                // Color glyphs are rasterized with their origin at the bottom left.
+                origin: Vector2I::new(0, -(image.height() as i32)),
+                image,
+                is_color: true,
+            }),
+            None => rasterize_glyph(
+                &font,
+                key.glyph_id,
+                key.size.to_f32_px(),
+                key.synthesis,
+                subpixel_x(key.subpixel),
+            )?,
+        };
+        let bitmap = bitmap.map(Rc::new);
+        self.bitmaps
+            .borrow_mut()
+            .insert(key.clone(), bitmap.clone());
+        Ok(bitmap)
+    }
+
+    /// Rasterizes the glyph `glyph_id` of `font` in color, if it's a color glyph.
+    fn rasterize_color(&self, font: &Font, glyph_id: u32, size_px: i32) -> Option<RgbaImage> {
+        let (font_data, font_index) = self.shaper.font_data(font).ok()?;
+        rasterize_color_glyph(&font_data, font_index, font, glyph_id, size_px)
+    }
+}
+
+/// Rasterizes the glyph `glyph_id` of `font` at `size_px`, shifted `subpixel_x` pixels to the
+/// right and altered by `synthesis`, into an image just big enough to hold it.  Returns `None` for
+/// glyphs with nothing to paint, such as spaces.
+fn rasterize_glyph(
+    font: &Font,
+    glyph_id: u32,
+    size_px: f32,
+    synthesis: Synthesis,
+    subpixel_x: f32,
+) -> Result<Option<GlyphBitmap>, GlyphLoadingError> {
+    let mut transform = Transform2F::from_translation(Vector2F::new(subpixel_x, 0.0));
+    if synthesis.oblique {
+        // Slant the glyph by skewing it, moving points further right the further above the
+        // baseline they are (y is negative above the baseline).
+        let skew = Synthesis::OBLIQUE_ANGLE_DEG.to_radians().tan();
+        transform = transform
+            * Transform2F {
+                matrix: Matrix2x2F::row_major(1.0, -skew, 0.0, 1.0),
+                vector: Vector2F::zero(),
+            };
+    }
+    let bounds = font.raster_bounds(
+        glyph_id,
+        size_px,
+        transform,
+        HintingOptions::None,
+        RasterizationOptions::GrayscaleAa,
+    )?;
+    // Emboldened glyphs are smeared to the right by the bold offset.
+    let bold_offset = synthesis.bold_offset(size_px);
+    let size = bounds.size() + Vector2I::new(bold_offset.ceil() as i32, 0);
+    if size.x() <= 0 || size.y() <= 0 {
+        return Ok(None);
+    }
+    let rasterize = |offset: f32| -> Result<Canvas, GlyphLoadingError> {
+        let mut canvas = Canvas::new(size, Format::A8);
+        font.rasterize_glyph(
+            &mut canvas,
+            glyph_id,
+            size_px,
+            Transform2F::from_translation(Vector2F::new(offset, 0.0) - bounds.origin().to_f32())
+                * transform,
+            HintingOptions::None,
+            RasterizationOptions::GrayscaleAa,
+        )?;
+        Ok(canvas)
+    };
+    let mut canvas = rasterize(0.0)?;
+    if synthesis.bold {
+        // Embolden the glyph by overlaying a copy of it shifted slightly to the right.
+        let shifted_canvas = rasterize(bold_offset)?;
+        for (pixel, shifted_pixel) in canvas.pixels.iter_mut().zip(shifted_canvas.pixels) {
+            *pixel = (*pixel).max(shifted_pixel);
+        }
+    }
+    // Coverage is stored as the alpha of white pixels, which are tinted with the text color when
+    // painted, so that glyphs of every color share the same rasterization.
+    let image = RgbaImage::from_fn(size.x() as u32, size.y() as u32, |x, y| {
+        Rgba([
+            255,
+            255,
+            255,
+            canvas.pixels[y as usize * canvas.stride + x as usize],
+        ])
+    });
+    Ok(Some(GlyphBitmap {
+        origin: bounds.origin(),
+        image,
+        is_color: false,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gfx::font::TEST_FONT;
+    use std::sync::Arc;
+
+    /// Returns the test font with `COLR` and `CPAL` tables added, which make its glyph for `ch` a
+    /// color glyph of a single layer, the glyph itself filled with `color` (as RGBA).
+    fn color_test_font(ch: char, color: [u8; 4]) -> Font {
+        let font = Font::from_bytes(Arc::new(TEST_FONT.to_vec()), 0).unwrap();
+        let glyph_id = font.glyph_for_char(ch).unwrap() as u16;
+        let [id_hi, id_lo] = glyph_id.to_be_bytes();
+        let colr = vec![
+            0, 0, // version
+            0, 1, // base glyph count
+            0, 0, 0, 14, // base glyph records offset
+            0, 0, 0, 20, // layer records offset
+            0, 1, // layer count
+            id_hi, id_lo, 0, 0, 0, 1, // base glyph: first layer 0, one layer
+            id_hi, id_lo, 0, 0, // layer: the glyph itself, palette entry 0
+        ];
+        let cpal = vec![
+            0, 0, // version
+            0, 1, // palette entry count
+            0, 1, // palette count
+            0, 1, // color record count
+            0, 0, 0, 14, // color records offset
+            0, 0, // first color of palette 0
+            color[2], color[1], color[0], color[3], // entry 0, BGRA
+        ];
+
+        // Rebuild the font's table directory with the new tables, in tag order.
+        let read_u32 = |data: &[u8], offset: usize| {
+            u32::from_be_bytes([
+                data[offset],
+                data[offset + 1],
+                data[offset + 2],
+                data[offset + 3],
+            ]) as usize
+        };
+        let table_count = u16::from_be_bytes([TEST_FONT[4], TEST_FONT[5]]) as usize;
+        let mut tables: Vec<([u8; 4], Vec<u8>)> = (0..table_count)
+            .map(|index| {
+                let record = 12 + index * 16;
+                let mut tag = [0; 4];
+                tag.copy_from_slice(&TEST_FONT[record..record + 4]);
+                let offset = read_u32(TEST_FONT, record + 8);
+                let length = read_u32(TEST_FONT, record + 12);
+                (tag, TEST_FONT[offset..offset + length].to_vec())
+            })
+            .collect();
+        tables.push((*b"COLR", colr));
+        tables.push((*b"CPAL", cpal));
+        tables.sort_by_key(|(tag, _)| *tag);
+
+        let table_count = tables.len() as u16;
+        let entry_selector = 15 - table_count.leading_zeros() as u16;
+        let search_range = 16 << entry_selector;
+        let mut font_data = TEST_FONT[0..4].to_vec();
+        for field in &[
+            table_count,
+            search_range,
+            entry_selector,
+            table_count * 16 - search_range,
+        ] {
+            font_data.extend_from_slice(&field.to_be_bytes());
+        }
+        let mut offset = 12 + tables.len() * 16;
+        for (tag, data) in &tables {
+            // Checksums aren't verified when fonts are loaded, so they're left out.
+            font_data.extend_from_slice(tag);
+            font_data.extend_from_slice(&[0; 4]);
+            font_data.extend_from_slice(&(offset as u32).to_be_bytes());
+            font_data.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            offset += (data.len() + 3) / 4 * 4;
+        }
+        for (_, data) in &tables {
+            font_data.extend_from_slice(data);
+            font_data.resize((font_data.len() + 3) / 4 * 4, 0);
+        }
+        Font::from_bytes(Arc::new(font_data), 0).unwrap()
+    }
+
+    #[test]
+    fn color_glyphs_are_painted_in_their_own_colors() {
+        let font = color_test_font('X', [0, 0, 255, 255]);
+        let key = |ch: char| GlyphKey {
+            postscript_name: "Ahem".to_owned(),
+            size: Au::from_px(16),
+            synthesis: Synthesis::default(),
+            glyph_id: font.glyph_for_char(ch).unwrap(),
+            subpixel: 0,
+        };
+        let (x_key, y_key) = (key('X'), key('Y'));
+        let rasterizer = GlyphRasterizer::new();
+        rasterizer.fonts.add_font(font);
+
+        let x = rasterizer.rasterize(&x_key).unwrap().unwrap();
+        assert!(x.is_color);
+        assert_eq!(x.origin, Vector2I::new(0, -16));
+        // The glyph is an em box, 0.8em above the baseline and 0.2em below it.
+        assert_eq!(x.image.get_pixel(8, 8).0, [0, 0, 255, 255]);
+        assert_eq!(x.image.get_pixel(8, 15).0, [0, 0, 255, 255]);
+
+        // Glyphs without color layers are still rasterized in white, to be tinted with the text
+        // color.
+        let y = rasterizer.rasterize(&y_key).unwrap().unwrap();
+        assert!(!y.is_color);
+        assert_eq!(y.image.get_pixel(8, 8).0, [255, 255, 255, 255]);
+    }
+}
@@ -0,0 +1,132 @@
+use crate::gfx::display::{DisplayCommand, DisplayList, ImageCommand};
+
+/// A part of the display list that's rasterized separately from the rest, then composited with
+/// the other layers (in order, each over those before it) to produce the output.
+///
+/// Content that changes every frame without otherwise changing the page is given a layer of its
+/// own, so that the layers around it don't have to be painted again when it changes; they only
+/// have to be composited again, which is much cheaper.  So are the stacking contexts of elements
+/// whose `will-change` hints that they're about to be animated.
+///
+/// TODO: Give scroll containers, transformed subtrees, and fixed-position content layers of their
+/// own once Kosmonaut supports them, so that scrolling and transform animations only re-composite.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Layer {
+    /// Content that's painted into a texture, which is kept until the content changes.
+    Painted(DisplayList),
+    /// An animated image, which is composited straight from the texture of its current frame, so
+    /// advancing the animation paints nothing.
+    AnimatedImage(ImageCommand),
+}
+
+/// Splits `display_list` into layers that, composited in order, paint the same output as it.
+/// Commands are kept in painting order, so runs of commands between content that's given a layer
+/// of its own (or layer boundaries) are painted into a layer together.
+pub fn layerize(display_list: &DisplayList) -> Vec<Layer> {
+    let mut layers = Vec::new();
+    let mut painted = DisplayList::new();
+    for command in display_list.commands() {
+        match command {
+            DisplayCommand::Image(image_command) if image_command.image().image().is_animated() => {
+                if !painted.commands().is_empty() {
+                    layers.push(Layer::Painted(std::mem::take(&mut painted)));
+                }
+                layers.push(Layer::AnimatedImage(image_command.clone()));
+            }
+            DisplayCommand::LayerBoundary => {
+                if !painted.commands().is_empty() {
+                    layers.push(Layer::Painted(std::mem::take(&mut painted)));
+                }
+            }
+            _ => painted.push(command.clone()),
+        }
+    }
+    if !painted.commands().is_empty() {
+        layers.push(Layer::Painted(painted));
+    }
+    layers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::{DecodedImage, ImageFrame, ImageUrl, LoadedImage};
+    use crate::layout::rect::Rect;
+    use crate::style::values::computed::length::CSSPixelLength;
+    use cssparser::RGBA;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn image_command(image: DecodedImage) -> DisplayCommand {
+        let rect = Rect {
+            start_x: 0.,
+            start_y: 0.,
+            width: CSSPixelLength::new(1.),
+            height: CSSPixelLength::new(1.),
+        };
+        let image = LoadedImage::new(ImageUrl::new("image".to_owned()), Arc::new(image));
+        DisplayCommand::Image(ImageCommand::new(image, 0, rect, rect))
+    }
+
+    #[test]
+    fn animated_images_get_layers_of_their_own() {
+        let frame = || ImageFrame::new(vec![0; 4], Duration::from_millis(100));
+        let animated = image_command(DecodedImage::new_animated(1, 1, vec![frame(), frame()]));
+        let still = image_command(DecodedImage::new(1, 1, vec![0; 4]));
+        let background = DisplayCommand::ViewportBackground(RGBA::new(255, 255, 255, 255));
+        let mut display_list = DisplayList::new();
+        for command in &[
+            background.clone(),
+            still.clone(),
+            animated.clone(),
+            still.clone(),
+        ] {
+            display_list.push(command.clone());
+        }
+
+        let layers = layerize(&display_list);
+        assert_eq!(layers.len(), 3);
+        assert_eq!(layers[0], {
+            let mut below = DisplayList::new();
+            below.push(background);
+            below.push(still.clone());
+            Layer::Painted(below)
+        });
+        match (&layers[1], animated) {
+            (Layer::AnimatedImage(layer_image), DisplayCommand::Image(image)) => {
+                assert_eq!(*layer_image, image)
+            }
+            _ => panic!("the animated image should've been in a layer of its own"),
+        }
+        assert_eq!(layers[2], {
+            let mut above = DisplayList::new();
+            above.push(still);
+            Layer::Painted(above)
+        });
+    }
+
+    #[test]
+    fn layer_boundaries_split_painted_layers() {
+        let painted = |color| {
+            let mut display_list = DisplayList::new();
+            display_list.push(DisplayCommand::ViewportBackground(color));
+            display_list
+        };
+        let (red, blue) = (RGBA::new(255, 0, 0, 255), RGBA::new(0, 0, 255, 255));
+        let mut display_list = painted(red);
+        for command in &[
+            DisplayCommand::LayerBoundary,
+            DisplayCommand::ViewportBackground(blue),
+            DisplayCommand::LayerBoundary,
+            DisplayCommand::LayerBoundary,
+        ] {
+            display_list.push(command.clone());
+        }
+
+        // Boundaries with nothing painted between them don't make empty layers.
+        assert_eq!(
+            layerize(&display_list),
+            vec![Layer::Painted(painted(red)), Layer::Painted(painted(blue))]
+        );
+    }
+}
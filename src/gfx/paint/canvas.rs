@@ -0,0 +1,80 @@
+use gl::texture::{Texture, TextureId, TextureKind};
+use gl::types::{GLint, GLuint};
+use gl::{
+    Gl, COLOR_ATTACHMENT0, FRAMEBUFFER, NEAREST, RGBA, RGBA8, TEXTURE_2D, TEXTURE_MAG_FILTER,
+    TEXTURE_MIN_FILTER, UNSIGNED_BYTE,
+};
+use std::ptr;
+
+/// An offscreen framebuffer a layer is painted into.  Unlike the buffers of the window, whose
+/// contents are undefined once they're swapped, the canvas keeps what was painted into it, so only
+/// the areas that changed since the last paint need to be painted again before the layer is
+/// composited into the window.
+///
+/// https://www.khronos.org/opengl/wiki/Framebuffer_Object
+pub struct Canvas {
+    gl: Gl,
+    framebuffer: GLuint,
+    /// The texture the canvas is painted into, which is attached to `framebuffer`.
+    texture: Texture,
+    width: u32,
+    height: u32,
+}
+
+impl Canvas {
+    pub fn new(gl: &Gl, width: u32, height: u32) -> Canvas {
+        let texture = Texture::new(TextureKind::TwoDimensional, gl);
+        let mut framebuffer = 0;
+        unsafe {
+            gl.BindTexture(TEXTURE_2D, texture.id());
+            gl.TexImage2D(
+                TEXTURE_2D,
+                0,
+                RGBA8 as GLint,
+                width as GLint,
+                height as GLint,
+                0,
+                RGBA,
+                UNSIGNED_BYTE,
+                ptr::null(),
+            );
+            gl.TexParameteri(TEXTURE_2D, TEXTURE_MIN_FILTER, NEAREST as GLint);
+            gl.TexParameteri(TEXTURE_2D, TEXTURE_MAG_FILTER, NEAREST as GLint);
+            gl.BindTexture(TEXTURE_2D, 0);
+
+            gl.GenFramebuffers(1, &mut framebuffer);
+            gl.BindFramebuffer(FRAMEBUFFER, framebuffer);
+            gl.FramebufferTexture2D(FRAMEBUFFER, COLOR_ATTACHMENT0, TEXTURE_2D, texture.id(), 0);
+            gl.BindFramebuffer(FRAMEBUFFER, 0);
+        }
+        Canvas {
+            gl: gl.clone(),
+            framebuffer,
+            texture,
+            width,
+            height,
+        }
+    }
+
+    /// Whether the canvas is the given size, in physical pixels.
+    pub fn has_size(&self, width: u32, height: u32) -> bool {
+        self.width == width && self.height == height
+    }
+
+    /// Directs painting into the canvas.
+    pub fn bind(&self) {
+        unsafe { self.gl.BindFramebuffer(FRAMEBUFFER, self.framebuffer) }
+    }
+
+    /// The texture holding what was painted into the canvas.  Its rows start at the bottom of
+    /// the canvas.
+    pub fn texture_id(&self) -> TextureId {
+        self.texture.id()
+    }
+}
+
+impl Drop for Canvas {
+    fn drop(&mut self) {
+        unsafe { self.gl.DeleteFramebuffers(1, &self.framebuffer) }
+    }
+}
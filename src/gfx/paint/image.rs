@@ -0,0 +1,199 @@
+use crate::gfx::display::ImageCommand;
+use crate::gfx::ndc::{ndc_x, ndc_y};
+use crate::gfx::paint::{build_program, ImagePaintData, ToVertices};
+use crate::image::{DecodedImage, ImageUrl, LoadedImage};
+use gl::program::Program;
+use gl::texture::{Texture, TextureId, TextureKind};
+use gl::types::{GLint, GLsizeiptr};
+use gl::vao::VertexArrayObject;
+use gl::vbo::VertexBufferObject;
+use gl::{
+    Gl, ARRAY_BUFFER, CLAMP_TO_EDGE, DYNAMIC_DRAW, FALSE, FLOAT, LINEAR, RGBA, TEXTURE0,
+    TEXTURE_2D, TEXTURE_MAG_FILTER, TEXTURE_MIN_FILTER, TEXTURE_WRAP_S, TEXTURE_WRAP_T, TRIANGLES,
+    UNSIGNED_BYTE,
+};
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::os::raw::c_void;
+
+/// Uses given OpenGL instance to paint decoded images.
+pub struct ImagePainter {
+    /// An instance of OpenGL.
+    gl: Gl,
+    /// The OpenGL program that will be used to paint images.
+    program: Program,
+    /// Image frames that have already been uploaded to the GPU, keyed by the URL of the image, the
+    /// width and height of the bitmap (which vary for SVGs rasterized at different sizes), and the
+    /// index of the frame.
+    textures: HashMap<(ImageUrl, u32, u32, usize), Texture>,
+    /// The VAO to use to paint images.
+    vao: VertexArrayObject,
+}
+
+impl ImagePainter {
+    pub fn new(gl: &Gl) -> Result<ImagePainter, String> {
+        let vbo = VertexBufferObject::new(gl);
+        let config_vao = |gl: &Gl| {
+            unsafe {
+                // Each image is a 2D quad of 6 vertices with 4 floats each -- two for position,
+                // and two for texture coordinates.
+                gl.BufferData(
+                    ARRAY_BUFFER,
+                    (6 * 4 * std::mem::size_of::<f32>()) as GLsizeiptr,
+                    std::ptr::null(),
+                    DYNAMIC_DRAW,
+                );
+                gl.EnableVertexAttribArray(0);
+                gl.VertexAttribPointer(
+                    0,
+                    4,
+                    FLOAT,
+                    FALSE,
+                    (4 * std::mem::size_of::<f32>()) as GLint,
+                    std::ptr::null(),
+                );
+            }
+        };
+        let vao = unsafe { VertexArrayObject::new(vbo, config_vao, gl) };
+
+        Ok(ImagePainter {
+            gl: gl.clone(),
+            program: build_image_program(gl)?,
+            textures: HashMap::new(),
+            vao,
+        })
+    }
+
+    pub fn paint(&mut self, paintable_images: &[ImagePaintData]) {
+        for image in paintable_images {
+            // Images that are entirely clipped away have nothing to paint.
+            if image.vertices.is_empty() {
+                continue;
+            }
+            let texture_id = self.texture_id(&image.image, image.frame_index);
+            self.paint_texture(texture_id, &image.vertices);
+        }
+    }
+
+    /// Paints the texture with the given ID onto the quad described by `vertices`, which hold a
+    /// position and texture coordinates for each vertex.
+    pub fn paint_texture(&mut self, texture_id: TextureId, vertices: &[f32]) {
+        // Panic rather than truncate data.
+        assert!(vertices.len() <= i32::max_value() as usize);
+
+        self.program.use_globally();
+        unsafe {
+            self.gl.ActiveTexture(TEXTURE0);
+            self.gl.BindVertexArray(self.vao.name());
+            self.gl.BindTexture(TEXTURE_2D, texture_id);
+            self.vao.store_vertex_data(vertices);
+            // Casting the `usize` to `GLint` will not truncate due to the above assert!().
+            self.gl.DrawArrays(TRIANGLES, 0, vertices.len() as i32);
+            self.gl.BindVertexArray(0);
+            self.gl.BindTexture(TEXTURE_2D, 0);
+        }
+    }
+
+    /// Returns the texture for the given frame of an image, uploading the frame to the GPU if this
+    /// is the first time it has been painted.
+    ///
+    /// TODO: Every frame of an animated image gets its own texture, and they're never evicted.
+    /// Large animations would be better served by re-uploading a single texture per image.
+    fn texture_id(&mut self, image: &LoadedImage, frame_index: usize) -> TextureId {
+        let gl = &self.gl;
+        self.textures
+            .entry((
+                image.url().clone(),
+                image.image().width(),
+                image.image().height(),
+                frame_index,
+            ))
+            .or_insert_with(|| upload_texture(gl, image.image(), frame_index))
+            .id()
+    }
+}
+
+fn upload_texture(gl: &Gl, image: &DecodedImage, frame_index: usize) -> Texture {
+    let texture = Texture::new(TextureKind::TwoDimensional, gl);
+    unsafe {
+        gl.BindTexture(TEXTURE_2D, texture.id());
+        // Rows of RGBA pixels are always four-byte aligned, so the default `UNPACK_ALIGNMENT` of
+        // four is fine here.
+        gl.TexImage2D(
+            TEXTURE_2D,
+            0,
+            RGBA as GLint,
+            image.width() as GLint,
+            image.height() as GLint,
+            0,
+            RGBA,
+            UNSIGNED_BYTE,
+            image.frame(frame_index).pixels().as_ptr() as *const c_void,
+        );
+        gl.TexParameteri(TEXTURE_2D, TEXTURE_WRAP_S, CLAMP_TO_EDGE as GLint);
+        gl.TexParameteri(TEXTURE_2D, TEXTURE_WRAP_T, CLAMP_TO_EDGE as GLint);
+        gl.TexParameteri(TEXTURE_2D, TEXTURE_MIN_FILTER, LINEAR as GLint);
+        gl.TexParameteri(TEXTURE_2D, TEXTURE_MAG_FILTER, LINEAR as GLint);
+    }
+    texture
+}
+
+fn build_image_program(gl: &Gl) -> Result<Program, String> {
+    let vertex_shader_src = &CString::new(include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/shader_src/image.vert"
+    )))
+    .expect("could not create cstring for image program");
+
+    let frag_shader_src = &CString::new(include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/shader_src/image.frag"
+    )))
+    .expect("could not create cstring for image program");
+
+    build_program(vertex_shader_src, frag_shader_src, gl)
+}
+
+impl ToVertices for ImageCommand {
+    fn to_vertices(
+        &self,
+        scaled_viewport_width: f32,
+        scaled_viewport_height: f32,
+        scale_factor: f32,
+    ) -> Vec<f32> {
+        let rect = self.rect().scaled_by(scale_factor);
+        // Only the part of the image inside the clip is painted, so shrink both the quad and the
+        // texture coordinates to the visible area.
+        let visible = match rect.intersection(self.clip().scaled_by(scale_factor)) {
+            Some(visible) => visible,
+            None => return Vec::new(),
+        };
+        let start_x = ndc_x(visible.start_x, scaled_viewport_width);
+        let end_x = ndc_x(
+            (visible.start_x + visible.width).px(),
+            scaled_viewport_width,
+        );
+        let start_y = ndc_y(visible.start_y, scaled_viewport_height);
+        let end_y = ndc_y(
+            (visible.start_y + visible.height).px(),
+            scaled_viewport_height,
+        );
+
+        // Image rows are uploaded top-first, so a texture coordinate `t` of 0 is the top of the
+        // image.
+        let start_s = (visible.start_x - rect.start_x) / rect.width.px();
+        let end_s = start_s + visible.width.px() / rect.width.px();
+        let start_t = (visible.start_y - rect.start_y) / rect.height.px();
+        let end_t = start_t + visible.height.px() / rect.height.px();
+        let mut vertices = Vec::new();
+        // First triangle: top-left, top-right, bottom-left.
+        vertices.extend_from_slice(&[start_x, start_y, start_s, start_t]);
+        vertices.extend_from_slice(&[end_x, start_y, end_s, start_t]);
+        vertices.extend_from_slice(&[start_x, end_y, start_s, end_t]);
+        // Second triangle: bottom-left, top-right, bottom-right.
+        vertices.extend_from_slice(&[start_x, end_y, start_s, end_t]);
+        vertices.extend_from_slice(&[end_x, start_y, end_s, start_t]);
+        vertices.extend_from_slice(&[end_x, end_y, end_s, end_t]);
+        vertices
+    }
+}
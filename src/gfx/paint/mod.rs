@@ -1,15 +1,23 @@
-use crate::gfx::display::DisplayCommand;
+use crate::gfx::char::{CharHandle, OpenglAtlasTexture};
+use crate::gfx::display::{Damage, DisplayCommand, DisplayList};
+use crate::gfx::layer::{layerize, Layer};
+use crate::gfx::paint::canvas::Canvas;
+use crate::gfx::paint::image::ImagePainter;
 use crate::gfx::paint::rect::RectPainter;
 use crate::gfx::paint::text::TextPainter;
+use crate::image::LoadedImage;
 use crate::style::values::CSSFloat;
 use cssparser::RGBA;
 use gl::program::Program;
 use gl::shader::{Shader, ShaderKind};
 use gl::texture::TextureId;
-use gl::Gl;
+use gl::types::{GLint, GLsizei};
+use gl::{Gl, ONE, ONE_MINUS_SRC_ALPHA, SRC_ALPHA};
 use glutin::{PossiblyCurrent, WindowedContext};
 use std::ffi::CString;
 
+pub mod canvas;
+pub mod image;
 pub mod rect;
 pub mod text;
 
@@ -20,6 +28,15 @@ pub mod text;
 pub struct MasterPainter {
     /// The OpenGL instance to paint to.
     gl: Gl,
+    /// The glyph atlases text is painted from.
+    char_handle: CharHandle<OpenglAtlasTexture>,
+    /// The canvases of the painted layers last composited, in compositing order.  They're made
+    /// again whenever the window is resized.
+    layer_canvases: Vec<LayerCanvas>,
+    /// The display list that was last painted.
+    display_list: DisplayList,
+    image_painter: ImagePainter,
+    image_vertices: Vec<ImagePaintData>,
     rect_painter: RectPainter,
     rect_vertices: Vec<f32>,
     scale_factor: f32,
@@ -27,41 +44,92 @@ pub struct MasterPainter {
     text_vertices: Vec<CharPaintData>,
 }
 
+/// The canvas a painted layer was last painted into, and the display list painted into it.
+struct LayerCanvas {
+    canvas: Canvas,
+    display_list: DisplayList,
+}
+
+/// A quad covering the whole viewport, textured with a canvas.  The rows of a canvas start at the
+/// bottom, so the top of the viewport gets a texture coordinate `t` of 1.
+#[rustfmt::skip]
+const CANVAS_VERTICES: [f32; 24] = [
+    // First triangle: top-left, top-right, bottom-left.
+    -1., 1., 0., 1.,
+    1., 1., 1., 1.,
+    -1., -1., 0., 0.,
+    // Second triangle: bottom-left, top-right, bottom-right.
+    -1., -1., 0., 0.,
+    1., 1., 1., 1.,
+    1., -1., 1., 0.,
+];
+
 /// Data necessary to paint a character with OpenGL.
 #[derive(Clone, Debug)]
 pub struct CharPaintData {
-    // TODO: It would probably be cleaner to handle colors like RectPainter does vs. passing it here
-    pub color: RGBA,
+    /// The glyph atlas the character was packed into.
     pub texture_id: TextureId,
     pub vertices: Vec<f32>,
 }
 
 impl CharPaintData {
-    pub fn new(color: RGBA, texture_id: TextureId, vertices: Vec<f32>) -> Self {
+    pub fn new(texture_id: TextureId, vertices: Vec<f32>) -> Self {
         CharPaintData {
-            color,
             texture_id,
             vertices,
         }
     }
 }
 
+/// Data necessary to paint an image with OpenGL.
+#[derive(Clone, Debug)]
+pub struct ImagePaintData {
+    pub image: LoadedImage,
+    pub frame_index: usize,
+    pub vertices: Vec<f32>,
+}
+
+impl ImagePaintData {
+    pub fn new(image: LoadedImage, frame_index: usize, vertices: Vec<f32>) -> Self {
+        ImagePaintData {
+            image,
+            frame_index,
+            vertices,
+        }
+    }
+}
+
 impl MasterPainter {
-    pub fn new(gl: &Gl, scale_factor: f32) -> Result<MasterPainter, String> {
+    pub fn new(gl: &Gl) -> Result<MasterPainter, String> {
         Ok(MasterPainter {
             gl: gl.clone(),
+            char_handle: CharHandle::new_opengl(gl),
+            layer_canvases: Vec::new(),
+            display_list: DisplayList::new(),
+            image_painter: ImagePainter::new(gl)?,
+            image_vertices: Vec::new(),
             rect_painter: RectPainter::new(gl)?,
             rect_vertices: Vec::new(),
-            scale_factor,
+            // Set by each paint.
+            scale_factor: 1.,
             text_painter: TextPainter::new(gl)?,
             text_vertices: Vec::new(),
         })
     }
 
+    /// The display list that was last painted, which is what's currently shown in the window.
+    pub fn display_list(&self) -> &DisplayList {
+        &self.display_list
+    }
+
+    /// Paints `display_list` to the window, at `scale_factor` device pixels per CSS pixel.  The
+    /// display list is split into layers, and each painted layer only has the area that's damaged
+    /// since the last paint painted again before the layers are composited into the window.
     pub fn paint(
         &mut self,
         windowed_context: &WindowedContext<PossiblyCurrent>,
-        display_list: &[DisplayCommand],
+        display_list: DisplayList,
+        scale_factor: f32,
     ) {
         // Note: For semantic correctness, the OpenGL instance (the `gl` member on `self`) must
         // also have its viewport specified to the below dimensions, presumably done outside this
@@ -70,21 +138,142 @@ impl MasterPainter {
         let viewport_width = windowed_context.window().inner_size().width;
         let viewport_height = windowed_context.window().inner_size().height;
 
-        for command in display_list {
-            self.process_display_command(
-                command,
-                viewport_width as CSSFloat,
-                viewport_height as CSSFloat,
-            );
+        if scale_factor != self.scale_factor {
+            // Every layer is painted at a different size, so none of them can be kept.
+            self.layer_canvases.clear();
+            self.scale_factor = scale_factor;
+        }
+        let layers = layerize(&display_list);
+        let mut previous_canvases = std::mem::take(&mut self.layer_canvases).into_iter();
+        // Layers are transparent wherever nothing is painted into them, so blend their alpha
+        // such that they hold premultiplied colors, which composite correctly.
+        unsafe {
+            self.gl
+                .BlendFuncSeparate(SRC_ALPHA, ONE_MINUS_SRC_ALPHA, ONE, ONE_MINUS_SRC_ALPHA)
+        };
+        for layer in &layers {
+            if let Layer::Painted(layer_display_list) = layer {
+                let (canvas, damage) = match previous_canvases.next() {
+                    Some(previous) if previous.canvas.has_size(viewport_width, viewport_height) => {
+                        let damage = layer_display_list.damage_since(&previous.display_list);
+                        (previous.canvas, damage)
+                    }
+                    _ => (
+                        Canvas::new(&self.gl, viewport_width, viewport_height),
+                        Damage::Full,
+                    ),
+                };
+                canvas.bind();
+                self.paint_damage(layer_display_list, damage, viewport_width, viewport_height);
+                self.layer_canvases.push(LayerCanvas {
+                    canvas,
+                    display_list: layer_display_list.clone(),
+                });
+            }
+        }
+        self.composite(&layers, viewport_width, viewport_height);
+        windowed_context
+            .swap_buffers()
+            .expect("couldn't swap window buffers");
+        self.display_list = display_list;
+    }
+
+    /// Paints the commands of `display_list` that paint into the `damage`d area, into the bound
+    /// canvas, leaving the rest of the canvas as it was.
+    fn paint_damage(
+        &mut self,
+        display_list: &DisplayList,
+        damage: Damage,
+        viewport_width: u32,
+        viewport_height: u32,
+    ) {
+        if damage == Damage::None {
+            return;
+        }
+        if let Damage::Area(area) = damage {
+            // Scissor rects are in device pixels, starting at the bottom-left of the viewport.
+            let area = area.scaled_by(self.scale_factor);
+            let start_x = area.start_x.floor();
+            let end_x = (area.start_x + area.width).px().ceil();
+            let start_y = area.start_y.floor();
+            let end_y = (area.start_y + area.height).px().ceil();
+            unsafe {
+                self.gl.Enable(gl::SCISSOR_TEST);
+                self.gl.Scissor(
+                    start_x as GLint,
+                    viewport_height as GLint - end_y as GLint,
+                    (end_x - start_x) as GLsizei,
+                    (end_y - start_y) as GLsizei,
+                );
+            }
+        }
+        unsafe {
+            self.gl.ClearColor(0., 0., 0., 0.);
+            self.gl.Clear(gl::COLOR_BUFFER_BIT);
+        }
+        for command in display_list.commands() {
+            let is_damaged = match (damage, command.painted_area()) {
+                (Damage::None, _) | (_, Damage::None) => false,
+                (Damage::Full, _) | (_, Damage::Full) => true,
+                (Damage::Area(area), Damage::Area(painted_area)) => {
+                    area.intersection(painted_area).is_some()
+                }
+            };
+            if is_damaged {
+                self.process_display_command(
+                    command,
+                    viewport_width as CSSFloat,
+                    viewport_height as CSSFloat,
+                );
+            }
         }
+        // TODO: Painting bucket-by-bucket doesn't respect painting order, e.g. a background color
+        // painted after an image in the display list should cover that image.
         self.rect_painter.paint(self.rect_vertices.as_slice());
+        self.image_painter.paint(self.image_vertices.as_slice());
         self.text_painter.paint(self.text_vertices.as_slice());
         // Now that we've painted, let's dump the paint buckets so they're clean for the next paint.
+        self.image_vertices.clear();
         self.rect_vertices.clear();
         self.text_vertices.clear();
-        windowed_context
-            .swap_buffers()
-            .expect("couldn't swap window buffers");
+        unsafe { self.gl.Disable(gl::SCISSOR_TEST) };
+    }
+
+    /// Composites `layers` into the back buffer of the window, each over those before it.
+    fn composite(&mut self, layers: &[Layer], viewport_width: u32, viewport_height: u32) {
+        unsafe {
+            self.gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+            self.gl.ClearColor(0., 0., 0., 0.);
+            self.gl.Clear(gl::COLOR_BUFFER_BIT);
+        }
+        let mut canvases = self.layer_canvases.iter();
+        for layer in layers {
+            match layer {
+                Layer::Painted(_) => {
+                    let canvas = &canvases
+                        .next()
+                        .expect("every painted layer should have a canvas")
+                        .canvas;
+                    unsafe { self.gl.BlendFunc(ONE, ONE_MINUS_SRC_ALPHA) };
+                    self.image_painter
+                        .paint_texture(canvas.texture_id(), &CANVAS_VERTICES);
+                }
+                Layer::AnimatedImage(image_command) => {
+                    unsafe { self.gl.BlendFunc(SRC_ALPHA, ONE_MINUS_SRC_ALPHA) };
+                    self.image_painter.paint(&[ImagePaintData::new(
+                        image_command.image().clone(),
+                        image_command.frame_index(),
+                        image_command.to_vertices(
+                            viewport_width as CSSFloat,
+                            viewport_height as CSSFloat,
+                            self.scale_factor,
+                        ),
+                    )]);
+                }
+            }
+        }
+        // Restore the blending the rest of Kosmonaut expects.
+        unsafe { self.gl.BlendFunc(SRC_ALPHA, ONE_MINUS_SRC_ALPHA) };
     }
 
     fn process_display_command(
@@ -94,16 +283,45 @@ impl MasterPainter {
         viewport_height: CSSFloat,
     ) {
         match command {
-            DisplayCommand::Char(char_command) => {
-                self.text_vertices.push(CharPaintData::new(
-                    char_command.color(),
-                    char_command.texture_id(),
-                    char_command.to_vertices(viewport_width, viewport_height, self.scale_factor),
+            DisplayCommand::Text(text_command) => {
+                let char_commands = match self
+                    .char_handle
+                    .prepare_text(text_command, self.scale_factor)
+                {
+                    Ok(char_commands) => char_commands,
+                    Err(err) => {
+                        eprintln!("couldn't prepare text for painting: {:?}", err);
+                        return;
+                    }
+                };
+                for char_command in char_commands {
+                    self.text_vertices.push(CharPaintData::new(
+                        char_command.texture_id(),
+                        char_command.to_vertices(
+                            viewport_width,
+                            viewport_height,
+                            self.scale_factor,
+                        ),
+                    ));
+                }
+            }
+            DisplayCommand::Image(image_command) => {
+                self.image_vertices.push(ImagePaintData::new(
+                    image_command.image().clone(),
+                    image_command.frame_index(),
+                    image_command.to_vertices(viewport_width, viewport_height, self.scale_factor),
                 ));
             }
-            DisplayCommand::RectSolidColor(rgba, rect) => self.rect_vertices.extend(
-                (rect, rgba).to_vertices(viewport_width, viewport_height, self.scale_factor),
-            ),
+            DisplayCommand::RectSolidColor(rgba, rect) | DisplayCommand::Border(rgba, rect) => {
+                self.rect_vertices.extend((rect, rgba).to_vertices(
+                    viewport_width,
+                    viewport_height,
+                    self.scale_factor,
+                ))
+            }
+            // Hit test areas are only used for hit testing, and layer boundaries only split the
+            // display list into layers.
+            DisplayCommand::HitTestArea(..) | DisplayCommand::LayerBoundary => {}
             DisplayCommand::ViewportBackground(rgba) => unsafe {
                 self.gl.ClearColor(
                     rgba.red_f32(),
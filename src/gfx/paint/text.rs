@@ -1,13 +1,19 @@
-use crate::gfx::display::CharCommand;
+use crate::gfx::char::CharCommand;
 use crate::gfx::ndc::{ndc_x, ndc_y};
 use crate::gfx::paint::{build_program, CharPaintData, ToVertices};
+use cssparser::RGBA;
 use gl::program::Program;
-use gl::types::{GLint, GLsizeiptr};
+use gl::texture::TextureId;
+use gl::types::{GLint, GLvoid};
 use gl::vao::VertexArrayObject;
 use gl::vbo::VertexBufferObject;
-use gl::{Gl, ARRAY_BUFFER, DYNAMIC_DRAW, FALSE, FLOAT, TEXTURE0, TEXTURE_2D, TRIANGLES};
+use gl::{Gl, FALSE, FLOAT, TEXTURE0, TEXTURE_2D, TRIANGLES};
 use std::ffi::CString;
 
+/// Each vertex of a glyph's quad is its position and texture coordinates, followed by the color
+/// to tint the glyph with.
+const FLOATS_PER_VERTEX: usize = 8;
+
 /// Uses given OpenGL instance to paint arbitrary text.
 pub struct TextPainter {
     /// An instance of OpenGL.
@@ -23,16 +29,8 @@ impl TextPainter {
         let vbo = VertexBufferObject::new(gl);
         let config_vao = |gl: &Gl| {
             unsafe {
-                // Our 2D quad requires 6 vertices of 4 floats each, so pass that as the size of the buffer.
-                // Pass a null pointer because we don't want to initialize the buffer with any data right now.
-                // DYNAMIC_DRAW because this buffer will be updated very often (once for each character drawn).
-                gl.BufferData(
-                    ARRAY_BUFFER,
-                    (6 * 4 * std::mem::size_of::<f32>()) as GLsizeiptr,
-                    std::ptr::null(),
-                    DYNAMIC_DRAW,
-                );
-                // Enable use of `layout (location=0)` data in our vertex shader.
+                // Enable use of `layout (location=0)` data in our vertex shader: the position and
+                // texture coordinates of each vertex.
                 gl.EnableVertexAttribArray(0);
                 // Location index 0 data has a size of four floats and is not normalized, where normalization
                 // is the process of OpenGL mapping the values to a [-1,1] range for signed values or a range
@@ -42,9 +40,19 @@ impl TextPainter {
                     4,
                     FLOAT,
                     FALSE,
-                    (4 * std::mem::size_of::<f32>()) as GLint,
+                    (FLOATS_PER_VERTEX * std::mem::size_of::<f32>()) as GLint,
                     std::ptr::null(),
                 );
+                // Enable use of `layout (location=1)` data: the color to tint the glyph with.
+                gl.EnableVertexAttribArray(1);
+                gl.VertexAttribPointer(
+                    1,
+                    4,
+                    FLOAT,
+                    FALSE,
+                    (FLOATS_PER_VERTEX * std::mem::size_of::<f32>()) as GLint,
+                    (4 * std::mem::size_of::<f32>()) as *const GLvoid,
+                );
             }
         };
         let vao = unsafe { VertexArrayObject::new(vbo, config_vao, gl) };
@@ -56,44 +64,42 @@ impl TextPainter {
         })
     }
 
+    /// Paints `paintable_chars` in order, batching each stretch of chars packed into the same
+    /// glyph atlas into a single draw call.
     pub fn paint(&mut self, paintable_chars: &[CharPaintData]) {
         self.program.use_globally();
-
-        let text_color_str =
-            CString::new("textColor").expect("couldn't create `textColor` cstring");
         unsafe {
             self.gl.ActiveTexture(TEXTURE0);
             self.gl.BindVertexArray(self.vao.name());
         }
-        for ch in paintable_chars {
-            // Panic rather than truncate data.
-            assert!(ch.vertices.len() <= i32::max_value() as usize);
-
-            unsafe {
-                self.gl.Uniform3f(
-                    self.gl
-                        .GetUniformLocation(self.program.id(), text_color_str.as_ptr()),
-                    ch.color.red_f32(),
-                    ch.color.green_f32(),
-                    ch.color.blue_f32(),
-                );
-                self.gl.BindTexture(TEXTURE_2D, ch.texture_id);
-                self.vao.store_vertex_data(&ch.vertices);
-                // Casting the `usize` to `GLint` will not truncate due to the above assert!().
-                self.gl.DrawArrays(TRIANGLES, 0, ch.vertices.len() as i32);
-                // TODO:  We need to increment the `x_pos` (which is calculated somewhere else) with the advance of this character.
-                // This should probably happen somewhere else (layout?  display command processing?)
-                // Here is some C++ code that does this work:
-                //     now advance cursors for next glyph (note that advance is number of 1/64 pixels)
-                //     x += (ch.Advance >> 6) * scale; // bitshift by 6 to get value in pixels (2^6 = 64)
+        let mut batch_vertices = Vec::new();
+        for (index, ch) in paintable_chars.iter().enumerate() {
+            batch_vertices.extend_from_slice(&ch.vertices);
+            let batch_ends = paintable_chars
+                .get(index + 1)
+                .map_or(true, |next| next.texture_id != ch.texture_id);
+            if batch_ends {
+                self.paint_batch(ch.texture_id, &batch_vertices);
+                batch_vertices.clear();
             }
         }
-
         unsafe {
             self.gl.BindVertexArray(0);
             self.gl.BindTexture(TEXTURE_2D, 0);
         }
     }
+
+    fn paint_batch(&mut self, texture_id: TextureId, vertices: &[f32]) {
+        let vertex_count = vertices.len() / FLOATS_PER_VERTEX;
+        // Panic rather than truncate data.
+        assert!(vertex_count <= i32::max_value() as usize);
+        unsafe {
+            self.gl.BindTexture(TEXTURE_2D, texture_id);
+            self.vao.store_vertex_data(vertices);
+            // Casting the `usize` to `GLint` will not truncate due to the above assert!().
+            self.gl.DrawArrays(TRIANGLES, 0, vertex_count as i32);
+        }
+    }
 }
 
 fn build_text_program(gl: &Gl) -> Result<Program, String> {
@@ -112,59 +118,69 @@ fn build_text_program(gl: &Gl) -> Result<Program, String> {
     build_program(vertex_shader_src, frag_shader_src, gl)
 }
 
-impl ToVertices for CharCommand {
+impl<Id: Copy> ToVertices for CharCommand<Id> {
     fn to_vertices(
         &self,
         scaled_viewport_width: f32,
         scaled_viewport_height: f32,
-        _scale_factor: f32,
+        scale_factor: f32,
     ) -> Vec<f32> {
-        // TODO: Use scale_factor
-        let x_pos = self.start_coords().x() + self.bearing().x();
-        let y_pos = self.start_coords().y() - (self.size().y() - self.bearing().y());
-
-        // Transpose the quad width and height values (which are the second half of each vertex)
-        // relative to what https://learnopengl.com/In-Practice/Text-Rendering has because of the
-        // way we flip `ndc_y` values to render them at the top of the screen.
-        let mut vertices = Vec::new();
-        vertices.extend_from_slice(&[
-            ndc_x(x_pos, scaled_viewport_width),
-            ndc_y(y_pos + self.size().y(), scaled_viewport_height),
-            1.0,
-            1.0,
-        ]);
-        vertices.extend_from_slice(&[
-            ndc_x(x_pos, scaled_viewport_width),
-            ndc_y(y_pos, scaled_viewport_height),
-            1.0,
-            0.0,
-        ]);
-        vertices.extend_from_slice(&[
-            ndc_x(x_pos + self.size().x(), scaled_viewport_width),
-            ndc_y(y_pos, scaled_viewport_height),
-            0.0,
-            0.0,
-        ]);
-
-        vertices.extend_from_slice(&[
-            ndc_x(x_pos, scaled_viewport_width),
-            ndc_y(y_pos + self.size().y(), scaled_viewport_height),
-            1.0,
-            1.0,
-        ]);
-        vertices.extend_from_slice(&[
-            ndc_x(x_pos + self.size().x(), scaled_viewport_width),
-            ndc_y(y_pos, scaled_viewport_height),
-            0.0,
-            0.0,
-        ]);
-        vertices.extend_from_slice(&[
-            ndc_x(x_pos + self.size().x(), scaled_viewport_width),
-            ndc_y(y_pos + self.size().y(), scaled_viewport_height),
-            0.0,
-            1.0,
-        ]);
-
-        vertices
+        // The glyph's metrics are already in device pixels, but its origin is in CSS pixels.
+        let top_left = self.start_coords() * scale_factor + self.bearing();
+        let bottom_right = top_left + self.size();
+        let tex_coords = self.tex_coords();
+        // Color glyphs are painted in their own colors, so they're tinted with white.
+        let tint = if self.is_color() {
+            RGBA::new(255, 255, 255, self.color().alpha)
+        } else {
+            self.color()
+        };
+        let vertex = |x: f32, y: f32, s: f32, t: f32| {
+            [
+                ndc_x(x, scaled_viewport_width),
+                ndc_y(y, scaled_viewport_height),
+                s,
+                t,
+                tint.red_f32(),
+                tint.green_f32(),
+                tint.blue_f32(),
+                tint.alpha_f32(),
+            ]
+        };
+        let top_left_vertex = vertex(
+            top_left.x(),
+            top_left.y(),
+            tex_coords.min_x(),
+            tex_coords.min_y(),
+        );
+        let top_right_vertex = vertex(
+            bottom_right.x(),
+            top_left.y(),
+            tex_coords.max_x(),
+            tex_coords.min_y(),
+        );
+        let bottom_left_vertex = vertex(
+            top_left.x(),
+            bottom_right.y(),
+            tex_coords.min_x(),
+            tex_coords.max_y(),
+        );
+        let bottom_right_vertex = vertex(
+            bottom_right.x(),
+            bottom_right.y(),
+            tex_coords.max_x(),
+            tex_coords.max_y(),
+        );
+        // Two triangles: top left, top right, bottom left; then bottom left, top right, bottom
+        // right.
+        [
+            top_left_vertex,
+            top_right_vertex,
+            bottom_left_vertex,
+            bottom_left_vertex,
+            top_right_vertex,
+            bottom_right_vertex,
+        ]
+        .concat()
     }
 }
@@ -0,0 +1,1000 @@
+//! Printing of display lists into PDF documents.  Pages are written as vectors (rects are PDF
+//! paths and text is PDF text), so they scale without loss and their text can be selected.
+//!
+//! Text is shown in the fonts it was shaped with, which are embedded as Type0 fonts whose codes
+//! are glyph ids, with each glyph placed where layout placed it.  The `ToUnicode` CMap of each font
+//! and the `ActualText` of each text run map glyphs back to the text they were shaped from, so that
+//! it can be copied.  Text without glyphs (when no fonts are installed), or whose fonts can't be
+//! embedded, is shown in the standard Helvetica font in WinAnsiEncoding instead, so only its
+//! Latin-1 characters are printed (see `escape_text`).
+//!
+//! https://www.adobe.com/content/dam/acom/en/devnet/pdf/pdfs/PDF32000_2008.pdf
+
+use crate::gfx::display::{DisplayCommand, DisplayList, ImageCommand, TextCommand};
+use crate::gfx::glyph::{PostscriptName, Synthesis};
+use crate::image::DecodedImage;
+use crate::layout::rect::Rect;
+use crate::layout::text::font_data;
+use crate::style::values::computed::length::CSSPixelLength;
+use cssparser::RGBA;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+use ttf_parser::{Face, GlyphId, Tag};
+
+/// The size of the pages documents are printed onto (US Letter), in CSS pixels.
+const PAGE_WIDTH_PX: f32 = 816.;
+const PAGE_HEIGHT_PX: f32 = 1056.;
+/// The margins on each side of a page, which are half an inch.
+///
+/// TODO: Take the page size and margins from `@page` rules.
+const PAGE_MARGIN_PX: f32 = 48.;
+/// The size of the page area, the part of each page that content is laid out and painted in.
+///
+/// https://drafts.csswg.org/css-page-3/#page-area
+pub const PAGE_AREA_WIDTH_PX: f32 = PAGE_WIDTH_PX - 2. * PAGE_MARGIN_PX;
+pub const PAGE_AREA_HEIGHT_PX: f32 = PAGE_HEIGHT_PX - 2. * PAGE_MARGIN_PX;
+/// PDF lengths are in points, of which there are 72 to the inch, to 96 CSS pixels.
+const PT_PER_PX: f32 = 0.75;
+/// The line height of text, as a multiple of its font size, which is used to size text without
+/// glyphs, and so without a font size, from the height of its text run.
+const LINE_HEIGHT: f32 = 1.2;
+
+/// Prints `display_list`, built from a document laid out in a viewport the size of the page area,
+/// to a PDF with as many pages as it takes to fit the document.
+pub fn print_to_pdf(display_list: &DisplayList) -> Vec<u8> {
+    let mut writer = PdfWriter::default();
+    let catalog_id = writer.reserve();
+    let pages_id = writer.reserve();
+    let font_id = writer.add(
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+            .to_vec(),
+    );
+    // Images and fonts are written once, no matter how many times they're painted.
+    let mut image_ids = HashMap::new();
+    let mut fonts = EmbeddedFonts::default();
+    let mut page_ids = Vec::new();
+    for page in paginate(display_list) {
+        let page_content = write_page_content(&page, &mut writer, &mut image_ids, &mut fonts);
+        let content_id = writer.add(stream("", page_content.content.as_bytes()));
+        let mut resources = format!("/Font << /Helvetica {} 0 R", font_id);
+        for font_id in &page_content.fonts {
+            write!(resources, " /F{} {} 0 R", font_id, font_id).unwrap();
+        }
+        resources.push_str(" >>");
+        if !page_content.images.is_empty() {
+            resources.push_str(" /XObject <<");
+            for image_id in &page_content.images {
+                write!(resources, " /Im{} {} 0 R", image_id, image_id).unwrap();
+            }
+            resources.push_str(" >>");
+        }
+        if !page_content.alphas.is_empty() {
+            resources.push_str(" /ExtGState <<");
+            for alpha in &page_content.alphas {
+                write!(
+                    resources,
+                    " /GS{} << /ca {} /CA {} >>",
+                    alpha,
+                    *alpha as f32 / 255.,
+                    *alpha as f32 / 255.
+                )
+                .unwrap();
+            }
+            resources.push_str(" >>");
+        }
+        page_ids.push(
+            writer.add(
+                format!(
+                    "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}] /Resources << {} >> \
+                 /Contents {} 0 R >>",
+                    pages_id,
+                    PAGE_WIDTH_PX * PT_PER_PX,
+                    PAGE_HEIGHT_PX * PT_PER_PX,
+                    resources,
+                    content_id
+                )
+                .into_bytes(),
+            ),
+        );
+    }
+    fonts.finish(&mut writer);
+    let kids = page_ids
+        .iter()
+        .map(|page_id| format!("{} 0 R", page_id))
+        .collect::<Vec<_>>()
+        .join(" ");
+    writer.set(
+        pages_id,
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids,
+            page_ids.len()
+        )
+        .into_bytes(),
+    );
+    writer.set(
+        catalog_id,
+        format!("<< /Type /Catalog /Pages {} 0 R >>", pages_id).into_bytes(),
+    );
+    writer.finish(catalog_id)
+}
+
+/// Fragments `display_list` into pages the height of the page area, giving each page the commands
+/// that paint into it in the page's coordinates.
+///
+/// Boxes are sliced at page boundaries rather than broken between by layout.  Text runs are
+/// never sliced, and are printed whole on the page their top edge is on.
+///
+/// TODO: Fragment boxes during layout, honoring `break-before`, `break-after`, and
+/// `break-inside`.  https://drafts.csswg.org/css-break-3/
+fn paginate(display_list: &DisplayList) -> Vec<DisplayList> {
+    let page_count = display_list
+        .commands()
+        .iter()
+        .filter_map(last_page_of)
+        .max()
+        .map_or(1, |last_page| last_page + 1);
+    (0..page_count)
+        .map(|page_index| {
+            let page_top = page_index as f32 * PAGE_AREA_HEIGHT_PX;
+            let mut page = DisplayList::new();
+            for command in display_list.commands() {
+                if let Some(command) = command_on_page(command, page_top) {
+                    page.push(command);
+                }
+            }
+            page
+        })
+        .collect()
+}
+
+/// The index of the last page `command` paints into, if it paints into any in particular.
+fn last_page_of(command: &DisplayCommand) -> Option<usize> {
+    let bottom = match command {
+        DisplayCommand::Border(_, rect) | DisplayCommand::RectSolidColor(_, rect) => {
+            rect.start_y + rect.height.px()
+        }
+        DisplayCommand::Image(image_command) => {
+            let visible = image_command.rect().intersection(image_command.clip())?;
+            visible.start_y + visible.height.px()
+        }
+        DisplayCommand::Text(text_command) => {
+            return Some(page_of(text_command.rect().start_y));
+        }
+        DisplayCommand::HitTestArea(..)
+        | DisplayCommand::LayerBoundary
+        | DisplayCommand::ViewportBackground(_) => return None,
+    };
+    // A command whose bottom edge is on the boundary of two pages ends on the first of them.
+    Some(((bottom / PAGE_AREA_HEIGHT_PX).ceil().max(1.) as usize) - 1)
+}
+
+fn page_of(y: f32) -> usize {
+    (y / PAGE_AREA_HEIGHT_PX).floor().max(0.) as usize
+}
+
+/// The part of `command` that paints into the page `page_top` pixels down the document, in the
+/// page's coordinates.
+fn command_on_page(command: &DisplayCommand, page_top: f32) -> Option<DisplayCommand> {
+    match command {
+        DisplayCommand::Border(rgba, rect) => {
+            Some(DisplayCommand::Border(*rgba, slice_rect(*rect, page_top)?))
+        }
+        DisplayCommand::RectSolidColor(rgba, rect) => Some(DisplayCommand::RectSolidColor(
+            *rgba,
+            slice_rect(*rect, page_top)?,
+        )),
+        DisplayCommand::Image(image_command) => Some(DisplayCommand::Image(ImageCommand::new(
+            image_command.image().clone(),
+            image_command.frame_index(),
+            translate_rect(image_command.rect(), page_top),
+            slice_rect(image_command.clip(), page_top)?,
+        ))),
+        DisplayCommand::Text(text_command)
+            if page_of(text_command.rect().start_y) == page_of(page_top) =>
+        {
+            Some(DisplayCommand::Text(text_command.translated(0., -page_top)))
+        }
+        // The viewport background is the background of every page.
+        DisplayCommand::ViewportBackground(rgba) => Some(DisplayCommand::ViewportBackground(*rgba)),
+        // Pages are printed without layers.
+        DisplayCommand::HitTestArea(..)
+        | DisplayCommand::LayerBoundary
+        | DisplayCommand::Text(_) => None,
+    }
+}
+
+/// The part of `rect` within the page `page_top` pixels down the document, in the page's
+/// coordinates.
+fn slice_rect(rect: Rect, page_top: f32) -> Option<Rect> {
+    let start_y = rect.start_y.max(page_top);
+    let end_y = (rect.start_y + rect.height.px()).min(page_top + PAGE_AREA_HEIGHT_PX);
+    if end_y <= start_y {
+        return None;
+    }
+    Some(Rect {
+        start_y: start_y - page_top,
+        height: CSSPixelLength::new(end_y - start_y),
+        ..rect
+    })
+}
+
+fn translate_rect(rect: Rect, page_top: f32) -> Rect {
+    Rect {
+        start_y: rect.start_y - page_top,
+        ..rect
+    }
+}
+
+/// The content stream of a page, along with the resources it uses.
+struct PageContent {
+    content: String,
+    /// The object ids of the images painted.
+    images: BTreeSet<usize>,
+    /// The object ids of the fonts text is shown in, each of which is named `F` followed by its id.
+    fonts: BTreeSet<usize>,
+    /// The alphas of the colors painted with, each of which has a graphics state named after it.
+    alphas: BTreeSet<u8>,
+}
+
+/// Writes the content stream painting `page`.  Images are added to `writer` as they're first
+/// painted, and their object ids kept in `image_ids`.  Fonts are embedded in `fonts` as text is
+/// first shown in them.
+fn write_page_content(
+    page: &DisplayList,
+    writer: &mut PdfWriter,
+    image_ids: &mut HashMap<(*const DecodedImage, usize), usize>,
+    fonts: &mut EmbeddedFonts,
+) -> PageContent {
+    let mut page_content = PageContent {
+        content: String::new(),
+        images: BTreeSet::new(),
+        fonts: BTreeSet::new(),
+        alphas: BTreeSet::new(),
+    };
+    // Paint in CSS pixels, with the y-axis pointing down from the top of the page like it does in
+    // layout, rather than up from the bottom.
+    writeln!(
+        page_content.content,
+        "{} 0 0 {} 0 {} cm",
+        PT_PER_PX,
+        -PT_PER_PX,
+        PAGE_HEIGHT_PX * PT_PER_PX
+    )
+    .unwrap();
+    for command in page.commands() {
+        if let DisplayCommand::ViewportBackground(rgba) = command {
+            let page_rect = rect(0., 0., PAGE_WIDTH_PX, PAGE_HEIGHT_PX);
+            page_content.fill_rect(*rgba, page_rect);
+        }
+    }
+    // Move into the page area, and clip to it.
+    writeln!(
+        page_content.content,
+        "1 0 0 1 {} {} cm 0 0 {} {} re W n",
+        PAGE_MARGIN_PX, PAGE_MARGIN_PX, PAGE_AREA_WIDTH_PX, PAGE_AREA_HEIGHT_PX
+    )
+    .unwrap();
+    for command in page.commands() {
+        match command {
+            DisplayCommand::Border(rgba, rect) | DisplayCommand::RectSolidColor(rgba, rect) => {
+                page_content.fill_rect(*rgba, *rect)
+            }
+            DisplayCommand::Image(image_command) => {
+                let image = image_command.image().image();
+                let frame_index = image_command.frame_index();
+                let image_id = *image_ids
+                    .entry((image as *const DecodedImage, frame_index))
+                    .or_insert_with(|| add_image(writer, image, frame_index));
+                page_content.draw_image(image_id, image_command);
+            }
+            DisplayCommand::Text(text_command) => {
+                page_content.show_text(text_command, writer, fonts)
+            }
+            DisplayCommand::HitTestArea(..)
+            | DisplayCommand::LayerBoundary
+            | DisplayCommand::ViewportBackground(_) => {}
+        }
+    }
+    page_content
+}
+
+impl PageContent {
+    fn fill_rect(&mut self, rgba: RGBA, rect: Rect) {
+        if rgba.alpha == 0 {
+            return;
+        }
+        self.content.push_str("q ");
+        self.set_fill_color(rgba);
+        writeln!(
+            self.content,
+            "{} {} {} {} re f Q",
+            rect.start_x,
+            rect.start_y,
+            rect.width.px(),
+            rect.height.px()
+        )
+        .unwrap();
+    }
+
+    fn draw_image(&mut self, image_id: usize, image_command: &ImageCommand) {
+        let (rect, clip) = (image_command.rect(), image_command.clip());
+        self.images.insert(image_id);
+        // Images are drawn into the unit square, upside down in the y-down space pages are
+        // painted in, so they're flipped back over when scaled to `rect`.
+        writeln!(
+            self.content,
+            "q {} {} {} {} re W n {} 0 0 {} {} {} cm /Im{} Do Q",
+            clip.start_x,
+            clip.start_y,
+            clip.width.px(),
+            clip.height.px(),
+            rect.width.px(),
+            -rect.height.px(),
+            rect.start_x,
+            rect.start_y + rect.height.px(),
+            image_id
+        )
+        .unwrap();
+    }
+
+    /// Shows the glyphs of `text_command` in the fonts they were shaped with, embedding the fonts
+    /// in `fonts` if they haven't been yet.  Text without glyphs, or with glyphs of fonts that
+    /// can't be embedded, is shown in Helvetica instead.
+    fn show_text(
+        &mut self,
+        text_command: &TextCommand,
+        writer: &mut PdfWriter,
+        fonts: &mut EmbeddedFonts,
+    ) {
+        let rgba = text_command.color();
+        if rgba.alpha == 0 {
+            return;
+        }
+        let runs = text_command.runs();
+        if runs.is_empty()
+            || !runs
+                .iter()
+                .all(|run| fonts.get(writer, &run.font).is_some())
+        {
+            self.show_text_in_helvetica(text_command);
+            return;
+        }
+        let text = text_command.text();
+        self.content.push_str("q ");
+        self.clip_text(text_command);
+        self.set_fill_color(rgba);
+        if runs.iter().any(|run| run.synthesis.bold) {
+            write!(
+                self.content,
+                "{} {} {} RG ",
+                rgba.red_f32(),
+                rgba.green_f32(),
+                rgba.blue_f32()
+            )
+            .unwrap();
+        }
+        // Text copied from the run is the text it was shaped from, including any of it (such as
+        // collapsed spaces) that has no glyph.
+        writeln!(
+            self.content,
+            "/Span << /ActualText <FEFF{}> >> BDC BT",
+            utf16_hex(text)
+        )
+        .unwrap();
+        let mut cluster_starts = runs
+            .iter()
+            .flat_map(|run| run.glyphs.iter().map(|glyph| glyph.cluster))
+            .collect::<Vec<_>>();
+        cluster_starts.sort_unstable();
+        cluster_starts.dedup();
+        for run in runs {
+            let font = fonts.get(writer, &run.font).unwrap();
+            self.fonts.insert(font.id);
+            // Synthesized bold glyphs are stroked as well as filled, which widens them by the
+            // width of the stroke.
+            let rendering_mode = if run.synthesis.bold { 2 } else { 0 };
+            write!(self.content, "{} Tr ", rendering_mode).unwrap();
+            let skew = if run.synthesis.oblique {
+                Synthesis::OBLIQUE_ANGLE_DEG.to_radians().tan()
+            } else {
+                0.
+            };
+            let mut size_px = None;
+            for glyph in &run.glyphs {
+                if size_px != Some(glyph.size_px) {
+                    size_px = Some(glyph.size_px);
+                    write!(self.content, "/F{} {} Tf ", font.id, glyph.size_px).unwrap();
+                    if run.synthesis.bold {
+                        let bold_offset = run.synthesis.bold_offset(glyph.size_px);
+                        write!(self.content, "{} w ", bold_offset).unwrap();
+                    }
+                }
+                // Glyphs are flipped back upright, since pages are painted with the y-axis
+                // pointing down.
+                let position = text_command.origin() + glyph.position;
+                writeln!(
+                    self.content,
+                    "1 0 {} -1 {} {} Tm <{:04X}> Tj",
+                    skew,
+                    position.x(),
+                    position.y(),
+                    glyph.glyph_id
+                )
+                .unwrap();
+                // A glyph was shaped from the text up to the start of the next cluster.
+                let cluster_end = cluster_starts
+                    .iter()
+                    .copied()
+                    .find(|&start| start > glyph.cluster)
+                    .unwrap_or(text.len());
+                let glyph_text = text.get(glyph.cluster..cluster_end).unwrap_or("");
+                font.glyphs
+                    .entry(glyph.glyph_id as u16)
+                    .or_insert_with(|| glyph_text.to_owned());
+            }
+        }
+        writeln!(self.content, "ET EMC Q").unwrap();
+    }
+
+    /// Shows the text of `text_command` in Helvetica, at the size of its glyphs if it has any, or
+    /// else sized to fit the height of its text run.
+    fn show_text_in_helvetica(&mut self, text_command: &TextCommand) {
+        let font_size = text_command
+            .runs()
+            .iter()
+            .find_map(|run| run.glyphs.first())
+            .map_or(text_command.rect().height.px() / LINE_HEIGHT, |glyph| {
+                glyph.size_px
+            });
+        let rgba = text_command.color();
+        if rgba.alpha == 0 || font_size <= 0. {
+            return;
+        }
+        self.content.push_str("q ");
+        self.clip_text(text_command);
+        self.set_fill_color(rgba);
+        // Glyphs are flipped back upright, since pages are painted with the y-axis pointing down.
+        let origin = text_command.origin();
+        writeln!(
+            self.content,
+            "BT /Helvetica {} Tf 1 0 0 -1 {} {} Tm ({}) Tj ET Q",
+            font_size,
+            origin.x(),
+            origin.y(),
+            escape_text(text_command.text())
+        )
+        .unwrap();
+    }
+
+    fn clip_text(&mut self, text_command: &TextCommand) {
+        if let Some(clip) = text_command.clip() {
+            write!(
+                self.content,
+                "{} {} {} {} re W n ",
+                clip.start_x,
+                clip.start_y,
+                clip.width.px(),
+                clip.height.px()
+            )
+            .unwrap();
+        }
+    }
+
+    fn set_fill_color(&mut self, rgba: RGBA) {
+        if rgba.alpha != 255 {
+            self.alphas.insert(rgba.alpha);
+            write!(self.content, "/GS{} gs ", rgba.alpha).unwrap();
+        }
+        write!(
+            self.content,
+            "{} {} {} rg ",
+            rgba.red_f32(),
+            rgba.green_f32(),
+            rgba.blue_f32()
+        )
+        .unwrap();
+    }
+}
+
+/// Adds frame `frame_index` of `image` to `writer`, returning the id of its image XObject.  Its
+/// alpha channel is written as a separate soft mask.
+///
+/// TODO: Compress images.
+fn add_image(writer: &mut PdfWriter, image: &DecodedImage, frame_index: usize) -> usize {
+    let pixels = image.frame(frame_index).pixels();
+    let (width, height) = (image.width(), image.height());
+    let rgb = pixels
+        .chunks(4)
+        .flat_map(|pixel| pixel[..3].iter().copied())
+        .collect::<Vec<_>>();
+    let alpha = pixels.chunks(4).map(|pixel| pixel[3]).collect::<Vec<_>>();
+    let soft_mask_id = writer.add(stream(
+        &format!(
+            "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceGray \
+             /BitsPerComponent 8",
+            width, height
+        ),
+        &alpha,
+    ));
+    writer.add(stream(
+        &format!(
+            "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB \
+             /BitsPerComponent 8 /SMask {} 0 R",
+            width, height, soft_mask_id
+        ),
+        &rgb,
+    ))
+}
+
+/// The fonts text has been shown in so far, or `None` for those that can't be embedded.
+#[derive(Default)]
+struct EmbeddedFonts(BTreeMap<PostscriptName, Option<EmbeddedFont>>);
+
+impl EmbeddedFonts {
+    /// The font named `postscript_name`, loading it the first time it's asked for.
+    fn get(&mut self, writer: &mut PdfWriter, postscript_name: &str) -> Option<&mut EmbeddedFont> {
+        self.0
+            .entry(postscript_name.to_owned())
+            .or_insert_with(|| EmbeddedFont::load(writer, postscript_name))
+            .as_mut()
+    }
+
+    /// Writes out the fonts, now that the glyphs shown in them are known.
+    fn finish(self, writer: &mut PdfWriter) {
+        for (postscript_name, font) in self.0 {
+            if let Some(font) = font {
+                font.write(writer, &postscript_name);
+            }
+        }
+    }
+}
+
+/// A font embedded in a PDF as a Type0 font, whose two-byte codes are glyph ids (CIDs, with the
+/// `Identity-H` encoding).
+///
+/// TODO: Subset fonts to the glyphs shown in them, and compress them.
+struct EmbeddedFont {
+    /// The object id of the Type0 font, which is set once every page has been written, since it
+    /// maps the glyphs shown to text.
+    id: usize,
+    /// The font file, holding a single TrueType or OpenType font.
+    data: Vec<u8>,
+    units_per_em: f32,
+    /// The bounding box of all of the font's glyphs, as `[x_min, y_min, x_max, y_max]`, in font
+    /// units.
+    bounding_box: [i16; 4],
+    ascent: i16,
+    descent: i16,
+    cap_height: i16,
+    italic_angle: f32,
+    /// The advance of each of the font's glyphs, in font units.
+    advances: Vec<u16>,
+    /// The glyphs shown in the font, with the text each was (first) shaped from.
+    glyphs: BTreeMap<u16, String>,
+}
+
+impl EmbeddedFont {
+    /// Loads the font named `postscript_name` that text is shaped with, reserving the id of its
+    /// object in `writer`.  Returns `None` if the font can't be loaded.
+    fn load(writer: &mut PdfWriter, postscript_name: &str) -> Option<EmbeddedFont> {
+        let (data, index) = font_data(postscript_name)?;
+        let data = single_font(&data, index)?;
+        let face = Face::from_slice(&data, 0).ok()?;
+        let head = face.table_data(Tag::from_bytes(b"head"))?;
+        let units_per_em = read_u16(head, 18).filter(|&units_per_em| units_per_em > 0)?;
+        let bounding_box = [
+            read_i16(head, 36)?,
+            read_i16(head, 38)?,
+            read_i16(head, 40)?,
+            read_i16(head, 42)?,
+        ];
+        let advances = (0..face.number_of_glyphs())
+            .map(|glyph_id| face.glyph_hor_advance(GlyphId(glyph_id)).unwrap_or(0))
+            .collect();
+        let (ascent, descent) = (face.ascender(), face.descender());
+        let cap_height = face.capital_height().unwrap_or(ascent);
+        let italic_angle = face.italic_angle().unwrap_or(0.);
+        Some(EmbeddedFont {
+            id: writer.reserve(),
+            data,
+            units_per_em: f32::from(units_per_em),
+            bounding_box,
+            ascent,
+            descent,
+            cap_height,
+            italic_angle,
+            advances,
+            glyphs: BTreeMap::new(),
+        })
+    }
+
+    /// Writes the font, named `postscript_name`, along with its descendant CIDFont, font
+    /// descriptor, font file, and `ToUnicode` CMap.  Fonts with CFF outlines are embedded whole as
+    /// OpenType fonts, and those with TrueType outlines as TrueType fonts.
+    fn write(self, writer: &mut PdfWriter, postscript_name: &str) {
+        let to_pdf_units = |units: f32| units * 1000. / self.units_per_em;
+        let scaled = |units: i16| to_pdf_units(f32::from(units));
+        let name = escape_name(postscript_name);
+        let is_cff = self.data.starts_with(b"OTTO");
+        let (font_file_id, font_file_key) = if is_cff {
+            (
+                writer.add(stream("/Subtype /OpenType", &self.data)),
+                "FontFile3",
+            )
+        } else {
+            (
+                writer.add(stream(&format!("/Length1 {}", self.data.len()), &self.data)),
+                "FontFile2",
+            )
+        };
+        let [x_min, y_min, x_max, y_max] = self.bounding_box;
+        // Fonts are flagged as symbolic, since their glyphs aren't in any standard encoding.
+        let descriptor_id = writer.add(
+            format!(
+                "<< /Type /FontDescriptor /FontName /{} /Flags 4 /FontBBox [{} {} {} {}] \
+                 /ItalicAngle {} /Ascent {} /Descent {} /CapHeight {} /StemV 80 /{} {} 0 R >>",
+                name,
+                scaled(x_min),
+                scaled(y_min),
+                scaled(x_max),
+                scaled(y_max),
+                self.italic_angle,
+                scaled(self.ascent),
+                scaled(self.descent),
+                scaled(self.cap_height),
+                font_file_key,
+                font_file_id
+            )
+            .into_bytes(),
+        );
+        let widths = self
+            .glyphs
+            .keys()
+            .map(|&glyph_id| {
+                let advance = self.advances.get(usize::from(glyph_id)).copied();
+                format!(
+                    "{} [{}]",
+                    glyph_id,
+                    to_pdf_units(f32::from(advance.unwrap_or(0)))
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        let (subtype, cid_to_gid_map) = if is_cff {
+            ("CIDFontType0", "")
+        } else {
+            ("CIDFontType2", " /CIDToGIDMap /Identity")
+        };
+        let cid_font_id = writer.add(
+            format!(
+                "<< /Type /Font /Subtype /{} /BaseFont /{} /CIDSystemInfo << /Registry (Adobe) \
+                 /Ordering (Identity) /Supplement 0 >> /FontDescriptor {} 0 R /W [{}]{} >>",
+                subtype, name, descriptor_id, widths, cid_to_gid_map
+            )
+            .into_bytes(),
+        );
+        let to_unicode_id = writer.add(stream("", to_unicode_cmap(&self.glyphs).as_bytes()));
+        writer.set(
+            self.id,
+            format!(
+                "<< /Type /Font /Subtype /Type0 /BaseFont /{} /Encoding /Identity-H \
+                 /DescendantFonts [{} 0 R] /ToUnicode {} 0 R >>",
+                name, cid_font_id, to_unicode_id
+            )
+            .into_bytes(),
+        );
+    }
+}
+
+/// The font at `index` in `data`, which is either a single font, or a collection of fonts whose
+/// tables are copied out into a font of their own, since PDFs can't embed collections.
+fn single_font(data: &[u8], index: u32) -> Option<Vec<u8>> {
+    if !data.starts_with(b"ttcf") {
+        return if index == 0 {
+            Some(data.to_vec())
+        } else {
+            None
+        };
+    }
+    let offset = read_u32(data, 12 + 4 * index as usize)? as usize;
+    let table_count = usize::from(read_u16(data, offset + 4)?);
+    let records = data.get(offset + 12..offset + 12 + 16 * table_count)?;
+    let mut font = data.get(offset..offset + 12)?.to_vec();
+    let mut tables = Vec::new();
+    let mut table_offset = 12 + 16 * table_count;
+    for record in records.chunks(16) {
+        let start = read_u32(record, 8)? as usize;
+        let length = read_u32(record, 12)? as usize;
+        let mut table = data.get(start..start + length)?.to_vec();
+        // Each table record is its tag, checksum, offset, and length.
+        font.extend_from_slice(&record[..8]);
+        font.extend_from_slice(&(table_offset as u32).to_be_bytes());
+        font.extend_from_slice(&record[12..]);
+        // Tables start on four-byte boundaries.
+        table.resize((length + 3) & !3, 0);
+        table_offset += table.len();
+        tables.extend(table);
+    }
+    font.extend(tables);
+    Some(font)
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_i16(data: &[u8], offset: usize) -> Option<i16> {
+    read_u16(data, offset).map(|value| value as i16)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// A `ToUnicode` CMap mapping the ids of `glyphs` to the text each was shaped from, which is the
+/// text copied when the glyphs are selected.
+fn to_unicode_cmap(glyphs: &BTreeMap<u16, String>) -> String {
+    let mappings = glyphs
+        .iter()
+        .filter(|(_, text)| !text.is_empty())
+        .map(|(glyph_id, text)| format!("<{:04X}> <{}>", glyph_id, utf16_hex(text)))
+        .collect::<Vec<_>>();
+    let mut cmap = String::from(
+        "/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n\
+         /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+         /CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n\
+         1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+    );
+    // Each block of mappings can hold at most 100 of them.
+    for block in mappings.chunks(100) {
+        writeln!(
+            cmap,
+            "{} beginbfchar\n{}\nendbfchar",
+            block.len(),
+            block.join("\n")
+        )
+        .unwrap();
+    }
+    cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend");
+    cmap
+}
+
+/// `text` in UTF-16BE, as hexadecimal digits.
+fn utf16_hex(text: &str) -> String {
+    text.encode_utf16()
+        .map(|unit| format!("{:04X}", unit))
+        .collect()
+}
+
+/// Escapes `name` into a PDF name (without its leading `/`), in which delimiters, `#`, and bytes
+/// outside of printable ASCII are written as `#` followed by their hexadecimal code.
+fn escape_name(name: &str) -> String {
+    let mut escaped = String::new();
+    for byte in name.bytes() {
+        match byte {
+            b'!'..=b'~' if !b"#%()/<>[]{}".contains(&byte) => escaped.push(byte as char),
+            _ => write!(escaped, "#{:02X}", byte).unwrap(),
+        }
+    }
+    escaped
+}
+
+/// Escapes `text` into a PDF string in WinAnsiEncoding, which Helvetica is used with.  Characters
+/// WinAnsiEncoding has no code for are replaced by `?`.
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::new();
+    for ch in text.chars() {
+        match ch {
+            '(' | ')' | '\\' => {
+                escaped.push('\\');
+                escaped.push(ch);
+            }
+            ' '..='~' => escaped.push(ch),
+            '\t' | '\n' | '\r' => escaped.push(' '),
+            // WinAnsiEncoding shares these codes with Latin-1.
+            '\u{a0}'..='\u{ff}' => write!(escaped, "\\{:03o}", ch as u32).unwrap(),
+            _ => escaped.push('?'),
+        }
+    }
+    escaped
+}
+
+fn rect(start_x: f32, start_y: f32, width: f32, height: f32) -> Rect {
+    Rect {
+        start_x,
+        start_y,
+        width: CSSPixelLength::new(width),
+        height: CSSPixelLength::new(height),
+    }
+}
+
+/// A stream object with the entries `dict_entries` in its dictionary.
+fn stream(dict_entries: &str, data: &[u8]) -> Vec<u8> {
+    let mut object =
+        format!("<< {} /Length {} >>\nstream\n", dict_entries, data.len()).into_bytes();
+    object.extend_from_slice(data);
+    object.extend_from_slice(b"\nendstream");
+    object
+}
+
+/// Collects the objects of a PDF, and writes them out with the cross-reference table that
+/// locates them.
+#[derive(Default)]
+struct PdfWriter {
+    /// The objects, the id of each of which is one more than its index.
+    objects: Vec<Vec<u8>>,
+}
+
+impl PdfWriter {
+    /// Reserves an id for an object that's set later, once the objects it refers to are added.
+    fn reserve(&mut self) -> usize {
+        self.add(Vec::new())
+    }
+
+    fn set(&mut self, id: usize, object: Vec<u8>) {
+        self.objects[id - 1] = object;
+    }
+
+    fn add(&mut self, object: Vec<u8>) -> usize {
+        self.objects.push(object);
+        self.objects.len()
+    }
+
+    fn finish(self, root_id: usize) -> Vec<u8> {
+        // The comment with bytes above 127 marks the file as binary.  Version 1.6 is the first
+        // that can embed OpenType fonts as they are.
+        let mut pdf = b"%PDF-1.6\n%\xe2\xe3\xcf\xd3\n".to_vec();
+        let mut offsets = Vec::new();
+        for (index, object) in self.objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+            pdf.extend_from_slice(object);
+            pdf.extend_from_slice(b"\nendobj\n");
+        }
+        let xref_offset = pdf.len();
+        let mut xref = format!("xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1);
+        for offset in offsets {
+            writeln!(xref, "{:010} 00000 n ", offset).unwrap();
+        }
+        writeln!(
+            xref,
+            "trailer\n<< /Size {} /Root {} 0 R >>\nstartxref\n{}\n%%EOF",
+            self.objects.len() + 1,
+            root_id,
+            xref_offset
+        )
+        .unwrap();
+        pdf.extend_from_slice(xref.as_bytes());
+        pdf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gfx::display::TextCommand;
+    use pathfinder_geometry::vector::Vector2F;
+
+    /// Text without glyphs, which is all a PDF needs to show it.
+    fn text(text: &str, rgba: RGBA, rect: Rect) -> DisplayCommand {
+        DisplayCommand::Text(TextCommand::new(
+            text.to_owned(),
+            Vec::new(),
+            rgba,
+            Vector2F::new(rect.start_x, rect.start_y),
+            rect,
+        ))
+    }
+
+    #[test]
+    fn slices_rects_across_pages() {
+        let mut display_list = DisplayList::new();
+        display_list.push(DisplayCommand::ViewportBackground(RGBA::new(
+            255, 255, 255, 255,
+        )));
+        let red = RGBA::new(255, 0, 0, 255);
+        display_list.push(DisplayCommand::RectSolidColor(
+            red,
+            rect(0., PAGE_AREA_HEIGHT_PX - 10., 20., 30.),
+        ));
+        display_list.push(text(
+            "Kosmonaut",
+            red,
+            rect(0., PAGE_AREA_HEIGHT_PX - 5., 100., 20.),
+        ));
+        let pages = paginate(&display_list);
+        assert_eq!(pages.len(), 2);
+        assert_eq!(
+            pages[0].commands()[1..],
+            [
+                DisplayCommand::RectSolidColor(red, rect(0., PAGE_AREA_HEIGHT_PX - 10., 20., 10.)),
+                text(
+                    "Kosmonaut",
+                    red,
+                    rect(0., PAGE_AREA_HEIGHT_PX - 5., 100., 20.)
+                ),
+            ]
+        );
+        // Every page has the viewport background, but text runs are only on the page they start
+        // on.
+        assert_eq!(
+            pages[1].commands(),
+            [
+                DisplayCommand::ViewportBackground(RGBA::new(255, 255, 255, 255)),
+                DisplayCommand::RectSolidColor(red, rect(0., 0., 20., 20.)),
+            ]
+        );
+    }
+
+    #[test]
+    fn rects_ending_on_a_page_boundary_dont_start_another_page() {
+        let mut display_list = DisplayList::new();
+        display_list.push(DisplayCommand::RectSolidColor(
+            RGBA::new(0, 0, 0, 255),
+            rect(0., 0., 10., PAGE_AREA_HEIGHT_PX),
+        ));
+        assert_eq!(paginate(&display_list).len(), 1);
+        assert_eq!(paginate(&DisplayList::new()).len(), 1);
+    }
+
+    #[test]
+    fn writes_text_as_text() {
+        let mut display_list = DisplayList::new();
+        display_list.push(text(
+            "(Hello) wörld",
+            RGBA::new(0, 0, 0, 255),
+            rect(0., 0., 100., 24.),
+        ));
+        let pdf = String::from_utf8_lossy(&print_to_pdf(&display_list)).into_owned();
+        assert!(pdf.starts_with("%PDF-1.6"));
+        assert!(pdf.contains("/Count 1"));
+        assert!(pdf.contains("(\\(Hello\\) w\\366rld) Tj"));
+        assert!(pdf.ends_with("%%EOF\n"));
+    }
+
+    #[cfg(feature = "window")]
+    #[test]
+    fn embeds_the_fonts_text_is_shaped_with() {
+        use crate::layout::text::shape_text;
+        use crate::style::values::computed::ComputedValues;
+
+        let shaped = shape_text("Añ", &ComputedValues::default(), 0.);
+        let mut display_list = DisplayList::new();
+        display_list.push(DisplayCommand::Text(TextCommand::new(
+            "Añ".to_owned(),
+            shaped.runs,
+            RGBA::new(0, 0, 0, 255),
+            Vector2F::new(0., 16.),
+            rect(0., 0., 32., 16.),
+        )));
+        let pdf = String::from_utf8_lossy(&print_to_pdf(&display_list)).into_owned();
+        assert!(pdf.contains("/Subtype /Type0"));
+        assert!(pdf.contains("/Subtype /CIDFontType2"));
+        assert!(pdf.contains("/FontFile2"));
+        // Both the run and the font's glyphs map back to the text they were shaped from.
+        assert!(pdf.contains("/ActualText <FEFF004100F1>"));
+        assert!(pdf.contains("beginbfchar"));
+        assert!(pdf.contains("> <0041>"));
+        assert!(!pdf.contains("/Helvetica 16 Tf"));
+    }
+
+    #[test]
+    fn cross_reference_table_locates_objects() {
+        let pdf = print_to_pdf(&DisplayList::new());
+        let xref_start = pdf
+            .windows(5)
+            .rposition(|window| window == b"xref\n")
+            .unwrap();
+        // The table's header is `xref\n0 6\n`, and its first entry is the free entry for object 0,
+        // so the entry for object 1 is the second.
+        let first_object_entry = &pdf[xref_start + 29..xref_start + 39];
+        let offset = std::str::from_utf8(first_object_entry)
+            .unwrap()
+            .parse::<usize>()
+            .unwrap();
+        assert!(pdf[offset..].starts_with(b"1 0 obj\n"));
+    }
+}
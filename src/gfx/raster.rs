@@ -0,0 +1,246 @@
+#[cfg(feature = "window")]
+use crate::gfx::display::TextCommand;
+use crate::gfx::display::{DisplayCommand, DisplayList, ImageCommand};
+#[cfg(feature = "window")]
+use crate::gfx::glyph_raster::{place_glyphs, GlyphRasterizer};
+use crate::image::{demultiply, premultiply, DecodedImage};
+#[cfg(feature = "window")]
+use crate::layout::rect::Point;
+use crate::layout::rect::Rect;
+use cssparser::RGBA;
+use tiny_skia::{FilterQuality, Paint, Pattern, Pixmap, SpreadMode, Transform};
+use tracing::info_span;
+
+/// Rasterizes `display_list` on the CPU into an image `width` by `height` device pixels in size,
+/// at `scale_factor` device pixels per CSS pixel.
+///
+/// Unlike the rendering backends, this needs neither a GPU nor a window, so Kosmonaut can render
+/// anywhere (e.g. in CI, on servers, or in WASM).  Like them, it paints pixel-aligned rectangles
+/// without anti-aliasing, so the output of each is the same.  Text is only painted when Kosmonaut
+/// is built with fonts (the `window` feature), since that's what glyphs are rasterized with.
+pub fn rasterize(
+    display_list: &DisplayList,
+    width: u32,
+    height: u32,
+    scale_factor: f32,
+) -> DecodedImage {
+    let _span = info_span!("rasterize").entered();
+    let mut pixmap =
+        Pixmap::new(width, height).expect("couldn't allocate pixmap to rasterize into");
+    for command in display_list.commands() {
+        match command {
+            DisplayCommand::Border(rgba, rect) | DisplayCommand::RectSolidColor(rgba, rect) => {
+                let mut paint = Paint::default();
+                paint.set_color_rgba8(rgba.red, rgba.green, rgba.blue, rgba.alpha);
+                fill_rect(&mut pixmap, rect.scaled_by(scale_factor), paint);
+            }
+            DisplayCommand::Image(image_command) => {
+                rasterize_image(&mut pixmap, image_command, scale_factor)
+            }
+            DisplayCommand::ViewportBackground(rgba) => pixmap.fill(color(*rgba)),
+            #[cfg(feature = "window")]
+            DisplayCommand::Text(text_command) => {
+                rasterize_text(&mut pixmap, text_command, scale_factor)
+            }
+            #[cfg(not(feature = "window"))]
+            DisplayCommand::Text(_) => {}
+            DisplayCommand::HitTestArea(..) | DisplayCommand::LayerBoundary => {}
+        }
+    }
+    DecodedImage::new(width, height, demultiply(pixmap.take()))
+}
+
+/// Rasterizes the frame of the image `image_command` paints, scaled to fill its rect and clipped
+/// to its clip.
+fn rasterize_image(pixmap: &mut Pixmap, image_command: &ImageCommand, scale_factor: f32) {
+    let rect = image_command.rect().scaled_by(scale_factor);
+    let visible = match rect.intersection(image_command.clip().scaled_by(scale_factor)) {
+        Some(visible) => visible,
+        None => return,
+    };
+    let image = image_command.image().image();
+    let mut image_pixmap = match Pixmap::new(image.width(), image.height()) {
+        Some(image_pixmap) => image_pixmap,
+        None => return,
+    };
+    image_pixmap.data_mut().copy_from_slice(&premultiply(
+        image.frame(image_command.frame_index()).pixels(),
+    ));
+    let transform = Transform::from_row(
+        rect.width.px() / image.width() as f32,
+        0.,
+        0.,
+        rect.height.px() / image.height() as f32,
+        rect.start_x,
+        rect.start_y,
+    );
+    if let Some(transform) = transform {
+        let paint = Paint {
+            shader: Pattern::new(
+                image_pixmap.as_ref(),
+                SpreadMode::Pad,
+                FilterQuality::Bilinear,
+                1.,
+                transform,
+            ),
+            ..Paint::default()
+        };
+        fill_rect(pixmap, visible, paint);
+    }
+}
+
+#[cfg(feature = "window")]
+thread_local! {
+    /// Rasterizes the glyphs of the text painted on this thread, keeping them for later rasters.
+    static GLYPH_RASTERIZER: GlyphRasterizer = GlyphRasterizer::new();
+}
+
+/// Rasterizes the glyphs of `text_command`, tinted with its color and clipped to its clip.
+#[cfg(feature = "window")]
+fn rasterize_text(pixmap: &mut Pixmap, text_command: &TextCommand, scale_factor: f32) {
+    let clip = text_command.clip().map(|clip| clip.scaled_by(scale_factor));
+    let (width, height) = (pixmap.width() as i32, pixmap.height() as i32);
+    let color = text_command.color();
+    let data = pixmap.data_mut();
+    GLYPH_RASTERIZER.with(|rasterizer| {
+        for glyph in place_glyphs(text_command, scale_factor) {
+            let bitmap = match rasterizer.rasterize(&glyph.key) {
+                Ok(Some(bitmap)) => bitmap,
+                Ok(None) => continue,
+                Err(err) => {
+                    eprintln!("couldn't rasterize glyph: {:?}", err);
+                    continue;
+                }
+            };
+            let top_left = glyph.origin + bitmap.origin;
+            for (x, y, pixel) in bitmap.image.enumerate_pixels() {
+                let (x, y) = (top_left.x() + x as i32, top_left.y() + y as i32);
+                if x < 0 || y < 0 || x >= width || y >= height {
+                    continue;
+                }
+                let center = Point {
+                    x: x as f32 + 0.5,
+                    y: y as f32 + 0.5,
+                };
+                if clip.map_or(false, |clip| !clip.contains(center)) {
+                    continue;
+                }
+                // Color glyphs are painted in their own colors, others in the text color.  Either
+                // way, the pixmap holds premultiplied colors to blend over.
+                let alpha = pixel[3] as f32 / 255. * color.alpha_f32();
+                let rgb = if bitmap.is_color {
+                    [pixel[0], pixel[1], pixel[2]]
+                } else {
+                    [color.red, color.green, color.blue]
+                };
+                let start = ((y * width + x) * 4) as usize;
+                let dst = &mut data[start..start + 4];
+                for (dst, src) in dst.iter_mut().zip(rgb.iter().chain(&[255])) {
+                    *dst = (*src as f32 * alpha + *dst as f32 * (1. - alpha)).round() as u8;
+                }
+            }
+        }
+    });
+}
+
+/// Fills `rect`, given in device pixels, with `paint`.
+fn fill_rect(pixmap: &mut Pixmap, rect: Rect, mut paint: Paint) {
+    paint.anti_alias = false;
+    // Empty rects have nothing to paint.
+    if let Some(rect) = tiny_skia::Rect::from_xywh(
+        rect.start_x,
+        rect.start_y,
+        rect.width.px(),
+        rect.height.px(),
+    ) {
+        pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+    }
+}
+
+fn color(rgba: RGBA) -> tiny_skia::Color {
+    tiny_skia::Color::from_rgba8(rgba.red, rgba.green, rgba.blue, rgba.alpha)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::{ImageUrl, LoadedImage};
+    use crate::style::values::computed::length::CSSPixelLength;
+    use std::sync::Arc;
+
+    fn rect(start_x: f32, start_y: f32, width: f32, height: f32) -> Rect {
+        Rect {
+            start_x,
+            start_y,
+            width: CSSPixelLength::new(width),
+            height: CSSPixelLength::new(height),
+        }
+    }
+
+    fn pixel(image: &DecodedImage, x: u32, y: u32) -> &[u8] {
+        let start = ((y * image.width() + x) * 4) as usize;
+        &image.frame(0).pixels()[start..start + 4]
+    }
+
+    #[test]
+    fn paints_rects_over_the_viewport_background() {
+        let mut display_list = DisplayList::new();
+        display_list.push(DisplayCommand::ViewportBackground(RGBA::new(
+            255, 255, 255, 255,
+        )));
+        display_list.push(DisplayCommand::RectSolidColor(
+            RGBA::new(255, 0, 0, 255),
+            rect(1., 1., 2., 1.),
+        ));
+        let image = rasterize(&display_list, 8, 6, 2.);
+        assert_eq!(pixel(&image, 0, 0), &[255, 255, 255, 255]);
+        // The rect is scaled from CSS pixels to device pixels.
+        assert_eq!(pixel(&image, 2, 2), &[255, 0, 0, 255]);
+        assert_eq!(pixel(&image, 5, 3), &[255, 0, 0, 255]);
+        assert_eq!(pixel(&image, 6, 3), &[255, 255, 255, 255]);
+        assert_eq!(pixel(&image, 5, 4), &[255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn clips_images() {
+        let blue = DecodedImage::new(1, 1, vec![0, 0, 255, 255]);
+        let image = LoadedImage::new(ImageUrl::new("blue".to_owned()), Arc::new(blue));
+        let mut display_list = DisplayList::new();
+        display_list.push(DisplayCommand::ViewportBackground(RGBA::new(0, 0, 0, 255)));
+        display_list.push(DisplayCommand::Image(ImageCommand::new(
+            image,
+            0,
+            rect(0., 0., 4., 4.),
+            rect(0., 0., 2., 4.),
+        )));
+        let raster = rasterize(&display_list, 4, 4, 1.);
+        assert_eq!(pixel(&raster, 1, 3), &[0, 0, 255, 255]);
+        assert_eq!(pixel(&raster, 2, 0), &[0, 0, 0, 255]);
+    }
+
+    #[cfg(feature = "window")]
+    #[test]
+    fn paints_glyphs_in_the_text_color() {
+        use crate::layout::text::shape_text;
+        use crate::style::values::computed::ComputedValues;
+        use pathfinder_geometry::vector::Vector2F;
+
+        // Every glyph of the test font is an em box, 0.8em above the baseline.
+        let shaped = shape_text("X", &ComputedValues::default(), 0.);
+        let mut display_list = DisplayList::new();
+        display_list.push(DisplayCommand::ViewportBackground(RGBA::new(
+            255, 255, 255, 255,
+        )));
+        display_list.push(DisplayCommand::Text(TextCommand::new(
+            "X".to_owned(),
+            shaped.runs,
+            RGBA::new(255, 0, 0, 255),
+            Vector2F::new(2., shaped.ascent),
+            rect(2., 0., shaped.advance, shaped.ascent + shaped.descent),
+        )));
+        let raster = rasterize(&display_list, 24, 20, 1.);
+        assert_eq!(pixel(&raster, 10, 8), &[255, 0, 0, 255]);
+        assert_eq!(pixel(&raster, 0, 8), &[255, 255, 255, 255]);
+        assert_eq!(pixel(&raster, 10, 18), &[255, 255, 255, 255]);
+    }
+}
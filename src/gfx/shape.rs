@@ -0,0 +1,762 @@
+//! Shaping of text into positioned glyphs.  Shaping, rather than looking up a glyph for each
+//! char, is what makes ligatures, kerning, combining marks, and complex scripts (e.g. Arabic and
+//! Devanagari) render and measure correctly.
+//!
+//! https://harfbuzz.github.io/what-is-harfbuzz.html
+
+use crate::gfx::color_glyph::read_u16;
+use crate::gfx::fallback::{script_of, Script};
+use crate::gfx::glyph::{PostscriptName, Synthesis};
+use crate::style::values::computed::{Direction, FontFeatureSettings, FontVariantCaps, TabSize};
+use accountable_refcell::RefCell;
+use font_kit::font::Font;
+use font_kit::handle::Handle;
+use pathfinder_geometry::vector::Vector2F;
+use rustybuzz::{Face, Feature, UnicodeBuffer};
+use std::collections::HashMap;
+use std::mem;
+use std::ops::Range;
+use std::rc::Rc;
+use std::sync::Arc;
+use ttf_parser::Tag;
+
+/// How many shaped runs are kept in each generation of the run cache.  Runs that go unused for a
+/// whole generation are dropped, so that the cache doesn't grow without bound as text changes.
+const RUN_CACHE_GENERATION_SIZE: usize = 2048;
+
+/// How large synthesized small capitals are relative to the font size, for fonts whose metrics
+/// don't say how tall their lowercase letters are.
+const SMALL_CAPS_SCALE: f32 = 0.7;
+
+/// A glyph placed by shaping, relative to where the pen was when the glyph was reached.  All
+/// distances are in CSS pixels, with y increasing downwards.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShapedGlyph {
+    /// The id of the glyph in the font it was shaped with.
+    pub glyph_id: u32,
+    /// The byte index into the shaped text of the first char this glyph was shaped from.  Glyphs
+    /// shaped from the same chars (e.g. a base letter and its combining marks) share a cluster.
+    pub cluster: usize,
+    /// How far to move the pen after painting this glyph.
+    pub advance: Vector2F,
+    /// How far from the pen to paint this glyph, without moving the pen.
+    pub offset: Vector2F,
+    /// The font size the glyph was shaped at, which is smaller than the run's for synthesized
+    /// small capitals.
+    pub size_px: f32,
+}
+
+impl ShapedGlyph {
+    /// Converts a glyph's position from the font units it was shaped in, where y increases
+    /// upwards, into CSS pixels at `size_px` in a font with `units_per_em`.
+    fn from_font_units(
+        glyph_id: u32,
+        cluster: u32,
+        position: &rustybuzz::GlyphPosition,
+        size_px: f32,
+        units_per_em: f32,
+    ) -> ShapedGlyph {
+        let px_per_unit = size_px / units_per_em;
+        ShapedGlyph {
+            glyph_id,
+            cluster: cluster as usize,
+            advance: Vector2F::new(
+                position.x_advance as f32 * px_per_unit,
+                -position.y_advance as f32 * px_per_unit,
+            ),
+            offset: Vector2F::new(
+                position.x_offset as f32 * px_per_unit,
+                -position.y_offset as f32 * px_per_unit,
+            ),
+            size_px,
+        }
+    }
+}
+
+/// A run of text shaped with a single font at a single size.  Glyphs are in visual order, so
+/// right-to-left text is already reversed and can be painted from left to right.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShapedRun {
+    glyphs: Vec<ShapedGlyph>,
+    /// The font size the run was shaped at, in CSS pixels.
+    size_px: f32,
+    /// How the run's font is altered to match the style it was shaped for.
+    synthesis: Synthesis,
+}
+
+impl ShapedRun {
+    pub fn glyphs(&self) -> &[ShapedGlyph] {
+        &self.glyphs
+    }
+
+    pub fn size_px(&self) -> f32 {
+        self.size_px
+    }
+
+    pub fn synthesis(&self) -> Synthesis {
+        self.synthesis
+    }
+
+    /// The horizontal distance the run takes up, in CSS pixels.
+    pub fn advance_width(&self) -> f32 {
+        self.glyphs.iter().map(|glyph| glyph.advance.x()).sum()
+    }
+}
+
+/// Everything besides its text and font that a run is shaped according to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShapeOptions {
+    /// The font size, in CSS pixels.
+    pub size_px: f32,
+    /// How the font is altered to match the style the run is shaped for.
+    pub synthesis: Synthesis,
+    /// The direction of the run, or `None` to guess it from the text's contents.
+    pub direction: Option<Direction>,
+    /// Extra space added after each typographic character unit (cluster), in CSS pixels.
+    ///
+    /// https://drafts.csswg.org/css-text-3/#letter-spacing-property
+    pub letter_spacing: f32,
+    /// Which glyphs to render letters with, e.g. small capitals.
+    pub variant_caps: FontVariantCaps,
+    /// OpenType features to turn on or off, after those selected by `variant_caps`.
+    pub feature_settings: FontFeatureSettings,
+    /// How far apart tab stops are.  Tabs advance the pen to the next tab stop rather than being
+    /// rendered with a glyph of their own.
+    ///
+    /// https://drafts.csswg.org/css-text-3/#tab-size-property
+    pub tab_size: TabSize,
+    /// How far the run starts from the start of its line, in CSS pixels.  Tab stops are measured
+    /// from the start of the line rather than of the run.
+    pub line_offset: f32,
+}
+
+/// Where the tab stops of a run are.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct TabStops {
+    /// The distance between tab stops, in CSS pixels.
+    interval: f32,
+    /// The distance from the start of the line to the start of the run, in CSS pixels.
+    line_offset: f32,
+    /// The least a tab may advance the pen; tabs closer than this to a tab stop advance to the
+    /// one after it.
+    min_advance: f32,
+}
+
+impl TabStops {
+    /// Finds the tab stops of a run shaped with `font` according to `options`.  A number of
+    /// spaces is measured with the font's space, including its letter spacing.
+    fn new(font: &Font, options: &ShapeOptions) -> TabStops {
+        let interval = match options.tab_size {
+            TabSize::Number(spaces) => {
+                let space = char_advance(font, ' ', options.size_px).unwrap_or(0.);
+                spaces * (space + options.letter_spacing)
+            }
+            TabSize::Length(length) => length.px(),
+        };
+        // Tabs advance at least half of a `ch` unit, the advance of the font's zero.
+        //
+        // https://drafts.csswg.org/css-text-3/#tab-stop
+        let ch = char_advance(font, '0', options.size_px).unwrap_or(options.size_px / 2.);
+        TabStops {
+            interval,
+            line_offset: options.line_offset,
+            min_advance: ch / 2.,
+        }
+    }
+
+    /// How far a tab advances the pen from `pen`, measured from the start of the line.
+    fn advance_from(self, pen: f32) -> f32 {
+        if self.interval <= 0. {
+            return 0.;
+        }
+        let mut stop = ((pen / self.interval).floor() + 1.) * self.interval;
+        if stop - pen < self.min_advance {
+            stop += self.interval;
+        }
+        stop - pen
+    }
+}
+
+/// Identifies a shaped run in the run cache.  Lengths are compared by the bits of their `f32`s.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct RunKey {
+    text: String,
+    postscript_name: PostscriptName,
+    size_px: u32,
+    synthesis: Synthesis,
+    direction: Option<Direction>,
+    letter_spacing: u32,
+    variant_caps: FontVariantCaps,
+    feature_settings: FontFeatureSettings,
+    /// The interval between tab stops and the line offset, for text with tabs.  Where text
+    /// without tabs starts in its line doesn't change how it's shaped.
+    tab_stops: Option<(u32, u32)>,
+}
+
+/// Shapes text, caching the data of each font it shapes with and the runs it has shaped, so that
+/// relayouts (e.g. on resize or during animations) don't shape unchanged text again.
+#[derive(Default)]
+pub struct Shaper {
+    font_data: RefCell<HashMap<PostscriptName, FontData>>,
+    /// The runs shaped or used since the current generation of the cache began.
+    runs: RefCell<HashMap<RunKey, Rc<ShapedRun>>>,
+    /// The runs shaped or used during the previous generation, which are moved into `runs` when
+    /// they're used again.
+    previous_runs: RefCell<HashMap<RunKey, Rc<ShapedRun>>>,
+}
+
+struct FontData {
+    bytes: Arc<Vec<u8>>,
+    /// The index of the font within `bytes`, which may be a collection of several fonts.
+    index: u32,
+}
+
+impl Shaper {
+    pub fn new() -> Shaper {
+        Shaper {
+            font_data: RefCell::new(HashMap::new()),
+            runs: RefCell::new(HashMap::new()),
+            previous_runs: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the data of `font`, which may be a collection of several fonts, along with the
+    /// index of `font` within it.
+    pub fn font_data(&self, font: &Font) -> Result<(Arc<Vec<u8>>, u32), ShapeError> {
+        let postscript_name = font.postscript_name().ok_or(ShapeError::NoPostscriptName)?;
+        let mut font_data = self.font_data.borrow_mut();
+        if !font_data.contains_key(&postscript_name) {
+            let bytes = font.copy_font_data().ok_or(ShapeError::NoFontData)?;
+            let index = match font.handle() {
+                Some(Handle::Path { font_index, .. }) | Some(Handle::Memory { font_index, .. }) => {
+                    font_index
+                }
+                None => 0,
+            };
+            font_data.insert(postscript_name.clone(), FontData { bytes, index });
+        }
+        let data = &font_data[&postscript_name];
+        Ok((data.bytes.clone(), data.index))
+    }
+
+    /// Shapes `text` with `font` according to `options`, reusing the run shaped last time if the
+    /// same text was recently shaped the same way.
+    pub fn shape(
+        &self,
+        font: &Font,
+        options: &ShapeOptions,
+        text: &str,
+    ) -> Result<Rc<ShapedRun>, ShapeError> {
+        let tab_stops = if text.contains('\t') {
+            Some(TabStops::new(font, options))
+        } else {
+            None
+        };
+        let key = RunKey {
+            text: text.to_owned(),
+            postscript_name: font.postscript_name().ok_or(ShapeError::NoPostscriptName)?,
+            size_px: options.size_px.to_bits(),
+            synthesis: options.synthesis,
+            direction: options.direction,
+            letter_spacing: options.letter_spacing.to_bits(),
+            variant_caps: options.variant_caps,
+            feature_settings: options.feature_settings.clone(),
+            tab_stops: tab_stops
+                .map(|stops| (stops.interval.to_bits(), stops.line_offset.to_bits())),
+        };
+        if let Some(run) = self.runs.borrow().get(&key) {
+            return Ok(run.clone());
+        }
+        let run = match self.previous_runs.borrow_mut().remove(&key) {
+            Some(run) => run,
+            None => Rc::new(self.shape_uncached(font, options, tab_stops, text)?),
+        };
+        let mut runs = self.runs.borrow_mut();
+        if runs.len() >= RUN_CACHE_GENERATION_SIZE {
+            *self.previous_runs.borrow_mut() = mem::take(&mut *runs);
+        }
+        runs.insert(key, run.clone());
+        Ok(run)
+    }
+
+    fn shape_uncached(
+        &self,
+        font: &Font,
+        options: &ShapeOptions,
+        tab_stops: Option<TabStops>,
+        text: &str,
+    ) -> Result<ShapedRun, ShapeError> {
+        let (bytes, index) = self.font_data(font)?;
+        let face = Face::from_slice(&bytes, index).ok_or(ShapeError::InvalidFace)?;
+
+        let gsub_features = ttf_parser::Face::from_slice(&bytes, index)
+            .ok()
+            .and_then(|face| {
+                face.table_data(Tag::from_bytes(b"GSUB"))
+                    .map(gsub_feature_tags)
+            })
+            .unwrap_or_default();
+        let has_feature = |tag: &[u8; 4]| gsub_features.contains(tag);
+        let caps_features = caps_features(options.variant_caps, has_feature);
+        let features: Vec<Feature> = caps_features
+            .iter()
+            .map(|tag| (tag, 1))
+            .chain(
+                options
+                    .feature_settings
+                    .0
+                    .iter()
+                    .map(|setting| (&setting.tag, setting.value)),
+            )
+            .map(|(tag, value)| Feature::new(Tag::from_bytes(tag), value, ..))
+            .collect();
+
+        // Small capitals the font doesn't have are synthesized from its capitals, scaled down to
+        // the height of its lowercase letters.
+        //
+        // https://drafts.csswg.org/css-fonts-4/#font-variant-caps-prop
+        let synthesize_lowercase = caps_features.contains(b"smcp") && !has_feature(b"smcp");
+        let synthesize_uppercase = caps_features.contains(b"c2sc") && !has_feature(b"c2sc");
+        let metrics = font.metrics();
+        let small_caps_scale = if metrics.x_height > 0. && metrics.cap_height > 0. {
+            metrics.x_height / metrics.cap_height
+        } else {
+            SMALL_CAPS_SCALE
+        };
+        let units_per_em = metrics.units_per_em as f32;
+
+        // Small capitals are only synthesized for cased scripts, which are all written left to
+        // right, so segments are shaped and placed in the order they come in the text.
+        let mut glyphs = Vec::new();
+        for (range, synthesized) in
+            small_caps_segments(text, synthesize_lowercase, synthesize_uppercase)
+        {
+            let segment_text = &text[range.clone()];
+            if synthesized {
+                let (uppercase, clusters) = uppercase_with_clusters(segment_text);
+                glyphs.extend(
+                    shape_glyphs(
+                        &face,
+                        &uppercase,
+                        &features,
+                        options.direction,
+                        options.size_px * small_caps_scale,
+                        units_per_em,
+                    )
+                    .into_iter()
+                    .map(|mut glyph| {
+                        glyph.cluster = range.start + clusters[glyph.cluster];
+                        glyph
+                    }),
+                );
+            } else {
+                glyphs.extend(
+                    shape_glyphs(
+                        &face,
+                        segment_text,
+                        &features,
+                        options.direction,
+                        options.size_px,
+                        units_per_em,
+                    )
+                    .into_iter()
+                    .map(|mut glyph| {
+                        glyph.cluster += range.start;
+                        glyph
+                    }),
+                );
+            }
+        }
+
+        // Emboldened glyphs are wider, so they're spaced further apart.  Glyphs that don't advance
+        // the pen, such as combining marks, stay where they are.
+        for glyph in &mut glyphs {
+            if glyph.advance.x() != 0. {
+                glyph.advance += Vector2F::new(options.synthesis.bold_offset(glyph.size_px), 0.);
+            }
+        }
+        add_letter_spacing(&mut glyphs, options.letter_spacing);
+        if let Some(tab_stops) = tab_stops {
+            advance_to_tab_stops(&mut glyphs, text, tab_stops, font.glyph_for_char(' '));
+        }
+        Ok(ShapedRun {
+            glyphs,
+            size_px: options.size_px,
+            synthesis: options.synthesis,
+        })
+    }
+}
+
+/// Shapes `text` with `face` at `size_px`, returning glyphs whose clusters are byte indices into
+/// `text`.  `direction` is guessed from the text's contents if it's `None`.
+fn shape_glyphs(
+    face: &Face,
+    text: &str,
+    features: &[Feature],
+    direction: Option<Direction>,
+    size_px: f32,
+    units_per_em: f32,
+) -> Vec<ShapedGlyph> {
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(text);
+    buffer.guess_segment_properties();
+    match direction {
+        Some(Direction::Ltr) => buffer.set_direction(rustybuzz::Direction::LeftToRight),
+        Some(Direction::Rtl) => buffer.set_direction(rustybuzz::Direction::RightToLeft),
+        None => {}
+    }
+    let glyph_buffer = rustybuzz::shape(face, features, buffer);
+    glyph_buffer
+        .glyph_infos()
+        .iter()
+        .zip(glyph_buffer.glyph_positions())
+        .map(|(info, position)| {
+            ShapedGlyph::from_font_units(
+                info.glyph_id,
+                info.cluster,
+                position,
+                size_px,
+                units_per_em,
+            )
+        })
+        .collect()
+}
+
+/// Returns the OpenType features that render text in `caps`.  Fonts without petite capitals, as
+/// told by `has_feature`, get small capitals instead.
+///
+/// https://docs.microsoft.com/en-us/typography/opentype/spec/featurelist
+fn caps_features(
+    caps: FontVariantCaps,
+    has_feature: impl Fn(&[u8; 4]) -> bool,
+) -> &'static [[u8; 4]] {
+    match caps {
+        FontVariantCaps::Normal => &[],
+        FontVariantCaps::SmallCaps => &[*b"smcp"],
+        FontVariantCaps::AllSmallCaps => &[*b"c2sc", *b"smcp"],
+        FontVariantCaps::PetiteCaps if has_feature(b"pcap") => &[*b"pcap"],
+        FontVariantCaps::PetiteCaps => &[*b"smcp"],
+        FontVariantCaps::AllPetiteCaps if has_feature(b"c2pc") && has_feature(b"pcap") => {
+            &[*b"c2pc", *b"pcap"]
+        }
+        FontVariantCaps::AllPetiteCaps => &[*b"c2sc", *b"smcp"],
+        FontVariantCaps::Unicase => &[*b"unic"],
+        FontVariantCaps::TitlingCaps => &[*b"titl"],
+    }
+}
+
+/// Returns the tags of the features in a `GSUB` (glyph substitution) table, such as `smcp` if the
+/// font has small capitals.
+///
+/// https://docs.microsoft.com/en-us/typography/opentype/spec/chapter2#feature-list-table
+fn gsub_feature_tags(gsub: &[u8]) -> Vec<[u8; 4]> {
+    let feature_list = match read_u16(gsub, 6) {
+        Some(offset) => offset as usize,
+        None => return Vec::new(),
+    };
+    let feature_count = read_u16(gsub, feature_list).unwrap_or(0) as usize;
+    // Feature records are six bytes each: tag, offset of the feature table.
+    (0..feature_count)
+        .filter_map(|index| {
+            let record = feature_list + 2 + index * 6;
+            let tag = gsub.get(record..record + 4)?;
+            Some([tag[0], tag[1], tag[2], tag[3]])
+        })
+        .collect()
+}
+
+/// Splits `text` into segments of letters to synthesize small capitals for and of other text,
+/// returning the byte range of each segment along with whether it's synthesized.  Lowercase
+/// letters are synthesized if `lowercase` is true, and uppercase letters if `uppercase` is true.
+/// Combining marks stay in the segment of the letter they're on.
+fn small_caps_segments(text: &str, lowercase: bool, uppercase: bool) -> Vec<(Range<usize>, bool)> {
+    let mut segments: Vec<(Range<usize>, bool)> = Vec::new();
+    for (index, ch) in text.char_indices() {
+        let end = index + ch.len_utf8();
+        let synthesized = match segments.last() {
+            Some((_, synthesized)) if script_of(ch) == Script::Inherited => *synthesized,
+            _ => (lowercase && ch.is_lowercase()) || (uppercase && ch.is_uppercase()),
+        };
+        match segments.last_mut() {
+            Some((range, is_synthesized)) if *is_synthesized == synthesized => range.end = end,
+            _ => segments.push((index..end, synthesized)),
+        }
+    }
+    segments
+}
+
+/// Uppercases `text`, returning the uppercased text along with, for each of its bytes, the byte
+/// index into `text` of the char it came from.  Some chars become several when uppercased, such
+/// as "ß", which becomes "SS".
+fn uppercase_with_clusters(text: &str) -> (String, Vec<usize>) {
+    let mut uppercase = String::with_capacity(text.len());
+    let mut clusters = Vec::with_capacity(text.len());
+    for (index, ch) in text.char_indices() {
+        for upper in ch.to_uppercase() {
+            uppercase.push(upper);
+            clusters.resize(uppercase.len(), index);
+        }
+    }
+    (uppercase, clusters)
+}
+
+/// Adds `letter_spacing` to the advance of the last glyph of each cluster, so that the space
+/// comes after each typographic character unit rather than between the glyphs of one.
+fn add_letter_spacing(glyphs: &mut [ShapedGlyph], letter_spacing: f32) {
+    if letter_spacing == 0. {
+        return;
+    }
+    let clusters: Vec<usize> = glyphs.iter().map(|glyph| glyph.cluster).collect();
+    for (index, glyph) in glyphs.iter_mut().enumerate() {
+        if clusters.get(index + 1) != Some(&glyph.cluster) {
+            glyph.advance += Vector2F::new(letter_spacing, 0.);
+        }
+    }
+}
+
+/// Advances the pen from each tab in `text` to the next of `tab_stops`.  Tabs are painted as
+/// `space_glyph`, which is blank, rather than as whatever glyph the font has for them.
+fn advance_to_tab_stops(
+    glyphs: &mut [ShapedGlyph],
+    text: &str,
+    tab_stops: TabStops,
+    space_glyph: Option<u32>,
+) {
+    let mut pen = tab_stops.line_offset;
+    for glyph in glyphs {
+        if text[glyph.cluster..].starts_with('\t') {
+            glyph.advance = Vector2F::new(tab_stops.advance_from(pen), 0.);
+            glyph.offset = Vector2F::zero();
+            if let Some(space_glyph) = space_glyph {
+                glyph.glyph_id = space_glyph;
+            }
+        }
+        pen += glyph.advance.x();
+    }
+}
+
+/// The advance of `ch` in `font` at `size_px`, in CSS pixels, or `None` if the font has no glyph
+/// for it.
+fn char_advance(font: &Font, ch: char, size_px: f32) -> Option<f32> {
+    let advance = font.advance(font.glyph_for_char(ch)?).ok()?;
+    Some(advance.x() * size_px / font.metrics().units_per_em as f32)
+}
+
+#[derive(Debug)]
+pub enum ShapeError {
+    /// The font's tables couldn't be parsed for shaping.
+    InvalidFace,
+    /// The font's data couldn't be read, e.g. because it's a native font without font files.
+    NoFontData,
+    NoPostscriptName,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions_are_converted_to_css_pixels_with_y_down() {
+        // A mark shaped 1024 units up and 256 units left of the pen, at 16px in a 2048 unit em.
+        let mut position = rustybuzz::GlyphPosition::default();
+        position.x_offset = -256;
+        position.y_offset = 1024;
+        let mark = ShapedGlyph::from_font_units(7, 3, &position, 16., 2048.);
+        assert_eq!(mark.glyph_id, 7);
+        assert_eq!(mark.cluster, 3);
+        assert_eq!(mark.advance, Vector2F::zero());
+        assert_eq!(mark.offset, Vector2F::new(-2., -8.));
+        assert_eq!(mark.size_px, 16.);
+    }
+
+    #[test]
+    fn advance_width_sums_horizontal_advances() {
+        let glyph = |glyph_id, advance| ShapedGlyph {
+            glyph_id,
+            cluster: 0,
+            advance: Vector2F::new(advance, 0.),
+            offset: Vector2F::zero(),
+            size_px: 16.,
+        };
+        let run = ShapedRun {
+            glyphs: vec![glyph(1, 8.5), glyph(2, 0.), glyph(3, 4.25)],
+            size_px: 16.,
+            synthesis: Synthesis::default(),
+        };
+        assert_eq!(run.advance_width(), 12.75);
+    }
+
+    #[test]
+    fn letter_spacing_follows_each_cluster() {
+        // "e" with a combining accent, shaped into two glyphs of one cluster, then "x".
+        let glyph = |glyph_id, cluster, advance| ShapedGlyph {
+            glyph_id,
+            cluster,
+            advance: Vector2F::new(advance, 0.),
+            offset: Vector2F::zero(),
+            size_px: 16.,
+        };
+        let mut glyphs = vec![glyph(1, 0, 8.), glyph(2, 0, 0.), glyph(3, 3, 7.)];
+        add_letter_spacing(&mut glyphs, 2.);
+        assert_eq!(
+            glyphs,
+            vec![glyph(1, 0, 8.), glyph(2, 0, 2.), glyph(3, 3, 9.)]
+        );
+    }
+
+    #[test]
+    fn petite_caps_fall_back_to_small_caps() {
+        let has_petite_caps = |tag: &[u8; 4]| tag == b"pcap" || tag == b"c2pc";
+        let has_nothing = |_: &[u8; 4]| false;
+        assert_eq!(
+            caps_features(FontVariantCaps::PetiteCaps, has_petite_caps),
+            &[*b"pcap"]
+        );
+        assert_eq!(
+            caps_features(FontVariantCaps::PetiteCaps, has_nothing),
+            &[*b"smcp"]
+        );
+        assert_eq!(
+            caps_features(FontVariantCaps::AllPetiteCaps, has_nothing),
+            &[*b"c2sc", *b"smcp"]
+        );
+        assert!(caps_features(FontVariantCaps::Normal, has_nothing).is_empty());
+    }
+
+    #[test]
+    fn reads_gsub_feature_tags() {
+        let gsub = [
+            0, 1, 0, 0, // version
+            0, 0, // script list offset
+            0, 10, // feature list offset
+            0, 0, // lookup list offset
+            0, 2, // feature count
+            b'l', b'i', b'g', b'a', 0, 0, // feature record
+            b's', b'm', b'c', b'p', 0, 0, // feature record
+        ];
+        assert_eq!(gsub_feature_tags(&gsub), vec![*b"liga", *b"smcp"]);
+        assert_eq!(gsub_feature_tags(&gsub[..4]), Vec::<[u8; 4]>::new());
+    }
+
+    #[test]
+    fn splits_letters_to_synthesize_small_caps_for() {
+        let segments = |text: &'static str, lowercase, uppercase| -> Vec<(&str, bool)> {
+            small_caps_segments(text, lowercase, uppercase)
+                .into_iter()
+                .map(|(range, synthesized)| (&text[range], synthesized))
+                .collect()
+        };
+        // The combining acute accent stays with the "e" it's on.
+        assert_eq!(
+            segments("Cafe\u{301} 1", true, false),
+            vec![("C", false), ("afe\u{301}", true), (" 1", false)]
+        );
+        assert_eq!(
+            segments("Cafe 1", true, true),
+            vec![("Cafe", true), (" 1", false)]
+        );
+        assert_eq!(segments("Cafe", false, false), vec![("Cafe", false)]);
+    }
+
+    #[test]
+    fn uppercased_bytes_map_to_the_chars_they_came_from() {
+        assert_eq!(
+            uppercase_with_clusters("aßé"),
+            ("ASSÉ".to_owned(), vec![0, 1, 1, 3, 3])
+        );
+    }
+
+    #[test]
+    fn tabs_advance_to_the_next_tab_stop() {
+        let tab_stops = TabStops {
+            interval: 32.,
+            line_offset: 10.,
+            min_advance: 4.,
+        };
+        let glyph = |glyph_id, cluster, advance| ShapedGlyph {
+            glyph_id,
+            cluster,
+            advance: Vector2F::new(advance, 0.),
+            offset: Vector2F::zero(),
+            size_px: 16.,
+        };
+        // "a\tbc\td": the first tab advances from 18px to 32px, and the second from 61px, which
+        // is too close to 64px, to 96px.
+        let mut glyphs = vec![
+            glyph(1, 0, 8.),
+            glyph(9, 1, 5.),
+            glyph(2, 2, 7.),
+            glyph(3, 3, 22.),
+            glyph(9, 4, 5.),
+            glyph(4, 5, 8.),
+        ];
+        advance_to_tab_stops(&mut glyphs, "a\tbc\td", tab_stops, Some(0));
+        assert_eq!(
+            glyphs,
+            vec![
+                glyph(1, 0, 8.),
+                glyph(0, 1, 14.),
+                glyph(2, 2, 7.),
+                glyph(3, 3, 22.),
+                glyph(0, 4, 35.),
+                glyph(4, 5, 8.),
+            ]
+        );
+    }
+
+    #[test]
+    fn runs_are_reused_until_a_generation_goes_by_without_them() {
+        use crate::gfx::font::TEST_FONT;
+
+        let font = Font::from_bytes(Arc::new(TEST_FONT.to_vec()), 0).unwrap();
+        let options = ShapeOptions {
+            size_px: 16.,
+            synthesis: Synthesis::default(),
+            direction: None,
+            letter_spacing: 0.,
+            variant_caps: FontVariantCaps::Normal,
+            feature_settings: FontFeatureSettings::initial_value(),
+            tab_size: TabSize::initial_value(),
+            line_offset: 0.,
+        };
+        let shaper = Shaper::new();
+        let shape =
+            |options: &ShapeOptions, text: &str| shaper.shape(&font, options, text).unwrap();
+        // Shapes a generation's worth of runs that haven't been shaped before.
+        let fill_generation = |generation: usize| {
+            for index in 0..RUN_CACHE_GENERATION_SIZE {
+                shape(&options, &format!("{} {}", generation, index));
+            }
+        };
+
+        let hello = shape(&options, "Hello");
+        assert!(Rc::ptr_eq(&hello, &shape(&options, "Hello")));
+        // Where text without tabs starts in its line doesn't change how it's shaped.
+        let offset = ShapeOptions {
+            line_offset: 20.,
+            ..options.clone()
+        };
+        assert!(Rc::ptr_eq(&hello, &shape(&offset, "Hello")));
+        let bigger = ShapeOptions {
+            size_px: 32.,
+            ..options.clone()
+        };
+        assert!(!Rc::ptr_eq(&hello, &shape(&bigger, "Hello")));
+
+        fill_generation(0);
+        assert!(Rc::ptr_eq(&hello, &shape(&options, "Hello")));
+        fill_generation(1);
+        fill_generation(2);
+        assert!(!Rc::ptr_eq(&hello, &shape(&options, "Hello")));
+    }
+
+    #[test]
+    fn zero_tab_size_makes_tabs_zero_width() {
+        let tab_stops = TabStops {
+            interval: 0.,
+            line_offset: 0.,
+            min_advance: 4.,
+        };
+        assert_eq!(tab_stops.advance_from(12.), 0.);
+    }
+}
@@ -0,0 +1,92 @@
+use crate::gfx::{
+    DEFAULT_INNER_WINDOW_HEIGHT_PX, DEFAULT_INNER_WINDOW_WIDTH_PX, DEFAULT_WINDOW_TITLE,
+};
+use gl::util::opengl_version;
+use gl::viewport::resize_viewport;
+use gl::{Gl, BLEND, ONE_MINUS_SRC_ALPHA, SRC_ALPHA};
+use glutin::dpi::PhysicalSize;
+use glutin::event_loop::EventLoop;
+use glutin::window::{Icon, WindowBuilder};
+use glutin::{ContextBuilder, GlProfile, PossiblyCurrent, WindowedContext};
+use image::ImageFormat;
+use std::io::Cursor;
+
+/// Describes the main window, which is `inner_width_opt` by `inner_height_opt` physical pixels,
+/// or a default size if those aren't given.
+pub fn main_window_builder(
+    inner_width_opt: Option<f32>,
+    inner_height_opt: Option<f32>,
+) -> WindowBuilder {
+    // This was an arbitrary choice in size.  We can revisit this later.
+    let initial_physical_size = PhysicalSize {
+        width: inner_width_opt.unwrap_or(DEFAULT_INNER_WINDOW_WIDTH_PX) as u32,
+        height: inner_height_opt.unwrap_or(DEFAULT_INNER_WINDOW_HEIGHT_PX) as u32,
+    };
+    WindowBuilder::new()
+        .with_title(DEFAULT_WINDOW_TITLE)
+        .with_inner_size(initial_physical_size)
+        .with_window_icon(Some(kosmonaut_icon()))
+}
+
+/// The Kosmonaut logo, which is the main window's icon when the document doesn't declare one.
+pub fn kosmonaut_icon() -> Icon {
+    let icon = image::load(
+        Cursor::new(&include_bytes!("../../img/Kosmonaut_Logo_164x164-01.png")[..]),
+        ImageFormat::Png,
+    )
+    .unwrap()
+    .to_rgba8();
+    let icon_dimensions = icon.dimensions();
+    Icon::from_rgba(icon.to_vec(), icon_dimensions.0, icon_dimensions.1).unwrap()
+}
+
+pub fn init_main_window_and_gl<T>(
+    el: &EventLoop<T>,
+    inner_width_opt: Option<f32>,
+    inner_height_opt: Option<f32>,
+) -> (WindowedContext<PossiblyCurrent>, Gl) {
+    let wb = main_window_builder(inner_width_opt, inner_height_opt);
+    let windowed_context = ContextBuilder::new()
+        .with_gl_profile(GlProfile::Core)
+        .build_windowed(wb, el)
+        .unwrap();
+    let windowed_context = unsafe { windowed_context.make_current().unwrap() };
+    let gl_context = windowed_context.context();
+    let gl = Gl::load_with(|ptr| gl_context.get_proc_address(ptr) as *const _);
+    configure_gl_blend(&gl);
+    let initial_physical_size = windowed_context.window().inner_size();
+    resize_window(&gl, &windowed_context, &initial_physical_size);
+    (windowed_context, gl)
+}
+
+/// Enables and configures blending for the entire OpenGL instance.  This blending configuration is
+/// required to support text rendering.  If we require other blending configurations, this function
+/// probably shouldn't set this blending configuration instance-wide here.
+///
+/// https://learnopengl.com/In-Practice/Text-Rendering
+fn configure_gl_blend(gl: &Gl) {
+    unsafe {
+        gl.Enable(BLEND);
+        gl.BlendFunc(SRC_ALPHA, ONE_MINUS_SRC_ALPHA);
+    }
+}
+
+pub fn resize_window(
+    gl: &Gl,
+    windowed_context: &WindowedContext<PossiblyCurrent>,
+    new_size: &PhysicalSize<u32>,
+) {
+    resize_viewport(gl, new_size.width, new_size.height);
+    windowed_context.resize(*new_size);
+}
+
+pub fn print_gl_info(windowed_context: &WindowedContext<PossiblyCurrent>, gl: &Gl) {
+    println!("-------------------------------------------------");
+    println!(
+        "Pixel format of the window's GL context: {:?}",
+        windowed_context.get_pixel_format()
+    );
+    println!("OpenGL API in use: {:?}", windowed_context.get_api());
+    println!("OpenGL version {}", opengl_version(gl));
+    println!("-------------------------------------------------");
+}
@@ -0,0 +1,265 @@
+//! The nested browsing contexts `<iframe>` elements display documents in.  Each nested document
+//! has its own DOM, device, styles, and box tree, so it's styled and laid out independently of the
+//! document its iframe is in, in a viewport the size of the iframe's content box.  Its display list
+//! is painted into that content box, clipped to it.
+//!
+//! https://html.spec.whatwg.org/multipage/iframe-embed-object.html#the-iframe-element
+
+use std::cell::{Ref, RefCell};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::dom::navigation::{resolve_link, LinkTarget};
+use crate::dom::tree::NodeRef;
+use crate::gfx::display::{build_page_display_list, DisplayList};
+use crate::image::load_document_images;
+use crate::layout::behavior::BaseLayoutBoxBehavior;
+use crate::layout::box_tree::build_box_tree;
+use crate::layout::layout_box::LayoutBox;
+use crate::page::{
+    layout_document, load_document_in, screen_device, update_device, LoadedDocument,
+};
+use crate::style::media::Device;
+use crate::style::stylesheet::Stylesheet;
+
+/// The size of iframes whose width and height aren't specified, in CSS pixels.
+///
+/// https://html.spec.whatwg.org/multipage/rendering.html#embedded-content-rendering-rules
+/// https://drafts.csswg.org/css-images-3/#default-object-size
+pub const DEFAULT_IFRAME_SIZE_PX: (f32, f32) = (300., 150.);
+
+/// How deeply iframes can be nested in one another.  Iframes nested deeper than this are left
+/// empty, as are iframes of a document that's already open in one of their ancestors, so that
+/// documents including themselves don't load forever.
+const MAX_NESTING_DEPTH: usize = 10;
+
+/// The document an `<iframe>` displays, in a browsing context nested in the browsing context of
+/// the document the iframe is in.  Cheap to clone, with clones referring to the same context.
+///
+/// https://html.spec.whatwg.org/multipage/document-sequences.html#child-navigable
+#[derive(Clone)]
+pub struct NestedBrowsingContext(Rc<RefCell<BrowsingContextData>>);
+
+struct BrowsingContextData {
+    document: LoadedDocument,
+    /// The screen the document is rendered on, whose viewport is the iframe's content box.
+    device: Device,
+    ua_sheets: Vec<Stylesheet>,
+    box_tree: Option<LayoutBox>,
+}
+
+impl NestedBrowsingContext {
+    /// The nested document.
+    pub fn document(&self) -> NodeRef {
+        self.0.borrow().document.dom.clone()
+    }
+
+    /// The path of the file the nested document was loaded from.
+    pub fn path(&self) -> PathBuf {
+        self.0.borrow().document.path.clone()
+    }
+
+    /// The nested document's box tree, as last laid out, which is `None` if the document
+    /// generates no boxes.
+    pub fn box_tree(&self) -> Ref<Option<LayoutBox>> {
+        Ref::map(self.0.borrow(), |context| &context.box_tree)
+    }
+
+    /// Lays out the nested document in a viewport `width` by `height` CSS pixels in size, at
+    /// `scale_factor` device pixels per CSS pixel, restyling it first if that changes which media
+    /// queries match.  The documents of any iframes it has are laid out in turn.
+    pub fn layout(&self, width: f32, height: f32, scale_factor: f32) {
+        let context = &mut *self.0.borrow_mut();
+        update_device(
+            &mut context.device,
+            screen_device(width * scale_factor, height * scale_factor, scale_factor),
+            &context.document.dom,
+            &context.ua_sheets,
+            &[],
+            &mut context.box_tree,
+        );
+        load_document_images(
+            &context.document.dom,
+            &context.document.image_cache,
+            width,
+            scale_factor,
+        );
+        layout_document(
+            &mut context.box_tree,
+            &context.document.dom,
+            &context.device,
+            &context.ua_sheets,
+            &[],
+        );
+    }
+
+    /// Builds the display list painting the nested document as last laid out, relative to the
+    /// top-left corner of its viewport.
+    pub fn display_list(&self, animation_time: Duration, scale_factor: f32) -> DisplayList {
+        let context = self.0.borrow();
+        build_page_display_list(
+            context.box_tree.as_ref(),
+            &context.document.image_cache,
+            animation_time,
+            scale_factor,
+        )
+    }
+}
+
+// Don't print the nested document, which would dump its whole DOM into the debug output of its
+// iframe (and every ancestor of it).
+impl fmt::Debug for NestedBrowsingContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("NestedBrowsingContext")
+            .field(&self.0.borrow().document.path)
+            .finish()
+    }
+}
+
+/// Nested browsing contexts are the same if they're clones of one another.
+impl PartialEq for NestedBrowsingContext {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Loads the document of each `<iframe>` in `document` (which was loaded from `document_path`, in
+/// an iframe of each of the documents at `ancestor_paths`) into a nested browsing context of the
+/// iframe, styled by `ua_sheets` for a viewport of the default iframe size on `device`.  Nested
+/// documents load the documents of their own iframes in turn.
+///
+/// Iframes whose `src` can't be loaded (e.g. because it's a remote URL, or the file doesn't exist)
+/// are left empty, as browsers show an error page in them rather than failing to load the page.
+///
+/// https://html.spec.whatwg.org/multipage/iframe-embed-object.html#process-the-iframe-attributes
+pub fn load_nested_browsing_contexts(
+    document: &NodeRef,
+    document_path: &Path,
+    device: &Device,
+    ua_sheets: &[Stylesheet],
+    ancestor_paths: &[PathBuf],
+) {
+    if ancestor_paths.len() >= MAX_NESTING_DEPTH {
+        return;
+    }
+    let mut paths = ancestor_paths.to_vec();
+    paths.push(document_path.to_path_buf());
+    for node in document.descendants() {
+        let element = match node.as_element() {
+            Some(element)
+                if element.name.ns == ns!(html) && element.name.local == local_name!("iframe") =>
+            {
+                element
+            }
+            _ => continue,
+        };
+        let src = match element.attributes.borrow().get(local_name!("src")) {
+            Some(src) if !src.trim().is_empty() => src.to_owned(),
+            _ => continue,
+        };
+        // An iframe of a fragment of its own document would be a copy of its document.
+        let path = match resolve_link(document, document_path, &src) {
+            Ok(LinkTarget::Document(path)) if !paths.contains(&path) => path,
+            Ok(_) => continue,
+            Err(err) => {
+                eprintln!("couldn't load iframe '{}': {:?}", src, err);
+                continue;
+            }
+        };
+        let mut nested_device = Device::new(
+            device.media_type(),
+            DEFAULT_IFRAME_SIZE_PX.0,
+            DEFAULT_IFRAME_SIZE_PX.1,
+        );
+        nested_device.set_resolution(device.resolution());
+        nested_device.set_color_scheme(device.color_scheme());
+        let nested_document = match load_document_in(&path, &nested_device, ua_sheets, &[], &paths)
+        {
+            Ok(nested_document) => nested_document,
+            Err(err) => {
+                eprintln!("couldn't load iframe '{}': {}", src, err);
+                continue;
+            }
+        };
+        let box_tree = build_box_tree(nested_document.dom.clone(), None);
+        *element.nested_browsing_context.borrow_mut() = Some(NestedBrowsingContext(Rc::new(
+            RefCell::new(BrowsingContextData {
+                document: nested_document,
+                device: nested_device,
+                ua_sheets: ua_sheets.to_vec(),
+                box_tree,
+            }),
+        )));
+    }
+}
+
+/// Lays out the nested documents of the iframes among `layout_box` and its descendants, each in a
+/// viewport the size of its iframe's content box.  `layout_box` must already be laid out.
+pub fn layout_nested_browsing_contexts(layout_box: &LayoutBox, scale_factor: f32) {
+    if let Some(context) = layout_box.nested_browsing_context() {
+        let content_box = layout_box.dimensions().content;
+        context.layout(
+            content_box.width.px(),
+            content_box.height.px(),
+            scale_factor,
+        );
+    }
+    if let Some(children) = layout_box.children() {
+        for child in children {
+            layout_nested_browsing_contexts(child, scale_factor);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::page::{load_document, user_agent_stylesheet};
+    use crate::style::test_utils::laid_out_box_tree;
+    use tempdir::TempDir;
+
+    #[test]
+    fn loads_and_lays_out_nested_documents_in_their_iframes() {
+        let tempdir = TempDir::new("iframe").unwrap();
+        let html_file = tempdir.path().join("outer.html");
+        std::fs::write(
+            &html_file,
+            "<iframe src=inner.html style=\"width: 200px; height: 100px\"></iframe>\
+             <iframe src=outer.html></iframe><iframe src=missing.html></iframe>",
+        )
+        .unwrap();
+        std::fs::write(
+            tempdir.path().join("inner.html"),
+            "<div style=\"height: 20px\"></div><iframe></iframe>",
+        )
+        .unwrap();
+        let device = screen_device(800., 600., 1.);
+        let ua_sheets = vec![user_agent_stylesheet()];
+        let document = load_document(&html_file, &device, &ua_sheets, &[]).unwrap();
+        let iframes = document
+            .dom
+            .descendants()
+            .filter_map(|node| {
+                let element = node.as_element()?;
+                if element.name.local != local_name!("iframe") {
+                    return None;
+                }
+                Some(element.nested_browsing_context.borrow().clone())
+            })
+            .collect::<Vec<_>>();
+        // Iframes of their own document, or of documents that can't be loaded, are left empty.
+        assert_eq!(iframes.len(), 3);
+        assert!(iframes[1].is_none() && iframes[2].is_none());
+        let inner = iframes[0].clone().unwrap();
+        assert_eq!(inner.path(), tempdir.path().join("inner.html"));
+
+        laid_out_box_tree(&document.dom);
+        let inner_box_tree = inner.box_tree();
+        let inner_html = inner_box_tree.as_ref().unwrap();
+        // The nested document's viewport is the iframe's content box.
+        assert_eq!(inner_html.dimensions().content.width.px(), 200.);
+        assert_eq!(inner_html.dimensions().content.start_y, 0.);
+    }
+}
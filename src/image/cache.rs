@@ -0,0 +1,125 @@
+use crate::image::svg::SvgImage;
+use crate::image::{decode_image, DecodedImage, ImageError, ImageFormat, ImageUrl, LoadedImage};
+use accountable_refcell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Fetches, decodes, and caches images, keyed by the absolute URL of the image.  Each image is
+/// only decoded once no matter how many elements or backgrounds reference it.
+///
+/// Only local files are supported for now, so URLs are resolved against the directory containing
+/// the document being rendered.
+pub struct ImageCache {
+    base_dir: PathBuf,
+    /// The directory URLs starting with a slash are resolved against, if not the root of the file
+    /// system (see `with_document_root`).
+    document_root: Option<PathBuf>,
+    cached_images: RefCell<HashMap<ImageUrl, LoadedImage>>,
+    /// Rasterizations of SVG images, keyed by URL and the width and height of the rasterization.
+    ///
+    /// TODO: These are never evicted, so continuously resizing the window or zooming will grow
+    /// this cache without bound.
+    svg_rasters: RefCell<HashMap<(ImageUrl, u32, u32), Arc<DecodedImage>>>,
+}
+
+impl ImageCache {
+    pub fn new<P: AsRef<Path>>(base_dir: P) -> Self {
+        ImageCache {
+            base_dir: base_dir.as_ref().to_path_buf(),
+            document_root: None,
+            cached_images: RefCell::new(HashMap::new()),
+            svg_rasters: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Resolves URLs starting with a slash against `document_root`, as if the document were
+    /// served from a server whose root is that directory, rather than the root of the file system.
+    pub fn with_document_root<P: AsRef<Path>>(mut self, document_root: P) -> Self {
+        self.document_root = Some(document_root.as_ref().to_path_buf());
+        self
+    }
+
+    /// Resolves `url` relative to the document and returns its decoded image, decoding and caching
+    /// it first if necessary.
+    pub fn load(&self, url: &str) -> Result<LoadedImage, ImageError> {
+        let (image_url, path) = self.resolve(url)?;
+        if let Some(cached) = self.cached_images.borrow().get(&image_url) {
+            return Ok(cached.clone());
+        }
+        let bytes = std::fs::read(&path)?;
+        let loaded_image = match ImageFormat::sniff(&bytes) {
+            Some(ImageFormat::Svg) => {
+                LoadedImage::new_svg(image_url.clone(), Arc::new(SvgImage::parse(&bytes)?))
+            }
+            _ => LoadedImage::new(image_url.clone(), Arc::new(decode_image(&bytes)?)),
+        };
+        self.cached_images
+            .borrow_mut()
+            .insert(image_url, loaded_image.clone());
+        Ok(loaded_image)
+    }
+
+    /// Returns the image already cached for `url`, if any.  Never fetches or decodes.
+    pub fn get(&self, url: &str) -> Option<LoadedImage> {
+        let (image_url, _) = self.resolve(url).ok()?;
+        self.cached_images.borrow().get(&image_url).cloned()
+    }
+
+    /// Returns a version of `image` suitable for painting into an area `width` by `height` device
+    /// pixels large.  SVGs are re-rasterized (with the result cached) so they stay sharp at any
+    /// size, without distorting their aspect ratio; every other kind of image is returned as-is.
+    pub fn rasterize(&self, image: &LoadedImage, width: f32, height: f32) -> LoadedImage {
+        let svg = match image.svg() {
+            Some(svg) => svg,
+            None => return image.clone(),
+        };
+        let (natural_width, natural_height) = svg.natural_size();
+        if natural_width == 0 || natural_height == 0 {
+            return image.clone();
+        }
+        let scale = (width / natural_width as f32).max(height / natural_height as f32);
+        if !scale.is_finite() || scale <= 0. {
+            return image.clone();
+        }
+        let (raster_width, raster_height) = svg.raster_size(scale);
+        let raster = self
+            .svg_rasters
+            .borrow_mut()
+            .entry((image.url().clone(), raster_width, raster_height))
+            .or_insert_with(|| Arc::new(svg.rasterize(scale)))
+            .clone();
+        image.with_raster(raster)
+    }
+
+    /// Returns how long after `time` (measured from the start of the animation timeline) the
+    /// displayed frame of any cached animated image will change, or `None` if no cached image is
+    /// animated.
+    pub fn time_until_next_frame(&self, time: Duration) -> Option<Duration> {
+        self.cached_images
+            .borrow()
+            .values()
+            .filter_map(|loaded_image| loaded_image.image().time_until_next_frame(time))
+            .min()
+    }
+
+    fn resolve(&self, url: &str) -> Result<(ImageUrl, PathBuf), ImageError> {
+        let url = url.trim();
+        let path = if let Some(file_path) = url.strip_prefix("file://") {
+            PathBuf::from(file_path)
+        } else if let Some(scheme_end) = url.find(':') {
+            // Anything else with a scheme (`https:`, `data:`, ...) would need a network stack or
+            // a data URL parser, neither of which exist yet.
+            return Err(ImageError::UnsupportedScheme(url[..scheme_end].to_owned()));
+        } else if let (Some(document_root), Some(root_relative_url)) =
+            (&self.document_root, url.strip_prefix('/'))
+        {
+            document_root.join(root_relative_url)
+        } else {
+            self.base_dir.join(url)
+        };
+        let image_url = ImageUrl::new(format!("file://{}", path.display()));
+        Ok((image_url, path))
+    }
+}
@@ -0,0 +1,579 @@
+pub mod cache;
+pub mod srcset;
+pub mod svg;
+
+use crate::dom::tree::NodeRef;
+use crate::image::cache::ImageCache;
+use crate::image::srcset::select_image_source;
+use crate::image::svg::{is_svg, SvgImage};
+use crate::style::values::computed::BackgroundImage;
+use ::image::codecs::gif::GifDecoder;
+use ::image::codecs::png::PngDecoder;
+use ::image::{AnimationDecoder, Frame};
+use std::convert::{TryFrom, TryInto};
+use std::fmt;
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A fully decoded image, made up of one or more frames.  Every frame covers the entire image, so
+/// any frame can be painted on its own.
+#[derive(Clone, PartialEq)]
+pub struct DecodedImage {
+    width: u32,
+    height: u32,
+    frames: Vec<ImageFrame>,
+}
+
+impl DecodedImage {
+    /// Creates a still (single frame) image.
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Self {
+        DecodedImage::new_animated(
+            width,
+            height,
+            vec![ImageFrame::new(pixels, Duration::from_secs(0))],
+        )
+    }
+
+    /// Creates an image from one or more frames, each of which is `width` by `height` pixels.
+    pub fn new_animated(width: u32, height: u32, frames: Vec<ImageFrame>) -> Self {
+        assert!(!frames.is_empty());
+        for frame in &frames {
+            assert_eq!(frame.pixels.len(), width as usize * height as usize * 4);
+        }
+        DecodedImage {
+            width,
+            height,
+            frames,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn frame(&self, index: usize) -> &ImageFrame {
+        &self.frames[index]
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_animated(&self) -> bool {
+        self.frames.len() > 1
+    }
+
+    /// Returns the index of the frame that should be displayed `time` after the animation started.
+    /// Animations loop forever.
+    ///
+    /// TODO: Respect the loop count of the image (the NETSCAPE2.0 extension for GIFs, `num_plays`
+    /// for APNGs).
+    pub fn frame_index_at(&self, time: Duration) -> usize {
+        self.locate_frame(time).0
+    }
+
+    /// Returns how long after `time` the displayed frame of this image will change, or `None` if
+    /// this image is not animated.
+    pub fn time_until_next_frame(&self, time: Duration) -> Option<Duration> {
+        if !self.is_animated() {
+            return None;
+        }
+        Some(self.locate_frame(time).1)
+    }
+
+    /// Returns the index of the frame displayed at `time`, and how much longer that frame will be
+    /// displayed for.
+    fn locate_frame(&self, time: Duration) -> (usize, Duration) {
+        let loop_duration: Duration = self.frames.iter().map(|frame| frame.delay).sum();
+        if loop_duration.as_nanos() == 0 {
+            return (0, Duration::from_secs(0));
+        }
+        let mut time_into_loop =
+            Duration::from_nanos((time.as_nanos() % loop_duration.as_nanos()) as u64);
+        for (index, frame) in self.frames.iter().enumerate() {
+            if time_into_loop < frame.delay {
+                return (index, frame.delay - time_into_loop);
+            }
+            time_into_loop -= frame.delay;
+        }
+        unreachable!("time into loop is always less than the loop duration")
+    }
+}
+
+// Don't print the pixel data -- even small images have thousands of bytes of it, which makes debug
+// output of anything holding an image (like the DOM) unreadable.
+impl fmt::Debug for DecodedImage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DecodedImage")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("frame_count", &self.frames.len())
+            .finish()
+    }
+}
+
+/// A single frame of a decoded image, stored as a non-premultiplied RGBA bitmap with 8 bits per
+/// channel.  Pixels are stored row-by-row, starting with the top-left pixel of the image.
+#[derive(Clone, PartialEq)]
+pub struct ImageFrame {
+    pixels: Vec<u8>,
+    /// How long this frame is displayed before moving to the next one.  Meaningless for still
+    /// images.
+    delay: Duration,
+}
+
+impl ImageFrame {
+    pub fn new(pixels: Vec<u8>, delay: Duration) -> Self {
+        ImageFrame { pixels, delay }
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+}
+
+/// Converts straight RGBA pixels, which images are stored as, to the premultiplied RGBA tiny-skia
+/// paints with.
+pub fn premultiply(pixels: &[u8]) -> Vec<u8> {
+    let mut premultiplied = pixels.to_vec();
+    for pixel in premultiplied.chunks_exact_mut(4) {
+        let alpha = pixel[3] as u32;
+        if alpha != 255 {
+            for channel in &mut pixel[..3] {
+                *channel = ((*channel as u32 * alpha + 127) / 255) as u8;
+            }
+        }
+    }
+    premultiplied
+}
+
+/// Converts premultiplied RGBA pixels, which tiny-skia produces, to straight RGBA.
+pub fn demultiply(mut pixels: Vec<u8>) -> Vec<u8> {
+    for pixel in pixels.chunks_exact_mut(4) {
+        let alpha = pixel[3] as u32;
+        if alpha != 0 && alpha != 255 {
+            for channel in &mut pixel[..3] {
+                *channel = ((*channel as u32 * 255 + alpha / 2) / alpha).min(255) as u8;
+            }
+        }
+    }
+    pixels
+}
+
+/// Writes the first frame of `image` to a PNG at `path`.
+pub fn save_png(image: &DecodedImage, path: &Path) -> ::image::ImageResult<()> {
+    ::image::save_buffer(
+        path,
+        image.frame(0).pixels(),
+        image.width(),
+        image.height(),
+        ::image::ColorType::Rgba8,
+    )
+}
+
+/// Encodes the first frame of `image` as a PNG.
+pub fn encode_png(image: &DecodedImage) -> ::image::ImageResult<Vec<u8>> {
+    let mut png = Vec::new();
+    ::image::png::PngEncoder::new(&mut png).encode(
+        image.frame(0).pixels(),
+        image.width(),
+        image.height(),
+        ::image::ColorType::Rgba8,
+    )?;
+    Ok(png)
+}
+
+/// An absolute URL identifying an image resource.  Used as the key for all image caches.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ImageUrl(String);
+
+impl ImageUrl {
+    pub fn new(url: String) -> Self {
+        ImageUrl(url)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for ImageUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A decoded image along with the URL it was fetched from.  Cheap to clone, and can be sent to
+/// other threads (e.g. in display lists sent to the compositor).
+///
+/// For SVGs, `image` is one rasterization of the vector image, by default at its natural size.
+/// `ImageCache::rasterize` can be used to get a rasterization better suited to the size the image
+/// is painted at.
+#[derive(Clone, Debug)]
+pub struct LoadedImage {
+    url: ImageUrl,
+    image: Arc<DecodedImage>,
+    svg: Option<Arc<SvgImage>>,
+}
+
+/// Loaded images are the same if they share a rasterization of the same URL.
+impl PartialEq for LoadedImage {
+    fn eq(&self, other: &Self) -> bool {
+        self.url == other.url && Arc::ptr_eq(&self.image, &other.image)
+    }
+}
+
+impl LoadedImage {
+    pub fn new(url: ImageUrl, image: Arc<DecodedImage>) -> Self {
+        LoadedImage {
+            url,
+            image,
+            svg: None,
+        }
+    }
+
+    /// Creates a loaded image for an SVG, rasterized at its natural size.
+    pub fn new_svg(url: ImageUrl, svg: Arc<SvgImage>) -> Self {
+        LoadedImage {
+            url,
+            image: Arc::new(svg.rasterize(1.)),
+            svg: Some(svg),
+        }
+    }
+
+    /// Returns a copy of this image that paints the given rasterization of its SVG.
+    fn with_raster(&self, image: Arc<DecodedImage>) -> Self {
+        LoadedImage {
+            url: self.url.clone(),
+            image,
+            svg: self.svg.clone(),
+        }
+    }
+
+    pub fn url(&self) -> &ImageUrl {
+        &self.url
+    }
+
+    pub fn image(&self) -> &DecodedImage {
+        &self.image
+    }
+
+    pub fn svg(&self) -> Option<&SvgImage> {
+        self.svg.as_deref()
+    }
+
+    /// The natural width of the image in image pixels.
+    pub fn natural_width(&self) -> u32 {
+        match &self.svg {
+            Some(svg) => svg.natural_size().0,
+            None => self.image.width(),
+        }
+    }
+
+    /// The natural height of the image in image pixels.
+    pub fn natural_height(&self) -> u32 {
+        match &self.svg {
+            Some(svg) => svg.natural_size().1,
+            None => self.image.height(),
+        }
+    }
+}
+
+/// The image an `<img>` element has selected and loaded, along with the pixel density it was
+/// selected for.
+///
+/// https://html.spec.whatwg.org/multipage/images.html#image-request
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageRequest {
+    image: LoadedImage,
+    /// https://html.spec.whatwg.org/multipage/images.html#current-pixel-density
+    current_pixel_density: f32,
+}
+
+impl ImageRequest {
+    pub fn new(image: LoadedImage, current_pixel_density: f32) -> Self {
+        ImageRequest {
+            image,
+            current_pixel_density,
+        }
+    }
+
+    pub fn image(&self) -> &LoadedImage {
+        &self.image
+    }
+
+    pub fn current_pixel_density(&self) -> f32 {
+        self.current_pixel_density
+    }
+
+    /// The natural width of the image in CSS pixels, taking its pixel density into account.
+    /// https://html.spec.whatwg.org/multipage/images.html#density-corrected-natural-width-and-height
+    pub fn density_corrected_natural_width(&self) -> f32 {
+        self.image.natural_width() as f32 / self.current_pixel_density
+    }
+
+    /// The natural height of the image in CSS pixels, taking its pixel density into account.
+    pub fn density_corrected_natural_height(&self) -> f32 {
+        self.image.natural_height() as f32 / self.current_pixel_density
+    }
+}
+
+/// The image formats Kosmonaut knows how to decode.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImageFormat {
+    Gif,
+    Jpeg,
+    Png,
+    Svg,
+    WebP,
+}
+
+impl ImageFormat {
+    /// Determines the format of an image by examining its signature (the first few bytes of the
+    /// resource), per the rules in https://mimesniff.spec.whatwg.org/#matching-an-image-type-pattern.
+    ///
+    /// The mimesniff spec never sniffs SVGs (it relies on the `Content-Type` header instead), but
+    /// Kosmonaut only loads local files, so SVGs are detected by their root element.
+    ///
+    /// Sniffing is used over trusting file extensions since extensions are frequently wrong.
+    pub fn sniff(bytes: &[u8]) -> Option<ImageFormat> {
+        if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+            Some(ImageFormat::Gif)
+        } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(ImageFormat::Jpeg)
+        } else if bytes.starts_with(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]) {
+            Some(ImageFormat::Png)
+        } else if bytes.len() >= 14 && &bytes[0..4] == b"RIFF" && &bytes[8..14] == b"WEBPVP" {
+            Some(ImageFormat::WebP)
+        } else if is_svg(bytes) {
+            Some(ImageFormat::Svg)
+        } else {
+            None
+        }
+    }
+}
+
+impl TryFrom<ImageFormat> for ::image::ImageFormat {
+    type Error = ImageError;
+
+    fn try_from(format: ImageFormat) -> Result<Self, Self::Error> {
+        Ok(match format {
+            ImageFormat::Gif => ::image::ImageFormat::Gif,
+            ImageFormat::Jpeg => ::image::ImageFormat::Jpeg,
+            ImageFormat::Png => ::image::ImageFormat::Png,
+            ImageFormat::WebP => ::image::ImageFormat::WebP,
+            // SVGs are rasterized rather than decoded -- see `SvgImage`.
+            ImageFormat::Svg => return Err(ImageError::UnsupportedFormat),
+        })
+    }
+}
+
+/// Decodes the given image resource into RGBA bitmaps.  Every frame of animated GIFs and APNGs is
+/// decoded; other formats only ever have a single frame.
+///
+/// TODO: Decode animated WebPs.
+pub fn decode_image(bytes: &[u8]) -> Result<DecodedImage, ImageError> {
+    let format = ImageFormat::sniff(bytes).ok_or(ImageError::UnsupportedFormat)?;
+    let frames = match format {
+        ImageFormat::Gif => Some(GifDecoder::new(Cursor::new(bytes))?.into_frames()),
+        ImageFormat::Png => {
+            let decoder = PngDecoder::new(Cursor::new(bytes))?;
+            if decoder.is_apng() {
+                Some(decoder.apng().into_frames())
+            } else {
+                None
+            }
+        }
+        ImageFormat::Jpeg | ImageFormat::Svg | ImageFormat::WebP => None,
+    };
+    match frames {
+        Some(frames) => decode_animation(frames.collect_frames()?),
+        None => {
+            let rgba = ::image::load_from_memory_with_format(bytes, format.try_into()?)?.to_rgba8();
+            let (width, height) = rgba.dimensions();
+            Ok(DecodedImage::new(width, height, rgba.into_raw()))
+        }
+    }
+}
+
+fn decode_animation(frames: Vec<Frame>) -> Result<DecodedImage, ImageError> {
+    let (width, height) = match frames.first() {
+        Some(first_frame) => first_frame.buffer().dimensions(),
+        None => return Err(ImageError::NoFrames),
+    };
+    let frames = frames
+        .into_iter()
+        // The decoders composite each frame onto the full canvas, so frames should never be a
+        // different size than the first.  Drop any that are rather than painting garbage.
+        .filter(|frame| frame.buffer().dimensions() == (width, height))
+        .map(|frame| {
+            let (numer, denom) = frame.delay().numer_denom_ms();
+            let delay = clamp_frame_delay(Duration::from_micros(
+                numer as u64 * 1000 / denom.max(1) as u64,
+            ));
+            ImageFrame::new(frame.into_buffer().into_raw(), delay)
+        })
+        .collect();
+    Ok(DecodedImage::new_animated(width, height, frames))
+}
+
+/// Many animated images on the web specify frame delays of zero or near-zero, expecting them to be
+/// treated as a "default" delay.  Every major browser bumps delays of 10ms or less up to 100ms,
+/// so do the same.
+fn clamp_frame_delay(delay: Duration) -> Duration {
+    if delay <= Duration::from_millis(10) {
+        Duration::from_millis(100)
+    } else {
+        delay
+    }
+}
+
+/// Fetches and decodes every image referenced by the given (already styled) document.  This is
+/// the image selected from the `src` and `srcset` attributes of each `<img>` element, which is
+/// stored as that element's current request, and the `background-image` of each element.
+///
+/// The `<img>` sources selected depend on the viewport, so this should be called again whenever
+/// the viewport width (in CSS pixels) or device pixel ratio changes.
+///
+/// https://html.spec.whatwg.org/multipage/images.html#update-the-image-data
+pub fn load_document_images(
+    document: &NodeRef,
+    image_cache: &ImageCache,
+    viewport_width: f32,
+    device_pixel_ratio: f32,
+) {
+    for node in document.shadow_including_inclusive_descendants() {
+        let element = match node.as_element() {
+            Some(element) => element,
+            None => continue,
+        };
+        if let BackgroundImage::Url(url) = &node.computed_values().background_image {
+            if let Err(err) = image_cache.load(url) {
+                eprintln!("couldn't load background image '{}': {:?}", url, err);
+            }
+        }
+        if element.name.local != local_name!("img") {
+            continue;
+        }
+        let attributes = element.attributes.borrow();
+        let selected_source = select_image_source(
+            attributes.get(local_name!("src")),
+            attributes.get(local_name!("srcset")),
+            attributes.get(local_name!("sizes")),
+            viewport_width,
+            device_pixel_ratio,
+        );
+        drop(attributes);
+        let new_request = selected_source.and_then(|source| match image_cache.load(&source.url) {
+            Ok(loaded_image) => Some(ImageRequest::new(loaded_image, source.pixel_density)),
+            Err(err) => {
+                eprintln!("couldn't load image '{}': {:?}", source.url, err);
+                None
+            }
+        });
+        *element.current_request.borrow_mut() = new_request;
+    }
+}
+
+#[derive(Debug)]
+pub enum ImageError {
+    Decoding(::image::ImageError),
+    Io(std::io::Error),
+    /// The image was animated, but had no frames.
+    NoFrames,
+    /// The image was an SVG, but couldn't be parsed.
+    Svg(usvg::Error),
+    /// The image resource did not match the signature of any supported format.
+    UnsupportedFormat,
+    /// The image URL uses a scheme we can't fetch from, such as `https:` or `data:`.
+    UnsupportedScheme(String),
+}
+
+impl From<::image::ImageError> for ImageError {
+    fn from(err: ::image::ImageError) -> Self {
+        ImageError::Decoding(err)
+    }
+}
+
+impl From<usvg::Error> for ImageError {
+    fn from(err: usvg::Error) -> Self {
+        ImageError::Svg(err)
+    }
+}
+
+impl From<std::io::Error> for ImageError {
+    fn from(err: std::io::Error) -> Self {
+        ImageError::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn animation(delays_ms: &[u64]) -> DecodedImage {
+        DecodedImage::new_animated(
+            1,
+            1,
+            delays_ms
+                .iter()
+                .map(|&delay| ImageFrame::new(vec![0; 4], Duration::from_millis(delay)))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn still_image_always_shows_first_frame() {
+        let image = DecodedImage::new(1, 1, vec![0; 4]);
+        assert_eq!(image.frame_index_at(Duration::from_secs(100)), 0);
+        assert_eq!(image.time_until_next_frame(Duration::from_secs(0)), None);
+    }
+
+    #[test]
+    fn animation_frames_follow_delays_and_loop() {
+        let image = animation(&[100, 200, 50]);
+        assert_eq!(image.frame_index_at(Duration::from_millis(0)), 0);
+        assert_eq!(image.frame_index_at(Duration::from_millis(99)), 0);
+        assert_eq!(image.frame_index_at(Duration::from_millis(100)), 1);
+        assert_eq!(image.frame_index_at(Duration::from_millis(320)), 2);
+        // The loop is 350ms long, so this is 10ms into the second loop.
+        assert_eq!(image.frame_index_at(Duration::from_millis(360)), 0);
+        assert_eq!(
+            image.time_until_next_frame(Duration::from_millis(150)),
+            Some(Duration::from_millis(150))
+        );
+    }
+
+    #[test]
+    fn sniffs_image_signatures() {
+        assert_eq!(ImageFormat::sniff(b"GIF89a..."), Some(ImageFormat::Gif));
+        assert_eq!(
+            ImageFormat::sniff(b"RIFF\x00\x00\x00\x00WEBPVP8 "),
+            Some(ImageFormat::WebP)
+        );
+        assert_eq!(ImageFormat::sniff(b"<svg></svg>"), Some(ImageFormat::Svg));
+        assert_eq!(ImageFormat::sniff(b"<html></html>"), None);
+    }
+
+    #[test]
+    fn premultiplies_partially_transparent_pixels() {
+        assert_eq!(premultiply(&[128, 0, 64, 128]), vec![64, 0, 32, 128]);
+        assert_eq!(premultiply(&[10, 20, 30, 255]), vec![10, 20, 30, 255]);
+    }
+
+    #[test]
+    fn demultiplies_partially_transparent_pixels() {
+        assert_eq!(demultiply(vec![64, 0, 32, 128]), vec![128, 0, 64, 128]);
+        assert_eq!(demultiply(vec![0, 0, 0, 0]), vec![0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,363 @@
+//! Parsing of the `srcset` and `sizes` attributes of `<img>`, and selection of the image source
+//! best suited to the current viewport.
+//!
+//! https://html.spec.whatwg.org/multipage/images.html#srcset-attributes
+
+use cssparser::{Delimiter, ParseError, Parser, ParserInput, Token};
+
+/// Relative lengths in media conditions, like those in `sizes`, are resolved against the initial
+/// value of `font-size` rather than any element's font size.
+///
+/// https://drafts.csswg.org/mediaqueries/#units
+const INITIAL_FONT_SIZE_PX: f32 = 16.;
+
+/// A single entry of a `srcset` attribute.
+///
+/// https://html.spec.whatwg.org/multipage/images.html#image-candidate-string
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageCandidate {
+    pub url: String,
+    pub descriptor: CandidateDescriptor,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CandidateDescriptor {
+    /// An `x` descriptor, giving the pixel density of the image.
+    Density(f32),
+    /// A `w` descriptor, giving the width of the image in image pixels.
+    Width(u32),
+}
+
+/// Parses a `srcset` attribute into its image candidates, dropping any candidates that are
+/// invalid.
+///
+/// https://html.spec.whatwg.org/multipage/images.html#parse-a-srcset-attribute
+pub fn parse_srcset(srcset: &str) -> Vec<ImageCandidate> {
+    let mut candidates = Vec::new();
+    let mut remaining = srcset;
+    loop {
+        remaining = remaining.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == ',');
+        if remaining.is_empty() {
+            return candidates;
+        }
+        let url_end = remaining
+            .find(|c: char| c.is_ascii_whitespace())
+            .unwrap_or(remaining.len());
+        let (raw_url, rest) = remaining.split_at(url_end);
+        let url = raw_url.trim_end_matches(',');
+        let descriptors = if url.len() != raw_url.len() {
+            // A URL ending with a comma has no descriptors.
+            remaining = rest;
+            ""
+        } else {
+            let descriptors_end = rest.find(',').unwrap_or(rest.len());
+            remaining = &rest[descriptors_end..];
+            &rest[..descriptors_end]
+        };
+        if let Some(descriptor) = parse_descriptors(descriptors) {
+            candidates.push(ImageCandidate {
+                url: url.to_owned(),
+                descriptor,
+            });
+        }
+    }
+}
+
+/// Parses the descriptors of a single image candidate.  Returns `None` if they are invalid, in
+/// which case the candidate should be dropped.
+fn parse_descriptors(descriptors: &str) -> Option<CandidateDescriptor> {
+    let mut density = None;
+    let mut width = None;
+    for descriptor in descriptors.split_ascii_whitespace() {
+        let kind_start = descriptor
+            .char_indices()
+            .last()
+            .map_or(0, |(index, _)| index);
+        let (value, kind) = descriptor.split_at(kind_start);
+        match kind {
+            "x" if density.is_none() && width.is_none() => {
+                density = Some(value.parse::<f32>().ok().filter(|d| *d >= 0.)?)
+            }
+            "w" if density.is_none() && width.is_none() => {
+                width = Some(value.parse::<u32>().ok().filter(|w| *w > 0)?)
+            }
+            // Height descriptors are only valid alongside width descriptors, and are otherwise
+            // ignored (they're reserved for future use).
+            "h" => {
+                value.parse::<u32>().ok().filter(|h| *h > 0)?;
+            }
+            _ => return None,
+        }
+    }
+    Some(match (density, width) {
+        (_, Some(width)) => CandidateDescriptor::Width(width),
+        (Some(density), None) => CandidateDescriptor::Density(density),
+        (None, None) => CandidateDescriptor::Density(1.),
+    })
+}
+
+/// A single entry of a `sizes` attribute.
+///
+/// https://html.spec.whatwg.org/multipage/images.html#sizes-attributes
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceSize {
+    condition: Option<Vec<MediaFeature>>,
+    length: SourceSizeLength,
+}
+
+/// The media features allowed in a `sizes` media condition.  Only the width of the viewport is
+/// supported, and features may only be combined with `and`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MediaFeature {
+    MaxWidth(SourceSizeLength),
+    MinWidth(SourceSizeLength),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SourceSizeLength {
+    Px(f32),
+    Vw(f32),
+}
+
+impl SourceSizeLength {
+    fn to_px(self, viewport_width: f32) -> f32 {
+        match self {
+            SourceSizeLength::Px(px) => px,
+            SourceSizeLength::Vw(vw) => vw * viewport_width / 100.,
+        }
+    }
+
+    fn parse<'i, 't>(input: &mut Parser<'i, 't>) -> Result<Self, ParseError<'i, ()>> {
+        let location = input.current_source_location();
+        match input.next()?.clone() {
+            Token::Dimension {
+                value, ref unit, ..
+            } => match_ignore_ascii_case! { &**unit,
+                "px" => Ok(SourceSizeLength::Px(value)),
+                "em" | "rem" => Ok(SourceSizeLength::Px(value * INITIAL_FONT_SIZE_PX)),
+                "vw" => Ok(SourceSizeLength::Vw(value)),
+                _ => Err(location.new_custom_error(())),
+            },
+            Token::Number { value, .. } if value == 0. => Ok(SourceSizeLength::Px(0.)),
+            _ => Err(location.new_custom_error(())),
+        }
+    }
+}
+
+impl MediaFeature {
+    fn matches(self, viewport_width: f32) -> bool {
+        match self {
+            MediaFeature::MaxWidth(length) => viewport_width <= length.to_px(viewport_width),
+            MediaFeature::MinWidth(length) => viewport_width >= length.to_px(viewport_width),
+        }
+    }
+
+    fn parse<'i, 't>(input: &mut Parser<'i, 't>) -> Result<Self, ParseError<'i, ()>> {
+        input.expect_parenthesis_block()?;
+        input.parse_nested_block(|input| {
+            let location = input.current_source_location();
+            let name = input.expect_ident()?.clone();
+            input.expect_colon()?;
+            let length = SourceSizeLength::parse(input)?;
+            match_ignore_ascii_case! { &*name,
+                "max-width" => Ok(MediaFeature::MaxWidth(length)),
+                "min-width" => Ok(MediaFeature::MinWidth(length)),
+                _ => Err(location.new_custom_error(())),
+            }
+        })
+    }
+}
+
+impl SourceSize {
+    fn parse<'i, 't>(input: &mut Parser<'i, 't>) -> Result<Self, ParseError<'i, ()>> {
+        let condition = input
+            .try_parse(|input| {
+                let mut features = vec![MediaFeature::parse(input)?];
+                while input
+                    .try_parse(|input| input.expect_ident_matching("and"))
+                    .is_ok()
+                {
+                    features.push(MediaFeature::parse(input)?);
+                }
+                Ok::<_, ParseError<'i, ()>>(features)
+            })
+            .ok();
+        let length = SourceSizeLength::parse(input)?;
+        input.expect_exhausted()?;
+        Ok(SourceSize { condition, length })
+    }
+
+    fn matches(&self, viewport_width: f32) -> bool {
+        match &self.condition {
+            Some(features) => features
+                .iter()
+                .all(|feature| feature.matches(viewport_width)),
+            None => true,
+        }
+    }
+}
+
+/// Parses a `sizes` attribute, dropping any entries that are invalid.
+///
+/// https://html.spec.whatwg.org/multipage/images.html#parse-a-sizes-attribute
+pub fn parse_sizes(sizes: &str) -> Vec<SourceSize> {
+    let mut input = ParserInput::new(sizes);
+    let mut parser = Parser::new(&mut input);
+    let mut source_sizes = Vec::new();
+    loop {
+        if let Ok(source_size) = parser.parse_until_before(Delimiter::Comma, SourceSize::parse) {
+            source_sizes.push(source_size);
+        }
+        // Either consume the comma separating this entry from the next, or we're at the end.
+        if parser.next().is_err() {
+            return source_sizes;
+        }
+    }
+}
+
+/// Returns the width, in CSS pixels, that the image is expected to be displayed at.  This is the
+/// length of the first entry of `sizes` whose media condition matches, or `100vw` if none do.
+fn source_size(sizes: &[SourceSize], viewport_width: f32) -> f32 {
+    sizes
+        .iter()
+        .find(|source_size| source_size.matches(viewport_width))
+        .map(|source_size| source_size.length.to_px(viewport_width))
+        .unwrap_or(viewport_width)
+}
+
+/// An image source chosen from an `<img>` element's `src` and `srcset` attributes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SelectedSource {
+    pub url: String,
+    /// The pixel density of the image, used to convert image pixels into CSS pixels.
+    /// https://html.spec.whatwg.org/multipage/images.html#current-pixel-density
+    pub pixel_density: f32,
+}
+
+/// Selects the image source best suited to a viewport `viewport_width` CSS pixels wide with the
+/// given device pixel ratio.  Returns `None` if there are no candidates to choose from.
+///
+/// https://html.spec.whatwg.org/multipage/images.html#select-an-image-source
+pub fn select_image_source(
+    src: Option<&str>,
+    srcset: Option<&str>,
+    sizes: Option<&str>,
+    viewport_width: f32,
+    device_pixel_ratio: f32,
+) -> Option<SelectedSource> {
+    let source_size = source_size(&sizes.map(parse_sizes).unwrap_or_default(), viewport_width);
+    let mut sources = srcset
+        .map(parse_srcset)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|candidate| SelectedSource {
+            pixel_density: match candidate.descriptor {
+                CandidateDescriptor::Density(density) => density,
+                CandidateDescriptor::Width(width) => width as f32 / source_size,
+            },
+            url: candidate.url,
+        })
+        .filter(|source| source.pixel_density.is_finite() && source.pixel_density > 0.)
+        .collect::<Vec<_>>();
+    // The `src` attribute acts as a `1x` candidate, unless `srcset` already provided one.
+    if let Some(src) = src.map(str::trim).filter(|src| !src.is_empty()) {
+        if !sources.iter().any(|source| source.pixel_density == 1.) {
+            sources.push(SelectedSource {
+                url: src.to_owned(),
+                pixel_density: 1.,
+            });
+        }
+    }
+
+    // Pick the lowest density that is still at least the device pixel ratio, so the image is
+    // never blurry.  If every candidate is too low-density, settle for the highest.
+    sources.sort_by(|a, b| a.pixel_density.partial_cmp(&b.pixel_density).unwrap());
+    let best_index = sources
+        .iter()
+        .position(|source| source.pixel_density >= device_pixel_ratio)
+        .unwrap_or(sources.len().saturating_sub(1));
+    if sources.is_empty() {
+        None
+    } else {
+        Some(sources.swap_remove(best_index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_srcset_candidates() {
+        assert_eq!(
+            parse_srcset("small.png, medium.png 2x,large.png 3.5x ,  wide.png 800w, bad.png 2q"),
+            vec![
+                ImageCandidate {
+                    url: "small.png".to_owned(),
+                    descriptor: CandidateDescriptor::Density(1.)
+                },
+                ImageCandidate {
+                    url: "medium.png".to_owned(),
+                    descriptor: CandidateDescriptor::Density(2.)
+                },
+                ImageCandidate {
+                    url: "large.png".to_owned(),
+                    descriptor: CandidateDescriptor::Density(3.5)
+                },
+                ImageCandidate {
+                    url: "wide.png".to_owned(),
+                    descriptor: CandidateDescriptor::Width(800)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn sizes_uses_first_matching_condition() {
+        let sizes = parse_sizes(
+            "(max-width: 600px) 100vw, (min-width: 601px) and (max-width: 60em) 50vw, 400px",
+        );
+        assert_eq!(source_size(&sizes, 500.), 500.);
+        assert_eq!(source_size(&sizes, 800.), 400.);
+        assert_eq!(source_size(&sizes, 1000.), 400.);
+    }
+
+    #[test]
+    fn invalid_sizes_entries_are_skipped() {
+        let sizes = parse_sizes("(orientation: landscape) 10px, calc(5px + 5px), 20px");
+        assert_eq!(source_size(&sizes, 1000.), 20.);
+    }
+
+    #[test]
+    fn selects_smallest_sufficient_density() {
+        let select = |dpr| {
+            select_image_source(
+                Some("1x.png"),
+                Some("2x.png 2x, 3x.png 3x"),
+                None,
+                1000.,
+                dpr,
+            )
+            .unwrap()
+            .url
+        };
+        assert_eq!(select(1.), "1x.png");
+        assert_eq!(select(1.5), "2x.png");
+        assert_eq!(select(2.), "2x.png");
+        assert_eq!(select(4.), "3x.png");
+    }
+
+    #[test]
+    fn width_descriptors_are_relative_to_source_size() {
+        let selected = select_image_source(
+            None,
+            Some("400.png 400w, 800.png 800w"),
+            Some("(max-width: 500px) 100vw, 400px"),
+            1000.,
+            2.,
+        )
+        .unwrap();
+        assert_eq!(selected.url, "800.png");
+        assert_eq!(selected.pixel_density, 2.);
+    }
+}
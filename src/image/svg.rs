@@ -0,0 +1,106 @@
+use crate::image::{demultiply, DecodedImage, ImageError};
+use std::fmt;
+
+/// A parsed SVG document.  Unlike other images, SVGs are resolution-independent, so rather than
+/// being decoded once they are rasterized at whatever size they're painted at.
+///
+/// usvg's trees can't be sent between threads, while images are sent to the compositor, so the
+/// source of the document is kept rather than its tree, and parsed again for each rasterization.
+/// Rasterizations are cached by the `ImageCache`, so this only happens when the size an SVG is
+/// painted at changes.
+pub struct SvgImage {
+    source: Vec<u8>,
+    natural_size: (u32, u32),
+}
+
+impl SvgImage {
+    pub fn parse(bytes: &[u8]) -> Result<SvgImage, ImageError> {
+        let tree = usvg::Tree::from_data(bytes, &usvg::Options::default())?;
+        // TODO: An SVG without a `width` and `height` has no natural size, only a natural aspect
+        // ratio from its `viewBox`.  usvg falls back to the size of the `viewBox` in that case.
+        let size = tree.svg_node().size;
+        Ok(SvgImage {
+            source: bytes.to_vec(),
+            natural_size: (size.width().ceil() as u32, size.height().ceil() as u32),
+        })
+    }
+
+    /// The natural width and height of the image, taken from the `width` and `height` attributes
+    /// of the root `<svg>` element.
+    pub fn natural_size(&self) -> (u32, u32) {
+        self.natural_size
+    }
+
+    /// The width and height of the bitmap `rasterize` produces for the given `scale`.
+    pub fn raster_size(&self, scale: f32) -> (u32, u32) {
+        let (natural_width, natural_height) = self.natural_size();
+        (
+            ((natural_width as f32 * scale).ceil() as u32).max(1),
+            ((natural_height as f32 * scale).ceil() as u32).max(1),
+        )
+    }
+
+    /// Rasterizes the image, scaling its natural size by `scale`.
+    pub fn rasterize(&self, scale: f32) -> DecodedImage {
+        let tree = usvg::Tree::from_data(&self.source, &usvg::Options::default())
+            .expect("svg was parsed when it was loaded");
+        let (width, height) = self.raster_size(scale);
+        let mut pixmap =
+            tiny_skia::Pixmap::new(width, height).expect("couldn't allocate pixmap for svg");
+        resvg::render(&tree, usvg::FitTo::Zoom(scale), pixmap.as_mut());
+        // tiny-skia produces premultiplied RGBA, while every other image is painted as straight
+        // RGBA.
+        DecodedImage::new(width, height, demultiply(pixmap.take()))
+    }
+}
+
+impl fmt::Debug for SvgImage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (width, height) = self.natural_size();
+        f.debug_struct("SvgImage")
+            .field("width", &width)
+            .field("height", &height)
+            .finish()
+    }
+}
+
+/// Whether the given resource looks like an SVG document.  SVG is a text format, so unlike the
+/// binary image formats it has no fixed signature to sniff; instead look for an `<svg` tag near
+/// the start of the document.
+pub fn is_svg(bytes: &[u8]) -> bool {
+    let prefix = &bytes[..bytes.len().min(1024)];
+    let prefix = String::from_utf8_lossy(prefix);
+    let trimmed = prefix.trim_start_matches('\u{FEFF}').trim_start();
+    (trimmed.starts_with("<svg") || trimmed.starts_with("<?xml") || trimmed.starts_with("<!"))
+        && trimmed.contains("<svg")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED_4X2: &[u8] = br#"<svg xmlns="http://www.w3.org/2000/svg" width="4" height="2">
+        <rect width="4" height="2" fill="red"/>
+    </svg>"#;
+
+    #[test]
+    fn sniffs_svg_documents() {
+        assert!(is_svg(RED_4X2));
+        assert!(is_svg(
+            b"<?xml version=\"1.0\"?>\n<svg xmlns=\"http://www.w3.org/2000/svg\"/>"
+        ));
+        assert!(!is_svg(b"GIF89a"));
+        assert!(!is_svg(b"<html><body></body></html>"));
+    }
+
+    #[test]
+    fn rasterizes_at_scale() {
+        let svg = SvgImage::parse(RED_4X2).unwrap();
+        assert_eq!(svg.natural_size(), (4, 2));
+        let image = svg.rasterize(2.);
+        assert_eq!((image.width(), image.height()), (8, 4));
+        for pixel in image.frame(0).pixels().chunks_exact(4) {
+            assert_eq!(pixel, &[255, 0, 0, 255]);
+        }
+    }
+}
@@ -0,0 +1,161 @@
+//! The baselines of laid out boxes, which are what boxes are aligned by when they're aligned with
+//! the text around them (e.g. atomic inlines within their lines).
+//!
+//! https://drafts.csswg.org/css-align-3/#baseline-rules
+
+use crate::layout::behavior::BaseLayoutBoxBehavior;
+use crate::layout::flow::block::BlockLevelBox;
+use crate::layout::flow::inline::{InlineLevelBox, InlineLevelContent};
+use crate::layout::layout_box::LayoutBox;
+use crate::style::values::CSSFloat;
+
+/// The first and last baselines of a box, as block-axis coordinates (i.e. y-coordinates in
+/// horizontal writing modes and x-coordinates in vertical ones), like the baselines of line boxes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Baselines {
+    pub first: CSSFloat,
+    pub last: CSSFloat,
+}
+
+/// The baselines of `layout_box` as it was last laid out, or `None` if it has none, in which case
+/// it's aligned by its margin edges instead.
+///
+/// The baselines of block containers are propagated up from their contents.  Per
+/// https://drafts.csswg.org/css-align-3/#baseline-export:
+///   > The first/last main-axis baseline set of a block container is generated from the alignment
+///     baseline of the first/last in-flow line box in the block container, or taken from the
+///     first/last in-flow block-level child in the block container that contributes a set of
+///     first/last baselines, whichever comes first/last. If there is no such line box or child,
+///     then the block container has no baseline set.
+///
+/// Absolutely positioned children are out of flow, so they contribute no baselines.  Block
+/// containers that skip their contents have no baselines, as if they were empty.  Form controls
+/// that show text have the baselines of their first and last lines of text (see
+/// `ReplacedBox::baselines`), while other replaced boxes have none.
+///
+/// TODO: Inline-level block containers (`inline-block`) aren't supported yet, as they're sized
+/// to fit their contents, which needs intrinsic sizes (see `handle_child_node_by_display`).
+/// Once they are, their baselines are those of the block containers here, except that they
+/// have none if their `overflow` isn't `visible` (see `leaf_baseline_extent`).
+///
+/// TODO: Flex containers should take their baselines from their first and last lines of flex
+/// items, once flex layout is supported.
+pub fn baselines(layout_box: &LayoutBox) -> Option<Baselines> {
+    match layout_box {
+        LayoutBox::BlockLevel(BlockLevelBox::Replaced(replaced_box))
+        | LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(InlineLevelBox::Replaced(
+            replaced_box,
+        ))) => replaced_box.baselines(),
+        LayoutBox::BlockLevel(block_level_box) if block_level_box.skips_contents() => None,
+        LayoutBox::BlockLevel(block_level_box) => {
+            let in_flow_children = block_level_box
+                .children()?
+                .iter()
+                .filter(|child| !child.is_absolutely_positioned());
+            Some(Baselines {
+                first: in_flow_children.clone().find_map(baselines)?.first,
+                last: in_flow_children.rev().find_map(baselines)?.last,
+            })
+        }
+        LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(
+            InlineLevelBox::AnonymousInline(root_inline_box),
+        )) => {
+            let writing_mode = root_inline_box.computed_values().writing_mode;
+            let mut lines = root_inline_box
+                .lines()
+                .iter()
+                .filter(|line| !line.is_phantom(writing_mode));
+            let first = lines.next()?.baseline();
+            let last = lines.last().map_or(first, |line| line.baseline());
+            Some(Baselines { first, last })
+        }
+        // Inline boxes and text runs are aligned within their lines, rather than by baselines of
+        // their own.
+        LayoutBox::InlineLevel(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::traits::*;
+    use crate::layout::geometry::box_geometry;
+    use crate::style::test_utils::laid_out_document;
+
+    /// The first box generated by the element named `name`, in tree order.
+    fn find<'a>(layout_box: &'a LayoutBox, name: &str) -> Option<&'a LayoutBox> {
+        let node = layout_box.node();
+        if node
+            .as_element()
+            .map_or(false, |element| &*element.name.local == name)
+        {
+            return Some(layout_box);
+        }
+        layout_box
+            .children()?
+            .iter()
+            .find_map(|child| find(child, name))
+    }
+
+    #[test]
+    fn baselines_propagate_from_lines_and_align_atomic_inlines() {
+        let (document, box_tree) = laid_out_document(
+            "<!DOCTYPE html><div><p>one</p><p><img style=\"width: 10px; height: 30px\">\
+             <textarea rows=3></textarea></p></div>",
+            "body, p { margin-top: 0px; margin-right: 0px } \
+             body, p { margin-bottom: 0px; margin-left: 0px } \
+             textarea { font-size: 10px }",
+        );
+
+        // The text area is below the first paragraph's 16px line of text.  Its last line of text
+        // is 2 * 12px down its content box, which is inside 1px of border and 1px of padding, and
+        // its baseline is 9px down that line.
+        let textarea = find(&box_tree, "textarea").unwrap();
+        assert_eq!(
+            baselines(textarea),
+            Some(Baselines {
+                first: 27.,
+                last: 51.
+            })
+        );
+        // Images have no baselines, so the image's bottom edge is aligned with the text area's
+        // last baseline, which is the baseline of the line.
+        let img = document.select_first("img").unwrap().as_node().clone();
+        assert_eq!(
+            box_geometry(&box_tree, &img).unwrap().margin_box.start_y,
+            21.
+        );
+        // The first baseline of the `<div>` is that of the first line in it, 0.8em down the test
+        // font's text, and its last that of the last line.
+        assert_eq!(
+            baselines(find(&box_tree, "div").unwrap()),
+            Some(Baselines {
+                first: 12.8,
+                last: 51.
+            })
+        );
+        assert_eq!(baselines(find(&box_tree, "img").unwrap()), None);
+    }
+
+    #[test]
+    fn absolutely_positioned_children_contribute_no_baselines() {
+        let (_, box_tree) = laid_out_document(
+            "<!DOCTYPE html><section><p><textarea rows=1></textarea></p>\
+             <aside><textarea rows=3></textarea></aside></section>",
+            "body, p { margin-top: 0px; margin-right: 0px } \
+             body, p { margin-bottom: 0px; margin-left: 0px } \
+             textarea { font-size: 10px } \
+             aside { position: absolute }",
+        );
+
+        // The `<aside>` is laid out below the `<p>`, where it would have gone if it were in flow,
+        // but the baselines of the `<section>` are still only those of the `<p>`.
+        let p_baselines = baselines(find(&box_tree, "p").unwrap()).unwrap();
+        let aside_baselines = baselines(find(&box_tree, "aside").unwrap()).unwrap();
+        assert!(aside_baselines.last > p_baselines.last);
+        assert_eq!(
+            baselines(find(&box_tree, "section").unwrap()),
+            Some(p_baselines)
+        );
+    }
+}
@@ -42,6 +42,18 @@ pub trait BaseLayoutBoxBehavior {
     fn formatting_context(&self) -> FormattingContextRef;
     fn is_root(&self) -> bool;
     fn node(&self) -> NodeRef;
+    /// Whether this box has to be laid out (again) if its containing block is `containing_block`.
+    fn needs_layout_in(&self, containing_block: ContainingBlock) -> bool;
+    fn mark_needs_layout(&mut self);
+    /// Whether a descendant of this box has to be laid out again, even though this box doesn't
+    /// (see `LayoutBox::mark_nodes_need_layout`).
+    fn descendant_needs_layout(&self) -> bool;
+    fn mark_descendant_needs_layout(&mut self);
+    fn mark_laid_out_in(&mut self, containing_block: ContainingBlock);
+    /// The containing block this box was last laid out in, if it was laid out.  Note that the
+    /// block size of the containing block of a box in normal flow is only its size so far, since
+    /// its containing block grows with the boxes laid out after it.
+    fn laid_out_in(&self) -> Option<ContainingBlock>;
 }
 
 #[macro_export]
@@ -76,5 +88,35 @@ macro_rules! layout_box_behavior_base_box_passthrough_impls {
         fn node(&self) -> NodeRef {
             self.base.node()
         }
+
+        #[inline(always)]
+        fn needs_layout_in(&self, containing_block: ContainingBlock) -> bool {
+            self.base.needs_layout_in(containing_block)
+        }
+
+        #[inline(always)]
+        fn mark_needs_layout(&mut self) {
+            self.base.mark_needs_layout()
+        }
+
+        #[inline(always)]
+        fn descendant_needs_layout(&self) -> bool {
+            self.base.descendant_needs_layout()
+        }
+
+        #[inline(always)]
+        fn mark_descendant_needs_layout(&mut self) {
+            self.base.mark_descendant_needs_layout()
+        }
+
+        #[inline(always)]
+        fn mark_laid_out_in(&mut self, containing_block: ContainingBlock) {
+            self.base.mark_laid_out_in(containing_block)
+        }
+
+        #[inline(always)]
+        fn laid_out_in(&self) -> Option<ContainingBlock> {
+            self.base.laid_out_in()
+        }
     };
 }
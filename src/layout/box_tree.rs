@@ -1,13 +1,20 @@
+use std::collections::HashSet;
+
+use crate::dom::details;
+use crate::dom::mutation::Mutation;
 use crate::dom::tree::{NodeData, NodeRef};
 use crate::layout::behavior::BaseLayoutBoxBehavior;
 use crate::layout::flow::block::{AnonymousBlockBox, BlockLevelBox};
-use crate::layout::flow::inline::{InlineBox, TextRun};
+use crate::layout::flow::inline::{InlineBox, InlineLevelBox, TextRun};
 use crate::layout::formatting_context::{
     FormattingContext, FormattingContextRef, QualifiedFormattingContext,
 };
 use crate::layout::layout_box::LayoutBox;
+use crate::layout::replaced::{is_replaced_element, ReplacedBox};
 use crate::style::values::computed::display::{DisplayBox, InnerDisplay, OuterDisplay};
-use crate::style::values::computed::Display;
+use crate::style::values::computed::{Display, WhiteSpace};
+use crate::style::Restyle;
+use tracing::info_span;
 
 /// Takes a DOM node and builds the corresponding box tree of it and its children.  Returns
 /// `None` if `node` is a `Display::None`.
@@ -26,6 +33,7 @@ pub fn build_box_tree(
     parent_context: Option<FormattingContextRef>,
 ) -> Option<LayoutBox> {
     if let NodeData::Document(_) = node.data() {
+        let _span = info_span!("build_box_tree").entered();
         // We don't want to create boxes for the document node nor the doctype nodes, so skip past
         // them to the root <html> element and start building the box tree there.
         //
@@ -49,7 +57,8 @@ pub fn build_box_tree(
     let mut layout_box = if let NodeData::Text(text) = node.data() {
         // https://drafts.csswg.org/css-display-3/#flow-layout
         // > If the [text] sequence contains no text, however, it does not generate a text run.
-        let contents = text.clone().take().trim().to_owned();
+        let contents =
+            collapse_white_space(&text.clone().take(), node.computed_values().white_space);
         if contents.is_empty() {
             return None;
         }
@@ -63,11 +72,22 @@ pub fn build_box_tree(
         }
     };
 
-    for child in node.children() {
+    // Replaced elements render their content themselves (e.g. the label of a `<button>`), so their
+    // children don't generate boxes.
+    if is_replaced_element(&node) {
+        return Some(layout_box);
+    }
+
+    // Shadow hosts render their shadow tree, in which slots render the host's children.
+    for child in node.flat_tree_children() {
+        if !details::is_rendered_child(&child) {
+            continue;
+        }
         if let NodeData::Text(text) = child.data() {
             // https://drafts.csswg.org/css-display-3/#flow-layout
             // > If the [text] sequence contains no text, however, it does not generate a text run.
-            let contents = text.clone().take().trim().to_owned();
+            let contents =
+                collapse_white_space(&text.clone().take(), child.computed_values().white_space);
             if contents.is_empty() {
                 continue;
             }
@@ -78,7 +98,7 @@ pub fn build_box_tree(
                 TextRun::new(
                     child.clone(),
                     inline_container.formatting_context(),
-                    text.clone().take().trim().to_owned(),
+                    contents,
                 )
                 .into(),
             );
@@ -89,25 +109,165 @@ pub fn build_box_tree(
     Some(layout_box)
 }
 
+/// Brings `box_tree`, which was built from `document`, up to date with the DOM `mutations` and
+/// the `restyle` they caused, reusing as much of it as possible.
+///
+/// Changes that affect which boxes are generated (e.g. inserting nodes or changing `display`)
+/// rebuild the boxes of the nearest block container enclosing them, which is the smallest subtree
+/// whose boxes don't depend on its siblings.  The boxes of every other changed node are kept, but
+/// marked as needing layout, so that only they and their ancestors are laid out again.
+pub fn update_box_tree(
+    box_tree: &mut Option<LayoutBox>,
+    document: &NodeRef,
+    mutations: &[Mutation],
+    restyle: &Restyle,
+) {
+    let _span = info_span!("update_box_tree").entered();
+    let layout_root_box = match box_tree {
+        Some(layout_root_box) => layout_root_box,
+        None => {
+            // The root element didn't generate a box before, but may now.
+            *box_tree = build_box_tree(document.clone(), None);
+            return;
+        }
+    };
+    // The nodes whose parents' boxes depend on them.
+    let restructured_children = mutations
+        .iter()
+        .filter_map(|mutation| match mutation {
+            Mutation::ChildList { parent, .. } => Some(parent.clone()),
+            Mutation::CharacterData { node } => node.parent(),
+            // Whether an element is replaced can depend on its `type`.
+            Mutation::Attribute { element, name, .. } if *name == local_name!("type") => {
+                element.parent()
+            }
+            // Opening or closing a `<details>` element shows or hides its children.
+            Mutation::Attribute { element, name, .. } if *name == local_name!("open") => {
+                Some(element.clone())
+            }
+            Mutation::Attribute { .. } | Mutation::State { .. } => None,
+        })
+        .chain(
+            restyle
+                .display_changed_nodes
+                .iter()
+                .filter_map(|node| node.parent()),
+        );
+    let mut rebuild_roots = Vec::new();
+    for node in restructured_children {
+        match node.inclusive_ancestors().find(generates_block_container) {
+            Some(rebuild_root) => rebuild_roots.push(rebuild_root),
+            None => {
+                *box_tree = build_box_tree(document.clone(), None);
+                return;
+            }
+        }
+    }
+    // Rebuilding a block container rebuilds everything inside it, too.
+    let outermost_rebuild_roots = rebuild_roots
+        .iter()
+        .filter(|root| {
+            !root
+                .ancestors()
+                .any(|ancestor| rebuild_roots.contains(&ancestor))
+        })
+        .cloned()
+        .collect::<HashSet<_>>();
+    for rebuild_root in &outermost_rebuild_roots {
+        if !rebuild_block_container(layout_root_box, rebuild_root, None) {
+            *box_tree = build_box_tree(document.clone(), None);
+            return;
+        }
+    }
+
+    // Rebuilt boxes need layout, and so do the boxes of changed nodes.  Attributes can change the
+    // size of replaced elements without changing their styles (e.g. the `size` of an `<input>`).
+    let mut changed_nodes = outermost_rebuild_roots;
+    changed_nodes.extend(restyle.restyled_nodes.iter().cloned());
+    changed_nodes.extend(mutations.iter().map(|mutation| mutation.target().clone()));
+    layout_root_box.mark_nodes_need_layout(&changed_nodes);
+}
+
+/// Whether `node` generates a block-level block container, per its computed `display`.
+fn generates_block_container(node: &NodeRef) -> bool {
+    if node.as_element().is_none() || is_replaced_element(node) {
+        return false;
+    }
+    match node.computed_values().display {
+        Display::Full(full_display) => full_display.outer() == OuterDisplay::Block,
+        Display::Box(DisplayBox::None) => false,
+    }
+}
+
+/// Replaces the block container generated by `node` within the subtree of `layout_box` with a
+/// newly built one.  Returns whether the block container was found and replaced.
+///
+/// `parent_context` is the formatting context `layout_box` was built to participate in.
+fn rebuild_block_container(
+    layout_box: &mut LayoutBox,
+    node: &NodeRef,
+    parent_context: Option<FormattingContextRef>,
+) -> bool {
+    if let LayoutBox::BlockLevel(BlockLevelBox::BlockContainer(_)) = layout_box {
+        if layout_box.node() == *node {
+            return match build_box_tree(node.clone(), parent_context) {
+                Some(rebuilt_box) => {
+                    *layout_box = rebuilt_box;
+                    true
+                }
+                None => false,
+            };
+        }
+    }
+    let formatting_context = layout_box.formatting_context();
+    match layout_box.children_mut() {
+        Some(children) => children
+            .iter_mut()
+            .filter(|child| {
+                node.inclusive_ancestors()
+                    .any(|ancestor| ancestor == child.node())
+            })
+            .any(|child| rebuild_block_container(child, node, Some(formatting_context.clone()))),
+        None => false,
+    }
+}
+
 fn handle_child_node_by_display(parent_box: &mut LayoutBox, child_node: NodeRef) {
     let child_computed_values = &*child_node.computed_values();
     match child_computed_values.display {
         Display::Full(full_display) => {
             match (full_display.outer(), full_display.inner()) {
+                // Absolutely positioned boxes are out of flow, so rather than splitting the
+                // inline-level content around them like other block-level boxes, they're kept
+                // among it, at their static position in it.
+                (OuterDisplay::Block, InnerDisplay::Flow)
+                | (OuterDisplay::Block, InnerDisplay::FlowRoot)
+                    if child_computed_values.position.is_absolutely_positioned()
+                        && continues_inline_content(parent_box) =>
+                {
+                    let inline_container =
+                        get_or_create_inline_container(parent_box, child_node.clone());
+                    if let Some(child_box) = build_box_tree(
+                        child_node.clone(),
+                        Some(inline_container.formatting_context()),
+                    ) {
+                        inline_container.add_child(child_box)
+                    }
+                }
                 (OuterDisplay::Block, InnerDisplay::Flow)
                 | (OuterDisplay::Block, InnerDisplay::FlowRoot) => {
                     if let Some(child_box) =
                         build_box_tree(child_node.clone(), Some(parent_box.formatting_context()))
                     {
-                        // TODO: We don't handle the case where a block-flow child box is added to an inline box.
-                        // This current behavior is wrong — we should be checking if `node` is an `Display::Inline` and
-                        // doing something different here.  To fix, see: https://www.w3.org/TR/CSS2/visuren.html#box-gen
-                        // Namely, the paragraph that begins with "When an inline box contains an in-flow block-level box"
-                        // This concept _might_ be called "fragmenting".
+                        // Block-level boxes added to inline boxes are moved out of them once the
+                        // outermost inline box is added to its block container (see
+                        // `split_around_block_level_boxes`).
                         parent_box.add_child(child_box)
                     }
                 }
-                (OuterDisplay::Inline, InnerDisplay::Flow) => {
+                (OuterDisplay::Inline, InnerDisplay::FlowRoot)
+                    if is_replaced_element(&child_node) =>
+                {
                     let inline_container =
                         get_or_create_inline_container(parent_box, child_node.clone());
                     if let Some(child_box) = build_box_tree(
@@ -117,7 +277,38 @@ fn handle_child_node_by_display(parent_box: &mut LayoutBox, child_node: NodeRef)
                         inline_container.add_child(child_box)
                     }
                 }
-                (OuterDisplay::Inline, InnerDisplay::FlowRoot) => unimplemented!(),
+                // TODO: Inline-level block containers aren't supported yet, so elements that
+                // aren't replaced but are `inline-block` (such as `<button>` in SVG, or in XML
+                // documents without the HTML namespace) are laid out as inline boxes instead.
+                (OuterDisplay::Inline, InnerDisplay::Flow)
+                | (OuterDisplay::Inline, InnerDisplay::FlowRoot) => {
+                    let in_inline_box = matches!(parent_box, LayoutBox::InlineLevel(_));
+                    let formatting_context =
+                        get_or_create_inline_container(parent_box, child_node.clone())
+                            .formatting_context();
+                    let child_box =
+                        match build_box_tree(child_node.clone(), Some(formatting_context)) {
+                            Some(child_box) => child_box,
+                            None => return,
+                        };
+                    // Inline boxes within inline boxes are split along with the outermost one.
+                    let pieces = if in_inline_box {
+                        vec![SplitPiece::InlineLevel(child_box)]
+                    } else {
+                        split_around_block_level_boxes(child_box)
+                    };
+                    // Each run of inline-level content between the block-level boxes is wrapped in
+                    // an anonymous block box of its own.
+                    for piece in pieces {
+                        match piece {
+                            SplitPiece::InlineLevel(inline_box) => {
+                                get_or_create_inline_container(parent_box, child_node.clone())
+                                    .add_child(inline_box)
+                            }
+                            SplitPiece::BlockLevel(block_box) => parent_box.add_child(block_box),
+                        }
+                    }
+                }
             }
         }
         Display::Box(DisplayBox::None) => {}
@@ -153,7 +344,18 @@ fn build_box_from_display(
                         // There is no parent formatting context -- create a new BFC.
                         _ => FormattingContextRef::new_independent_block(),
                     };
-                    BlockLevelBox::new_block_container(node.clone(), formatting_context).into()
+                    if is_replaced_element(&node) {
+                        BlockLevelBox::new_replaced(node.clone(), formatting_context).into()
+                    } else {
+                        BlockLevelBox::new_block_container(node.clone(), formatting_context).into()
+                    }
+                }
+                (OuterDisplay::Block, InnerDisplay::FlowRoot) if is_replaced_element(&node) => {
+                    BlockLevelBox::new_replaced(
+                        node.clone(),
+                        FormattingContextRef::new_independent_block(),
+                    )
+                    .into()
                 }
                 (OuterDisplay::Block, InnerDisplay::FlowRoot) => {
                     BlockLevelBox::new_block_container(
@@ -162,7 +364,27 @@ fn build_box_from_display(
                     )
                     .into()
                 }
-                (OuterDisplay::Inline, InnerDisplay::Flow) => {
+                // Replaced elements are atomic inlines whether their inner display type is `flow` or
+                // `flow-root`, since their contents aren't laid out by CSS.
+                (OuterDisplay::Inline, InnerDisplay::Flow)
+                | (OuterDisplay::Inline, InnerDisplay::FlowRoot)
+                    if is_replaced_element(&node) =>
+                {
+                    let formatting_context = parent_context
+                        .filter(|parent_context| parent_context.is_inline_formatting_context())
+                        .expect("atomic inlines must be added to an inline formatting context");
+                    LayoutBox::InlineLevel(
+                        InlineLevelBox::Replaced(ReplacedBox::new(
+                            node.clone(),
+                            formatting_context,
+                        ))
+                        .into(),
+                    )
+                }
+                // Elements that aren't replaced are laid out as inline boxes even if they're
+                // `inline-block` (see `handle_child_node_by_display`).
+                (OuterDisplay::Inline, InnerDisplay::Flow)
+                | (OuterDisplay::Inline, InnerDisplay::FlowRoot) => {
                     let formatting_context = match parent_context.clone() {
                         Some(rc_qfc) => {
                             match *rc_qfc {
@@ -179,13 +401,51 @@ fn build_box_from_display(
                     };
                     InlineBox::new(node.clone(), formatting_context).into()
                 }
-                (OuterDisplay::Inline, InnerDisplay::FlowRoot) => unimplemented!(),
             }
         }
         Display::Box(DisplayBox::None) => return None,
     })
 }
 
+/// The contents of the text run generated by a text node with the text `text` and the given
+/// `white-space`: its sequences of white space collapsed into single spaces, with any at its start
+/// or end removed.  Text whose spaces are preserved is kept as it is, and text whose newlines are
+/// preserved has each of its lines collapsed separately.
+///
+/// TODO: White space between text runs is removed too, rather than collapsed across them.
+///
+/// https://drafts.csswg.org/css-text-3/#white-space-phase-1
+fn collapse_white_space(text: &str, white_space: WhiteSpace) -> String {
+    let collapse = |text: &str| text.split_ascii_whitespace().collect::<Vec<_>>().join(" ");
+    if white_space.preserves_spaces() {
+        text.to_owned()
+    } else if white_space.preserves_newlines() {
+        text.split('\n')
+            .map(collapse)
+            .collect::<Vec<_>>()
+            .join("\n")
+    } else {
+        collapse(text)
+    }
+}
+
+/// Whether the next child added to `parent_box` would be among inline-level content: either
+/// `parent_box` is an inline box, or it's a block container that has no children yet or whose
+/// last child is the anonymous block holding its inline-level content so far.
+fn continues_inline_content(parent_box: &LayoutBox) -> bool {
+    match parent_box {
+        LayoutBox::InlineLevel(_) => true,
+        LayoutBox::BlockLevel(_) => parent_box.children().map_or(false, |children| {
+            children.last().map_or(true, |last_child| {
+                matches!(
+                    last_child,
+                    LayoutBox::BlockLevel(BlockLevelBox::AnonymousBlock(_))
+                )
+            })
+        }),
+    }
+}
+
 fn get_or_create_inline_container(
     layout_box: &mut LayoutBox,
     node_for_container: NodeRef,
@@ -199,6 +459,79 @@ fn get_or_create_inline_container(
     layout_box.get_mut_inline_container().unwrap()
 }
 
+/// A piece of an inline box split by `split_around_block_level_boxes`.
+enum SplitPiece {
+    /// A part of the inline box, holding the inline-level content between two block-level boxes.
+    InlineLevel(LayoutBox),
+    BlockLevel(LayoutBox),
+}
+
+/// Splits `inline_box` around the block-level boxes inside it (or inside the inline boxes within
+/// it), returning the pieces in tree order: the block-level boxes, and before, between, and after
+/// them, parts of `inline_box` holding the inline-level content between them.  Boxes without
+/// block-level boxes inside them are returned whole.
+///
+/// Absolutely positioned boxes are out of flow, so they don't split inline boxes, and stay in the
+/// parts holding the inline-level content around them.
+///
+/// https://www.w3.org/TR/CSS2/visuren.html#anonymous-block-level
+/// > When an inline box contains an in-flow block-level box, the inline box (and its inline
+/// > ancestors within the same line box) are broken around the block-level box (and any
+/// > block-level siblings that are consecutive or separated only by collapsible whitespace and/or
+/// > out-of-flow elements), splitting the inline box into two boxes (even if either side is
+/// > empty), one on each side of the block-level box(es).
+fn split_around_block_level_boxes(mut inline_box: LayoutBox) -> Vec<SplitPiece> {
+    if !contains_block_level_box(&inline_box) {
+        return vec![SplitPiece::InlineLevel(inline_box)];
+    }
+    let node = inline_box.node();
+    let formatting_context = inline_box.formatting_context();
+    let new_part =
+        || -> LayoutBox { InlineBox::new(node.clone(), formatting_context.clone()).into() };
+    let children = std::mem::take(
+        inline_box
+            .children_mut()
+            .expect("boxes containing block-level boxes have children"),
+    );
+    let mut pieces = Vec::new();
+    let mut part: Option<LayoutBox> = Some(inline_box);
+    for child in children {
+        let child_pieces = match child {
+            LayoutBox::BlockLevel(_) if child.is_absolutely_positioned() => {
+                vec![SplitPiece::InlineLevel(child)]
+            }
+            LayoutBox::BlockLevel(_) => vec![SplitPiece::BlockLevel(child)],
+            LayoutBox::InlineLevel(_) => split_around_block_level_boxes(child),
+        };
+        for piece in child_pieces {
+            match piece {
+                SplitPiece::InlineLevel(inline_level_box) => part
+                    .get_or_insert_with(new_part)
+                    .add_child(inline_level_box),
+                SplitPiece::BlockLevel(block_box) => {
+                    if let Some(part) = part.take() {
+                        pieces.push(SplitPiece::InlineLevel(part));
+                    }
+                    pieces.push(SplitPiece::BlockLevel(block_box));
+                }
+            }
+        }
+    }
+    pieces.push(SplitPiece::InlineLevel(part.unwrap_or_else(new_part)));
+    pieces
+}
+
+/// Whether any of the children of `layout_box`, or of the inline boxes within it, are in-flow
+/// block-level boxes.
+fn contains_block_level_box(layout_box: &LayoutBox) -> bool {
+    layout_box.children().map_or(false, |children| {
+        children.iter().any(|child| match child {
+            LayoutBox::BlockLevel(_) => !child.is_absolutely_positioned(),
+            LayoutBox::InlineLevel(_) => contains_block_level_box(child),
+        })
+    })
+}
+
 fn create_inline_container(node: NodeRef) -> LayoutBox {
     // Create a new IFC for this inline content.
     let mut anonymous_block_box =
@@ -209,3 +542,491 @@ fn create_inline_container(node: NodeRef) -> LayoutBox {
     ));
     anonymous_block_box.into()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::mutation;
+    use crate::dom::traits::*;
+    use crate::layout::DumpLayoutVerbosity;
+    use crate::layout::{global_layout, DumpLayout};
+    use crate::page::user_agent_stylesheet;
+    use crate::style::invalidation::InvalidationMap;
+    use crate::style::media::{Device, MediaType};
+    use crate::style::stylesheet::{parse_css_to_stylesheet, Stylesheet};
+    use crate::style::test_utils::{lay_out, styled_document, test_device};
+    use crate::style::{recompute_values, restyle_mutations};
+    use html5ever::LocalName;
+
+    struct TestDocument {
+        document: NodeRef,
+        ua_sheets: Vec<Stylesheet>,
+        author_sheets: Vec<Stylesheet>,
+        box_tree: Option<LayoutBox>,
+    }
+
+    impl TestDocument {
+        fn new(html: &str, css: &str) -> TestDocument {
+            let document = styled_document(html, css);
+            let box_tree = laid_out_tree(&document);
+            TestDocument {
+                document,
+                // The sheets `styled_document` styles with, to restyle with after mutations.
+                ua_sheets: vec![user_agent_stylesheet()],
+                author_sheets: vec![parse_css_to_stylesheet(None, &mut css.to_owned())],
+                box_tree,
+            }
+        }
+
+        /// Restyles and lays out the document again after it has been mutated.
+        fn update(&mut self) -> Restyle {
+            let mutations = mutation::take_mutations(&self.document);
+            let restyle = restyle_mutations(
+                &self.document,
+                &mutations,
+                &InvalidationMap::new(self.ua_sheets.iter().chain(self.author_sheets.iter())),
+                &test_device(),
+                &self.ua_sheets,
+                &[],
+                &self.author_sheets,
+            );
+            update_box_tree(&mut self.box_tree, &self.document, &mutations, &restyle);
+            if let Some(box_tree) = &mut self.box_tree {
+                lay_out(box_tree);
+            }
+            restyle
+        }
+
+        /// Asserts that the box tree is laid out the same as one built from scratch.
+        fn assert_matches_full_layout(&self) {
+            assert_eq!(
+                self.box_tree.as_ref().map(dump),
+                laid_out_tree(&self.document).as_ref().map(dump)
+            );
+        }
+    }
+
+    fn dump(box_tree: &LayoutBox) -> String {
+        let mut dump = Vec::new();
+        box_tree.dump_layout(&mut dump, 0, DumpLayoutVerbosity::Verbose);
+        String::from_utf8(dump).unwrap()
+    }
+
+    fn laid_out_tree(document: &NodeRef) -> Option<LayoutBox> {
+        let mut box_tree = build_box_tree(document.clone(), None);
+        if let Some(box_tree) = &mut box_tree {
+            lay_out(box_tree);
+        }
+        box_tree
+    }
+
+    fn set_attribute(element: &NodeRef, name: LocalName, value: &str) {
+        let old_value = element
+            .as_element()
+            .unwrap()
+            .attributes
+            .borrow_mut()
+            .insert(name.clone(), value.to_owned())
+            .map(|attribute| attribute.value);
+        mutation::record(Mutation::Attribute {
+            element: element.clone(),
+            name,
+            old_value,
+        });
+    }
+
+    fn select(document: &NodeRef, selector: &str) -> NodeRef {
+        document.select_first(selector).unwrap().as_node().clone()
+    }
+
+    #[test]
+    fn very_verbose_dumps_include_lines_and_text_fragments() {
+        let test_document = TestDocument::new(
+            "<p>Hello</p>",
+            "body, p { margin-top: 0px; margin-right: 0px; margin-bottom: 0px; margin-left: 0px }",
+        );
+        let mut dump = Vec::new();
+        test_document
+            .box_tree
+            .unwrap()
+            .dump_layout(&mut dump, 0, DumpLayoutVerbosity::VeryVerbose);
+        let dump = String::from_utf8(dump).unwrap();
+        let lines = dump.lines().collect::<Vec<_>>();
+        // The line boxes of the root inline box come right after it, before its children.
+        let root_inline_box = lines
+            .iter()
+            .position(|line| line.trim_start().starts_with("AnonymousInlineBox"))
+            .unwrap();
+        assert_eq!(
+            lines[root_inline_box + 1..root_inline_box + 3],
+            [
+                "          LineBox at (0, 0) size 800x16 baseline 12.8",
+                "            TextFragment \"Hello\" chars 0..5 at (0, 0) size 80x16",
+            ]
+        );
+    }
+
+    #[test]
+    fn preformatted_text_keeps_its_tabs_and_newlines() {
+        let test_document = TestDocument::new(
+            "<pre>a\tb\ncd</pre>",
+            "body, pre { margin-top: 0px; margin-right: 0px; margin-bottom: 0px; margin-left: 0px }
+             pre { tab-size: 4 }",
+        );
+        let mut dump = Vec::new();
+        test_document
+            .box_tree
+            .unwrap()
+            .dump_layout(&mut dump, 0, DumpLayoutVerbosity::VeryVerbose);
+        let dump = String::from_utf8(dump).unwrap();
+        let text_fragments = dump
+            .lines()
+            .map(str::trim_start)
+            .filter(|line| line.starts_with("TextFragment"))
+            .collect::<Vec<_>>();
+        // The tab advances "b" to the tab stop 4 spaces in, and the newline starts a new line.
+        assert_eq!(
+            text_fragments,
+            [
+                "TextFragment \"a\\tb\" chars 0..3 at (0, 0) size 80x16",
+                "TextFragment \"cd\" chars 4..6 at (0, 16) size 32x16",
+            ]
+        );
+    }
+
+    #[test]
+    fn inline_boxes_are_laid_out_around_their_contents_with_their_edges() {
+        let test_document = TestDocument::new(
+            "<p><span style=\"padding-top: 3px; padding-right: 5px; padding-bottom: 3px; \
+             padding-left: 5px; border-left-width: 2px; border-left-style: solid\"></span>\
+             <span style=\"margin-left: 10px; margin-top: 20px\">\
+             <span style=\"padding-right: 4px\">text</span></span></p>",
+            "body, p { margin-top: 0px; margin-right: 0px; margin-bottom: 0px; margin-left: 0px }",
+        );
+        let mut dump = Vec::new();
+        test_document
+            .box_tree
+            .unwrap()
+            .dump_layout(&mut dump, 0, DumpLayoutVerbosity::VeryVerbose);
+        let dump = String::from_utf8(dump).unwrap();
+        let lines = dump.lines().collect::<Vec<_>>();
+        let root_inline_box = lines
+            .iter()
+            .position(|line| line.trim_start().starts_with("AnonymousInlineBox"))
+            .unwrap();
+        // The empty span still takes up its inline-axis edges, and its block-axis padding sticks
+        // out around the baseline without making the line taller.  Block-axis margins don't apply
+        // at all.
+        assert_eq!(
+            lines[root_inline_box + 1..root_inline_box + 6],
+            [
+                "          LineBox at (0, 0) size 800x16 baseline 12.8",
+                "            InlineBoxFragment SPAN at (0, 9.8) size 12x6",
+                "            InlineBoxFragment SPAN at (22, 0) size 68x16",
+                "            InlineBoxFragment SPAN at (22, 0) size 68x16",
+                "            TextFragment \"text\" chars 0..4 at (22, 0) size 64x16",
+            ]
+        );
+    }
+
+    #[test]
+    fn inline_boxes_are_fragmented_across_lines() {
+        let test_document = TestDocument::new(
+            "<p><span style=\"padding-left: 3px; padding-right: 3px; border-left-width: 2px; \
+             border-left-style: solid; border-right-width: 2px; border-right-style: solid\">\
+             <img style=\"width: 500px; height: 10px\"><img style=\"width: 500px; height: 20px\">\
+             </span></p>",
+            "body, p { margin-top: 0px; margin-right: 0px; margin-bottom: 0px; margin-left: 0px }",
+        );
+        let mut dump = Vec::new();
+        test_document
+            .box_tree
+            .unwrap()
+            .dump_layout(&mut dump, 0, DumpLayoutVerbosity::VeryVerbose);
+        let dump = String::from_utf8(dump).unwrap();
+        let lines = dump.lines().collect::<Vec<_>>();
+        let root_inline_box = lines
+            .iter()
+            .position(|line| line.trim_start().starts_with("AnonymousInlineBox"))
+            .unwrap();
+        // Each fragment only has the inline-axis edges on the side the span starts or ends on.
+        assert_eq!(
+            lines[root_inline_box + 1..root_inline_box + 5],
+            [
+                "          LineBox at (0, 0) size 800x10 baseline 10",
+                "            InlineBoxFragment SPAN at (0, 0) size 505x10",
+                "          LineBox at (0, 10) size 800x20 baseline 30",
+                "            InlineBoxFragment SPAN at (0, 10) size 505x20",
+            ]
+        );
+    }
+
+    #[test]
+    fn verbose_dumps_identify_the_element_of_each_box() {
+        let test_document = TestDocument::new(
+            "<div id=main class=\"card  wide\"><p>Hello</p></div>",
+            "body, p { margin-top: 0px; margin-right: 0px; margin-bottom: 0px; margin-left: 0px }",
+        );
+        let dump = dump(test_document.box_tree.as_ref().unwrap());
+        let lines = dump.lines().map(str::trim_start).collect::<Vec<_>>();
+        assert!(lines[0].starts_with("HTML BlockContainer at (0, 0)"));
+        assert!(lines[0].contains(" path \"html\" (ml0 "));
+        let path = |label| {
+            let line = lines.iter().find(|line| line.starts_with(label)).unwrap();
+            line.split('"').nth(1).unwrap()
+        };
+        assert_eq!(path("DIV"), "html > body > div#main.card.wide");
+        // Anonymous boxes and text belong to the element they're in.
+        assert_eq!(
+            path("AnonymousInlineBox"),
+            "html > body > div#main.card.wide > p"
+        );
+        assert!(lines.iter().any(|line| line
+            .contains("path \"html > body > div#main.card.wide > p\"")
+            && line.starts_with("TEXT")));
+    }
+
+    #[test]
+    fn inline_boxes_are_split_around_the_block_level_boxes_inside_them() {
+        let test_document = TestDocument::new(
+            "<div>one<span>two<p>three</p><em>four<p>five</p></em>six</span>seven<p>eight</p>\
+             nine</div>",
+            "",
+        );
+        let div = test_document.box_tree.as_ref().unwrap().children().unwrap()[0]
+            .children()
+            .unwrap()[0]
+            .clone();
+        let mut dump = Vec::new();
+        div.dump_layout(&mut dump, 0, DumpLayoutVerbosity::NonVerbose);
+        let boxes = String::from_utf8(dump)
+            .unwrap()
+            .lines()
+            .map(|line| line.split(" at (").next().unwrap().to_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            boxes,
+            [
+                "DIV BlockContainer",
+                "  AnonymousBlockBox",
+                "    AnonymousInlineBox",
+                "      TEXT \"one\" TextRun",
+                "      SPAN InlineBox",
+                "        TEXT \"two\" TextRun",
+                "  P BlockContainer",
+                "    AnonymousBlockBox",
+                "      AnonymousInlineBox",
+                "        TEXT \"three\" TextRun",
+                // The parts of the span after the first paragraph hold parts of the em.
+                "  AnonymousBlockBox",
+                "    AnonymousInlineBox",
+                "      SPAN InlineBox",
+                "        EM InlineBox",
+                "          TEXT \"four\" TextRun",
+                "  P BlockContainer",
+                "    AnonymousBlockBox",
+                "      AnonymousInlineBox",
+                "        TEXT \"five\" TextRun",
+                "  AnonymousBlockBox",
+                "    AnonymousInlineBox",
+                "      SPAN InlineBox",
+                "        EM InlineBox",
+                "        TEXT \"six\" TextRun",
+                "      TEXT \"seven\" TextRun",
+                "  P BlockContainer",
+                "    AnonymousBlockBox",
+                "      AnonymousInlineBox",
+                "        TEXT \"eight\" TextRun",
+                "  AnonymousBlockBox",
+                "    AnonymousInlineBox",
+                "      TEXT \"nine\" TextRun",
+            ]
+        );
+    }
+
+    #[test]
+    fn absolutely_positioned_boxes_stay_among_the_inline_level_content_around_them() {
+        let test_document = TestDocument::new(
+            "<div>a <span>b</span> c<span>d<em>e</em>f</span></div>",
+            "span, em { position: absolute }",
+        );
+        let div = test_document.box_tree.as_ref().unwrap().children().unwrap()[0]
+            .children()
+            .unwrap()[0]
+            .clone();
+        let mut dump = Vec::new();
+        div.dump_layout(&mut dump, 0, DumpLayoutVerbosity::NonVerbose);
+        let boxes = String::from_utf8(dump)
+            .unwrap()
+            .lines()
+            .map(|line| line.split(" at (").next().unwrap().to_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            boxes,
+            [
+                "DIV BlockContainer",
+                "  AnonymousBlockBox",
+                "    AnonymousInlineBox",
+                "      TEXT \"a\" TextRun",
+                "      SPAN BlockContainer",
+                "        AnonymousBlockBox",
+                "          AnonymousInlineBox",
+                "            TEXT \"b\" TextRun",
+                "      TEXT \"c\" TextRun",
+                "      SPAN BlockContainer",
+                "        AnonymousBlockBox",
+                "          AnonymousInlineBox",
+                "            TEXT \"d\" TextRun",
+                "            EM BlockContainer",
+                "              AnonymousBlockBox",
+                "                AnonymousInlineBox",
+                "                  TEXT \"e\" TextRun",
+                "            TEXT \"f\" TextRun",
+            ]
+        );
+    }
+
+    #[test]
+    fn elements_that_are_inline_block_but_not_replaced_are_laid_out_as_inline_boxes() {
+        // The button is in the MathML namespace, so it isn't a form control, but it's still
+        // `inline-block` since the user agent stylesheet matches elements in any namespace.
+        let test_document = TestDocument::new("<p><math><button>go</button></math></p>", "");
+        let p = test_document.box_tree.as_ref().unwrap().children().unwrap()[0]
+            .children()
+            .unwrap()[0]
+            .clone();
+        let mut dump = Vec::new();
+        p.dump_layout(&mut dump, 0, DumpLayoutVerbosity::NonVerbose);
+        let boxes = String::from_utf8(dump)
+            .unwrap()
+            .lines()
+            .map(|line| line.split(" at (").next().unwrap().to_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            boxes,
+            [
+                "P BlockContainer",
+                "  AnonymousBlockBox",
+                "    AnonymousInlineBox",
+                "      MATH InlineBox",
+                "        BUTTON InlineBox",
+                "          TEXT \"go\" TextRun",
+            ]
+        );
+    }
+
+    #[test]
+    fn incremental_layout_matches_full_layout() {
+        let mut test_document = TestDocument::new(
+            "<div id=a></div><div id=b></div><div id=c></div>",
+            "body { margin-top: 0px; margin-right: 0px; margin-bottom: 0px; margin-left: 0px } \
+             div { height: 10px }",
+        );
+        let b = select(&test_document.document, "#b");
+        set_attribute(&b, local_name!("style"), "height: 30px");
+        let restyle = test_document.update();
+        assert_eq!(restyle.restyled_nodes.len(), 1);
+        assert!(restyle.display_changed_nodes.is_empty());
+        test_document.assert_matches_full_layout();
+    }
+
+    #[test]
+    fn changes_inside_boxes_that_isolate_their_layout_only_lay_out_those_boxes() {
+        let mut test_document = TestDocument::new(
+            "<div id=a><div id=b></div></div><div id=c></div>",
+            "body { margin-top: 0px; margin-right: 0px; margin-bottom: 0px; margin-left: 0px } \
+             div { height: 10px } #a { contain: strict; height: 20px }",
+        );
+        let b = select(&test_document.document, "#b");
+        let box_tree = test_document.box_tree.as_mut().unwrap();
+        box_tree.mark_nodes_need_layout(&vec![b.clone()].into_iter().collect());
+        let needs_layout =
+            |layout_box: &LayoutBox| layout_box.needs_layout_in(layout_box.laid_out_in().unwrap());
+        let body = &box_tree.children().unwrap()[0];
+        let a = &body.children().unwrap()[0];
+        assert!(needs_layout(a));
+        assert!(!needs_layout(body));
+        assert!(body.descendant_needs_layout());
+        assert!(!needs_layout(&body.children().unwrap()[1]));
+
+        set_attribute(&b, local_name!("style"), "height: 30px");
+        test_document.update();
+        test_document.assert_matches_full_layout();
+        let box_tree = test_document.box_tree.as_ref().unwrap();
+        assert!(!box_tree.children().unwrap()[0].descendant_needs_layout());
+    }
+
+    #[test]
+    fn patches_boxes_of_changed_subtrees() {
+        let mut test_document = TestDocument::new(
+            "<div id=a><span>text</span></div><div id=b></div>",
+            "div { height: 10px } .hidden { display: none }",
+        );
+        let a = select(&test_document.document, "#a");
+        let inserted = NodeRef::new_text("more text");
+        a.append(inserted.clone());
+        mutation::record(Mutation::ChildList {
+            parent: a,
+            added: vec![inserted],
+        });
+        test_document.update();
+        test_document.assert_matches_full_layout();
+
+        let b = select(&test_document.document, "#b");
+        set_attribute(&b, local_name!("class"), "hidden");
+        let restyle = test_document.update();
+        assert!(restyle.display_changed_nodes.contains(&b));
+        test_document.assert_matches_full_layout();
+    }
+
+    #[test]
+    fn closed_details_only_render_their_summary() {
+        let mut test_document = TestDocument::new(
+            "<details><summary id=first>Summary</summary><p>Contents</p>\
+             <summary id=second>Not the summary</summary>More contents</details>",
+            "",
+        );
+        let dump_of = |test_document: &TestDocument| dump(test_document.box_tree.as_ref().unwrap());
+        let closed = dump_of(&test_document);
+        assert!(closed.contains("SUMMARY"));
+        assert!(!closed.contains("P Block"));
+        assert!(!closed.contains("More contents"));
+
+        let summary = select(&test_document.document, "#first");
+        assert!(details::toggle(&summary));
+        test_document.update();
+        test_document.assert_matches_full_layout();
+        let open = dump_of(&test_document);
+        assert!(open.contains("P Block"));
+        assert!(open.contains("More contents"));
+
+        // Only the first summary toggles its details.
+        assert!(!details::toggle(&select(
+            &test_document.document,
+            "#second"
+        )));
+        assert!(details::toggle(&summary));
+        test_document.update();
+        test_document.assert_matches_full_layout();
+        assert_eq!(dump_of(&test_document), closed);
+    }
+
+    #[test]
+    fn resizing_the_viewport_relays_out_viewport_percentage_lengths() {
+        let mut test_document = TestDocument::new(
+            "<div id=a></div><div id=b></div>",
+            "body { margin-top: 0px; margin-right: 0px; margin-bottom: 0px; margin-left: 0px } \
+             #a { height: 50vh } #b { height: 10px }",
+        );
+        let document = test_document.document.clone();
+        let restyle = recompute_values(&document, &Device::new(MediaType::Screen, 400., 300.));
+        assert_eq!(restyle.restyled_nodes.len(), 1);
+        assert!(restyle.restyled_nodes.contains(&select(&document, "#a")));
+        update_box_tree(&mut test_document.box_tree, &document, &[], &restyle);
+        let box_tree = test_document.box_tree.as_mut().unwrap();
+        global_layout(box_tree, 400., 300., 1.);
+        let mut full_layout = build_box_tree(document, None).unwrap();
+        global_layout(&mut full_layout, 400., 300., 1.);
+        assert_eq!(dump(box_tree), dump(&full_layout));
+    }
+}
@@ -10,19 +10,77 @@ use crate::style::values::CSSFloat;
 ///
 /// https://www.w3.org/TR/2011/REC-CSS2-20110607/visudet.html#containing-block-details
 /// https://drafts.csswg.org/css-writing-modes-4/#logical-direction-layout
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct ContainingBlock {
     rect: Rect,
     direction: Direction,
     writing_mode: WritingMode,
+    /// The height percentage heights resolve against, or `None` if the height of the containing
+    /// block depends on its contents, in which case percentage heights behave as `auto`.
+    ///
+    /// https://drafts.csswg.org/css2/#the-height-property
+    definite_height: Option<CSSPixelLength>,
+    /// The containing block of the root element, which the sizes of orthogonal flow roots fall
+    /// back to (see `orthogonal_available_inline_size`).
+    initial_containing_block: Rect,
 }
 
 impl ContainingBlock {
+    /// Creates the initial containing block, whose height is definite since it's the viewport's.
     pub fn new(rect: Rect, direction: Direction, writing_mode: WritingMode) -> Self {
         Self {
             rect,
             direction,
             writing_mode,
+            definite_height: Some(rect.height),
+            initial_containing_block: rect,
+        }
+    }
+
+    /// Creates a containing block formed by a box nested in this one, whose content box is `rect`,
+    /// and whose height is definite until told otherwise.
+    pub fn nested(&self, rect: Rect, direction: Direction, writing_mode: WritingMode) -> Self {
+        Self {
+            rect,
+            direction,
+            writing_mode,
+            definite_height: Some(rect.height),
+            initial_containing_block: self.initial_containing_block,
+        }
+    }
+
+    /// This containing block moved `x` pixels right and `y` pixels down.
+    pub fn translated(self, x: CSSFloat, y: CSSFloat) -> Self {
+        Self {
+            rect: self.rect.translated(x, y),
+            ..self
+        }
+    }
+
+    pub fn with_definite_height(self, definite_height: Option<CSSPixelLength>) -> Self {
+        Self {
+            definite_height,
+            ..self
+        }
+    }
+
+    pub fn definite_height(&self) -> Option<CSSPixelLength> {
+        self.definite_height
+    }
+
+    /// The space available to the inline size of an orthogonal flow root in this containing
+    /// block, which lies in the block axis of this containing block: its block size if that's
+    /// definite, and otherwise the size of the initial containing block in that axis.
+    ///
+    /// https://drafts.csswg.org/css-writing-modes-4/#orthogonal-auto
+    pub fn orthogonal_available_inline_size(&self) -> CSSPixelLength {
+        if self.writing_mode.is_horizontal() {
+            self.definite_height
+                .unwrap_or(self.initial_containing_block.height)
+        } else {
+            // Only definite heights are tracked, so the block size of a vertical containing block
+            // is treated as indefinite.
+            self.initial_containing_block.width
         }
     }
 
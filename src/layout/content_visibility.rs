@@ -0,0 +1,154 @@
+//! Relevance to the user, which decides whether elements with `content-visibility: auto` skip
+//! their contents (see `ContentVisibility`).  An element is relevant to the user while it's on or
+//! near the screen, or contains the focused element, and each time a document is laid out, the
+//! elements whose relevance changed are laid out again, with or without their contents.
+//!
+//! TODO: Elements that contain part of the selection should be relevant to the user too.
+//!
+//! https://drafts.csswg.org/css-contain-2/#relevant-to-the-user
+
+use crate::dom::tree::NodeRef;
+use crate::layout::behavior::BaseLayoutBoxBehavior;
+use crate::layout::flow::block::BlockLevelBox;
+use crate::layout::layout_box::LayoutBox;
+use crate::layout::rect::{EdgeSizes, Rect};
+use crate::style::values::computed::length::CSSPixelLength;
+use crate::style::values::computed::ContentVisibility;
+use std::collections::HashSet;
+
+/// How far outside the viewport an element can be while still being relevant to the user, as a
+/// fraction of the viewport's size, so that its contents are laid out a little before they're
+/// scrolled into view.
+const VIEWPORT_MARGIN: f32 = 0.5;
+
+/// Whether `node` is an element that was relevant to the user when its document was last laid
+/// out.  Elements start out irrelevant until their document is first laid out.
+pub fn is_relevant_to_user(node: &NodeRef) -> bool {
+    node.as_element()
+        .map_or(false, |element| element.relevant_to_user.get())
+}
+
+/// Updates which elements with `content-visibility: auto` in the box tree laid out in `root` are
+/// relevant to the user, given the viewport its document is shown through (in page coordinates),
+/// and marks the boxes of those whose relevance changed as needing layout.  Returns whether any
+/// were marked, in which case the box tree has to be laid out again.
+pub fn update_relevance(root: &mut LayoutBox, viewport: Rect) -> bool {
+    let margin_x = CSSPixelLength::new(viewport.width.px() * VIEWPORT_MARGIN);
+    let margin_y = CSSPixelLength::new(viewport.height.px() * VIEWPORT_MARGIN);
+    let relevant_area = viewport.expanded_by_edges(EdgeSizes {
+        left: margin_x,
+        right: margin_x,
+        top: margin_y,
+        bottom: margin_y,
+    });
+    let mut changed = HashSet::new();
+    collect_relevance_changes(root, relevant_area, &mut changed);
+    !changed.is_empty() && root.mark_nodes_need_layout(&changed)
+}
+
+/// Updates the relevance of the elements with `content-visibility: auto` among `layout_box` and
+/// its laid out descendants, collecting those whose relevance changed into `changed`.
+fn collect_relevance_changes(
+    layout_box: &LayoutBox,
+    relevant_area: Rect,
+    changed: &mut HashSet<NodeRef>,
+) {
+    if let LayoutBox::BlockLevel(BlockLevelBox::BlockContainer(_)) = layout_box {
+        let node = layout_box.node();
+        let content_visibility = layout_box.computed_values().content_visibility;
+        if let (ContentVisibility::Auto, Some(element)) = (content_visibility, node.as_element()) {
+            let relevant = overlaps(layout_box.dimensions().border_box(), relevant_area)
+                || contains_focus(&node);
+            if element.relevant_to_user.replace(relevant) != relevant {
+                changed.insert(node.clone());
+            }
+        }
+    }
+    // The descendants of boxes that skip their contents weren't laid out, so where they'd be is
+    // unknown (and they're skipped along with the rest of the contents either way).
+    if layout_box.skips_contents() {
+        return;
+    }
+    if let Some(children) = layout_box.children() {
+        for child in children {
+            collect_relevance_changes(child, relevant_area, changed);
+        }
+    }
+}
+
+/// Whether `rect` and `area` overlap or touch, so that even empty boxes at the edge of the area
+/// count as being in it.
+fn overlaps(rect: Rect, area: Rect) -> bool {
+    rect.start_x <= (area.start_x + area.width).px()
+        && area.start_x <= (rect.start_x + rect.width).px()
+        && rect.start_y <= (area.start_y + area.height).px()
+        && area.start_y <= (rect.start_y + rect.height).px()
+}
+
+/// Whether `node` or one of its descendants is the focused element of its document.
+fn contains_focus(node: &NodeRef) -> bool {
+    node.inclusive_descendants().any(|descendant| {
+        descendant
+            .as_element()
+            .map_or(false, |element| element.focused.get())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::traits::*;
+    use crate::layout::box_tree::build_box_tree;
+    use crate::layout::rect::Point;
+    use crate::style::test_utils::{lay_out, styled_document};
+
+    #[test]
+    fn elements_skip_their_contents_until_they_come_into_view() {
+        let document = styled_document(
+            "<!DOCTYPE html><div id=hidden><div class=inner></div></div>\
+             <div id=a><div class=inner></div></div><div id=b><div class=inner></div></div>\
+             <div id=c><div class=inner></div></div><div id=d><div class=inner></div></div>\
+             <div id=e><div class=inner></div></div>",
+            "body { margin-top: 0px; margin-right: 0px } \
+             body { margin-bottom: 0px; margin-left: 0px } \
+             div { content-visibility: auto; contain-intrinsic-size: 100px 200px } \
+             #hidden { content-visibility: hidden } \
+             .inner { content-visibility: visible; height: 300px }",
+        );
+        let mut box_tree = build_box_tree(document.clone(), None).unwrap();
+        let heights = |box_tree: &LayoutBox| {
+            let body = &box_tree.children().unwrap()[0];
+            body.children()
+                .unwrap()
+                .iter()
+                .map(|div| div.dimensions().content.height.px())
+                .collect::<Vec<_>>()
+        };
+        let select = |selector| document.select_first(selector).unwrap().as_node().clone();
+
+        // Every element starts out skipping its contents, sized by its `contain-intrinsic-size`,
+        // and those that are then within half a viewport of it are laid out with their contents.
+        lay_out(&mut box_tree);
+        assert_eq!(heights(&box_tree), vec![200., 300., 300., 300., 300., 200.]);
+        assert!(select("#hidden .inner").client_rects().is_empty());
+        assert!(!select("#a .inner").client_rects().is_empty());
+        assert!(select("#e .inner").client_rects().is_empty());
+
+        // Once the document is scrolled down, the elements that scrolled out of view skip their
+        // contents, and those that scrolled into it stop skipping them.
+        document
+            .as_document()
+            .unwrap()
+            .scroll_position
+            .set(Point { x: 0., y: 1000. });
+        lay_out(&mut box_tree);
+        assert_eq!(heights(&box_tree), vec![200., 200., 300., 300., 300., 300.]);
+        assert!(select("#a .inner").client_rects().is_empty());
+        assert!(!select("#e .inner").client_rects().is_empty());
+
+        // The focused element is relevant to the user wherever it is.
+        select("#a").as_element().unwrap().focused.set(true);
+        lay_out(&mut box_tree);
+        assert_eq!(heights(&box_tree), vec![200., 300., 300., 300., 300., 300.]);
+    }
+}
@@ -0,0 +1,165 @@
+//! Comparison of text layout dumps, used by `dump-layout --diff` so that large snapshot changes can
+//! be triaged by looking at only the boxes that changed.
+
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+
+/// A box of a text layout dump.
+#[derive(Debug, PartialEq)]
+struct DumpedBox<'a> {
+    /// The label (e.g. `DIV BlockContainer`) of the box and of each of its ancestors, from the root
+    /// down, each along with the number of earlier siblings with the same label.  This identifies
+    /// the box across dumps.
+    path: Vec<(&'a str, usize)>,
+    /// Everything printed after the label, i.e. the position and size of the box, along with its
+    /// margins, borders, and padding in verbose dumps.
+    geometry: &'a str,
+}
+
+/// Parses a text layout dump, in which each box is on its own line, indented two spaces deeper
+/// than its parent.
+fn parse_layout_dump(dump: &str) -> Vec<DumpedBox> {
+    let mut boxes = Vec::new();
+    let mut path: Vec<(&str, usize)> = Vec::new();
+    // For each depth of `path` (and the one below it), how many boxes with each label have been
+    // seen among the children of the box at that depth so far.
+    let mut sibling_labels: Vec<HashMap<&str, usize>> = vec![HashMap::new()];
+    for line in dump.lines().filter(|line| !line.trim().is_empty()) {
+        let content = line.trim_start();
+        let depth = ((line.len() - content.len()) / 2).min(path.len());
+        let (label, geometry) = match content.rfind("at (") {
+            Some(index) => (content[..index].trim_end(), &content[index..]),
+            None => (content, ""),
+        };
+        path.truncate(depth);
+        sibling_labels.truncate(depth + 1);
+        let seen = sibling_labels[depth].entry(label).or_insert(0);
+        path.push((label, *seen));
+        *seen += 1;
+        sibling_labels.push(HashMap::new());
+        boxes.push(DumpedBox {
+            path: path.clone(),
+            geometry,
+        });
+    }
+    boxes
+}
+
+/// Compares two text layout dumps box by box, writing each box whose geometry differs with its
+/// geometry before and after.  Boxes only in `current` are written in dump order with only their
+/// new geometry, followed by boxes only in `previous` with only their old geometry.  Nothing is
+/// written if the dumps lay out the same boxes the same way.
+pub fn diff_layout_dumps<W: Write>(previous: &str, current: &str, write_to: &mut W) {
+    let previous_boxes = parse_layout_dump(previous);
+    let current_boxes = parse_layout_dump(current);
+    let previous_geometries = previous_boxes
+        .iter()
+        .map(|dumped_box| (&dumped_box.path[..], dumped_box.geometry))
+        .collect::<HashMap<_, _>>();
+    let current_paths = current_boxes
+        .iter()
+        .map(|dumped_box| &dumped_box.path[..])
+        .collect::<HashSet<_>>();
+    for current_box in &current_boxes {
+        match previous_geometries.get(&current_box.path[..]) {
+            Some(&previous_geometry) if previous_geometry == current_box.geometry => {}
+            previous_geometry => write_box_diff(
+                write_to,
+                &current_box.path,
+                previous_geometry.copied(),
+                Some(current_box.geometry),
+            ),
+        }
+    }
+    for removed_box in previous_boxes
+        .iter()
+        .filter(|dumped_box| !current_paths.contains(&dumped_box.path[..]))
+    {
+        write_box_diff(
+            write_to,
+            &removed_box.path,
+            Some(removed_box.geometry),
+            None,
+        );
+    }
+}
+
+fn write_box_diff<W: Write>(
+    write_to: &mut W,
+    path: &[(&str, usize)],
+    before: Option<&str>,
+    after: Option<&str>,
+) {
+    let path = path
+        .iter()
+        .map(|&(label, earlier_siblings)| match earlier_siblings {
+            0 => label.to_owned(),
+            _ => format!("{} #{}", label, earlier_siblings + 1),
+        })
+        .collect::<Vec<_>>()
+        .join(" > ");
+    writeln!(write_to, "{}", path).expect("could not write layout dump diff");
+    if let Some(before) = before {
+        writeln!(write_to, "  - {}", before).expect("could not write layout dump diff");
+    }
+    if let Some(after) = after {
+        writeln!(write_to, "  + {}", after).expect("could not write layout dump diff");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diff(previous: &str, current: &str) -> String {
+        let mut diff = Vec::new();
+        diff_layout_dumps(previous, current, &mut diff);
+        String::from_utf8(diff).unwrap()
+    }
+
+    #[test]
+    fn identifies_boxes_by_their_ancestors_and_siblings() {
+        let dump = "HTML BlockContainer at (0, 0) size 800x20\n  \
+                    BODY BlockContainer at (8, 8) size 784x10\n    \
+                    DIV BlockContainer at (8, 8) size 784x0\n    \
+                    DIV BlockContainer at (8, 8) size 784x10\n";
+        let boxes = parse_layout_dump(dump);
+        assert_eq!(
+            boxes[3],
+            DumpedBox {
+                path: vec![
+                    ("HTML BlockContainer", 0),
+                    ("BODY BlockContainer", 0),
+                    ("DIV BlockContainer", 1),
+                ],
+                geometry: "at (8, 8) size 784x10",
+            }
+        );
+    }
+
+    #[test]
+    fn writes_only_changed_boxes() {
+        let previous = "HTML BlockContainer at (0, 0) size 800x20\n  \
+                        DIV BlockContainer at (0, 0) size 800x10\n  \
+                        DIV BlockContainer at (0, 10) size 800x10\n  \
+                        P BlockContainer at (0, 20) size 800x0\n";
+        let current = "HTML BlockContainer at (0, 0) size 800x25\n  \
+                       DIV BlockContainer at (0, 0) size 800x10\n  \
+                       DIV BlockContainer at (0, 10) size 800x15\n  \
+                       SPAN InlineBox at (0, 25) size 0x0\n";
+        assert_eq!(
+            diff(previous, current),
+            "HTML BlockContainer\n  \
+             - at (0, 0) size 800x20\n  \
+             + at (0, 0) size 800x25\n\
+             HTML BlockContainer > DIV BlockContainer #2\n  \
+             - at (0, 10) size 800x10\n  \
+             + at (0, 10) size 800x15\n\
+             HTML BlockContainer > SPAN InlineBox\n  \
+             + at (0, 25) size 0x0\n\
+             HTML BlockContainer > P BlockContainer\n  \
+             - at (0, 20) size 800x0\n"
+        );
+        assert_eq!(diff(previous, previous), "");
+    }
+}
@@ -2,10 +2,15 @@ use crate::apply_page_relative_properties_base_box_passthrough_impls;
 use crate::dom::tree::NodeRef;
 use crate::layout::behavior::{ApplyPageRelativeProperties, BaseLayoutBoxBehavior};
 use crate::layout::containing_block::ContainingBlock;
+use crate::layout::content_visibility::is_relevant_to_user;
 use crate::layout::dimensions::Dimensions;
 use crate::layout::flow::{BlockContainer, FlowSide, OriginRelativeProgression};
 use crate::layout::formatting_context::FormattingContextRef;
-use crate::layout::layout_box::{get_anonymous_inline_layout_box, BaseBox, LayoutBox};
+use crate::layout::intrinsic::{content_sizes, IntrinsicSizes};
+use crate::layout::layout_box::{
+    get_anonymous_inline_layout_box, is_root_body, BaseBox, LayoutBox,
+};
+use crate::layout::replaced::ReplacedBox;
 use crate::layout::{BoxComponent, DumpLayoutFormat, Layout, LayoutContext};
 use crate::layout_box_behavior_base_box_passthrough_impls;
 use crate::style::values::computed::length::{
@@ -16,6 +21,7 @@ use crate::style::values::used::ToPx;
 use crate::style::values::CSSFloat;
 use accountable_refcell::Ref;
 use enum_dispatch::enum_dispatch;
+use html5ever::tree_builder::QuirksMode;
 
 #[enum_dispatch]
 #[derive(Clone, Debug, IntoStaticStr)]
@@ -26,6 +32,9 @@ pub enum BlockLevelBox {
     /// A block-level block container (note block containers can also be inline-level).
     /// https://drafts.csswg.org/css-display-3/#block-container
     BlockContainer(BlockContainer),
+    /// A block-level box generated by a replaced element, such as `<img>`.
+    /// https://drafts.csswg.org/css-display-3/#replaced-element
+    Replaced(ReplacedBox),
 }
 
 impl BlockLevelBox {
@@ -34,20 +43,41 @@ impl BlockLevelBox {
         BlockLevelBox::BlockContainer(BlockContainer::new(node, formatting_context))
     }
 
+    /// Creates a new block-level replaced box.
+    pub fn new_replaced(node: NodeRef, formatting_context: FormattingContextRef) -> Self {
+        BlockLevelBox::Replaced(ReplacedBox::new(node, formatting_context))
+    }
+
     pub fn add_child(&mut self, new_child: LayoutBox) {
         match self {
             BlockLevelBox::AnonymousBlock(ab) => ab.add_child(new_child),
             BlockLevelBox::BlockContainer(bc) => bc.add_child(new_child),
+            BlockLevelBox::Replaced(_) => panic!("tried to add child to replaced box"),
         }
     }
 
-    pub fn children(&self) -> &Vec<LayoutBox> {
+    /// Returns the children of this box, or `None` if this box can't have children.
+    pub fn children(&self) -> Option<&Vec<LayoutBox>> {
         match self {
-            BlockLevelBox::AnonymousBlock(ab) => ab.children(),
-            BlockLevelBox::BlockContainer(bc) => bc.children(),
+            BlockLevelBox::AnonymousBlock(ab) => Some(ab.children()),
+            BlockLevelBox::BlockContainer(bc) => Some(bc.children()),
+            BlockLevelBox::Replaced(_) => None,
         }
     }
 
+    /// Returns the children of this box mutably, or `None` if this box can't have children.
+    pub fn children_mut(&mut self) -> Option<&mut Vec<LayoutBox>> {
+        match self {
+            BlockLevelBox::AnonymousBlock(ab) => Some(ab.children_mut()),
+            BlockLevelBox::BlockContainer(bc) => Some(bc.children_mut()),
+            BlockLevelBox::Replaced(_) => None,
+        }
+    }
+
+    pub fn is_anonymous_block(&self) -> bool {
+        matches!(self, BlockLevelBox::AnonymousBlock(_))
+    }
+
     pub fn get_mut_inline_container(&mut self) -> Option<&mut LayoutBox> {
         match self {
             BlockLevelBox::AnonymousBlock(abb) => {
@@ -64,16 +94,132 @@ impl BlockLevelBox {
                     _ => None,
                 })
                 .flatten(),
+            BlockLevelBox::Replaced(_) => None,
+        }
+    }
+
+    /// Whether this box has size containment, which only applies to the boxes of elements.  The
+    /// natural size of replaced boxes with size containment is handled by `ReplacedBox`.
+    pub fn has_size_containment(&self) -> bool {
+        match self {
+            BlockLevelBox::BlockContainer(_) => {
+                let computed_values = self.computed_values();
+                computed_values.contain.size
+                    || computed_values.container_type.containment().size
+                    || self.skips_contents()
+            }
+            BlockLevelBox::AnonymousBlock(_) | BlockLevelBox::Replaced(_) => false,
+        }
+    }
+
+    /// Whether this box skips its contents (see `ContentVisibility`), which only applies to the
+    /// block containers of elements.
+    pub fn skips_contents(&self) -> bool {
+        match self {
+            BlockLevelBox::BlockContainer(_) => self
+                .computed_values()
+                .content_visibility
+                .skips_contents(is_relevant_to_user(&self.node())),
+            BlockLevelBox::AnonymousBlock(_) | BlockLevelBox::Replaced(_) => false,
+        }
+    }
+
+    /// Whether this box is absolutely positioned, which anonymous blocks, whose computed values
+    /// are those of their parent, never are.
+    pub fn is_absolutely_positioned(&self) -> bool {
+        match self {
+            BlockLevelBox::BlockContainer(_) | BlockLevelBox::Replaced(_) => {
+                self.computed_values().position.is_absolutely_positioned()
+            }
+            BlockLevelBox::AnonymousBlock(_) => false,
+        }
+    }
+
+    /// Whether this box is an orthogonal flow root: a block container whose writing mode is
+    /// orthogonal to that of its containing block, such as a `vertical-rl` box in a `horizontal-tb`
+    /// one.  The block axis of such a box is the inline axis of its containing block, and vice
+    /// versa.
+    ///
+    /// https://drafts.csswg.org/css-writing-modes-4/#orthogonal-flows
+    fn is_orthogonal_flow_root(&self, containing_block: ContainingBlock) -> bool {
+        matches!(self, BlockLevelBox::BlockContainer(_))
+            && self.computed_values().writing_mode.is_horizontal()
+                != containing_block.writing_mode().is_horizontal()
+    }
+
+    /// Solves the inline size of an orthogonal flow root, which lies in the block axis of its
+    /// containing block.  If that wasn't resolved along with the rest of the block-axis properties
+    /// of this box, it's resolved against the space available to it instead.
+    ///
+    /// TODO: The inline size should be the fit-content size within the available space (see
+    /// `IntrinsicSizes::shrink_to_fit`), but this box stretches to fill it.
+    ///
+    /// https://drafts.csswg.org/css-writing-modes-4/#orthogonal-auto
+    fn solve_and_set_orthogonal_inline_size(&mut self, containing_block: ContainingBlock) {
+        let writing_mode = self.computed_values().writing_mode;
+        let is_definite = match self.computed_values().inline_size(writing_mode) {
+            LengthPercentageOrAuto::LengthPercentage(LengthPercentage::Length(_)) => true,
+            // Percentage heights resolve only against definite heights (see
+            // `apply_block_page_relative_properties`), whereas percentage widths always resolve.
+            LengthPercentageOrAuto::LengthPercentage(LengthPercentage::Percentage(_)) => {
+                !containing_block.writing_mode().is_horizontal()
+                    || containing_block.definite_height().is_some()
+            }
+            LengthPercentageOrAuto::Auto => false,
+        };
+        if is_definite {
+            return;
+        }
+        let dimensions = self.dimensions();
+        let outer_inline_size = dimensions.margin_box_inline_size(writing_mode)
+            - dimensions.get_inline_size(None, writing_mode);
+        let inline_size = (containing_block.orthogonal_available_inline_size() - outer_inline_size)
+            .max(CSSPixelLength::new(0.));
+        self.dimensions_mut()
+            .set_inline_size(inline_size, writing_mode);
+        // This box was placed before its size in the block axis of its containing block was
+        // known, so if it was placed from its physical end, it's moved to end there.
+        if containing_block.block_start_origin_relative_progression()
+            == OriginRelativeProgression::TowardsOrigin
+        {
+            self.dimensions_mut()
+                .add_to_block_start_coord(-inline_size.px(), containing_block.writing_mode());
+        }
+    }
+
+    /// The content block size of this box if it has size containment: its own block size, or if
+    /// that's `auto`, its `contain-intrinsic-size` in the block axis.
+    fn contained_block_size(&self) -> CSSPixelLength {
+        let computed_values = self.computed_values();
+        let writing_mode = computed_values.writing_mode;
+        match computed_values.block_size(writing_mode) {
+            LengthPercentageOrAuto::Auto => computed_values
+                .contain_intrinsic_block_size(writing_mode)
+                .unwrap_or_else(|| self.dimensions().content_box_block_size(writing_mode)),
+            _ => self.dimensions().content_box_block_size(writing_mode),
         }
     }
 
-    fn layout_children(&mut self, containing_block: ContainingBlock) {
+    fn layout_children(
+        &mut self,
+        containing_block: ContainingBlock,
+        quirks_fill_height: Option<CSSPixelLength>,
+    ) {
         let direction = self.computed_values().direction;
         let writing_mode = self.computed_values().writing_mode;
+        let block_start_progression =
+            OriginRelativeProgression::block_start_origin_relative_direction(writing_mode);
+        let definite_height =
+            self.definite_height_for_children(containing_block, quirks_fill_height);
 
         let (children, self_dimensions) = match self {
             BlockLevelBox::AnonymousBlock(abb) => (&mut abb.children, abb.base.dimensions_mut()),
             BlockLevelBox::BlockContainer(bc) => (&mut bc.children, bc.base.dimensions_mut()),
+            BlockLevelBox::Replaced(rb) => {
+                // Replaced boxes have no children to lay out, but still need to size their content.
+                rb.layout_content(containing_block);
+                return;
+            }
         };
         for child in children {
             // The rectangle selected as the containing block will need to change when we support other
@@ -83,49 +229,141 @@ impl BlockLevelBox {
             // 10.1.2: For other [not-root] elements, if the element's position is 'relative' or
             // 'static', the containing block is formed by the content edge of the nearest block
             // container ancestor box.
-            child.layout(LayoutContext::new(ContainingBlock::new(
-                self_dimensions.content,
-                direction,
-                writing_mode,
-            )));
+            //
+            // Absolutely positioned children are laid out at their static position, where the next
+            // in-flow child will be placed.
+            // https://drafts.csswg.org/css-position-3/#staticpos-rect
+            // TODO: Insets aren't supported yet, so they're all `auto`, which places these children
+            // at their static position in the same containing block as their in-flow siblings.
+            // Once they are, these children need laying out in the padding box of their nearest
+            // positioned ancestor.
+            child.layout(LayoutContext::new(
+                containing_block
+                    .nested(self_dimensions.content, direction, writing_mode)
+                    .with_definite_height(definite_height),
+            ));
+            // They're out of flow, so take up no space.
+            if child.is_absolutely_positioned() {
+                continue;
+            }
             // Add this child's margin-box to our content box so the next child is laid out after
             // this one.
-            self_dimensions.add_to_block_size(
-                child.dimensions().margin_box_block_size(writing_mode),
-                containing_block.writing_mode(),
-            );
+            let child_block_size = child.dimensions().margin_box_block_size(writing_mode);
+            self_dimensions.add_to_block_size(child_block_size, writing_mode);
+            // If our block-start is our physical end (e.g. our right edge in `vertical-rl`), our
+            // content box grows towards the origin, so that edge stays where it is.
+            if block_start_progression == OriginRelativeProgression::TowardsOrigin {
+                self_dimensions.add_to_block_start_coord(-child_block_size.px(), writing_mode);
+            }
         }
     }
 
+    /// The height percentage heights of this box's children resolve against (see
+    /// `ContainingBlock::definite_height`), which is its own height if that doesn't depend on its
+    /// contents.  `quirks_fill_height` is the height this box fills in quirks mode, if any.
+    fn definite_height_for_children(
+        &self,
+        containing_block: ContainingBlock,
+        quirks_fill_height: Option<CSSPixelLength>,
+    ) -> Option<CSSPixelLength> {
+        // Anonymous boxes are skipped over when resolving percentages.
+        if self.is_anonymous_block() {
+            return containing_block.definite_height();
+        }
+        // The height of a vertical orthogonal flow root is its inline size, which is always
+        // resolved before its contents are laid out (see `solve_and_set_orthogonal_inline_size`).
+        if self.is_orthogonal_flow_root(containing_block)
+            && !self.computed_values().writing_mode.is_horizontal()
+        {
+            return Some(self.dimensions().content.height);
+        }
+        let is_definite = match self.computed_values().height.size {
+            LengthPercentageOrAuto::LengthPercentage(LengthPercentage::Length(_)) => true,
+            LengthPercentageOrAuto::LengthPercentage(LengthPercentage::Percentage(_)) => {
+                containing_block.definite_height().is_some()
+            }
+            LengthPercentageOrAuto::Auto => false,
+        };
+        if is_definite {
+            Some(self.dimensions().content.height)
+        } else if quirks_fill_height.is_some() {
+            quirks_fill_height
+        } else if self.node().quirks_mode() == QuirksMode::Quirks {
+            // In quirks mode, percentage heights resolve against the nearest ancestor whose
+            // height is definite, rather than behaving as `auto`.
+            // https://quirks.spec.whatwg.org/#the-percentage-height-calculation-quirk
+            containing_block.definite_height()
+        } else {
+            None
+        }
+    }
+
+    /// In quirks mode, the root element and `<body>` are at least as tall as their containing
+    /// block (less their own margins, borders, and padding) when their heights are `auto`.  This
+    /// is the content height they're at least as tall as, or `None` if neither quirk applies.
+    ///
+    /// https://quirks.spec.whatwg.org/#the-html-element-fills-the-viewport-quirk
+    /// https://quirks.spec.whatwg.org/#the-body-element-fills-the-html-element-quirk
+    fn quirks_fill_height(&self, containing_block: ContainingBlock) -> Option<CSSPixelLength> {
+        if self.is_anonymous_block()
+            || !containing_block.writing_mode().is_horizontal()
+            || self.computed_values().height.size != LengthPercentageOrAuto::Auto
+        {
+            return None;
+        }
+        let node = self.node();
+        if node.quirks_mode() != QuirksMode::Quirks || !(self.is_root() || is_root_body(&node)) {
+            return None;
+        }
+        let dimensions = self.dimensions();
+        let outer_height = dimensions.margin_box().height - dimensions.content.height;
+        containing_block
+            .definite_height()
+            .map(|height| (height - outer_height).max(CSSPixelLength::new(0.)))
+    }
+
     pub fn solve_and_set_inline_level_properties(&mut self, containing_block: ContainingBlock) {
         // Use the containing block's writing mode for resolving flow-relative directions.
         // https://drafts.csswg.org/css-writing-modes-4/#logical-direction-layout
         let writing_mode = containing_block.writing_mode();
 
-        let computed_values = self.computed_values();
-        let border_inline_start =
-            computed_values.border_flow_relative(FlowSide::InlineStart, writing_mode);
-        let border_inline_end =
-            computed_values.border_flow_relative(FlowSide::InlineEnd, writing_mode);
-        let padding_inline_start =
-            computed_values.padding_flow_relative(FlowSide::InlineStart, writing_mode);
-        let padding_inline_end =
-            computed_values.padding_flow_relative(FlowSide::InlineEnd, writing_mode);
-
-        let solved_inline_sizes = solve_block_level_inline_size(SolveInlineSizeInput {
-            containing_block,
-            margin_inline_start: computed_values
-                .margin_flow_relative(FlowSide::InlineStart, writing_mode),
-            margin_inline_end: computed_values
-                .margin_flow_relative(FlowSide::InlineEnd, writing_mode),
-            border_inline_start,
-            border_inline_end,
-            padding_inline_start,
-            padding_inline_end,
-            inline_size: computed_values.inline_size(writing_mode),
-        });
-        // Release this &self borrow so we can mutably borrow below.
-        drop(computed_values);
+        let solve_inline_size_input = if self.is_anonymous_block() {
+            SolveInlineSizeInput::anonymous(containing_block)
+        } else {
+            let computed_values = self.computed_values();
+            SolveInlineSizeInput {
+                containing_block,
+                margin_inline_start: computed_values
+                    .margin_flow_relative(FlowSide::InlineStart, writing_mode),
+                margin_inline_end: computed_values
+                    .margin_flow_relative(FlowSide::InlineEnd, writing_mode),
+                border_inline_start: computed_values
+                    .border_flow_relative(FlowSide::InlineStart, writing_mode),
+                border_inline_end: computed_values
+                    .border_flow_relative(FlowSide::InlineEnd, writing_mode),
+                padding_inline_start: computed_values
+                    .padding_flow_relative(FlowSide::InlineStart, writing_mode),
+                padding_inline_end: computed_values
+                    .padding_flow_relative(FlowSide::InlineEnd, writing_mode),
+                inline_size: match &*self {
+                    BlockLevelBox::Replaced(rb) => {
+                        LengthPercentageOrAuto::new_len_px(rb.used_content_size(containing_block).0)
+                    }
+                    _ => computed_values.inline_size(writing_mode),
+                },
+            }
+        };
+        let border_inline_start = solve_inline_size_input.border_inline_start;
+        let border_inline_end = solve_inline_size_input.border_inline_end;
+        let padding_inline_start = solve_inline_size_input.padding_inline_start;
+        let padding_inline_end = solve_inline_size_input.padding_inline_end;
+        let solved_inline_sizes = if self.is_absolutely_positioned() {
+            solve_absolutely_positioned_inline_size(solve_inline_size_input, || {
+                content_sizes(self, containing_block)
+            })
+        } else {
+            solve_block_level_inline_size(solve_inline_size_input)
+        };
 
         let direction = containing_block.direction();
         self.dimensions_mut().set_margin(
@@ -169,7 +407,9 @@ impl BlockLevelBox {
         // Before computing the `inline_start_coord` of this box, we need to apply values the author
         // has specified in the inline-direction (e.g. `width` in `writing:mode: horizontal-tb`, or
         // `height` in the other `writing-modes`.
-        self.apply_inline_page_relative_properties(containing_block);
+        if !self.is_anonymous_block() {
+            self.apply_inline_page_relative_properties(containing_block);
+        }
         let inline_start_coord = compute_inline_start_coord(&self.dimensions(), containing_block);
         self.dimensions_mut()
             .set_inline_start_coord(inline_start_coord, containing_block.writing_mode());
@@ -182,23 +422,37 @@ impl BlockLevelBox {
         // https://drafts.csswg.org/css-writing-modes-4/#logical-direction-layout
         let writing_mode = containing_block.writing_mode();
 
-        let computed_values = self.computed_values();
-        let mut margin_block_start =
-            computed_values.margin_flow_relative(FlowSide::BlockStart, writing_mode);
-        let mut margin_block_end =
-            computed_values.margin_flow_relative(FlowSide::BlockEnd, writing_mode);
-        let border_block_start =
-            computed_values.border_flow_relative(FlowSide::BlockStart, writing_mode);
-        let border_block_end =
-            computed_values.border_flow_relative(FlowSide::BlockEnd, writing_mode);
-        let padding_block_start =
-            computed_values.padding_flow_relative(FlowSide::BlockStart, writing_mode);
-        let padding_block_end =
-            computed_values.padding_flow_relative(FlowSide::BlockEnd, writing_mode);
-        // Release immutable borrow of &self so we can mutably borrow below.
-        drop(computed_values);
-
         let zero = LengthPercentageOrAuto::new_len(0.);
+        let (
+            mut margin_block_start,
+            mut margin_block_end,
+            border_block_start,
+            border_block_end,
+            padding_block_start,
+            padding_block_end,
+        ) = if self.is_anonymous_block() {
+            // See `SolveInlineSizeInput::anonymous`.
+            let no_padding = LengthPercentage::new_len(0.);
+            (
+                zero,
+                zero,
+                CSSPixelLength::new(0.),
+                CSSPixelLength::new(0.),
+                no_padding,
+                no_padding,
+            )
+        } else {
+            let computed_values = self.computed_values();
+            (
+                computed_values.margin_flow_relative(FlowSide::BlockStart, writing_mode),
+                computed_values.margin_flow_relative(FlowSide::BlockEnd, writing_mode),
+                computed_values.border_flow_relative(FlowSide::BlockStart, writing_mode),
+                computed_values.border_flow_relative(FlowSide::BlockEnd, writing_mode),
+                computed_values.padding_flow_relative(FlowSide::BlockStart, writing_mode),
+                computed_values.padding_flow_relative(FlowSide::BlockEnd, writing_mode),
+            )
+        };
+
         let auto = LengthPercentageOrAuto::Auto;
         // If the block-start or blond-end margins are auto, their used value is 0.
         if margin_block_start == auto {
@@ -258,7 +512,9 @@ impl BlockLevelBox {
         };
         // Before calculating this boxes block start coordinate, ensure we've applied the authors
         // specified styles.
-        self.apply_block_page_relative_properties(containing_block);
+        if !self.is_anonymous_block() {
+            self.apply_block_page_relative_properties(containing_block);
+        }
         let block_start_coord = compute_block_start_coord(
             &self.dimensions(),
             preceeding_sibling_blockwise_space_consumed,
@@ -279,7 +535,86 @@ impl Layout for BlockLevelBox {
         let LayoutContext { containing_block } = context;
         self.solve_and_set_inline_level_properties(containing_block);
         self.solve_and_set_block_level_properties(containing_block);
-        self.layout_children(containing_block);
+        let writing_mode = self.computed_values().writing_mode;
+        let quirks_fill_height = self.quirks_fill_height(containing_block);
+        // A box with size containment is sized as if it had no contents, which are laid out (and
+        // overflow it) all the same, unless the box skips them.
+        // https://drafts.csswg.org/css-contain-2/#containment-size
+        let contained_block_size = if self.has_size_containment() {
+            Some(self.contained_block_size())
+        } else {
+            None
+        };
+        // The block size of an orthogonal flow root lies in the inline axis of its containing
+        // block, so it's been solved as an inline size above, filling the containing block if it
+        // was `auto`.  Only a block size that isn't `auto` is kept, as otherwise it's the block
+        // size of the contents of this box, like any other.
+        //
+        // If the block-start of this box is its physical end (e.g. its right edge in `vertical-rl`),
+        // its contents are laid out from there.  But this box was placed from its physical start,
+        // the inline-start of its containing block, so its end isn't known until its block size
+        // is, which if it's `auto` means laying out its contents from its physical start, then
+        // moving them (and its content box) past it by that block size.
+        //
+        // TODO: In containing blocks whose inline-start is on the right, an orthogonal flow root
+        // with an `auto` block size should be moved to the right once its contents are laid out.
+        let is_orthogonal_flow_root = self.is_orthogonal_flow_root(containing_block);
+        let block_start_towards_origin =
+            OriginRelativeProgression::block_start_origin_relative_direction(writing_mode)
+                == OriginRelativeProgression::TowardsOrigin;
+        let physical_start_coord = self.dimensions_mut().get_block_start_coord(writing_mode);
+        let specified_block_size = if is_orthogonal_flow_root {
+            self.solve_and_set_orthogonal_inline_size(containing_block);
+            let block_size = self.dimensions().content_box_block_size(writing_mode);
+            self.dimensions_mut()
+                .set_block_size(CSSPixelLength::new(0.), writing_mode);
+            match self.computed_values().block_size(writing_mode) {
+                LengthPercentageOrAuto::Auto => None,
+                LengthPercentageOrAuto::LengthPercentage(_) => Some(block_size),
+            }
+        } else {
+            None
+        };
+        if is_orthogonal_flow_root && block_start_towards_origin {
+            if let Some(block_size) = specified_block_size {
+                self.dimensions_mut()
+                    .set_block_start_coord(physical_start_coord + block_size.px(), writing_mode);
+            }
+        }
+        if !self.skips_contents() {
+            self.layout_children(containing_block, quirks_fill_height);
+            if is_orthogonal_flow_root
+                && block_start_towards_origin
+                && specified_block_size.is_none()
+            {
+                let block_size = self.dimensions().content_box_block_size(writing_mode).px();
+                self.dimensions_mut()
+                    .add_to_block_start_coord(block_size, writing_mode);
+                let (x, y) = if writing_mode.is_horizontal() {
+                    (0., block_size)
+                } else {
+                    (block_size, 0.)
+                };
+                for child in self.children_mut().into_iter().flatten() {
+                    child.translate(x, y);
+                }
+            }
+        }
+        if let Some(block_size) = contained_block_size.or(specified_block_size) {
+            let laid_out_block_size = self.dimensions().content_box_block_size(writing_mode);
+            self.dimensions_mut()
+                .set_block_size(block_size, writing_mode);
+            // Boxes whose block-start is their physical end keep that edge where it is.
+            if block_start_towards_origin {
+                self.dimensions_mut().add_to_block_start_coord(
+                    (laid_out_block_size - block_size).px(),
+                    writing_mode,
+                );
+            }
+        }
+        if let Some(fill_height) = quirks_fill_height {
+            self.dimensions_mut().content.height.max_assign(fill_height);
+        }
     }
 }
 
@@ -338,6 +673,28 @@ pub struct SolveInlineSizeInput {
     pub inline_size: LengthPercentageOrAuto,
 }
 
+impl SolveInlineSizeInput {
+    /// The input for an anonymous block box.  Anonymous boxes inherit inheritable properties from
+    /// the element they were generated for, but take the initial value of the rest, so they have
+    /// no margins, borders, or padding, and an `auto` inline-size.
+    ///
+    /// https://drafts.csswg.org/css-display-3/#anonymous
+    pub fn anonymous(containing_block: ContainingBlock) -> Self {
+        let zero = LengthPercentageOrAuto::new_len(0.);
+        let no_padding = LengthPercentage::new_len(0.);
+        SolveInlineSizeInput {
+            containing_block,
+            margin_inline_start: zero,
+            margin_inline_end: zero,
+            border_inline_start: CSSPixelLength::new(0.),
+            border_inline_end: CSSPixelLength::new(0.),
+            padding_inline_start: no_padding,
+            padding_inline_end: no_padding,
+            inline_size: LengthPercentageOrAuto::Auto,
+        }
+    }
+}
+
 pub struct SolveInlineSizeOutput {
     pub margin_inline_start: CSSPixelLength,
     pub margin_inline_end: CSSPixelLength,
@@ -436,6 +793,40 @@ pub fn solve_block_level_inline_size(input: SolveInlineSizeInput) -> SolveInline
     }
 }
 
+/// Determines the used inline-wise sizes of an absolutely positioned box, given the intrinsic sizes
+/// of its contents, which are only found if its inline size is `auto`.
+///
+/// Corresponds to CSS 2.1 section 10.3.7 when the box's insets are all `auto`, which is all that's
+/// supported so far: its `auto` margins are zero, and an `auto` inline size shrinks to fit its
+/// contents within the containing block.
+/// https://www.w3.org/TR/CSS2/visudet.html#abs-non-replaced-width
+pub fn solve_absolutely_positioned_inline_size(
+    input: SolveInlineSizeInput,
+    content_sizes: impl FnOnce() -> IntrinsicSizes,
+) -> SolveInlineSizeOutput {
+    let percentage_basis = input.containing_block.self_relative_inline_size();
+    let margin_inline_start = input.margin_inline_start.to_px(percentage_basis);
+    let margin_inline_end = input.margin_inline_end.to_px(percentage_basis);
+    let inline_size = match input.inline_size {
+        LengthPercentageOrAuto::Auto => {
+            let available_space = percentage_basis
+                - margin_inline_start
+                - margin_inline_end
+                - input.border_inline_start
+                - input.border_inline_end
+                - input.padding_inline_start.to_px(percentage_basis)
+                - input.padding_inline_end.to_px(percentage_basis);
+            content_sizes().shrink_to_fit(available_space)
+        }
+        inline_size => inline_size.to_px(percentage_basis),
+    };
+    SolveInlineSizeOutput {
+        margin_inline_start,
+        margin_inline_end,
+        inline_size,
+    }
+}
+
 /// Computes the block start coordinate value (`x` or `y` depending on the writing mode) for
 /// the given box according to the rules of block layout.
 fn compute_block_start_coord(
@@ -453,7 +844,16 @@ fn compute_block_start_coord(
                 + box_dimensions.get_mbp(FlowSide::BlockStart, containing_block.writing_mode(), containing_block.direction())
         }
         OriginRelativeProgression::TowardsOrigin => {
-            unimplemented!("towards origin block_start_coord computation")
+            // The block-start of the containing block is its physical end (e.g. its right edge in
+            // `vertical-rl`), which stays put as it grows towards the origin (see
+            // `layout_children`), so boxes are placed from there.
+            let containing_block_block_end_coord = containing_block
+                .self_relative_block_start_coord()
+                + containing_block.self_relative_block_size();
+            containing_block_block_end_coord
+                - preceeding_sibling_blockwise_space_consumed
+                - box_dimensions.get_mbp(FlowSide::BlockStart, containing_block.writing_mode(), containing_block.direction())
+                - box_dimensions.content_box_block_size(containing_block.writing_mode())
         }
     }.px()
 }
@@ -494,3 +894,228 @@ fn compute_inline_start_coord(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::layout::behavior::BaseLayoutBoxBehavior;
+    use crate::layout::flow::inline::{InlineLevelBox, InlineLevelContent};
+    use crate::layout::layout_box::LayoutBox;
+    use crate::layout::rect::Rect;
+    use crate::style::test_utils::laid_out_document;
+
+    /// Lays out `html` in an 800x600 viewport with `div { height: 50% }`, returning the content
+    /// heights of the root element, `<body>`, and the `<div>` in it.
+    fn heights(html: &str) -> (f32, f32, f32) {
+        let (_, box_tree) = laid_out_document(html, "div { height: 50% }");
+        let body = &box_tree.children().unwrap()[0];
+        let div = &body.children().unwrap()[0];
+        (
+            box_tree.dimensions().content.height.px(),
+            body.dimensions().content.height.px(),
+            div.dimensions().content.height.px(),
+        )
+    }
+
+    #[test]
+    fn percentage_heights_in_auto_height_containing_blocks_are_auto() {
+        assert_eq!(heights("<!DOCTYPE html><div></div>"), (0., 0., 0.));
+        // Limited-quirks mode has none of the quirks of height calculation.
+        assert_eq!(
+            heights(
+                "<!DOCTYPE html PUBLIC \"-//W3C//DTD XHTML 1.0 Transitional//EN\" \
+                 \"http://www.w3.org/TR/xhtml1/DTD/xhtml1-transitional.dtd\"><div></div>"
+            ),
+            (0., 0., 0.)
+        );
+    }
+
+    #[test]
+    fn boxes_with_size_containment_are_sized_as_if_empty() {
+        let (_, box_tree) = laid_out_document(
+            "<!DOCTYPE html><div class=contained><div></div></div>\
+             <div class=contained id=tall><div></div></div>",
+            "div { height: 10px } .contained { contain: size; height: auto } \
+             #tall { height: 5px }",
+        );
+        let body = &box_tree.children().unwrap()[0];
+        let heights = body
+            .children()
+            .unwrap()
+            .iter()
+            .map(|contained| {
+                let child = &contained.children().unwrap()[0];
+                (
+                    contained.dimensions().content.height.px(),
+                    child.dimensions().content.height.px(),
+                )
+            })
+            .collect::<Vec<_>>();
+        // The contents are still laid out, overflowing the box.
+        assert_eq!(heights, vec![(0., 10.), (5., 10.)]);
+    }
+
+    #[test]
+    fn root_and_body_fill_the_viewport_in_quirks_mode() {
+        // The body is as tall as the viewport less its 8px margins, and the percentage height
+        // of the div resolves against that.
+        assert_eq!(heights("<div></div>"), (600., 584., 292.));
+    }
+
+    #[test]
+    fn orthogonal_flow_roots_are_sized_against_the_available_space() {
+        let (_, box_tree) = laid_out_document(
+            "<!DOCTYPE html><div id=vertical><div></div><div></div></div><div id=after></div>",
+            "#vertical { writing-mode: vertical-lr } #vertical div { width: 10px } \
+             #after { height: 5px }",
+        );
+        let body = &box_tree.children().unwrap()[0];
+        let content_rect = |layout_box: &LayoutBox| {
+            let content = layout_box.dimensions().content;
+            (
+                content.start_x,
+                content.start_y,
+                content.width.px(),
+                content.height.px(),
+            )
+        };
+        let vertical = &body.children().unwrap()[0];
+        // The height of the body depends on its contents, so the vertical box is as tall as the
+        // viewport, and as wide as its contents.
+        assert_eq!(content_rect(vertical), (8., 8., 20., 600.));
+        let children = vertical.children().unwrap();
+        assert_eq!(content_rect(&children[0]), (8., 8., 10., 600.));
+        assert_eq!(content_rect(&children[1]), (18., 8., 10., 600.));
+        let after = &body.children().unwrap()[1];
+        assert_eq!(content_rect(after), (8., 608., 784., 5.));
+    }
+
+    #[test]
+    fn vertical_rl_orthogonal_flow_roots_lay_out_their_contents_from_the_right() {
+        let (_, box_tree) = laid_out_document(
+            "<!DOCTYPE html><div id=vertical><div></div><div></div></div>\
+             <div id=specified><div></div></div><div id=after></div>",
+            "#vertical, #specified { writing-mode: vertical-rl } \
+             #vertical div, #specified div { width: 10px } #specified { width: 50px } \
+             #after { height: 5px }",
+        );
+        let body = &box_tree.children().unwrap()[0];
+        let content_rect = |layout_box: &LayoutBox| {
+            let content = layout_box.dimensions().content;
+            (
+                content.start_x,
+                content.start_y,
+                content.width.px(),
+                content.height.px(),
+            )
+        };
+        let vertical = &body.children().unwrap()[0];
+        assert_eq!(content_rect(vertical), (8., 8., 20., 600.));
+        let children = vertical.children().unwrap();
+        assert_eq!(content_rect(&children[0]), (18., 8., 10., 600.));
+        assert_eq!(content_rect(&children[1]), (8., 8., 10., 600.));
+        // A box with a specified block size lays out its contents from the end of it.
+        let specified = &body.children().unwrap()[1];
+        assert_eq!(content_rect(specified), (8., 608., 50., 600.));
+        let child = &specified.children().unwrap()[0];
+        assert_eq!(content_rect(child), (48., 608., 10., 600.));
+        let after = &body.children().unwrap()[2];
+        assert_eq!(content_rect(after), (8., 1208., 784., 5.));
+    }
+
+    #[test]
+    fn vertical_rl_orthogonal_flow_roots_lay_out_lines_from_the_right() {
+        let (_, box_tree) = laid_out_document(
+            "<!DOCTYPE html><div class=vertical><div><span><img></span></div></div>\
+             <div class=vertical id=specified><div><span><img></span></div></div>",
+            ".vertical { writing-mode: vertical-rl } #specified { width: 10px } \
+             img { width: 10px; height: 20px } span { padding-left: 2px }",
+        );
+        // The rects of everything laid out in `root`, relative to its content box.
+        let rects = |root: &LayoutBox| {
+            let origin = root.dimensions().content;
+            let relative = |rect: Rect| {
+                let rect = rect.translated(-origin.start_x, -origin.start_y);
+                (
+                    rect.start_x,
+                    rect.start_y,
+                    rect.width.px(),
+                    rect.height.px(),
+                )
+            };
+            let inner = &root.children().unwrap()[0];
+            let anonymous_block = &inner.children().unwrap()[0];
+            let root_inline_box = match &anonymous_block.children().unwrap()[0] {
+                LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(
+                    InlineLevelBox::AnonymousInline(root_inline_box),
+                )) => root_inline_box,
+                _ => panic!("expected the root inline box of the inner block"),
+            };
+            let line = &root_inline_box.lines()[0];
+            let img = &root_inline_box.children()[0].children().unwrap()[0];
+            (
+                relative(inner.dimensions().content),
+                relative(line.rect()),
+                line.baseline() - origin.start_x,
+                relative(line.inline_box_fragments()[0].rect()),
+                relative(img.dimensions().content),
+            )
+        };
+        let body = &box_tree.children().unwrap()[0];
+        let auto = &body.children().unwrap()[0];
+        let specified = &body.children().unwrap()[1];
+        // The contents of a box with an `auto` block size are laid out as if it had been specified.
+        assert_eq!(auto.dimensions().content.width.px(), 10.);
+        assert_eq!(rects(auto), rects(specified));
+    }
+
+    #[test]
+    fn absolutely_positioned_boxes_are_out_of_flow_at_their_static_position() {
+        let (_, box_tree) = laid_out_document(
+            "<!DOCTYPE html><div id=parent><div></div><span id=abspos></span><div></div></div>",
+            "div { height: 10px } #parent { height: auto } \
+             #abspos { position: absolute; height: 20px }",
+        );
+        let body = &box_tree.children().unwrap()[0];
+        let parent = &body.children().unwrap()[0];
+        let start_ys_and_heights = parent
+            .children()
+            .unwrap()
+            .iter()
+            .map(|child| {
+                let content = child.dimensions().content;
+                (content.start_y, content.height.px())
+            })
+            .collect::<Vec<_>>();
+        // The `<span>` is blockified and follows a block-level box rather than inline-level
+        // content, so it's a sibling of the `<div>`s, placed where the next one goes.  It neither
+        // moves the `<div>` after it nor adds to its parent's height.
+        assert_eq!(
+            start_ys_and_heights,
+            vec![(8., 10.), (18., 20.), (18., 10.)]
+        );
+        assert_eq!(parent.dimensions().content.height.px(), 20.);
+    }
+
+    #[test]
+    fn absolutely_positioned_boxes_shrink_to_fit_their_contents() {
+        let (_, box_tree) = laid_out_document(
+            "<!DOCTYPE html><div id=narrow>ab</div><div id=wide>a b c d e f g h i j k l m n \
+             o p q r s t u v w x y z</div>",
+            "div { position: absolute; padding-left: 5px }",
+        );
+        // Out-of-flow boxes are kept among the inline-level content of `<body>`, which has none
+        // otherwise.
+        let body = &box_tree.children().unwrap()[0];
+        let root_inline_box = &body.children().unwrap()[0].children().unwrap()[0];
+        let widths = root_inline_box
+            .children()
+            .unwrap()
+            .iter()
+            .map(|child| child.dimensions().content.width.px())
+            .collect::<Vec<_>>();
+        // Each glyph of the test font is 16px wide.  The first `<div>` is as wide as its text,
+        // while the second one's text wraps to fit within its containing block, the 784px wide
+        // content box of `<body>`, less its padding.
+        assert_eq!(widths, vec![32., 779.]);
+    }
+}
@@ -1,15 +1,27 @@
 use crate::apply_page_relative_properties_base_box_passthrough_impls;
 use crate::dom::tree::NodeRef;
+use crate::gfx::glyph::GlyphRun;
+use crate::layout::baseline::baselines;
 use crate::layout::behavior::{ApplyPageRelativeProperties, BaseLayoutBoxBehavior};
 use crate::layout::containing_block::ContainingBlock;
 use crate::layout::dimensions::Dimensions;
+use crate::layout::flow::{FlowSide, OriginRelativeProgression};
 use crate::layout::formatting_context::FormattingContextRef;
 use crate::layout::layout_box::{BaseBox, LayoutBox};
-use crate::layout::{DumpLayoutFormat, Layout, LayoutContext};
+use crate::layout::rect::Rect;
+use crate::layout::replaced::ReplacedBox;
+use crate::layout::text::{shape_text, ShapedText};
+use crate::layout::DumpLayoutVerbosity;
+use crate::layout::{DumpLayout, DumpLayoutFormat, Layout, LayoutContext};
 use crate::layout_box_behavior_base_box_passthrough_impls;
-use crate::style::values::computed::ComputedValues;
+use crate::style::values::computed::length::CSSPixelLength;
+use crate::style::values::computed::{ComputedValues, WritingMode};
+use crate::style::values::used::ToPx;
+use crate::style::values::CSSFloat;
 use accountable_refcell::Ref;
 use enum_dispatch::enum_dispatch;
+use std::io::Write;
+use std::ops::Range;
 
 /// Content that participates in inline layout. Specifically, inline-level boxes and text runs.
 ///
@@ -88,6 +100,11 @@ pub enum InlineLevelBox {
     ///
     /// https://drafts.csswg.org/css-display/#inline-box
     InlineBox(InlineBox),
+    /// An inline-level box generated by a replaced element, such as an `<img>` or form control.
+    /// Replaced inline-level boxes are atomic inlines, laid out as a single unit within a line.
+    ///
+    /// https://drafts.csswg.org/css-display-3/#atomic-inline
+    Replaced(ReplacedBox),
 }
 
 impl InlineLevelBox {
@@ -95,27 +112,46 @@ impl InlineLevelBox {
         match self {
             InlineLevelBox::AnonymousInline(aib) => aib.children.push(new_child),
             InlineLevelBox::InlineBox(ib) => ib.children.push(new_child),
+            InlineLevelBox::Replaced(_) => panic!("tried to add child to replaced box"),
         }
     }
 
-    pub fn children(&self) -> &Vec<LayoutBox> {
+    /// Returns the children of this box, or `None` if this box can't have children.
+    pub fn children(&self) -> Option<&Vec<LayoutBox>> {
+        match self {
+            InlineLevelBox::AnonymousInline(aib) => Some(aib.children()),
+            InlineLevelBox::InlineBox(ib) => Some(ib.children()),
+            InlineLevelBox::Replaced(_) => None,
+        }
+    }
+
+    /// Returns the children of this box mutably, or `None` if this box can't have children.
+    pub fn children_mut(&mut self) -> Option<&mut Vec<LayoutBox>> {
         match self {
-            InlineLevelBox::AnonymousInline(aib) => aib.children(),
-            InlineLevelBox::InlineBox(ib) => ib.children(),
+            InlineLevelBox::AnonymousInline(aib) => Some(&mut aib.children),
+            InlineLevelBox::InlineBox(ib) => Some(&mut ib.children),
+            InlineLevelBox::Replaced(_) => None,
         }
     }
 
     pub fn is_anonymous_inline(&self) -> bool {
         match self {
             InlineLevelBox::AnonymousInline(_) => true,
-            InlineLevelBox::InlineBox(_) => false,
+            InlineLevelBox::InlineBox(_) | InlineLevelBox::Replaced(_) => false,
         }
     }
 }
 
 impl Layout for InlineLevelBox {
-    fn layout(&mut self, _context: LayoutContext) {
-        unimplemented!()
+    fn layout(&mut self, context: LayoutContext) {
+        match self {
+            InlineLevelBox::AnonymousInline(aib) => aib.layout(context),
+            // Other inline-level boxes are laid out by the line layout of the root inline box they
+            // belong to.
+            InlineLevelBox::InlineBox(_) | InlineLevelBox::Replaced(_) => {
+                unimplemented!("layout called on inline-level box outside of a root inline box")
+            }
+        }
     }
 }
 
@@ -123,6 +159,8 @@ impl Layout for InlineLevelBox {
 pub struct AnonymousInlineBox {
     base: BaseBox,
     children: Vec<LayoutBox>,
+    /// The line boxes the children were split into by the last layout.
+    lines: Vec<LineBox>,
 }
 
 impl AnonymousInlineBox {
@@ -130,12 +168,854 @@ impl AnonymousInlineBox {
         Self {
             base: BaseBox::new(node, formatting_context),
             children: Vec::new(),
+            lines: Vec::new(),
         }
     }
 
     pub fn children(&self) -> &Vec<LayoutBox> {
         &self.children
     }
+
+    pub fn lines(&self) -> &[LineBox] {
+        &self.lines
+    }
+
+    /// Moves the lines this box was laid out into, and the fragments on them, `x` pixels right and
+    /// `y` pixels down (see `LayoutBox::translate`).  `writing_mode` is the one the lines were laid
+    /// out in, whose block axis their baselines are coordinates in.
+    pub fn translate_lines(&mut self, x: CSSFloat, y: CSSFloat, writing_mode: WritingMode) {
+        for line in &mut self.lines {
+            line.rect = line.rect.translated(x, y);
+            line.baseline += if writing_mode.is_horizontal() { y } else { x };
+            for fragment in &mut line.inline_box_fragments {
+                fragment.dimensions.content = fragment.dimensions.content.translated(x, y);
+            }
+            for fragment in &mut line.text_fragments {
+                fragment.rect = fragment.rect.translated(x, y);
+            }
+        }
+    }
+}
+
+impl Layout for AnonymousInlineBox {
+    /// Lays out this box as a root inline box, which fills the inline-size of its containing block
+    /// and is as tall as the line boxes its contents are split into.
+    fn layout(&mut self, context: LayoutContext) {
+        let LayoutContext { containing_block } = context;
+        let writing_mode = containing_block.writing_mode();
+        let dimensions = self.base.dimensions_mut();
+        dimensions.set_inline_size(containing_block.self_relative_inline_size(), writing_mode);
+        dimensions.set_inline_start_coord(
+            containing_block.self_relative_inline_start_coord(),
+            writing_mode,
+        );
+        dimensions.set_block_start_coord(
+            containing_block.self_relative_block_start_coord(),
+            writing_mode,
+        );
+        let (block_size, lines) = layout_lines(
+            &mut self.children,
+            // The root inline box is anonymous, so it's skipped over when resolving percentages.
+            containing_block
+                .nested(
+                    dimensions.content,
+                    containing_block.direction(),
+                    writing_mode,
+                )
+                .with_definite_height(containing_block.definite_height()),
+        );
+        self.lines = lines;
+        self.base
+            .dimensions_mut()
+            .set_block_size(block_size, writing_mode);
+    }
+}
+
+/// A line box: a row of the inline-level content of a root inline box.
+///
+/// https://drafts.csswg.org/css-inline-3/#line-box
+#[derive(Clone, Debug)]
+pub struct LineBox {
+    /// The area of the line, which spans the inline-size of the root inline box.
+    rect: Rect,
+    /// The block-axis coordinate of the line's baseline, i.e. a y-coordinate in horizontal
+    /// writing modes and an x-coordinate in vertical ones.  The contents of the line are aligned
+    /// by it.
+    baseline: CSSFloat,
+    /// The parts of inline boxes placed on the line, in tree order.
+    inline_box_fragments: Vec<InlineBoxFragment>,
+    /// The text placed on the line, in the order it was placed.
+    text_fragments: Vec<TextFragment>,
+    /// Whether any atomic inlines were placed on the line.
+    has_atomic_inlines: bool,
+}
+
+impl LineBox {
+    pub fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    pub fn baseline(&self) -> CSSFloat {
+        self.baseline
+    }
+
+    /// Whether this is a phantom line box, which doesn't count as a line for anything but
+    /// positioning its contents (e.g. when finding the baselines of its block container).  Per
+    /// https://drafts.csswg.org/css-inline-3/#invisible-line-boxes:
+    ///   > Line boxes that contain no text, no preserved white space, no inline boxes with
+    ///     non-zero inline-axis margins, padding, or borders, and no other in-flow content (such
+    ///     as atomic inlines or ruby annotations), and do not end with a forced line break are
+    ///     phantom line boxes.
+    ///
+    /// Inline boxes on a line without text or atomic inlines have no content, so they have
+    /// inline-axis edges if and only if they take up any of the line.  `writing_mode` is the one
+    /// the line was laid out in.
+    pub fn is_phantom(&self, writing_mode: WritingMode) -> bool {
+        self.text_fragments.is_empty()
+            && !self.has_atomic_inlines
+            && self.inline_box_fragments.iter().all(|fragment| {
+                fragment
+                    .dimensions()
+                    .margin_box_inline_size(writing_mode)
+                    .px()
+                    == 0.
+            })
+    }
+
+    pub fn inline_box_fragments(&self) -> &[InlineBoxFragment] {
+        &self.inline_box_fragments
+    }
+
+    pub fn text_fragments(&self) -> &[TextFragment] {
+        &self.text_fragments
+    }
+}
+
+/// The part of an inline box that's placed on a line (see `InlineBox::lay_out_fragments`).
+#[derive(Clone, Debug)]
+pub struct InlineBoxFragment {
+    /// The element that generated the inline box.
+    node: NodeRef,
+    /// The fragment's content box and edges.  Edges the fragment was sliced at are empty.
+    dimensions: Dimensions,
+}
+
+impl InlineBoxFragment {
+    pub fn node(&self) -> &NodeRef {
+        &self.node
+    }
+
+    pub fn dimensions(&self) -> Dimensions {
+        self.dimensions
+    }
+
+    /// The fragment's border box.
+    pub fn rect(&self) -> Rect {
+        self.dimensions.border_box()
+    }
+}
+
+/// The part of a text run that's placed on a line.
+#[derive(Clone, Debug)]
+pub struct TextFragment {
+    /// The text node the fragment's text run was generated by.
+    node: NodeRef,
+    text: String,
+    /// The range of the fragment's characters (not bytes) within its text run.
+    char_range: Range<usize>,
+    rect: Rect,
+    /// The distance from the top of `rect` to the baseline the fragment's glyphs are placed on.
+    ascent: CSSFloat,
+    /// The glyphs of the fragment's text, placed relative to the start of its baseline.
+    runs: Vec<GlyphRun>,
+    /// The x-coordinate of each boundary between the fragment's characters, including those at
+    /// its start and end, relative to the left edge of `rect` (see `ShapedText::char_edges`).
+    char_edges: Vec<CSSFloat>,
+}
+
+impl TextFragment {
+    pub fn node(&self) -> &NodeRef {
+        &self.node
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn char_range(&self) -> Range<usize> {
+        self.char_range.clone()
+    }
+
+    pub fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    pub fn runs(&self) -> &[GlyphRun] {
+        &self.runs
+    }
+
+    /// The start (left end) of the baseline the fragment's glyphs are placed relative to.
+    ///
+    /// TODO: Text is laid out horizontally even in vertical writing modes.
+    pub fn baseline_start(&self) -> (CSSFloat, CSSFloat) {
+        (self.rect.start_x, self.rect.start_y + self.ascent)
+    }
+
+    /// The rect covering the given characters of the fragment's text run (clamped to those of the
+    /// fragment), used to paint the selection.
+    pub fn chars_rect(&self, chars: Range<usize>) -> Rect {
+        let edge = |index: usize| {
+            let index = index.clamp(self.char_range.start, self.char_range.end);
+            self.char_edges[index - self.char_range.start]
+        };
+        let (start, end) = (edge(chars.start), edge(chars.end));
+        Rect {
+            start_x: self.rect.start_x + start.min(end),
+            start_y: self.rect.start_y,
+            width: CSSPixelLength::new((end - start).abs()),
+            height: self.rect.height,
+        }
+    }
+
+    /// The index (within its text run) of the boundary between the fragment's characters that's
+    /// closest to the x-coordinate `x`.
+    pub fn char_offset_at(&self, x: CSSFloat) -> usize {
+        let x = x - self.rect.start_x;
+        let closest = (0..self.char_edges.len())
+            .min_by(|&a, &b| {
+                (self.char_edges[a] - x)
+                    .abs()
+                    .partial_cmp(&(self.char_edges[b] - x).abs())
+                    .unwrap()
+            })
+            .unwrap_or(0);
+        self.char_range.start + closest
+    }
+}
+
+impl DumpLayout for LineBox {
+    fn dump_layout<W: Write>(
+        &self,
+        write_to: &mut W,
+        indent_spaces: usize,
+        _verbosity: DumpLayoutVerbosity,
+    ) {
+        writeln!(
+            write_to,
+            "{:indent_spaces$}LineBox at ({}, {}) size {}x{} baseline {}",
+            "",
+            self.rect.start_x.dump_layout_format(),
+            self.rect.start_y.dump_layout_format(),
+            self.rect.width.dump_layout_format(),
+            self.rect.height.dump_layout_format(),
+            self.baseline.dump_layout_format(),
+            indent_spaces = indent_spaces,
+        )
+        .expect("error writing layout dump");
+        for fragment in &self.inline_box_fragments {
+            writeln!(
+                write_to,
+                "{:indent_spaces$}InlineBoxFragment {} at ({}, {}) size {}x{}",
+                "",
+                fragment.node.data().dump_layout_format(),
+                fragment.rect().start_x.dump_layout_format(),
+                fragment.rect().start_y.dump_layout_format(),
+                fragment.rect().width.dump_layout_format(),
+                fragment.rect().height.dump_layout_format(),
+                indent_spaces = indent_spaces + 2,
+            )
+            .expect("error writing layout dump");
+        }
+        for fragment in &self.text_fragments {
+            writeln!(
+                write_to,
+                "{:indent_spaces$}TextFragment {:?} chars {}..{} at ({}, {}) size {}x{}",
+                "",
+                fragment.text,
+                fragment.char_range.start,
+                fragment.char_range.end,
+                fragment.rect.start_x.dump_layout_format(),
+                fragment.rect.start_y.dump_layout_format(),
+                fragment.rect.width.dump_layout_format(),
+                fragment.rect.height.dump_layout_format(),
+                indent_spaces = indent_spaces + 2,
+            )
+            .expect("error writing layout dump");
+        }
+    }
+}
+
+/// The position of a leaf of inline-level content (a text run or an atomic inline) within the lines
+/// of a root inline box.
+#[derive(Clone, Copy, Debug)]
+struct LinePlacement {
+    /// The index of the line the leaf was placed on.
+    line: usize,
+    /// The distance from the inline-start edge of the line to the inline-start margin edge of the
+    /// leaf.
+    inline_offset: CSSPixelLength,
+}
+
+/// The part of a text run placed on a line by breaking it into lines.
+#[derive(Clone, Debug)]
+struct TextPlacement {
+    /// The index of the line the text was placed on.
+    line: usize,
+    /// The distance from the inline-start edge of the line to the start of the text.
+    inline_offset: CSSPixelLength,
+    /// The byte range of the text within its text run.
+    range: Range<usize>,
+    /// The text as it was shaped for its line.
+    shaped: ShapedText,
+}
+
+/// Where the part of an inline box that's on one line is, as decided by breaking the contents of
+/// its root inline box into lines.
+#[derive(Clone, Copy, Debug)]
+struct FragmentExtent {
+    /// The index of the line the fragment is on.
+    line: usize,
+    /// The distances from the inline-start edge of the line to the inline-start and inline-end
+    /// edges of the fragment's margin box.
+    inline_start: CSSPixelLength,
+    inline_end: CSSPixelLength,
+    /// How far the box's contents on the line extend before and after its baseline.
+    baseline_extent: BaselineExtent,
+}
+
+/// How far the contents of a line (or of an inline box on a line) extend before and after the
+/// line's baseline in the block axis.
+#[derive(Clone, Copy, Debug)]
+struct BaselineExtent {
+    ascent: CSSPixelLength,
+    descent: CSSPixelLength,
+}
+
+impl BaselineExtent {
+    fn empty() -> BaselineExtent {
+        BaselineExtent {
+            ascent: CSSPixelLength::new(0.),
+            descent: CSSPixelLength::new(0.),
+        }
+    }
+
+    /// Extends this to cover `other` too.
+    fn include(&mut self, other: BaselineExtent) {
+        self.ascent = self.ascent.max(other.ascent);
+        self.descent = self.descent.max(other.descent);
+    }
+
+    fn block_size(self) -> CSSPixelLength {
+        self.ascent + self.descent
+    }
+}
+
+/// The extent of `leaf` (a text run or an atomic inline) around the baseline it's aligned by
+/// within its line, which is its last baseline (see `baselines`), or the block-end edge of its
+/// margin box if it has none.  Per https://www.w3.org/TR/CSS2/visudet.html#line-height:
+///   > The baseline of an 'inline-block' is the baseline of its last line box in the normal flow,
+///     unless it has either no in-flow line boxes or if its 'overflow' property has a computed
+///     value other than 'visible', in which case the baseline is the bottom margin edge.
+///
+/// TODO: Support values of `vertical-align` other than `baseline`.
+fn leaf_baseline_extent(leaf: &LayoutBox, writing_mode: WritingMode) -> BaselineExtent {
+    let dimensions = leaf.dimensions();
+    let margin_box_block_size = dimensions.margin_box_block_size(writing_mode);
+    // The baselines of atomic inlines are only found in horizontal writing modes so far (see
+    // `ReplacedBox::baselines`).
+    let ascent = match baselines(leaf) {
+        Some(baselines) if writing_mode.is_horizontal() => {
+            CSSPixelLength::new(baselines.last - dimensions.margin_box().start_y)
+        }
+        _ => margin_box_block_size,
+    };
+    BaselineExtent {
+        ascent,
+        descent: margin_box_block_size - ascent,
+    }
+}
+
+/// Decides which line each leaf of the inline-level content of a root inline box goes on, and
+/// where the inline boxes around the leaves start and end on each line they span.  Leaves and
+/// inline boxes are recorded in tree order, so they can be positioned by walking the box tree in
+/// the same order once the size of every line is known.
+struct LineBreaker {
+    containing_block: ContainingBlock,
+    /// The extent of the contents of each line so far around its baseline.
+    line_extents: Vec<BaselineExtent>,
+    /// How much of the inline-size of the last line is taken up.
+    line_inline_size: CSSPixelLength,
+    /// Where each leaf other than a text run went, in tree order.
+    leaf_placements: Vec<LinePlacement>,
+    /// The parts of each text run placed on lines, in tree order.
+    text_placements: Vec<Vec<TextPlacement>>,
+    /// The fragments of each inline box, in tree order.
+    inline_box_fragments: Vec<Vec<FragmentExtent>>,
+    /// The inline boxes that have started, along with the size of their inline-start margin,
+    /// border, and padding, whose first fragment hasn't been placed yet.  An inline box's start
+    /// goes on the same line as its first content, so they wait to be placed with it.
+    pending_inline_boxes: Vec<(usize, CSSPixelLength)>,
+    /// The inline boxes with a fragment on the last line that haven't ended yet, outermost first.
+    open_inline_boxes: Vec<usize>,
+}
+
+impl LineBreaker {
+    fn new(containing_block: ContainingBlock) -> LineBreaker {
+        LineBreaker {
+            containing_block,
+            line_extents: vec![BaselineExtent::empty()],
+            line_inline_size: CSSPixelLength::new(0.),
+            leaf_placements: Vec::new(),
+            text_placements: Vec::new(),
+            inline_box_fragments: Vec::new(),
+            pending_inline_boxes: Vec::new(),
+            open_inline_boxes: Vec::new(),
+        }
+    }
+
+    /// Sizes `children` and breaks them into lines, along with the contents of any inline boxes
+    /// among them.
+    fn break_children(&mut self, children: &mut [LayoutBox]) {
+        let writing_mode = self.containing_block.writing_mode();
+        let direction = self.containing_block.direction();
+        for child in children {
+            match child {
+                LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(
+                    InlineLevelBox::InlineBox(inline_box),
+                )) => {
+                    inline_box.set_edges(self.containing_block);
+                    let dimensions = inline_box.dimensions();
+                    let index = self.inline_box_fragments.len();
+                    self.inline_box_fragments.push(Vec::new());
+                    self.pending_inline_boxes.push((
+                        index,
+                        dimensions.get_mbp(FlowSide::InlineStart, writing_mode, direction),
+                    ));
+                    self.break_children(&mut inline_box.children);
+                    // Empty inline boxes still take up the space of their edges.
+                    if self
+                        .pending_inline_boxes
+                        .iter()
+                        .any(|&(pending, _)| pending == index)
+                    {
+                        self.place(CSSPixelLength::new(0.), BaselineExtent::empty());
+                    }
+                    // An inline box's end goes on the same line as its last content, even if its
+                    // inline-end edges overflow the line.
+                    self.line_inline_size +=
+                        dimensions.get_mbp(FlowSide::InlineEnd, writing_mode, direction);
+                    self.end_fragment(index);
+                    self.open_inline_boxes.pop();
+                }
+                LayoutBox::InlineLevel(InlineLevelContent::TextRun(text_run)) => {
+                    let placements =
+                        self.break_text(&text_run.contents, &text_run.computed_values());
+                    self.text_placements.push(placements);
+                }
+                // Absolutely positioned boxes are out of flow, so they take up none of the line,
+                // and are laid out where the next leaf would go once the line is positioned.
+                _ if child.is_absolutely_positioned() => self.leaf_placements.push(LinePlacement {
+                    line: self.line_extents.len() - 1,
+                    inline_offset: self.line_inline_size,
+                }),
+                _ => {
+                    size_inline_level_item(child, self.containing_block);
+                    let placement = self.place(
+                        child.dimensions().margin_box_inline_size(writing_mode),
+                        leaf_baseline_extent(child, writing_mode),
+                    );
+                    self.leaf_placements.push(placement);
+                }
+            }
+        }
+    }
+
+    /// Places a leaf taking up `inline_size` on its line, and extending `baseline_extent` around
+    /// the line's baseline, after the inline-start edges of the inline boxes it's the first
+    /// content of.  The leaf (with those edges) goes on a new line if it doesn't fit on the last one.
+    ///
+    /// Leaves are only moved to a new line if there's already something on the last one, since a
+    /// leaf too big for any line would otherwise never be placed.
+    fn place(
+        &mut self,
+        inline_size: CSSPixelLength,
+        baseline_extent: BaselineExtent,
+    ) -> LinePlacement {
+        if self.line_inline_size > CSSPixelLength::new(0.) && inline_size > self.available_space() {
+            self.break_line();
+        }
+        self.place_on_last_line(inline_size, baseline_extent)
+    }
+
+    /// Places a leaf on the last line like `place`, whether or not it fits.
+    fn place_on_last_line(
+        &mut self,
+        inline_size: CSSPixelLength,
+        baseline_extent: BaselineExtent,
+    ) -> LinePlacement {
+        let line = self.line_extents.len() - 1;
+        for (index, edges) in std::mem::take(&mut self.pending_inline_boxes) {
+            self.inline_box_fragments[index].push(FragmentExtent {
+                line,
+                inline_start: self.line_inline_size,
+                inline_end: self.line_inline_size,
+                baseline_extent: BaselineExtent::empty(),
+            });
+            self.line_inline_size += edges;
+            self.open_inline_boxes.push(index);
+        }
+        let placement = LinePlacement {
+            line,
+            inline_offset: self.line_inline_size,
+        };
+        self.line_inline_size += inline_size;
+        self.line_extents[line].include(baseline_extent);
+        for &index in &self.open_inline_boxes {
+            let fragment = self.inline_box_fragments[index].last_mut().unwrap();
+            fragment.baseline_extent.include(baseline_extent);
+        }
+        placement
+    }
+
+    /// How much of the inline-size of the last line is left for the next leaf, after the
+    /// inline-start edges of the inline boxes waiting to be placed with it.
+    fn available_space(&self) -> CSSPixelLength {
+        let pending_edges = self
+            .pending_inline_boxes
+            .iter()
+            .fold(CSSPixelLength::new(0.), |total, &(_, edges)| total + edges);
+        self.containing_block.self_relative_inline_size() - self.line_inline_size - pending_edges
+    }
+
+    /// Breaks `text`, the contents of a text run styled by `style`, into parts that each go on a
+    /// line, and places them.  Lines break after the spaces between words, unless `white-space`
+    /// keeps them from wrapping, and at the newlines it preserves.  The spaces a line breaks at
+    /// are left out of the parts placed on either side of it.  A word too wide for any line
+    /// overflows the line it starts.
+    ///
+    /// TODO: Lines only break within text runs, not between a text run and the leaf after it, nor
+    /// within words (e.g. for `overflow-wrap`).  Text is laid out horizontally even in vertical
+    /// writing modes.  Lines left empty by consecutive preserved newlines take up no block size.
+    ///
+    /// https://drafts.csswg.org/css-text-3/#line-breaking
+    fn break_text(&mut self, text: &str, style: &ComputedValues) -> Vec<TextPlacement> {
+        let mut placements = Vec::new();
+        if !style.white_space.preserves_newlines() {
+            self.break_segment(text, 0, style, &mut placements);
+            return placements;
+        }
+        let mut segment_start = 0;
+        for (index, segment) in text.split('\n').enumerate() {
+            if index > 0 {
+                self.break_line();
+            }
+            self.break_segment(segment, segment_start, style, &mut placements);
+            segment_start += segment.len() + 1;
+        }
+        placements
+    }
+
+    /// Breaks `text`, a part of a text run without preserved newlines that starts at byte `offset`
+    /// of the run, into lines as `break_text` does, and adds its placements to `placements`.
+    fn break_segment(
+        &mut self,
+        text: &str,
+        offset: usize,
+        style: &ComputedValues,
+        placements: &mut Vec<TextPlacement>,
+    ) {
+        let wraps = style.white_space.wraps();
+        let mut start = 0;
+        // The rest of the text, shaped for where it starts on the last line.
+        let mut shaped = shape_text(text, style, self.line_inline_size.px());
+        while start < text.len() {
+            let rest = &text[start..];
+            let available_space = self.available_space().px();
+            let opportunities = if wraps {
+                break_opportunities(rest)
+            } else {
+                vec![rest.len()]
+            };
+            let fitting_end = opportunities
+                .iter()
+                .copied()
+                .take_while(|&end| {
+                    shaped.advance_before(rest[..end].trim_end_matches(' ').len())
+                        <= available_space
+                })
+                .last();
+            let end = match fitting_end {
+                Some(end) => end,
+                // Nothing fits after what's already on the line, so start a new one.
+                None if wraps && self.line_inline_size > CSSPixelLength::new(0.) => {
+                    self.break_line();
+                    if rest.contains('\t') {
+                        shaped = shape_text(rest, style, 0.);
+                    }
+                    continue;
+                }
+                None => opportunities[0],
+            };
+            let content_end = rest[..end].trim_end_matches(' ').len();
+            let piece = shaped.slice(0..content_end);
+            let placement = self.place_on_last_line(
+                CSSPixelLength::new(piece.advance),
+                BaselineExtent {
+                    ascent: CSSPixelLength::new(piece.ascent),
+                    descent: CSSPixelLength::new(piece.descent),
+                },
+            );
+            placements.push(TextPlacement {
+                line: placement.line,
+                inline_offset: placement.inline_offset,
+                range: offset + start..offset + start + content_end,
+                shaped: piece,
+            });
+            if end == rest.len() {
+                break;
+            }
+            self.break_line();
+            shaped = if rest.contains('\t') {
+                shape_text(&rest[end..], style, 0.)
+            } else {
+                shaped.slice(end..rest.len())
+            };
+            start += end;
+        }
+    }
+
+    /// Starts a new line, ending the fragments of the open inline boxes on the last one and
+    /// continuing them on the new one.
+    fn break_line(&mut self) {
+        for index in self.open_inline_boxes.clone() {
+            self.end_fragment(index);
+        }
+        self.line_extents.push(BaselineExtent::empty());
+        self.line_inline_size = CSSPixelLength::new(0.);
+        let line = self.line_extents.len() - 1;
+        for &index in &self.open_inline_boxes {
+            self.inline_box_fragments[index].push(FragmentExtent {
+                line,
+                inline_start: CSSPixelLength::new(0.),
+                inline_end: CSSPixelLength::new(0.),
+                baseline_extent: BaselineExtent::empty(),
+            });
+        }
+    }
+
+    /// Ends the last fragment of the inline box at `index` where the last line is taken up to.
+    fn end_fragment(&mut self, index: usize) {
+        let fragment = self.inline_box_fragments[index].last_mut().unwrap();
+        fragment.inline_end = self.line_inline_size;
+    }
+}
+
+/// The byte indices `text` can be broken into lines at, which are after each sequence of spaces,
+/// followed by its length.
+pub fn break_opportunities(text: &str) -> Vec<usize> {
+    let mut opportunities: Vec<usize> = text
+        .char_indices()
+        .zip(text.chars().skip(1))
+        .filter(|&((_, ch), next)| ch == ' ' && next != ' ')
+        .map(|((index, _), _)| index + 1)
+        .collect();
+    opportunities.push(text.len());
+    opportunities
+}
+
+/// Splits the given inline-level content into lines that fit in the inline-size of the containing
+/// block, stacked in the block direction starting at the block-start of the containing block.
+/// Returns the total block-size of the lines, along with the lines themselves.
+///
+/// Atomic inlines (i.e. replaced boxes) are placed as a unit, text runs are broken between words
+/// (see `LineBreaker::break_text`), and inline boxes are split into a fragment on each line their
+/// contents are placed on (see `InlineBox::lay_out_fragments`).  Leaves within a line are aligned
+/// by their baselines (see `leaf_baseline_extent`), and the line is just tall enough to fit them.
+///
+/// TODO: Alignment ignores the strut.
+///
+/// https://drafts.csswg.org/css-inline-3/#line-boxes
+fn layout_lines(
+    children: &mut [LayoutBox],
+    containing_block: ContainingBlock,
+) -> (CSSPixelLength, Vec<LineBox>) {
+    let writing_mode = containing_block.writing_mode();
+
+    // First, size each leaf and decide which line it goes on.
+    let mut line_breaker = LineBreaker::new(containing_block);
+    line_breaker.break_children(children);
+
+    // Then, with the size of every line known, position each leaf and inline box fragment within
+    // its line.
+    let mut lines = Vec::with_capacity(line_breaker.line_extents.len());
+    let mut lines_block_size = CSSPixelLength::new(0.);
+    for line_extent in &line_breaker.line_extents {
+        let line_block_size = line_extent.block_size();
+        let line_block_start =
+            containing_block.self_relative_block_start_coord() + lines_block_size;
+        let mut line_dimensions = Dimensions::default();
+        line_dimensions.set_inline_start_coord(
+            containing_block.self_relative_inline_start_coord(),
+            writing_mode,
+        );
+        line_dimensions.set_block_start_coord(line_block_start.px(), writing_mode);
+        line_dimensions.set_inline_size(containing_block.self_relative_inline_size(), writing_mode);
+        line_dimensions.set_block_size(line_block_size, writing_mode);
+        lines.push(LineBox {
+            rect: line_dimensions.content,
+            baseline: (line_block_start + line_extent.ascent).px(),
+            inline_box_fragments: Vec::new(),
+            text_fragments: Vec::new(),
+            has_atomic_inlines: false,
+        });
+        lines_block_size += line_block_size;
+    }
+    position_children(
+        children,
+        &mut lines,
+        &mut line_breaker.leaf_placements.into_iter(),
+        &mut line_breaker.text_placements.into_iter(),
+        &mut line_breaker.inline_box_fragments.into_iter(),
+        containing_block,
+    );
+    (lines_block_size, lines)
+}
+
+/// Sizes `item` for layout in `containing_block`, if it's an atomic inline.
+fn size_inline_level_item(item: &mut LayoutBox, containing_block: ContainingBlock) {
+    if let LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(InlineLevelBox::Replaced(
+        replaced_box,
+    ))) = item
+    {
+        replaced_box.solve_and_set_atomic_inline_properties(containing_block);
+    }
+}
+
+/// Positions `children` (and the contents of inline boxes among them) on `lines`, where the line
+/// breaker placed them.  `leaf_placements`, `text_placements`, and `inline_box_fragments` are what
+/// it decided for each leaf, text run, and inline box, in tree order.  The text and inline box
+/// fragments placed are recorded on the lines.
+fn position_children(
+    children: &mut [LayoutBox],
+    lines: &mut [LineBox],
+    leaf_placements: &mut impl Iterator<Item = LinePlacement>,
+    text_placements: &mut impl Iterator<Item = Vec<TextPlacement>>,
+    inline_box_fragments: &mut impl Iterator<Item = Vec<FragmentExtent>>,
+    containing_block: ContainingBlock,
+) {
+    let writing_mode = containing_block.writing_mode();
+    let direction = containing_block.direction();
+    for child in children {
+        match child {
+            LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(
+                InlineLevelBox::InlineBox(inline_box),
+            )) => {
+                let extents = inline_box_fragments
+                    .next()
+                    .expect("inline box wasn't broken into lines");
+                inline_box.lay_out_fragments(&extents, lines, containing_block);
+                position_children(
+                    &mut inline_box.children,
+                    lines,
+                    leaf_placements,
+                    text_placements,
+                    inline_box_fragments,
+                    containing_block,
+                );
+                continue;
+            }
+            LayoutBox::InlineLevel(InlineLevelContent::TextRun(text_run)) => {
+                let placements = text_placements
+                    .next()
+                    .expect("text run wasn't broken into lines");
+                text_run.lay_out_fragments(placements, lines, containing_block);
+                continue;
+            }
+            _ => {}
+        }
+        let placement = leaf_placements
+            .next()
+            .expect("inline-level content wasn't broken into lines");
+        let line = &mut lines[placement.line];
+        if child.is_absolutely_positioned() {
+            lay_out_at_static_position(child, line, placement.inline_offset, containing_block);
+            continue;
+        }
+        let dimensions = child.dimensions();
+        let inline_start_coord =
+            content_inline_start_coord(dimensions, placement.inline_offset, containing_block);
+        let block_start_coord = CSSPixelLength::new(line.baseline)
+            - leaf_baseline_extent(child, writing_mode).ascent
+            + dimensions.get_mbp(FlowSide::BlockStart, writing_mode, direction);
+        let dimensions = child.dimensions_mut();
+        dimensions.set_inline_start_coord(inline_start_coord.px(), writing_mode);
+        dimensions.set_block_start_coord(block_start_coord.px(), writing_mode);
+        line.has_atomic_inlines = true;
+    }
+}
+
+/// Lays out the absolutely positioned box `child` at its static position among the inline-level
+/// content of its root inline box: with its margin box at the block-start of `line`,
+/// `inline_offset` from the line's inline-start edge.
+///
+/// https://drafts.csswg.org/css-position-3/#staticpos-rect
+///
+/// TODO: Insets aren't supported yet, so this is also where the box is positioned.
+fn lay_out_at_static_position(
+    child: &mut LayoutBox,
+    line: &LineBox,
+    inline_offset: CSSPixelLength,
+    containing_block: ContainingBlock,
+) {
+    let writing_mode = containing_block.writing_mode();
+    let direction = containing_block.direction();
+    child.layout(LayoutContext::new(containing_block));
+    let dimensions = child.dimensions();
+    let mut line_dimensions = Dimensions::default();
+    line_dimensions.content = line.rect;
+    let mut positioned = dimensions;
+    positioned.set_inline_start_coord(
+        content_inline_start_coord(dimensions, inline_offset, containing_block).px(),
+        writing_mode,
+    );
+    positioned.set_block_start_coord(
+        line_dimensions.get_block_start_coord(writing_mode)
+            + dimensions
+                .get_mbp(FlowSide::BlockStart, writing_mode, direction)
+                .px(),
+        writing_mode,
+    );
+    child.translate(
+        positioned.content.start_x - dimensions.content.start_x,
+        positioned.content.start_y - dimensions.content.start_y,
+    );
+}
+
+/// The self-relative inline-start coordinate of the content box of a box with the given
+/// `dimensions`, whose margin box starts `inline_offset` from the inline-start edge of its line.
+fn content_inline_start_coord(
+    dimensions: Dimensions,
+    inline_offset: CSSPixelLength,
+    containing_block: ContainingBlock,
+) -> CSSPixelLength {
+    let writing_mode = containing_block.writing_mode();
+    let direction = containing_block.direction();
+    match containing_block.inline_start_origin_relative_progression() {
+        OriginRelativeProgression::AwayFromOrigin => {
+            containing_block.self_relative_inline_start_coord()
+                + inline_offset
+                + dimensions.get_mbp(FlowSide::InlineStart, writing_mode, direction)
+        }
+        OriginRelativeProgression::TowardsOrigin => {
+            // The inline-start edge is the physically greater one, so the box's content starts
+            // after the margin, border, and padding on its inline-end side.
+            containing_block.self_relative_inline_start_coord()
+                + containing_block.self_relative_inline_size()
+                - inline_offset
+                - dimensions.margin_box_inline_size(writing_mode)
+                + dimensions.get_mbp(FlowSide::InlineEnd, writing_mode, direction)
+        }
+    }
 }
 
 impl BaseLayoutBoxBehavior for AnonymousInlineBox {
@@ -158,6 +1038,8 @@ impl DumpLayoutFormat for AnonymousInlineBox {
 pub struct InlineBox {
     base: BaseBox,
     children: Vec<LayoutBox>,
+    /// The parts of this box on each line it spans, from the last layout.
+    fragments: Vec<InlineBoxFragment>,
 }
 
 impl InlineBox {
@@ -165,12 +1047,144 @@ impl InlineBox {
         Self {
             base: BaseBox::new(node, formatting_context),
             children: Vec::new(),
+            fragments: Vec::new(),
         }
     }
 
     fn children(&self) -> &Vec<LayoutBox> {
         &self.children
     }
+
+    pub fn fragments(&self) -> &[InlineBoxFragment] {
+        &self.fragments
+    }
+
+    /// Moves the fragments of this box `x` pixels right and `y` pixels down (see
+    /// `LayoutBox::translate`).
+    pub fn translate_fragments(&mut self, x: CSSFloat, y: CSSFloat) {
+        for fragment in &mut self.fragments {
+            fragment.dimensions.content = fragment.dimensions.content.translated(x, y);
+        }
+    }
+
+    /// Sets the margins, borders, and padding of this box.  Those in the inline axis separate it
+    /// from the content around it, even when it's empty, while its borders and padding in the block
+    /// axis are painted around its content box without making its lines taller.  Margins in the
+    /// block axis don't apply to inline boxes.
+    ///
+    /// https://www.w3.org/TR/CSS2/visudet.html#inline-non-replaced
+    fn set_edges(&mut self, containing_block: ContainingBlock) {
+        let writing_mode = containing_block.writing_mode();
+        let direction = containing_block.direction();
+        // Percentage margins and padding refer to the inline-size of the containing block, even
+        // in the block axis.
+        let percentage_basis = containing_block.self_relative_inline_size();
+        let computed_values = self.computed_values();
+        let edges = [
+            FlowSide::BlockStart,
+            FlowSide::BlockEnd,
+            FlowSide::InlineStart,
+            FlowSide::InlineEnd,
+        ]
+        .iter()
+        .map(|&side| {
+            let margin = match side {
+                FlowSide::InlineStart | FlowSide::InlineEnd => computed_values
+                    .margin_flow_relative(side, writing_mode)
+                    .to_px(percentage_basis),
+                FlowSide::BlockStart | FlowSide::BlockEnd => CSSPixelLength::new(0.),
+            };
+            (
+                side,
+                margin,
+                computed_values.border_flow_relative(side, writing_mode),
+                computed_values
+                    .padding_flow_relative(side, writing_mode)
+                    .to_px(percentage_basis),
+            )
+        })
+        .collect::<Vec<_>>();
+        // Release this &self borrow so we can mutably borrow below.
+        drop(computed_values);
+
+        let dimensions = self.dimensions_mut();
+        for (side, margin, border, padding) in edges {
+            dimensions.set_margin(side, margin, writing_mode, direction);
+            dimensions.set_border(side, border, writing_mode, direction);
+            dimensions.set_padding(side, padding, writing_mode, direction);
+        }
+    }
+
+    /// Lays out a fragment of this box on each line it spans, as decided by breaking its root
+    /// inline box into lines, and records them on those lines.  Each fragment's content box spans
+    /// the box's contents on its line.
+    ///
+    /// Fragments are sliced out of the box as if it were laid out on a single line, so its
+    /// inline-start margin, border, and padding are only on its first fragment, and its inline-end
+    /// ones only on its last, while every fragment has its borders and padding in the block axis.
+    /// This box's own dimensions are those of its first fragment.
+    ///
+    /// https://drafts.csswg.org/css-break-3/#valdef-box-decoration-break-slice
+    fn lay_out_fragments(
+        &mut self,
+        extents: &[FragmentExtent],
+        lines: &mut [LineBox],
+        containing_block: ContainingBlock,
+    ) {
+        let writing_mode = containing_block.writing_mode();
+        let direction = containing_block.direction();
+        let node = self.node();
+        let edges = self.dimensions();
+        let zero = CSSPixelLength::new(0.);
+        self.fragments = extents
+            .iter()
+            .enumerate()
+            .map(|(index, extent)| {
+                let mut dimensions = edges;
+                let mut sliced_sides = Vec::new();
+                if index > 0 {
+                    sliced_sides.push(FlowSide::InlineStart);
+                }
+                if index < extents.len() - 1 {
+                    sliced_sides.push(FlowSide::InlineEnd);
+                }
+                for side in sliced_sides {
+                    dimensions.set_margin(side, zero, writing_mode, direction);
+                    dimensions.set_border(side, zero, writing_mode, direction);
+                    dimensions.set_padding(side, zero, writing_mode, direction);
+                }
+                dimensions.set_inline_size(
+                    extent.inline_end
+                        - extent.inline_start
+                        - dimensions.get_mbp(FlowSide::InlineStart, writing_mode, direction)
+                        - dimensions.get_mbp(FlowSide::InlineEnd, writing_mode, direction),
+                    writing_mode,
+                );
+                dimensions.set_inline_start_coord(
+                    content_inline_start_coord(dimensions, extent.inline_start, containing_block)
+                        .px(),
+                    writing_mode,
+                );
+                // The content box spans the box's contents on the line around its baseline, so the
+                // borders and padding in the block axis stick out of the line.
+                let line = &mut lines[extent.line];
+                dimensions.set_block_size(extent.baseline_extent.block_size(), writing_mode);
+                dimensions.set_block_start_coord(
+                    line.baseline - extent.baseline_extent.ascent.px(),
+                    writing_mode,
+                );
+                let fragment = InlineBoxFragment {
+                    node: node.clone(),
+                    dimensions,
+                };
+                line.inline_box_fragments.push(fragment.clone());
+                fragment
+            })
+            .collect();
+        if let Some(first_fragment) = self.fragments.first() {
+            *self.dimensions_mut() = first_fragment.dimensions;
+        }
+    }
 }
 
 impl BaseLayoutBoxBehavior for InlineBox {
@@ -203,6 +1217,8 @@ pub struct TextRun {
     /// TODO: This can be an owned String for now for simplicity's sake, but it would be probably
     /// be more efficient if this were a `&'DOM_LIFETIME str`.
     contents: String,
+    /// The parts of this run on each line it spans, from the last layout.
+    fragments: Vec<TextFragment>,
 }
 
 impl TextRun {
@@ -210,12 +1226,78 @@ impl TextRun {
         Self {
             base: BaseBox::new(node, formatting_context),
             contents,
+            fragments: Vec::new(),
         }
     }
 
     pub fn contents(&self) -> String {
         self.contents.clone()
     }
+
+    pub fn fragments(&self) -> &[TextFragment] {
+        &self.fragments
+    }
+
+    /// Moves the fragments of this run `x` pixels right and `y` pixels down (see
+    /// `LayoutBox::translate`).
+    pub fn translate_fragments(&mut self, x: CSSFloat, y: CSSFloat) {
+        for fragment in &mut self.fragments {
+            fragment.rect = fragment.rect.translated(x, y);
+        }
+    }
+
+    /// Lays out a fragment of this run for each part of it placed on a line, and records them on
+    /// those lines.  Each fragment's rect spans its text's advance and the extent of its fonts
+    /// around the line's baseline.  This run's own content box is the rect of its first fragment.
+    fn lay_out_fragments(
+        &mut self,
+        placements: Vec<TextPlacement>,
+        lines: &mut [LineBox],
+        containing_block: ContainingBlock,
+    ) {
+        let writing_mode = containing_block.writing_mode();
+        let node = self.node();
+        let contents = &self.contents;
+        self.fragments = placements
+            .into_iter()
+            .map(|placement| {
+                let TextPlacement {
+                    line,
+                    inline_offset,
+                    range,
+                    shaped,
+                } = placement;
+                let line = &mut lines[line];
+                let mut dimensions = Dimensions::default();
+                dimensions.set_inline_size(CSSPixelLength::new(shaped.advance), writing_mode);
+                dimensions.set_block_size(
+                    CSSPixelLength::new(shaped.ascent + shaped.descent),
+                    writing_mode,
+                );
+                dimensions.set_inline_start_coord(
+                    content_inline_start_coord(dimensions, inline_offset, containing_block).px(),
+                    writing_mode,
+                );
+                dimensions.set_block_start_coord(line.baseline - shaped.ascent, writing_mode);
+                let text = &contents[range.clone()];
+                let char_start = contents[..range.start].chars().count();
+                let fragment = TextFragment {
+                    node: node.clone(),
+                    text: text.to_owned(),
+                    char_range: char_start..char_start + text.chars().count(),
+                    rect: dimensions.content,
+                    ascent: shaped.ascent,
+                    char_edges: shaped.char_edges(text),
+                    runs: shaped.runs,
+                };
+                line.text_fragments.push(fragment.clone());
+                fragment
+            })
+            .collect();
+        if let Some(first_fragment) = self.fragments.first() {
+            self.base.dimensions_mut().content = first_fragment.rect;
+        }
+    }
 }
 
 impl BaseLayoutBoxBehavior for TextRun {
@@ -0,0 +1,476 @@
+//! Queries of the geometry of laid out boxes, for anything that needs to know where a box ended up
+//! (e.g. the `offsetTop` of scripts, hit testing, or developer tools).
+//!
+//! The boxes of the box tree are positioned in page coordinates: relative to the top left of the
+//! initial containing block, before the document is scrolled.
+
+use crate::dom::tree::NodeRef;
+use crate::layout::behavior::BaseLayoutBoxBehavior;
+use crate::layout::flow::block::BlockLevelBox;
+use crate::layout::flow::inline::{InlineLevelBox, InlineLevelContent};
+use crate::layout::layout_box::LayoutBox;
+use crate::layout::rect::{Point, Rect};
+use crate::style::container::QueryContainer;
+use crate::style::values::computed::{ContainerType, Position};
+use std::collections::HashSet;
+
+/// The geometry of a laid out box, in page coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoxGeometry {
+    /// The rectangle of the box's containing block, which is the initial containing block for
+    /// the root box.  Otherwise it's formed by the content box of its nearest block container
+    /// ancestor, unless the box is positioned: absolutely positioned boxes have the padding box
+    /// of their nearest ancestor that contains them (see
+    /// `LayoutBox::contains_absolutely_positioned_descendants`), and `position: fixed` boxes
+    /// that of the nearest one that contains those, or the initial containing block if there
+    /// isn't one.
+    ///
+    /// https://drafts.csswg.org/css2/#containing-block-details
+    /// https://drafts.csswg.org/css-transforms-1/#containing-block-for-all-descendants
+    pub containing_block: Rect,
+    pub margin_box: Rect,
+    pub border_box: Rect,
+    pub padding_box: Rect,
+    pub content_box: Rect,
+}
+
+impl BoxGeometry {
+    fn new(layout_box: &LayoutBox, containing_block: Rect) -> BoxGeometry {
+        let dimensions = layout_box.dimensions();
+        BoxGeometry {
+            containing_block,
+            margin_box: dimensions.margin_box(),
+            border_box: dimensions.border_box(),
+            padding_box: dimensions.padding_box(),
+            content_box: dimensions.content,
+        }
+    }
+
+    /// The offset of the top left of the border box from the top left of the containing block,
+    /// along the lines of `offsetLeft` and `offsetTop`.
+    ///
+    /// https://drafts.csswg.org/cssom-view/#extensions-to-the-htmlelement-interface
+    pub fn offset(&self) -> Point {
+        Point {
+            x: self.border_box.start_x - self.containing_block.start_x,
+            y: self.border_box.start_y - self.containing_block.start_y,
+        }
+    }
+}
+
+/// The geometry of the first box `node` generates in the box tree laid out in `root`, or `None`
+/// if it generates no boxes (or the box tree wasn't laid out).
+pub fn box_geometry(root: &LayoutBox, node: &NodeRef) -> Option<BoxGeometry> {
+    let containing_blocks = ContainingBlocks::initial(*root.laid_out_in()?.rect());
+    let mut path = Vec::new();
+    find_box(root, node, &mut path)?;
+    let (layout_box, containing_blocks) = path_containing_blocks(&path, containing_blocks).pop()?;
+    Some(BoxGeometry::new(
+        layout_box,
+        containing_blocks.of(layout_box).rect,
+    ))
+}
+
+/// The containing block chain of the first box `node` generates in the box tree laid out in
+/// `root`: the box's containing block, then the containing block of the box that's formed by, and
+/// so on, ending with the initial containing block.  `None` if `node` generates no boxes (or the
+/// box tree wasn't laid out).
+pub fn containing_block_chain(root: &LayoutBox, node: &NodeRef) -> Option<Vec<Rect>> {
+    let containing_blocks = ContainingBlocks::initial(*root.laid_out_in()?.rect());
+    let mut path = Vec::new();
+    find_box(root, node, &mut path)?;
+    let path = path_containing_blocks(&path, containing_blocks);
+    let mut chain = Vec::new();
+    let mut depth = Some(path.len() - 1);
+    while let Some(index) = depth {
+        let (layout_box, containing_blocks) = path[index];
+        let containing_block = containing_blocks.of(layout_box);
+        chain.push(containing_block.rect);
+        depth = containing_block.formed_by;
+    }
+    Some(chain)
+}
+
+/// Calls `visit` with each box of the box tree laid out in `root` and its geometry, in tree order.
+pub fn for_each_box_geometry<'a, F>(root: &'a LayoutBox, mut visit: F)
+where
+    F: FnMut(&'a LayoutBox, BoxGeometry),
+{
+    if let Some(initial_containing_block) = root.laid_out_in() {
+        let containing_blocks = ContainingBlocks::initial(*initial_containing_block.rect());
+        visit_box_geometries(root, containing_blocks, 0, &mut visit);
+    }
+}
+
+fn visit_box_geometries<'a, F>(
+    layout_box: &'a LayoutBox,
+    containing_blocks: ContainingBlocks,
+    depth: usize,
+    visit: &mut F,
+) where
+    F: FnMut(&'a LayoutBox, BoxGeometry),
+{
+    let containing_block = containing_blocks.of(layout_box).rect;
+    visit(layout_box, BoxGeometry::new(layout_box, containing_block));
+    let containing_blocks = containing_blocks.for_children_of(layout_box, depth);
+    if let Some(children) = laid_out_children(layout_box) {
+        for child in children {
+            visit_box_geometries(child, containing_blocks, depth + 1, visit);
+        }
+    }
+}
+
+/// A containing block that boxes can get from their ancestors, along with the depth in the box
+/// tree of the box that forms it (`None` for the initial containing block).
+#[derive(Clone, Copy, Debug)]
+struct AncestorContainingBlock {
+    rect: Rect,
+    formed_by: Option<usize>,
+}
+
+/// The containing blocks boxes get from their ancestors, depending on how they're positioned.
+///
+/// https://drafts.csswg.org/css-position/#def-cb
+#[derive(Clone, Copy, Debug)]
+struct ContainingBlocks {
+    in_flow: AncestorContainingBlock,
+    absolute: AncestorContainingBlock,
+    fixed: AncestorContainingBlock,
+}
+
+impl ContainingBlocks {
+    /// The containing blocks of the root box, which are all the initial containing block.
+    fn initial(initial_containing_block: Rect) -> ContainingBlocks {
+        let containing_block = AncestorContainingBlock {
+            rect: initial_containing_block,
+            formed_by: None,
+        };
+        ContainingBlocks {
+            in_flow: containing_block,
+            absolute: containing_block,
+            fixed: containing_block,
+        }
+    }
+
+    /// The containing block of `layout_box`, if these are the containing blocks of its siblings.
+    fn of(&self, layout_box: &LayoutBox) -> AncestorContainingBlock {
+        if !layout_box.is_absolutely_positioned() {
+            return self.in_flow;
+        }
+        match layout_box.computed_values().position {
+            Position::Fixed => self.fixed,
+            _ => self.absolute,
+        }
+    }
+
+    /// The containing blocks of the children of `layout_box`, which is `depth` boxes deep in the
+    /// box tree, if these are the containing blocks of its siblings.
+    fn for_children_of(self, layout_box: &LayoutBox, depth: usize) -> ContainingBlocks {
+        let formed_by = |rect| AncestorContainingBlock {
+            rect,
+            formed_by: Some(depth),
+        };
+        let padding_box = formed_by(layout_box.dimensions().padding_box());
+        ContainingBlocks {
+            in_flow: containing_block_for_children(layout_box).map_or(self.in_flow, formed_by),
+            absolute: if layout_box.contains_absolutely_positioned_descendants() {
+                padding_box
+            } else {
+                self.absolute
+            },
+            fixed: if layout_box.contains_fixed_position_descendants() {
+                padding_box
+            } else {
+                self.fixed
+            },
+        }
+    }
+}
+
+/// Finds the first box generated by `node` among `layout_box` and its descendants, pushing the
+/// boxes on the way to it, and then the box itself, onto `path`.
+fn find_box<'a>(
+    layout_box: &'a LayoutBox,
+    node: &NodeRef,
+    path: &mut Vec<&'a LayoutBox>,
+) -> Option<&'a LayoutBox> {
+    path.push(layout_box);
+    if layout_box.node() == *node {
+        return Some(layout_box);
+    }
+    let found = laid_out_children(layout_box).and_then(|children| {
+        children
+            .iter()
+            .find_map(|child| find_box(child, node, path))
+    });
+    if found.is_none() {
+        path.pop();
+    }
+    found
+}
+
+/// Pairs each box of `path`, which starts at the root box, with the containing blocks it gets
+/// from its ancestors, given those of the root box.
+fn path_containing_blocks<'a>(
+    path: &[&'a LayoutBox],
+    root_containing_blocks: ContainingBlocks,
+) -> Vec<(&'a LayoutBox, ContainingBlocks)> {
+    let mut containing_blocks = root_containing_blocks;
+    path.iter()
+        .enumerate()
+        .map(|(depth, layout_box)| {
+            let paired = (*layout_box, containing_blocks);
+            containing_blocks = containing_blocks.for_children_of(layout_box, depth);
+            paired
+        })
+        .collect()
+}
+
+/// Records the client rects of the nodes of the document laid out in `root` (see
+/// `NodeRef::client_rects`), replacing those recorded when it was last laid out.
+pub fn record_client_rects(root: &LayoutBox) {
+    let document = root.node().inclusive_ancestors().last().unwrap();
+    for node in document.shadow_including_inclusive_descendants() {
+        node.set_client_rects(Vec::new());
+    }
+    record_box_client_rects(root);
+}
+
+fn record_box_client_rects(layout_box: &LayoutBox) {
+    match layout_box {
+        // Anonymous boxes aren't generated by a node, and the text of text runs is recorded by
+        // the fragments of it on each line.
+        LayoutBox::BlockLevel(BlockLevelBox::AnonymousBlock(_))
+        | LayoutBox::InlineLevel(InlineLevelContent::TextRun(_)) => {}
+        LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(
+            InlineLevelBox::AnonymousInline(root_inline_box),
+        )) => {
+            for fragment in root_inline_box
+                .lines()
+                .iter()
+                .flat_map(|line| line.text_fragments())
+            {
+                fragment.node().push_client_rect(fragment.rect());
+            }
+        }
+        // Inline boxes have a client rect for each line they're on.
+        LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(InlineLevelBox::InlineBox(
+            inline_box,
+        ))) => {
+            for fragment in inline_box.fragments() {
+                layout_box.node().push_client_rect(fragment.rect());
+            }
+        }
+        _ => layout_box
+            .node()
+            .push_client_rect(layout_box.dimensions().border_box()),
+    }
+    if let Some(children) = laid_out_children(layout_box) {
+        for child in children {
+            record_box_client_rects(child);
+        }
+    }
+}
+
+/// Records what the query containers of the document laid out in `root` look like now (see
+/// `QueryContainer`), returning the elements that were resized, or became or stopped being query
+/// containers, since it was last laid out.  The `@container` rules of their descendants have to
+/// be evaluated anew.
+pub fn record_query_containers(root: &LayoutBox) -> HashSet<NodeRef> {
+    let mut changed = HashSet::new();
+    record_box_query_container(root, &mut changed);
+    changed
+}
+
+fn record_box_query_container(layout_box: &LayoutBox, changed: &mut HashSet<NodeRef>) {
+    // Only block containers can be query containers, as size containment doesn't apply to
+    // inline boxes.
+    if let LayoutBox::BlockLevel(BlockLevelBox::BlockContainer(_)) = layout_box {
+        let node = layout_box.node();
+        if let Some(element) = node.as_element() {
+            let computed_values = layout_box.computed_values();
+            let query_container = match computed_values.container_type {
+                ContainerType::Normal => None,
+                container_type => {
+                    let content_box = layout_box.dimensions().content;
+                    Some(QueryContainer {
+                        container_type,
+                        name: computed_values.container_name.clone(),
+                        width: content_box.width.px(),
+                        height: content_box.height.px(),
+                        horizontal: computed_values.writing_mode.is_horizontal(),
+                    })
+                }
+            };
+            if *element.query_container.borrow() != query_container {
+                *element.query_container.borrow_mut() = query_container;
+                changed.insert(node.clone());
+            }
+        }
+    }
+    if let Some(children) = laid_out_children(layout_box) {
+        for child in children {
+            record_box_query_container(child, changed);
+        }
+    }
+}
+
+/// The children of `layout_box` whose geometry is up to date, which are none if it skips its
+/// contents (see `LayoutBox::skips_contents`), as they aren't laid out.
+fn laid_out_children(layout_box: &LayoutBox) -> Option<&Vec<LayoutBox>> {
+    layout_box
+        .children()
+        .filter(|_| !layout_box.skips_contents())
+}
+
+/// The containing block `layout_box` forms for its children, if it's a block container (the
+/// children of inline boxes have the containing block of the inline box).
+pub fn containing_block_for_children(layout_box: &LayoutBox) -> Option<Rect> {
+    match layout_box {
+        LayoutBox::BlockLevel(_) => Some(layout_box.dimensions().content),
+        LayoutBox::InlineLevel(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::traits::*;
+    use crate::layout::DumpLayout;
+    use crate::layout::DumpLayoutVerbosity;
+    use crate::style::test_utils::laid_out_document;
+    use crate::style::values::computed::length::CSSPixelLength;
+
+    fn rect(start_x: f32, start_y: f32, width: f32, height: f32) -> Rect {
+        Rect {
+            start_x,
+            start_y,
+            width: CSSPixelLength::new(width),
+            height: CSSPixelLength::new(height),
+        }
+    }
+
+    #[test]
+    fn queries_containing_blocks_and_offsets() {
+        let (document, box_tree) = laid_out_document(
+            "<!DOCTYPE html><div id=outer><div id=inner></div></div><div id=last></div>",
+            "body { margin-top: 0px; margin-right: 0px } \
+             body { margin-bottom: 0px; margin-left: 0px } \
+             #outer { height: 100px } \
+             #outer { padding-top: 10px; padding-right: 10px; padding-bottom: 10px } \
+             #outer { padding-left: 10px } \
+             #inner { margin-left: 5px; height: 20px } \
+             #inner { border-top-style: solid; border-right-style: solid } \
+             #inner { border-bottom-style: solid; border-left-style: solid } \
+             #inner { border-top-width: 1px; border-right-width: 1px } \
+             #inner { border-bottom-width: 1px; border-left-width: 1px } \
+             #last { height: 10px }",
+        );
+        let select = |selector| document.select_first(selector).unwrap().as_node().clone();
+
+        let inner = box_geometry(&box_tree, &select("#inner")).unwrap();
+        assert_eq!(inner.containing_block, rect(10., 10., 780., 100.));
+        assert_eq!(inner.border_box, rect(15., 10., 775., 22.));
+        assert_eq!(inner.padding_box, rect(16., 11., 773., 20.));
+        assert_eq!(inner.content_box, rect(16., 11., 773., 20.));
+        assert_eq!(inner.offset(), Point { x: 5., y: 0. });
+        // The containing block of `#last` is the whole content box of `<body>`, not only the
+        // part of it that was laid out before `#last`.
+        let last = box_geometry(&box_tree, &select("#last")).unwrap();
+        assert_eq!(last.containing_block, rect(0., 0., 800., 130.));
+        assert_eq!(last.offset(), Point { x: 0., y: 120. });
+
+        assert_eq!(
+            containing_block_chain(&box_tree, &select("#inner")).unwrap(),
+            vec![
+                rect(10., 10., 780., 100.),
+                rect(0., 0., 800., 130.),
+                rect(0., 0., 800., 130.),
+                rect(0., 0., 800., 600.),
+            ]
+        );
+        assert_eq!(containing_block_chain(&box_tree, &select("head")), None);
+        let mut boxes = 0;
+        for_each_box_geometry(&box_tree, |_, _| boxes += 1);
+        assert_eq!(boxes, 5);
+
+        // Layout records the border boxes of the boxes of each node on the node.
+        assert_eq!(select("#inner").client_rects(), vec![inner.border_box]);
+        assert_eq!(select("#inner").bounding_client_rect(), inner.border_box);
+        assert!(select("head").client_rects().is_empty());
+
+        let mut dump = Vec::new();
+        box_tree.dump_layout(&mut dump, 0, DumpLayoutVerbosity::Verbose);
+        let dump = String::from_utf8(dump).unwrap();
+        assert!(dump
+            .lines()
+            .next()
+            .unwrap()
+            .ends_with(" cb at (0, 0) size 800x600"));
+        assert_eq!(
+            dump.lines()
+                .filter(|line| line.ends_with(" cb at (10, 10) size 780x100"))
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn positioned_and_transform_hinting_ancestors_contain_positioned_descendants() {
+        let (document, box_tree) = laid_out_document(
+            "<!DOCTYPE html>\
+             <div id=hint><div id=static><div id=fixed></div><div id=absolute></div></div></div>\
+             <div id=relative><div id=fixed-in-relative></div>\
+             <div id=absolute-in-relative></div></div>",
+            "body { margin-top: 0px; margin-right: 0px } \
+             body { margin-bottom: 0px; margin-left: 0px } \
+             #hint { will-change: transform; padding-left: 10px; height: 100px } \
+             #relative { position: relative; padding-top: 5px; height: 50px } \
+             #fixed, #fixed-in-relative { position: fixed } \
+             #absolute, #absolute-in-relative { position: absolute }",
+        );
+        let select = |selector| document.select_first(selector).unwrap().as_node().clone();
+        let containing_block = |selector| {
+            box_geometry(&box_tree, &select(selector))
+                .unwrap()
+                .containing_block
+        };
+
+        // Boxes that hint they'll be transformed contain fixed and absolutely positioned
+        // descendants in their padding boxes, while static ones don't contain either.
+        assert_eq!(containing_block("#static"), rect(10., 0., 790., 100.));
+        assert_eq!(containing_block("#fixed"), rect(0., 0., 800., 100.));
+        assert_eq!(containing_block("#absolute"), rect(0., 0., 800., 100.));
+        // Positioned boxes only contain absolutely positioned descendants.
+        assert_eq!(
+            containing_block("#absolute-in-relative"),
+            rect(0., 100., 800., 55.)
+        );
+        assert_eq!(
+            containing_block("#fixed-in-relative"),
+            rect(0., 0., 800., 600.)
+        );
+
+        // The containing block chain skips the boxes that don't contain positioned boxes.
+        assert_eq!(
+            containing_block_chain(&box_tree, &select("#absolute")).unwrap(),
+            vec![
+                rect(0., 0., 800., 100.),
+                rect(0., 0., 800., 155.),
+                rect(0., 0., 800., 155.),
+                rect(0., 0., 800., 600.),
+            ]
+        );
+        assert_eq!(
+            containing_block_chain(&box_tree, &select("#fixed-in-relative")).unwrap(),
+            vec![rect(0., 0., 800., 600.)]
+        );
+        let mut fixed_containing_block = None;
+        for_each_box_geometry(&box_tree, |layout_box, geometry| {
+            if layout_box.node() == select("#fixed") {
+                fixed_containing_block = Some(geometry.containing_block);
+            }
+        });
+        assert_eq!(fixed_containing_block, Some(rect(0., 0., 800., 100.)));
+    }
+}
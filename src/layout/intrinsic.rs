@@ -0,0 +1,309 @@
+//! The intrinsic inline sizes of boxes, which are the sizes their contents would take up without
+//! regard for the space available to them.  Boxes that are sized to fit their contents, such as
+//! absolutely positioned boxes with an `auto` inline size, are sized from these.
+//!
+//! https://drafts.csswg.org/css-sizing-3/#intrinsic-sizes
+
+use crate::layout::behavior::BaseLayoutBoxBehavior;
+use crate::layout::containing_block::ContainingBlock;
+use crate::layout::flow::block::BlockLevelBox;
+use crate::layout::flow::inline::{break_opportunities, InlineLevelBox, InlineLevelContent};
+use crate::layout::flow::FlowSide;
+use crate::layout::layout_box::LayoutBox;
+use crate::layout::text::shape_text;
+use crate::style::values::computed::length::{
+    CSSPixelLength, LengthPercentage, LengthPercentageOrAuto,
+};
+use crate::style::values::computed::{ComputedValues, WritingMode};
+use crate::style::values::used::ToPx;
+
+/// The min-content and max-content inline sizes of a box: the narrowest it can be without its
+/// contents overflowing it, and the size it would be if its lines never wrapped.
+///
+/// https://drafts.csswg.org/css-sizing-3/#min-content
+/// https://drafts.csswg.org/css-sizing-3/#max-content
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct IntrinsicSizes {
+    pub min_content: CSSPixelLength,
+    pub max_content: CSSPixelLength,
+}
+
+impl IntrinsicSizes {
+    /// The shrink-to-fit (fit-content) size of a box with these intrinsic sizes, given the space
+    /// available to it: its max-content size if that fits, and otherwise the available space, but
+    /// no less than its min-content size.
+    ///
+    /// https://drafts.csswg.org/css-sizing-3/#fit-content-size
+    pub fn shrink_to_fit(self, available_space: CSSPixelLength) -> CSSPixelLength {
+        self.max_content.min(available_space.max(self.min_content))
+    }
+
+    fn max(self, other: IntrinsicSizes) -> IntrinsicSizes {
+        IntrinsicSizes {
+            min_content: self.min_content.max(other.min_content),
+            max_content: self.max_content.max(other.max_content),
+        }
+    }
+}
+
+/// The intrinsic inline sizes of the contents of `block_level_box`, not including its own margins,
+/// borders, and padding, in the writing mode of the box.  Percentages resolve against the inline
+/// size of `containing_block`.
+///
+/// TODO: Orthogonal flow roots among the contents contribute as if they were in the writing mode
+/// of this box, rather than by their block size.
+pub fn content_sizes(
+    block_level_box: &BlockLevelBox,
+    containing_block: ContainingBlock,
+) -> IntrinsicSizes {
+    // Boxes with size containment are sized as if they were empty, as `contain-intrinsic-size`
+    // only applies in the block axis so far.
+    if block_level_box.has_size_containment() {
+        return IntrinsicSizes::default();
+    }
+    let writing_mode = block_level_box.computed_values().writing_mode;
+    let percentage_basis = containing_block.self_relative_inline_size();
+    let mut sizes = IntrinsicSizes::default();
+    for child in block_level_box.children().into_iter().flatten() {
+        let child_sizes = match child {
+            _ if child.is_absolutely_positioned() => continue,
+            LayoutBox::BlockLevel(child) => {
+                contribution(child, writing_mode, percentage_basis, containing_block)
+            }
+            LayoutBox::InlineLevel(_) => inline_content_sizes(
+                child.children().map_or(&[][..], |children| &children[..]),
+                writing_mode,
+                percentage_basis,
+                containing_block,
+            ),
+        };
+        sizes = sizes.max(child_sizes);
+    }
+    sizes
+}
+
+/// The intrinsic size contributions of `block_level_box` to its parent: the intrinsic sizes of its
+/// contents, or its own inline size if that's a length, plus its margins, borders, and padding.
+/// Percentage inline sizes behave as `auto`, since they resolve against the size being found.
+fn contribution(
+    block_level_box: &BlockLevelBox,
+    writing_mode: WritingMode,
+    percentage_basis: CSSPixelLength,
+    containing_block: ContainingBlock,
+) -> IntrinsicSizes {
+    let sizes = match block_level_box {
+        BlockLevelBox::Replaced(replaced_box) => {
+            let inline_size = replaced_box.used_content_size(containing_block).0;
+            IntrinsicSizes {
+                min_content: inline_size,
+                max_content: inline_size,
+            }
+        }
+        _ if block_level_box.is_anonymous_block() => {
+            return content_sizes(block_level_box, containing_block)
+        }
+        _ => match block_level_box.computed_values().inline_size(writing_mode) {
+            LengthPercentageOrAuto::LengthPercentage(LengthPercentage::Length(length)) => {
+                IntrinsicSizes {
+                    min_content: length,
+                    max_content: length,
+                }
+            }
+            _ => content_sizes(block_level_box, containing_block),
+        },
+    };
+    let edges = inline_edges(
+        &block_level_box.computed_values(),
+        FlowSide::InlineStart,
+        writing_mode,
+        percentage_basis,
+    ) + inline_edges(
+        &block_level_box.computed_values(),
+        FlowSide::InlineEnd,
+        writing_mode,
+        percentage_basis,
+    );
+    IntrinsicSizes {
+        min_content: sizes.min_content + edges,
+        max_content: sizes.max_content + edges,
+    }
+}
+
+/// The margin, border, and padding on the `side` of a box with `computed_values`, where `auto`
+/// margins are zero.
+fn inline_edges(
+    computed_values: &ComputedValues,
+    side: FlowSide,
+    writing_mode: WritingMode,
+    percentage_basis: CSSPixelLength,
+) -> CSSPixelLength {
+    computed_values
+        .margin_flow_relative(side, writing_mode)
+        .to_px(percentage_basis)
+        + computed_values.border_flow_relative(side, writing_mode)
+        + computed_values
+            .padding_flow_relative(side, writing_mode)
+            .to_px(percentage_basis)
+}
+
+/// The intrinsic sizes of the inline-level content `children` of a root inline box.  Its
+/// max-content size is the size of its longest line when lines only break at preserved newlines,
+/// and its min-content size is that of its widest piece of content lines can't break within.
+fn inline_content_sizes(
+    children: &[LayoutBox],
+    writing_mode: WritingMode,
+    percentage_basis: CSSPixelLength,
+    containing_block: ContainingBlock,
+) -> IntrinsicSizes {
+    let mut sizer = InlineContentSizer {
+        writing_mode,
+        percentage_basis,
+        containing_block,
+        sizes: IntrinsicSizes::default(),
+        line: CSSPixelLength::new(0.),
+        unbreakable: CSSPixelLength::new(0.),
+    };
+    sizer.add_children(children);
+    sizer.end_unbreakable();
+    sizer.end_line();
+    sizer.sizes
+}
+
+/// Sizes inline-level content as if it were laid out on lines of unlimited size, in the same order
+/// as `LineBreaker` places it.
+struct InlineContentSizer {
+    writing_mode: WritingMode,
+    percentage_basis: CSSPixelLength,
+    containing_block: ContainingBlock,
+    /// The intrinsic sizes found so far.
+    sizes: IntrinsicSizes,
+    /// The size of the content since the last forced line break.
+    line: CSSPixelLength,
+    /// The size of the content since the last line break opportunity.
+    unbreakable: CSSPixelLength,
+}
+
+impl InlineContentSizer {
+    fn add_children(&mut self, children: &[LayoutBox]) {
+        for child in children {
+            match child {
+                _ if child.is_absolutely_positioned() => {}
+                LayoutBox::InlineLevel(InlineLevelContent::TextRun(text_run)) => {
+                    self.add_text(&text_run.contents(), &text_run.computed_values())
+                }
+                LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(
+                    InlineLevelBox::InlineBox(_),
+                )) => {
+                    // Lines can't break between an inline box's edges and the content next to
+                    // them.
+                    let computed_values = child.computed_values();
+                    self.add(self.inline_edges(&computed_values, FlowSide::InlineStart));
+                    self.add_children(child.children().unwrap());
+                    self.add(self.inline_edges(&computed_values, FlowSide::InlineEnd));
+                }
+                LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(
+                    InlineLevelBox::Replaced(replaced_box),
+                )) => {
+                    let computed_values = child.computed_values();
+                    let size = self.inline_edges(&computed_values, FlowSide::InlineStart)
+                        + replaced_box.used_content_size(self.containing_block).0
+                        + self.inline_edges(&computed_values, FlowSide::InlineEnd);
+                    self.add(size);
+                }
+                LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(
+                    InlineLevelBox::AnonymousInline(_),
+                ))
+                | LayoutBox::BlockLevel(_) => self.add_children(child.children().unwrap()),
+            }
+        }
+    }
+
+    /// Adds `text`, the contents of a text run styled by `style`, breaking it where
+    /// `LineBreaker::break_text` would.
+    fn add_text(&mut self, text: &str, style: &ComputedValues) {
+        let white_space = style.white_space;
+        let segments: Vec<&str> = if white_space.preserves_newlines() {
+            text.split('\n').collect()
+        } else {
+            vec![text]
+        };
+        for (index, segment) in segments.into_iter().enumerate() {
+            if index > 0 {
+                self.end_unbreakable();
+                self.end_line();
+            }
+            let shaped = shape_text(segment, style, self.line.px());
+            if !white_space.wraps() {
+                self.add(CSSPixelLength::new(shaped.advance));
+                continue;
+            }
+            let mut start = 0;
+            for end in break_opportunities(segment) {
+                // The spaces lines break at hang, so they take up none of the unbreakable piece
+                // they end, but they still take up room on lines that don't break there.
+                let content_end = segment[..end].trim_end_matches(' ').len();
+                let advance_before_start = shaped.advance_before(start);
+                self.unbreakable +=
+                    CSSPixelLength::new(shaped.advance_before(content_end) - advance_before_start);
+                self.line += CSSPixelLength::new(shaped.advance_before(end) - advance_before_start);
+                if end < segment.len() {
+                    self.end_unbreakable();
+                }
+                start = end;
+            }
+        }
+    }
+
+    /// Adds content of `size` that lines can't break within.
+    fn add(&mut self, size: CSSPixelLength) {
+        self.unbreakable += size;
+        self.line += size;
+    }
+
+    fn end_unbreakable(&mut self) {
+        self.sizes.min_content.max_assign(self.unbreakable);
+        self.unbreakable = CSSPixelLength::new(0.);
+    }
+
+    fn end_line(&mut self) {
+        self.sizes.max_content.max_assign(self.line);
+        self.line = CSSPixelLength::new(0.);
+    }
+
+    fn inline_edges(&self, computed_values: &ComputedValues, side: FlowSide) -> CSSPixelLength {
+        inline_edges(
+            computed_values,
+            side,
+            self.writing_mode,
+            self.percentage_basis,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::style::test_utils::laid_out_document;
+
+    #[test]
+    fn text_is_as_wide_as_its_widest_word_at_min_content() {
+        let (_, box_tree) = laid_out_document(
+            "<!DOCTYPE html><p>a bbb cc</p><p>dddd</p>",
+            "p { padding-left: 2px }",
+        );
+        let body = match &box_tree.children().unwrap()[0] {
+            LayoutBox::BlockLevel(body) => body,
+            LayoutBox::InlineLevel(_) => panic!("body should be block-level"),
+        };
+        let containing_block = body.laid_out_in().unwrap();
+        // Every glyph of the test font is 16px wide, and the widest words of both paragraphs are
+        // 4 glyphs wide once "bbb " breaks after its space.
+        assert_eq!(
+            content_sizes(body, containing_block),
+            IntrinsicSizes {
+                min_content: CSSPixelLength::new(66.),
+                max_content: CSSPixelLength::new(130.),
+            }
+        );
+    }
+}
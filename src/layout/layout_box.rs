@@ -1,20 +1,35 @@
-use crate::cli::DumpLayoutVerbosity;
+use crate::dom::selection::BoundaryPoint;
 use crate::dom::tree::{NodeData, NodeRef};
+use crate::iframe::NestedBrowsingContext;
+use crate::layout::baseline::baselines;
 use crate::layout::behavior::ApplyPageRelativeProperties;
 use crate::layout::behavior::BaseLayoutBoxBehavior;
 use crate::layout::containing_block::ContainingBlock;
+use crate::layout::content_visibility::is_relevant_to_user;
 use crate::layout::dimensions::Dimensions;
 use crate::layout::flow::block::{AnonymousBlockBox, BlockLevelBox};
 use crate::layout::flow::inline::{
-    AnonymousInlineBox, InlineBox, InlineLevelBox, InlineLevelContent, TextRun,
+    AnonymousInlineBox, InlineBox, InlineLevelBox, InlineLevelContent, LineBox, TextFragment,
+    TextRun,
 };
 use crate::layout::formatting_context::FormattingContextRef;
-use crate::layout::{DumpLayout, DumpLayoutFormat, Layout, LayoutContext};
-use crate::style::values::computed::length::LengthPercentageOrAuto;
-use crate::style::values::computed::ComputedValues;
+use crate::layout::geometry::containing_block_for_children;
+use crate::layout::rect::{EdgeSizes, Point, Rect};
+use crate::layout::DumpLayoutVerbosity;
+use crate::layout::{
+    dump_layout_dom_path, dump_layout_json_px, DumpLayout, DumpLayoutFilter, DumpLayoutFormat,
+    DumpLayoutJson, Layout, LayoutContext,
+};
+use crate::style::select::Selectors;
+use crate::style::values::computed::length::{LengthPercentage, LengthPercentageOrAuto};
+use crate::style::values::computed::{ComputedValues, Contain, Position};
 use crate::style::values::used::ToPx;
+use crate::style::values::CSSFloat;
 use accountable_refcell::Ref;
 use enum_dispatch::enum_dispatch;
+use serde_json::{json, Value};
+use std::cmp::Ordering;
+use std::collections::HashSet;
 use std::io::Write;
 use strum_macros::IntoStaticStr;
 
@@ -55,11 +70,30 @@ impl LayoutBox {
         }
     }
 
+    /// The name of the type of this box, as printed in layout dumps.
+    pub fn box_type(&self) -> &'static str {
+        match self {
+            LayoutBox::BlockLevel(BlockLevelBox::AnonymousBlock(_)) => "AnonymousBlockBox",
+            LayoutBox::BlockLevel(BlockLevelBox::BlockContainer(_)) => "BlockContainer",
+            LayoutBox::BlockLevel(BlockLevelBox::Replaced(_))
+            | LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(
+                InlineLevelBox::Replaced(_),
+            )) => "ReplacedBox",
+            LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(
+                InlineLevelBox::AnonymousInline(_),
+            )) => "AnonymousInlineBox",
+            LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(
+                InlineLevelBox::InlineBox(_),
+            )) => "InlineBox",
+            LayoutBox::InlineLevel(InlineLevelContent::TextRun(_)) => "TextRun",
+        }
+    }
+
     /// Returns the children of this layout box, if there are any.
     pub fn children(&self) -> Option<&Vec<LayoutBox>> {
         match self {
-            LayoutBox::BlockLevel(blb) => Some(blb.children()),
-            LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(ilb)) => Some(ilb.children()),
+            LayoutBox::BlockLevel(blb) => blb.children(),
+            LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(ilb)) => ilb.children(),
             LayoutBox::InlineLevel(InlineLevelContent::TextRun(_)) => None,
         }
     }
@@ -70,20 +104,352 @@ impl LayoutBox {
     pub fn get_mut_inline_container(&mut self) -> Option<&mut LayoutBox> {
         match self {
             LayoutBox::BlockLevel(blb) => blb.get_mut_inline_container(),
-            LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(ilb)) => Some(match ilb {
-                InlineLevelBox::AnonymousInline(_) => self,
-                InlineLevelBox::InlineBox(_) => self,
-            }),
+            LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(ilb)) => match ilb {
+                InlineLevelBox::AnonymousInline(_) => Some(self),
+                InlineLevelBox::InlineBox(_) => Some(self),
+                InlineLevelBox::Replaced(_) => None,
+            },
+            LayoutBox::InlineLevel(InlineLevelContent::TextRun(_)) => None,
+        }
+    }
+
+    /// Returns the children of this layout box mutably, if there are any.
+    pub fn children_mut(&mut self) -> Option<&mut Vec<LayoutBox>> {
+        match self {
+            LayoutBox::BlockLevel(blb) => blb.children_mut(),
+            LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(ilb)) => ilb.children_mut(),
             LayoutBox::InlineLevel(InlineLevelContent::TextRun(_)) => None,
         }
     }
 
+    /// Marks the boxes generated by any of `nodes` as needing layout, along with their ancestors,
+    /// whose sizes can depend on them.  Returns whether any box in this subtree was marked.
+    ///
+    /// Changes inside a box that isolates its layout (see `Contain::isolates_layout`) can't change
+    /// the layout of anything outside of it, so its ancestors are only marked as having a
+    /// descendant that needs layout, and the rest of the box tree isn't laid out again.
+    pub fn mark_nodes_need_layout(&mut self, nodes: &HashSet<NodeRef>) -> bool {
+        self.mark_changed_boxes(nodes) != SubtreeChange::Unchanged
+    }
+
+    fn mark_changed_boxes(&mut self, nodes: &HashSet<NodeRef>) -> SubtreeChange {
+        let is_changed = nodes.contains(&self.node());
+        let mut change = if is_changed {
+            SubtreeChange::Changed
+        } else {
+            SubtreeChange::Unchanged
+        };
+        if let Some(children) = self.children_mut() {
+            for child in children {
+                change = change.max(child.mark_changed_boxes(nodes));
+            }
+        }
+        match change {
+            SubtreeChange::Unchanged => SubtreeChange::Unchanged,
+            SubtreeChange::Contained => {
+                self.mark_descendant_needs_layout();
+                SubtreeChange::Contained
+            }
+            SubtreeChange::Changed => {
+                self.mark_needs_layout();
+                if !is_changed && self.isolates_layout() {
+                    SubtreeChange::Contained
+                } else {
+                    SubtreeChange::Changed
+                }
+            }
+        }
+    }
+
+    /// The containment of this box (see `Contain`), which doesn't apply to anonymous boxes, whose
+    /// computed values are those of their parent, or to inline boxes.  Block containers also get
+    /// the containment their `content-visibility` and `container-type` give them.
+    ///
+    /// https://drafts.csswg.org/css-contain-2/#contain-property
+    pub fn containment(&self) -> Contain {
+        match self {
+            LayoutBox::BlockLevel(BlockLevelBox::AnonymousBlock(_))
+            | LayoutBox::InlineLevel(InlineLevelContent::TextRun(_))
+            | LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(
+                InlineLevelBox::AnonymousInline(_),
+            ))
+            | LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(
+                InlineLevelBox::InlineBox(_),
+            )) => Contain::initial_value(),
+            LayoutBox::BlockLevel(BlockLevelBox::BlockContainer(_)) => {
+                let computed_values = self.computed_values();
+                computed_values.contain
+                    | computed_values
+                        .content_visibility
+                        .containment(is_relevant_to_user(&self.node()))
+                    | computed_values.container_type.containment()
+            }
+            _ => self.computed_values().contain,
+        }
+    }
+
+    /// Whether this box skips its contents (see `ContentVisibility`): they aren't laid out,
+    /// painted, or hit tested, and the box is sized as if it had none.
+    pub fn skips_contents(&self) -> bool {
+        match self {
+            LayoutBox::BlockLevel(block_level_box) => block_level_box.skips_contents(),
+            LayoutBox::InlineLevel(_) => false,
+        }
+    }
+
+    /// Moves this box `x` pixels right and `y` pixels down, along with everything laid out inside
+    /// of it: its descendants, and the lines and fragments they were laid out in.  The result is
+    /// as if the box had been laid out that much further along in a containing block moved as far.
+    pub fn translate(&mut self, x: CSSFloat, y: CSSFloat) {
+        let dimensions = self.dimensions_mut();
+        dimensions.content = dimensions.content.translated(x, y);
+        // Text runs aren't laid out on their own, but by the line layout of their root inline box.
+        if let LayoutBox::InlineLevel(InlineLevelContent::TextRun(text_run)) = self {
+            text_run.translate_fragments(x, y);
+        }
+        if let Some(containing_block) = self.laid_out_in() {
+            match self {
+                LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(
+                    InlineLevelBox::AnonymousInline(aib),
+                )) => aib.translate_lines(x, y, containing_block.writing_mode()),
+                LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(
+                    InlineLevelBox::InlineBox(inline_box),
+                )) => inline_box.translate_fragments(x, y),
+                _ => {}
+            }
+            self.mark_laid_out_in(containing_block.translated(x, y));
+        }
+        if let Some(children) = self.children_mut() {
+            for child in children {
+                child.translate(x, y);
+            }
+        }
+    }
+
+    /// Whether this box is absolutely positioned (see `Position`), which takes it out of flow.
+    /// Absolutely positioned boxes are always block-level.
+    pub fn is_absolutely_positioned(&self) -> bool {
+        match self {
+            LayoutBox::BlockLevel(block_level_box) => block_level_box.is_absolutely_positioned(),
+            LayoutBox::InlineLevel(_) => false,
+        }
+    }
+
+    /// Whether this box is the containing block of its `position: fixed` descendants (and so of
+    /// its absolutely positioned ones too), instead of the initial containing block.  Boxes with
+    /// layout or paint containment are, as are boxes that hint they'll be transformed or filtered
+    /// (see `WillChange`), as transformed and filtered boxes are.
+    ///
+    /// TODO: Inline boxes can form containing blocks for positioned descendants too, from the
+    /// first and last of their fragments, but only block containers do for now.
+    ///
+    /// https://drafts.csswg.org/css-transforms-1/#containing-block-for-all-descendants
+    /// https://drafts.csswg.org/css-contain-2/#containment-layout
+    pub fn contains_fixed_position_descendants(&self) -> bool {
+        match self {
+            LayoutBox::BlockLevel(BlockLevelBox::BlockContainer(_))
+            | LayoutBox::BlockLevel(BlockLevelBox::Replaced(_)) => {
+                let containment = self.containment();
+                containment.layout
+                    || containment.paint
+                    || self
+                        .computed_values()
+                        .will_change
+                        .contains_fixed_position_descendants()
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether this box is the containing block of its absolutely positioned descendants, which
+    /// positioned boxes (those that aren't `position: static`) are, as well as those that contain
+    /// `position: fixed` descendants.
+    ///
+    /// https://drafts.csswg.org/css-position/#absolute-cb
+    pub fn contains_absolutely_positioned_descendants(&self) -> bool {
+        match self {
+            LayoutBox::BlockLevel(BlockLevelBox::BlockContainer(_))
+            | LayoutBox::BlockLevel(BlockLevelBox::Replaced(_)) => {
+                let computed_values = self.computed_values();
+                computed_values.position != Position::Static
+                    || computed_values
+                        .will_change
+                        .contains_absolutely_positioned_descendants()
+                    || self.contains_fixed_position_descendants()
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether this box isolates its layout: changes to its descendants can't change its size or
+    /// the layout of anything outside of it, so it can be laid out again by itself, in the same
+    /// containing block it was last laid out in.
+    ///
+    /// https://drafts.csswg.org/css-contain-2/#containment-layout
+    fn isolates_layout(&self) -> bool {
+        match self {
+            LayoutBox::BlockLevel(BlockLevelBox::BlockContainer(_)) => {
+                self.laid_out_in().is_some() && self.containment().isolates_layout()
+            }
+            _ => false,
+        }
+    }
+
+    /// Lays out the descendants of this box that changed inside boxes that isolate their layout,
+    /// each in the containing block it was last laid out in, skipping every other subtree.
+    fn layout_changed_descendants(&mut self) {
+        if let Some(children) = self.children_mut() {
+            for child in children {
+                if let Some(containing_block) = child.laid_out_in() {
+                    child.layout(LayoutContext::new(containing_block));
+                }
+            }
+        }
+    }
+
+    /// The browsing context of the document this box displays, if it's the box of an `<iframe>`
+    /// whose document has been loaded.
+    pub fn nested_browsing_context(&self) -> Option<NestedBrowsingContext> {
+        match self {
+            LayoutBox::BlockLevel(BlockLevelBox::Replaced(replaced_box))
+            | LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(
+                InlineLevelBox::Replaced(replaced_box),
+            )) => replaced_box.nested_browsing_context(),
+            _ => None,
+        }
+    }
+
+    /// The box of the `<body>` element of the document whose root box this is, which the root
+    /// element's background and `overflow` propagate from when the root's own are unset.
+    ///
+    /// https://drafts.csswg.org/css-backgrounds/#body-background
+    pub fn root_body_box(&self) -> Option<&LayoutBox> {
+        if !self.is_root() {
+            return None;
+        }
+        self.children()?
+            .iter()
+            .find(|child| is_root_body(&child.node()))
+    }
+
     pub fn is_anonymous_inline(&self) -> bool {
         match self {
             LayoutBox::BlockLevel(_) => false,
             LayoutBox::InlineLevel(ilc) => ilc.is_anonymous_inline(),
         }
     }
+
+    /// The position in the laid out text of this subtree closest to `point`, which is where
+    /// selecting text from `point` starts or ends.  This is on the line `point` is on, or
+    /// otherwise the line closest to it vertically.  `None` if there's no text.
+    ///
+    /// The text of iframes isn't found, since it's in a document of its own.
+    pub fn text_position_at(&self, point: Point) -> Option<BoundaryPoint> {
+        let mut lines = Vec::new();
+        self.collect_lines(&mut lines);
+        let vertical_distance = |line: &LineBox| {
+            let rect = line.rect();
+            if point.y < rect.start_y {
+                rect.start_y - point.y
+            } else {
+                (point.y - (rect.start_y + rect.height).px()).max(0.)
+            }
+        };
+        let line = lines
+            .into_iter()
+            .filter(|line| !line.text_fragments().is_empty())
+            .min_by(|line, other| {
+                vertical_distance(line)
+                    .partial_cmp(&vertical_distance(other))
+                    .unwrap_or(Ordering::Equal)
+            })?;
+        let horizontal_distance = |fragment: &TextFragment| {
+            let rect = fragment.chars_rect(fragment.char_range());
+            if point.x < rect.start_x {
+                rect.start_x - point.x
+            } else {
+                (point.x - (rect.start_x + rect.width).px()).max(0.)
+            }
+        };
+        let fragment = line.text_fragments().iter().min_by(|fragment, other| {
+            horizontal_distance(fragment)
+                .partial_cmp(&horizontal_distance(other))
+                .unwrap_or(Ordering::Equal)
+        })?;
+        Some(BoundaryPoint {
+            node: fragment.node().clone(),
+            offset: fragment.char_offset_at(point.x),
+        })
+    }
+
+    /// The text node whose laid out text in this subtree is at `point`, if any.
+    pub fn text_at(&self, point: Point) -> Option<NodeRef> {
+        let mut lines = Vec::new();
+        self.collect_lines(&mut lines);
+        lines
+            .into_iter()
+            .flat_map(|line| line.text_fragments())
+            .find(|fragment| fragment.chars_rect(fragment.char_range()).contains(point))
+            .map(|fragment| fragment.node().clone())
+    }
+
+    /// Appends the line boxes of the root inline boxes of this subtree to `lines`.
+    fn collect_lines<'a>(&'a self, lines: &mut Vec<&'a LineBox>) {
+        if let LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(
+            InlineLevelBox::AnonymousInline(aib),
+        )) = self
+        {
+            lines.extend(aib.lines());
+        }
+        if let Some(children) = self.children() {
+            for child in children {
+                child.collect_lines(lines);
+            }
+        }
+    }
+
+    /// Whether this box was generated by an element matching `selectors`.  Anonymous boxes have
+    /// the node of the element they're in, but aren't generated by it, so they never match.
+    pub fn is_generated_by_match(&self, selectors: &Selectors) -> bool {
+        let is_anonymous = match self {
+            LayoutBox::BlockLevel(blb) => blb.is_anonymous_block(),
+            LayoutBox::InlineLevel(_) => self.is_anonymous_inline(),
+        };
+        !is_anonymous
+            && self
+                .node()
+                .into_element_ref()
+                .map_or(false, |element| selectors.matches(&element))
+    }
+
+    /// Returns the boxes of this subtree that `filter` dumps, in tree order.  These are the boxes
+    /// generated by matching elements, with their descendants if the filter includes them, or
+    /// without any children otherwise.
+    pub fn filtered_for_dump(&self, filter: &DumpLayoutFilter) -> Vec<LayoutBox> {
+        let mut filtered = Vec::new();
+        self.collect_filtered_for_dump(filter, &mut filtered);
+        filtered
+    }
+
+    fn collect_filtered_for_dump(&self, filter: &DumpLayoutFilter, filtered: &mut Vec<LayoutBox>) {
+        if self.is_generated_by_match(&filter.selectors) {
+            let mut matched = self.clone();
+            if filter.include_descendants {
+                // Matches among the descendants are dumped along with this box.
+                filtered.push(matched);
+                return;
+            }
+            if let Some(children) = matched.children_mut() {
+                children.clear();
+            }
+            filtered.push(matched);
+        }
+        if let Some(children) = self.children() {
+            for child in children {
+                child.collect_filtered_for_dump(filter, filtered);
+            }
+        }
+    }
 }
 
 impl From<AnonymousBlockBox> for LayoutBox {
@@ -115,18 +481,63 @@ impl From<TextRun> for LayoutBox {
 }
 
 impl Layout for LayoutBox {
+    /// Lays out this box, unless it hasn't changed since it was last laid out in the same
+    /// containing block (in which case its dimensions are already correct).
     fn layout(&mut self, context: LayoutContext) {
+        let containing_block = context.containing_block;
+        if !self.needs_layout_in(containing_block) {
+            if self.descendant_needs_layout() {
+                // Changes to skipped contents are laid out once they're no longer skipped.
+                if !self.skips_contents() {
+                    self.layout_changed_descendants();
+                }
+                self.mark_laid_out_in(containing_block);
+            }
+            return;
+        }
+        // Layout expects to start from a box that hasn't been sized yet, rather than building on
+        // the dimensions of the previous layout.
+        *self.dimensions_mut() = Dimensions::default();
         match self {
             LayoutBox::BlockLevel(blb) => blb.layout(context),
             LayoutBox::InlineLevel(ilc) => ilc.layout(context),
         }
+        self.mark_laid_out_in(containing_block);
     }
 }
 
+/// How the boxes marked as needing layout in a subtree affect the layout of the rest of the box
+/// tree, from least to most.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum SubtreeChange {
+    Unchanged,
+    /// Only boxes inside boxes that isolate their layout were marked, so nothing outside of those
+    /// needs layout.
+    Contained,
+    /// The size of the subtree's root may have changed, so its ancestors need layout.
+    Changed,
+}
+
 pub fn get_anonymous_inline_layout_box(boxes: &mut Vec<LayoutBox>) -> Option<&mut LayoutBox> {
     boxes.iter_mut().find(|child| child.is_anonymous_inline())
 }
 
+/// Whether `node` is a `<body>` element that's a child of the root `<html>` element.
+pub fn is_root_body(node: &NodeRef) -> bool {
+    let is_html_element = |node: &NodeRef, local_name| {
+        node.as_element().map_or(false, |element| {
+            element.name.ns == ns!(html) && element.name.local == local_name
+        })
+    };
+    is_html_element(node, local_name!("body"))
+        && node.parent().map_or(false, |parent| {
+            is_html_element(&parent, local_name!("html"))
+                && parent
+                    .parent()
+                    .map_or(false, |grandparent| grandparent.as_document().is_some())
+        })
+}
+
 /// Base box containing state and behavior common to all boxes.  To be clear, this is an ease-of-use
 /// construct, not something that maps to spec-language.
 #[derive(Clone, Debug)]
@@ -139,6 +550,15 @@ pub struct BaseBox {
     /// to a node to get computed values during layout.  If the box is a block, inline, or any other
     /// non-anonymous box, this field is simply the actual DOM node associated with this box.
     node: NodeRef,
+    /// Whether this box has to be laid out again, either because it has never been laid out or
+    /// because it (or one of its descendants, whose sizes its own can depend on) has changed since.
+    needs_layout: bool,
+    /// Whether a descendant inside a box that isolates its layout has to be laid out again,
+    /// without laying out this box again.
+    descendant_needs_layout: bool,
+    /// The containing block this box was last laid out in.  Laying out an unchanged box in the
+    /// same containing block would give the same result, so it's skipped.
+    laid_out_in: Option<ContainingBlock>,
 }
 
 impl BaseBox {
@@ -147,15 +567,28 @@ impl BaseBox {
             dimensions: Dimensions::default(),
             formatting_context,
             node,
+            needs_layout: true,
+            descendant_needs_layout: false,
+            laid_out_in: None,
         }
     }
 
     pub fn apply_block_page_relative_properties(&mut self, containing_block: ContainingBlock) {
         if containing_block.writing_mode().is_horizontal() {
             let height = self.computed_values().height.size;
-            if let LengthPercentageOrAuto::LengthPercentage(lp) = height {
-                self.dimensions_mut()
-                    .set_height(lp.to_px(containing_block.rect().height));
+            let height_px = match height {
+                LengthPercentageOrAuto::LengthPercentage(LengthPercentage::Length(length)) => {
+                    Some(length)
+                }
+                // Percentages of a height that depends on the contents of the containing block
+                // behave as `auto`.
+                LengthPercentageOrAuto::LengthPercentage(lp) => containing_block
+                    .definite_height()
+                    .map(|height| lp.to_px(height)),
+                LengthPercentageOrAuto::Auto => None,
+            };
+            if let Some(height_px) = height_px {
+                self.dimensions_mut().set_height(height_px);
             }
         } else {
             let width = self.computed_values().width.size;
@@ -217,6 +650,108 @@ impl BaseBox {
     pub fn node(&self) -> NodeRef {
         self.node.clone()
     }
+
+    pub fn needs_layout_in(&self, containing_block: ContainingBlock) -> bool {
+        self.needs_layout || self.laid_out_in != Some(containing_block)
+    }
+
+    pub fn mark_needs_layout(&mut self) {
+        self.needs_layout = true;
+    }
+
+    pub fn descendant_needs_layout(&self) -> bool {
+        self.descendant_needs_layout
+    }
+
+    pub fn mark_descendant_needs_layout(&mut self) {
+        self.descendant_needs_layout = true;
+    }
+
+    pub fn mark_laid_out_in(&mut self, containing_block: ContainingBlock) {
+        self.needs_layout = false;
+        self.descendant_needs_layout = false;
+        self.laid_out_in = Some(containing_block);
+    }
+
+    pub fn laid_out_in(&self) -> Option<ContainingBlock> {
+        self.laid_out_in
+    }
+}
+
+/// Builds a JSON representation of the layout tree starting with the `self` LayoutBox, e.g.:
+///
+/// {
+///   "node": "DIV",
+///   "box": "BlockContainer",
+///   "rect": { "x": 8, "y": 8, "width": 1904, "height": 0 },
+///   "margin": { "top": 0, "right": 0, "bottom": 0, "left": 0 },
+///   "border": { "top": 0, "right": 0, "bottom": 0, "left": 0 },
+///   "padding": { "top": 0, "right": 0, "bottom": 0, "left": 0 },
+///   "writing_mode": "horizontal-tb",
+///   "children": []
+/// }
+///
+/// `node` is `null` for anonymous boxes, which aren't generated by a node of the DOM, and text
+/// runs also have a `text` member with their contents.  The boxes of iframes whose documents were
+/// loaded have a `content_document` member, with the dump of the nested document's box tree (or
+/// `null` if it generates no boxes).
+impl DumpLayoutJson for LayoutBox {
+    fn dump_layout_json(&self) -> Value {
+        let dimensions = self.dimensions();
+        let edges = |edges: EdgeSizes| {
+            json!({
+                "top": dump_layout_json_px(edges.top),
+                "right": dump_layout_json_px(edges.right),
+                "bottom": dump_layout_json_px(edges.bottom),
+                "left": dump_layout_json_px(edges.left),
+            })
+        };
+        let node = match self {
+            LayoutBox::BlockLevel(BlockLevelBox::AnonymousBlock(_))
+            | LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(
+                InlineLevelBox::AnonymousInline(_),
+            )) => Value::Null,
+            _ => Value::from(match self.node().data() {
+                NodeData::Element(element_data) => element_data.name.local.to_uppercase(),
+                NodeData::Text(_) => "#text".to_owned(),
+                node_data => node_data.dump_layout_format(),
+            }),
+        };
+        let children: Vec<Value> = self
+            .children()
+            .map(|children| {
+                children
+                    .iter()
+                    .map(|child| child.dump_layout_json())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let mut json = json!({
+            "node": node,
+            "box": self.box_type(),
+            "rect": {
+                "x": dump_layout_json_px(dimensions.content.start_x.into()),
+                "y": dump_layout_json_px(dimensions.content.start_y.into()),
+                "width": dump_layout_json_px(dimensions.content.width),
+                "height": dump_layout_json_px(dimensions.content.height),
+            },
+            "margin": edges(dimensions.margin),
+            "border": edges(dimensions.border),
+            "padding": edges(dimensions.padding),
+            "writing_mode": self.computed_values().writing_mode.as_css_str(),
+            "children": children,
+        });
+        if let LayoutBox::InlineLevel(InlineLevelContent::TextRun(text_run)) = self {
+            json["text"] = Value::from(text_run.contents());
+        }
+        if let Some(context) = self.nested_browsing_context() {
+            json["content_document"] = context
+                .box_tree()
+                .as_ref()
+                .map_or(Value::Null, |box_tree| box_tree.dump_layout_json());
+        }
+        json
+    }
 }
 
 /// Writes a textual representation of the layout tree starting with the `self` LayoutBox.  Built
@@ -231,19 +766,65 @@ impl BaseBox {
 /// BX-O--LC --          NAV RenderFlexibleBox at (0,0) size 260x819 renderer->(0x30ddf2e20) node->(0x30dde41c0)
 ///
 /// With the `verbose` flag, much more information is printed (such as all of the margin, border,
-/// and padding values).
+/// and padding values).  At its highest level, the line boxes of root inline boxes are printed too,
+/// before their children, along with the fragments of inline boxes (their border boxes) and the
+/// text fragments placed on them, and the first and last baselines of boxes that have them (see
+/// `baselines`) are printed after their containing blocks:
+///
+/// AnonymousInlineBox at (8, 8) size 784x16 ... baselines first 20.8 last 20.8
+///   LineBox at (8, 8) size 784x16 baseline 20.8
+///     InlineBoxFragment SPAN at (8, 5) size 90x22
+///     TextFragment "Hello" chars 0..5 at (13, 8) size 80x16
+///
+/// The box tree of an iframe's document is dumped under the iframe's box, after its children,
+/// with positions relative to the iframe's content box:
+///
+/// IFRAME ReplacedBox at (10, 10) size 300x150
+///   HTML BlockContainer at (0, 0) size 300x16
+///
+/// Verbose dumps also include the path through the DOM to the element each box belongs to (see
+/// `dump_layout_dom_path`) before its edges, and its containing block after them.  The containing
+/// block of the box the dump starts with is the one it was last laid out in, which is exact for the
+/// root box (whose containing block is the initial containing block):
+///
+/// DIV BlockContainer at (8, 8) size 784x0 path "html > body > div#main.card" (ml0 ...) cb at ...
 impl DumpLayout for LayoutBox {
     fn dump_layout<W: Write>(
         &self,
         write_to: &mut W,
         indent_spaces: usize,
         verbosity: DumpLayoutVerbosity,
+    ) {
+        let containing_block = self
+            .laid_out_in()
+            .map(|containing_block| *containing_block.rect());
+        self.dump_layout_in(write_to, indent_spaces, verbosity, containing_block);
+    }
+}
+
+impl LayoutBox {
+    fn dump_layout_in<W: Write>(
+        &self,
+        write_to: &mut W,
+        indent_spaces: usize,
+        verbosity: DumpLayoutVerbosity,
+        containing_block: Option<Rect>,
     ) {
         let dimensions = self.dimensions();
+        let containing_block_str = containing_block.map_or("".to_owned(), |rect| {
+            format!(
+                " cb at ({}, {}) size {}x{}",
+                rect.start_x.dump_layout_format(),
+                rect.start_y.dump_layout_format(),
+                rect.width.dump_layout_format(),
+                rect.height.dump_layout_format(),
+            )
+        });
         let verbose_str = match verbosity {
-            DumpLayoutVerbosity::Verbose => {
+            DumpLayoutVerbosity::Verbose | DumpLayoutVerbosity::VeryVerbose => {
                 format!(
-                    " (ml{} mr{} mb{} mt{} bl{} br{} bb{} bt{} pl{} pr{} pb{} pt{})",
+                    " path \"{}\" (ml{} mr{} mb{} mt{} bl{} br{} bb{} bt{} pl{} pr{} pb{} pt{}){}",
+                    dump_layout_dom_path(&self.node()),
                     dimensions.margin.left.dump_layout_format(),
                     dimensions.margin.right.dump_layout_format(),
                     dimensions.margin.bottom.dump_layout_format(),
@@ -256,10 +837,19 @@ impl DumpLayout for LayoutBox {
                     dimensions.padding.right.dump_layout_format(),
                     dimensions.padding.bottom.dump_layout_format(),
                     dimensions.padding.top.dump_layout_format(),
+                    containing_block_str,
                 )
             }
             DumpLayoutVerbosity::NonVerbose => "".to_owned(),
         };
+        let baselines_str = match (verbosity, baselines(self)) {
+            (DumpLayoutVerbosity::VeryVerbose, Some(baselines)) => format!(
+                " baselines first {} last {}",
+                baselines.first.dump_layout_format(),
+                baselines.last.dump_layout_format()
+            ),
+            _ => "".to_owned(),
+        };
         // Note some boxes don't dump their node data, e.g. anonymous boxes because they are not
         // directly generated by an element of the DOM.
         let mut box_and_node_dump = match self {
@@ -271,7 +861,7 @@ impl DumpLayout for LayoutBox {
         }
         writeln!(
             write_to,
-            "{:indent_spaces$}{}at ({}, {}) size {}x{}{}",
+            "{:indent_spaces$}{}at ({}, {}) size {}x{}{}{}",
             "",
             box_and_node_dump,
             dimensions.content.start_x.dump_layout_format(),
@@ -279,15 +869,33 @@ impl DumpLayout for LayoutBox {
             dimensions.content.width.dump_layout_format(),
             dimensions.content.height.dump_layout_format(),
             verbose_str,
+            baselines_str,
             indent_spaces = indent_spaces,
         )
         .expect("error writing layout dump");
 
+        if let (
+            DumpLayoutVerbosity::VeryVerbose,
+            LayoutBox::InlineLevel(InlineLevelContent::InlineLevelBox(
+                InlineLevelBox::AnonymousInline(aib),
+            )),
+        ) = (verbosity, self)
+        {
+            for line in aib.lines() {
+                line.dump_layout(write_to, indent_spaces + 2, verbosity);
+            }
+        }
         if let Some(children) = self.children() {
             let new_indent = indent_spaces + 2;
+            let containing_block = containing_block_for_children(self).or(containing_block);
             children.iter().for_each(|child| {
-                child.dump_layout(write_to, new_indent, verbosity);
+                child.dump_layout_in(write_to, new_indent, verbosity, containing_block);
             })
         }
+        if let Some(context) = self.nested_browsing_context() {
+            if let Some(box_tree) = context.box_tree().as_ref() {
+                box_tree.dump_layout(write_to, indent_spaces + 2, verbosity);
+            }
+        }
     }
 }
@@ -1,27 +1,39 @@
+pub mod baseline;
 pub mod behavior;
 pub mod box_tree;
 pub mod containing_block;
+pub mod content_visibility;
+pub mod diff;
 pub mod dimensions;
 pub mod flow;
 pub mod formatting_context;
+pub mod geometry;
+pub mod intrinsic;
 pub mod layout_box;
 pub mod rect;
+pub mod replaced;
+pub mod scroll;
+pub mod text;
 pub mod values;
 
-use crate::cli::DumpLayoutVerbosity;
-use crate::dom::tree::NodeData;
+use crate::dom::tree::{NodeData, NodeRef};
+use crate::iframe::layout_nested_browsing_contexts;
 use crate::layout::behavior::BaseLayoutBoxBehavior;
 use crate::layout::containing_block::ContainingBlock;
+use crate::layout::content_visibility::update_relevance;
 use crate::layout::flow::block::BlockLevelBox;
 use crate::layout::flow::inline::InlineLevelBox;
 use crate::layout::flow::inline::InlineLevelContent;
 use crate::layout::flow::OriginRelativeProgression;
+use crate::layout::geometry::record_client_rects;
 use crate::layout::layout_box::LayoutBox;
-use crate::layout::rect::Rect;
+use crate::layout::rect::{Point, Rect};
+use crate::style::select::Selectors;
 use crate::style::values::computed::length::CSSPixelLength;
 use crate::style::values::CSSFloat;
 use enum_dispatch::enum_dispatch;
 use std::io::Write;
+use tracing::info_span;
 
 /// Given a `window` and a `layout_root_box`, perform a layout with the dimensions of the `window`.
 pub fn global_layout(
@@ -30,18 +42,40 @@ pub fn global_layout(
     inner_window_height: f32,
     scale_factor: f32,
 ) {
+    let _span = info_span!("layout").entered();
     let writing_mode = layout_root_box.computed_values().writing_mode;
     let direction = layout_root_box.computed_values().direction;
-    layout_root_box.layout(LayoutContext::new(ContainingBlock::new(
-        Rect {
-            start_x: 0.0,
-            start_y: 0.0,
-            width: CSSPixelLength::new(inner_window_width / scale_factor),
-            height: CSSPixelLength::new(inner_window_height / scale_factor),
-        },
+    let initial_containing_block = Rect {
+        start_x: 0.0,
+        start_y: 0.0,
+        width: CSSPixelLength::new(inner_window_width / scale_factor),
+        height: CSSPixelLength::new(inner_window_height / scale_factor),
+    };
+    let context = LayoutContext::new(ContainingBlock::new(
+        initial_containing_block,
         direction,
         writing_mode,
-    )));
+    ));
+    layout_root_box.layout(context);
+    // Elements with `content-visibility: auto` that scrolled into or out of view, or were only
+    // just laid out, skip their contents (or stop skipping them) from now on.  Their relevance
+    // isn't determined again until the next layout, so that it can't flip back and forth.
+    let document = layout_root_box.node().inclusive_ancestors().last().unwrap();
+    let scroll_position = document
+        .as_document()
+        .map_or(Point::default(), |document| document.scroll_position.get());
+    let viewport = Rect {
+        start_x: scroll_position.x,
+        start_y: scroll_position.y,
+        ..initial_containing_block
+    };
+    if update_relevance(layout_root_box, viewport) {
+        layout_root_box.layout(context);
+    }
+    record_client_rects(layout_root_box);
+    // The documents of iframes are laid out in viewports the size of their iframes, so they can't
+    // be laid out until their iframes are.
+    layout_nested_browsing_contexts(layout_root_box, scale_factor);
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -88,6 +122,80 @@ pub trait DumpLayout {
     );
 }
 
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DumpLayoutVerbosity {
+    /// Includes more information in layout-dump, such as margin, border, and padding values for
+    /// each box, and the path through the DOM to the element it belongs to.
+    Verbose,
+    /// The most minimal layout-dump representation, including information such as box size, box
+    /// type, xy position coordinates, and more.
+    NonVerbose,
+    /// Includes everything `Verbose` does, along with the line boxes of inline formatting
+    /// contexts, the inline box fragments and text fragments placed on each line (with their
+    /// rects and character ranges), the baseline of each line, and the first and last baselines of
+    /// boxes.
+    VeryVerbose,
+}
+
+impl DumpLayoutVerbosity {
+    pub fn to_cli_string(&self) -> String {
+        // The CLI form of this flag is a boolean, or 2 for the highest level.
+        match self {
+            DumpLayoutVerbosity::Verbose => "1",
+            DumpLayoutVerbosity::NonVerbose => "0",
+            DumpLayoutVerbosity::VeryVerbose => "2",
+        }
+        .to_owned()
+    }
+}
+
+/// The formats the layout-tree can be dumped in.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LayoutDumpFormat {
+    /// An indented line of text per box, loosely matching WebKit's layout dumps.
+    Text,
+    /// A tree of JSON objects, one per box, for tools (e.g. differential testing against other
+    /// engines) to consume.
+    Json,
+}
+
+impl LayoutDumpFormat {
+    pub fn to_cli_string(&self) -> &'static str {
+        match self {
+            LayoutDumpFormat::Text => "text",
+            LayoutDumpFormat::Json => "json",
+        }
+    }
+}
+
+/// What a layout dump includes, and how it's written.
+#[derive(Clone)]
+pub struct DumpLayoutOptions {
+    pub verbosity: DumpLayoutVerbosity,
+    pub format: LayoutDumpFormat,
+    /// Restricts the dump to some of the boxes, or `None` to dump the whole tree.
+    pub filter: Option<DumpLayoutFilter>,
+    /// A previous text dump to compare this one with, writing only the boxes that differ instead
+    /// of the whole dump.
+    pub diff_against: Option<String>,
+}
+
+/// Restricts a layout dump to the boxes generated by elements matching `selectors`, to keep dumps
+/// of large pages focused on the part of the page under test.
+#[derive(Clone)]
+pub struct DumpLayoutFilter {
+    pub selectors: Selectors,
+    /// Whether to dump the descendants of matching boxes along with them, rather than only the
+    /// matching boxes themselves.
+    pub include_descendants: bool,
+}
+
+/// Trait describing behavior necessary for dumping the layout tree as JSON, for tools (e.g.
+/// differential testing against other engines) to consume rather than people to read.
+pub trait DumpLayoutJson {
+    fn dump_layout_json(&self) -> serde_json::Value;
+}
+
 /// Trait describing behavior necessary for formatting ones data in preparation for a layout tree
 /// dump.
 #[enum_dispatch(BlockLevelBox, InlineLevelBox, InlineLevelContent)]
@@ -110,6 +218,46 @@ impl DumpLayoutFormat for CSSPixelLength {
     }
 }
 
+/// The path through the DOM to the element `node` is (or is in, for text), written like a selector
+/// of the elements on the way from the root element, each with its id and classes (e.g.
+/// `html > body > div#main.card`).  Verbose layout dumps include it for each box, so that diffs of
+/// dumps of complex pages show which element a changed box belongs to.
+pub fn dump_layout_dom_path(node: &NodeRef) -> String {
+    let mut elements = node
+        .inclusive_ancestors()
+        .filter_map(|ancestor| dump_layout_element(&ancestor))
+        .collect::<Vec<_>>();
+    elements.reverse();
+    elements.join(" > ")
+}
+
+/// The local name of `node` followed by its id and classes, written like a selector (e.g.
+/// `div#main.card`), if it's an element.
+pub fn dump_layout_element(node: &NodeRef) -> Option<String> {
+    let element = node.as_element()?;
+    let attributes = element.attributes.borrow();
+    let mut selector = element.name.local.to_string();
+    if let Some(id) = attributes.get("id") {
+        selector.push('#');
+        selector.push_str(id);
+    }
+    for class in attributes
+        .get("class")
+        .unwrap_or_default()
+        .split_ascii_whitespace()
+    {
+        selector.push('.');
+        selector.push_str(class);
+    }
+    Some(selector)
+}
+
+/// Formats `length` as a JSON number, rounded to two decimal places like text layout dumps so
+/// that `f32` imprecision doesn't show up as noise in the output.
+pub fn dump_layout_json_px(length: CSSPixelLength) -> serde_json::Value {
+    serde_json::Value::from((f64::from(length.px()) * 100.).round() / 100.)
+}
+
 impl DumpLayoutFormat for NodeData {
     fn dump_layout_format(&self) -> String {
         match self {
@@ -120,6 +268,7 @@ impl DumpLayoutFormat for NodeData {
             NodeData::Element(element_data) => element_data.name.local.to_uppercase(),
             NodeData::Text(text) => format!("TEXT \"{}\"", text.clone().take().trim()),
             NodeData::ProcessingInstruction(_) => "PROCESSING_INSTRUCTION".to_owned(),
+            NodeData::ShadowRoot(_) => "SHADOW_ROOT".to_owned(),
         }
     }
 }
@@ -1,7 +1,14 @@
 use crate::style::values::computed::length::CSSPixelLength;
 use crate::style::values::CSSFloat;
 
-#[derive(Clone, Copy, Debug, Default)]
+/// A point in CSS pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Point {
+    pub x: CSSFloat,
+    pub y: CSSFloat,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct Rect {
     /// The exact point where the rectangle begins on the x-axis.
     pub start_x: CSSFloat,
@@ -40,6 +47,55 @@ impl Rect {
             height: self.height * scale_factor,
         }
     }
+
+    /// Returns this rect moved `x` pixels right and `y` pixels down.
+    pub fn translated(&self, x: f32, y: f32) -> Rect {
+        Rect {
+            start_x: self.start_x + x,
+            start_y: self.start_y + y,
+            ..*self
+        }
+    }
+
+    /// Whether `point` lies within this rect.  Points on the start edges are within the rect, while
+    /// points on the end edges are not, so that adjacent rects never both contain a point.
+    pub fn contains(&self, point: Point) -> bool {
+        point.x >= self.start_x
+            && point.x < (self.start_x + self.width).px()
+            && point.y >= self.start_y
+            && point.y < (self.start_y + self.height).px()
+    }
+
+    /// Returns the area covered by both this rect and `other`, or `None` if they don't overlap.
+    pub fn intersection(&self, other: Rect) -> Option<Rect> {
+        let start_x = self.start_x.max(other.start_x);
+        let start_y = self.start_y.max(other.start_y);
+        let end_x = (self.start_x + self.width).min(other.start_x + other.width);
+        let end_y = (self.start_y + self.height).min(other.start_y + other.height);
+        if end_x <= start_x || end_y <= start_y {
+            return None;
+        }
+        Some(Rect {
+            start_x,
+            start_y,
+            width: end_x - start_x,
+            height: end_y - start_y,
+        })
+    }
+
+    /// Returns the smallest rect that covers both this rect and `other`.
+    pub fn union(&self, other: Rect) -> Rect {
+        let start_x = self.start_x.min(other.start_x);
+        let start_y = self.start_y.min(other.start_y);
+        let end_x = (self.start_x + self.width).max(other.start_x + other.width);
+        let end_y = (self.start_y + self.height).max(other.start_y + other.height);
+        Rect {
+            start_x,
+            start_y,
+            width: end_x - start_x,
+            height: end_y - start_y,
+        }
+    }
 }
 
 /// A collection of edges, e.g. borders, margins, padding.
@@ -0,0 +1,468 @@
+use crate::apply_page_relative_properties_base_box_passthrough_impls;
+use crate::dom::form::{self, FormControlKind};
+use crate::dom::tree::NodeRef;
+use crate::iframe::{NestedBrowsingContext, DEFAULT_IFRAME_SIZE_PX};
+use crate::image::{ImageRequest, LoadedImage};
+use crate::layout::baseline::Baselines;
+use crate::layout::behavior::{ApplyPageRelativeProperties, BaseLayoutBoxBehavior};
+use crate::layout::containing_block::ContainingBlock;
+use crate::layout::dimensions::Dimensions;
+use crate::layout::flow::FlowSide;
+use crate::layout::formatting_context::FormattingContextRef;
+use crate::layout::layout_box::BaseBox;
+use crate::layout::rect::Rect;
+use crate::layout::DumpLayoutFormat;
+use crate::layout_box_behavior_base_box_passthrough_impls;
+use crate::style::values::computed::length::{
+    CSSPixelLength, LengthPercentage, LengthPercentageOrAuto,
+};
+use crate::style::values::computed::{ComputedValues, ObjectFit};
+use crate::style::values::used::ToPx;
+use accountable_refcell::Ref;
+use std::ops::Range;
+
+/// The average advance of a character, as a fraction of the font size.  Form controls are sized
+/// using this rather than by measuring their text, since there's no text layout yet.
+pub const AVERAGE_CHAR_WIDTH_EM: f32 = 0.5;
+/// The height of a line of text in a form control, as a fraction of the font size.
+pub const LINE_HEIGHT_EM: f32 = 1.2;
+/// The distance from the top of a line of text in a form control to its baseline, as a fraction of
+/// the font size: the half-leading of the line, plus the ascent of a typical font (0.8em).
+pub(crate) const BASELINE_EM: f32 = 0.9;
+/// The width and height of checkboxes and radio buttons, which don't scale with the font size.
+const TOGGLE_SIZE_PX: f32 = 13.;
+
+/// Determines whether the given node is a replaced element, meaning its content is outside the
+/// scope of CSS and is rendered independently of it.  The replaced elements Kosmonaut supports are
+/// `<img>`, `<iframe>`, and the form controls it renders as widgets.
+///
+/// https://drafts.csswg.org/css-display-3/#replaced-element
+/// https://html.spec.whatwg.org/multipage/rendering.html#form-controls
+pub fn is_replaced_element(node: &NodeRef) -> bool {
+    match node.as_element() {
+        Some(element) => {
+            matches!(
+                element.name.local,
+                local_name!("img") | local_name!("iframe")
+            ) || FormControlKind::of(node).is_some()
+        }
+        None => false,
+    }
+}
+
+/// A box generated by a replaced element.  Replaced boxes never have children; their content is
+/// whatever external resource the element represents (e.g. the image of an `<img>`, or the
+/// document of an `<iframe>`), or the widget of a form control.
+#[derive(Clone, Debug)]
+pub struct ReplacedBox {
+    base: BaseBox,
+}
+
+impl ReplacedBox {
+    pub fn new(node: NodeRef, formatting_context: FormattingContextRef) -> Self {
+        ReplacedBox {
+            base: BaseBox::new(node, formatting_context),
+        }
+    }
+
+    /// The image request of this box's element, if the element has successfully loaded an image.
+    pub fn image_request(&self) -> Option<ImageRequest> {
+        self.base
+            .node()
+            .as_element()
+            .and_then(|element| element.current_request.borrow().clone())
+    }
+
+    /// The image this box should display, if the element has successfully loaded one.
+    pub fn image(&self) -> Option<LoadedImage> {
+        self.image_request()
+            .map(|image_request| image_request.image().clone())
+    }
+
+    /// The browsing context of the document this box displays, if its element is an `<iframe>`
+    /// whose document has been loaded.
+    pub fn nested_browsing_context(&self) -> Option<NestedBrowsingContext> {
+        self.base
+            .node()
+            .as_element()
+            .and_then(|element| element.nested_browsing_context.borrow().clone())
+    }
+
+    /// Whether this box's element is an `<iframe>`, loaded or not.
+    fn is_iframe(&self) -> bool {
+        self.base
+            .node()
+            .as_element()
+            .map_or(false, |element| element.name.local == local_name!("iframe"))
+    }
+
+    /// The kind of form control this box displays, if its element is one.
+    pub fn form_control(&self) -> Option<FormControlKind> {
+        FormControlKind::of(&self.base.node())
+    }
+
+    /// Returns the used inline-size and block-size of this box's content.
+    ///
+    /// Corresponds to CSS 2.1 sections 10.3.2 and 10.6.2.  Replaced elements that have no image
+    /// (e.g. because it failed to load) and aren't form controls are treated as having a natural
+    /// size of zero.  Iframes have no natural size or ratio, so their unspecified sizes are those
+    /// of the default object size.
+    /// https://www.w3.org/TR/CSS2/visudet.html#inline-replaced-width
+    /// https://www.w3.org/TR/CSS2/visudet.html#inline-replaced-height
+    pub fn used_content_size(
+        &self,
+        containing_block: ContainingBlock,
+    ) -> (CSSPixelLength, CSSPixelLength) {
+        let writing_mode = containing_block.writing_mode();
+        let (natural_width, natural_height) = self.natural_size();
+        let (natural_inline_size, natural_block_size) = if writing_mode.is_horizontal() {
+            (natural_width, natural_height)
+        } else {
+            (natural_height, natural_width)
+        };
+
+        let computed_values = self.computed_values();
+        let specified_inline_size = match computed_values.inline_size(writing_mode) {
+            LengthPercentageOrAuto::LengthPercentage(lp) => {
+                Some(lp.to_px(containing_block.self_relative_inline_size()).px())
+            }
+            LengthPercentageOrAuto::Auto => None,
+        };
+        let specified_block_size = match computed_values.block_size(writing_mode) {
+            // Percentages of a height that depends on the contents of the containing block behave
+            // as `auto`.
+            LengthPercentageOrAuto::LengthPercentage(lp @ LengthPercentage::Percentage(_))
+                if writing_mode.is_horizontal() =>
+            {
+                containing_block
+                    .definite_height()
+                    .map(|height| lp.to_px(height).px())
+            }
+            LengthPercentageOrAuto::LengthPercentage(lp) => {
+                Some(lp.to_px(containing_block.self_relative_block_size()).px())
+            }
+            LengthPercentageOrAuto::Auto => None,
+        };
+
+        let has_ratio = !self.is_iframe() && natural_inline_size > 0. && natural_block_size > 0.;
+        let (inline_size, block_size) = match (specified_inline_size, specified_block_size) {
+            (Some(inline_size), Some(block_size)) => (inline_size, block_size),
+            // If only one of the sizes is specified, the other follows from the natural ratio.
+            (Some(inline_size), None) if has_ratio => (
+                inline_size,
+                inline_size * natural_block_size / natural_inline_size,
+            ),
+            (None, Some(block_size)) if has_ratio => (
+                block_size * natural_inline_size / natural_block_size,
+                block_size,
+            ),
+            (Some(inline_size), None) => (inline_size, natural_block_size),
+            (None, Some(block_size)) => (natural_inline_size, block_size),
+            (None, None) => (natural_inline_size, natural_block_size),
+        };
+        (
+            CSSPixelLength::new(inline_size),
+            CSSPixelLength::new(block_size),
+        )
+    }
+
+    /// Returns the rect the image of this box should be painted into, which is sized according to
+    /// `object-fit` and positioned within the content box according to `object-position`.  The
+    /// returned rect may extend past the content box, in which case the image should be clipped to
+    /// the content box.
+    ///
+    /// https://drafts.csswg.org/css-images-3/#the-object-fit
+    /// https://drafts.csswg.org/css-images-3/#the-object-position
+    pub fn object_rect(&self) -> Rect {
+        let content_box = self.dimensions().content;
+        let computed_values = self.computed_values();
+        let (width, height) = concrete_object_size(
+            computed_values.object_fit,
+            self.natural_size(),
+            (content_box.width.px(), content_box.height.px()),
+        );
+        let (width, height) = (CSSPixelLength::new(width), CSSPixelLength::new(height));
+        let object_position = computed_values.object_position;
+        Rect {
+            start_x: content_box.start_x
+                + object_position
+                    .horizontal
+                    .to_px(content_box.width - width)
+                    .px(),
+            start_y: content_box.start_y
+                + object_position
+                    .vertical
+                    .to_px(content_box.height - height)
+                    .px(),
+            width,
+            height,
+        }
+    }
+
+    /// The rect covering the given columns of the given line of this box's text, used to paint the
+    /// caret and selection of text controls.  Like the sizing of form controls, this assumes every
+    /// character has the average character width.
+    ///
+    /// TODO: Measure the actual text once text layout is implemented.
+    pub fn text_rect(&self, line: usize, columns: Range<usize>) -> Rect {
+        let content_box = self.dimensions().content;
+        let font_size = self.computed_values().font_size.size.px();
+        let char_width = font_size * AVERAGE_CHAR_WIDTH_EM;
+        let line_height = font_size * LINE_HEIGHT_EM;
+        Rect {
+            start_x: content_box.start_x + columns.start as f32 * char_width,
+            start_y: content_box.start_y + line as f32 * line_height,
+            width: CSSPixelLength::new(columns.len() as f32 * char_width),
+            height: CSSPixelLength::new(line_height),
+        }
+    }
+
+    /// The baselines of the first and last lines of text of this box, if it's a form control that
+    /// shows text (i.e. a text field, text area, button, or `<select>`).  Other replaced boxes
+    /// have no baselines, so they're aligned by their margin edges.
+    ///
+    /// TODO: Use the metrics of the font once text layout is implemented.  Like the text of form
+    /// controls, this only supports horizontal writing modes.
+    pub fn baselines(&self) -> Option<Baselines> {
+        let node = self.base.node();
+        let element = node.as_element()?;
+        let lines = match self.form_control()? {
+            FormControlKind::TextField | FormControlKind::Button => 1,
+            FormControlKind::TextArea => form::textarea_rows(element),
+            FormControlKind::Select if form::is_drop_down_box(element) => 1,
+            FormControlKind::Select => form::select_display_size(element),
+            FormControlKind::Checkbox | FormControlKind::Radio => return None,
+        };
+        let computed_values = self.computed_values();
+        if !computed_values.writing_mode.is_horizontal() {
+            return None;
+        }
+        let font_size = computed_values.font_size.size.px();
+        let first = self.dimensions().content.start_y + font_size * BASELINE_EM;
+        Some(Baselines {
+            first,
+            last: first + lines.saturating_sub(1) as f32 * font_size * LINE_HEIGHT_EM,
+        })
+    }
+
+    /// The natural width and height of this box's content.  For images, this is the
+    /// density-corrected natural size of the image, or zero for both if there is no image.
+    /// Iframes don't have a natural size, so they're given the default size instead, and neither
+    /// do boxes with size containment, which are sized as if they had no image.
+    ///
+    /// https://drafts.csswg.org/css-contain-2/#containment-size
+    fn natural_size(&self) -> (f32, f32) {
+        if self.computed_values().contain.size {
+            return (0., 0.);
+        }
+        if let Some(kind) = self.form_control() {
+            let font_size = self.computed_values().font_size.size.px();
+            return form_control_natural_size(kind, &self.base.node(), font_size);
+        }
+        if self.is_iframe() {
+            return DEFAULT_IFRAME_SIZE_PX;
+        }
+        self.image_request()
+            .map(|image_request| {
+                (
+                    image_request.density_corrected_natural_width(),
+                    image_request.density_corrected_natural_height(),
+                )
+            })
+            .unwrap_or((0., 0.))
+    }
+
+    /// Sets the block-size of this box.  The inline-size is set while solving the inline-level
+    /// properties of this box, like all other block-level boxes.
+    pub fn layout_content(&mut self, containing_block: ContainingBlock) {
+        let (_, block_size) = self.used_content_size(containing_block);
+        self.dimensions_mut()
+            .set_block_size(block_size, containing_block.writing_mode());
+    }
+
+    /// Sets the margins, borders, padding, and content size of this box when it is laid out as an
+    /// atomic inline.  Positioning the box within its line is left to line layout.
+    ///
+    /// Corresponds to CSS 2.1 sections 10.3.2 and 10.6.2, under which `auto` margins of inline
+    /// replaced elements are zero.
+    /// https://www.w3.org/TR/CSS2/visudet.html#inline-replaced-width
+    pub fn solve_and_set_atomic_inline_properties(&mut self, containing_block: ContainingBlock) {
+        let writing_mode = containing_block.writing_mode();
+        let direction = containing_block.direction();
+        let (inline_size, block_size) = self.used_content_size(containing_block);
+        // Percentage margins and padding refer to the inline-size of the containing block, even
+        // in the block axis.
+        let percentage_basis = containing_block.self_relative_inline_size();
+
+        let computed_values = self.computed_values();
+        let sides = [
+            FlowSide::BlockStart,
+            FlowSide::BlockEnd,
+            FlowSide::InlineStart,
+            FlowSide::InlineEnd,
+        ];
+        let box_components = sides
+            .iter()
+            .map(|&side| {
+                (
+                    side,
+                    computed_values
+                        .margin_flow_relative(side, writing_mode)
+                        .to_px(percentage_basis),
+                    computed_values.border_flow_relative(side, writing_mode),
+                    computed_values
+                        .padding_flow_relative(side, writing_mode)
+                        .to_px(percentage_basis),
+                )
+            })
+            .collect::<Vec<_>>();
+        // Release this &self borrow so we can mutably borrow below.
+        drop(computed_values);
+
+        let dimensions = self.dimensions_mut();
+        for (side, margin, border, padding) in box_components {
+            dimensions.set_margin(side, margin, writing_mode, direction);
+            dimensions.set_border(side, border, writing_mode, direction);
+            dimensions.set_padding(side, padding, writing_mode, direction);
+        }
+        dimensions.set_inline_size(inline_size, writing_mode);
+        dimensions.set_block_size(block_size, writing_mode);
+    }
+}
+
+/// Determines the natural size of a form control widget from its attributes and font size, in the
+/// same way browsers size them: text fields by a number of average-width characters, buttons and
+/// drop-down boxes by their labels, list boxes by their labels and the number of options they
+/// show, and checkboxes and radio buttons at a fixed size.
+///
+/// TODO: Measure the actual text of labels once text layout is implemented.
+fn form_control_natural_size(kind: FormControlKind, node: &NodeRef, font_size: f32) -> (f32, f32) {
+    let char_width = font_size * AVERAGE_CHAR_WIDTH_EM;
+    let line_height = font_size * LINE_HEIGHT_EM;
+    let element = node
+        .as_element()
+        .expect("form controls are always elements");
+    match kind {
+        FormControlKind::TextField => (form::input_size(element) as f32 * char_width, line_height),
+        FormControlKind::TextArea => (
+            form::textarea_cols(element) as f32 * char_width,
+            form::textarea_rows(element) as f32 * line_height,
+        ),
+        FormControlKind::Button => (
+            form::button_label(node).chars().count() as f32 * char_width,
+            line_height,
+        ),
+        FormControlKind::Select => {
+            let longest_label = form::select_option_labels(node)
+                .iter()
+                .map(|label| label.chars().count())
+                .max()
+                .unwrap_or(0) as f32
+                * char_width;
+            if form::is_drop_down_box(element) {
+                // Leave a square at the inline-end of the box for the drop-down arrow.
+                (longest_label + line_height, line_height)
+            } else {
+                // List boxes show a line for each of the options they show at once.
+                let rows = form::select_display_size(element);
+                (longest_label, rows as f32 * line_height)
+            }
+        }
+        FormControlKind::Checkbox | FormControlKind::Radio => (TOGGLE_SIZE_PX, TOGGLE_SIZE_PX),
+    }
+}
+
+/// Determines the concrete size of an object with the given natural size when it is fit into a box
+/// of the given size, per `object-fit`.  Objects without a natural size fill the box.
+///
+/// https://drafts.csswg.org/css-images-3/#concrete-object-size
+fn concrete_object_size(
+    object_fit: ObjectFit,
+    (natural_width, natural_height): (f32, f32),
+    (box_width, box_height): (f32, f32),
+) -> (f32, f32) {
+    if natural_width <= 0. || natural_height <= 0. {
+        return (box_width, box_height);
+    }
+    let contain_scale = (box_width / natural_width).min(box_height / natural_height);
+    let cover_scale = (box_width / natural_width).max(box_height / natural_height);
+    let scale = match object_fit {
+        ObjectFit::Fill => return (box_width, box_height),
+        ObjectFit::Contain => contain_scale,
+        ObjectFit::Cover => cover_scale,
+        ObjectFit::None => 1.,
+        ObjectFit::ScaleDown => contain_scale.min(1.),
+    };
+    (natural_width * scale, natural_height * scale)
+}
+
+impl BaseLayoutBoxBehavior for ReplacedBox {
+    layout_box_behavior_base_box_passthrough_impls!();
+}
+
+impl ApplyPageRelativeProperties for ReplacedBox {
+    apply_page_relative_properties_base_box_passthrough_impls!();
+}
+
+impl DumpLayoutFormat for ReplacedBox {
+    fn dump_layout_format(&self) -> String {
+        format!("{} ReplacedBox", self.node().data().dump_layout_format())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::parser::parse_html;
+    use crate::dom::traits::*;
+
+    #[test]
+    fn form_control_natural_sizes() {
+        let document = parse_html().one(
+            "<input size=10><textarea cols=4 rows=3></textarea><button>Go</button>\
+             <select><option>One</option><option>Three</option></select><input type=radio>\
+             <select multiple><option>Two</option></select>",
+        );
+        let natural_size = |selector: &str| {
+            let node = document.select_first(selector).unwrap().as_node().clone();
+            form_control_natural_size(FormControlKind::of(&node).unwrap(), &node, 10.)
+        };
+        assert_eq!(natural_size("input"), (50., 12.));
+        assert_eq!(natural_size("textarea"), (20., 36.));
+        assert_eq!(natural_size("button"), (10., 12.));
+        assert_eq!(natural_size("select"), (37., 12.));
+        assert_eq!(natural_size("input[type=radio]"), (13., 13.));
+        assert_eq!(natural_size("select[multiple]"), (15., 48.));
+    }
+
+    #[test]
+    fn concrete_object_size_for_each_object_fit() {
+        let natural_size = (40., 20.);
+        let box_size = (20., 20.);
+        assert_eq!(
+            concrete_object_size(ObjectFit::Fill, natural_size, box_size),
+            (20., 20.)
+        );
+        assert_eq!(
+            concrete_object_size(ObjectFit::Contain, natural_size, box_size),
+            (20., 10.)
+        );
+        assert_eq!(
+            concrete_object_size(ObjectFit::Cover, natural_size, box_size),
+            (40., 20.)
+        );
+        assert_eq!(
+            concrete_object_size(ObjectFit::None, natural_size, box_size),
+            (40., 20.)
+        );
+        assert_eq!(
+            concrete_object_size(ObjectFit::ScaleDown, natural_size, box_size),
+            (20., 10.)
+        );
+        // Scaling down never scales the object up.
+        assert_eq!(
+            concrete_object_size(ObjectFit::ScaleDown, natural_size, (80., 80.)),
+            (40., 20.)
+        );
+    }
+}
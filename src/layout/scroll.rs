@@ -0,0 +1,723 @@
+//! Scrolling of the root document, the only scroll container Kosmonaut supports so far.
+//!
+//! https://drafts.csswg.org/cssom-view/#scrolling
+
+use crate::dom::tree::NodeRef;
+use crate::layout::behavior::BaseLayoutBoxBehavior;
+use crate::layout::layout_box::LayoutBox;
+use crate::layout::rect::{EdgeSizes, Point, Rect};
+use crate::style::values::computed::length::CSSPixelLength;
+use crate::style::values::computed::{
+    Overflow, ScrollBehavior, ScrollPadding, ScrollSnapAxis, ScrollSnapStrictness, ScrollSnapType,
+    SnapAlignment,
+};
+use crate::style::values::used::ToPx;
+use std::cmp::Ordering;
+use std::time::{Duration, Instant};
+
+/// How far one line of scrolling (e.g. a notch of a mouse wheel, or a press of an arrow key)
+/// scrolls the document, in CSS pixels.
+pub const LINE_SCROLL_DISTANCE_PX: f32 = 40.;
+
+/// How long smooth scrolls take to reach where they're scrolling to.
+const SMOOTH_SCROLL_DURATION: Duration = Duration::from_millis(150);
+
+/// How far from a `proximity` snap position a scroll can end and still snap to it, as a fraction
+/// of the size of the snapport in that axis.
+const SNAP_PROXIMITY: f32 = 0.3;
+
+/// How long after the last movement of a continuous scroll (e.g. a trackpad gesture) the scroll
+/// is taken to have ended, and settles on a snap position.
+const SNAP_SETTLE_DELAY: Duration = Duration::from_millis(100);
+
+/// Returns the furthest position the document laid out in `root` can be scrolled to in a
+/// viewport `viewport_width` by `viewport_height` CSS pixels in size: the end of its scrollable
+/// overflow, less the size of the viewport.
+///
+/// The scrollable overflow of the viewport is the initial containing block, along with the
+/// border boxes of every box in the document.  Overflow to the left or top of the initial
+/// containing block can't be scrolled to, so the smallest position is always the origin.
+///
+/// Axes the viewport's `overflow` doesn't let the user scroll in, as with
+/// `body { overflow: hidden }`, can't be scrolled at all.
+///
+/// TODO: Let programmatic scrolls (see `ScrollTarget`) scroll `overflow: hidden` axes.
+///
+/// https://drafts.csswg.org/css-overflow-3/#scrollable
+///
+/// TODO: Clip the scrollable overflow of boxes with `overflow` other than `visible` once
+/// Kosmonaut supports it.
+pub fn max_scroll_position(root: &LayoutBox, viewport_width: f32, viewport_height: f32) -> Point {
+    let viewport = Rect {
+        start_x: 0.,
+        start_y: 0.,
+        width: CSSPixelLength::new(viewport_width),
+        height: CSSPixelLength::new(viewport_height),
+    };
+    let overflow = scrollable_overflow(root, viewport);
+    let (overflow_x, overflow_y) = viewport_overflow(root);
+    let max_position = |overflow_end: CSSPixelLength, viewport_size: f32, axis: Overflow| {
+        if axis.is_user_scrollable() {
+            (overflow_end.px() - viewport_size).max(0.)
+        } else {
+            0.
+        }
+    };
+    Point {
+        x: max_position(
+            overflow.start_x + overflow.width,
+            viewport_width,
+            overflow_x,
+        ),
+        y: max_position(
+            overflow.start_y + overflow.height,
+            viewport_height,
+            overflow_y,
+        ),
+    }
+}
+
+/// The `overflow-x` and `overflow-y` of the viewport, which are propagated from the root element,
+/// or from `<body>` if the root element's are both `visible`.
+///
+/// https://drafts.csswg.org/css-overflow-3/#overflow-propagation
+pub fn viewport_overflow(root: &LayoutBox) -> (Overflow, Overflow) {
+    let overflow = |layout_box: &LayoutBox| {
+        let computed_values = layout_box.computed_values();
+        (computed_values.overflow_x, computed_values.overflow_y)
+    };
+    let visible = (Overflow::Visible, Overflow::Visible);
+    let (overflow_x, overflow_y) = match overflow(root) {
+        root_overflow if root_overflow != visible => root_overflow,
+        _ => root.root_body_box().map_or(visible, overflow),
+    };
+    (
+        overflow_x.used_for_viewport(),
+        overflow_y.used_for_viewport(),
+    )
+}
+
+/// What a programmatic scroll of the root document scrolls to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScrollTarget {
+    /// Scroll the border box of the given element to the top left of the viewport, as
+    /// `element.scrollIntoView()` does.
+    ///
+    /// https://drafts.csswg.org/cssom-view/#scroll-an-element-into-view
+    Element(NodeRef),
+    /// Scroll to the given position, in CSS pixels, as `window.scrollTo()` does.
+    ///
+    /// https://drafts.csswg.org/cssom-view/#dom-window-scrollto
+    Position(Point),
+}
+
+/// How a programmatic scroll moves, as its caller asks.
+///
+/// https://drafts.csswg.org/cssom-view/#enumdef-scrollbehavior
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ScrollOptionsBehavior {
+    /// Scroll smoothly if the viewport's `scroll-behavior` is `smooth`, and instantly otherwise.
+    Auto,
+    Instant,
+    Smooth,
+}
+
+/// Where a programmatic scroll of the document laid out in `root` to `target` scrolls to, and
+/// whether it scrolls there smoothly.  `None` if `target` is an element that isn't being
+/// rendered, so there's nowhere to scroll to.
+///
+/// The position isn't clamped to the document's scrollable overflow, which the scroller does.
+///
+/// https://drafts.csswg.org/cssom-view/#perform-a-scroll
+pub fn resolve_scroll(
+    root: &LayoutBox,
+    target: &ScrollTarget,
+    behavior: ScrollOptionsBehavior,
+) -> Option<(Point, bool)> {
+    let position = match target {
+        ScrollTarget::Element(element) => {
+            if element.client_rects().is_empty() {
+                return None;
+            }
+            let border_box = element.bounding_client_rect();
+            Point {
+                x: border_box.start_x,
+                y: border_box.start_y,
+            }
+        }
+        ScrollTarget::Position(position) => *position,
+    };
+    // The `scroll-behavior` of the root element applies to the viewport, and unlike `overflow`,
+    // `<body>`'s isn't propagated to it.
+    let smooth = match behavior {
+        ScrollOptionsBehavior::Auto => {
+            root.computed_values().scroll_behavior == ScrollBehavior::Smooth
+        }
+        ScrollOptionsBehavior::Instant => false,
+        ScrollOptionsBehavior::Smooth => true,
+    };
+    Some((position, smooth))
+}
+
+fn scrollable_overflow(layout_box: &LayoutBox, overflow: Rect) -> Rect {
+    let overflow = overflow.union(layout_box.dimensions().border_box());
+    match layout_box.children() {
+        Some(children) => children.iter().fold(overflow, |overflow, child| {
+            scrollable_overflow(child, overflow)
+        }),
+        None => overflow,
+    }
+}
+
+/// The scroll positions the viewport snaps to, which scrolls of the root document settle on.
+///
+/// https://drafts.csswg.org/css-scroll-snap-1/#snap-positions
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SnapPositions {
+    x: Option<AxisSnapPositions>,
+    y: Option<AxisSnapPositions>,
+}
+
+/// The snap positions in one axis.
+#[derive(Clone, Debug, PartialEq)]
+struct AxisSnapPositions {
+    positions: Vec<f32>,
+    /// How far from a snap position a scroll can end and still snap to it, which is infinite if
+    /// snapping is `mandatory`.
+    proximity: f32,
+}
+
+impl SnapPositions {
+    /// Where a scroll that would end at `position` ends once it's snapped.  Scrolls in a
+    /// direction from where they started at `from` (e.g. by a notch of a mouse wheel) snap to
+    /// positions beyond `from` in that direction, so that they move from one snap position to
+    /// the next rather than snapping back.
+    ///
+    /// https://drafts.csswg.org/css-scroll-snap-1/#choosing
+    pub fn snap(&self, position: Point, from: Option<Point>) -> Point {
+        Point {
+            x: self
+                .x
+                .as_ref()
+                .map_or(position.x, |x| x.snap(position.x, from.map(|from| from.x))),
+            y: self
+                .y
+                .as_ref()
+                .map_or(position.y, |y| y.snap(position.y, from.map(|from| from.y))),
+        }
+    }
+}
+
+impl AxisSnapPositions {
+    fn snap(&self, position: f32, from: Option<f32>) -> f32 {
+        let nearest = |candidates: &mut dyn Iterator<Item = f32>| {
+            candidates.min_by(|a, b| {
+                (a - position)
+                    .abs()
+                    .partial_cmp(&(b - position).abs())
+                    .unwrap_or(Ordering::Equal)
+            })
+        };
+        // Scrolls by less than a pixel don't have a direction.
+        let direction = from
+            .map(|from| (from, position - from))
+            .filter(|(_, delta)| delta.abs() >= 1.);
+        let is_beyond_start = |candidate: &f32| {
+            direction.map_or(true, |(from, delta)| {
+                (candidate - from) * delta.signum() >= 1.
+            })
+        };
+        let mut snapped = nearest(&mut self.positions.iter().copied().filter(is_beyond_start));
+        // Mandatory snapping rests on a snap position even when there's none in the direction
+        // scrolled, so scrolling past the last one scrolls back to it.
+        if snapped.is_none() && self.proximity.is_infinite() {
+            snapped = nearest(&mut self.positions.iter().copied());
+        }
+        match snapped {
+            Some(snapped) if (snapped - position).abs() <= self.proximity => snapped,
+            _ => position,
+        }
+    }
+}
+
+/// The snap positions of the viewport, with the document laid out in `root` in a viewport
+/// `viewport_width` by `viewport_height` CSS pixels in size.  The viewport snaps as the root
+/// element's `scroll-snap-type` says (which, unlike `overflow`, isn't propagated from `<body>`),
+/// aligning the snap area of each element with a `scroll-snap-align` (its border box, outset by
+/// its `scroll-margin`) with the snapport: the viewport, inset by the root element's
+/// `scroll-padding`.  Snap positions beyond where the document can be scrolled to are clamped
+/// to it.
+///
+/// TODO: Align `start` and `end` with the sides the writing mode and direction start and end on,
+/// rather than always the top and left.
+///
+/// https://drafts.csswg.org/css-scroll-snap-1/#snap-positions
+pub fn snap_positions(
+    root: &LayoutBox,
+    viewport_width: f32,
+    viewport_height: f32,
+) -> SnapPositions {
+    let computed_values = root.computed_values();
+    let (axis, strictness) = match computed_values.scroll_snap_type {
+        ScrollSnapType::None => return SnapPositions::default(),
+        ScrollSnapType::Snap { axis, strictness } => (axis, strictness),
+    };
+    // The alignments of snap areas are in the block and inline axes of the snap container.
+    let is_horizontal = computed_values.writing_mode.is_horizontal();
+    let (snaps_x, snaps_y) = match axis {
+        ScrollSnapAxis::X => (true, false),
+        ScrollSnapAxis::Y => (false, true),
+        ScrollSnapAxis::Block => (!is_horizontal, is_horizontal),
+        ScrollSnapAxis::Inline => (is_horizontal, !is_horizontal),
+        ScrollSnapAxis::Both => (true, true),
+    };
+    // `auto` scroll padding is used as zero.
+    let padding = |padding: ScrollPadding, viewport_size: f32| {
+        padding.size.to_px(CSSPixelLength::new(viewport_size)).px()
+    };
+    let snapport_x = (
+        padding(computed_values.scroll_padding_left, viewport_width),
+        viewport_width - padding(computed_values.scroll_padding_right, viewport_width),
+    );
+    let snapport_y = (
+        padding(computed_values.scroll_padding_top, viewport_height),
+        viewport_height - padding(computed_values.scroll_padding_bottom, viewport_height),
+    );
+    let max_position = max_scroll_position(root, viewport_width, viewport_height);
+
+    let mut positions_x = Vec::new();
+    let mut positions_y = Vec::new();
+    for node in root.node().inclusive_descendants() {
+        if node.as_element().is_none() || node.client_rects().is_empty() {
+            continue;
+        }
+        let values = node.computed_values();
+        let align = values.scroll_snap_align;
+        if align.is_none() {
+            continue;
+        }
+        let area = node.bounding_client_rect().expanded_by_edges(EdgeSizes {
+            left: values.scroll_margin_left.size,
+            right: values.scroll_margin_right.size,
+            top: values.scroll_margin_top.size,
+            bottom: values.scroll_margin_bottom.size,
+        });
+        let (align_x, align_y) = if is_horizontal {
+            (align.inline, align.block)
+        } else {
+            (align.block, align.inline)
+        };
+        if let Some(position) = snap_position(align_x, area.start_x, area.width.px(), snapport_x) {
+            positions_x.push(position.min(max_position.x).max(0.));
+        }
+        if let Some(position) = snap_position(align_y, area.start_y, area.height.px(), snapport_y) {
+            positions_y.push(position.min(max_position.y).max(0.));
+        }
+    }
+    let axis_positions = |snaps: bool, positions: Vec<f32>, snapport: (f32, f32)| {
+        if !snaps || positions.is_empty() {
+            return None;
+        }
+        let proximity = match strictness {
+            ScrollSnapStrictness::Mandatory => f32::INFINITY,
+            ScrollSnapStrictness::Proximity => (snapport.1 - snapport.0).max(0.) * SNAP_PROXIMITY,
+        };
+        Some(AxisSnapPositions {
+            positions,
+            proximity,
+        })
+    };
+    SnapPositions {
+        x: axis_positions(snaps_x, positions_x, snapport_x),
+        y: axis_positions(snaps_y, positions_y, snapport_y),
+    }
+}
+
+/// The scroll position in one axis that aligns a snap area starting at `area_start` and
+/// `area_size` long with the snapport spanning `snapport` of the viewport, or `None` if the area
+/// doesn't snap in this axis.
+fn snap_position(
+    alignment: SnapAlignment,
+    area_start: f32,
+    area_size: f32,
+    snapport: (f32, f32),
+) -> Option<f32> {
+    let (snapport_start, snapport_end) = snapport;
+    match alignment {
+        SnapAlignment::None => None,
+        SnapAlignment::Start => Some(area_start - snapport_start),
+        SnapAlignment::End => Some(area_start + area_size - snapport_end),
+        SnapAlignment::Center => {
+            Some(area_start + area_size / 2. - (snapport_start + snapport_end) / 2.)
+        }
+    }
+}
+
+/// The scroll position of the root document: the point of the document shown at the top left
+/// of the viewport, in CSS pixels.
+///
+/// Scrolls by discrete steps (e.g. lines or pages) are animated, easing into where they scroll
+/// to, while scrolls that are already continuous (e.g. trackpad gestures) jump straight there.
+/// Discrete and programmatic scrolls snap to the viewport's snap positions (if any) straight
+/// away, while continuous scrolls settle on them once they stop.
+///
+/// https://drafts.csswg.org/cssom-view/#smooth-scroll
+#[derive(Clone, Debug, Default)]
+pub struct RootScroller {
+    /// Where the document is scrolled to, or is being smoothly scrolled to.
+    target: Point,
+    /// The smooth scroll towards `target` in progress, if any.
+    animation: Option<ScrollAnimation>,
+    /// The furthest position the document can be scrolled to.
+    max_position: Point,
+    /// The positions scrolls snap to.
+    snap_positions: SnapPositions,
+    /// When the continuous scroll in progress, if any, is taken to have ended, so it settles on
+    /// a snap position.
+    settles_at: Option<Instant>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct ScrollAnimation {
+    from: Point,
+    start: Instant,
+}
+
+impl RootScroller {
+    /// Returns the scroll position at `now`, part of the way through any smooth scroll.
+    pub fn position_at(&self, now: Instant) -> Point {
+        let animation = match self.animation {
+            Some(animation) => animation,
+            None => return self.target,
+        };
+        let progress = now.saturating_duration_since(animation.start).as_secs_f32()
+            / SMOOTH_SCROLL_DURATION.as_secs_f32();
+        if progress >= 1. {
+            return self.target;
+        }
+        // Ease out (cubically), so scrolls start quickly and settle gently.
+        let eased = 1. - (1. - progress).powi(3);
+        Point {
+            x: animation.from.x + (self.target.x - animation.from.x) * eased,
+            y: animation.from.y + (self.target.y - animation.from.y) * eased,
+        }
+    }
+
+    /// Whether a smooth scroll is still in progress at `now`, and so needs painting.
+    pub fn is_animating(&self, now: Instant) -> bool {
+        self.animation.map_or(false, |animation| {
+            now.saturating_duration_since(animation.start) < SMOOTH_SCROLL_DURATION
+        })
+    }
+
+    /// Smoothly scrolls by `delta` from where the document is being scrolled to, so that
+    /// repeated scrolls (e.g. holding an arrow key) add up.  Infinite deltas scroll to the start
+    /// or end of the document.
+    pub fn scroll_by(&mut self, delta: Point, now: Instant) {
+        let from = self.position_at(now);
+        self.target = self.snapped(
+            Point {
+                x: self.target.x + delta.x,
+                y: self.target.y + delta.y,
+            },
+            Some(self.target),
+        );
+        self.animation = Some(ScrollAnimation { from, start: now });
+    }
+
+    /// Immediately scrolls by `delta` from the current scroll position, ending any smooth scroll.
+    pub fn jump_by(&mut self, delta: Point, now: Instant) {
+        let position = self.position_at(now);
+        self.target = self.snapped(
+            Point {
+                x: position.x + delta.x,
+                y: position.y + delta.y,
+            },
+            Some(position),
+        );
+        self.animation = None;
+    }
+
+    /// Immediately scrolls by `delta` from the current scroll position as part of a continuous
+    /// scroll (e.g. a trackpad gesture), which isn't snapped until it settles.
+    pub fn drag_by(&mut self, delta: Point, now: Instant) {
+        let position = self.position_at(now);
+        self.target = self.clamp(Point {
+            x: position.x + delta.x,
+            y: position.y + delta.y,
+        });
+        self.animation = None;
+        self.settles_at = Some(now + SNAP_SETTLE_DELAY);
+    }
+
+    /// Smoothly scrolls to `position` from the current scroll position.
+    pub fn scroll_to(&mut self, position: Point, now: Instant) {
+        let from = self.position_at(now);
+        self.target = self.snapped(position, None);
+        self.animation = Some(ScrollAnimation { from, start: now });
+    }
+
+    /// Immediately scrolls to `position`, ending any smooth scroll.
+    pub fn jump_to(&mut self, position: Point) {
+        self.target = self.snapped(position, None);
+        self.animation = None;
+    }
+
+    /// When the continuous scroll in progress, if any, settles on a snap position (see
+    /// `settle`).
+    pub fn settles_at(&self) -> Option<Instant> {
+        self.settles_at
+    }
+
+    /// Settles the continuous scroll in progress on the nearest snap position (smoothly, if
+    /// `smooth`) if it has stopped by `now`, returning whether the document is scrolled.
+    pub fn settle(&mut self, now: Instant, smooth: bool) -> bool {
+        match self.settles_at {
+            Some(settles_at) if settles_at <= now => self.settles_at = None,
+            _ => return false,
+        }
+        let position = self.position_at(now);
+        let snapped = self.snapped(position, None);
+        if snapped == position {
+            return false;
+        }
+        if smooth {
+            self.scroll_to(snapped, now);
+        } else {
+            self.jump_to(snapped);
+        }
+        true
+    }
+
+    /// Sets the positions scrolls snap to, e.g. after the document was laid out again.
+    ///
+    /// TODO: Snap the current scroll position again when the snap positions change.
+    pub fn set_snap_positions(&mut self, snap_positions: SnapPositions) {
+        self.snap_positions = snap_positions;
+    }
+
+    /// Sets the furthest position the document can be scrolled to, e.g. after the document was
+    /// laid out again, scrolling back within it if needed.
+    pub fn set_max_position(&mut self, max_position: Point) {
+        self.max_position = max_position;
+        self.target = self.clamp(self.target);
+    }
+
+    /// `position`, snapped as a scroll from `from` (see `SnapPositions::snap`), and clamped.
+    fn snapped(&self, position: Point, from: Option<Point>) -> Point {
+        self.clamp(self.snap_positions.snap(self.clamp(position), from))
+    }
+
+    fn clamp(&self, position: Point) -> Point {
+        Point {
+            x: position.x.min(self.max_position.x).max(0.),
+            y: position.y.min(self.max_position.y).max(0.),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::traits::*;
+    use crate::style::test_utils::laid_out_document;
+
+    fn scroller(max_x: f32, max_y: f32) -> RootScroller {
+        let mut scroller = RootScroller::default();
+        scroller.set_max_position(Point { x: max_x, y: max_y });
+        scroller
+    }
+
+    #[test]
+    fn smooth_scrolls_ease_into_their_target() {
+        let mut scroller = scroller(0., 1000.);
+        let start = Instant::now();
+        scroller.scroll_by(Point { x: 0., y: 100. }, start);
+        assert_eq!(scroller.position_at(start), Point::default());
+        let halfway = scroller.position_at(start + SMOOTH_SCROLL_DURATION / 2);
+        // Easing out covers most of the distance in the first half of the scroll.
+        assert!(halfway.y > 50. && halfway.y < 100.);
+        assert!(scroller.is_animating(start + SMOOTH_SCROLL_DURATION / 2));
+        let end = start + SMOOTH_SCROLL_DURATION;
+        assert_eq!(scroller.position_at(end), Point { x: 0., y: 100. });
+        assert!(!scroller.is_animating(end));
+    }
+
+    #[test]
+    fn repeated_scrolls_add_up() {
+        let mut scroller = scroller(0., 1000.);
+        let start = Instant::now();
+        scroller.scroll_by(Point { x: 0., y: 100. }, start);
+        scroller.scroll_by(Point { x: 0., y: 100. }, start + Duration::from_millis(10));
+        assert_eq!(
+            scroller.position_at(start + Duration::from_secs(1)),
+            Point { x: 0., y: 200. }
+        );
+    }
+
+    #[test]
+    fn scrolls_are_clamped_to_the_scrollable_overflow() {
+        let mut scroller = scroller(50., 300.);
+        let start = Instant::now();
+        scroller.scroll_by(
+            Point {
+                x: -10.,
+                y: f32::INFINITY,
+            },
+            start,
+        );
+        let end = start + SMOOTH_SCROLL_DURATION;
+        assert_eq!(scroller.position_at(end), Point { x: 0., y: 300. });
+        scroller.jump_by(Point { x: 80., y: -100. }, end);
+        assert_eq!(scroller.position_at(end), Point { x: 50., y: 200. });
+        // Shrinking the document scrolls back within it.
+        scroller.set_max_position(Point { x: 0., y: 150. });
+        assert_eq!(scroller.position_at(end), Point { x: 0., y: 150. });
+    }
+
+    #[test]
+    fn scrolls_to_positions() {
+        let mut scroller = scroller(0., 1000.);
+        let start = Instant::now();
+        scroller.scroll_by(Point { x: 0., y: 100. }, start);
+        scroller.scroll_to(Point { x: 0., y: 50. }, start + SMOOTH_SCROLL_DURATION / 2);
+        assert!(scroller.position_at(start + SMOOTH_SCROLL_DURATION / 2).y > 50.);
+        assert_eq!(
+            scroller.position_at(start + SMOOTH_SCROLL_DURATION * 2),
+            Point { x: 0., y: 50. }
+        );
+        scroller.jump_to(Point { x: 10., y: 2000. });
+        assert_eq!(scroller.position_at(start), Point { x: 0., y: 1000. });
+    }
+
+    #[test]
+    fn resolves_scrolls_to_elements() {
+        let (document, box_tree) = laid_out_document(
+            "<!DOCTYPE html><div style='height: 300px'></div><p>Target</p>\
+             <p style='display: none'>Hidden</p>",
+            "html { scroll-behavior: smooth } p { margin-top: 0px }",
+        );
+        let target = |selector: &str| {
+            ScrollTarget::Element(document.select_first(selector).unwrap().as_node().clone())
+        };
+        let body_margin = 8.;
+        assert_eq!(
+            resolve_scroll(&box_tree, &target("p"), ScrollOptionsBehavior::Auto),
+            Some((
+                Point {
+                    x: body_margin,
+                    y: body_margin + 300.
+                },
+                true
+            ))
+        );
+        assert_eq!(
+            resolve_scroll(
+                &box_tree,
+                &ScrollTarget::Position(Point { x: 0., y: 20. }),
+                ScrollOptionsBehavior::Instant
+            ),
+            Some((Point { x: 0., y: 20. }, false))
+        );
+        assert_eq!(
+            resolve_scroll(
+                &box_tree,
+                &target("p:last-child"),
+                ScrollOptionsBehavior::Smooth
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn scrolls_snap_to_the_snap_areas_of_elements() {
+        let (_, box_tree) = laid_out_document(
+            "<!DOCTYPE html><section></section><section></section><section></section>\
+             <section></section>",
+            "html { scroll-snap-type: y mandatory; scroll-padding-top: 10px } \
+             body { margin: 0px } \
+             section { height: 500px; scroll-snap-align: start; scroll-margin-top: 5px }",
+        );
+        let mut scroller = scroller(0., 1400.);
+        scroller.set_snap_positions(snap_positions(&box_tree, 800., 600.));
+        let start = Instant::now();
+        let at = |y| Point { x: 0., y };
+
+        // Each section snaps 15px above its top, except those beyond where the document can be
+        // scrolled to.
+        scroller.jump_by(at(40.), start);
+        assert_eq!(scroller.position_at(start), at(485.));
+        scroller.scroll_by(at(40.), start);
+        let later = start + SMOOTH_SCROLL_DURATION;
+        assert_eq!(scroller.position_at(later), at(985.));
+        scroller.jump_by(at(-1.), later);
+        assert_eq!(scroller.position_at(later), at(485.));
+        scroller.jump_to(at(1300.));
+        assert_eq!(scroller.position_at(later), at(1400.));
+
+        // Continuous scrolls only snap once they settle.
+        scroller.drag_by(at(-100.), later);
+        assert_eq!(scroller.position_at(later), at(1300.));
+        assert!(!scroller.settle(later, false));
+        let settled = scroller.settles_at().unwrap();
+        assert!(scroller.settle(settled, false));
+        assert_eq!(scroller.position_at(settled), at(1400.));
+        assert_eq!(scroller.settles_at(), None);
+    }
+
+    #[test]
+    fn proximity_snapping_only_snaps_nearby_scrolls() {
+        let snap_positions = SnapPositions {
+            x: None,
+            y: Some(AxisSnapPositions {
+                positions: vec![0., 500.],
+                proximity: 100.,
+            }),
+        };
+        let snap = |y, from: Option<f32>| {
+            snap_positions
+                .snap(Point { x: 3., y }, from.map(|y| Point { x: 3., y }))
+                .y
+        };
+        assert_eq!(snap(450., None), 500.);
+        assert_eq!(snap(300., None), 300.);
+        // Scrolls away from a snap position don't snap back to it.
+        assert_eq!(snap(40., Some(0.)), 40.);
+        assert_eq!(snap(420., Some(380.)), 500.);
+        assert_eq!(snap(20., Some(60.)), 0.);
+    }
+
+    /// The furthest a document with a 2000px by 2000px box in it, styled by `css`, can be scrolled
+    /// in an 800px by 600px viewport.
+    fn max_scroll_position_with(css: &str) -> Point {
+        let (_, box_tree) = laid_out_document(
+            "<!DOCTYPE html><div style='width: 2000px; height: 2000px'></div>",
+            css,
+        );
+        max_scroll_position(&box_tree, 800., 600.)
+    }
+
+    #[test]
+    fn overflow_propagates_to_the_viewport() {
+        let scrollable = Point { x: 1208., y: 1416. };
+        assert_eq!(max_scroll_position_with(""), scrollable);
+        assert_eq!(
+            max_scroll_position_with("body { overflow: hidden }"),
+            Point::default()
+        );
+        assert_eq!(
+            max_scroll_position_with("body { overflow: scroll hidden }"),
+            Point { x: 1208., y: 0. }
+        );
+        assert_eq!(
+            max_scroll_position_with("html { overflow-y: clip }"),
+            Point { x: 1208., y: 0. }
+        );
+        // The root element's `overflow` takes precedence over `<body>`'s, which then isn't
+        // propagated.
+        assert_eq!(
+            max_scroll_position_with("html { overflow: auto } body { overflow: hidden }"),
+            scrollable
+        );
+    }
+}
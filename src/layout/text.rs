@@ -0,0 +1,472 @@
+//! Shaping of the text of text runs for line layout.  Text is measured by shaping it with the
+//! fonts it's rendered with, so that lines break where the text actually ends, and the glyphs it
+//! was shaped into are kept to be painted where layout put them.
+//!
+//! Without the `window` feature there are no system fonts to shape with, and text that no
+//! installed font can render has no glyphs either.  Such text takes up the average char width
+//! (see `AVERAGE_CHAR_WIDTH_EM`) per char, and paints nothing.
+
+use crate::gfx::glyph::{GlyphRun, PositionedGlyph};
+use crate::layout::replaced::{AVERAGE_CHAR_WIDTH_EM, LINE_HEIGHT_EM};
+use crate::style::values::computed::ComputedValues;
+use pathfinder_geometry::vector::Vector2F;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// The part of a line the ascent of text without a font takes up, which is where the test font
+/// puts its baseline.
+const FALLBACK_ASCENT_RATIO: f32 = 0.8;
+
+/// Text shaped for layout.  Distances are in CSS pixels.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShapedText {
+    /// The glyphs the text was shaped into, in visual order, positioned relative to the start of
+    /// the text's baseline.
+    pub runs: Vec<GlyphRun>,
+    /// The clusters of the text, in logical order.
+    clusters: Vec<Cluster>,
+    /// How far the text advances the pen.
+    pub advance: f32,
+    /// How far the text extends above its baseline, including half of its fonts' line gap.
+    pub ascent: f32,
+    /// How far the text extends below its baseline, including the other half of the line gap.
+    pub descent: f32,
+}
+
+/// Chars shaped together, such as a letter and its combining marks, or those of a ligature.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Cluster {
+    /// The byte index of the first char of the cluster in the shaped text.
+    start: usize,
+    /// The x-coordinate of the left edge of the cluster, relative to the start of the text.
+    x: f32,
+    advance: f32,
+    /// The advance of the clusters before this one in logical order.
+    logical_offset: f32,
+    /// Whether the cluster is of right-to-left text, whose chars run from right to left.
+    rtl: bool,
+}
+
+impl ShapedText {
+    /// Text without any clusters.
+    fn empty() -> ShapedText {
+        ShapedText {
+            runs: Vec::new(),
+            clusters: Vec::new(),
+            advance: 0.,
+            ascent: 0.,
+            descent: 0.,
+        }
+    }
+
+    /// The advance of the text before the byte index `end`.
+    pub fn advance_before(&self, end: usize) -> f32 {
+        let index = self.clusters.partition_point(|cluster| cluster.start < end);
+        self.clusters
+            .get(index)
+            .map_or(self.advance, |cluster| cluster.logical_offset)
+    }
+
+    /// The part of the text shaped from the bytes in `range`, moved to start at the start of the
+    /// baseline, with byte indices relative to `range.start`.  Its vertical extent is that of the
+    /// whole text.
+    ///
+    /// The text isn't shaped again, so glyphs shaped across either end of the range (e.g. kerned
+    /// against a neighbour) stay as they were.
+    pub fn slice(&self, range: Range<usize>) -> ShapedText {
+        let clusters: Vec<Cluster> = self
+            .clusters
+            .iter()
+            .filter(|cluster| range.contains(&cluster.start))
+            .copied()
+            .collect();
+        let start_x = clusters
+            .iter()
+            .map(|cluster| cluster.x)
+            .fold(f32::INFINITY, f32::min);
+        let start_x = if start_x.is_finite() { start_x } else { 0. };
+        let logical_offset = clusters
+            .first()
+            .map_or(0., |cluster| cluster.logical_offset);
+        let runs = self
+            .runs
+            .iter()
+            .map(|run| GlyphRun {
+                glyphs: run
+                    .glyphs
+                    .iter()
+                    .filter(|glyph| range.contains(&glyph.cluster))
+                    .map(|glyph| PositionedGlyph {
+                        cluster: glyph.cluster - range.start,
+                        position: glyph.position - Vector2F::new(start_x, 0.),
+                        ..*glyph
+                    })
+                    .collect(),
+                ..run.clone()
+            })
+            .filter(|run| !run.glyphs.is_empty())
+            .collect();
+        ShapedText {
+            runs,
+            advance: clusters.iter().map(|cluster| cluster.advance).sum(),
+            clusters: clusters
+                .into_iter()
+                .map(|cluster| Cluster {
+                    start: cluster.start - range.start,
+                    x: cluster.x - start_x,
+                    logical_offset: cluster.logical_offset - logical_offset,
+                    ..cluster
+                })
+                .collect(),
+            ascent: self.ascent,
+            descent: self.descent,
+        }
+    }
+
+    /// The x-coordinate of the boundary before each char of `text`, which this was shaped from,
+    /// followed by that of the boundary after its last char, relative to the start of the text.
+    /// Chars shaped into a single cluster split it evenly between them.
+    pub fn char_edges(&self, text: &str) -> Vec<f32> {
+        let char_starts: Vec<usize> = text.char_indices().map(|(index, _)| index).collect();
+        let mut edges = Vec::with_capacity(char_starts.len() + 1);
+        for (index, cluster) in self.clusters.iter().enumerate() {
+            let end = self
+                .clusters
+                .get(index + 1)
+                .map_or(text.len(), |next| next.start);
+            let chars = char_starts
+                .iter()
+                .filter(|&&start| start >= cluster.start && start < end)
+                .count()
+                .max(1);
+            for char_index in 0..chars {
+                let offset = cluster.advance * char_index as f32 / chars as f32;
+                edges.push(if cluster.rtl {
+                    cluster.x + cluster.advance - offset
+                } else {
+                    cluster.x + offset
+                });
+            }
+        }
+        let end = self.clusters.last().map_or(0., |cluster| {
+            if cluster.rtl {
+                cluster.x
+            } else {
+                cluster.x + cluster.advance
+            }
+        });
+        edges.resize(char_starts.len() + 1, end);
+        edges
+    }
+}
+
+/// Shapes `text`, which is styled by `style`, for layout.  `line_offset` is how far from the start
+/// of its line the text starts.
+pub fn shape_text(text: &str, style: &ComputedValues, line_offset: f32) -> ShapedText {
+    #[cfg(feature = "window")]
+    {
+        if let Some(shaped) = fonts::shape_with_fonts(text, style, line_offset) {
+            return shaped;
+        }
+    }
+    let _ = line_offset;
+    shape_without_fonts(text, style)
+}
+
+/// The data of the font named `postscript_name` that text is shaped with, which may be a
+/// collection of several fonts, along with the index of the font within it.  Returns `None` if the
+/// font can't be loaded, e.g. in builds without the system fonts text is shaped with.
+pub fn font_data(postscript_name: &str) -> Option<(Arc<Vec<u8>>, u32)> {
+    #[cfg(feature = "window")]
+    {
+        if let Some(font_data) = fonts::font_data(postscript_name) {
+            return Some(font_data);
+        }
+    }
+    let _ = postscript_name;
+    None
+}
+
+/// Measures `text` as if each of its chars were the average char width, with nothing to paint.
+fn shape_without_fonts(text: &str, style: &ComputedValues) -> ShapedText {
+    let size_px = style.font_size.size.px();
+    let char_width = size_px * AVERAGE_CHAR_WIDTH_EM;
+    let mut builder = TextBuilder::default();
+    builder.include_extent(
+        size_px * LINE_HEIGHT_EM * FALLBACK_ASCENT_RATIO,
+        size_px * LINE_HEIGHT_EM * (1. - FALLBACK_ASCENT_RATIO),
+    );
+    builder.push_clusters(
+        text.char_indices()
+            .map(|(index, _)| (index, char_width))
+            .collect(),
+        false,
+    );
+    builder.finish()
+}
+
+/// Lays shaped runs out one after another along a baseline.
+#[derive(Default)]
+struct TextBuilder {
+    runs: Vec<GlyphRun>,
+    clusters: Vec<Cluster>,
+    pen: f32,
+    ascent: f32,
+    descent: f32,
+}
+
+impl TextBuilder {
+    fn include_extent(&mut self, ascent: f32, descent: f32) {
+        self.ascent = self.ascent.max(ascent);
+        self.descent = self.descent.max(descent);
+    }
+
+    /// Adds clusters, given as their start and advance in visual order, at the pen, and moves it
+    /// past them.
+    fn push_clusters(&mut self, clusters: Vec<(usize, f32)>, rtl: bool) {
+        for (start, advance) in clusters {
+            self.clusters.push(Cluster {
+                start,
+                x: self.pen,
+                advance,
+                logical_offset: 0.,
+                rtl,
+            });
+            self.pen += advance;
+        }
+    }
+
+    fn finish(mut self) -> ShapedText {
+        if self.clusters.is_empty() {
+            return ShapedText::empty();
+        }
+        self.clusters.sort_by_key(|cluster| cluster.start);
+        let mut logical_offset = 0.;
+        for cluster in &mut self.clusters {
+            cluster.logical_offset = logical_offset;
+            logical_offset += cluster.advance;
+        }
+        ShapedText {
+            runs: self.runs,
+            clusters: self.clusters,
+            advance: self.pen,
+            ascent: self.ascent,
+            descent: self.descent,
+        }
+    }
+}
+
+#[cfg(feature = "window")]
+mod fonts {
+    use super::{ShapedText, TextBuilder};
+    use crate::gfx::font::{FontHandle, FontRun};
+    use crate::gfx::glyph::{GlyphRun, PositionedGlyph};
+    use crate::gfx::shape::{ShapeOptions, ShapedRun, Shaper};
+    use crate::style::values::computed::ComputedValues;
+    use font_kit::font::Font;
+    use pathfinder_geometry::vector::Vector2F;
+    use std::sync::Arc;
+
+    thread_local! {
+        /// The fonts text is shaped with, and the runs shaped with them so far.  Layout happens on
+        /// a single thread, so runs shaped by one layout are reused by the next.
+        static FONTS: (FontHandle, Shaper) = (FontHandle::new(), Shaper::new());
+    }
+
+    /// Shapes `text` with the fonts it's rendered with, falling back from the families of
+    /// `font-family` to other installed fonts for the chars they have no glyphs for, and
+    /// synthesizing bold and oblique faces the families don't have (see
+    /// `FontHandle::font_runs`).  Returns `None` if no font is installed at all.
+    pub(super) fn shape_with_fonts(
+        text: &str,
+        style: &ComputedValues,
+        line_offset: f32,
+    ) -> Option<ShapedText> {
+        let font_runs = FONTS.with(|(fonts, _)| fonts.font_runs(style, text));
+        shape_font_runs(text, style, line_offset, font_runs)
+    }
+
+    pub(super) fn font_data(postscript_name: &str) -> Option<(Arc<Vec<u8>>, u32)> {
+        FONTS.with(|(fonts, shaper)| {
+            let font = fonts.get_font(postscript_name).ok()?;
+            shaper.font_data(&font).ok()
+        })
+    }
+
+    /// Shapes each of `font_runs` of `text` with its matched font, one after the other.
+    ///
+    /// TODO: Runs of different fonts are laid out in logical order, even in right-to-left text.
+    pub(super) fn shape_font_runs(
+        text: &str,
+        style: &ComputedValues,
+        line_offset: f32,
+        font_runs: Vec<FontRun>,
+    ) -> Option<ShapedText> {
+        if font_runs.is_empty() {
+            return None;
+        }
+        FONTS.with(|(fonts, shaper)| {
+            let mut builder = TextBuilder::default();
+            for font_run in font_runs {
+                let font = fonts.get_font(&font_run.font.postscript_name).ok()?;
+                let options = ShapeOptions {
+                    size_px: style.font_size.size.px(),
+                    synthesis: font_run.font.synthesis,
+                    direction: None,
+                    letter_spacing: 0.,
+                    variant_caps: style.font_variant_caps,
+                    feature_settings: style.font_feature_settings.clone(),
+                    tab_size: style.tab_size,
+                    // Tab stops are measured from the start of the line, not of the font run.
+                    line_offset: line_offset + builder.pen,
+                };
+                let run = shaper
+                    .shape(&font, &options, &text[font_run.range.clone()])
+                    .ok()?;
+                builder.push_run(
+                    &font,
+                    &font_run.font.postscript_name,
+                    &run,
+                    font_run.range.start,
+                );
+            }
+            Some(builder.finish())
+        })
+    }
+
+    impl TextBuilder {
+        /// Adds `run`, shaped with `font` from the text starting at the byte index `text_start`,
+        /// at the pen, and moves it past the run.
+        pub(super) fn push_run(
+            &mut self,
+            font: &Font,
+            postscript_name: &str,
+            run: &ShapedRun,
+            text_start: usize,
+        ) {
+            let metrics = font.metrics();
+            let to_px = |units: f32| units * run.size_px() / metrics.units_per_em as f32;
+            let half_line_gap = to_px(metrics.line_gap) / 2.;
+            self.include_extent(
+                to_px(metrics.ascent) + half_line_gap,
+                to_px(-metrics.descent) + half_line_gap,
+            );
+            let mut pen = Vector2F::new(self.pen, 0.);
+            let mut glyphs = Vec::with_capacity(run.glyphs().len());
+            // Clusters are in visual order, as the glyphs they're made of are.
+            let mut clusters: Vec<(usize, f32)> = Vec::new();
+            for glyph in run.glyphs() {
+                let cluster = text_start + glyph.cluster;
+                glyphs.push(PositionedGlyph {
+                    glyph_id: glyph.glyph_id,
+                    cluster,
+                    position: pen + glyph.offset,
+                    advance: glyph.advance.x(),
+                    size_px: glyph.size_px,
+                });
+                pen += glyph.advance;
+                match clusters.last_mut() {
+                    Some((start, advance)) if *start == cluster => *advance += glyph.advance.x(),
+                    _ => clusters.push((cluster, glyph.advance.x())),
+                }
+            }
+            let rtl = match (clusters.first(), clusters.last()) {
+                (Some((first, _)), Some((last, _))) => first > last,
+                _ => false,
+            };
+            self.push_clusters(clusters, rtl);
+            self.runs.push(GlyphRun {
+                font: postscript_name.to_owned(),
+                synthesis: run.synthesis(),
+                glyphs,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::style::test_utils::laid_out_document;
+
+    #[test]
+    fn text_is_shaped_with_the_test_font_in_unit_tests() {
+        let (_, box_tree) = laid_out_document("<!DOCTYPE html><p>Hi</p>", "");
+        let style = box_tree.computed_values();
+        let shaped = shape_text("Hi there", &style, 0.);
+        // Every glyph of the test font, including the space, is a 16px box at the default size.
+        assert_eq!(shaped.advance, 128.);
+        assert_eq!((shaped.ascent, shaped.descent), (12.8, 3.2));
+        assert_eq!(shaped.advance_before(3), 48.);
+        let there = shaped.slice(3..8);
+        assert_eq!(there.advance, 80.);
+        assert_eq!(there.runs[0].glyphs[0].position, Vector2F::new(0., 0.));
+        assert_eq!(there.runs[0].glyphs[0].cluster, 0);
+        assert_eq!(there.char_edges("there"), vec![0., 16., 32., 48., 64., 80.]);
+    }
+
+    #[test]
+    fn text_falls_back_from_families_that_arent_installed() {
+        let (_, box_tree) = laid_out_document(
+            "<!DOCTYPE html><p>Hi</p>",
+            "html { font-family: \"Not A Real Font\", serif }",
+        );
+        let style = box_tree.computed_values();
+        let shaped = shape_text("Hi", &style, 0.);
+        // Unit tests render every family with the test font, as a single run.
+        assert_eq!(shaped.runs.len(), 1);
+        assert_eq!(shaped.runs[0].font, "Ahem");
+        assert_eq!(shaped.advance, 32.);
+    }
+
+    #[cfg(feature = "window")]
+    #[test]
+    fn synthesized_bold_text_is_wider() {
+        use crate::gfx::font::{FontRun, MatchedFont};
+        use crate::gfx::glyph::Synthesis;
+
+        let style = ComputedValues::default();
+        let bold = Synthesis {
+            bold: true,
+            oblique: false,
+        };
+        // The test font is never synthesized when matched, so match it by hand.
+        let font_runs = vec![FontRun {
+            range: 0..2,
+            font: MatchedFont {
+                postscript_name: "Ahem".to_owned(),
+                synthesis: bold,
+            },
+        }];
+        let shaped = fonts::shape_font_runs("Hi", &style, 0., font_runs).unwrap();
+        assert_eq!(shaped.runs[0].synthesis, bold);
+        assert_eq!(shaped.advance, 2. * (16. + bold.bold_offset(16.)));
+    }
+
+    #[test]
+    fn small_caps_the_font_lacks_are_synthesized_from_capitals() {
+        let (_, box_tree) = laid_out_document(
+            "<!DOCTYPE html><p>Hi</p>",
+            "html { font-variant-caps: small-caps }",
+        );
+        let style = box_tree.computed_values();
+        // The test font has no small capitals, so "ß" is rendered as the capitals "SS".
+        let shaped = shape_text("\u{df}", &style, 0.);
+        let clusters: Vec<usize> = shaped.runs[0]
+            .glyphs
+            .iter()
+            .map(|glyph| glyph.cluster)
+            .collect();
+        assert_eq!(clusters, vec![0, 0]);
+    }
+
+    #[test]
+    fn text_without_fonts_takes_up_the_average_char_width() {
+        let (_, box_tree) = laid_out_document("<!DOCTYPE html><p>Hi</p>", "");
+        let style = box_tree.computed_values();
+        let shaped = shape_without_fonts("abcd", &style);
+        assert!(shaped.runs.is_empty());
+        assert_eq!(shaped.advance, 32.);
+        assert_eq!(shaped.char_edges("abcd"), vec![0., 8., 16., 24., 32.]);
+    }
+}
@@ -1,2 +1,38 @@
-/// Export things here to make them available in integration tests.
-pub mod cli;
+//! Kosmonaut's engine: parsing HTML and CSS into a styled DOM, building and laying out boxes, and
+//! painting them, as a library other programs can embed.  `Page` is the place to start; the
+//! `kosmonaut` binary (`src/bin/kosmonaut`) is a browser window, and a set of headless tools,
+//! built on it.
+
+#![feature(or_patterns)]
+#![feature(type_name_of_val)]
+
+#[macro_use]
+extern crate cssparser;
+#[macro_use]
+extern crate html5ever;
+#[macro_use]
+extern crate matches;
+#[macro_use]
+extern crate strum_macros;
+#[macro_use]
+extern crate derive_builder;
+
+pub mod a11y;
+pub mod common;
+pub mod dom;
+pub mod ffi;
+pub mod gfx;
+pub mod iframe;
+pub mod image;
+pub mod layout;
+pub mod memory;
+pub mod page;
+pub mod script;
+pub mod style;
+pub mod trace;
+pub mod unsupported;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
+
+pub use common::Side;
+pub use page::Page;
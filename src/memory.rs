@@ -0,0 +1,139 @@
+//! Measuring how much heap memory each part of a page (its DOM, computed styles, box tree, display
+//! list, and caches) retains, so that memory regressions are visible.
+//!
+//! Memory is measured by counting the bytes allocated and freed through `CountingAllocator`, which
+//! the binary installs as the global allocator, before and after building each part.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The number of bytes currently allocated through `CountingAllocator`.
+static LIVE_HEAP_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// The system allocator, counting the bytes allocated through it.
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            LIVE_HEAP_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            LIVE_HEAP_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        LIVE_HEAP_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            LIVE_HEAP_BYTES.fetch_add(new_size, Ordering::Relaxed);
+            LIVE_HEAP_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+        }
+        new_ptr
+    }
+}
+
+/// The number of heap bytes currently allocated, which is always zero unless `CountingAllocator` is
+/// the global allocator.
+pub fn live_heap_bytes() -> usize {
+    LIVE_HEAP_BYTES.load(Ordering::Relaxed)
+}
+
+/// The heap memory retained by each part of a page, in the order they were measured.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MemoryReport {
+    parts: Vec<(&'static str, isize)>,
+}
+
+impl MemoryReport {
+    pub fn new() -> MemoryReport {
+        MemoryReport::default()
+    }
+
+    /// Runs `build`, and records the heap memory retained by what it built (i.e. allocated by it,
+    /// and not freed before it returned) as the size of `part`.
+    pub fn measure<T>(&mut self, part: &'static str, build: impl FnOnce() -> T) -> T {
+        let bytes_before = live_heap_bytes() as isize;
+        let built = build();
+        self.record(part, live_heap_bytes() as isize - bytes_before);
+        built
+    }
+
+    pub fn record(&mut self, part: &'static str, bytes: isize) {
+        self.parts.push((part, bytes));
+    }
+
+    pub fn parts(&self) -> &[(&'static str, isize)] {
+        &self.parts
+    }
+
+    pub fn total_bytes(&self) -> isize {
+        self.parts.iter().map(|(_, bytes)| bytes).sum()
+    }
+}
+
+/// Formats the report as a table of the size of each part, followed by the total, e.g.:
+///
+/// ```text
+/// stylesheets         12.5 KiB
+/// DOM                  3.1 KiB
+/// total               15.6 KiB
+/// ```
+impl std::fmt::Display for MemoryReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let total = ("total", self.total_bytes());
+        for (part, bytes) in self.parts.iter().chain(std::iter::once(&total)) {
+            writeln!(f, "{:<16}{:>12}", part, format_bytes(*bytes))?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats a number of bytes in the largest binary unit it's at least one of, e.g. `1.5 KiB`.
+pub fn format_bytes(bytes: isize) -> String {
+    const UNITS: [&str; 3] = ["KiB", "MiB", "GiB"];
+    if bytes.abs() < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.;
+    let mut unit = 0;
+    while size.abs() >= 1024. && unit < UNITS.len() - 1 {
+        size /= 1024.;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_reports_in_binary_units() {
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(-3 * 1024 * 1024), "-3.0 MiB");
+        let mut report = MemoryReport::new();
+        report.record("DOM", 2048);
+        report.record("box tree", 512);
+        assert_eq!(report.total_bytes(), 2560);
+        assert_eq!(
+            report.to_string(),
+            "DOM                  2.0 KiB\n\
+             box tree               512 B\n\
+             total                2.5 KiB\n"
+        );
+    }
+}
@@ -0,0 +1,715 @@
+//! The API for embedding Kosmonaut in other programs: loading a document, setting the size of the
+//! viewport it's rendered in, laying it out, rendering it, and finding what's painted at a point,
+//! all without opening a window.
+//!
+//! ```no_run
+//! use kosmonaut_core::layout::rect::Point;
+//! use kosmonaut_core::Page;
+//! use std::path::Path;
+//!
+//! let mut page = Page::load_document(Path::new("index.html"), Vec::new())?;
+//! page.set_viewport(1280., 720., 2.);
+//! page.render_to(Path::new("index.png"))?;
+//! for node in page.hit_test(Point { x: 100., y: 50. }) {
+//!     println!("{:?}", node);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tracing::info_span;
+
+use crate::dom::mutation;
+use crate::dom::parser::{
+    is_xml_document, parse_html_with_options, parse_xml, IncrementalParser, ParseOpts,
+};
+use crate::dom::traits::TendrilSink;
+use crate::dom::tree::NodeRef;
+use crate::gfx::display::{build_page_display_list, DisplayList};
+use crate::gfx::raster::rasterize;
+use crate::gfx::{DEFAULT_INNER_WINDOW_HEIGHT_PX, DEFAULT_INNER_WINDOW_WIDTH_PX};
+use crate::iframe::load_nested_browsing_contexts;
+use crate::image::cache::ImageCache;
+use crate::image::{load_document_images, save_png, DecodedImage};
+use crate::layout::box_tree::{build_box_tree, update_box_tree};
+use crate::layout::geometry::record_query_containers;
+use crate::layout::global_layout;
+use crate::layout::layout_box::LayoutBox;
+use crate::layout::rect::Point;
+use crate::script::{ScriptEngine, ScriptEngineSlot};
+use crate::style::invalidation::InvalidationMap;
+use crate::style::media::{ColorScheme, Device, MediaType};
+use crate::style::stylesheet::{parse_css_to_stylesheet, Stylesheet};
+use crate::style::{apply_styles, recompute_values, restyle_mutations, restyle_query_containers};
+
+/// How many times laying out a document can resize its query containers in a row before the
+/// layout is kept as it is, so that `@container` rules whose styles resize the containers they
+/// query can't restyle and lay out the document forever.
+const MAX_CONTAINER_QUERY_PASSES: usize = 4;
+
+/// The user agent stylesheet, which gives elements their default styles.
+pub fn user_agent_stylesheet() -> Stylesheet {
+    parse_css_to_stylesheet(
+        Some("browser.css".to_owned()),
+        &mut include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/web/browser.css")).to_owned(),
+    )
+}
+
+/// Parses the stylesheet in the file at `path`.
+pub fn load_stylesheet(path: &Path) -> io::Result<Stylesheet> {
+    Ok(parse_css_to_stylesheet(
+        Some(path.to_string_lossy().into_owned()),
+        &mut std::fs::read_to_string(path)?,
+    ))
+}
+
+/// A document loaded from a file, with styles applied.
+pub struct LoadedDocument {
+    pub dom: NodeRef,
+    /// The cache the document's images are loaded into.
+    pub image_cache: ImageCache,
+    /// The path of the file the document was loaded from.
+    pub path: PathBuf,
+    /// The engine the document's scripts ran in, if it had any.
+    pub script_engine: Option<ScriptEngine>,
+}
+
+/// Parses the HTML document at `html_file` (or XML document, if it's an XHTML file; see
+/// `is_xml_document`) and applies the given stylesheets to it, as rendered on `device`.
+pub fn load_document(
+    html_file: &Path,
+    device: &Device,
+    ua_sheets: &[Stylesheet],
+    author_sheets: &[Stylesheet],
+) -> io::Result<LoadedDocument> {
+    load_document_in(html_file, device, ua_sheets, author_sheets, &[])
+}
+
+/// Loads the document at `html_file`, like `load_document` does, into an iframe of each of the
+/// documents at `ancestor_paths` (the outermost first).
+pub(crate) fn load_document_in(
+    html_file: &Path,
+    device: &Device,
+    ua_sheets: &[Stylesheet],
+    author_sheets: &[Stylesheet],
+    ancestor_paths: &[PathBuf],
+) -> io::Result<LoadedDocument> {
+    let script_engine = ScriptEngineSlot::default();
+    let dom = {
+        let _span = info_span!("parse_html").entered();
+        let mut file = File::open(html_file)?;
+        if is_xml_document(html_file) {
+            parse_xml().from_utf8().read_from(&mut file)?
+        } else {
+            parse_html_with_options(ParseOpts {
+                script_engine: Some(script_engine.clone()),
+                ..ParseOpts::default()
+            })
+            .from_utf8()
+            .read_from(&mut file)?
+        }
+    };
+    // Scripts run as the document is parsed, so any changes they made to the DOM (including to
+    // inline styles) are picked up here.
+    apply_styles(dom.clone(), device, ua_sheets, &[], author_sheets);
+    load_nested_browsing_contexts(&dom, html_file, device, ua_sheets, ancestor_paths);
+    // Relative image URLs are resolved against the directory containing the HTML file.
+    let image_cache = ImageCache::new(html_file.parent().unwrap_or_else(|| Path::new("")));
+    Ok(LoadedDocument {
+        dom,
+        image_cache,
+        path: html_file.to_path_buf(),
+        script_engine: script_engine.borrow_mut().take(),
+    })
+}
+
+/// Starts loading the HTML document at `html_file` as its source is read (see `DocumentLoad`),
+/// styled by the given stylesheets as rendered on `device`.  The document loaded so far, which
+/// is empty, is returned along with the load, which the rest of the document is fed to.
+pub fn begin_loading_document(
+    html_file: &Path,
+    device: &Device,
+    ua_sheets: &[Stylesheet],
+    author_sheets: &[Stylesheet],
+) -> (LoadedDocument, DocumentLoad) {
+    let load = DocumentLoad::new(html_file);
+    let dom = load.document().clone();
+    apply_styles(dom.clone(), device, ua_sheets, &[], author_sheets);
+    // Relative image URLs are resolved against the directory containing the HTML file.
+    let image_cache = ImageCache::new(html_file.parent().unwrap_or_else(|| Path::new("")));
+    let document = LoadedDocument {
+        dom,
+        image_cache,
+        path: html_file.to_path_buf(),
+        script_engine: None,
+    };
+    (document, load)
+}
+
+/// The load of an HTML document whose source is parsed as it arrives, rather than all at once.
+/// The parser records the nodes it inserts as mutations (see `mutation::take_mutations`), so
+/// what's been parsed so far can be restyled, have its boxes updated, and be rendered like the
+/// changes scripts make, with only what each chunk added restyled and laid out.
+pub struct DocumentLoad {
+    parser: IncrementalParser,
+    /// Where the engine the document's scripts run in is kept while it's parsed.
+    script_engine: ScriptEngineSlot,
+    /// The path the relative URLs of the document's iframes are resolved against.
+    path: PathBuf,
+}
+
+impl DocumentLoad {
+    /// Starts loading a document whose relative URLs are resolved against `path`.
+    pub fn new(path: &Path) -> DocumentLoad {
+        let script_engine = ScriptEngineSlot::default();
+        let parser = IncrementalParser::new(ParseOpts {
+            script_engine: Some(script_engine.clone()),
+            ..ParseOpts::default()
+        });
+        DocumentLoad {
+            parser,
+            script_engine,
+            path: path.to_path_buf(),
+        }
+    }
+
+    /// The document, as loaded so far.
+    pub fn document(&self) -> &NodeRef {
+        self.parser.document()
+    }
+
+    /// Parses the next `bytes` of the document's source.
+    pub fn feed(&mut self, bytes: &[u8]) {
+        let _span = info_span!("parse_html").entered();
+        self.parser.feed(bytes);
+    }
+
+    /// Parses the rest of the document's source, once the end of it has been fed, and loads its
+    /// iframes, returning the engine its scripts ran in, if it had any.
+    ///
+    /// Declarative shadow roots are only attached once the whole document is parsed, and that
+    /// isn't recorded as mutations, so the document has to be restyled and its boxes built anew
+    /// afterwards.
+    pub fn finish(self, device: &Device, ua_sheets: &[Stylesheet]) -> Option<ScriptEngine> {
+        let dom = {
+            let _span = info_span!("parse_html").entered();
+            self.parser.finish()
+        };
+        load_nested_browsing_contexts(&dom, &self.path, device, ua_sheets, &[]);
+        self.script_engine.take()
+    }
+}
+
+/// Restyles the nodes of `styled_dom` that the mutations recorded since it was last restyled
+/// could have changed (see `mutation::take_mutations`), and brings `box_tree` up to date with
+/// them, returning whether there were any.
+pub fn apply_mutations(
+    styled_dom: &NodeRef,
+    invalidation_map: &InvalidationMap,
+    device: &Device,
+    ua_sheets: &[Stylesheet],
+    author_sheets: &[Stylesheet],
+    box_tree: &mut Option<LayoutBox>,
+) -> bool {
+    let mutations = mutation::take_mutations(styled_dom);
+    if mutations.is_empty() {
+        return false;
+    }
+    let restyle = restyle_mutations(
+        styled_dom,
+        &mutations,
+        invalidation_map,
+        device,
+        ua_sheets,
+        &[],
+        author_sheets,
+    );
+    update_box_tree(box_tree, styled_dom, &mutations, &restyle);
+    true
+}
+
+/// The screen a document is rendered on in a viewport `inner_width` by `inner_height` device
+/// pixels in size, at `scale_factor` device pixels per CSS pixel.
+pub fn screen_device(inner_width: f32, inner_height: f32, scale_factor: f32) -> Device {
+    let mut device = Device::new(
+        MediaType::Screen,
+        inner_width / scale_factor,
+        inner_height / scale_factor,
+    );
+    device.set_resolution(scale_factor);
+    device
+}
+
+/// Makes `new_device` the device the document is rendered on, keeping the media type and user
+/// preferences (like the color scheme) of the current `device` (so only the size and resolution of the viewport change).  If
+/// that changes which rules apply (e.g. because the viewport crossed a `min-width` breakpoint),
+/// the document is restyled and its boxes built anew.  Otherwise, if the viewport changed size,
+/// values with viewport-percentage lengths are recomputed, and the boxes of nodes whose values
+/// changed are laid out again.
+pub fn update_device(
+    device: &mut Device,
+    mut new_device: Device,
+    styled_dom: &NodeRef,
+    ua_sheets: &[Stylesheet],
+    author_sheets: &[Stylesheet],
+    box_tree: &mut Option<LayoutBox>,
+) {
+    new_device.set_media_type(device.media_type());
+    new_device.set_color_scheme(device.color_scheme());
+    new_device.set_motion_preference(device.motion_preference());
+    new_device.set_contrast_preference(device.contrast_preference());
+    new_device.set_forced_colors(device.forced_colors());
+    let restyle = ua_sheets
+        .iter()
+        .chain(author_sheets.iter())
+        .any(|sheet| sheet.rules_differ_between(device, &new_device));
+    let resized = new_device != *device;
+    *device = new_device;
+    if restyle {
+        apply_styles(styled_dom.clone(), device, ua_sheets, &[], author_sheets);
+        *box_tree = build_box_tree(styled_dom.clone(), None);
+    } else if resized {
+        let restyle = recompute_values(styled_dom, device);
+        update_box_tree(box_tree, styled_dom, &[], &restyle);
+    }
+}
+
+/// Lays out `box_tree`, which was built from `styled_dom`, in the viewport of `device`.  If that
+/// resized any query containers, the `@container` rules evaluated against them may no longer
+/// apply (or may now), so their descendants are restyled against their new sizes and the document
+/// is laid out again, until the sizes of its query containers settle.
+///
+/// TODO: The headless modes of the CLI (like `dump-layout` and `--screenshot`) lay documents out
+/// once, without evaluating `@container` rules against the sizes they were laid out at.
+///
+/// https://drafts.csswg.org/css-contain-3/#container-queries
+pub fn layout_document(
+    box_tree: &mut Option<LayoutBox>,
+    styled_dom: &NodeRef,
+    device: &Device,
+    ua_sheets: &[Stylesheet],
+    author_sheets: &[Stylesheet],
+) {
+    // The viewport is sized in device pixels, which layout divides into CSS pixels.
+    let scale_factor = device.resolution();
+    let width = device.viewport_width() * scale_factor;
+    let height = device.viewport_height() * scale_factor;
+    for pass in 1..=MAX_CONTAINER_QUERY_PASSES {
+        let layout_root_box = match box_tree.as_mut() {
+            Some(layout_root_box) => layout_root_box,
+            None => return,
+        };
+        global_layout(layout_root_box, width, height, scale_factor);
+        let changed_containers = record_query_containers(layout_root_box);
+        if changed_containers.is_empty() || pass == MAX_CONTAINER_QUERY_PASSES {
+            return;
+        }
+        let restyle = restyle_query_containers(
+            styled_dom,
+            &changed_containers,
+            device,
+            ua_sheets,
+            &[],
+            author_sheets,
+        );
+        update_box_tree(box_tree, styled_dom, &[], &restyle);
+    }
+}
+
+/// Makes `color_scheme` the color scheme the user of `device` prefers.  If it changed, the
+/// document is restyled and its boxes built anew, since both `prefers-color-scheme` media queries
+/// and the system colors of elements supporting more than one color scheme depend on it.
+pub fn update_color_scheme(
+    device: &mut Device,
+    color_scheme: ColorScheme,
+    styled_dom: &NodeRef,
+    ua_sheets: &[Stylesheet],
+    author_sheets: &[Stylesheet],
+    box_tree: &mut Option<LayoutBox>,
+) {
+    if device.color_scheme() == color_scheme {
+        return;
+    }
+    device.set_color_scheme(color_scheme);
+    apply_styles(styled_dom.clone(), device, ua_sheets, &[], author_sheets);
+    *box_tree = build_box_tree(styled_dom.clone(), None);
+}
+
+/// The screen pages are rendered on until their viewport is set: a window of the default size, at
+/// one device pixel per CSS pixel.
+fn default_device() -> Device {
+    screen_device(
+        DEFAULT_INNER_WINDOW_WIDTH_PX,
+        DEFAULT_INNER_WINDOW_HEIGHT_PX,
+        1.,
+    )
+}
+
+/// A document rendered on a screen, which can be laid out, rendered, and hit tested.
+pub struct Page {
+    dom: NodeRef,
+    image_cache: ImageCache,
+    /// The engine the document's scripts ran in, which is kept so the objects they created stay
+    /// alive.
+    _script_engine: Option<ScriptEngine>,
+    device: Device,
+    ua_sheets: Vec<Stylesheet>,
+    author_sheets: Vec<Stylesheet>,
+    box_tree: Option<LayoutBox>,
+    /// The display list painting the page as last laid out, or `None` if the page hasn't been
+    /// laid out since it was loaded or its viewport last changed.
+    display_list: Option<DisplayList>,
+    /// Which elements changes to the DOM restyle, which depends only on the stylesheets.
+    invalidation_map: InvalidationMap,
+    /// The load of the document, while its source is still being fed to the page.
+    loading: Option<DocumentLoad>,
+}
+
+impl Page {
+    /// Loads the HTML document at `html_file`, styled by the user agent stylesheet and
+    /// `author_sheets`, in a viewport of the default window size at one device pixel per CSS
+    /// pixel.
+    pub fn load_document(html_file: &Path, author_sheets: Vec<Stylesheet>) -> io::Result<Page> {
+        let device = default_device();
+        let ua_sheets = vec![user_agent_stylesheet()];
+        let LoadedDocument {
+            dom,
+            image_cache,
+            script_engine,
+            ..
+        } = load_document(html_file, &device, &ua_sheets, &author_sheets)?;
+        Ok(Page::new(
+            dom,
+            image_cache,
+            script_engine,
+            device,
+            ua_sheets,
+            author_sheets,
+        ))
+    }
+
+    /// Loads the document `html` is the source of, like `load_document` does, resolving the
+    /// relative URLs of its images and iframes against `base_dir`.
+    pub fn load_html(html: &str, base_dir: &Path, author_sheets: Vec<Stylesheet>) -> Page {
+        let device = default_device();
+        let ua_sheets = vec![user_agent_stylesheet()];
+        let script_engine = ScriptEngineSlot::default();
+        let dom = {
+            let _span = info_span!("parse_html").entered();
+            parse_html_with_options(ParseOpts {
+                script_engine: Some(script_engine.clone()),
+                ..ParseOpts::default()
+            })
+            .one(html)
+        };
+        apply_styles(dom.clone(), &device, &ua_sheets, &[], &author_sheets);
+        // Iframes' URLs are resolved against `base_dir` too, as if the document were a file in it.
+        load_nested_browsing_contexts(&dom, &base_dir.join(""), &device, &ua_sheets, &[]);
+        let script_engine = script_engine.borrow_mut().take();
+        Page::new(
+            dom,
+            ImageCache::new(base_dir),
+            script_engine,
+            device,
+            ua_sheets,
+            author_sheets,
+        )
+    }
+
+    /// Starts loading a document whose source will be fed to the page as it arrives (see
+    /// `feed_html`), resolving the relative URLs of its images and iframes against `base_dir`.
+    /// Until then, the page's document is empty.
+    pub fn begin_loading(base_dir: &Path, author_sheets: Vec<Stylesheet>) -> Page {
+        // Iframes' URLs are resolved against `base_dir`, as if the document were a file in it.
+        let load = DocumentLoad::new(&base_dir.join(""));
+        let mut page = Page::new(
+            load.document().clone(),
+            ImageCache::new(base_dir),
+            None,
+            default_device(),
+            vec![user_agent_stylesheet()],
+            author_sheets,
+        );
+        page.loading = Some(load);
+        page
+    }
+
+    /// Parses the next `bytes` of the source of the document being loaded, and restyles what
+    /// they added to it, so the partial document can be laid out, rendered, and hit tested before
+    /// the rest of it arrives.  Does nothing if the page isn't loading a document.
+    pub fn feed_html(&mut self, bytes: &[u8]) {
+        let loading = match self.loading.as_mut() {
+            Some(loading) => loading,
+            None => return,
+        };
+        loading.feed(bytes);
+        if apply_mutations(
+            &self.dom,
+            &self.invalidation_map,
+            &self.device,
+            &self.ua_sheets,
+            &self.author_sheets,
+            &mut self.box_tree,
+        ) {
+            self.display_list = None;
+        }
+    }
+
+    /// Parses the rest of the source of the document being loaded, once the end of it has been
+    /// fed to the page, and loads its iframes.  Does nothing if the page isn't loading a document.
+    pub fn finish_loading(&mut self) {
+        let loading = match self.loading.take() {
+            Some(loading) => loading,
+            None => return,
+        };
+        self._script_engine = loading.finish(&self.device, &self.ua_sheets);
+        // The document is restyled from scratch, so the mutations recorded since it was last
+        // restyled are dropped.
+        mutation::take_mutations(&self.dom);
+        self.restyle();
+    }
+
+    /// Whether the page is loading a document whose source hasn't all been fed to it yet.
+    pub fn is_loading(&self) -> bool {
+        self.loading.is_some()
+    }
+
+    fn restyle(&mut self) {
+        apply_styles(
+            self.dom.clone(),
+            &self.device,
+            &self.ua_sheets,
+            &[],
+            &self.author_sheets,
+        );
+        self.box_tree = build_box_tree(self.dom.clone(), None);
+        self.display_list = None;
+    }
+
+    fn new(
+        dom: NodeRef,
+        image_cache: ImageCache,
+        script_engine: Option<ScriptEngine>,
+        device: Device,
+        ua_sheets: Vec<Stylesheet>,
+        author_sheets: Vec<Stylesheet>,
+    ) -> Page {
+        let box_tree = build_box_tree(dom.clone(), None);
+        let invalidation_map = InvalidationMap::new(ua_sheets.iter().chain(author_sheets.iter()));
+        Page {
+            dom,
+            image_cache,
+            _script_engine: script_engine,
+            device,
+            ua_sheets,
+            author_sheets,
+            box_tree,
+            display_list: None,
+            invalidation_map,
+            loading: None,
+        }
+    }
+
+    /// The document, whose nodes can be read (e.g. to find elements by selector) once it's
+    /// loaded.
+    pub fn document(&self) -> &NodeRef {
+        &self.dom
+    }
+
+    /// The screen the document is rendered on.
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
+    /// Sets the viewport to `width` by `height` device pixels, at `scale_factor` device pixels
+    /// per CSS pixel, restyling the document if that changes which media queries match.
+    pub fn set_viewport(&mut self, width: f32, height: f32, scale_factor: f32) {
+        update_device(
+            &mut self.device,
+            screen_device(width, height, scale_factor),
+            &self.dom,
+            &self.ua_sheets,
+            &self.author_sheets,
+            &mut self.box_tree,
+        );
+        self.display_list = None;
+    }
+
+    /// Lays out the page in its viewport (loading the images that are displayed at its size),
+    /// returning its box tree, which is `None` if the document generates no boxes (e.g. because
+    /// of `html { display: none }`).
+    pub fn layout(&mut self) -> Option<&LayoutBox> {
+        let _span = info_span!("page_layout").entered();
+        let scale_factor = self.device.resolution();
+        load_document_images(
+            &self.dom,
+            &self.image_cache,
+            self.device.viewport_width(),
+            scale_factor,
+        );
+        layout_document(
+            &mut self.box_tree,
+            &self.dom,
+            &self.device,
+            &self.ua_sheets,
+            &self.author_sheets,
+        );
+        self.display_list = Some(build_page_display_list(
+            self.box_tree.as_ref(),
+            &self.image_cache,
+            Duration::default(),
+            scale_factor,
+        ));
+        self.box_tree.as_ref()
+    }
+
+    /// Renders the page into an image the size of its viewport in device pixels, laying it out
+    /// first if it hasn't been laid out in its current viewport.
+    pub fn render(&mut self) -> DecodedImage {
+        let scale_factor = self.device.resolution();
+        let (width, height) = (
+            self.device.viewport_width() * scale_factor,
+            self.device.viewport_height() * scale_factor,
+        );
+        rasterize(
+            self.display_list(),
+            width as u32,
+            height as u32,
+            scale_factor,
+        )
+    }
+
+    /// Renders the page, like `render`, and writes the image to a PNG at `path`.
+    pub fn render_to(&mut self, path: &Path) -> ::image::ImageResult<()> {
+        save_png(&self.render(), path)
+    }
+
+    /// Finds the element painted at `point` (in CSS pixels, relative to the viewport), followed by
+    /// each of its ancestors up to and including the document, laying the page out first if it
+    /// hasn't been laid out in its current viewport.  Nothing is returned if nothing was painted
+    /// at `point`.
+    pub fn hit_test(&mut self, point: Point) -> Vec<NodeRef> {
+        let document = self.dom.clone();
+        self.display_list().hit_test(&document, point)
+    }
+
+    /// The display list painting the page, laying it out first if it hasn't been laid out in its
+    /// current viewport.
+    pub fn display_list(&mut self) -> &DisplayList {
+        if self.display_list.is_none() {
+            self.layout();
+        }
+        self.display_list
+            .as_ref()
+            .expect("laying out the page builds its display list")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::traits::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn renders_and_hit_tests_documents() {
+        let tempdir = TempDir::new("page").unwrap();
+        let html_file = tempdir.path().join("page.html");
+        std::fs::write(
+            &html_file,
+            "<div id=box style=\"width: 100px; height: 50px; background-color: red\"></div>",
+        )
+        .unwrap();
+        let mut page = Page::load_document(&html_file, Vec::new()).unwrap();
+        page.set_viewport(400., 200., 2.);
+        assert_eq!(page.device().viewport_width(), 200.);
+
+        // The body's margin puts the box at (8, 8) in CSS pixels.
+        let hit = page.hit_test(Point { x: 10., y: 10. });
+        let element = hit[0].as_element().expect("an element should be hit");
+        assert_eq!(&*element.name.local, "div");
+        assert!(page.hit_test(Point { x: 150., y: 10. })[0]
+            .as_element()
+            .map_or(false, |element| &*element.name.local == "body"));
+
+        let image = page.render();
+        assert_eq!((image.width(), image.height()), (400, 200));
+        let pixel = |x: usize, y: usize| {
+            let start = (y * image.width() as usize + x) * 4;
+            image.frame(0).pixels()[start..start + 4].to_vec()
+        };
+        assert_eq!(pixel(20, 20), vec![255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn lays_out_documents_as_they_arrive() {
+        let tempdir = TempDir::new("page").unwrap();
+        let mut page = Page::begin_loading(tempdir.path(), Vec::new());
+        assert!(page.is_loading());
+        page.feed_html(b"<div id=first style=\"height: 50px; background-color: red\"></div><di");
+        // The body's margin puts the first box at (8, 8) in CSS pixels.
+        assert!(page.hit_test(Point { x: 10., y: 10. })[0]
+            .as_element()
+            .map_or(false, |element| &*element.name.local == "div"));
+        assert!(page.hit_test(Point { x: 10., y: 60. })[0]
+            .as_element()
+            .map_or(false, |element| &*element.name.local == "body"));
+
+        // Only what each chunk added is restyled, which the second box is.
+        page.feed_html(b"v id=second style=\"height: 50px\"></div>");
+        let second_hit = |page: &mut Page| {
+            let hit = page.hit_test(Point { x: 10., y: 60. });
+            hit[0].as_element().map_or(false, |element| {
+                element.attributes.borrow().get("id") == Some("second")
+            })
+        };
+        assert!(
+            second_hit(&mut page),
+            "the second box is laid out once it's parsed"
+        );
+        assert!(!mutation::has_mutations(page.document()));
+
+        page.finish_loading();
+        assert!(!page.is_loading());
+        assert!(second_hit(&mut page));
+    }
+
+    #[test]
+    fn evaluates_container_queries_against_laid_out_sizes() {
+        let tempdir = TempDir::new("page").unwrap();
+        let author_sheet = parse_css_to_stylesheet(
+            None,
+            &mut "#narrow { width: 200px; container-type: inline-size } \
+                  #wide { container: card / inline-size } \
+                  p { height: 10px } \
+                  @container (max-width: 300px) { p { height: 20px } } \
+                  @container card (min-width: 500px) { @media screen { p { height: 40px } } }"
+                .to_owned(),
+        );
+        let mut page = Page::load_html(
+            "<div id=narrow><p></p></div><div id=wide><p></p></div>",
+            tempdir.path(),
+            vec![author_sheet],
+        );
+        let heights = |page: &mut Page| {
+            page.layout();
+            page.document()
+                .select("p")
+                .unwrap()
+                .map(|p| p.as_node().client_rects()[0].height.px())
+                .collect::<Vec<_>>()
+        };
+
+        // The containers are laid out before their queries are evaluated, so the paragraphs are
+        // restyled and laid out again once their containers' sizes are known.
+        page.set_viewport(800., 600., 1.);
+        assert_eq!(heights(&mut page), vec![20., 40.]);
+        // The wide container shrinks along with the viewport, so its query stops matching.
+        page.set_viewport(400., 600., 1.);
+        assert_eq!(heights(&mut page), vec![20., 10.]);
+    }
+}
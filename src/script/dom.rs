@@ -0,0 +1,502 @@
+use crate::dom::mutation::{self, Mutation};
+use crate::dom::tree::{NodeData, NodeRef};
+use crate::script::style::InlineStyle;
+use boa::{Context, Result, Value};
+use html5ever::{LocalName, QualName};
+use std::cell::RefCell;
+
+thread_local! {
+    /// The nodes exposed to scripts, indexed by the IDs the native functions below refer to them
+    /// by.  The document is always at index 0.
+    static NODES: RefCell<Vec<NodeRef>> = RefCell::new(Vec::new());
+}
+
+thread_local! {
+    /// The markup `document.write` has inserted at the parser's insertion point, or `None` if
+    /// there's no insertion point because the parser isn't running a script.
+    static WRITTEN_MARKUP: RefCell<Option<String>> = RefCell::new(None);
+}
+
+/// Registers the native functions `prelude.js` builds the DOM objects scripts see on, with
+/// `document` as the document.
+///
+/// TODO: Nodes are never removed from the registry, so they're kept alive for as long as the thread
+/// is, or until another engine is created.
+pub(super) fn register(context: &mut Context, document: NodeRef) -> Result<()> {
+    NODES.with(|nodes| *nodes.borrow_mut() = vec![document]);
+    context.register_global_function("__kosmonautLog", 0, log)?;
+    context.register_global_function("__kosmonautReportException", 1, report_exception)?;
+    context.register_global_function("__kosmonautNodeType", 1, node_type)?;
+    context.register_global_function("__kosmonautNodeName", 1, node_name)?;
+    context.register_global_function("__kosmonautTextContent", 1, text_content)?;
+    context.register_global_function("__kosmonautSetTextContent", 2, set_text_content)?;
+    context.register_global_function("__kosmonautParentNode", 1, parent_node)?;
+    context.register_global_function("__kosmonautAppendChild", 2, append_child)?;
+    context.register_global_function("__kosmonautRemoveChild", 2, remove_child)?;
+    context.register_global_function("__kosmonautQuerySelector", 3, query_selector)?;
+    context.register_global_function("__kosmonautGetAttribute", 2, get_attribute)?;
+    context.register_global_function("__kosmonautSetAttribute", 3, set_attribute)?;
+    context.register_global_function("__kosmonautGetStyleText", 1, get_style_text)?;
+    context.register_global_function("__kosmonautGetStyleProperty", 2, get_style_property)?;
+    context.register_global_function("__kosmonautSetStyleProperty", 4, set_style_property)?;
+    context.register_global_function("__kosmonautRemoveStyleProperty", 2, remove_style_property)?;
+    context.register_global_function("__kosmonautGetElementById", 1, get_element_by_id)?;
+    context.register_global_function("__kosmonautDocumentElement", 0, document_element)?;
+    context.register_global_function("__kosmonautBody", 0, body)?;
+    context.register_global_function("__kosmonautCreateElement", 1, create_element)?;
+    context.register_global_function("__kosmonautCreateTextNode", 1, create_text_node)?;
+    context.register_global_function("__kosmonautWrite", 1, write)?;
+    Ok(())
+}
+
+/// `console.log(...data)`, which prints its arguments to stdout separated by spaces.
+///
+/// https://console.spec.whatwg.org/#log
+fn log(_: &Value, args: &[Value], context: &mut Context) -> Result<Value> {
+    let mut message = Vec::with_capacity(args.len());
+    for arg in args {
+        message.push(arg.to_string(context)?.to_string());
+    }
+    println!("{}", message.join(" "));
+    Ok(Value::undefined())
+}
+
+/// `__kosmonautReportException(exception)`, which reports an exception thrown by a callback (e.g.
+/// a timer's) that nothing caught.
+///
+/// https://html.spec.whatwg.org/multipage/webappapis.html#report-the-exception
+fn report_exception(_: &Value, args: &[Value], context: &mut Context) -> Result<Value> {
+    let exception = string_arg(args, 0, context)?;
+    eprintln!("uncaught exception in script: {}", exception);
+    Ok(Value::undefined())
+}
+
+/// `__kosmonautNodeType(node)`
+///
+/// https://dom.spec.whatwg.org/#dom-node-nodetype
+fn node_type(_: &Value, args: &[Value], context: &mut Context) -> Result<Value> {
+    let node = node_arg(args, 0, context)?;
+    let node_type = match node.data() {
+        NodeData::Element(_) => 1,
+        NodeData::Text(_) => 3,
+        NodeData::ProcessingInstruction(_) => 7,
+        NodeData::Comment(_) => 8,
+        NodeData::Document(_) => 9,
+        NodeData::Doctype(_) => 10,
+        NodeData::DocumentFragment | NodeData::ShadowRoot(_) => 11,
+    };
+    Ok(Value::from(node_type))
+}
+
+/// `__kosmonautNodeName(node)`
+///
+/// https://dom.spec.whatwg.org/#dom-node-nodename
+fn node_name(_: &Value, args: &[Value], context: &mut Context) -> Result<Value> {
+    let node = node_arg(args, 0, context)?;
+    let name = match node.data() {
+        NodeData::Element(element) if element.name.ns == ns!(html) => {
+            element.name.local.to_ascii_uppercase()
+        }
+        NodeData::Element(element) => element.name.local.to_string(),
+        NodeData::Text(_) => "#text".to_owned(),
+        NodeData::Comment(_) => "#comment".to_owned(),
+        NodeData::Document(_) => "#document".to_owned(),
+        NodeData::DocumentFragment | NodeData::ShadowRoot(_) => "#document-fragment".to_owned(),
+        NodeData::Doctype(doctype) => doctype.name.clone(),
+        NodeData::ProcessingInstruction(contents) => contents.borrow().0.clone(),
+    };
+    Ok(Value::from(name))
+}
+
+/// `__kosmonautTextContent(node)`
+///
+/// https://dom.spec.whatwg.org/#dom-node-textcontent
+fn text_content(_: &Value, args: &[Value], context: &mut Context) -> Result<Value> {
+    let node = node_arg(args, 0, context)?;
+    Ok(match node.data() {
+        NodeData::Document(_) | NodeData::Doctype(_) => Value::null(),
+        _ => Value::from(node.text_contents()),
+    })
+}
+
+/// `__kosmonautSetTextContent(node, value)`, which replaces the children of elements and
+/// fragments with a single text node, or replaces the data of character data nodes.
+///
+/// https://dom.spec.whatwg.org/#set-text-content
+fn set_text_content(_: &Value, args: &[Value], context: &mut Context) -> Result<Value> {
+    let node = node_arg(args, 0, context)?;
+    let value = string_arg(args, 1, context)?;
+    let mutation = match node.data() {
+        NodeData::Element(_) | NodeData::DocumentFragment | NodeData::ShadowRoot(_) => {
+            while let Some(child) = node.first_child() {
+                child.detach();
+            }
+            let mut added = Vec::new();
+            if !value.is_empty() {
+                let text = NodeRef::new_text(value);
+                node.append(text.clone());
+                added.push(text);
+            }
+            Mutation::ChildList {
+                parent: node.clone(),
+                added,
+            }
+        }
+        NodeData::Text(data) | NodeData::Comment(data) => {
+            *data.borrow_mut() = value;
+            Mutation::CharacterData { node: node.clone() }
+        }
+        NodeData::ProcessingInstruction(contents) => {
+            contents.borrow_mut().1 = value;
+            Mutation::CharacterData { node: node.clone() }
+        }
+        NodeData::Document(_) | NodeData::Doctype(_) => return Ok(Value::undefined()),
+    };
+    mutation::record(mutation);
+    Ok(Value::undefined())
+}
+
+/// `__kosmonautParentNode(node)`
+fn parent_node(_: &Value, args: &[Value], context: &mut Context) -> Result<Value> {
+    let node = node_arg(args, 0, context)?;
+    // Shadow roots have their host as a parent in the tree, but not in the DOM.
+    if node.as_shadow_root().is_some() {
+        return Ok(Value::null());
+    }
+    Ok(node_value(node.parent()))
+}
+
+/// `__kosmonautAppendChild(parent, node)`, which moves `node` to the end of `parent`'s children
+/// and returns it.
+///
+/// TODO: Only the most common of the pre-insertion validity checks are made.
+///
+/// https://dom.spec.whatwg.org/#dom-node-appendchild
+fn append_child(_: &Value, args: &[Value], context: &mut Context) -> Result<Value> {
+    let parent = node_arg(args, 0, context)?;
+    let node = node_arg(args, 1, context)?;
+    let valid_parent = matches!(
+        parent.data(),
+        NodeData::Document(_)
+            | NodeData::DocumentFragment
+            | NodeData::ShadowRoot(_)
+            | NodeData::Element(_)
+    );
+    let valid_child = match node.data() {
+        NodeData::Document(_) | NodeData::ShadowRoot(_) => false,
+        NodeData::Text(_) => parent.as_document().is_none(),
+        _ => true,
+    };
+    if !valid_parent
+        || !valid_child
+        || parent
+            .inclusive_ancestors()
+            .any(|ancestor| ancestor == node)
+    {
+        return Err(
+            context.construct_error("HierarchyRequestError: the node can't be inserted here")
+        );
+    }
+    if let Some(old_parent) = node.parent() {
+        mutation::record(Mutation::ChildList {
+            parent: old_parent,
+            added: Vec::new(),
+        });
+    }
+    parent.append(node.clone());
+    mutation::record(Mutation::ChildList {
+        parent,
+        added: vec![node.clone()],
+    });
+    Ok(node_value(Some(node)))
+}
+
+/// `__kosmonautRemoveChild(parent, child)`, which removes `child` from `parent` and returns it.
+///
+/// https://dom.spec.whatwg.org/#dom-node-removechild
+fn remove_child(_: &Value, args: &[Value], context: &mut Context) -> Result<Value> {
+    let parent = node_arg(args, 0, context)?;
+    let child = node_arg(args, 1, context)?;
+    if child.parent().as_ref() != Some(&parent) {
+        return Err(context.construct_error("NotFoundError: the node isn't a child of this node"));
+    }
+    child.detach();
+    mutation::record(Mutation::ChildList {
+        parent,
+        added: Vec::new(),
+    });
+    Ok(node_value(Some(child)))
+}
+
+/// `__kosmonautQuerySelector(node, selectors, index)`, which returns the element at `index` among
+/// the descendants of `node` that match `selectors`, or null if there are that many or fewer.
+///
+/// https://dom.spec.whatwg.org/#scope-match-a-selectors-string
+fn query_selector(_: &Value, args: &[Value], context: &mut Context) -> Result<Value> {
+    let node = node_arg(args, 0, context)?;
+    let selectors = string_arg(args, 1, context)?;
+    let index = argument(args, 2).to_number(context)? as usize;
+    let matches = match node.select_str(&selectors) {
+        Ok(matches) => matches,
+        Err(()) => {
+            return Err(
+                context.construct_syntax_error(format!("'{}' is not a valid selector", selectors))
+            )
+        }
+    };
+    let found = matches
+        .map(|element| element.as_node().clone())
+        // Only descendants are matched, not the node itself.
+        .filter(|element| *element != node)
+        .nth(index);
+    Ok(node_value(found))
+}
+
+/// `__kosmonautGetAttribute(element, qualifiedName)`
+///
+/// https://dom.spec.whatwg.org/#dom-element-getattribute
+fn get_attribute(_: &Value, args: &[Value], context: &mut Context) -> Result<Value> {
+    let node = node_arg(args, 0, context)?;
+    let name = string_arg(args, 1, context)?.to_ascii_lowercase();
+    let value = node
+        .as_element()
+        .and_then(|element| element.attributes.borrow().get(name).map(str::to_owned));
+    Ok(value.map_or_else(Value::null, Value::from))
+}
+
+/// `__kosmonautSetAttribute(element, qualifiedName, value)`
+///
+/// https://dom.spec.whatwg.org/#dom-element-setattribute
+fn set_attribute(_: &Value, args: &[Value], context: &mut Context) -> Result<Value> {
+    let node = node_arg(args, 0, context)?;
+    let name = string_arg(args, 1, context)?.to_ascii_lowercase();
+    let value = string_arg(args, 2, context)?;
+    if let Some(element) = node.as_element() {
+        let old_attribute = element.attributes.borrow_mut().insert(name.clone(), value);
+        mutation::record(Mutation::Attribute {
+            element: node.clone(),
+            name: LocalName::from(name),
+            old_value: old_attribute.map(|attribute| attribute.value),
+        });
+    }
+    Ok(Value::undefined())
+}
+
+/// `__kosmonautGetStyleText(element)`, which is the serialization of the element's `style`
+/// attribute.
+///
+/// https://drafts.csswg.org/cssom/#dom-cssstyledeclaration-csstext
+fn get_style_text(_: &Value, args: &[Value], context: &mut Context) -> Result<Value> {
+    let node = node_arg(args, 0, context)?;
+    Ok(Value::from(inline_style(&node).css_text()))
+}
+
+/// `__kosmonautGetStyleProperty(element, property)`
+///
+/// https://drafts.csswg.org/cssom/#dom-cssstyledeclaration-getpropertyvalue
+fn get_style_property(_: &Value, args: &[Value], context: &mut Context) -> Result<Value> {
+    let node = node_arg(args, 0, context)?;
+    let property = string_arg(args, 1, context)?;
+    Ok(Value::from(
+        inline_style(&node).get_property_value(&property),
+    ))
+}
+
+/// `__kosmonautSetStyleProperty(element, property, value, priority)`
+///
+/// https://drafts.csswg.org/cssom/#dom-cssstyledeclaration-setproperty
+fn set_style_property(_: &Value, args: &[Value], context: &mut Context) -> Result<Value> {
+    let node = node_arg(args, 0, context)?;
+    let property = string_arg(args, 1, context)?;
+    let value = string_arg(args, 2, context)?;
+    let priority = string_arg(args, 3, context)?;
+    let mut style = inline_style(&node);
+    if style.set_property(&property, &value, &priority) {
+        set_inline_style(&node, &style);
+    }
+    Ok(Value::undefined())
+}
+
+/// `__kosmonautRemoveStyleProperty(element, property)`
+///
+/// https://drafts.csswg.org/cssom/#dom-cssstyledeclaration-removeproperty
+fn remove_style_property(_: &Value, args: &[Value], context: &mut Context) -> Result<Value> {
+    let node = node_arg(args, 0, context)?;
+    let property = string_arg(args, 1, context)?;
+    let mut style = inline_style(&node);
+    let removed = style.remove_property(&property);
+    if !removed.is_empty() {
+        set_inline_style(&node, &style);
+    }
+    Ok(Value::from(removed))
+}
+
+/// `__kosmonautGetElementById(elementId)`
+///
+/// https://dom.spec.whatwg.org/#dom-nonelementparentnode-getelementbyid
+fn get_element_by_id(_: &Value, args: &[Value], context: &mut Context) -> Result<Value> {
+    let element_id = string_arg(args, 0, context)?;
+    let found = document().descendants().find(|node| {
+        node.as_element().map_or(false, |element| {
+            element.attributes.borrow().get(local_name!("id")) == Some(element_id.as_str())
+        })
+    });
+    Ok(node_value(found))
+}
+
+/// `__kosmonautDocumentElement()`
+///
+/// https://dom.spec.whatwg.org/#dom-document-documentelement
+fn document_element(_: &Value, _: &[Value], _: &mut Context) -> Result<Value> {
+    Ok(node_value(
+        document()
+            .children()
+            .find(|child| child.as_element().is_some()),
+    ))
+}
+
+/// `__kosmonautBody()`
+///
+/// https://html.spec.whatwg.org/multipage/dom.html#dom-document-body
+fn body(_: &Value, _: &[Value], _: &mut Context) -> Result<Value> {
+    let body = document()
+        .children()
+        .find(|child| child.as_element().is_some())
+        .and_then(|html| {
+            html.children().find(|child| {
+                child
+                    .as_element()
+                    .map_or(false, |element| element.name.local == local_name!("body"))
+            })
+        });
+    Ok(node_value(body))
+}
+
+/// `__kosmonautCreateElement(localName)`, which creates an HTML element.
+///
+/// https://dom.spec.whatwg.org/#dom-document-createelement
+fn create_element(_: &Value, args: &[Value], context: &mut Context) -> Result<Value> {
+    let local_name = string_arg(args, 0, context)?.to_ascii_lowercase();
+    // A looser version of the XML `Name` production, which only rules out names that couldn't be
+    // written as a tag.
+    if local_name.is_empty()
+        || local_name
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '<' | '>' | '/' | '=' | '"' | '\''))
+    {
+        return Err(context.construct_error(format!(
+            "InvalidCharacterError: '{}' is not a valid element name",
+            local_name
+        )));
+    }
+    let element = NodeRef::new_element(
+        QualName::new(None, ns!(html), LocalName::from(local_name)),
+        Vec::new(),
+    );
+    Ok(node_value(Some(element)))
+}
+
+/// `__kosmonautCreateTextNode(data)`
+///
+/// https://dom.spec.whatwg.org/#dom-document-createtextnode
+fn create_text_node(_: &Value, args: &[Value], context: &mut Context) -> Result<Value> {
+    let data = string_arg(args, 0, context)?;
+    Ok(node_value(Some(NodeRef::new_text(data))))
+}
+
+/// `__kosmonautWrite(text)`, which inserts `text` into the parser's input at the insertion
+/// point, for `document.write` and `document.writeln`.
+///
+/// TODO: Writing when there's no insertion point (e.g. from a timer, after the document was
+/// parsed) should replace the document with a new one, but the write is ignored instead.
+///
+/// https://html.spec.whatwg.org/multipage/dynamic-markup-insertion.html#document-write-steps
+fn write(_: &Value, args: &[Value], context: &mut Context) -> Result<Value> {
+    let text = string_arg(args, 0, context)?;
+    WRITTEN_MARKUP.with(|written| {
+        if let Some(written) = written.borrow_mut().as_mut() {
+            written.push_str(&text);
+        }
+    });
+    Ok(Value::undefined())
+}
+
+/// Sets an insertion point for `document.write`, while the parser runs a script.
+pub(super) fn begin_writing() {
+    WRITTEN_MARKUP.with(|written| *written.borrow_mut() = Some(String::new()));
+}
+
+/// Removes the insertion point set by `begin_writing`, returning the markup written since.
+pub(super) fn end_writing() -> String {
+    WRITTEN_MARKUP
+        .with(|written| written.borrow_mut().take())
+        .unwrap_or_default()
+}
+
+fn document() -> NodeRef {
+    NODES.with(|nodes| nodes.borrow()[0].clone())
+}
+
+/// The declarations in `node`'s `style` attribute, which are empty if it isn't an element.
+fn inline_style(node: &NodeRef) -> InlineStyle {
+    node.as_element()
+        .and_then(|element| {
+            element
+                .attributes
+                .borrow()
+                .get(local_name!("style"))
+                .map(InlineStyle::parse)
+        })
+        .unwrap_or_default()
+}
+
+/// Reflects `style` back into `node`'s `style` attribute, which is what styles are applied from.
+fn set_inline_style(node: &NodeRef, style: &InlineStyle) {
+    if let Some(element) = node.as_element() {
+        let old_attribute = element
+            .attributes
+            .borrow_mut()
+            .insert(local_name!("style"), style.css_text());
+        mutation::record(Mutation::Attribute {
+            element: node.clone(),
+            name: local_name!("style"),
+            old_value: old_attribute.map(|attribute| attribute.value),
+        });
+    }
+}
+
+/// The ID scripts refer to `node` by (or null if there is no node), registering the node if it
+/// hasn't been exposed to scripts before.
+fn node_value(node: Option<NodeRef>) -> Value {
+    let node = match node {
+        Some(node) => node,
+        None => return Value::null(),
+    };
+    let id = NODES.with(|nodes| {
+        let mut nodes = nodes.borrow_mut();
+        match nodes.iter().position(|exposed| *exposed == node) {
+            Some(id) => id,
+            None => {
+                nodes.push(node);
+                nodes.len() - 1
+            }
+        }
+    });
+    Value::from(id as f64)
+}
+
+/// The node whose ID is the argument at `index`.  Throws a `TypeError` if there's no such node.
+fn node_arg(args: &[Value], index: usize, context: &mut Context) -> Result<NodeRef> {
+    let id = argument(args, index).to_number(context)?;
+    let node = NODES.with(|nodes| nodes.borrow().get(id as usize).cloned());
+    node.ok_or_else(|| context.construct_type_error("argument is not a node"))
+}
+
+fn string_arg(args: &[Value], index: usize, context: &mut Context) -> Result<String> {
+    Ok(argument(args, index).to_string(context)?.to_string())
+}
+
+/// The argument at `index`, which is `undefined` if it wasn't passed.
+fn argument(args: &[Value], index: usize) -> Value {
+    args.get(index).cloned().unwrap_or_else(Value::undefined)
+}
@@ -0,0 +1,425 @@
+mod dom;
+mod style;
+mod timers;
+
+use crate::dom::tree::NodeRef;
+use boa::{Context, Value};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// The objects scripts can access beyond the ECMAScript built-ins, such as `document`.
+const PRELUDE: &str = include_str!("prelude.js");
+
+/// Executes JavaScript against a document, using the Boa engine.  Scripts see the document as the
+/// global `document`, and can write to stdout with `console.log`.
+pub struct ScriptEngine {
+    context: Context,
+}
+
+/// Where a document's script engine is kept, shared between the parser (which creates the engine
+/// when it reaches the first script) and whatever runs the document afterwards.
+pub type ScriptEngineSlot = Rc<RefCell<Option<ScriptEngine>>>;
+
+#[derive(Debug)]
+pub enum ScriptError {
+    /// The script threw an exception that it didn't catch (including syntax errors), described as
+    /// a string.
+    Uncaught(String),
+}
+
+impl ScriptEngine {
+    pub fn new(document: NodeRef) -> ScriptEngine {
+        let mut context = Context::new();
+        dom::register(&mut context, document).expect("couldn't register dom functions");
+        timers::register(&mut context).expect("couldn't register timer functions");
+        context
+            .eval(PRELUDE)
+            .expect("couldn't evaluate script prelude");
+        ScriptEngine { context }
+    }
+
+    /// Evaluates `source` as a classic script, returning its completion value converted to a
+    /// string.
+    pub fn eval(&mut self, source: &str) -> Result<String, ScriptError> {
+        match self.context.eval(source) {
+            Ok(value) => Ok(self.value_to_string(&value)),
+            Err(exception) => Err(ScriptError::Uncaught(self.value_to_string(&exception))),
+        }
+    }
+
+    /// Runs the given `<script>` element if it's a classic script.  Like browsers, uncaught
+    /// exceptions are reported rather than stopping anything else from running.
+    ///
+    /// TODO: External scripts (those with a `src` attribute) aren't fetched yet, and so are
+    /// skipped.
+    ///
+    /// https://html.spec.whatwg.org/multipage/scripting.html#prepare-the-script-element
+    pub fn run_script_element(&mut self, script: &NodeRef) {
+        let element = match script.as_element() {
+            Some(element) => element,
+            None => return,
+        };
+        {
+            let attributes = element.attributes.borrow();
+            if attributes.contains(local_name!("src"))
+                || !is_javascript_type(attributes.get(local_name!("type")))
+            {
+                return;
+            }
+        }
+        if let Err(ScriptError::Uncaught(exception)) = self.eval(&script.text_contents()) {
+            eprintln!("uncaught exception in script: {}", exception);
+        }
+    }
+
+    /// Runs a `<script>` element the parser paused at, like `run_script_element`, returning the
+    /// markup the script inserted into the parser's input with `document.write`.
+    ///
+    /// https://html.spec.whatwg.org/multipage/dynamic-markup-insertion.html#document.write()
+    pub fn run_parser_inserted_script(&mut self, script: &NodeRef) -> String {
+        dom::begin_writing();
+        self.run_script_element(script);
+        dom::end_writing()
+    }
+
+    /// Runs the handlers of the timers scheduled with `setTimeout` and `setInterval` that are
+    /// due.
+    ///
+    /// https://html.spec.whatwg.org/multipage/timers-and-user-prompts.html#timer-initialisation-steps
+    pub fn run_due_timers(&mut self) {
+        self.eval_scheduler("runDueTimers()");
+    }
+
+    /// How long until the next timer is due, or `None` if no timers are scheduled.
+    pub fn time_until_next_timer(&mut self) -> Option<Duration> {
+        let millis = self.eval_scheduler("timeUntilNextTimer()");
+        if millis.is_null_or_undefined() {
+            return None;
+        }
+        let millis = millis.to_number(&mut self.context).unwrap_or(0.);
+        Some(Duration::from_secs_f64(millis.max(0.) / 1000.))
+    }
+
+    /// Whether any callbacks are waiting to be run before the next paint.
+    pub fn has_animation_frame_callbacks(&mut self) -> bool {
+        self.eval_scheduler("hasAnimationFrameCallbacks()")
+            .to_boolean()
+    }
+
+    /// Runs the callbacks scheduled with `requestAnimationFrame`, which must be done just before
+    /// painting.  Callbacks they schedule wait until the next paint.
+    ///
+    /// https://html.spec.whatwg.org/multipage/imagebitmap-and-animations.html#run-the-animation-frame-callbacks
+    pub fn run_animation_frame_callbacks(&mut self) {
+        self.eval_scheduler("runAnimationFrameCallbacks()");
+    }
+
+    /// Calls a method of the scheduler `prelude.js` sets up.  Exceptions thrown by callbacks are
+    /// reported within the scheduler, and scripts can't replace it, but anything the call throws
+    /// regardless is reported like an uncaught exception, returning `undefined`.
+    fn eval_scheduler(&mut self, call: &str) -> Value {
+        match self.context.eval(&format!("__kosmonautScheduler.{}", call)) {
+            Ok(value) => value,
+            Err(exception) => {
+                eprintln!(
+                    "uncaught exception in script scheduler: {}",
+                    self.value_to_string(&exception)
+                );
+                Value::undefined()
+            }
+        }
+    }
+
+    fn value_to_string(&mut self, value: &Value) -> String {
+        match value.to_string(&mut self.context) {
+            Ok(string) => string.to_string(),
+            // Converting some values (like objects with a throwing `toString`) can itself throw.
+            Err(_) => value.display().to_string(),
+        }
+    }
+}
+
+/// Whether a script element with the given `type` attribute is a classic script.  Scripts without
+/// a type, or with an empty one, are classic scripts; otherwise the type must be a JavaScript MIME
+/// type essence match.
+///
+/// https://mimesniff.spec.whatwg.org/#javascript-mime-type
+fn is_javascript_type(script_type: Option<&str>) -> bool {
+    let script_type = match script_type {
+        Some(script_type) => script_type.trim().to_ascii_lowercase(),
+        None => return true,
+    };
+    script_type.is_empty()
+        || matches!(
+            script_type.as_str(),
+            "application/ecmascript"
+                | "application/javascript"
+                | "application/x-ecmascript"
+                | "application/x-javascript"
+                | "text/ecmascript"
+                | "text/javascript"
+                | "text/javascript1.0"
+                | "text/javascript1.1"
+                | "text/javascript1.2"
+                | "text/javascript1.3"
+                | "text/javascript1.4"
+                | "text/javascript1.5"
+                | "text/jscript"
+                | "text/livescript"
+                | "text/x-ecmascript"
+                | "text/x-javascript"
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::parser::{parse_html, parse_html_with_options, ParseOpts};
+    use crate::dom::traits::*;
+    use crate::style::test_utils::style;
+    use cssparser::RGBA;
+
+    fn parse_with_scripts(html: &str) -> NodeRef {
+        parse_html_with_options(ParseOpts {
+            script_engine: Some(ScriptEngineSlot::default()),
+            ..ParseOpts::default()
+        })
+        .one(html)
+    }
+
+    fn attribute(document: &NodeRef, selector: &str, name: &str) -> Option<String> {
+        let element = document.select_first(selector).unwrap();
+        let attributes = element.attributes.borrow();
+        attributes.get(name).map(str::to_owned)
+    }
+
+    #[test]
+    fn scripts_run_as_they_are_parsed() {
+        let document = parse_with_scripts(
+            "<p id=first>One</p>\
+             <script>\
+               var first = document.getElementById('first');\
+               first.setAttribute('data-paragraphs', document.querySelectorAll('p').length);\
+             </script>\
+             <p>Two</p>",
+        );
+        // Only the paragraphs before the script had been parsed when it ran.
+        assert_eq!(
+            attribute(&document, "#first", "data-paragraphs"),
+            Some("1".to_owned())
+        );
+    }
+
+    #[test]
+    fn document_write_inserts_markup_after_the_script() {
+        let document = parse_with_scripts(
+            "<p id=first>One</p>\
+             <script>\
+               document.write('<p id=written>Two<script>document.write(\"<i>nested</i>\")</scr');\
+               document.writeln('ipt></p>');\
+             </script>\
+             <p id=last>Three</p>",
+        );
+        let paragraphs = document
+            .select("p")
+            .unwrap()
+            .map(|p| p.attributes.borrow().get("id").unwrap().to_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(paragraphs, vec!["first", "written", "last"]);
+        // The markup written by a script that was itself written is parsed too.
+        assert_eq!(
+            document.select_first("#written").unwrap().text_contents(),
+            "Twodocument.write(\"<i>nested</i>\")nested"
+        );
+        // Writes after parsing finished have no insertion point to insert markup at.
+        let mut engine = ScriptEngine::new(document.clone());
+        engine.eval("document.write('<p>Too late</p>')").unwrap();
+        assert_eq!(document.select("p").unwrap().count(), 3);
+    }
+
+    #[test]
+    fn only_classic_scripts_run() {
+        let document = parse_with_scripts(
+            "<body>\
+             <script type=module>document.body.setAttribute('module', 'ran')</script>\
+             <script type=text/plain>document.body.setAttribute('plain', 'ran')</script>\
+             <script type=' Text/JavaScript '>document.body.setAttribute('js', 'ran')</script>",
+        );
+        assert_eq!(attribute(&document, "body", "module"), None);
+        assert_eq!(attribute(&document, "body", "plain"), None);
+        assert_eq!(attribute(&document, "body", "js"), Some("ran".to_owned()));
+        // Scripts don't run unless asked to.
+        let document =
+            parse_html().one("<body><script>document.body.setAttribute('id', 'x')</script>");
+        assert_eq!(attribute(&document, "body", "id"), None);
+    }
+
+    #[test]
+    fn exposes_document_and_elements() {
+        let document =
+            parse_html().one("<body class=page><div id=a><span>Hello</span> world</div></body>");
+        let mut engine = ScriptEngine::new(document);
+        let mut eval = |source: &str| engine.eval(source).unwrap();
+        assert_eq!(eval("document.body.className"), "page");
+        assert_eq!(eval("document.documentElement.tagName"), "HTML");
+        assert_eq!(
+            eval("document.getElementById('a').textContent"),
+            "Hello world"
+        );
+        assert_eq!(eval("document.querySelector('#a span').tagName"), "SPAN");
+        assert_eq!(eval("document.getElementById('missing')"), "null");
+        assert_eq!(
+            eval("document.querySelector('span').parentNode === document.getElementById('a')"),
+            "true"
+        );
+        assert_eq!(
+            eval("document.body.parentNode.parentNode === document"),
+            "true"
+        );
+        assert!(matches!(
+            engine.eval("document.querySelector('[')"),
+            Err(ScriptError::Uncaught(_))
+        ));
+    }
+
+    #[test]
+    fn mutates_the_tree() {
+        let document = parse_html().one("<body><ul><li>One</li></ul></body>");
+        let mut engine = ScriptEngine::new(document.clone());
+        let mut eval = |source: &str| engine.eval(source).unwrap();
+        eval(
+            "var list = document.querySelector('ul');\
+             var item = document.createElement('LI');\
+             item.textContent = 'Two';\
+             list.appendChild(item);\
+             list.appendChild(document.createTextNode('!'));",
+        );
+        assert_eq!(
+            document
+                .select_first("ul")
+                .unwrap()
+                .as_node()
+                .text_contents(),
+            "OneTwo!"
+        );
+        assert_eq!(eval("item.parentNode === list"), "true");
+        assert_eq!(eval("item.tagName"), "LI");
+        assert_eq!(
+            eval("list.removeChild(list.querySelector('li')).textContent"),
+            "One"
+        );
+        assert_eq!(eval("list.textContent"), "Two!");
+        assert_eq!(eval("list.textContent = null; list.textContent"), "");
+        // A node can't be inserted into itself, or removed from something that isn't its parent.
+        assert!(engine.eval("list.appendChild(list)").is_err());
+        assert!(engine.eval("document.body.removeChild(item)").is_err());
+        assert!(engine.eval("list.appendChild({})").is_err());
+    }
+
+    #[test]
+    fn inline_style_changes_are_applied() {
+        let document = parse_with_scripts(
+            "<p id=a style='color: red'>Styled</p>\
+             <script>\
+               var style = document.getElementById('a').style;\
+               style.color = 'green';\
+               style.setProperty('margin-top', '4px', 'important');\
+               style.width = 'not a width';\
+             </script>",
+        );
+        assert_eq!(
+            attribute(&document, "#a", "style"),
+            Some("color: green; margin-top: 4px !important;".to_owned())
+        );
+        // Scripts run during parsing, so their changes are picked up when styles are first applied.
+        style(&document, "");
+        let paragraph = document.select_first("#a").unwrap();
+        assert_eq!(
+            paragraph.as_node().computed_values().color.rgba(),
+            RGBA::new(0, 128, 0, 255)
+        );
+        let mut engine = ScriptEngine::new(document);
+        assert_eq!(
+            engine
+                .eval("document.getElementById('a').style.marginTop")
+                .unwrap(),
+            "4px"
+        );
+    }
+
+    #[test]
+    fn timers_run_when_due() {
+        let document = parse_html().one("<body>");
+        let mut engine = ScriptEngine::new(document);
+        engine
+            .eval(
+                "var log = [];\
+                 setTimeout(function () { log.push('timeout'); }, 0);\
+                 var interval = setInterval(function () { log.push('interval'); });\
+                 clearTimeout(setTimeout(function () { log.push('cleared'); }, 0));\
+                 setTimeout(function () { log.push('later'); }, 60000);\
+                 setTimeout(function () { throw new Error('reported, not propagated'); });",
+            )
+            .unwrap();
+        // Timers never run synchronously.
+        assert_eq!(engine.eval("log.join()").unwrap(), "");
+        engine.run_due_timers();
+        assert_eq!(engine.eval("log.join()").unwrap(), "timeout,interval");
+        engine.run_due_timers();
+        assert_eq!(
+            engine.eval("log.join()").unwrap(),
+            "timeout,interval,interval"
+        );
+        engine.eval("clearInterval(interval)").unwrap();
+        engine.run_due_timers();
+        assert_eq!(
+            engine.eval("log.join()").unwrap(),
+            "timeout,interval,interval"
+        );
+        assert!(engine.time_until_next_timer().unwrap() > Duration::from_secs(50));
+    }
+
+    #[test]
+    fn animation_frame_callbacks_run_once_per_frame() {
+        let document = parse_html().one("<body>");
+        let mut engine = ScriptEngine::new(document.clone());
+        assert!(!engine.has_animation_frame_callbacks());
+        engine
+            .eval(
+                "var frames = [];\
+                 requestAnimationFrame(function (now) {\
+                   frames.push(typeof now);\
+                   document.body.textContent = 'animated';\
+                   requestAnimationFrame(function () { frames.push('next'); });\
+                 });\
+                 cancelAnimationFrame(requestAnimationFrame(function () { frames.push('x'); }));",
+            )
+            .unwrap();
+        assert!(engine.has_animation_frame_callbacks());
+        engine.run_animation_frame_callbacks();
+        assert_eq!(engine.eval("frames.join()").unwrap(), "number");
+        assert_eq!(document.text_contents(), "animated");
+        engine.run_animation_frame_callbacks();
+        assert_eq!(engine.eval("frames.join()").unwrap(), "number,next");
+        assert!(!engine.has_animation_frame_callbacks());
+    }
+
+    #[test]
+    fn scripts_cannot_replace_the_scheduler() {
+        let document = parse_html().one("<body>");
+        let mut engine = ScriptEngine::new(document);
+        engine
+            .eval(
+                "var log = [];\
+                 setTimeout(function () { log.push('timeout'); });\
+                 __kosmonautScheduler.runDueTimers = null;\
+                 __kosmonautScheduler = null;\
+                 delete __kosmonautScheduler;",
+            )
+            .unwrap();
+        engine.run_due_timers();
+        assert_eq!(engine.eval("log.join()").unwrap(), "timeout");
+        assert_eq!(engine.time_until_next_timer(), None);
+    }
+}
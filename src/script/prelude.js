@@ -0,0 +1,430 @@
+// Builds the objects scripts interact with (`document`, its nodes, `console`, and timers) on top of
+// the native functions Kosmonaut registers in `script/dom.rs` and `script/timers.rs`.  The native
+// functions refer to nodes by numeric ID, with the document always having ID 0.
+
+var console = {
+    log: __kosmonautLog,
+    info: __kosmonautLog,
+    warn: __kosmonautLog,
+    error: __kosmonautLog,
+};
+
+var document = (function () {
+    // Each node is always represented by the same object, so that e.g.
+    // `document.body === document.querySelector("body")`.
+    var wrappers = {};
+
+    function wrap(id) {
+        if (id === null) {
+            return null;
+        }
+        if (!(id in wrappers)) {
+            switch (__kosmonautNodeType(id)) {
+                case Node.ELEMENT_NODE:
+                    wrappers[id] = new Element(id);
+                    break;
+                case Node.TEXT_NODE:
+                case Node.COMMENT_NODE:
+                    wrappers[id] = new CharacterData(id);
+                    break;
+                case Node.DOCUMENT_NODE:
+                    wrappers[id] = new Document(id);
+                    break;
+                default:
+                    wrappers[id] = new Node(id);
+            }
+        }
+        return wrappers[id];
+    }
+
+    // The ID of `node`, throwing a `TypeError` if it isn't a node.
+    function unwrap(node) {
+        if (node === null || typeof node !== "object" || !("__nodeId" in node)) {
+            throw new TypeError("argument is not a node");
+        }
+        return node.__nodeId;
+    }
+
+    function accessor(prototype, name, get, set) {
+        Object.defineProperty(prototype, name, {
+            get: get,
+            set: set,
+            enumerable: true,
+            configurable: true,
+        });
+    }
+
+    // Treats `null` as the empty string, like the `[LegacyNullToEmptyString]` WebIDL attribute.
+    function stringOrEmpty(value) {
+        return value === null ? "" : String(value);
+    }
+
+    // https://dom.spec.whatwg.org/#interface-node
+    function Node(id) {
+        this.__nodeId = id;
+    }
+    Node.ELEMENT_NODE = 1;
+    Node.TEXT_NODE = 3;
+    Node.COMMENT_NODE = 8;
+    Node.DOCUMENT_NODE = 9;
+    accessor(Node.prototype, "nodeType", function () {
+        return __kosmonautNodeType(this.__nodeId);
+    });
+    accessor(Node.prototype, "nodeName", function () {
+        return __kosmonautNodeName(this.__nodeId);
+    });
+    accessor(
+        Node.prototype,
+        "textContent",
+        function () {
+            return __kosmonautTextContent(this.__nodeId);
+        },
+        function (value) {
+            __kosmonautSetTextContent(this.__nodeId, stringOrEmpty(value));
+        }
+    );
+    accessor(Node.prototype, "parentNode", function () {
+        return wrap(__kosmonautParentNode(this.__nodeId));
+    });
+    Node.prototype.appendChild = function (node) {
+        return wrap(__kosmonautAppendChild(this.__nodeId, unwrap(node)));
+    };
+    Node.prototype.removeChild = function (child) {
+        return wrap(__kosmonautRemoveChild(this.__nodeId, unwrap(child)));
+    };
+
+    // https://dom.spec.whatwg.org/#interface-parentnode
+    function defineParentNodeMembers(prototype) {
+        prototype.querySelector = function (selectors) {
+            return wrap(__kosmonautQuerySelector(this.__nodeId, selectors, 0));
+        };
+        // TODO: This should return a static `NodeList` rather than an array.
+        prototype.querySelectorAll = function (selectors) {
+            var elements = [];
+            for (var index = 0; ; index++) {
+                var id = __kosmonautQuerySelector(this.__nodeId, selectors, index);
+                if (id === null) {
+                    return elements;
+                }
+                elements.push(wrap(id));
+            }
+        };
+    }
+
+    // https://dom.spec.whatwg.org/#interface-characterdata
+    function CharacterData(id) {
+        this.__nodeId = id;
+    }
+    CharacterData.prototype = Object.create(Node.prototype);
+    accessor(
+        CharacterData.prototype,
+        "data",
+        function () {
+            return __kosmonautTextContent(this.__nodeId);
+        },
+        function (value) {
+            __kosmonautSetTextContent(this.__nodeId, stringOrEmpty(value));
+        }
+    );
+
+    // https://dom.spec.whatwg.org/#interface-element
+    function Element(id) {
+        this.__nodeId = id;
+    }
+    Element.prototype = Object.create(Node.prototype);
+    defineParentNodeMembers(Element.prototype);
+    accessor(Element.prototype, "tagName", function () {
+        return __kosmonautNodeName(this.__nodeId);
+    });
+    accessor(Element.prototype, "id", function () {
+        var id = this.getAttribute("id");
+        return id === null ? "" : id;
+    });
+    accessor(Element.prototype, "className", function () {
+        var className = this.getAttribute("class");
+        return className === null ? "" : className;
+    });
+    // https://drafts.csswg.org/cssom/#dom-elementcssinlinestyle-style
+    accessor(Element.prototype, "style", function () {
+        if (!("__style" in this)) {
+            this.__style = new CSSStyleDeclaration(this.__nodeId);
+        }
+        return this.__style;
+    });
+    Element.prototype.getAttribute = function (name) {
+        return __kosmonautGetAttribute(this.__nodeId, name);
+    };
+    Element.prototype.setAttribute = function (name, value) {
+        __kosmonautSetAttribute(this.__nodeId, name, value);
+    };
+
+    // https://drafts.csswg.org/cssom/#the-cssstyledeclaration-interface
+    function CSSStyleDeclaration(elementId) {
+        this.__elementId = elementId;
+    }
+    accessor(
+        CSSStyleDeclaration.prototype,
+        "cssText",
+        function () {
+            return __kosmonautGetStyleText(this.__elementId);
+        },
+        function (value) {
+            __kosmonautSetAttribute(this.__elementId, "style", stringOrEmpty(value));
+        }
+    );
+    CSSStyleDeclaration.prototype.getPropertyValue = function (property) {
+        return __kosmonautGetStyleProperty(this.__elementId, property);
+    };
+    CSSStyleDeclaration.prototype.setProperty = function (property, value, priority) {
+        __kosmonautSetStyleProperty(
+            this.__elementId,
+            property,
+            stringOrEmpty(value),
+            priority === undefined ? "" : priority
+        );
+    };
+    CSSStyleDeclaration.prototype.removeProperty = function (property) {
+        return __kosmonautRemoveStyleProperty(this.__elementId, property);
+    };
+    // Camel-cased attributes (e.g. `style.backgroundColor`) for each property Kosmonaut supports,
+    // which should be kept in sync with `PropertyId::parse`.
+    // https://drafts.csswg.org/cssom/#dom-cssstyledeclaration-camel-cased-attribute
+    [
+        "background",
+        "background-color",
+        "background-image",
+        "border",
+        "border-bottom",
+        "border-bottom-color",
+        "border-bottom-style",
+        "border-bottom-width",
+        "border-left",
+        "border-left-color",
+        "border-left-style",
+        "border-left-width",
+        "border-right",
+        "border-right-color",
+        "border-right-style",
+        "border-right-width",
+        "border-top",
+        "border-top-color",
+        "border-top-style",
+        "border-top-width",
+        "border-width",
+        "color",
+        "direction",
+        "display",
+        "font-size",
+        "height",
+        "margin",
+        "margin-bottom",
+        "margin-left",
+        "margin-right",
+        "margin-top",
+        "object-fit",
+        "object-position",
+        "padding",
+        "padding-bottom",
+        "padding-left",
+        "padding-right",
+        "padding-top",
+        "width",
+        "writing-mode",
+    ].forEach(function (property) {
+        var words = property.split("-");
+        for (var index = 1; index < words.length; index++) {
+            words[index] = words[index].charAt(0).toUpperCase() + words[index].slice(1);
+        }
+        accessor(
+            CSSStyleDeclaration.prototype,
+            words.join(""),
+            function () {
+                return this.getPropertyValue(property);
+            },
+            function (value) {
+                this.setProperty(property, value);
+            }
+        );
+    });
+
+    // https://dom.spec.whatwg.org/#interface-document
+    function Document(id) {
+        this.__nodeId = id;
+    }
+    Document.prototype = Object.create(Node.prototype);
+    defineParentNodeMembers(Document.prototype);
+    accessor(Document.prototype, "documentElement", function () {
+        return wrap(__kosmonautDocumentElement());
+    });
+    accessor(Document.prototype, "body", function () {
+        return wrap(__kosmonautBody());
+    });
+    Document.prototype.getElementById = function (elementId) {
+        return wrap(__kosmonautGetElementById(elementId));
+    };
+    Document.prototype.createElement = function (localName) {
+        return wrap(__kosmonautCreateElement(localName));
+    };
+    Document.prototype.createTextNode = function (data) {
+        return wrap(__kosmonautCreateTextNode(data));
+    };
+    // https://html.spec.whatwg.org/multipage/dynamic-markup-insertion.html#dom-document-write
+    Document.prototype.write = function () {
+        __kosmonautWrite(Array.prototype.join.call(arguments, ""));
+    };
+    Document.prototype.writeln = function () {
+        __kosmonautWrite(Array.prototype.join.call(arguments, "") + "\n");
+    };
+
+    return wrap(0);
+})();
+
+// https://w3c.github.io/hr-time/#the-performance-attribute
+var performance = {
+    now: __kosmonautNow,
+};
+
+// The callbacks scripts have scheduled with `setTimeout`, `setInterval`, and
+// `requestAnimationFrame`, which Kosmonaut's event loop runs through `runDueTimers` and
+// `runAnimationFrameCallbacks`.
+//
+// https://html.spec.whatwg.org/multipage/timers-and-user-prompts.html#timers and
+// https://html.spec.whatwg.org/multipage/imagebitmap-and-animations.html#animation-frames
+//
+// The scheduler is a non-writable, non-configurable property of the global object, as are its
+// methods, so that scripts can't replace what the event loop calls.
+(function (global) {
+    // Active timers, ordered by when they're due (and then by when they were scheduled).
+    var timers = [];
+    var nextTimerHandle = 1;
+    // The callbacks to run before the next paint.
+    var animationFrameCallbacks = [];
+    var nextAnimationFrameHandle = 1;
+
+    function invoke(callback, argument) {
+        try {
+            callback(argument);
+        } catch (exception) {
+            __kosmonautReportException(exception);
+        }
+    }
+
+    function insertTimer(timer) {
+        var index = 0;
+        while (index < timers.length && timers[index].due <= timer.due) {
+            index++;
+        }
+        timers.splice(index, 0, timer);
+    }
+
+    // TODO: Support string handlers, passing extra arguments to the handler, and clamping the
+    // timeouts of deeply nested timers.
+    function scheduleTimer(handler, timeout, repeat) {
+        if (typeof handler !== "function") {
+            throw new TypeError("timer handlers must be functions");
+        }
+        timeout = Number(timeout);
+        if (!(timeout > 0)) {
+            timeout = 0;
+        }
+        var timer = {
+            handle: nextTimerHandle++,
+            handler: handler,
+            timeout: timeout,
+            repeat: repeat,
+            due: performance.now() + timeout,
+        };
+        insertTimer(timer);
+        return timer.handle;
+    }
+
+    function clearTimer(handle) {
+        for (var index = 0; index < timers.length; index++) {
+            if (timers[index].handle === handle) {
+                timers.splice(index, 1);
+                return;
+            }
+        }
+    }
+
+    var methods = {
+        setTimeout: function (handler, timeout) {
+            return scheduleTimer(handler, timeout, false);
+        },
+        setInterval: function (handler, timeout) {
+            return scheduleTimer(handler, timeout, true);
+        },
+        clearTimer: clearTimer,
+        requestAnimationFrame: function (callback) {
+            if (typeof callback !== "function") {
+                throw new TypeError("animation frame callbacks must be functions");
+            }
+            var entry = {
+                handle: nextAnimationFrameHandle++,
+                callback: callback,
+                cancelled: false,
+            };
+            animationFrameCallbacks.push(entry);
+            return entry.handle;
+        },
+        cancelAnimationFrame: function (handle) {
+            for (var index = 0; index < animationFrameCallbacks.length; index++) {
+                if (animationFrameCallbacks[index].handle === handle) {
+                    animationFrameCallbacks[index].cancelled = true;
+                }
+            }
+        },
+        // Runs the handlers of the timers that were due when this was called.  Timers scheduled
+        // by those handlers wait until the next call, even if they're already due.
+        runDueTimers: function () {
+            var now = performance.now();
+            var due = [];
+            while (timers.length > 0 && timers[0].due <= now) {
+                due.push(timers.shift());
+            }
+            for (var index = 0; index < due.length; index++) {
+                var timer = due[index];
+                if (timer.repeat) {
+                    timer.due = now + timer.timeout;
+                    insertTimer(timer);
+                }
+                invoke(timer.handler);
+            }
+        },
+        // The number of milliseconds until the next timer is due, or null if there are no
+        // timers.
+        timeUntilNextTimer: function () {
+            if (timers.length === 0) {
+                return null;
+            }
+            return Math.max(timers[0].due - performance.now(), 0);
+        },
+        hasAnimationFrameCallbacks: function () {
+            return animationFrameCallbacks.length > 0;
+        },
+        // https://html.spec.whatwg.org/multipage/imagebitmap-and-animations.html#run-the-animation-frame-callbacks
+        runAnimationFrameCallbacks: function () {
+            var now = performance.now();
+            var callbacks = animationFrameCallbacks;
+            animationFrameCallbacks = [];
+            for (var index = 0; index < callbacks.length; index++) {
+                if (!callbacks[index].cancelled) {
+                    invoke(callbacks[index].callback, now);
+                }
+            }
+        },
+    };
+
+    var scheduler = {};
+    Object.keys(methods).forEach(function (name) {
+        Object.defineProperty(scheduler, name, { value: methods[name] });
+    });
+    Object.defineProperty(global, "__kosmonautScheduler", { value: scheduler });
+})(this);
+
+var setTimeout = __kosmonautScheduler.setTimeout;
+var setInterval = __kosmonautScheduler.setInterval;
+var clearTimeout = __kosmonautScheduler.clearTimer;
+var clearInterval = __kosmonautScheduler.clearTimer;
+var requestAnimationFrame = __kosmonautScheduler.requestAnimationFrame;
+var cancelAnimationFrame = __kosmonautScheduler.cancelAnimationFrame;
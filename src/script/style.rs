@@ -0,0 +1,198 @@
+use cssparser::{
+    parse_important, AtRuleParser, CowRcStr, DeclarationListParser, DeclarationParser, Delimiter,
+    ParseError, Parser, ParserInput,
+};
+
+use crate::style::properties::parse_property_declaration_list;
+
+/// The declarations of an element's `style` attribute, as seen through `element.style`.  Values are
+/// kept as the text they were specified with, since Kosmonaut can't serialize parsed values.
+///
+/// https://drafts.csswg.org/cssom/#the-cssstyledeclaration-interface
+#[derive(Debug, Default)]
+pub(super) struct InlineStyle {
+    declarations: Vec<InlineDeclaration>,
+}
+
+#[derive(Debug)]
+struct InlineDeclaration {
+    /// The property name, lowercased.
+    name: String,
+    value: String,
+    important: bool,
+}
+
+impl InlineStyle {
+    /// Parses the value of a `style` attribute, dropping any declarations Kosmonaut doesn't
+    /// support (which would be dropped when styles are applied anyway).
+    ///
+    /// https://drafts.csswg.org/cssom/#parse-a-css-declaration-block
+    pub(super) fn parse(css_text: &str) -> InlineStyle {
+        let mut input = ParserInput::new(css_text);
+        let mut parser = Parser::new(&mut input);
+        let mut style = InlineStyle::default();
+        DeclarationListParser::new(&mut parser, RawDeclarationParser)
+            .filter_map(Result::ok)
+            .filter(|declaration| is_supported_declaration(&declaration.name, &declaration.value))
+            .for_each(|declaration| style.set(declaration));
+        style
+    }
+
+    /// https://drafts.csswg.org/cssom/#serialize-a-css-declaration-block
+    pub(super) fn css_text(&self) -> String {
+        self.declarations
+            .iter()
+            .map(|declaration| {
+                let priority = if declaration.important {
+                    " !important"
+                } else {
+                    ""
+                };
+                format!("{}: {}{};", declaration.name, declaration.value, priority)
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// https://drafts.csswg.org/cssom/#dom-cssstyledeclaration-getpropertyvalue
+    pub(super) fn get_property_value(&self, name: &str) -> String {
+        let name = name.to_ascii_lowercase();
+        self.declarations
+            .iter()
+            .find(|declaration| declaration.name == name)
+            .map_or_else(String::new, |declaration| declaration.value.clone())
+    }
+
+    /// Sets the property `name` to `value`, or removes it if `value` is empty.  Returns whether
+    /// the declarations changed, which they don't if the value is invalid or unsupported, or if
+    /// `priority` is anything other than `important` or empty.
+    ///
+    /// https://drafts.csswg.org/cssom/#dom-cssstyledeclaration-setproperty
+    pub(super) fn set_property(&mut self, name: &str, value: &str, priority: &str) -> bool {
+        let name = name.to_ascii_lowercase();
+        let value = value.trim();
+        if value.is_empty() {
+            return !self.remove_property(&name).is_empty();
+        }
+        let important = match priority.to_ascii_lowercase().as_str() {
+            "important" => true,
+            "" => false,
+            _ => return false,
+        };
+        if !is_supported_declaration(&name, value) {
+            return false;
+        }
+        self.set(InlineDeclaration {
+            name,
+            value: value.to_owned(),
+            important,
+        });
+        true
+    }
+
+    /// Removes the property `name`, returning its previous value (or an empty string if it wasn't
+    /// set).
+    ///
+    /// https://drafts.csswg.org/cssom/#dom-cssstyledeclaration-removeproperty
+    pub(super) fn remove_property(&mut self, name: &str) -> String {
+        let name = name.to_ascii_lowercase();
+        match self
+            .declarations
+            .iter()
+            .position(|declaration| declaration.name == name)
+        {
+            Some(index) => self.declarations.remove(index).value,
+            None => String::new(),
+        }
+    }
+
+    /// Replaces any existing declaration of the same property in place, or appends the
+    /// declaration otherwise.
+    fn set(&mut self, declaration: InlineDeclaration) {
+        match self
+            .declarations
+            .iter_mut()
+            .find(|existing| existing.name == declaration.name)
+        {
+            Some(existing) => *existing = declaration,
+            None => self.declarations.push(declaration),
+        }
+    }
+}
+
+/// Whether the style system understands `name: value`.  Values that would smuggle in further
+/// declarations (e.g. `red; width: 0`) aren't supported.
+fn is_supported_declaration(name: &str, value: &str) -> bool {
+    let declaration = format!("{}: {}", name, value);
+    let mut raw_input = ParserInput::new(&declaration);
+    let mut raw_parser = Parser::new(&mut raw_input);
+    if DeclarationListParser::new(&mut raw_parser, RawDeclarationParser).count() != 1 {
+        return false;
+    }
+    let mut input = ParserInput::new(&declaration);
+    !parse_property_declaration_list(&mut Parser::new(&mut input))
+        .declarations()
+        .is_empty()
+}
+
+/// Parses declarations without interpreting their values, so they can be serialized as written.
+struct RawDeclarationParser;
+
+impl<'i> DeclarationParser<'i> for RawDeclarationParser {
+    type Declaration = InlineDeclaration;
+    type Error = ();
+
+    fn parse_value<'t>(
+        &mut self,
+        name: CowRcStr<'i>,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<InlineDeclaration, ParseError<'i, ()>> {
+        let start = input.position();
+        input.parse_until_before(Delimiter::Bang, |input| {
+            while input.next().is_ok() {}
+            Ok(())
+        })?;
+        let value = input.slice_from(start).trim().to_owned();
+        let important = input.try_parse(parse_important).is_ok();
+        input.expect_exhausted()?;
+        Ok(InlineDeclaration {
+            name: name.to_ascii_lowercase(),
+            value,
+            important,
+        })
+    }
+}
+
+impl<'i> AtRuleParser<'i> for RawDeclarationParser {
+    type PreludeNoBlock = ();
+    type PreludeBlock = ();
+    type AtRule = InlineDeclaration;
+    type Error = ();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_supported_declarations() {
+        let mut style =
+            InlineStyle::parse("COLOR: red ; unknown: 1; width: 10px !important; height: nope");
+        assert_eq!(style.css_text(), "color: red; width: 10px !important;");
+        assert_eq!(style.get_property_value("width"), "10px");
+
+        assert!(style.set_property("color", "blue", ""));
+        assert!(style.set_property("margin-top", "2px", "important"));
+        assert!(!style.set_property("margin-top", "bogus", ""));
+        assert!(!style.set_property("float", "left", ""));
+        assert!(!style.set_property("color", "red; width: 0", ""));
+        assert_eq!(
+            style.css_text(),
+            "color: blue; width: 10px !important; margin-top: 2px !important;"
+        );
+
+        assert_eq!(style.remove_property("width"), "10px");
+        assert!(style.set_property("color", "", ""));
+        assert_eq!(style.css_text(), "margin-top: 2px !important;");
+    }
+}
@@ -0,0 +1,26 @@
+use boa::{Context, Result, Value};
+// `std::time::Instant` isn't implemented on wasm32, where this uses `performance.now()`.
+use instant::Instant;
+use std::cell::Cell;
+
+thread_local! {
+    /// The time `performance.now()` is measured from, which is when the engine was created.
+    ///
+    /// https://w3c.github.io/hr-time/#dfn-time-origin
+    static TIME_ORIGIN: Cell<Instant> = Cell::new(Instant::now());
+}
+
+/// Registers the native functions the timers in `prelude.js` are built on.
+pub(super) fn register(context: &mut Context) -> Result<()> {
+    TIME_ORIGIN.with(|time_origin| time_origin.set(Instant::now()));
+    context.register_global_function("__kosmonautNow", 0, now)?;
+    Ok(())
+}
+
+/// `performance.now()`, the number of milliseconds since the time origin.
+///
+/// https://w3c.github.io/hr-time/#dom-performance-now
+fn now(_: &Value, _: &[Value], _: &mut Context) -> Result<Value> {
+    let elapsed = TIME_ORIGIN.with(|time_origin| time_origin.get().elapsed());
+    Ok(Value::from(elapsed.as_secs_f64() * 1000.))
+}
@@ -0,0 +1,233 @@
+//! Container queries, which make the rules of `@container` rules apply only to elements whose
+//! query container (an ancestor with a `container-type`, see `ContainerType`) has a certain size.
+//!
+//! Unlike media queries, container queries depend on layout, so they're evaluated against what
+//! each query container looked like when its document was last laid out (see `QueryContainer`).
+//! Whenever laying out a document resizes a query container, its descendants are restyled and
+//! laid out again (see `page::layout_document`).
+//!
+//! https://drafts.csswg.org/css-contain-3/#container-queries
+
+use crate::dom::tree::NodeRef;
+use crate::style::media::{parse_length_px, Range};
+use crate::style::values::computed::container::parse_container_name;
+use crate::style::values::computed::{ContainerName, ContainerType};
+use cssparser::{ParseError, Parser};
+
+/// A query container as it was when its document was last laid out, which is what container
+/// queries are evaluated against, so that they can't depend on styles that weren't laid out yet.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryContainer {
+    pub container_type: ContainerType,
+    pub name: ContainerName,
+    /// The width and height of the container's content box, in CSS pixels.
+    pub width: f32,
+    pub height: f32,
+    /// Whether the container's writing mode is horizontal, so that its inline size is its width.
+    pub horizontal: bool,
+}
+
+impl QueryContainer {
+    /// The size of this container in `axis`, or `None` if its type doesn't let it be queried in
+    /// that axis.
+    fn size(&self, axis: Axis) -> Option<f32> {
+        let is_width = match axis {
+            Axis::Width => true,
+            Axis::Height => false,
+            Axis::InlineSize => self.horizontal,
+            Axis::BlockSize => !self.horizontal,
+        };
+        let is_inline = is_width == self.horizontal;
+        if !is_inline && !self.container_type.queries_block_size() {
+            return None;
+        }
+        Some(if is_width { self.width } else { self.height })
+    }
+}
+
+/// The condition of an `@container` rule, which selects a query container for each element
+/// (optionally by name), and queries its size.
+///
+/// https://drafts.csswg.org/css-contain-3/#container-rule
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContainerCondition {
+    /// The name the query container must have, if any.
+    name: Option<String>,
+    /// The size features combined with `and`, all of which must match.
+    features: Vec<SizeFeature>,
+}
+
+/// A size feature, which can be prefixed with `min-` or `max-`.
+///
+/// https://drafts.csswg.org/css-contain-3/#size-container
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct SizeFeature {
+    axis: Axis,
+    range: Range,
+    /// The size queried, in CSS pixels.
+    px: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Axis {
+    Width,
+    Height,
+    InlineSize,
+    BlockSize,
+}
+
+impl ContainerCondition {
+    /// Parses the prelude of an `@container` rule: an optional container name, followed by size
+    /// features combined with `and`.
+    ///
+    /// TODO: Support `not` and `or`, the range syntax (e.g. `(width > 400px)`), and the
+    /// `aspect-ratio` and `orientation` features.  Relative lengths should also be resolved
+    /// against the font size of the query container, rather than the initial font size.
+    pub fn parse<'i, 't>(input: &mut Parser<'i, 't>) -> Result<Self, ParseError<'i, ()>> {
+        let name = input.try_parse(parse_container_name).ok();
+        let mut features = vec![SizeFeature::parse(input)?];
+        while input
+            .try_parse(|input| input.expect_ident_matching("and"))
+            .is_ok()
+        {
+            features.push(SizeFeature::parse(input)?);
+        }
+        input.expect_exhausted()?;
+        Ok(ContainerCondition { name, features })
+    }
+
+    /// Whether the size features match the query container of `element`: its nearest ancestor
+    /// that was a query container with this condition's name (if it has one) when the document
+    /// was last laid out, and whose type lets it be queried in the axes of every feature.  Nothing
+    /// matches elements without a query container.
+    pub fn matches(&self, element: &NodeRef) -> bool {
+        element
+            .ancestors()
+            .find_map(|ancestor| {
+                let query_container = ancestor.as_element()?.query_container.borrow().clone();
+                query_container.filter(|container| self.selects(container))
+            })
+            .map_or(false, |container| {
+                self.features
+                    .iter()
+                    .all(|feature| feature.matches(&container))
+            })
+    }
+
+    /// Whether `container` can be the query container this condition is evaluated against.
+    fn selects(&self, container: &QueryContainer) -> bool {
+        self.name
+            .as_ref()
+            .map_or(true, |name| container.name.contains(name))
+            && self
+                .features
+                .iter()
+                .all(|feature| container.size(feature.axis).is_some())
+    }
+}
+
+impl SizeFeature {
+    fn parse<'i, 't>(input: &mut Parser<'i, 't>) -> Result<Self, ParseError<'i, ()>> {
+        input.expect_parenthesis_block()?;
+        input.parse_nested_block(|input| {
+            let location = input.current_source_location();
+            let name = input.expect_ident()?.clone();
+            let (axis, range) = match_ignore_ascii_case! { &name,
+                "width" => (Axis::Width, Range::Exact),
+                "min-width" => (Axis::Width, Range::Min),
+                "max-width" => (Axis::Width, Range::Max),
+                "height" => (Axis::Height, Range::Exact),
+                "min-height" => (Axis::Height, Range::Min),
+                "max-height" => (Axis::Height, Range::Max),
+                "inline-size" => (Axis::InlineSize, Range::Exact),
+                "min-inline-size" => (Axis::InlineSize, Range::Min),
+                "max-inline-size" => (Axis::InlineSize, Range::Max),
+                "block-size" => (Axis::BlockSize, Range::Exact),
+                "min-block-size" => (Axis::BlockSize, Range::Min),
+                "max-block-size" => (Axis::BlockSize, Range::Max),
+                _ => return Err(location.new_custom_error(())),
+            };
+            input.expect_colon()?;
+            let px = parse_length_px(input)?;
+            Ok(SizeFeature { axis, range, px })
+        })
+    }
+
+    fn matches(self, container: &QueryContainer) -> bool {
+        container
+            .size(self.axis)
+            .map_or(false, |size| self.range.matches(size, self.px))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::parser::parse_html;
+    use crate::dom::traits::*;
+    use cssparser::ParserInput;
+
+    fn parse(css: &str) -> Option<ContainerCondition> {
+        let mut input = ParserInput::new(css);
+        ContainerCondition::parse(&mut Parser::new(&mut input)).ok()
+    }
+
+    #[test]
+    fn parses_container_conditions() {
+        assert_eq!(
+            parse("card (min-width: 400px) and (max-block-size: 2em)"),
+            Some(ContainerCondition {
+                name: Some("card".to_owned()),
+                features: vec![
+                    SizeFeature {
+                        axis: Axis::Width,
+                        range: Range::Min,
+                        px: 400.
+                    },
+                    SizeFeature {
+                        axis: Axis::BlockSize,
+                        range: Range::Max,
+                        px: 32.
+                    },
+                ]
+            })
+        );
+        assert!(parse("(inline-size: 0)").is_some());
+        assert_eq!(parse("card"), None);
+        assert_eq!(parse("not (width: 10px)"), None);
+        assert_eq!(parse("(width: 10px) (height: 10px)"), None);
+        assert_eq!(parse("(aspect-ratio: 1)"), None);
+    }
+
+    #[test]
+    fn queries_the_nearest_container_that_can_be_queried() {
+        let document = parse_html()
+            .one("<!DOCTYPE html><div id=outer><div id=inner><p></p></div></div><span></span>");
+        let select = |selector| document.select_first(selector).unwrap();
+        let set_container = |selector, container_type, names: &[&str], width| {
+            *select(selector).query_container.borrow_mut() = Some(QueryContainer {
+                container_type,
+                name: ContainerName {
+                    names: names.iter().map(|name| name.to_string()).collect(),
+                },
+                width,
+                height: 100.,
+                horizontal: true,
+            });
+        };
+        set_container("#outer", ContainerType::Size, &["page"], 800.);
+        set_container("#inner", ContainerType::InlineSize, &[], 300.);
+        let p = select("p").as_node().clone();
+        let matches = |css| parse(css).unwrap().matches(&p);
+
+        assert!(matches("(max-width: 400px)"));
+        assert!(matches("page (min-width: 400px)"));
+        // The inner container can't be queried in its block axis, so the outer one is.
+        assert!(matches("(min-width: 400px) and (height: 100px)"));
+        assert!(!matches("sidebar (width: 300px)"));
+        // Nothing matches elements without a query container.
+        assert!(!parse("(min-width: 0px)")
+            .unwrap()
+            .matches(select("span").as_node()));
+    }
+}
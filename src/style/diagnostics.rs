@@ -0,0 +1,112 @@
+//! Diagnostics for the parts of stylesheets that are dropped while parsing them, so that authors
+//! can find out why their CSS has no effect.
+
+use std::fmt;
+
+use cssparser::{ParseError, ParseErrorKind};
+use selectors::parser::SelectorParseErrorKind;
+
+use crate::style::StyleParseErrorKind;
+
+/// Something in a stylesheet that was dropped because it couldn't be parsed, or isn't supported.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CssDiagnostic {
+    /// The line the error was found on, counting from one.
+    pub line: u32,
+    /// The column the error was found at, counting from one.
+    pub column: u32,
+    pub kind: CssDiagnosticKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CssDiagnosticKind {
+    /// A declaration of a property we don't know, with the name of the property.
+    UnknownProperty(String),
+    /// A declaration of a known property whose value was invalid (or unsupported), as written.
+    InvalidDeclaration(String),
+    /// A style rule whose selectors were invalid (or unsupported), as written.
+    InvalidSelector(String),
+    /// A style rule dropped for a pseudo-class or pseudo-element we don't support, with its name.
+    UnsupportedPseudoClassOrElement(String),
+    /// An at-rule we don't support, with its name.
+    UnsupportedAtRule(String),
+    /// Any other rule that couldn't be parsed, as written.
+    InvalidRule(String),
+}
+
+impl CssDiagnostic {
+    /// The diagnostic for a rule that was dropped with `error`, written as `rule`.
+    pub fn for_rule(error: &ParseError<StyleParseErrorKind>, rule: &str) -> CssDiagnostic {
+        let kind = match &error.kind {
+            ParseErrorKind::Custom(StyleParseErrorKind::UnsupportedAtRule(name)) => {
+                CssDiagnosticKind::UnsupportedAtRule(name.to_string())
+            }
+            ParseErrorKind::Custom(StyleParseErrorKind::SelectorError(
+                SelectorParseErrorKind::UnsupportedPseudoClassOrElement(name),
+            )) => CssDiagnosticKind::UnsupportedPseudoClassOrElement(name.to_string()),
+            // Style rules with a block are only dropped for their selectors, since invalid
+            // declarations are dropped from the block instead.
+            _ if !rule.trim_start().starts_with('@') && rule.contains('{') => {
+                let prelude = rule.split('{').next().unwrap_or(rule);
+                CssDiagnosticKind::InvalidSelector(collapse_whitespace(prelude))
+            }
+            _ => CssDiagnosticKind::InvalidRule(collapse_whitespace(rule)),
+        };
+        CssDiagnostic::new(error, kind)
+    }
+
+    /// The diagnostic for a declaration that was dropped with `error`, written as `declaration`.
+    pub fn for_declaration(
+        error: &ParseError<StyleParseErrorKind>,
+        declaration: &str,
+    ) -> CssDiagnostic {
+        let kind = match &error.kind {
+            ParseErrorKind::Custom(StyleParseErrorKind::UnknownProperty(name)) => {
+                CssDiagnosticKind::UnknownProperty(name.to_string())
+            }
+            _ => {
+                let declaration = declaration.trim_end().trim_end_matches(';');
+                CssDiagnosticKind::InvalidDeclaration(collapse_whitespace(declaration))
+            }
+        };
+        CssDiagnostic::new(error, kind)
+    }
+
+    fn new(error: &ParseError<StyleParseErrorKind>, kind: CssDiagnosticKind) -> CssDiagnostic {
+        CssDiagnostic {
+            // `cssparser` counts lines from zero, but columns from one.
+            line: error.location.line + 1,
+            column: error.location.column,
+            kind,
+        }
+    }
+}
+
+/// Collapses runs of whitespace (including newlines) in `css` into single spaces, so it can be
+/// quoted on one line.
+fn collapse_whitespace(css: &str) -> String {
+    css.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Formats the diagnostic as `LINE:COLUMN: MESSAGE`, to be prefixed with the stylesheet's name.
+impl fmt::Display for CssDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: ", self.line, self.column)?;
+        match &self.kind {
+            CssDiagnosticKind::UnknownProperty(name) => write!(f, "unknown property `{}`", name),
+            CssDiagnosticKind::InvalidDeclaration(declaration) => {
+                write!(f, "invalid declaration `{}`", declaration)
+            }
+            CssDiagnosticKind::InvalidSelector(selector) => {
+                write!(f, "invalid selector `{}`", selector)
+            }
+            CssDiagnosticKind::UnsupportedPseudoClassOrElement(name) => {
+                write!(f, "unsupported pseudo-class or pseudo-element `:{}`", name)
+            }
+            CssDiagnosticKind::UnsupportedAtRule(name) => {
+                write!(f, "unsupported at-rule `@{}`", name)
+            }
+            CssDiagnosticKind::InvalidRule(rule) => write!(f, "invalid rule `{}`", rule),
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use crate::dom::tree::{NodeData, NodeRef};
+use crate::style::properties::id::LonghandId;
+use std::io::Write;
+
+/// Writes the computed values of `longhands` for each element in the tree starting with `node`,
+/// indenting each element by its depth in the tree, e.g.:
+///
+/// HTML
+///   display: Display { ... }
+///   BODY
+///     display: Display { ... }
+///
+/// Used in the `dump-styles` command to make cascade and computation bugs diagnosable and
+/// snapshot-testable.  Values are written in their `Debug` representation.
+pub fn dump_styles<W: Write>(
+    node: &NodeRef,
+    write_to: &mut W,
+    indent_spaces: usize,
+    longhands: &[LonghandId],
+) {
+    let child_indent = match node.data() {
+        NodeData::Element(element_data) => {
+            writeln!(
+                write_to,
+                "{:indent_spaces$}{}",
+                "",
+                element_data.name.local.to_uppercase(),
+                indent_spaces = indent_spaces
+            )
+            .expect("error writing style dump");
+            let computed_values = node.computed_values();
+            for longhand in longhands {
+                writeln!(
+                    write_to,
+                    "{:indent_spaces$}{}: {}",
+                    "",
+                    longhand.name(),
+                    computed_values.dump_style_format(*longhand),
+                    indent_spaces = indent_spaces + 2
+                )
+                .expect("error writing style dump");
+            }
+            indent_spaces + 2
+        }
+        // Only elements have styles of their own, but the document holds the elements.
+        _ => indent_spaces,
+    };
+    for child in node.children() {
+        dump_styles(&child, write_to, child_indent, longhands);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::style::test_utils::styled_document;
+
+    #[test]
+    fn dumps_selected_properties_of_each_element() {
+        let document = styled_document("<p>text</p>", "p { tab-size: 4 }");
+        let mut dump = Vec::new();
+        dump_styles(&document, &mut dump, 0, &[LonghandId::TabSize]);
+        assert_eq!(
+            String::from_utf8(dump).unwrap(),
+            "HTML
+  tab-size: Number(8.0)
+  HEAD
+    tab-size: Number(8.0)
+  BODY
+    tab-size: Number(8.0)
+    P
+      tab-size: Number(4.0)
+"
+        );
+    }
+}
@@ -0,0 +1,381 @@
+use std::collections::{HashMap, HashSet};
+
+use html5ever::LocalName;
+use selectors::parser::{Combinator, Component};
+
+use crate::dom::mutation::Mutation;
+use crate::dom::tree::NodeRef;
+use crate::style::presentational_hints::is_hint_attribute;
+use crate::style::select::{KosmonautSelectors, PseudoClass, Selector};
+use crate::style::stylesheet::Stylesheet;
+use crate::style::CssRule;
+
+/// The ids, classes, and attributes the selectors of a set of stylesheets depend on, keyed by
+/// name.  When an element's ids, classes, or attributes change, only the elements whose matched
+/// rules could have changed as a result are restyled, rather than the entire document.
+///
+/// This is a much simplified version of Servo's invalidation map:
+/// https://github.com/servo/servo/blob/d2856ce8aeca11e543bc4d9f869400d73451374e/components/style/invalidation/element/invalidation_map.rs
+#[derive(Debug, Default)]
+pub struct InvalidationMap {
+    ids: HashMap<LocalName, InvalidationScope>,
+    classes: HashMap<LocalName, InvalidationScope>,
+    attributes: HashMap<LocalName, InvalidationScope>,
+    /// Where selectors match the state of elements (e.g. `:checked`, `:disabled` or `:focus`).
+    states: InvalidationScope,
+    /// Whether any selector depends on the position of elements among their siblings, or on
+    /// whether they have children (e.g. `:first-child`, `:empty`, or `h1 + p`).  If so, changing
+    /// the children of a node restyles all of its descendants.
+    structural: bool,
+}
+
+/// The elements a change to some feature of an element (e.g. one of its classes) can affect the
+/// styles of, which depends on where in selectors that feature appears.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct InvalidationScope {
+    /// The element itself, for features in the rightmost compound selector (e.g. `.a` in `p.a`).
+    element: bool,
+    /// The element's descendants, for features left of a descendant or child combinator (e.g.
+    /// `.a` in `.a p`).
+    descendants: bool,
+    /// The element's later siblings and their descendants, for features left of a sibling
+    /// combinator (e.g. `.a` in `.a + p`).
+    later_siblings: bool,
+}
+
+impl InvalidationScope {
+    fn union(&mut self, other: InvalidationScope) {
+        self.element |= other.element;
+        self.descendants |= other.descendants;
+        self.later_siblings |= other.later_siblings;
+    }
+}
+
+impl InvalidationMap {
+    pub fn new<'a, I>(sheets: I) -> InvalidationMap
+    where
+        I: IntoIterator<Item = &'a Stylesheet>,
+    {
+        let mut map = InvalidationMap::default();
+        for sheet in sheets {
+            for rule in sheet.rules() {
+                if let CssRule::Style(style_rule) = rule {
+                    style_rule
+                        .selectors
+                        .0
+                        .iter()
+                        .for_each(|selector| map.add_selector(selector));
+                }
+            }
+        }
+        map
+    }
+
+    fn add_selector(&mut self, selector: &Selector) {
+        let mut scope = InvalidationScope {
+            element: true,
+            ..InvalidationScope::default()
+        };
+        let mut iter = selector.iter();
+        loop {
+            for component in &mut iter {
+                self.add_component(component, scope);
+            }
+            match iter.next_sequence() {
+                Some(Combinator::Child) | Some(Combinator::Descendant) => {
+                    scope = InvalidationScope {
+                        descendants: true,
+                        ..InvalidationScope::default()
+                    };
+                }
+                Some(Combinator::NextSibling) | Some(Combinator::LaterSibling) => {
+                    self.structural = true;
+                    scope = InvalidationScope {
+                        later_siblings: true,
+                        ..InvalidationScope::default()
+                    };
+                }
+                Some(_) => {}
+                None => break,
+            }
+        }
+    }
+
+    fn add_component(
+        &mut self,
+        component: &Component<KosmonautSelectors>,
+        scope: InvalidationScope,
+    ) {
+        match component {
+            Component::ID(id) => self.ids.entry(id.clone()).or_default().union(scope),
+            Component::Class(class) => self.classes.entry(class.clone()).or_default().union(scope),
+            Component::AttributeInNoNamespaceExists { local_name, .. }
+            | Component::AttributeInNoNamespace { local_name, .. } => self
+                .attributes
+                .entry(local_name.clone())
+                .or_default()
+                .union(scope),
+            Component::AttributeOther(attribute) => self
+                .attributes
+                .entry(attribute.local_name.clone())
+                .or_default()
+                .union(scope),
+            // Links are matched by whether they have an `href`.
+            Component::NonTSPseudoClass(PseudoClass::AnyLink)
+            | Component::NonTSPseudoClass(PseudoClass::Link) => self
+                .attributes
+                .entry(local_name!("href"))
+                .or_default()
+                .union(scope),
+            Component::NonTSPseudoClass(PseudoClass::Checked)
+            | Component::NonTSPseudoClass(PseudoClass::Focus)
+            | Component::NonTSPseudoClass(PseudoClass::FocusVisible)
+            | Component::NonTSPseudoClass(PseudoClass::Target)
+            | Component::NonTSPseudoClass(PseudoClass::Enabled)
+            | Component::NonTSPseudoClass(PseudoClass::Disabled) => self.states.union(scope),
+            Component::Negation(negated) => negated
+                .iter()
+                .for_each(|component| self.add_component(component, scope)),
+            Component::FirstChild
+            | Component::LastChild
+            | Component::OnlyChild
+            | Component::Root
+            | Component::Empty
+            | Component::NthChild(..)
+            | Component::NthLastChild(..)
+            | Component::NthOfType(..)
+            | Component::NthLastOfType(..)
+            | Component::FirstOfType
+            | Component::LastOfType
+            | Component::OnlyOfType => self.structural = true,
+            _ => {}
+        }
+    }
+
+    /// The nodes whose matched rules `mutations` could have changed, which includes any nodes
+    /// that were inserted (since they haven't been styled at all).  Changes to text never affect
+    /// which rules match unless selectors are structural, since only elements are matched.
+    pub fn invalidated_nodes(&self, mutations: &[Mutation]) -> HashSet<NodeRef> {
+        let mut invalidated = HashSet::new();
+        for mutation in mutations {
+            match mutation {
+                Mutation::Attribute {
+                    element,
+                    name,
+                    old_value,
+                } => {
+                    let new_value = element.as_element().and_then(|element| {
+                        element
+                            .attributes
+                            .borrow()
+                            .get(name.clone())
+                            .map(str::to_owned)
+                    });
+                    let old_value = old_value.as_deref();
+                    let new_value = new_value.as_deref();
+                    let mut scope = self.attributes.get(name).copied().unwrap_or_default();
+                    match *name {
+                        // Inline styles only ever apply to the element itself.
+                        local_name!("style") => scope.element = true,
+                        local_name!("id") => {
+                            for id in old_value.iter().chain(new_value.iter()) {
+                                if let Some(id_scope) = self.ids.get(&LocalName::from(*id)) {
+                                    scope.union(*id_scope);
+                                }
+                            }
+                        }
+                        local_name!("class") => {
+                            for class in changed_classes(old_value, new_value) {
+                                if let Some(class_scope) = self.classes.get(&LocalName::from(class))
+                                {
+                                    scope.union(*class_scope);
+                                }
+                            }
+                        }
+                        // So do presentational hints.
+                        _ if is_hint_attribute(name) => scope.element = true,
+                        // These attributes change the state of other elements too: the
+                        // `disabled` of a `<fieldset>` disables the controls in it, and selecting
+                        // an option deselects its siblings.  So the element's siblings and their
+                        // descendants are matched again, as well as the element itself.
+                        local_name!("checked")
+                        | local_name!("disabled")
+                        | local_name!("selected")
+                            if self.states != InvalidationScope::default() =>
+                        {
+                            if let Some(parent) = element.parent() {
+                                let siblings_scope = InvalidationScope {
+                                    descendants: true,
+                                    ..self.states
+                                };
+                                invalidate(&parent, siblings_scope, &mut invalidated);
+                            }
+                            scope.union(self.states);
+                        }
+                        _ => {}
+                    }
+                    invalidate(element, scope, &mut invalidated);
+                }
+                Mutation::State { element } => invalidate(element, self.states, &mut invalidated),
+                Mutation::ChildList { parent, added } => {
+                    if self.structural {
+                        invalidated.extend(parent.inclusive_descendants());
+                    }
+                    for node in added {
+                        invalidated.extend(node.inclusive_descendants());
+                    }
+                }
+                Mutation::CharacterData { node } => {
+                    // Text can make an element no longer `:empty`, and vice versa.
+                    if let (true, Some(parent)) = (self.structural, node.parent()) {
+                        invalidated.extend(parent.inclusive_descendants());
+                    }
+                }
+            }
+        }
+        invalidated
+    }
+}
+
+/// The classes that are in one of the given `class` attribute values but not the other.
+fn changed_classes<'a>(old_value: Option<&'a str>, new_value: Option<&'a str>) -> Vec<&'a str> {
+    let old_classes: HashSet<&str> = old_value
+        .unwrap_or_default()
+        .split_ascii_whitespace()
+        .collect();
+    let new_classes: HashSet<&str> = new_value
+        .unwrap_or_default()
+        .split_ascii_whitespace()
+        .collect();
+    old_classes
+        .symmetric_difference(&new_classes)
+        .copied()
+        .collect()
+}
+
+fn invalidate(element: &NodeRef, scope: InvalidationScope, invalidated: &mut HashSet<NodeRef>) {
+    if scope.element {
+        invalidated.insert(element.clone());
+    }
+    if scope.descendants {
+        invalidated.extend(element.descendants());
+    }
+    if scope.later_siblings {
+        for sibling in element.following_siblings() {
+            invalidated.extend(sibling.inclusive_descendants());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::parser::parse_html;
+    use crate::dom::traits::*;
+    use crate::style::stylesheet::parse_css_to_stylesheet;
+
+    fn invalidation_map(css: &str) -> InvalidationMap {
+        let sheet = parse_css_to_stylesheet(None, &mut css.to_owned());
+        InvalidationMap::new(&[sheet])
+    }
+
+    fn set_attribute(element: &NodeRef, name: LocalName, value: &str) -> Mutation {
+        let old_value = element
+            .as_element()
+            .unwrap()
+            .attributes
+            .borrow_mut()
+            .insert(name.clone(), value.to_owned())
+            .map(|attribute| attribute.value);
+        Mutation::Attribute {
+            element: element.clone(),
+            name,
+            old_value,
+        }
+    }
+
+    fn ids(nodes: &HashSet<NodeRef>) -> Vec<String> {
+        let mut ids: Vec<String> = nodes
+            .iter()
+            .filter_map(|node| {
+                let element = node.as_element()?;
+                let id = element
+                    .attributes
+                    .borrow()
+                    .get(local_name!("id"))?
+                    .to_owned();
+                Some(id)
+            })
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn class_changes_only_invalidate_dependent_elements() {
+        let map = invalidation_map(".on { width: 1px; } .open > div { width: 2px; }");
+        let document = parse_html().one(
+            "<div id=a class=x><div id=b></div></div>\
+             <div id=c></div>",
+        );
+        let a = document.select_first("#a").unwrap().as_node().clone();
+        // No rules depend on `y`.
+        let mutation = set_attribute(&a, local_name!("class"), "x y");
+        assert!(ids(&map.invalidated_nodes(&[mutation])).is_empty());
+        // `.on` styles the element itself.
+        let mutation = set_attribute(&a, local_name!("class"), "on");
+        assert_eq!(ids(&map.invalidated_nodes(&[mutation])), vec!["a"]);
+        // `.open` styles the element's descendants.
+        let mutation = set_attribute(&a, local_name!("class"), "on open");
+        assert_eq!(ids(&map.invalidated_nodes(&[mutation])), vec!["b"]);
+    }
+
+    #[test]
+    fn structural_selectors_invalidate_siblings() {
+        let document = parse_html().one("<p id=a></p><p id=b></p><p id=c></p>");
+        let b = document.select_first("#b").unwrap().as_node().clone();
+        let map = invalidation_map("#b + p { width: 1px; }");
+        let mutation = set_attribute(&b, local_name!("id"), "renamed");
+        assert_eq!(ids(&map.invalidated_nodes(&[mutation])), vec!["c"]);
+
+        // Inserting a child restyles all of the parent's descendants when selectors depend on
+        // sibling position, but only the inserted node otherwise.
+        let body = b.parent().unwrap();
+        let inserted = NodeRef::new_text("text");
+        body.append(inserted.clone());
+        let mutation = Mutation::ChildList {
+            parent: body.clone(),
+            added: vec![inserted.clone()],
+        };
+        assert_eq!(
+            map.invalidated_nodes(&[mutation.clone()]).len(),
+            body.inclusive_descendants().count()
+        );
+        let map = invalidation_map("p { width: 1px; }");
+        assert_eq!(
+            map.invalidated_nodes(&[mutation])
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec![inserted]
+        );
+    }
+
+    #[test]
+    fn state_changes_invalidate_elements_matching_state() {
+        let document =
+            parse_html().one("<input id=a type=checkbox><label id=b></label><p id=c></p>");
+        let a = document.select_first("#a").unwrap().as_node().clone();
+        let mutation = Mutation::State { element: a.clone() };
+        let map = invalidation_map("p { width: 1px; }");
+        assert!(ids(&map.invalidated_nodes(&[mutation.clone()])).is_empty());
+        let map = invalidation_map(":checked + label { width: 1px; }");
+        assert_eq!(ids(&map.invalidated_nodes(&[mutation])), vec!["b", "c"]);
+        // The `disabled` attribute can change the state of other elements.
+        let map = invalidation_map(":disabled { width: 1px; }");
+        let mutation = set_attribute(&a, local_name!("disabled"), "");
+        assert_eq!(
+            ids(&map.invalidated_nodes(&[mutation])),
+            vec!["a", "b", "c"]
+        );
+    }
+}
@@ -0,0 +1,575 @@
+//! Media queries, which make the rules of `@media` rules apply only to certain devices.
+//!
+//! https://drafts.csswg.org/mediaqueries-4/
+
+use cssparser::{Delimiter, ParseError, Parser, Token};
+
+/// Relative lengths in media queries are resolved against the initial value of `font-size` rather
+/// than any element's font size.
+///
+/// https://drafts.csswg.org/mediaqueries/#units
+const INITIAL_FONT_SIZE_PX: f32 = 16.;
+
+/// The media types a document can be rendered for.
+///
+/// https://drafts.csswg.org/mediaqueries-4/#media-types
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MediaType {
+    Print,
+    Screen,
+}
+
+/// The color schemes a user can prefer pages to be rendered in.
+///
+/// https://drafts.csswg.org/mediaqueries-5/#prefers-color-scheme
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorScheme {
+    Light,
+    Dark,
+}
+
+/// Whether the user prefers pages to minimize non-essential motion.
+///
+/// https://drafts.csswg.org/mediaqueries-5/#prefers-reduced-motion
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MotionPreference {
+    NoPreference,
+    Reduce,
+}
+
+/// The contrast between colors the user prefers.
+///
+/// https://drafts.csswg.org/mediaqueries-5/#prefers-contrast
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ContrastPreference {
+    NoPreference,
+    More,
+    Less,
+    /// A particular set of colors that is neither more nor less contrasting (e.g. forced colors).
+    Custom,
+}
+
+/// Whether the user has made the OS force a limited palette of colors on pages (e.g. Windows'
+/// high contrast themes).
+///
+/// TODO: Pages' colors aren't forced yet, so pages only know forced colors are active through
+/// this media feature.
+///
+/// https://drafts.csswg.org/mediaqueries-5/#forced-colors
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ForcedColors {
+    None,
+    Active,
+}
+
+/// The device a document is rendered on, which media queries are evaluated against.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Device {
+    media_type: MediaType,
+    /// The size of the viewport in CSS pixels, which changes as the page is zoomed.
+    viewport_width: f32,
+    viewport_height: f32,
+    /// The number of device pixels per CSS pixel.
+    resolution: f32,
+    /// The color scheme the user prefers.
+    color_scheme: ColorScheme,
+    motion_preference: MotionPreference,
+    contrast_preference: ContrastPreference,
+    forced_colors: ForcedColors,
+}
+
+impl Device {
+    /// Creates a device with a resolution of one device pixel per CSS pixel, whose user prefers
+    /// the light color scheme, and has no other preferences.
+    pub fn new(media_type: MediaType, viewport_width: f32, viewport_height: f32) -> Device {
+        Device {
+            media_type,
+            viewport_width,
+            viewport_height,
+            resolution: 1.,
+            color_scheme: ColorScheme::Light,
+            motion_preference: MotionPreference::NoPreference,
+            contrast_preference: ContrastPreference::NoPreference,
+            forced_colors: ForcedColors::None,
+        }
+    }
+
+    pub fn media_type(&self) -> MediaType {
+        self.media_type
+    }
+
+    pub fn set_media_type(&mut self, media_type: MediaType) {
+        self.media_type = media_type;
+    }
+
+    pub fn resolution(&self) -> f32 {
+        self.resolution
+    }
+
+    pub fn set_resolution(&mut self, resolution: f32) {
+        self.resolution = resolution;
+    }
+
+    pub fn color_scheme(&self) -> ColorScheme {
+        self.color_scheme
+    }
+
+    pub fn set_color_scheme(&mut self, color_scheme: ColorScheme) {
+        self.color_scheme = color_scheme;
+    }
+
+    pub fn motion_preference(&self) -> MotionPreference {
+        self.motion_preference
+    }
+
+    pub fn set_motion_preference(&mut self, motion_preference: MotionPreference) {
+        self.motion_preference = motion_preference;
+    }
+
+    pub fn contrast_preference(&self) -> ContrastPreference {
+        self.contrast_preference
+    }
+
+    pub fn set_contrast_preference(&mut self, contrast_preference: ContrastPreference) {
+        self.contrast_preference = contrast_preference;
+    }
+
+    pub fn forced_colors(&self) -> ForcedColors {
+        self.forced_colors
+    }
+
+    pub fn set_forced_colors(&mut self, forced_colors: ForcedColors) {
+        self.forced_colors = forced_colors;
+    }
+
+    pub fn viewport_width(&self) -> f32 {
+        self.viewport_width
+    }
+
+    pub fn viewport_height(&self) -> f32 {
+        self.viewport_height
+    }
+}
+
+/// A comma-separated list of media queries, which matches a device if any of its queries do.
+///
+/// https://drafts.csswg.org/mediaqueries-4/#media-query-list
+#[derive(Clone, Debug, PartialEq)]
+pub struct MediaList(Vec<MediaQuery>);
+
+impl MediaList {
+    /// Parses a media query list.  Invalid queries are replaced by `not all` rather than making
+    /// the whole list invalid.
+    ///
+    /// https://drafts.csswg.org/mediaqueries-4/#error-handling
+    pub fn parse(input: &mut Parser) -> MediaList {
+        let mut queries = Vec::new();
+        if input.is_exhausted() {
+            return MediaList(queries);
+        }
+        loop {
+            queries.push(
+                input
+                    .parse_until_before(Delimiter::Comma, MediaQuery::parse)
+                    .unwrap_or(MediaQuery::NOT_ALL),
+            );
+            // Either consume the comma separating this query from the next, or we're at the end.
+            if input.next().is_err() {
+                return MediaList(queries);
+            }
+        }
+    }
+
+    /// Whether this list matches `device`.  An empty list matches every device.
+    pub fn matches(&self, device: &Device) -> bool {
+        self.0.is_empty() || self.0.iter().any(|query| query.matches(device))
+    }
+}
+
+/// https://drafts.csswg.org/mediaqueries-4/#media-query
+#[derive(Clone, Debug, PartialEq)]
+struct MediaQuery {
+    qualifier: Option<Qualifier>,
+    media_type: MediaQueryType,
+    /// The media features combined with `and`, all of which must match.
+    features: Vec<MediaFeature>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Qualifier {
+    Not,
+    Only,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MediaQueryType {
+    All,
+    Known(MediaType),
+    /// A media type that's unknown, or deprecated (e.g. `tv`), neither of which match any device.
+    Other,
+}
+
+/// The media features that can be queried.  Those with a `Range` can be prefixed with `min-` or
+/// `max-`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MediaFeature {
+    /// https://drafts.csswg.org/mediaqueries-4/#mf-dimensions
+    Width(Range, f32),
+    Height(Range, f32),
+    /// The resolution, in device pixels per CSS pixel (`dppx`).
+    ///
+    /// https://drafts.csswg.org/mediaqueries-4/#resolution
+    Resolution(Range, f32),
+    /// https://drafts.csswg.org/mediaqueries-5/#prefers-color-scheme
+    PrefersColorScheme(ColorScheme),
+    /// The preferences below can also be queried without a value (e.g. `(forced-colors)`), which
+    /// matches unless the user has no preference.  Such queries are parsed as `None`.
+    PrefersReducedMotion(Option<MotionPreference>),
+    PrefersContrast(Option<ContrastPreference>),
+    ForcedColors(Option<ForcedColors>),
+}
+
+/// How a queried value compares to the value of a feature with a range (e.g. `min-width` matches
+/// widths of at least the queried width).  Container queries share these with media queries.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Range {
+    Min,
+    Max,
+    Exact,
+}
+
+impl Range {
+    /// Whether `value` is in this range of `queried`.
+    pub(crate) fn matches(self, value: f32, queried: f32) -> bool {
+        match self {
+            Range::Min => value >= queried,
+            Range::Max => value <= queried,
+            Range::Exact => (value - queried).abs() < f32::EPSILON,
+        }
+    }
+}
+
+impl MediaFeature {
+    fn parse<'i, 't>(input: &mut Parser<'i, 't>) -> Result<Self, ParseError<'i, ()>> {
+        input.expect_parenthesis_block()?;
+        input.parse_nested_block(|input| {
+            let location = input.current_source_location();
+            let name = input.expect_ident()?.clone();
+            if input.is_exhausted() {
+                return match_ignore_ascii_case! { &name,
+                    "prefers-reduced-motion" => Ok(MediaFeature::PrefersReducedMotion(None)),
+                    "prefers-contrast" => Ok(MediaFeature::PrefersContrast(None)),
+                    "forced-colors" => Ok(MediaFeature::ForcedColors(None)),
+                    _ => Err(location.new_custom_error(())),
+                };
+            }
+            input.expect_colon()?;
+            match_ignore_ascii_case! { &name,
+                "width" => Ok(MediaFeature::Width(Range::Exact, parse_length_px(input)?)),
+                "min-width" => Ok(MediaFeature::Width(Range::Min, parse_length_px(input)?)),
+                "max-width" => Ok(MediaFeature::Width(Range::Max, parse_length_px(input)?)),
+                "height" => Ok(MediaFeature::Height(Range::Exact, parse_length_px(input)?)),
+                "min-height" => Ok(MediaFeature::Height(Range::Min, parse_length_px(input)?)),
+                "max-height" => Ok(MediaFeature::Height(Range::Max, parse_length_px(input)?)),
+                "resolution" => {
+                    Ok(MediaFeature::Resolution(Range::Exact, parse_resolution_dppx(input)?))
+                },
+                "min-resolution" => {
+                    Ok(MediaFeature::Resolution(Range::Min, parse_resolution_dppx(input)?))
+                },
+                "max-resolution" => {
+                    Ok(MediaFeature::Resolution(Range::Max, parse_resolution_dppx(input)?))
+                },
+                "prefers-color-scheme" => {
+                    let location = input.current_source_location();
+                    let color_scheme = match_ignore_ascii_case! { &input.expect_ident()?.clone(),
+                        "light" => ColorScheme::Light,
+                        "dark" => ColorScheme::Dark,
+                        _ => return Err(location.new_custom_error(())),
+                    };
+                    Ok(MediaFeature::PrefersColorScheme(color_scheme))
+                },
+                "prefers-reduced-motion" => {
+                    let location = input.current_source_location();
+                    let value = input.expect_ident()?.clone();
+                    let motion_preference = match_ignore_ascii_case! { &value,
+                        "no-preference" => MotionPreference::NoPreference,
+                        "reduce" => MotionPreference::Reduce,
+                        _ => return Err(location.new_custom_error(())),
+                    };
+                    Ok(MediaFeature::PrefersReducedMotion(Some(motion_preference)))
+                },
+                "prefers-contrast" => {
+                    let location = input.current_source_location();
+                    let value = input.expect_ident()?.clone();
+                    let contrast_preference = match_ignore_ascii_case! { &value,
+                        "no-preference" => ContrastPreference::NoPreference,
+                        "more" => ContrastPreference::More,
+                        "less" => ContrastPreference::Less,
+                        "custom" => ContrastPreference::Custom,
+                        _ => return Err(location.new_custom_error(())),
+                    };
+                    Ok(MediaFeature::PrefersContrast(Some(contrast_preference)))
+                },
+                "forced-colors" => {
+                    let location = input.current_source_location();
+                    let forced_colors = match_ignore_ascii_case! { &input.expect_ident()?.clone(),
+                        "none" => ForcedColors::None,
+                        "active" => ForcedColors::Active,
+                        _ => return Err(location.new_custom_error(())),
+                    };
+                    Ok(MediaFeature::ForcedColors(Some(forced_colors)))
+                },
+                _ => Err(location.new_custom_error(())),
+            }
+        })
+    }
+
+    fn matches(self, device: &Device) -> bool {
+        let (range, value, queried) = match self {
+            MediaFeature::Width(range, px) => (range, device.viewport_width, px),
+            MediaFeature::Height(range, px) => (range, device.viewport_height, px),
+            MediaFeature::Resolution(range, dppx) => (range, device.resolution, dppx),
+            MediaFeature::PrefersColorScheme(color_scheme) => {
+                return color_scheme == device.color_scheme
+            }
+            MediaFeature::PrefersReducedMotion(motion_preference) => {
+                return motion_preference.map_or(
+                    device.motion_preference != MotionPreference::NoPreference,
+                    |motion_preference| motion_preference == device.motion_preference,
+                )
+            }
+            MediaFeature::PrefersContrast(contrast_preference) => {
+                return contrast_preference.map_or(
+                    device.contrast_preference != ContrastPreference::NoPreference,
+                    |contrast_preference| contrast_preference == device.contrast_preference,
+                )
+            }
+            MediaFeature::ForcedColors(forced_colors) => {
+                return forced_colors.map_or(
+                    device.forced_colors != ForcedColors::None,
+                    |forced_colors| forced_colors == device.forced_colors,
+                )
+            }
+        };
+        range.matches(value, queried)
+    }
+}
+
+pub(crate) fn parse_length_px<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<f32, ParseError<'i, ()>> {
+    let location = input.current_source_location();
+    match input.next()?.clone() {
+        Token::Dimension {
+            value, ref unit, ..
+        } => match_ignore_ascii_case! { &**unit,
+            "px" => Ok(value),
+            "em" | "rem" => Ok(value * INITIAL_FONT_SIZE_PX),
+            _ => Err(location.new_custom_error(())),
+        },
+        Token::Number { value, .. } if value == 0. => Ok(0.),
+        _ => Err(location.new_custom_error(())),
+    }
+}
+
+/// Parses a resolution, returning it in device pixels per CSS pixel.
+///
+/// https://drafts.csswg.org/css-values-4/#resolution
+fn parse_resolution_dppx<'i, 't>(input: &mut Parser<'i, 't>) -> Result<f32, ParseError<'i, ()>> {
+    let location = input.current_source_location();
+    match input.next()?.clone() {
+        Token::Dimension {
+            value, ref unit, ..
+        } => match_ignore_ascii_case! { &**unit,
+            "dppx" | "x" => Ok(value),
+            // There are 96 CSS pixels per inch, and 2.54 centimeters per inch.
+            "dpi" => Ok(value / 96.),
+            "dpcm" => Ok(value * 2.54 / 96.),
+            _ => Err(location.new_custom_error(())),
+        },
+        _ => Err(location.new_custom_error(())),
+    }
+}
+
+impl MediaQuery {
+    /// The query that invalid queries are replaced by, which matches no device.
+    const NOT_ALL: MediaQuery = MediaQuery {
+        qualifier: Some(Qualifier::Not),
+        media_type: MediaQueryType::All,
+        features: Vec::new(),
+    };
+
+    /// Parses a media query: either a media type (with an optional `not` or `only`) followed by
+    /// any number of `and (<media feature>)`s, or just media features combined with `and`.
+    ///
+    /// TODO: Support `or`, and `not` before media features.  For now, queries using them fail to
+    /// parse, which makes them `not all`.
+    fn parse<'i, 't>(input: &mut Parser<'i, 't>) -> Result<Self, ParseError<'i, ()>> {
+        if let Ok(feature) = input.try_parse(MediaFeature::parse) {
+            let mut features = vec![feature];
+            while input
+                .try_parse(|input| input.expect_ident_matching("and"))
+                .is_ok()
+            {
+                features.push(MediaFeature::parse(input)?);
+            }
+            input.expect_exhausted()?;
+            return Ok(MediaQuery {
+                qualifier: None,
+                media_type: MediaQueryType::All,
+                features,
+            });
+        }
+        let location = input.current_source_location();
+        let ident = input.expect_ident()?.clone();
+        let (qualifier, media_type) = match_ignore_ascii_case! { &ident,
+            "not" => (Some(Qualifier::Not), input.expect_ident()?.clone()),
+            "only" => (Some(Qualifier::Only), input.expect_ident()?.clone()),
+            _ => (None, ident.clone()),
+        };
+        let media_type = match_ignore_ascii_case! { &media_type,
+            "all" => MediaQueryType::All,
+            "print" => MediaQueryType::Known(MediaType::Print),
+            "screen" => MediaQueryType::Known(MediaType::Screen),
+            // These keywords can't be used as media types.
+            "not" | "only" | "and" | "or" | "layer" => return Err(location.new_custom_error(())),
+            _ => MediaQueryType::Other,
+        };
+        let mut features = Vec::new();
+        while input
+            .try_parse(|input| input.expect_ident_matching("and"))
+            .is_ok()
+        {
+            features.push(MediaFeature::parse(input)?);
+        }
+        input.expect_exhausted()?;
+        Ok(MediaQuery {
+            qualifier,
+            media_type,
+            features,
+        })
+    }
+
+    fn matches(&self, device: &Device) -> bool {
+        let media_type_matches = match self.media_type {
+            MediaQueryType::All => true,
+            MediaQueryType::Known(media_type) => media_type == device.media_type(),
+            MediaQueryType::Other => false,
+        };
+        let query_matches =
+            media_type_matches && self.features.iter().all(|feature| feature.matches(device));
+        match self.qualifier {
+            Some(Qualifier::Not) => !query_matches,
+            Some(Qualifier::Only) | None => query_matches,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cssparser::ParserInput;
+
+    fn matches(media_list: &str, media_type: MediaType) -> bool {
+        matches_viewport(media_list, media_type, 800., 600.)
+    }
+
+    fn matches_viewport(media_list: &str, media_type: MediaType, width: f32, height: f32) -> bool {
+        let mut input = ParserInput::new(media_list);
+        MediaList::parse(&mut Parser::new(&mut input))
+            .matches(&Device::new(media_type, width, height))
+    }
+
+    #[test]
+    fn matches_media_types() {
+        assert!(matches("print", MediaType::Print));
+        assert!(!matches("print", MediaType::Screen));
+        assert!(matches("only SCREEN", MediaType::Screen));
+        assert!(matches("not print", MediaType::Screen));
+        assert!(matches("all", MediaType::Print));
+        assert!(matches("", MediaType::Print));
+        assert!(!matches("tv", MediaType::Screen));
+    }
+
+    #[test]
+    fn matches_viewport_sizes() {
+        let screen = MediaType::Screen;
+        assert!(matches_viewport(
+            "screen and (min-width: 600px)",
+            screen,
+            800.,
+            600.
+        ));
+        assert!(!matches_viewport(
+            "screen and (min-width: 600px)",
+            screen,
+            500.,
+            600.
+        ));
+        assert!(matches_viewport(
+            "(max-width: 40em) and (height: 600px)",
+            screen,
+            640.,
+            600.
+        ));
+        assert!(!matches_viewport("(max-width: 40em)", screen, 641., 600.));
+        assert!(matches_viewport(
+            "not print and (min-height: 1000px)",
+            screen,
+            800.,
+            600.
+        ));
+    }
+
+    #[test]
+    fn matches_resolutions_and_color_schemes() {
+        let parse = |media_list| {
+            let mut input = ParserInput::new(media_list);
+            MediaList::parse(&mut Parser::new(&mut input))
+        };
+        let mut device = Device::new(MediaType::Screen, 800., 600.);
+        assert!(parse("(resolution: 1x)").matches(&device));
+        assert!(!parse("(min-resolution: 2dppx)").matches(&device));
+        assert!(parse("(prefers-color-scheme: light)").matches(&device));
+        device.set_resolution(2.);
+        device.set_color_scheme(ColorScheme::Dark);
+        assert!(parse("(min-resolution: 192dpi)").matches(&device));
+        assert!(!parse("(max-resolution: 1.5x)").matches(&device));
+        assert!(parse("screen and (prefers-color-scheme: DARK)").matches(&device));
+        assert!(!parse("(prefers-color-scheme: light)").matches(&device));
+        assert!(!parse("(prefers-color-scheme: sepia)").matches(&device));
+    }
+
+    #[test]
+    fn matches_accessibility_preferences() {
+        let parse = |media_list| {
+            let mut input = ParserInput::new(media_list);
+            MediaList::parse(&mut Parser::new(&mut input))
+        };
+        let mut device = Device::new(MediaType::Screen, 800., 600.);
+        assert!(parse("(prefers-reduced-motion: no-preference)").matches(&device));
+        assert!(!parse("(prefers-reduced-motion)").matches(&device));
+        assert!(!parse("(prefers-contrast)").matches(&device));
+        assert!(parse("(forced-colors: none)").matches(&device));
+        device.set_motion_preference(MotionPreference::Reduce);
+        device.set_contrast_preference(ContrastPreference::Less);
+        device.set_forced_colors(ForcedColors::Active);
+        assert!(parse("(prefers-reduced-motion: reduce)").matches(&device));
+        assert!(parse("(prefers-reduced-motion)").matches(&device));
+        assert!(parse("(prefers-contrast) and (prefers-contrast: less)").matches(&device));
+        assert!(!parse("(prefers-contrast: more)").matches(&device));
+        assert!(parse("(forced-colors)").matches(&device));
+        assert!(!parse("(forced-colors: inactive)").matches(&device));
+    }
+
+    #[test]
+    fn invalid_queries_only_invalidate_themselves() {
+        assert!(!matches(
+            "screen and (orientation: portrait)",
+            MediaType::Screen
+        ));
+        assert!(matches("not and, print", MediaType::Print));
+        assert!(!matches("not and, print", MediaType::Screen));
+    }
+}
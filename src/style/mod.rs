@@ -1,66 +1,441 @@
+use std::collections::HashSet;
 use std::convert::From;
+use std::rc::Rc;
 
 use cssparser::{
-    AtRuleParser, CowRcStr, ParseError, Parser, QualifiedRuleParser, SourceLocation, Token,
+    AtRuleParser, AtRuleType, CowRcStr, ParseError, Parser, ParserInput, QualifiedRuleParser,
+    RuleListParser, SourceLocation, Token,
 };
 use selectors::parser::SelectorParseErrorKind;
+use tracing::info_span;
 
-use crate::dom::tree::{NodeData, NodeRef};
-use crate::style::properties::{parse_property_declaration_list, PropertyDeclarationBlock};
-use crate::style::select::Selectors;
-use crate::style::stylesheet::{apply_stylesheet_to_node, Stylesheet};
+use crate::dom::iter::NodeEdge;
+use crate::dom::mutation::{self, Mutation};
+use crate::dom::tree::{ElementData, NodeData, NodeRef};
+use crate::style::container::ContainerCondition;
+use crate::style::diagnostics::CssDiagnostic;
+use crate::style::invalidation::InvalidationMap;
+use crate::style::media::{Device, MediaList};
+use crate::style::presentational_hints::presentational_hints;
+use crate::style::properties::{
+    parse_property_declaration_list, parse_property_declaration_list_with_diagnostics,
+    ContextualPropertyDeclaration, PropertyDeclarationBlock,
+};
+use crate::style::rule_tree::{ApplicableDeclarationBlock, RuleTree, StyleSource};
+use crate::style::select::{SelectorScope, Selectors, Specificity};
+use crate::style::stylesheet::{
+    apply_scoped_stylesheet_to_node, apply_stylesheet_to_element, apply_stylesheet_to_node,
+    parse_css_to_stylesheet, Stylesheet,
+};
 use crate::style::values::computed::compute_values;
 
 #[macro_use]
 mod macros;
 
+pub mod container;
+pub mod diagnostics;
+pub mod dump;
+pub mod invalidation;
+pub mod media;
+pub mod presentational_hints;
 pub mod properties;
+pub mod rule_tree;
 pub mod select;
+pub mod selector_map;
 pub mod stylesheet;
 pub mod test_utils;
 pub mod values;
 
 pub fn apply_styles(
     dom: NodeRef,
+    device: &Device,
     ua_sheets: &[Stylesheet],
     user_sheets: &[Stylesheet],
     author_sheets: &[Stylesheet],
 ) {
+    let _span = info_span!("apply_styles").entered();
     // https://www.w3.org/TR/css-cascade-3/#value-stages
     // The final value of a CSS property for a given element or box is the result of a multi-step calculation:
 
     // 1. First, all the declared values applied to an element are collected, for each property on each element. There may be zero or many declared values applied to the element.
-    // TODO: Need to collect embedded styles (<style></style>)
+    // TODO: Need to collect embedded styles (<style></style>) of the document
+    let shadow_roots = dom
+        .shadow_including_inclusive_descendants()
+        .filter(|node| node.as_shadow_root().is_some())
+        .map(|shadow_root| {
+            let sheets = shadow_tree_stylesheets(&shadow_root);
+            (shadow_root, sheets)
+        })
+        .collect::<Vec<_>>();
+    // User agent styles apply to every tree alike.
     ua_sheets.iter().for_each(|stylesheet| {
-        apply_stylesheet_to_node(&dom, stylesheet, CascadeOrigin::UserAgent);
+        apply_stylesheet_to_node(&dom, stylesheet, CascadeOrigin::UserAgent, device);
+        for (shadow_root, _) in &shadow_roots {
+            apply_stylesheet_to_node(shadow_root, stylesheet, CascadeOrigin::UserAgent, device);
+        }
     });
 
-    user_sheets.iter().for_each(|stylesheet| {
-        apply_stylesheet_to_node(&dom, stylesheet, CascadeOrigin::User);
-    });
+    apply_stylesheets_to_tree(&dom, user_sheets, CascadeOrigin::User, device);
 
-    author_sheets.iter().for_each(|stylesheet| {
-        apply_stylesheet_to_node(&dom, stylesheet, CascadeOrigin::Author);
-    });
+    // The normal declarations of an outer tree win over those of an inner one, and declarations
+    // that are applied later win ties, so the rules of shadow trees are applied innermost first,
+    // starting with their `:host` rules, and those of the document last.
+    //
+    // TODO: Outer trees should win over inner ones no matter the specificity of their selectors,
+    // and important declarations of inner trees should win over those of outer ones.
+    // https://drafts.csswg.org/css-cascade-4/#cascade-context
+    for (shadow_root, sheets) in &shadow_roots {
+        if let Some(host) = shadow_root.parent().and_then(NodeRef::into_element_ref) {
+            sheets.iter().for_each(|stylesheet| {
+                apply_stylesheet_to_element(
+                    &host,
+                    stylesheet,
+                    CascadeOrigin::Author,
+                    device,
+                    SelectorScope::Host,
+                );
+            });
+        }
+    }
+    for (shadow_root, sheets) in shadow_roots.iter().rev() {
+        apply_stylesheets_to_tree(shadow_root, sheets, CascadeOrigin::Author, device);
+    }
+    apply_stylesheets_to_tree(&dom, author_sheets, CascadeOrigin::Author, device);
 
     // collect all inline styles
-    dom.inclusive_descendants().for_each(|node| {
-        if let NodeData::Element(element_data) = node.data() {
-            match element_data.attributes.try_borrow() {
-                Ok(attrs) => {
-                    if let Some(style_str) = attrs.get("style") {
-                        // TODO: Parse inline style and apply it to node.  Make sure these styles have a greater specificity than anything else
-                        // cssparser::ParserInput::new(style_str)
-                        dbg!("found inline style but did not collect it: {:?}", style_str);
+    dom.shadow_including_inclusive_descendants()
+        .for_each(|node| {
+            if let NodeData::Element(element_data) = node.data() {
+                apply_presentational_hints_to_node(&node, element_data);
+                match element_data.attributes.try_borrow() {
+                    Ok(attrs) => {
+                        if let Some(style_str) = attrs.get("style") {
+                            apply_inline_style_to_node(&node, style_str);
+                        }
+                    }
+                    Err(_e) => {
+                        dbg!("couldn't borrow node attributes");
                     }
-                }
-                Err(_e) => {
-                    dbg!("couldn't borrow node attributes");
                 }
             }
+        });
+    cascade_and_compute(&dom, device);
+    // Every node has just been styled, so earlier mutations don't need restyling.
+    mutation::take_mutations(&dom);
+}
+
+/// Applies `sheets`, which are in the tree `tree` is the root of (a document or shadow root), to
+/// the elements of that tree, and through `::part()` to the elements of the shadow trees of its
+/// shadow hosts.
+///
+/// https://drafts.csswg.org/css-scoping/#shadow-cascading
+fn apply_stylesheets_to_tree(
+    tree: &NodeRef,
+    sheets: &[Stylesheet],
+    origin: CascadeOrigin,
+    device: &Device,
+) {
+    let shadow_roots = tree.shadow_roots();
+    sheets.iter().for_each(|stylesheet| {
+        apply_stylesheet_to_node(tree, stylesheet, origin.clone(), device);
+        for shadow_root in &shadow_roots {
+            apply_scoped_stylesheet_to_node(
+                shadow_root,
+                stylesheet,
+                origin.clone(),
+                device,
+                SelectorScope::Parts,
+            );
         }
     });
-    cascade_and_compute(&dom);
+}
+
+/// The stylesheets of the `<style>` elements in the shadow tree of `shadow_root`, in tree order.
+/// They only apply to the elements of that tree, and to its host through `:host`.
+///
+/// https://html.spec.whatwg.org/multipage/semantics.html#update-a-style-block
+pub fn shadow_tree_stylesheets(shadow_root: &NodeRef) -> Vec<Stylesheet> {
+    shadow_root
+        .descendants()
+        .filter(|node| {
+            node.as_element().map_or(false, |element| {
+                element.name.ns == ns!(html) && element.name.local == local_name!("style")
+            })
+        })
+        .map(|style| parse_css_to_stylesheet(None, &mut style.text_contents()))
+        .collect()
+}
+
+/// What restyling a document after DOM mutations changed.
+#[derive(Debug, Default)]
+pub struct Restyle {
+    /// The nodes whose computed values were recomputed.
+    pub restyled_nodes: HashSet<NodeRef>,
+    /// The restyled nodes whose `display` changed, which changes the boxes they generate.
+    pub display_changed_nodes: HashSet<NodeRef>,
+}
+
+/// Restyles the nodes of `document` whose styles could have been changed by `mutations`, the DOM
+/// mutations recorded since styles were last applied.
+///
+/// Invalidated nodes have their rules matched anew, and have their values recomputed if that gave
+/// them a different rule node.  Since computed values inherit, the children of a node whose
+/// computed values changed have their values recomputed too.
+pub fn restyle_mutations(
+    document: &NodeRef,
+    mutations: &[Mutation],
+    invalidation_map: &InvalidationMap,
+    device: &Device,
+    ua_sheets: &[Stylesheet],
+    user_sheets: &[Stylesheet],
+    author_sheets: &[Stylesheet],
+) -> Restyle {
+    let _span = info_span!("restyle_mutations").entered();
+    if mutations.is_empty() {
+        return Restyle::default();
+    }
+    let invalidated = invalidation_map.invalidated_nodes(mutations);
+    restyle_invalidated_nodes(
+        document,
+        &invalidated,
+        device,
+        ua_sheets,
+        user_sheets,
+        author_sheets,
+    )
+}
+
+/// Restyles the descendants of `changed_containers`, the query containers of `document` that were
+/// resized (or became or stopped being query containers) when it was last laid out, since that
+/// can change which `@container` rules apply to them.  The same nodes are restyled the same way
+/// as by `restyle_mutations`.
+pub fn restyle_query_containers(
+    document: &NodeRef,
+    changed_containers: &HashSet<NodeRef>,
+    device: &Device,
+    ua_sheets: &[Stylesheet],
+    user_sheets: &[Stylesheet],
+    author_sheets: &[Stylesheet],
+) -> Restyle {
+    let _span = info_span!("restyle_query_containers").entered();
+    let invalidated = changed_containers
+        .iter()
+        .flat_map(|container| container.shadow_including_inclusive_descendants().skip(1))
+        .collect();
+    restyle_invalidated_nodes(
+        document,
+        &invalidated,
+        device,
+        ua_sheets,
+        user_sheets,
+        author_sheets,
+    )
+}
+
+/// Matches the rules of the `invalidated` nodes of `document` anew, recomputing the values of
+/// those whose rule node changed, and of the descendants of nodes whose values changed.
+fn restyle_invalidated_nodes(
+    document: &NodeRef,
+    invalidated: &HashSet<NodeRef>,
+    device: &Device,
+    ua_sheets: &[Stylesheet],
+    user_sheets: &[Stylesheet],
+    author_sheets: &[Stylesheet],
+) -> Restyle {
+    let mut restyle = Restyle::default();
+    let rule_tree = rule_tree_of(document);
+    // Whether the computed values of each open ancestor changed, so that its children need their
+    // values recomputed.
+    let mut changed_stack = vec![false];
+    for edge in document.shadow_including_traverse_inclusive() {
+        match edge {
+            NodeEdge::Start(node) => {
+                let old_rule_node = node.rule_node();
+                let mut recompute = *changed_stack.last().unwrap();
+                if invalidated.contains(&node) {
+                    rematch_node(&node, device, ua_sheets, user_sheets, author_sheets);
+                    cascade(&node, &rule_tree);
+                    recompute |= match (&old_rule_node, node.rule_node()) {
+                        (Some(old), Some(new)) => !Rc::ptr_eq(old, &new),
+                        _ => true,
+                    };
+                }
+                let mut changed = false;
+                if recompute {
+                    let old_values = node.computed_values().clone();
+                    compute_values(node.clone(), device);
+                    let new_values = node.computed_values();
+                    // Nodes that were never styled have changed no matter their values.
+                    changed = old_rule_node.is_none() || *new_values != old_values;
+                    if changed && new_values.display != old_values.display {
+                        restyle.display_changed_nodes.insert(node.clone());
+                    }
+                }
+                if changed {
+                    restyle.restyled_nodes.insert(node);
+                }
+                changed_stack.push(changed);
+            }
+            NodeEdge::End(_) => {
+                changed_stack.pop();
+            }
+        }
+    }
+    restyle
+}
+
+/// Recomputes the values of every node in `document` for `device`, without matching rules anew.
+/// This is for when the size of the viewport changed without changing which rules apply, in
+/// which case only values with viewport-percentage lengths (e.g. `vw`) can change.
+pub fn recompute_values(document: &NodeRef, device: &Device) -> Restyle {
+    let _span = info_span!("recompute_values").entered();
+    let mut restyle = Restyle::default();
+    // Nodes are recomputed in tree order, so that parents are recomputed before the children
+    // that inherit from them.
+    for node in document.shadow_including_inclusive_descendants() {
+        let old_values = node.computed_values().clone();
+        compute_values(node.clone(), device);
+        let changed = *node.computed_values() != old_values;
+        if changed {
+            restyle.restyled_nodes.insert(node);
+        }
+    }
+    restyle
+}
+
+/// Collects the declarations that apply to `node` anew, from the same stylesheets, in the same
+/// order, as `apply_styles`.
+fn rematch_node(
+    node: &NodeRef,
+    device: &Device,
+    ua_sheets: &[Stylesheet],
+    user_sheets: &[Stylesheet],
+    author_sheets: &[Stylesheet],
+) {
+    let element = match node.clone().into_element_ref() {
+        Some(element) => element,
+        None => return,
+    };
+    let apply = |sheets: &[Stylesheet], origin: CascadeOrigin, scope: SelectorScope| {
+        sheets.iter().for_each(|stylesheet| {
+            apply_stylesheet_to_element(&element, stylesheet, origin.clone(), device, scope);
+        });
+    };
+    apply(ua_sheets, CascadeOrigin::UserAgent, SelectorScope::Tree);
+    let shadow_root = node.containing_shadow_root();
+    // The stylesheets of the document only reach the shadow trees of its hosts, through
+    // `::part()`.
+    let document_scope = match &shadow_root {
+        None => Some(SelectorScope::Tree),
+        Some(shadow_root) if shadow_root.containing_shadow_root().is_none() => {
+            Some(SelectorScope::Parts)
+        }
+        Some(_) => None,
+    };
+    if let Some(scope) = document_scope {
+        apply(user_sheets, CascadeOrigin::User, scope);
+    }
+    if let Some(own_shadow_root) = node.shadow_root() {
+        let sheets = shadow_tree_stylesheets(&own_shadow_root);
+        apply(&sheets, CascadeOrigin::Author, SelectorScope::Host);
+    }
+    if let Some(shadow_root) = &shadow_root {
+        apply(
+            &shadow_tree_stylesheets(shadow_root),
+            CascadeOrigin::Author,
+            SelectorScope::Tree,
+        );
+        if let Some(outer_shadow_root) = shadow_root.containing_shadow_root() {
+            apply(
+                &shadow_tree_stylesheets(&outer_shadow_root),
+                CascadeOrigin::Author,
+                SelectorScope::Parts,
+            );
+        }
+    }
+    if let Some(scope) = document_scope {
+        apply(author_sheets, CascadeOrigin::Author, scope);
+    }
+    apply_presentational_hints_to_node(node, &element);
+    if let Some(style_str) = element.attributes.borrow().get(local_name!("style")) {
+        apply_inline_style_to_node(node, style_str);
+    }
+}
+
+/// Adds the presentational hints of `element`'s attributes to `node`.  Hints are cascaded at a
+/// level of their own, below every other author declaration.
+fn apply_presentational_hints_to_node(node: &NodeRef, element: &ElementData) {
+    let hints = match presentational_hints(element) {
+        Some(hints) => hints,
+        None => return,
+    };
+    let mut input = ParserInput::new(&hints);
+    let block = parse_property_declaration_list(&mut Parser::new(&mut input));
+    let decls = block
+        .declarations()
+        .iter()
+        .map(|decl| ContextualPropertyDeclaration {
+            inner_decl: decl.clone(),
+            important: false,
+            origin: CssOrigin::PresentationalHint,
+            source_location: None,
+            specificity: Specificity::new(0),
+        })
+        .collect();
+    if let Some(block) =
+        ApplicableDeclarationBlock::new(StyleSource::PresentationalHints(hints.clone()), decls)
+    {
+        node.add_declaration_block(block);
+    }
+}
+
+/// Parses the declarations in a `style` attribute and adds them to `node`.  Inline declarations
+/// have an author origin and a specificity greater than that of any selector.
+///
+/// https://www.w3.org/TR/css-style-attr/#interpret
+fn apply_inline_style_to_node(node: &NodeRef, style_str: &str) {
+    let mut input = ParserInput::new(style_str);
+    let block = parse_property_declaration_list(&mut Parser::new(&mut input));
+    let (important, normal): (Vec<_>, Vec<_>) = block
+        .declarations()
+        .iter()
+        .enumerate()
+        .map(|(index, decl)| ContextualPropertyDeclaration {
+            inner_decl: decl.clone(),
+            important: block
+                .declarations_importance()
+                .get(index)
+                .expect("important bit not set for declaration"),
+            origin: CssOrigin::Inline,
+            source_location: None,
+            specificity: Specificity::new(u32::MAX),
+        })
+        .partition(|decl| decl.important);
+    let add_block = |decls| {
+        let source = StyleSource::Inline(style_str.to_owned());
+        if let Some(block) = ApplicableDeclarationBlock::new(source, decls) {
+            node.add_declaration_block(block);
+        }
+    };
+    add_block(normal);
+    add_block(important);
+}
+
+/// Inserts the declarations collected for `node` into `rule_tree`, and gives `node` the resulting
+/// rule node.
+fn cascade(node: &NodeRef, rule_tree: &RuleTree) {
+    node.set_rule_node(rule_tree.insert(node.take_applicable_declarations()));
+}
+
+/// The rule tree of the document `node` is in, or a new one if it isn't in a document.
+fn rule_tree_of(node: &NodeRef) -> RuleTree {
+    node.inclusive_ancestors()
+        .last()
+        .and_then(|root| {
+            root.as_document()
+                .map(|document| document.rule_tree.clone())
+        })
+        .unwrap_or_default()
 }
 
 /// Performs steps 2-4 of https://www.w3.org/TR/2018/CR-css-cascade-3-20180828/#value-stages.
@@ -70,19 +445,25 @@ pub fn apply_styles(
 /// 2) Cascading — https://www.w3.org/TR/2018/CR-css-cascade-3-20180828/#cascade
 /// 3) Defaulting to specified values — https://www.w3.org/TR/2018/CR-css-cascade-3-20180828/#specified-value
 /// 4) Resolving specified values to computed values — https://www.w3.org/TR/2018/CR-css-cascade-3-20180828/#computed
-pub fn cascade_and_compute(start_node: &NodeRef) {
-    start_node.inclusive_descendants().for_each(|node| {
-        // Step 2
-        node.contextual_decls_mut().cascade_sort();
-        // Step 3 and 4
-        compute_values(node);
-    });
+pub fn cascade_and_compute(start_node: &NodeRef, device: &Device) {
+    let _span = info_span!("cascade_and_compute").entered();
+    let rule_tree = rule_tree_of(start_node);
+    start_node
+        .shadow_including_inclusive_descendants()
+        .for_each(|node| {
+            // Step 2
+            cascade(&node, &rule_tree);
+            // Step 3 and 4
+            compute_values(node, device);
+        });
 }
 
 // TODO: Servo supports many different types of rules, but we won't support those yet.  https://github.com/servo/servo/blob/d2856ce8aeca11e543bc4d9f869400d73451374e/components/style/stylesheets/mod.rs#L236
 #[derive(Clone, Debug)]
 pub enum CssRule {
     Style(StyleRule),
+    Media(MediaRule),
+    Container(ContainerRule),
     None,
 }
 
@@ -95,6 +476,44 @@ pub struct StyleRule {
     pub block: PropertyDeclarationBlock,
     /// The location in the sheet where it was found.
     pub source_location: SourceLocation,
+    /// The media lists of the `@media` rules this rule is nested in, outermost first.  The rule
+    /// only applies if every one of them matches the device.
+    pub media: Vec<Rc<MediaList>>,
+    /// The conditions of the `@container` rules this rule is nested in, outermost first.  The
+    /// rule only applies to elements every one of them matches.
+    pub containers: Vec<Rc<ContainerCondition>>,
+}
+
+impl StyleRule {
+    pub fn matches_device(&self, device: &Device) -> bool {
+        self.media.iter().all(|media| media.matches(device))
+    }
+
+    /// Whether the query containers of `element` match every `@container` rule this rule is
+    /// nested in.
+    pub fn matches_containers(&self, element: &NodeRef) -> bool {
+        self.containers
+            .iter()
+            .all(|condition| condition.matches(element))
+    }
+}
+
+/// An `@media` rule.  The style rules nested in it (including those in nested `@media` rules) are
+/// flattened into `rules`, each carrying the media lists that condition it.
+///
+/// https://drafts.csswg.org/css-conditional-3/#at-media
+#[derive(Clone, Debug)]
+pub struct MediaRule {
+    pub rules: Vec<StyleRule>,
+}
+
+/// An `@container` rule, whose nested style rules are flattened into `rules` like those of
+/// `@media` rules, each carrying the container conditions that condition it.
+///
+/// https://drafts.csswg.org/css-contain-3/#container-rule
+#[derive(Clone, Debug)]
+pub struct ContainerRule {
+    pub rules: Vec<StyleRule>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -106,6 +525,10 @@ pub enum CssOrigin {
     Inline,
     /// CSS found within <style></style> tags
     Embedded,
+    /// The presentational hints of an element's attributes (e.g. `bgcolor`), which have an author
+    /// origin but lose to every other author declaration.
+    /// https://html.spec.whatwg.org/multipage/rendering.html#presentational-hints
+    PresentationalHint,
     /// CSS found within a stylesheet
     Sheet(StylesheetOrigin),
 }
@@ -139,19 +562,102 @@ pub struct Color {
 }
 
 /// Parser for top-level CSS rules.
-pub struct TopLevelRuleParser {}
+#[derive(Default)]
+pub struct TopLevelRuleParser {
+    /// A diagnostic for each nested rule and declaration dropped while parsing.  Top-level rules
+    /// that are dropped are reported by the `RuleListParser` driving this.
+    pub diagnostics: Vec<CssDiagnostic>,
+}
 
-// TODO: Support @ rules
+// TODO: Support more @ rules
 pub enum AtRuleNonBlockPrelude {}
 
-pub enum AtRuleBlockPrelude {}
+pub enum AtRuleBlockPrelude {
+    Media(MediaList),
+    Container(ContainerCondition),
+}
 
-/// Kosmonaut currently does not support @rules, so fall back to the default @rule error impl.
+/// Only `@media` and `@container` rules are supported for now.  Any other @rule is an error.
 impl<'i> AtRuleParser<'i> for TopLevelRuleParser {
     type PreludeNoBlock = AtRuleNonBlockPrelude;
     type PreludeBlock = AtRuleBlockPrelude;
     type AtRule = CssRule;
     type Error = StyleParseErrorKind<'i>;
+
+    fn parse_prelude<'t>(
+        &mut self,
+        name: CowRcStr<'i>,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<AtRuleType<Self::PreludeNoBlock, Self::PreludeBlock>, ParseError<'i, Self::Error>>
+    {
+        match_ignore_ascii_case! { &*name,
+            "media" => {
+                let media = MediaList::parse(input);
+                Ok(AtRuleType::WithBlock(AtRuleBlockPrelude::Media(media)))
+            },
+            "container" => {
+                let condition = ContainerCondition::parse(input)
+                    .map_err(|_| input.new_custom_error(StyleParseErrorKind::UnspecifiedError))?;
+                Ok(AtRuleType::WithBlock(AtRuleBlockPrelude::Container(condition)))
+            },
+            _ => {
+                let error = StyleParseErrorKind::UnsupportedAtRule(name.clone());
+                Err(input.new_custom_error(error))
+            },
+        }
+    }
+
+    fn parse_block<'t>(
+        &mut self,
+        prelude: Self::PreludeBlock,
+        _location: SourceLocation,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<CssRule, ParseError<'i, Self::Error>> {
+        let mut rules = self.parse_nested_style_rules(input);
+        match prelude {
+            AtRuleBlockPrelude::Media(media) => {
+                let media = Rc::new(media);
+                for style_rule in &mut rules {
+                    style_rule.media.insert(0, media.clone());
+                }
+                Ok(CssRule::Media(MediaRule { rules }))
+            }
+            AtRuleBlockPrelude::Container(condition) => {
+                let condition = Rc::new(condition);
+                for style_rule in &mut rules {
+                    style_rule.containers.insert(0, condition.clone());
+                }
+                Ok(CssRule::Container(ContainerRule { rules }))
+            }
+        }
+    }
+}
+
+impl TopLevelRuleParser {
+    /// Parses the rules nested in the block of a conditional rule (like `@media`), flattening
+    /// those of nested conditional rules into the style rules they contain.
+    fn parse_nested_style_rules<'i, 't>(&mut self, input: &mut Parser<'i, 't>) -> Vec<StyleRule> {
+        let mut rules = Vec::new();
+        // Invalid nested rules are skipped, like invalid top-level rules.
+        let mut nested_rule_parser =
+            RuleListParser::new_for_nested_rule(input, TopLevelRuleParser::default());
+        while let Some(rule) = nested_rule_parser.next() {
+            match rule {
+                Ok(CssRule::Style(style_rule)) => rules.push(style_rule),
+                Ok(CssRule::Media(MediaRule {
+                    rules: nested_rules,
+                }))
+                | Ok(CssRule::Container(ContainerRule {
+                    rules: nested_rules,
+                })) => rules.extend(nested_rules),
+                Ok(CssRule::None) => {}
+                Err((err, rule)) => self.diagnostics.push(CssDiagnostic::for_rule(&err, rule)),
+            }
+        }
+        self.diagnostics
+            .append(&mut nested_rule_parser.parser.diagnostics);
+        rules
+    }
 }
 
 impl<'i> QualifiedRuleParser<'i> for TopLevelRuleParser {
@@ -176,8 +682,10 @@ impl<'i> QualifiedRuleParser<'i> for TopLevelRuleParser {
     ) -> Result<CssRule, ParseError<'i, Self::Error>> {
         Ok(CssRule::Style(StyleRule {
             selectors,
-            block: parse_property_declaration_list(input),
+            block: parse_property_declaration_list_with_diagnostics(input, &mut self.diagnostics),
             source_location,
+            media: Vec::new(),
+            containers: Vec::new(),
         }))
     }
 }
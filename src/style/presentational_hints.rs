@@ -0,0 +1,316 @@
+//! Presentational hints: the styles that legacy HTML attributes (like `bgcolor` and `width`) map
+//! to.  Hints are cascaded as author declarations with a specificity of zero that precede every
+//! author stylesheet, so any author rule overrides them.
+//!
+//! TODO: Map `align` and `<center>` to `text-align` once it's supported.
+//!
+//! https://html.spec.whatwg.org/multipage/rendering.html#presentational-hints
+
+use crate::dom::tree::ElementData;
+use cssparser::Color;
+use html5ever::LocalName;
+
+/// The attributes that map to presentational hints on some element, so changing them restyles
+/// the element.
+const HINT_ATTRIBUTES: &[&str] = &["bgcolor", "border", "color", "height", "text", "width"];
+
+/// Whether changing the attribute `name` may change the presentational hints of an element.
+pub fn is_hint_attribute(name: &LocalName) -> bool {
+    HINT_ATTRIBUTES.contains(&&**name)
+}
+
+/// The presentational hints of `element`, as the text of a declaration block, or `None` if its
+/// attributes map to none.
+pub fn presentational_hints(element: &ElementData) -> Option<String> {
+    if element.name.ns != ns!(html) {
+        return None;
+    }
+    let attributes = element.attributes.borrow();
+    let mut hints = Vec::new();
+    let mut hint = |property: &str, value: String| hints.push(format!("{}: {}", property, value));
+    let local_name = &element.name.local;
+
+    // https://html.spec.whatwg.org/multipage/rendering.html#dimRendering
+    let maps_dimensions = matches!(
+        *local_name,
+        local_name!("img")
+            | local_name!("iframe")
+            | local_name!("embed")
+            | local_name!("object")
+            | local_name!("video")
+            | local_name!("canvas")
+    );
+    // The dimensions of tables and their cells ignore zero.
+    let maps_nonzero_dimensions = matches!(
+        *local_name,
+        local_name!("table") | local_name!("td") | local_name!("th")
+    );
+    if maps_dimensions || maps_nonzero_dimensions || *local_name == local_name!("hr") {
+        if let Some(width) = attributes
+            .get(local_name!("width"))
+            .and_then(parse_dimension)
+        {
+            if !(maps_nonzero_dimensions && width.is_zero()) {
+                hint("width", width.to_css());
+            }
+        }
+    }
+    if maps_dimensions || maps_nonzero_dimensions {
+        if let Some(height) = attributes
+            .get(local_name!("height"))
+            .and_then(parse_dimension)
+        {
+            if !(maps_nonzero_dimensions && height.is_zero()) {
+                hint("height", height.to_css());
+            }
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/rendering.html#tables-2
+    // https://html.spec.whatwg.org/multipage/rendering.html#the-page
+    if matches!(
+        *local_name,
+        local_name!("body")
+            | local_name!("table")
+            | local_name!("thead")
+            | local_name!("tbody")
+            | local_name!("tfoot")
+            | local_name!("tr")
+            | local_name!("td")
+            | local_name!("th")
+    ) {
+        if let Some(color) = attributes
+            .get(local_name!("bgcolor"))
+            .and_then(parse_legacy_color)
+        {
+            hint("background-color", color);
+        }
+    }
+    let text_color = match *local_name {
+        local_name!("body") => attributes.get(local_name!("text")),
+        // https://html.spec.whatwg.org/multipage/rendering.html#phrasing-content-3
+        local_name!("font") => attributes.get(local_name!("color")),
+        _ => None,
+    };
+    if let Some(color) = text_color.and_then(parse_legacy_color) {
+        hint("color", color);
+    }
+
+    // https://html.spec.whatwg.org/multipage/rendering.html#images-3
+    if matches!(*local_name, local_name!("img") | local_name!("object")) {
+        let border = attributes
+            .get(local_name!("border"))
+            .and_then(|border| border.trim_start().parse::<u32>().ok())
+            .filter(|&border| border > 0);
+        if let Some(border) = border {
+            for side in &["top", "right", "bottom", "left"] {
+                hint(&format!("border-{}-width", side), format!("{}px", border));
+                hint(&format!("border-{}-style", side), "solid".to_owned());
+            }
+        }
+    }
+
+    if hints.is_empty() {
+        None
+    } else {
+        Some(hints.join("; "))
+    }
+}
+
+/// A length parsed from an attribute by the rules for parsing dimension values.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Dimension {
+    Px(f32),
+    Percentage(f32),
+}
+
+impl Dimension {
+    fn is_zero(self) -> bool {
+        match self {
+            Dimension::Px(value) | Dimension::Percentage(value) => value == 0.,
+        }
+    }
+
+    fn to_css(self) -> String {
+        match self {
+            Dimension::Px(value) => format!("{}px", value),
+            Dimension::Percentage(value) => format!("{}%", value),
+        }
+    }
+}
+
+/// Parses a dimension value: a non-negative number of pixels, or a percentage if it's followed by
+/// `%`.  Anything after the number is ignored, so `100px` is 100 pixels.
+///
+/// https://html.spec.whatwg.org/multipage/common-microsyntaxes.html#rules-for-parsing-dimension-values
+fn parse_dimension(input: &str) -> Option<Dimension> {
+    let input = input.trim_start_matches(|c: char| c.is_ascii_whitespace());
+    let integer_end = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    if integer_end == 0 {
+        return None;
+    }
+    let mut number_end = integer_end;
+    if input[integer_end..].starts_with('.') {
+        let fraction_digits = input[integer_end + 1..]
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(input.len() - integer_end - 1);
+        if fraction_digits > 0 {
+            number_end = integer_end + 1 + fraction_digits;
+        }
+    }
+    let value = input[..number_end].parse::<f32>().ok()?;
+    if input[number_end..].starts_with('%') {
+        Some(Dimension::Percentage(value))
+    } else {
+        Some(Dimension::Px(value))
+    }
+}
+
+/// Parses a legacy color value (e.g. of `bgcolor`), which can be a named color, or a hexadecimal
+/// color with or without its `#`, and which is very lenient about anything else, returning the
+/// color as CSS.
+///
+/// https://html.spec.whatwg.org/multipage/common-microsyntaxes.html#rules-for-parsing-a-legacy-colour-value
+fn parse_legacy_color(input: &str) -> Option<String> {
+    let input = input.trim_matches(|c: char| c.is_ascii_whitespace());
+    if input.is_empty() || input.eq_ignore_ascii_case("transparent") {
+        return None;
+    }
+    if let Ok(Color::RGBA(rgba)) = cssparser::parse_color_keyword(input) {
+        return Some(format!("rgb({}, {}, {})", rgba.red, rgba.green, rgba.blue));
+    }
+    let hex_digit = |c: char| c.to_digit(16);
+    if input.len() == 4 && input.starts_with('#') {
+        let digits = input[1..]
+            .chars()
+            .map(hex_digit)
+            .collect::<Option<Vec<_>>>();
+        if let Some(digits) = digits {
+            return Some(format!(
+                "rgb({}, {}, {})",
+                digits[0] * 17,
+                digits[1] * 17,
+                digits[2] * 17
+            ));
+        }
+    }
+    // Characters outside the BMP count as two digits, as they're two UTF-16 code units.
+    let mut digits = input
+        .chars()
+        .flat_map(|c| {
+            if c as u32 > 0xFFFF {
+                vec!['0', '0']
+            } else {
+                vec![c]
+            }
+        })
+        .take(128)
+        .collect::<Vec<_>>();
+    if digits.first() == Some(&'#') {
+        digits.remove(0);
+    }
+    let mut digits = digits
+        .into_iter()
+        .map(|c| hex_digit(c).unwrap_or(0))
+        .collect::<Vec<_>>();
+    while digits.is_empty() || digits.len() % 3 != 0 {
+        digits.push(0);
+    }
+    let mut length = digits.len() / 3;
+    let mut components = digits.chunks(length).collect::<Vec<_>>();
+    if length > 8 {
+        for component in &mut components {
+            *component = &component[length - 8..];
+        }
+        length = 8;
+    }
+    while length > 2 && components.iter().all(|component| component[0] == 0) {
+        for component in &mut components {
+            *component = &component[1..];
+        }
+        length -= 1;
+    }
+    let component_value = |component: &[u32]| {
+        component[..length.min(2)]
+            .iter()
+            .fold(0, |value, digit| value * 16 + digit)
+    };
+    Some(format!(
+        "rgb({}, {}, {})",
+        component_value(components[0]),
+        component_value(components[1]),
+        component_value(components[2])
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::traits::*;
+    use crate::style::test_utils::styled_document;
+    use crate::style::values::computed::display::DisplayBox;
+    use crate::style::values::computed::length::LengthPercentageOrAuto;
+    use crate::style::values::computed::Display;
+    use cssparser::RGBA;
+
+    #[test]
+    fn hints_lose_to_author_rules() {
+        let document = styled_document(
+            "<body bgcolor=lime text=#00f>\
+             <img id=sized width=100 height=50%><img id=overridden width=100>\
+             <table width=0></table><p hidden>Hidden</p></body>",
+            "#overridden { width: 10px }",
+        );
+        let values = |selector| {
+            document
+                .select_first(selector)
+                .unwrap()
+                .as_node()
+                .computed_values()
+                .clone()
+        };
+        assert_eq!(
+            values("body").background_color.rgba(),
+            RGBA::new(0, 255, 0, 255)
+        );
+        assert_eq!(values("body").color.rgba(), RGBA::new(0, 0, 255, 255));
+        assert_eq!(
+            values("#sized").width.size,
+            LengthPercentageOrAuto::new_len(100.)
+        );
+        assert_ne!(values("#sized").height.size, LengthPercentageOrAuto::Auto);
+        assert_eq!(
+            values("#overridden").width.size,
+            LengthPercentageOrAuto::new_len(10.)
+        );
+        // Tables ignore zero widths.
+        assert_eq!(values("table").width.size, LengthPercentageOrAuto::Auto);
+        assert_eq!(values("p").display, Display::Box(DisplayBox::None));
+    }
+
+    #[test]
+    fn parses_dimensions() {
+        assert_eq!(parse_dimension(" 100"), Some(Dimension::Px(100.)));
+        assert_eq!(parse_dimension("50.5%"), Some(Dimension::Percentage(50.5)));
+        assert_eq!(parse_dimension("20px"), Some(Dimension::Px(20.)));
+        assert_eq!(parse_dimension("3."), Some(Dimension::Px(3.)));
+        assert_eq!(parse_dimension("auto"), None);
+        assert_eq!(parse_dimension("-1"), None);
+    }
+
+    #[test]
+    fn parses_legacy_colors() {
+        let rgb = |color: &str| parse_legacy_color(color);
+        assert_eq!(rgb("red"), Some("rgb(255, 0, 0)".to_owned()));
+        assert_eq!(rgb("#0f0"), Some("rgb(0, 255, 0)".to_owned()));
+        assert_eq!(rgb("0000ff"), Some("rgb(0, 0, 255)".to_owned()));
+        // Invalid digits are zeroes, and short values are split into one-digit components.
+        assert_eq!(rgb("#zz8"), Some("rgb(0, 0, 8)".to_owned()));
+        // Long components are trimmed to their first two significant digits.
+        assert_eq!(rgb("chucknorris"), Some("rgb(192, 0, 0)".to_owned()));
+        assert_eq!(rgb("transparent"), None);
+        assert_eq!(rgb(""), None);
+    }
+}
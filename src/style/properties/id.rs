@@ -24,6 +24,7 @@ impl PropertyId {
         let id = match prop_name {
             // Longhands
             "background-color" => PropertyId::Longhand(LonghandId::BackgroundColor),
+            "background-image" => PropertyId::Longhand(LonghandId::BackgroundImage),
             "border-bottom-color" => PropertyId::Longhand(LonghandId::BorderBottomColor),
             "border-left-color" => PropertyId::Longhand(LonghandId::BorderLeftColor),
             "border-right-color" => PropertyId::Longhand(LonghandId::BorderRightColor),
@@ -37,23 +38,53 @@ impl PropertyId {
             "border-right-width" => PropertyId::Longhand(LonghandId::BorderRightWidth),
             "border-top-width" => PropertyId::Longhand(LonghandId::BorderTopWidth),
             "color" => PropertyId::Longhand(LonghandId::Color),
+            "color-scheme" => PropertyId::Longhand(LonghandId::ColorScheme),
+            "contain" => PropertyId::Longhand(LonghandId::Contain),
+            "contain-intrinsic-height" => PropertyId::Longhand(LonghandId::ContainIntrinsicHeight),
+            "contain-intrinsic-width" => PropertyId::Longhand(LonghandId::ContainIntrinsicWidth),
+            "container-name" => PropertyId::Longhand(LonghandId::ContainerName),
+            "container-type" => PropertyId::Longhand(LonghandId::ContainerType),
+            "content-visibility" => PropertyId::Longhand(LonghandId::ContentVisibility),
+            "cursor" => PropertyId::Longhand(LonghandId::Cursor),
             "direction" => PropertyId::Longhand(LonghandId::Direction),
             "display" => PropertyId::Longhand(LonghandId::Display),
             //            "float" => PropertyId::Longhand(LonghandId::Float),
-            //            "font-style" => PropertyId::Longhand(LonghandId::FontStyle),
-            //            "font-weight" => PropertyId::Longhand(LonghandId::FontWeight),
             //            "visibility" => PropertyId::Longhand(LonghandId::Visibility),
+            "font-family" => PropertyId::Longhand(LonghandId::FontFamily),
+            "font-feature-settings" => PropertyId::Longhand(LonghandId::FontFeatureSettings),
             "font-size" => PropertyId::Longhand(LonghandId::FontSize),
+            "font-style" => PropertyId::Longhand(LonghandId::FontStyle),
+            "font-variant-caps" => PropertyId::Longhand(LonghandId::FontVariantCaps),
+            "font-weight" => PropertyId::Longhand(LonghandId::FontWeight),
             "height" => PropertyId::Longhand(LonghandId::Height),
             "margin-bottom" => PropertyId::Longhand(LonghandId::MarginBottom),
             "margin-left" => PropertyId::Longhand(LonghandId::MarginLeft),
             "margin-right" => PropertyId::Longhand(LonghandId::MarginRight),
             "margin-top" => PropertyId::Longhand(LonghandId::MarginTop),
+            "object-fit" => PropertyId::Longhand(LonghandId::ObjectFit),
+            "object-position" => PropertyId::Longhand(LonghandId::ObjectPosition),
+            "overflow-x" => PropertyId::Longhand(LonghandId::OverflowX),
+            "overflow-y" => PropertyId::Longhand(LonghandId::OverflowY),
             "padding-bottom" => PropertyId::Longhand(LonghandId::PaddingBottom),
             "padding-left" => PropertyId::Longhand(LonghandId::PaddingLeft),
             "padding-right" => PropertyId::Longhand(LonghandId::PaddingRight),
             "padding-top" => PropertyId::Longhand(LonghandId::PaddingTop),
+            "position" => PropertyId::Longhand(LonghandId::Position),
+            "scroll-behavior" => PropertyId::Longhand(LonghandId::ScrollBehavior),
+            "scroll-margin-bottom" => PropertyId::Longhand(LonghandId::ScrollMarginBottom),
+            "scroll-margin-left" => PropertyId::Longhand(LonghandId::ScrollMarginLeft),
+            "scroll-margin-right" => PropertyId::Longhand(LonghandId::ScrollMarginRight),
+            "scroll-margin-top" => PropertyId::Longhand(LonghandId::ScrollMarginTop),
+            "scroll-padding-bottom" => PropertyId::Longhand(LonghandId::ScrollPaddingBottom),
+            "scroll-padding-left" => PropertyId::Longhand(LonghandId::ScrollPaddingLeft),
+            "scroll-padding-right" => PropertyId::Longhand(LonghandId::ScrollPaddingRight),
+            "scroll-padding-top" => PropertyId::Longhand(LonghandId::ScrollPaddingTop),
+            "scroll-snap-align" => PropertyId::Longhand(LonghandId::ScrollSnapAlign),
+            "scroll-snap-type" => PropertyId::Longhand(LonghandId::ScrollSnapType),
+            "tab-size" => PropertyId::Longhand(LonghandId::TabSize),
+            "white-space" => PropertyId::Longhand(LonghandId::WhiteSpace),
             "width" => PropertyId::Longhand(LonghandId::Width),
+            "will-change" => PropertyId::Longhand(LonghandId::WillChange),
             "writing-mode" => PropertyId::Longhand(LonghandId::WritingMode),
             // Shorthands
             "background" => PropertyId::Shorthand(ShorthandId::Background),
@@ -63,8 +94,14 @@ impl PropertyId {
             "border-bottom" => PropertyId::Shorthand(ShorthandId::BorderBottom),
             "border-left" => PropertyId::Shorthand(ShorthandId::BorderLeft),
             "border" => PropertyId::Shorthand(ShorthandId::Border),
+            "font-variant" => PropertyId::Shorthand(ShorthandId::FontVariant),
+            "overflow" => PropertyId::Shorthand(ShorthandId::Overflow),
             "margin" => PropertyId::Shorthand(ShorthandId::Margin),
             "padding" => PropertyId::Shorthand(ShorthandId::Padding),
+            "scroll-margin" => PropertyId::Shorthand(ShorthandId::ScrollMargin),
+            "scroll-padding" => PropertyId::Shorthand(ShorthandId::ScrollPadding),
+            "contain-intrinsic-size" => PropertyId::Shorthand(ShorthandId::ContainIntrinsicSize),
+            "container" => PropertyId::Shorthand(ShorthandId::Container),
             _ => return None,
         };
         Some(id)
@@ -72,7 +109,8 @@ impl PropertyId {
 }
 
 /// An identifier for a given longhand property.
-#[derive(Clone, Copy, Debug, EnumIter, Eq, Hash, PartialEq)]
+#[derive(Clone, Copy, Debug, EnumIter, Eq, Hash, IntoStaticStr, PartialEq)]
+#[strum(serialize_all = "kebab_case")]
 #[repr(u16)]
 pub enum LonghandId {
     //    /// align-content
@@ -111,12 +149,12 @@ pub enum LonghandId {
     //    Float = 16,
     //    /// font-stretch
     //    FontStretch = 17,
-    //    /// font-style
-    //    FontStyle = 18,
-    //    /// font-variant-caps
-    //    FontVariantCaps = 19,
-    //    /// font-weight
-    //    FontWeight = 20,
+    /// font-style
+    FontStyle = 18,
+    /// font-variant-caps
+    FontVariantCaps = 19,
+    /// font-weight
+    FontWeight = 20,
     //    /// image-rendering
     //    ImageRendering = 21,
     //    /// justify-content
@@ -137,8 +175,8 @@ pub enum LonghandId {
     //    OverflowWrap = 29,
     //    /// pointer-events
     //    PointerEvents = 30,
-    //    /// position
-    //    Position = 31,
+    /// position
+    Position = 31,
     //    /// table-layout
     //    TableLayout = 32,
     //    /// text-align
@@ -157,8 +195,8 @@ pub enum LonghandId {
     //    UnicodeBidi = 39,
     //    /// visibility
     //    Visibility = 40,
-    //    /// white-space
-    //    WhiteSpace = 41,
+    /// white-space
+    WhiteSpace = 41,
     //    /// word-break
     //    WordBreak = 42,
     /// writing-mode
@@ -173,10 +211,10 @@ pub enum LonghandId {
     //    OverflowBlock = 47,
     //    /// overflow-inline
     //    OverflowInline = 48,
-    //    /// overflow-x
-    //    OverflowX = 49,
-    //    /// overflow-y
-    //    OverflowY = 50,
+    /// overflow-x
+    OverflowX = 49,
+    /// overflow-y
+    OverflowY = 50,
     //    /// border-block-end-style
     //    BorderBlockEndStyle = 51,
     //    /// border-block-start-style
@@ -213,8 +251,8 @@ pub enum LonghandId {
     //    BackgroundAttachment = 67,
     //    /// background-clip
     //    BackgroundClip = 68,
-    //    /// background-image
-    //    BackgroundImage = 69,
+    /// background-image
+    BackgroundImage = 69,
     //    /// background-origin
     //    BackgroundOrigin = 70,
     //    /// background-position-x
@@ -251,14 +289,14 @@ pub enum LonghandId {
     //    CounterIncrement = 86,
     //    /// counter-reset
     //    CounterReset = 87,
-    //    /// cursor
-    //    Cursor = 88,
+    /// cursor
+    Cursor = 88,
     //    /// filter
     //    Filter = 89,
     //    /// flex-basis
     //    FlexBasis = 90,
-    //    /// font-family
-    //    FontFamily = 91,
+    /// font-family
+    FontFamily = 91,
     /// font-size
     FontSize = 92,
     //    /// letter-spacing
@@ -429,15 +467,69 @@ pub enum LonghandId {
     //    Right = 175,
     //    /// top
     //    Top = 176,
+    /// object-fit
+    ObjectFit = 177,
+    /// object-position
+    ObjectPosition = 178,
+    /// font-feature-settings
+    FontFeatureSettings = 179,
+    /// tab-size
+    TabSize = 180,
+    /// color-scheme
+    ColorScheme = 181,
+    /// scroll-behavior
+    ScrollBehavior = 182,
+    /// scroll-margin-bottom
+    ScrollMarginBottom = 183,
+    /// scroll-margin-left
+    ScrollMarginLeft = 184,
+    /// scroll-margin-right
+    ScrollMarginRight = 185,
+    /// scroll-margin-top
+    ScrollMarginTop = 186,
+    /// scroll-padding-bottom
+    ScrollPaddingBottom = 187,
+    /// scroll-padding-left
+    ScrollPaddingLeft = 188,
+    /// scroll-padding-right
+    ScrollPaddingRight = 189,
+    /// scroll-padding-top
+    ScrollPaddingTop = 190,
+    /// scroll-snap-align
+    ScrollSnapAlign = 191,
+    /// scroll-snap-type
+    ScrollSnapType = 192,
+    /// will-change
+    WillChange = 193,
+    /// contain
+    Contain = 194,
+    /// contain-intrinsic-height
+    ContainIntrinsicHeight = 195,
+    /// contain-intrinsic-width
+    ContainIntrinsicWidth = 196,
+    /// content-visibility
+    ContentVisibility = 197,
+    /// container-name
+    ContainerName = 198,
+    /// container-type
+    ContainerType = 199,
 }
 
 impl LonghandId {
+    /// The name of this property, as it's written in style sheets.
+    pub fn name(self) -> &'static str {
+        self.into()
+    }
+
     #[allow(unreachable_patterns)]
     pub fn value_default(self, cv_builder: &mut ComputedValuesBuilder, ctx: &ComputeContext) {
         match self {
             LonghandId::BackgroundColor => {
                 cv_builder.background_color(specified::BackgroundColor::value_default(ctx));
             }
+            LonghandId::BackgroundImage => {
+                cv_builder.background_image(computed::BackgroundImage::value_default(ctx));
+            }
             LonghandId::BorderBottomColor => {
                 cv_builder.border_bottom_color(specified::BorderBottomColor::value_default(ctx));
             }
@@ -477,15 +569,56 @@ impl LonghandId {
             LonghandId::Color => {
                 cv_builder.color(specified::Color::value_default(ctx));
             }
+            LonghandId::ColorScheme => {
+                cv_builder.color_scheme(computed::SupportedColorSchemes::value_default(ctx));
+            }
+            LonghandId::Contain => {
+                cv_builder.contain(computed::Contain::value_default(ctx));
+            }
+            LonghandId::ContainIntrinsicHeight => {
+                cv_builder
+                    .contain_intrinsic_height(specified::ContainIntrinsicSize::value_default(ctx));
+            }
+            LonghandId::ContainIntrinsicWidth => {
+                cv_builder
+                    .contain_intrinsic_width(specified::ContainIntrinsicSize::value_default(ctx));
+            }
+            LonghandId::ContainerName => {
+                cv_builder.container_name(computed::ContainerName::value_default(ctx));
+            }
+            LonghandId::ContainerType => {
+                cv_builder.container_type(computed::ContainerType::value_default(ctx));
+            }
+            LonghandId::ContentVisibility => {
+                cv_builder.content_visibility(computed::ContentVisibility::value_default(ctx));
+            }
+            LonghandId::Cursor => {
+                cv_builder.cursor(computed::Cursor::value_default(ctx));
+            }
             LonghandId::Direction => {
                 cv_builder.direction(computed::Direction::value_default(ctx));
             }
             LonghandId::Display => {
                 cv_builder.display(computed::Display::value_default(ctx));
             }
+            LonghandId::FontFamily => {
+                cv_builder.font_family(computed::FontFamily::value_default(ctx));
+            }
+            LonghandId::FontFeatureSettings => {
+                cv_builder.font_feature_settings(computed::FontFeatureSettings::value_default(ctx));
+            }
             LonghandId::FontSize => {
                 cv_builder.font_size(specified::FontSize::value_default(ctx));
             }
+            LonghandId::FontStyle => {
+                cv_builder.font_style(computed::FontStyle::value_default(ctx));
+            }
+            LonghandId::FontVariantCaps => {
+                cv_builder.font_variant_caps(computed::FontVariantCaps::value_default(ctx));
+            }
+            LonghandId::FontWeight => {
+                cv_builder.font_weight(specified::FontWeight::value_default(ctx));
+            }
             LonghandId::Height => {
                 cv_builder.height(specified::Height::value_default(ctx));
             }
@@ -501,6 +634,18 @@ impl LonghandId {
             LonghandId::MarginTop => {
                 cv_builder.margin_top(specified::MarginTop::value_default(ctx));
             }
+            LonghandId::ObjectFit => {
+                cv_builder.object_fit(computed::ObjectFit::value_default(ctx));
+            }
+            LonghandId::ObjectPosition => {
+                cv_builder.object_position(computed::ObjectPosition::value_default(ctx));
+            }
+            LonghandId::OverflowX => {
+                cv_builder.overflow_x(computed::Overflow::value_default(ctx));
+            }
+            LonghandId::OverflowY => {
+                cv_builder.overflow_y(computed::Overflow::value_default(ctx));
+            }
             LonghandId::PaddingBottom => {
                 cv_builder.padding_bottom(specified::PaddingBottom::value_default(ctx));
             }
@@ -513,9 +658,54 @@ impl LonghandId {
             LonghandId::PaddingTop => {
                 cv_builder.padding_top(specified::PaddingTop::value_default(ctx));
             }
+            LonghandId::Position => {
+                cv_builder.position(computed::Position::value_default(ctx));
+            }
+            LonghandId::ScrollBehavior => {
+                cv_builder.scroll_behavior(computed::ScrollBehavior::value_default(ctx));
+            }
+            LonghandId::ScrollMarginBottom => {
+                cv_builder.scroll_margin_bottom(specified::ScrollMargin::value_default(ctx));
+            }
+            LonghandId::ScrollMarginLeft => {
+                cv_builder.scroll_margin_left(specified::ScrollMargin::value_default(ctx));
+            }
+            LonghandId::ScrollMarginRight => {
+                cv_builder.scroll_margin_right(specified::ScrollMargin::value_default(ctx));
+            }
+            LonghandId::ScrollMarginTop => {
+                cv_builder.scroll_margin_top(specified::ScrollMargin::value_default(ctx));
+            }
+            LonghandId::ScrollPaddingBottom => {
+                cv_builder.scroll_padding_bottom(specified::ScrollPadding::value_default(ctx));
+            }
+            LonghandId::ScrollPaddingLeft => {
+                cv_builder.scroll_padding_left(specified::ScrollPadding::value_default(ctx));
+            }
+            LonghandId::ScrollPaddingRight => {
+                cv_builder.scroll_padding_right(specified::ScrollPadding::value_default(ctx));
+            }
+            LonghandId::ScrollPaddingTop => {
+                cv_builder.scroll_padding_top(specified::ScrollPadding::value_default(ctx));
+            }
+            LonghandId::ScrollSnapAlign => {
+                cv_builder.scroll_snap_align(computed::ScrollSnapAlign::value_default(ctx));
+            }
+            LonghandId::ScrollSnapType => {
+                cv_builder.scroll_snap_type(computed::ScrollSnapType::value_default(ctx));
+            }
+            LonghandId::TabSize => {
+                cv_builder.tab_size(specified::TabSize::value_default(ctx));
+            }
+            LonghandId::WhiteSpace => {
+                cv_builder.white_space(computed::WhiteSpace::value_default(ctx));
+            }
             LonghandId::Width => {
                 cv_builder.width(specified::Width::value_default(ctx));
             }
+            LonghandId::WillChange => {
+                cv_builder.will_change(computed::WillChange::value_default(ctx));
+            }
             LonghandId::WritingMode => {
                 cv_builder.writing_mode(computed::WritingMode::value_default(ctx));
             }
@@ -531,6 +721,7 @@ impl From<&PropertyDeclaration> for LonghandId {
     fn from(prop_decl: &PropertyDeclaration) -> Self {
         match prop_decl {
             PropertyDeclaration::BackgroundColor(_) => LonghandId::BackgroundColor,
+            PropertyDeclaration::BackgroundImage(_) => LonghandId::BackgroundImage,
             PropertyDeclaration::BorderBottomColor(_) => LonghandId::BorderBottomColor,
             PropertyDeclaration::BorderLeftColor(_) => LonghandId::BorderLeftColor,
             PropertyDeclaration::BorderRightColor(_) => LonghandId::BorderRightColor,
@@ -544,19 +735,51 @@ impl From<&PropertyDeclaration> for LonghandId {
             PropertyDeclaration::BorderRightWidth(_) => LonghandId::BorderRightWidth,
             PropertyDeclaration::BorderTopWidth(_) => LonghandId::BorderTopWidth,
             PropertyDeclaration::Color(_) => LonghandId::Color,
+            PropertyDeclaration::ColorScheme(_) => LonghandId::ColorScheme,
+            PropertyDeclaration::Contain(_) => LonghandId::Contain,
+            PropertyDeclaration::ContainIntrinsicHeight(_) => LonghandId::ContainIntrinsicHeight,
+            PropertyDeclaration::ContainIntrinsicWidth(_) => LonghandId::ContainIntrinsicWidth,
+            PropertyDeclaration::ContainerName(_) => LonghandId::ContainerName,
+            PropertyDeclaration::ContainerType(_) => LonghandId::ContainerType,
+            PropertyDeclaration::ContentVisibility(_) => LonghandId::ContentVisibility,
+            PropertyDeclaration::Cursor(_) => LonghandId::Cursor,
             PropertyDeclaration::Direction(_) => LonghandId::Direction,
             PropertyDeclaration::Display(_) => LonghandId::Display,
+            PropertyDeclaration::FontFamily(_) => LonghandId::FontFamily,
+            PropertyDeclaration::FontFeatureSettings(_) => LonghandId::FontFeatureSettings,
             PropertyDeclaration::FontSize(_) => LonghandId::FontSize,
+            PropertyDeclaration::FontStyle(_) => LonghandId::FontStyle,
+            PropertyDeclaration::FontVariantCaps(_) => LonghandId::FontVariantCaps,
+            PropertyDeclaration::FontWeight(_) => LonghandId::FontWeight,
             PropertyDeclaration::Height(_) => LonghandId::Height,
             PropertyDeclaration::MarginBottom(_) => LonghandId::MarginBottom,
             PropertyDeclaration::MarginLeft(_) => LonghandId::MarginLeft,
             PropertyDeclaration::MarginRight(_) => LonghandId::MarginRight,
             PropertyDeclaration::MarginTop(_) => LonghandId::MarginTop,
+            PropertyDeclaration::ObjectFit(_) => LonghandId::ObjectFit,
+            PropertyDeclaration::ObjectPosition(_) => LonghandId::ObjectPosition,
+            PropertyDeclaration::OverflowX(_) => LonghandId::OverflowX,
+            PropertyDeclaration::OverflowY(_) => LonghandId::OverflowY,
             PropertyDeclaration::PaddingBottom(_) => LonghandId::PaddingBottom,
             PropertyDeclaration::PaddingLeft(_) => LonghandId::PaddingLeft,
             PropertyDeclaration::PaddingRight(_) => LonghandId::PaddingRight,
             PropertyDeclaration::PaddingTop(_) => LonghandId::PaddingTop,
+            PropertyDeclaration::Position(_) => LonghandId::Position,
+            PropertyDeclaration::ScrollBehavior(_) => LonghandId::ScrollBehavior,
+            PropertyDeclaration::ScrollMarginBottom(_) => LonghandId::ScrollMarginBottom,
+            PropertyDeclaration::ScrollMarginLeft(_) => LonghandId::ScrollMarginLeft,
+            PropertyDeclaration::ScrollMarginRight(_) => LonghandId::ScrollMarginRight,
+            PropertyDeclaration::ScrollMarginTop(_) => LonghandId::ScrollMarginTop,
+            PropertyDeclaration::ScrollPaddingBottom(_) => LonghandId::ScrollPaddingBottom,
+            PropertyDeclaration::ScrollPaddingLeft(_) => LonghandId::ScrollPaddingLeft,
+            PropertyDeclaration::ScrollPaddingRight(_) => LonghandId::ScrollPaddingRight,
+            PropertyDeclaration::ScrollPaddingTop(_) => LonghandId::ScrollPaddingTop,
+            PropertyDeclaration::ScrollSnapAlign(_) => LonghandId::ScrollSnapAlign,
+            PropertyDeclaration::ScrollSnapType(_) => LonghandId::ScrollSnapType,
+            PropertyDeclaration::TabSize(_) => LonghandId::TabSize,
+            PropertyDeclaration::WhiteSpace(_) => LonghandId::WhiteSpace,
             PropertyDeclaration::Width(_) => LonghandId::Width,
+            PropertyDeclaration::WillChange(_) => LonghandId::WillChange,
             PropertyDeclaration::WritingMode(_) => LonghandId::WritingMode,
         }
     }
@@ -632,8 +855,8 @@ pub enum ShorthandId {
     //    BorderBlock = 22,
     //    /// border-inline
     //    BorderInline = 23,
-    //    /// overflow
-    //    Overflow = 24,
+    /// overflow
+    Overflow = 24,
     //    /// transition
     //    Transition = 25,
     //    /// animation
@@ -642,8 +865,8 @@ pub enum ShorthandId {
     //    Columns = 27,
     //    /// font
     //    Font = 28,
-    //    /// font-variant
-    //    FontVariant = 29,
+    /// font-variant
+    FontVariant = 29,
     //    /// list-style
     //    ListStyle = 30,
     /// margin
@@ -674,4 +897,32 @@ pub enum ShorthandId {
     //    TextDecoration = 43,
     //    /// all
     //    All = 44,
+    /// scroll-margin
+    ScrollMargin = 45,
+    /// scroll-padding
+    ScrollPadding = 46,
+    /// contain-intrinsic-size
+    ContainIntrinsicSize = 47,
+    /// container
+    Container = 48,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use strum::IntoEnumIterator;
+
+    #[test]
+    fn longhand_names_parse_back_into_their_ids() {
+        assert_eq!(
+            LonghandId::FontFeatureSettings.name(),
+            "font-feature-settings"
+        );
+        for longhand in LonghandId::iter() {
+            assert_eq!(
+                PropertyId::parse(longhand.name()),
+                Some(PropertyId::Longhand(longhand))
+            );
+        }
+    }
 }
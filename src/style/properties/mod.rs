@@ -8,17 +8,24 @@ use cssparser::{
 };
 use smallbitvec::SmallBitVec;
 
-use crate::style::properties::id::{LonghandId, PropertyId};
+use crate::style::diagnostics::CssDiagnostic;
+use crate::style::properties::id::{LonghandId, PropertyId, ShorthandId};
 use crate::style::select::Specificity;
 use crate::style::values::computed::direction::WritingMode;
-use crate::style::values::computed::{Direction, Display, LineStyle};
+use crate::style::values::computed::{
+    BackgroundImage, Contain, ContainerName, ContainerType, ContentVisibility, Cursor, Direction,
+    Display, FontFamily, FontFeatureSettings, FontStyle, FontVariantCaps, LineStyle, ObjectFit,
+    ObjectPosition, Overflow, Position, ScrollBehavior, ScrollSnapAlign, ScrollSnapType,
+    SupportedColorSchemes, WhiteSpace, WillChange,
+};
 use crate::style::values::specified::border::{
     BorderBottomColor, BorderLeftColor, BorderRightColor, BorderTopColor,
 };
 use crate::style::values::specified::{
     BackgroundColor, BorderBottomWidth, BorderLeftWidth, BorderRightWidth, BorderTopWidth, Color,
-    FontSize, Height, MarginBottom, MarginLeft, MarginRight, MarginTop, PaddingBottom, PaddingLeft,
-    PaddingRight, PaddingTop, Width,
+    ContainIntrinsicSize, FontSize, FontWeight, Height, MarginBottom, MarginLeft, MarginRight,
+    MarginTop, PaddingBottom, PaddingLeft, PaddingRight, PaddingTop, ScrollMargin, ScrollPadding,
+    TabSize, Width,
 };
 use crate::style::CascadeOrigin;
 use crate::style::{CssOrigin, StyleParseErrorKind};
@@ -27,6 +34,15 @@ pub mod id;
 
 /// Parses raw parser input into a block of property declarations.
 pub fn parse_property_declaration_list(input: &mut Parser) -> PropertyDeclarationBlock {
+    parse_property_declaration_list_with_diagnostics(input, &mut Vec::new())
+}
+
+/// Like `parse_property_declaration_list`, but adds a diagnostic to `diagnostics` for each
+/// declaration that's dropped.
+pub fn parse_property_declaration_list_with_diagnostics(
+    input: &mut Parser,
+    diagnostics: &mut Vec<CssDiagnostic>,
+) -> PropertyDeclarationBlock {
     let mut block = PropertyDeclarationBlock::new();
     let prop_parser = PropertyDeclarationParser {
         declarations: Vec::new(),
@@ -41,8 +57,8 @@ pub fn parse_property_declaration_list(input: &mut Parser) -> PropertyDeclaratio
                     block.add_declaration(decl.clone(), importance);
                 }
             }
-            Err(parse_err) => {
-                dbg!(parse_err);
+            Err((err, declaration)) => {
+                diagnostics.push(CssDiagnostic::for_declaration(&err, declaration));
             }
         }
     }
@@ -145,6 +161,7 @@ impl PropertyDeclarationBlock {
 
     pub fn remove_decl(&mut self, index: usize) {
         self.declarations.remove(index);
+        self.declarations_importance.remove(index);
     }
 
     pub fn declarations_importance(&self) -> &SmallBitVec {
@@ -164,6 +181,9 @@ impl PropertyDeclaration {
                 LonghandId::BackgroundColor => declarations.push(
                     PropertyDeclaration::BackgroundColor(BackgroundColor::parse(input)?),
                 ),
+                LonghandId::BackgroundImage => declarations.push(
+                    PropertyDeclaration::BackgroundImage(BackgroundImage::parse(input)?),
+                ),
                 LonghandId::BorderBottomColor => declarations.push(
                     PropertyDeclaration::BorderBottomColor(BorderBottomColor::parse(input)?),
                 ),
@@ -203,15 +223,60 @@ impl PropertyDeclaration {
                 LonghandId::Color => {
                     declarations.push(PropertyDeclaration::Color(Color::parse(input)?))
                 }
+                LonghandId::ColorScheme => declarations.push(PropertyDeclaration::ColorScheme(
+                    SupportedColorSchemes::parse(input)?,
+                )),
+                LonghandId::Contain => {
+                    declarations.push(PropertyDeclaration::Contain(Contain::parse(input)?))
+                }
+                LonghandId::ContainIntrinsicHeight => {
+                    declarations.push(PropertyDeclaration::ContainIntrinsicHeight(
+                        ContainIntrinsicSize::parse(input)?,
+                    ))
+                }
+                LonghandId::ContainIntrinsicWidth => declarations.push(
+                    PropertyDeclaration::ContainIntrinsicWidth(ContainIntrinsicSize::parse(input)?),
+                ),
+                LonghandId::ContainerName => declarations.push(PropertyDeclaration::ContainerName(
+                    ContainerName::parse(input)?,
+                )),
+                LonghandId::ContainerType => declarations.push(PropertyDeclaration::ContainerType(
+                    ContainerType::parse(input)?,
+                )),
+                LonghandId::ContentVisibility => declarations.push(
+                    PropertyDeclaration::ContentVisibility(ContentVisibility::parse(input)?),
+                ),
+                LonghandId::Cursor => {
+                    declarations.push(PropertyDeclaration::Cursor(Cursor::parse(input)?))
+                }
                 LonghandId::Direction => {
                     declarations.push(PropertyDeclaration::Direction(Direction::parse(input)?))
                 }
                 LonghandId::Display => {
                     declarations.push(PropertyDeclaration::Display(Display::parse(input)?))
                 }
+                LonghandId::FontFamily => {
+                    declarations.push(PropertyDeclaration::FontFamily(FontFamily::parse(input)?));
+                }
+                LonghandId::FontFeatureSettings => {
+                    declarations.push(PropertyDeclaration::FontFeatureSettings(
+                        FontFeatureSettings::parse(input)?,
+                    ));
+                }
                 LonghandId::FontSize => {
                     declarations.push(PropertyDeclaration::FontSize(FontSize::parse(input)?));
                 }
+                LonghandId::FontStyle => {
+                    declarations.push(PropertyDeclaration::FontStyle(FontStyle::parse(input)?));
+                }
+                LonghandId::FontVariantCaps => {
+                    declarations.push(PropertyDeclaration::FontVariantCaps(
+                        FontVariantCaps::parse(input)?,
+                    ));
+                }
+                LonghandId::FontWeight => {
+                    declarations.push(PropertyDeclaration::FontWeight(FontWeight::parse(input)?));
+                }
                 LonghandId::Height => {
                     declarations.push(PropertyDeclaration::Height(Height::parse(input)?));
                 }
@@ -229,6 +294,20 @@ impl PropertyDeclaration {
                 LonghandId::MarginTop => {
                     declarations.push(PropertyDeclaration::MarginTop(MarginTop::parse(input)?));
                 }
+                LonghandId::ObjectFit => {
+                    declarations.push(PropertyDeclaration::ObjectFit(ObjectFit::parse(input)?));
+                }
+                LonghandId::ObjectPosition => {
+                    declarations.push(PropertyDeclaration::ObjectPosition(ObjectPosition::parse(
+                        input,
+                    )?));
+                }
+                LonghandId::OverflowX => {
+                    declarations.push(PropertyDeclaration::OverflowX(Overflow::parse(input)?));
+                }
+                LonghandId::OverflowY => {
+                    declarations.push(PropertyDeclaration::OverflowY(Overflow::parse(input)?));
+                }
                 LonghandId::PaddingBottom => {
                     declarations.push(PropertyDeclaration::PaddingBottom(PaddingBottom::parse(
                         input,
@@ -245,9 +324,54 @@ impl PropertyDeclaration {
                 LonghandId::PaddingTop => {
                     declarations.push(PropertyDeclaration::PaddingTop(PaddingTop::parse(input)?));
                 }
+                LonghandId::Position => {
+                    declarations.push(PropertyDeclaration::Position(Position::parse(input)?));
+                }
+                LonghandId::ScrollBehavior => declarations.push(
+                    PropertyDeclaration::ScrollBehavior(ScrollBehavior::parse(input)?),
+                ),
+                LonghandId::ScrollMarginBottom => declarations.push(
+                    PropertyDeclaration::ScrollMarginBottom(ScrollMargin::parse(input)?),
+                ),
+                LonghandId::ScrollMarginLeft => declarations.push(
+                    PropertyDeclaration::ScrollMarginLeft(ScrollMargin::parse(input)?),
+                ),
+                LonghandId::ScrollMarginRight => declarations.push(
+                    PropertyDeclaration::ScrollMarginRight(ScrollMargin::parse(input)?),
+                ),
+                LonghandId::ScrollMarginTop => declarations.push(
+                    PropertyDeclaration::ScrollMarginTop(ScrollMargin::parse(input)?),
+                ),
+                LonghandId::ScrollPaddingBottom => declarations.push(
+                    PropertyDeclaration::ScrollPaddingBottom(ScrollPadding::parse(input)?),
+                ),
+                LonghandId::ScrollPaddingLeft => declarations.push(
+                    PropertyDeclaration::ScrollPaddingLeft(ScrollPadding::parse(input)?),
+                ),
+                LonghandId::ScrollPaddingRight => declarations.push(
+                    PropertyDeclaration::ScrollPaddingRight(ScrollPadding::parse(input)?),
+                ),
+                LonghandId::ScrollPaddingTop => declarations.push(
+                    PropertyDeclaration::ScrollPaddingTop(ScrollPadding::parse(input)?),
+                ),
+                LonghandId::ScrollSnapAlign => declarations.push(
+                    PropertyDeclaration::ScrollSnapAlign(ScrollSnapAlign::parse(input)?),
+                ),
+                LonghandId::ScrollSnapType => declarations.push(
+                    PropertyDeclaration::ScrollSnapType(ScrollSnapType::parse(input)?),
+                ),
+                LonghandId::TabSize => {
+                    declarations.push(PropertyDeclaration::TabSize(TabSize::parse(input)?));
+                }
+                LonghandId::WhiteSpace => {
+                    declarations.push(PropertyDeclaration::WhiteSpace(WhiteSpace::parse(input)?));
+                }
                 LonghandId::Width => {
                     declarations.push(PropertyDeclaration::Width(Width::parse(input)?));
                 }
+                LonghandId::WillChange => {
+                    declarations.push(PropertyDeclaration::WillChange(WillChange::parse(input)?));
+                }
                 LonghandId::WritingMode => {
                     declarations.push(PropertyDeclaration::WritingMode(WritingMode::parse(input)?));
                 }
@@ -256,6 +380,42 @@ impl PropertyDeclaration {
                     format!("value default by longhand for id: {:?}", longhand)
                 ),
             },
+            // Of the longhands `font-variant` sets, only `font-variant-caps` is supported, so only
+            // its values (along with `normal`) are accepted.
+            //
+            // https://drafts.csswg.org/css-fonts-4/#font-variant-prop
+            PropertyId::Shorthand(ShorthandId::FontVariant) => declarations.push(
+                PropertyDeclaration::FontVariantCaps(FontVariantCaps::parse(input)?),
+            ),
+            PropertyId::Shorthand(ShorthandId::Overflow) => {
+                let (overflow_x, overflow_y) = Overflow::parse_shorthand(input)?;
+                declarations.push(PropertyDeclaration::OverflowX(overflow_x));
+                declarations.push(PropertyDeclaration::OverflowY(overflow_y));
+            }
+            PropertyId::Shorthand(ShorthandId::ScrollMargin) => {
+                let [top, right, bottom, left] = ScrollMargin::parse_shorthand(input)?;
+                declarations.push(PropertyDeclaration::ScrollMarginTop(top));
+                declarations.push(PropertyDeclaration::ScrollMarginRight(right));
+                declarations.push(PropertyDeclaration::ScrollMarginBottom(bottom));
+                declarations.push(PropertyDeclaration::ScrollMarginLeft(left));
+            }
+            PropertyId::Shorthand(ShorthandId::ScrollPadding) => {
+                let [top, right, bottom, left] = ScrollPadding::parse_shorthand(input)?;
+                declarations.push(PropertyDeclaration::ScrollPaddingTop(top));
+                declarations.push(PropertyDeclaration::ScrollPaddingRight(right));
+                declarations.push(PropertyDeclaration::ScrollPaddingBottom(bottom));
+                declarations.push(PropertyDeclaration::ScrollPaddingLeft(left));
+            }
+            PropertyId::Shorthand(ShorthandId::ContainIntrinsicSize) => {
+                let [width, height] = ContainIntrinsicSize::parse_shorthand(input)?;
+                declarations.push(PropertyDeclaration::ContainIntrinsicWidth(width));
+                declarations.push(PropertyDeclaration::ContainIntrinsicHeight(height));
+            }
+            PropertyId::Shorthand(ShorthandId::Container) => {
+                let (name, container_type) = ContainerName::parse_shorthand(input)?;
+                declarations.push(PropertyDeclaration::ContainerName(name));
+                declarations.push(PropertyDeclaration::ContainerType(container_type));
+            }
             PropertyId::Shorthand(_short_id) => {}
         }
         Ok(())
@@ -266,6 +426,7 @@ impl PropertyDeclaration {
 #[repr(u16)]
 pub enum PropertyDeclaration {
     BackgroundColor(crate::style::values::specified::BackgroundColor),
+    BackgroundImage(crate::style::values::computed::BackgroundImage),
     BorderBottomColor(crate::style::values::specified::BorderBottomColor),
     BorderLeftColor(crate::style::values::specified::BorderLeftColor),
     BorderRightColor(crate::style::values::specified::BorderRightColor),
@@ -279,19 +440,51 @@ pub enum PropertyDeclaration {
     BorderRightWidth(crate::style::values::specified::BorderRightWidth),
     BorderTopWidth(crate::style::values::specified::BorderTopWidth),
     Color(crate::style::values::specified::Color),
+    ColorScheme(crate::style::values::computed::SupportedColorSchemes),
+    Contain(crate::style::values::computed::Contain),
+    ContainIntrinsicHeight(crate::style::values::specified::ContainIntrinsicSize),
+    ContainIntrinsicWidth(crate::style::values::specified::ContainIntrinsicSize),
+    ContainerName(crate::style::values::computed::ContainerName),
+    ContainerType(crate::style::values::computed::ContainerType),
+    ContentVisibility(crate::style::values::computed::ContentVisibility),
+    Cursor(crate::style::values::computed::Cursor),
     Direction(crate::style::values::computed::Direction),
     Display(crate::style::values::computed::Display),
+    FontFamily(crate::style::values::computed::FontFamily),
+    FontFeatureSettings(crate::style::values::computed::FontFeatureSettings),
     FontSize(crate::style::values::specified::FontSize),
+    FontStyle(crate::style::values::computed::FontStyle),
+    FontVariantCaps(crate::style::values::computed::FontVariantCaps),
+    FontWeight(crate::style::values::specified::FontWeight),
     Height(crate::style::values::specified::Height),
     MarginBottom(crate::style::values::specified::MarginBottom),
     MarginLeft(crate::style::values::specified::MarginLeft),
     MarginRight(crate::style::values::specified::MarginRight),
     MarginTop(crate::style::values::specified::MarginTop),
+    ObjectFit(crate::style::values::computed::ObjectFit),
+    ObjectPosition(crate::style::values::computed::ObjectPosition),
+    OverflowX(crate::style::values::computed::Overflow),
+    OverflowY(crate::style::values::computed::Overflow),
     PaddingBottom(crate::style::values::specified::PaddingBottom),
     PaddingLeft(crate::style::values::specified::PaddingLeft),
     PaddingRight(crate::style::values::specified::PaddingRight),
     PaddingTop(crate::style::values::specified::PaddingTop),
+    Position(crate::style::values::computed::Position),
+    ScrollBehavior(crate::style::values::computed::ScrollBehavior),
+    ScrollMarginBottom(crate::style::values::specified::ScrollMargin),
+    ScrollMarginLeft(crate::style::values::specified::ScrollMargin),
+    ScrollMarginRight(crate::style::values::specified::ScrollMargin),
+    ScrollMarginTop(crate::style::values::specified::ScrollMargin),
+    ScrollPaddingBottom(crate::style::values::specified::ScrollPadding),
+    ScrollPaddingLeft(crate::style::values::specified::ScrollPadding),
+    ScrollPaddingRight(crate::style::values::specified::ScrollPadding),
+    ScrollPaddingTop(crate::style::values::specified::ScrollPadding),
+    ScrollSnapAlign(crate::style::values::computed::ScrollSnapAlign),
+    ScrollSnapType(crate::style::values::computed::ScrollSnapType),
+    TabSize(crate::style::values::specified::TabSize),
+    WhiteSpace(crate::style::values::computed::WhiteSpace),
     Width(crate::style::values::specified::Width),
+    WillChange(crate::style::values::computed::WillChange),
     WritingMode(crate::style::values::computed::WritingMode),
 }
 
@@ -316,11 +509,6 @@ pub struct ContextualPropertyDeclarations {
     decls: Vec<ContextualPropertyDeclaration>,
     /// The LonghandIds present in this container.
     longhands: HashSet<LonghandId>,
-    /// Whether or not the property declarations are entirely sorted according to the cascade
-    /// algorithm.  Properties that lose the cascade will still be present in `decls`, but
-    /// will be found later in the `Vec`.
-    /// https://www.w3.org/TR/2018/CR-css-cascade-3-20180828/#cascading
-    is_sorted: bool,
 }
 
 impl ContextualPropertyDeclarations {
@@ -329,27 +517,13 @@ impl ContextualPropertyDeclarations {
         ContextualPropertyDeclarations::default()
     }
 
-    /// Sort according to the cascade algorithm.  Declarations are sorted from least applicable to
-    /// most applicable, meaning the last instance of a given property is the winner of the cascade.
-    ///
-    /// https://www.w3.org/TR/2018/CR-css-cascade-3-20180828/#cascading
-    #[inline]
-    pub fn cascade_sort(&mut self) {
-        // ContextualPropertyDeclarations override `Ord`, so this will sort by origin, importance,
-        // specificity.
-        self.decls.as_mut_slice().sort();
-        // After sorting by origin, importance, and specificity, rules must also be sorted by order
-        // of appearance.  However, we don't need to do anything to uphold that variant, since later
-        // rules are naturally pushed to the end of the Vec.
-        self.is_sorted = true;
-    }
-
     #[inline]
     pub fn contains(&self, longhand: LonghandId) -> bool {
         self.longhands.contains(&longhand)
     }
 
-    /// Finds the first matching `ContextualPropertyDeclaration` by `LonghandId`.
+    /// Finds the last `ContextualPropertyDeclaration` of `longhand`, which wins the cascade when
+    /// all of the declarations share an origin, importance, and specificity.
     #[inline]
     pub fn get_by_longhand(&self, longhand: LonghandId) -> Option<&ContextualPropertyDeclaration> {
         if !self.contains(longhand) {
@@ -367,7 +541,6 @@ impl ContextualPropertyDeclarations {
         self.longhands
             .insert(LonghandId::from(&new_decl.inner_decl));
         self.decls.push(new_decl);
-        self.is_sorted = false;
     }
 }
 
@@ -376,7 +549,6 @@ impl Default for ContextualPropertyDeclarations {
         ContextualPropertyDeclarations {
             decls: Vec::default(),
             longhands: HashSet::default(),
-            is_sorted: true,
         }
     }
 }
@@ -431,42 +603,21 @@ impl Ord for ContextualPropertyDeclaration {
         return Ordering::Equal;
 
         fn cmp_important_origins(a: &CssOrigin, b: &CssOrigin) -> Ordering {
-            match (a, b) {
-                (CssOrigin::Inline, CssOrigin::Inline)
-                | (CssOrigin::Inline, CssOrigin::Embedded)
-                | (CssOrigin::Embedded, CssOrigin::Inline)
-                | (CssOrigin::Embedded, CssOrigin::Embedded) => Ordering::Equal,
-                (CssOrigin::Inline, CssOrigin::Sheet(other_sheet_origin))
-                | (CssOrigin::Embedded, CssOrigin::Sheet(other_sheet_origin)) => {
-                    match &other_sheet_origin.cascade_origin {
-                        CascadeOrigin::UserAgent | CascadeOrigin::User => Ordering::Less,
-                        CascadeOrigin::Author => Ordering::Equal,
-                    }
-                }
-                (CssOrigin::Sheet(self_sheet_origin), CssOrigin::Inline)
-                | (CssOrigin::Sheet(self_sheet_origin), CssOrigin::Embedded) => {
-                    match &self_sheet_origin.cascade_origin {
-                        CascadeOrigin::UserAgent | CascadeOrigin::User => Ordering::Greater,
-                        CascadeOrigin::Author => Ordering::Equal,
-                    }
-                }
-                (CssOrigin::Sheet(self_sheet_origin), CssOrigin::Sheet(other_sheet_origin)) => {
-                    match (
-                        &self_sheet_origin.cascade_origin,
-                        &other_sheet_origin.cascade_origin,
-                    ) {
-                        (CascadeOrigin::UserAgent, CascadeOrigin::UserAgent) => Ordering::Equal,
-                        (CascadeOrigin::UserAgent, CascadeOrigin::User)
-                        | (CascadeOrigin::UserAgent, CascadeOrigin::Author) => Ordering::Greater,
-                        (CascadeOrigin::User, CascadeOrigin::UserAgent) => Ordering::Less,
-                        (CascadeOrigin::User, CascadeOrigin::User) => Ordering::Equal,
-                        (CascadeOrigin::User, CascadeOrigin::Author) => Ordering::Greater,
-                        (CascadeOrigin::Author, CascadeOrigin::UserAgent)
-                        | (CascadeOrigin::Author, CascadeOrigin::User) => Ordering::Less,
-                        (CascadeOrigin::Author, CascadeOrigin::Author) => Ordering::Equal,
+            // Inline and embedded styles and presentational hints have an author origin.
+            fn rank(origin: &CssOrigin) -> u8 {
+                let cascade_origin = match origin {
+                    CssOrigin::Inline | CssOrigin::Embedded | CssOrigin::PresentationalHint => {
+                        &CascadeOrigin::Author
                     }
+                    CssOrigin::Sheet(sheet_origin) => &sheet_origin.cascade_origin,
+                };
+                match cascade_origin {
+                    CascadeOrigin::Author => 0,
+                    CascadeOrigin::User => 1,
+                    CascadeOrigin::UserAgent => 2,
                 }
             }
+            rank(a).cmp(&rank(b))
         }
     }
 }
@@ -514,9 +665,11 @@ mod tests {
     use crate::style::test_utils::{display_by_type, font_size_px, font_size_px_or_panic};
 
     use super::*;
+    use crate::style::rule_tree::{ApplicableDeclarationBlock, RuleTree, StyleSource};
     use crate::style::values::computed::Display;
     use crate::style::values::specified::{AbsoluteLength, LengthPercentage, NoCalcLength};
     use crate::style::StylesheetOrigin;
+    use cssparser::ParserInput;
     use std::clone::Clone;
 
     #[test]
@@ -658,12 +811,16 @@ mod tests {
             sheet_name: "author_sheet.css".to_owned(),
             cascade_origin: CascadeOrigin::Author,
         });
-        let mut decls = ContextualPropertyDeclarations::new();
-        decls.add(ua_decl);
-        decls.add(author_decl);
-        decls.cascade_sort();
-        assert!(decls.is_sorted);
-        let last_display = decls
+        let source = |rule_index| StyleSource::Rule {
+            sheet_id: 0,
+            rule_index,
+        };
+        // The author declaration appears first, but is still more applicable.
+        let rule_node = RuleTree::new().insert(vec![
+            ApplicableDeclarationBlock::new(source(0), vec![author_decl]).unwrap(),
+            ApplicableDeclarationBlock::new(source(1), vec![ua_decl]).unwrap(),
+        ]);
+        let last_display = rule_node
             .get_by_longhand(LonghandId::Display)
             .expect("decl_sort_order_of_appearance should get display");
         match last_display.inner_decl {
@@ -685,13 +842,11 @@ mod tests {
         decls.add(font_size_px(20.0));
         decls.add(display_by_type(Display::new_inline()));
 
-        decls.cascade_sort();
-        assert!(decls.is_sorted);
         let last_font_size = decls
             .get_by_longhand(LonghandId::FontSize)
             .expect("decl_sort_order_of_appearance should get font_size");
-        // This should've been sorted to the top, since most recent / latest added declarations that are otherwise equal
-        // take precedence over later ones.
+        // The most recent / latest added declarations that are otherwise equal take precedence over
+        // earlier ones.
         assert_eq!(font_size_px_or_panic(&last_font_size.inner_decl), &20.0);
         let last_display = decls
             .get_by_longhand(LonghandId::Display)
@@ -749,4 +904,15 @@ mod tests {
         assert_eq!(decl_block.declarations.len(), 1);
         assert_eq!(&24.0, font_size_px_or_panic(&decl_block.declarations[0]));
     }
+
+    #[test]
+    fn font_variant_sets_font_variant_caps() {
+        let mut input = ParserInput::new("font-variant: small-caps");
+        let block = parse_property_declaration_list(&mut Parser::new(&mut input));
+        assert_eq!(block.declarations.len(), 1);
+        assert!(matches!(
+            block.declarations[0],
+            PropertyDeclaration::FontVariantCaps(FontVariantCaps::SmallCaps)
+        ));
+    }
 }
@@ -0,0 +1,252 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::iter::successors;
+use std::rc::{Rc, Weak};
+
+use crate::style::properties::id::LonghandId;
+use crate::style::properties::{ContextualPropertyDeclaration, ContextualPropertyDeclarations};
+use crate::style::select::Specificity;
+use crate::style::{CascadeOrigin, CssOrigin};
+
+/// The declaration blocks that apply to the elements of a document, arranged in a tree so that
+/// elements matching the same rules share them.  Each rule node adds one block of declarations to
+/// those of its ancestors, which precede it in the cascade, so the path from the root to an
+/// element's rule node holds every declaration that applies to the element, from least to most
+/// applicable.
+///
+/// Since elements that match the same rules share a rule node, checking whether the cascaded
+/// declarations of an element changed (e.g. after restyling it) is as cheap as comparing pointers.
+///
+/// Based on Servo's rule tree:
+/// https://github.com/servo/servo/blob/d2856ce8aeca11e543bc4d9f869400d73451374e/components/style/rule_tree/mod.rs
+#[derive(Clone, Debug, Default)]
+pub struct RuleTree {
+    root: Rc<RuleNode>,
+}
+
+/// Rule trees are the same if they share a root.
+impl PartialEq for RuleTree {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.root, &other.root)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RuleNode {
+    parent: Option<Rc<RuleNode>>,
+    declarations: ContextualPropertyDeclarations,
+    /// The children of this node, keyed by the declarations they add.  A rule node is dropped
+    /// once no element uses it, so these references are weak.
+    children: RefCell<HashMap<RuleNodeKey, Weak<RuleNode>>>,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct RuleNodeKey {
+    source: StyleSource,
+    level: CascadeLevel,
+    specificity: Specificity,
+}
+
+/// Where a block of declarations comes from.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum StyleSource {
+    /// A style rule, identified by the id of its stylesheet and its index in it.
+    Rule { sheet_id: usize, rule_index: usize },
+    /// The `style` attribute of an element, identified by its value.
+    Inline(String),
+    /// The presentational hints of an element, identified by the declarations they map to.
+    PresentationalHints(String),
+}
+
+/// The origin and importance of a declaration, in ascending order of precedence.
+///
+/// https://www.w3.org/TR/2018/CR-css-cascade-3-20180828/#cascade-origin
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum CascadeLevel {
+    UserAgentNormal,
+    UserNormal,
+    /// Presentational hints are author declarations that precede every other author declaration.
+    PresentationalHints,
+    AuthorNormal,
+    AuthorImportant,
+    UserImportant,
+    UserAgentImportant,
+}
+
+impl CascadeLevel {
+    /// Inline and embedded styles have an author origin.
+    pub fn new(origin: &CssOrigin, important: bool) -> CascadeLevel {
+        let cascade_origin = match origin {
+            CssOrigin::PresentationalHint => return CascadeLevel::PresentationalHints,
+            CssOrigin::Inline | CssOrigin::Embedded => &CascadeOrigin::Author,
+            CssOrigin::Sheet(sheet_origin) => &sheet_origin.cascade_origin,
+        };
+        match (cascade_origin, important) {
+            (CascadeOrigin::UserAgent, false) => CascadeLevel::UserAgentNormal,
+            (CascadeOrigin::User, false) => CascadeLevel::UserNormal,
+            (CascadeOrigin::Author, false) => CascadeLevel::AuthorNormal,
+            (CascadeOrigin::Author, true) => CascadeLevel::AuthorImportant,
+            (CascadeOrigin::User, true) => CascadeLevel::UserImportant,
+            (CascadeOrigin::UserAgent, true) => CascadeLevel::UserAgentImportant,
+        }
+    }
+}
+
+/// A block of declarations from the same source, with the same importance, that applies to an
+/// element.  Blocks are collected while matching, then inserted into the rule tree.
+#[derive(Clone, Debug)]
+pub struct ApplicableDeclarationBlock {
+    key: RuleNodeKey,
+    declarations: ContextualPropertyDeclarations,
+}
+
+impl ApplicableDeclarationBlock {
+    /// Gathers `declarations`, which must all share an origin, importance, and specificity, into
+    /// a block.  Returns `None` if there aren't any.
+    pub fn new(
+        source: StyleSource,
+        declarations: Vec<ContextualPropertyDeclaration>,
+    ) -> Option<ApplicableDeclarationBlock> {
+        let first = declarations.first()?;
+        let key = RuleNodeKey {
+            source,
+            level: CascadeLevel::new(&first.origin, first.important),
+            specificity: first.specificity,
+        };
+        let mut block = ContextualPropertyDeclarations::new();
+        declarations.into_iter().for_each(|decl| block.add(decl));
+        Some(ApplicableDeclarationBlock {
+            key,
+            declarations: block,
+        })
+    }
+}
+
+impl RuleTree {
+    pub fn new() -> RuleTree {
+        RuleTree::default()
+    }
+
+    /// Returns the rule node for an element the given `blocks` apply to, given in order of
+    /// appearance, creating it (and its ancestors) if no element uses it yet.
+    ///
+    /// https://www.w3.org/TR/2018/CR-css-cascade-3-20180828/#cascading
+    pub fn insert(&self, mut blocks: Vec<ApplicableDeclarationBlock>) -> Rc<RuleNode> {
+        // Sort by origin, importance, and specificity.  The sort is stable, so blocks that are
+        // otherwise equal stay in order of appearance.
+        blocks.sort_by_key(|block| (block.key.level, block.key.specificity));
+        blocks
+            .into_iter()
+            .fold(self.root.clone(), |node, block| node.ensure_child(block))
+    }
+}
+
+impl RuleNode {
+    /// Finds the declaration of `longhand` that wins the cascade among those of this node and its
+    /// ancestors.
+    pub fn get_by_longhand(&self, longhand: LonghandId) -> Option<&ContextualPropertyDeclaration> {
+        successors(Some(self), |node| node.parent.as_deref())
+            .find_map(|node| node.declarations.get_by_longhand(longhand))
+    }
+
+    fn ensure_child(self: Rc<Self>, block: ApplicableDeclarationBlock) -> Rc<RuleNode> {
+        let mut children = self.children.borrow_mut();
+        if let Some(child) = children.get(&block.key).and_then(Weak::upgrade) {
+            return child;
+        }
+        // Forget children that were dropped, as we're adding one anyway.
+        children.retain(|_, child| child.strong_count() > 0);
+        let child = Rc::new(RuleNode {
+            parent: Some(self.clone()),
+            declarations: block.declarations,
+            children: RefCell::new(HashMap::new()),
+        });
+        children.insert(block.key, Rc::downgrade(&child));
+        child
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::style::properties::PropertyDeclaration;
+    use crate::style::values::computed::Display;
+    use crate::style::StylesheetOrigin;
+
+    fn display_decl(
+        display: Display,
+        cascade_origin: CascadeOrigin,
+        important: bool,
+    ) -> ContextualPropertyDeclaration {
+        ContextualPropertyDeclaration {
+            inner_decl: PropertyDeclaration::Display(display),
+            important,
+            origin: CssOrigin::Sheet(StylesheetOrigin {
+                sheet_name: "file.css".to_owned(),
+                cascade_origin,
+            }),
+            source_location: None,
+            specificity: Specificity::new(0),
+        }
+    }
+
+    fn block(rule_index: usize, decl: ContextualPropertyDeclaration) -> ApplicableDeclarationBlock {
+        let source = StyleSource::Rule {
+            sheet_id: 0,
+            rule_index,
+        };
+        ApplicableDeclarationBlock::new(source, vec![decl]).unwrap()
+    }
+
+    fn cascaded_display(rule_node: &RuleNode) -> Display {
+        match rule_node
+            .get_by_longhand(LonghandId::Display)
+            .unwrap()
+            .inner_decl
+        {
+            PropertyDeclaration::Display(display) => display,
+            _ => panic!("this should've been a display property"),
+        }
+    }
+
+    #[test]
+    fn elements_matching_the_same_rules_share_rule_nodes() {
+        let rule_tree = RuleTree::new();
+        let ua = block(
+            0,
+            display_decl(Display::new_block(), CascadeOrigin::UserAgent, false),
+        );
+        let author = block(
+            1,
+            display_decl(Display::new_none(), CascadeOrigin::Author, false),
+        );
+        let first = rule_tree.insert(vec![ua.clone(), author.clone()]);
+        let second = rule_tree.insert(vec![ua.clone(), author]);
+        assert!(Rc::ptr_eq(&first, &second));
+        let ua_only = rule_tree.insert(vec![ua]);
+        assert!(Rc::ptr_eq(first.parent.as_ref().unwrap(), &ua_only));
+    }
+
+    #[test]
+    fn blocks_are_ordered_by_the_cascade() {
+        let rule_tree = RuleTree::new();
+        let author = block(
+            0,
+            display_decl(Display::new_none(), CascadeOrigin::Author, false),
+        );
+        let ua = block(
+            1,
+            display_decl(Display::new_block(), CascadeOrigin::UserAgent, false),
+        );
+        let important_ua = block(
+            2,
+            display_decl(Display::new_inline(), CascadeOrigin::UserAgent, true),
+        );
+        // Author declarations win over user agent declarations, even if they appear earlier.
+        let rule_node = rule_tree.insert(vec![author.clone(), ua.clone()]);
+        assert_eq!(cascaded_display(&rule_node), Display::new_none());
+        // Unless the user agent declarations are important.
+        let rule_node = rule_tree.insert(vec![important_ua, author, ua]);
+        assert_eq!(cascaded_display(&rule_node), Display::new_inline());
+    }
+}
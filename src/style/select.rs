@@ -1,7 +1,9 @@
 /// This file is a direct copy-paste from [Kuchiki](https://github.com/kuchiki-rs/kuchiki/blob/master/src/select.rs).
 /// Thanks to the authors of Kuchiki for their work.
 use crate::dom::attributes::ExpandedName;
+use crate::dom::form::{self, FormControlKind};
 use crate::dom::iter::{NodeIterator, Select};
+use crate::dom::navigation;
 use crate::dom::node_data_ref::NodeDataRef;
 use crate::dom::tree::{ElementData, Node, NodeData, NodeRef};
 use crate::style::StyleParseErrorKind;
@@ -9,10 +11,11 @@ use crate::style::StyleParseErrorKind;
 use cssparser::{self, CowRcStr, ParseError, SourceLocation, ToCss};
 use html5ever::{LocalName, Namespace};
 use selectors::attr::{AttrSelectorOperation, CaseSensitivity, NamespaceConstraint};
+use selectors::bloom::BloomFilter;
 use selectors::context::QuirksMode;
 use selectors::parser::{
-    NonTSPseudoClass, Parser, Selector as GenericSelector, SelectorImpl, SelectorList,
-    SelectorParseErrorKind,
+    AncestorHashes, Component, NonTSPseudoClass, Parser, Selector as GenericSelector, SelectorImpl,
+    SelectorIter, SelectorList, SelectorParseErrorKind,
 };
 use selectors::{self, matching, OpaqueElement};
 use std::fmt;
@@ -64,6 +67,8 @@ impl<'i> Parser<'i> for KosmonautParser {
             Ok(Active)
         } else if name.eq_ignore_ascii_case("focus") {
             Ok(Focus)
+        } else if name.eq_ignore_ascii_case("focus-visible") {
+            Ok(FocusVisible)
         } else if name.eq_ignore_ascii_case("hover") {
             Ok(Hover)
         } else if name.eq_ignore_ascii_case("enabled") {
@@ -72,6 +77,8 @@ impl<'i> Parser<'i> for KosmonautParser {
             Ok(Disabled)
         } else if name.eq_ignore_ascii_case("checked") {
             Ok(Checked)
+        } else if name.eq_ignore_ascii_case("target") {
+            Ok(Target)
         } else if name.eq_ignore_ascii_case("indeterminate") {
             Ok(Indeterminate)
         } else {
@@ -82,6 +89,16 @@ impl<'i> Parser<'i> for KosmonautParser {
             )
         }
     }
+
+    /// https://drafts.csswg.org/css-scoping/#host-selector
+    fn parse_host(&self) -> bool {
+        true
+    }
+
+    /// https://drafts.csswg.org/css-shadow-parts/#part
+    fn parse_part(&self) -> bool {
+        true
+    }
 }
 
 #[derive(PartialEq, Eq, Clone, Debug, Hash)]
@@ -91,10 +108,12 @@ pub enum PseudoClass {
     Visited,
     Active,
     Focus,
+    FocusVisible,
     Hover,
     Enabled,
     Disabled,
     Checked,
+    Target,
     Indeterminate,
 }
 
@@ -108,7 +127,10 @@ impl NonTSPseudoClass for PseudoClass {
     fn is_user_action_state(&self) -> bool {
         matches!(
             *self,
-            PseudoClass::Active | PseudoClass::Hover | PseudoClass::Focus
+            PseudoClass::Active
+                | PseudoClass::Hover
+                | PseudoClass::Focus
+                | PseudoClass::FocusVisible
         )
     }
 
@@ -128,10 +150,12 @@ impl ToCss for PseudoClass {
             PseudoClass::Visited => ":visited",
             PseudoClass::Active => ":active",
             PseudoClass::Focus => ":focus",
+            PseudoClass::FocusVisible => ":focus-visible",
             PseudoClass::Hover => ":hover",
             PseudoClass::Enabled => ":enabled",
             PseudoClass::Disabled => ":disabled",
             PseudoClass::Checked => ":checked",
+            PseudoClass::Target => ":target",
             PseudoClass::Indeterminate => ":indeterminate",
         })
     }
@@ -168,11 +192,15 @@ impl selectors::Element for NodeDataRef<ElementData> {
     }
     #[inline]
     fn parent_node_is_shadow_root(&self) -> bool {
-        false
+        self.as_node()
+            .parent()
+            .map_or(false, |parent| parent.as_shadow_root().is_some())
     }
     #[inline]
     fn containing_shadow_host(&self) -> Option<Self> {
-        None
+        self.as_node()
+            .containing_shadow_host()
+            .and_then(NodeRef::into_element_ref)
     }
 
     fn is_pseudo_element(&self) -> bool {
@@ -236,9 +264,24 @@ impl selectors::Element for NodeDataRef<ElementData> {
     {
         use self::PseudoClass::*;
         match *pseudo {
-            Active | Focus | Hover | Enabled | Disabled | Checked | Indeterminate | Visited => {
-                false
-            }
+            Active | Hover | Indeterminate | Visited => false,
+            // https://html.spec.whatwg.org/multipage/semantics-other.html#selector-focus
+            Focus => self.focused.get(),
+            // https://drafts.csswg.org/selectors-4/#the-focus-visible-pseudo
+            FocusVisible => self.focused.get() && self.focus_visible.get(),
+            Target => navigation::is_target_element(self.as_node()),
+            Enabled => form::can_be_disabled(self.as_node()) && !form::is_disabled(self.as_node()),
+            Disabled => form::is_disabled(self.as_node()),
+            // https://html.spec.whatwg.org/multipage/semantics-other.html#selector-checked
+            Checked => match FormControlKind::of(self.as_node()) {
+                Some(FormControlKind::Checkbox) | Some(FormControlKind::Radio) => {
+                    form::is_checked(self)
+                }
+                _ => {
+                    self.name.expanded() == expanded_name!(html "option")
+                        && form::is_selected(self.as_node())
+                }
+            },
             AnyLink | Link => {
                 self.name.ns == ns!(html)
                     && matches!(
@@ -273,7 +316,7 @@ impl selectors::Element for NodeDataRef<ElementData> {
 
     #[inline]
     fn is_html_slot_element(&self) -> bool {
-        false
+        self.name.ns == ns!(html) && self.name.local == local_name!("slot")
     }
 
     #[inline]
@@ -307,8 +350,14 @@ impl selectors::Element for NodeDataRef<ElementData> {
         None
     }
 
-    fn is_part(&self, _name: &LocalName) -> bool {
-        false
+    /// Whether `name` is one of the names in the element's `part` attribute.
+    ///
+    /// https://drafts.csswg.org/css-shadow-parts/#part-attr
+    fn is_part(&self, name: &LocalName) -> bool {
+        self.attributes.borrow().get("part").map_or(false, |part| {
+            part.split(SELECTOR_WHITESPACE)
+                .any(|part_name| part_name == &**name)
+        })
     }
 
     #[inline]
@@ -382,29 +431,6 @@ impl Selectors {
         self.0.iter().any(|s| s.matches(element))
     }
 
-    /// Returns a reference to the most specific matching selector.
-    /// https://www.w3.org/TR/selectors/#specificity-rules
-    ///   > If the selector is a selector list, this number is calculated for each selector in
-    ///   > the list. For a given matching process against the list, the specificity in effect
-    ///   > is that of the most specific selector in the list that matches.
-    #[inline]
-    pub fn most_specific_match(&self, element: &NodeDataRef<ElementData>) -> Option<&Selector> {
-        let mut highest_matching_spec_opt: Option<&Selector> = None;
-        self.0.iter().for_each(|selector| {
-            if selector.matches(element) {
-                match highest_matching_spec_opt {
-                    Some(cur_highest) => {
-                        if selector.specificity() > cur_highest.specificity() {
-                            highest_matching_spec_opt = Some(selector)
-                        }
-                    }
-                    None => highest_matching_spec_opt = Some(selector),
-                }
-            }
-        });
-        highest_matching_spec_opt
-    }
-
     /// Filter an element iterator, yielding those matching this list of selectors.
     #[inline]
     pub fn filter<I>(&self, iter: I) -> Select<I, &Selectors>
@@ -420,6 +446,9 @@ impl Selectors {
 
 impl Selector {
     /// Returns whether the given element matches this selector.
+    ///
+    /// TODO: Match ids and classes case-insensitively in quirks mode documents.
+    /// https://html.spec.whatwg.org/multipage/semantics-other.html#case-sensitivity-of-selectors
     #[inline]
     pub fn matches(&self, element: &NodeDataRef<ElementData>) -> bool {
         let mut context = matching::MatchingContext::new(
@@ -431,10 +460,158 @@ impl Selector {
         matching::matches_selector(&self.0, 0, None, element, &mut context, &mut |_, _| {})
     }
 
+    /// Like `matches`, but first checks `ancestor_hashes` (this selector's) against an
+    /// `ancestor_filter` of the element's ancestors, which can quickly rule out selectors whose
+    /// descendant and child combinators can't match.  The selector is in a stylesheet in `scope`
+    /// relative to the element, and must be able to apply in it.
+    #[inline]
+    pub fn matches_with_ancestor_filter(
+        &self,
+        element: &NodeDataRef<ElementData>,
+        ancestor_hashes: &AncestorHashes,
+        ancestor_filter: &AncestorFilter,
+        scope: SelectorScope,
+    ) -> bool {
+        let mut context = matching::MatchingContext::new(
+            matching::MatchingMode::Normal,
+            Some(&ancestor_filter.bloom_filter),
+            None,
+            QuirksMode::NoQuirks,
+        );
+        // `:host` matches the host of the shadow tree the stylesheet is in.
+        let shadow_host = match scope {
+            SelectorScope::Tree => selectors::Element::containing_shadow_host(element),
+            SelectorScope::Host => Some(element.clone()),
+            SelectorScope::Parts => None,
+        };
+        context.with_shadow_host(shadow_host, |context| {
+            matching::matches_selector(
+                &self.0,
+                0,
+                Some(ancestor_hashes),
+                element,
+                context,
+                &mut |_, _| {},
+            )
+        })
+    }
+
+    /// Whether this selector can match elements in `scope` relative to its stylesheet at all.
+    pub fn applies_in(&self, scope: SelectorScope) -> bool {
+        // Only the rightmost compound selector decides which element the selector matches.
+        let has_host = self
+            .0
+            .iter()
+            .any(|component| matches!(component, Component::Host(_)));
+        let has_part = self
+            .0
+            .iter()
+            .any(|component| matches!(component, Component::Part(..)));
+        match scope {
+            SelectorScope::Tree => !has_part,
+            SelectorScope::Host => has_host,
+            SelectorScope::Parts => has_part,
+        }
+    }
+
+    /// The hashes of the ids, classes, local names, and namespaces the ancestors of any element
+    /// this selector matches must have, for checking against an `AncestorFilter`.
+    pub fn ancestor_hashes(&self) -> AncestorHashes {
+        AncestorHashes::new(&self.0, QuirksMode::NoQuirks)
+    }
+
     /// Return the specificity of this selector.
     pub fn specificity(&self) -> Specificity {
         Specificity(self.0.specificity())
     }
+
+    /// Iterates over the components of this selector's compound selectors, right to left (the
+    /// order they're matched in).  `SelectorIter::next_sequence` moves on to the next compound
+    /// selector, returning the combinator between them.
+    pub fn iter(&self) -> SelectorIter<KosmonautSelectors> {
+        self.0.iter()
+    }
+}
+
+/// Where the stylesheet a selector is in is relative to an element it's matched against, which
+/// decides which selectors can match it.  Stylesheets only reach into the shadow trees of the
+/// hosts in their tree through `::part()`, and only reach the host of the shadow tree they're in
+/// through `:host`.
+///
+/// https://drafts.csswg.org/css-scoping/#shadow-cascading
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SelectorScope {
+    /// The stylesheet is in the element's tree: its document, or the shadow tree it's in.
+    Tree,
+    /// The stylesheet is in the element's shadow tree, so only `:host` selectors match it.
+    Host,
+    /// The stylesheet is in the tree of the host of the shadow tree the element is in, so only
+    /// `::part()` selectors match it.
+    Parts,
+}
+
+/// A bloom filter of the ids, classes, local names, and namespaces of the ancestors of the element
+/// being matched, maintained while traversing the DOM.  Selectors whose descendant and child
+/// combinators require ancestors with features the filter definitely doesn't contain can be
+/// rejected without walking up the tree.
+///
+/// Based on Servo's style bloom filter:
+/// https://github.com/servo/servo/blob/d2856ce8aeca11e543bc4d9f869400d73451374e/components/style/bloom.rs
+pub struct AncestorFilter {
+    bloom_filter: BloomFilter,
+}
+
+impl AncestorFilter {
+    pub fn new() -> AncestorFilter {
+        AncestorFilter {
+            bloom_filter: BloomFilter::new(),
+        }
+    }
+
+    /// Creates a filter containing the ancestors of `node`, for matching `node` itself.
+    pub fn for_ancestors_of(node: &NodeRef) -> AncestorFilter {
+        let mut ancestor_filter = AncestorFilter::new();
+        node.ancestors()
+            .elements()
+            .for_each(|ancestor| ancestor_filter.push(&ancestor));
+        ancestor_filter
+    }
+
+    /// Adds `element` to the filter, before matching its descendants.
+    pub fn push(&mut self, element: &ElementData) {
+        let bloom_filter = &mut self.bloom_filter;
+        each_element_hash(element, |hash| bloom_filter.insert_hash(hash));
+    }
+
+    /// Removes `element` from the filter, after matching its descendants.  `element` must have been
+    /// pushed, and must not have changed since.
+    pub fn pop(&mut self, element: &ElementData) {
+        let bloom_filter = &mut self.bloom_filter;
+        each_element_hash(element, |hash| bloom_filter.remove_hash(hash));
+    }
+}
+
+impl Default for AncestorFilter {
+    fn default() -> Self {
+        AncestorFilter::new()
+    }
+}
+
+/// Calls `f` with the hash of each feature of `element` that selectors' ancestor hashes can refer
+/// to.
+fn each_element_hash<F: FnMut(u32)>(element: &ElementData, mut f: F) {
+    f(element.name.local.get_hash());
+    f(element.name.ns.get_hash());
+    let attributes = element.attributes.borrow();
+    if let Some(id) = attributes.get(local_name!("id")) {
+        f(LocalName::from(id).get_hash());
+    }
+    if let Some(classes) = attributes.get(local_name!("class")) {
+        classes
+            .split(SELECTOR_WHITESPACE)
+            .filter(|class| !class.is_empty())
+            .for_each(|class| f(LocalName::from(class).get_hash()));
+    }
 }
 
 impl ::std::str::FromStr for Selectors {
@@ -489,16 +666,18 @@ mod tests {
         let selectors =
             Selectors::compile_str("div, div.specific, div.specific.even-more-specific")
                 .expect("should've been able to compile selectors in match_most_specific_works()");
-        let div = get_div("specific even-more-specific", "hello");
+        let div = get_div("specific even-more-specific", "hello")
+            .into_element_ref()
+            .expect("should be able to get element ref for canned node");
         // specificity of most specific selector, `div.specific.even-more-specific`, is 2049
         assert_eq!(
             selectors
-                .most_specific_match(
-                    &div.into_element_ref()
-                        .expect("should be able to get element ref for canned node")
-                )
-                .expect("should've found a most-specific match")
-                .specificity(),
+                .0
+                .iter()
+                .filter(|selector| selector.matches(&div))
+                .map(Selector::specificity)
+                .max()
+                .expect("should've found a most-specific match"),
             Specificity(2049)
         )
     }
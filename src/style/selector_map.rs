@@ -0,0 +1,204 @@
+use std::collections::{BTreeMap, HashMap};
+
+use html5ever::LocalName;
+use selectors::parser::{AncestorHashes, Component};
+
+use crate::dom::node_data_ref::NodeDataRef;
+use crate::dom::tree::ElementData;
+use crate::style::select::{AncestorFilter, Selector, SelectorScope, Specificity};
+use crate::style::CssRule;
+
+/// The selectors of a stylesheet's style rules, bucketed by a feature of their rightmost compound
+/// selector that every element they match must have: an id, a class, or a local name.  Only the
+/// selectors in the buckets of an element's own features (plus those without any such feature)
+/// need to be matched against it, which keeps matching fast as stylesheets grow.
+///
+/// Based on Servo's `SelectorMap`:
+/// https://github.com/servo/servo/blob/d2856ce8aeca11e543bc4d9f869400d73451374e/components/style/selector_map.rs
+#[derive(Clone, Debug, Default)]
+pub struct SelectorMap {
+    ids: HashMap<LocalName, Vec<SelectorMapEntry>>,
+    classes: HashMap<LocalName, Vec<SelectorMapEntry>>,
+    local_names: HashMap<LocalName, Vec<SelectorMapEntry>>,
+    /// Selectors whose rightmost compound has none of the above (e.g. `*` or `[href]`).
+    other: Vec<SelectorMapEntry>,
+}
+
+#[derive(Clone, Debug)]
+struct SelectorMapEntry {
+    /// The index of the rule the selector belongs to in its stylesheet.
+    rule_index: usize,
+    selector: Selector,
+    ancestor_hashes: AncestorHashes,
+}
+
+/// The feature of a selector's rightmost compound it's bucketed by.
+enum BucketKey<'a> {
+    Id(&'a LocalName),
+    Class(&'a LocalName),
+    LocalName(&'a LocalName),
+    Other,
+}
+
+impl SelectorMap {
+    pub fn new(rules: &[CssRule]) -> SelectorMap {
+        let mut selector_map = SelectorMap::default();
+        rules
+            .iter()
+            .enumerate()
+            .for_each(|(rule_index, rule)| selector_map.insert_rule(rule_index, rule));
+        selector_map
+    }
+
+    /// Adds the selectors of `rule`, which is at `rule_index` in its stylesheet.
+    pub fn insert_rule(&mut self, rule_index: usize, rule: &CssRule) {
+        let style_rule = match rule {
+            CssRule::Style(style_rule) => style_rule,
+            CssRule::Media(_) | CssRule::Container(_) | CssRule::None => return,
+        };
+        for selector in &style_rule.selectors.0 {
+            let entry = SelectorMapEntry {
+                rule_index,
+                selector: selector.clone(),
+                ancestor_hashes: selector.ancestor_hashes(),
+            };
+            let bucket = match bucket_key(selector) {
+                BucketKey::Id(id) => self.ids.entry(id.clone()).or_default(),
+                BucketKey::Class(class) => self.classes.entry(class.clone()).or_default(),
+                BucketKey::LocalName(local_name) => {
+                    self.local_names.entry(local_name.clone()).or_default()
+                }
+                BucketKey::Other => &mut self.other,
+            };
+            bucket.push(entry);
+        }
+    }
+
+    /// The indices of the rules with a selector matching `element`, in the order they appear in
+    /// their stylesheet, along with the specificity of the most specific of their selectors that
+    /// matches.  `ancestor_filter` must contain the ancestors of `element`, and the stylesheet is
+    /// in `scope` relative to `element`.
+    ///
+    /// https://www.w3.org/TR/selectors/#specificity-rules
+    ///   > If the selector is a selector list, this number is calculated for each selector in
+    ///   > the list. For a given matching process against the list, the specificity in effect
+    ///   > is that of the most specific selector in the list that matches.
+    pub fn matching_rules(
+        &self,
+        element: &NodeDataRef<ElementData>,
+        ancestor_filter: &AncestorFilter,
+        scope: SelectorScope,
+    ) -> Vec<(usize, Specificity)> {
+        let attributes = element.attributes.borrow();
+        let id_bucket = attributes
+            .get(local_name!("id"))
+            .and_then(|id| self.ids.get(&LocalName::from(id)));
+        let class_buckets = attributes
+            .get(local_name!("class"))
+            .unwrap_or_default()
+            .split_ascii_whitespace()
+            .filter_map(|class| self.classes.get(&LocalName::from(class)));
+        let candidates = id_bucket
+            .into_iter()
+            .chain(class_buckets)
+            .chain(self.local_names.get(&element.name.local))
+            .chain(Some(&self.other))
+            .flatten()
+            .collect::<Vec<_>>();
+        // Matching can look at the attributes of `element`, too.
+        drop(attributes);
+
+        let mut matching_rules = BTreeMap::new();
+        for entry in candidates {
+            if !entry.selector.applies_in(scope)
+                || !entry.selector.matches_with_ancestor_filter(
+                    element,
+                    &entry.ancestor_hashes,
+                    ancestor_filter,
+                    scope,
+                )
+            {
+                continue;
+            }
+            let specificity = entry.selector.specificity();
+            matching_rules
+                .entry(entry.rule_index)
+                .and_modify(|highest: &mut Specificity| *highest = (*highest).max(specificity))
+                .or_insert(specificity);
+        }
+        matching_rules.into_iter().collect()
+    }
+}
+
+/// Picks the most selective feature of the rightmost compound of `selector` to bucket it by.
+fn bucket_key(selector: &Selector) -> BucketKey {
+    let mut key = BucketKey::Other;
+    for component in selector.iter() {
+        match component {
+            Component::ID(id) => return BucketKey::Id(id),
+            Component::Class(class) => key = BucketKey::Class(class),
+            Component::LocalName(local_name) if !matches!(key, BucketKey::Class(_)) => {
+                // Elements are looked up by their lowercase local name.
+                key = BucketKey::LocalName(&local_name.lower_name)
+            }
+            _ => {}
+        }
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::parser::parse_html;
+    use crate::dom::traits::*;
+    use crate::style::stylesheet::parse_css_to_stylesheet;
+
+    fn matching_rules(css: &str, html: &str, selector: &str) -> Vec<usize> {
+        let sheet = parse_css_to_stylesheet(None, &mut css.to_owned());
+        let selector_map = SelectorMap::new(sheet.rules());
+        let document = parse_html().one(html);
+        let element = document.select_first(selector).unwrap();
+        let ancestor_filter = AncestorFilter::for_ancestors_of(element.as_node());
+        selector_map
+            .matching_rules(&element, &ancestor_filter, SelectorScope::Tree)
+            .into_iter()
+            .map(|(rule_index, _)| rule_index)
+            .collect()
+    }
+
+    #[test]
+    fn finds_matching_rules_in_source_order() {
+        let css = "#x { color: red } \
+                   .a .b { color: red } \
+                   p { color: red } \
+                   .c { color: red } \
+                   * { color: red } \
+                   div p.b { color: red } \
+                   [title] { color: red }";
+        assert_eq!(
+            matching_rules(css, "<div class=a><p id=x class=b></p></div>", "p"),
+            vec![0, 1, 2, 4, 5]
+        );
+        // The ancestor filter rules out `.a .b`, and `div p.b`.
+        assert_eq!(
+            matching_rules(css, "<section><p class='b c'></p></section>", "p"),
+            vec![2, 3, 4]
+        );
+    }
+
+    #[test]
+    fn uses_the_highest_specificity_of_matching_selectors() {
+        let sheet = parse_css_to_stylesheet(None, &mut "p, .b, #x { color: red }".to_owned());
+        let selector_map = SelectorMap::new(sheet.rules());
+        let document = parse_html().one("<p class=b></p>");
+        let element = document.select_first("p").unwrap();
+        let matching_rules =
+            selector_map.matching_rules(&element, &AncestorFilter::new(), SelectorScope::Tree);
+        let class_specificity = match &sheet.rules()[0] {
+            CssRule::Style(style_rule) => style_rule.selectors.0[1].specificity(),
+            CssRule::Media(_) | CssRule::Container(_) | CssRule::None => unreachable!(),
+        };
+        assert_eq!(matching_rules, vec![(0, class_specificity)]);
+    }
+}
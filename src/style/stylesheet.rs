@@ -1,65 +1,186 @@
 use std::mem::discriminant;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use cssparser::{ParseError, Parser, ParserInput, RuleListParser};
+use tracing::info_span;
 
-use crate::dom::tree::NodeRef;
+use crate::dom::iter::NodeEdge;
+use crate::dom::node_data_ref::NodeDataRef;
+use crate::dom::tree::{ElementData, NodeRef};
+use crate::style::diagnostics::CssDiagnostic;
+use crate::style::media::Device;
 use crate::style::properties::ContextualPropertyDeclaration;
+use crate::style::rule_tree::{ApplicableDeclarationBlock, StyleSource};
+use crate::style::select::{AncestorFilter, SelectorScope, Specificity};
+use crate::style::selector_map::SelectorMap;
 use crate::style::{
-    CascadeOrigin, CssOrigin, CssRule, StyleParseErrorKind, StylesheetOrigin, TopLevelRuleParser,
+    CascadeOrigin, ContainerRule, CssOrigin, CssRule, MediaRule, StyleParseErrorKind, StyleRule,
+    StylesheetOrigin, TopLevelRuleParser,
 };
 
-/// Parses string containing CSS into StyleRules.
-pub fn parse_css_to_stylesheet(
-    sheet_name: Option<String>,
-    css_str: &mut str,
-) -> Result<Stylesheet, (ParseError<StyleParseErrorKind>, &str)> {
+/// Parses string containing CSS into StyleRules.  Invalid rules are skipped rather than failing
+/// the whole stylesheet, as are invalid declarations within valid rules, so any input (however
+/// malformed) parses into a stylesheet.  Each thing skipped is recorded in the stylesheet's
+/// `diagnostics`.
+///
+/// https://drafts.csswg.org/css-syntax-3/#error-handling
+pub fn parse_css_to_stylesheet(sheet_name: Option<String>, css_str: &mut str) -> Stylesheet {
     let input = &mut ParserInput::new(css_str);
     let parser = &mut Parser::new(input);
-    let rule_parser = RuleListParser::new_for_stylesheet(parser, TopLevelRuleParser {});
+    let mut rule_parser = RuleListParser::new_for_stylesheet(parser, TopLevelRuleParser::default());
     let mut sheet = if let Some(name) = sheet_name {
         Stylesheet::new_with_name(name)
     } else {
         Stylesheet::new()
     };
-    for rule in rule_parser {
-        sheet.add_rule(rule?);
+    // The rule parser has already skipped to the start of the next rule after an invalid one.
+    let mut diagnostics = Vec::new();
+    while let Some(rule) = rule_parser.next() {
+        match rule {
+            Ok(rule) => sheet.add_rule(rule),
+            Err((err, rule)) => diagnostics.push(CssDiagnostic::for_rule(&err, rule)),
+        }
     }
-    Ok(sheet)
+    // Diagnostics of nested rules and declarations are collected as each top-level rule is
+    // parsed, so they're sorted into source order with those of top-level rules.
+    diagnostics.append(&mut rule_parser.parser.diagnostics);
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.line, diagnostic.column));
+    sheet.diagnostics = diagnostics;
+    sheet
+}
+
+pub fn apply_stylesheet_to_node(
+    node: &NodeRef,
+    sheet: &Stylesheet,
+    origin: CascadeOrigin,
+    device: &Device,
+) {
+    apply_scoped_stylesheet_to_node(node, sheet, origin, device, SelectorScope::Tree)
 }
 
-pub fn apply_stylesheet_to_node(node: &NodeRef, sheet: &Stylesheet, origin: CascadeOrigin) {
-    sheet.rules().iter().for_each(|rule| {
-        if let CssRule::Style(style_rule) = rule {
-            node.select(&style_rule.selectors)
-                .for_each(|matching_node| {
-                    style_rule
-                        .block
-                        .declarations()
-                        .iter()
-                        .enumerate()
-                        .for_each(|(index, decl)| {
-                            matching_node.as_node().add_decl(ContextualPropertyDeclaration {
-                                inner_decl: decl.clone(),
-                                important: style_rule
-                                    .block
-                                    .declarations_importance()
-                                    .get(index)
-                                    .expect("important bit not set for declaration"),
-                                origin: CssOrigin::Sheet(StylesheetOrigin {
-                                    sheet_name: sheet.name.clone(),
-                                    cascade_origin: origin.clone(),
-                                }),
-                                source_location: Some(style_rule.source_location),
-                                specificity: style_rule
-                                    .selectors
-                                    .most_specific_match(&matching_node)
-                                    .expect("there should be at least one matching selector at this point")
-                                    .specificity()
-                            });
-                        });
-                });
+/// Like `apply_stylesheet_to_node`, but for a stylesheet in `scope` relative to the nodes (which
+/// must all be in the same tree).  Shadow trees in the subtree of `node` aren't styled.
+pub fn apply_scoped_stylesheet_to_node(
+    node: &NodeRef,
+    sheet: &Stylesheet,
+    origin: CascadeOrigin,
+    device: &Device,
+    scope: SelectorScope,
+) {
+    let _span = info_span!("match_stylesheet", sheet = sheet.name()).entered();
+    let mut ancestor_filter = AncestorFilter::for_ancestors_of(node);
+    for edge in node.traverse_inclusive() {
+        match edge {
+            NodeEdge::Start(node) => {
+                if let Some(element) = node.into_element_ref() {
+                    apply_matching_rules(
+                        &element,
+                        sheet,
+                        &ancestor_filter,
+                        origin.clone(),
+                        scope,
+                        device,
+                    );
+                    ancestor_filter.push(&element);
+                }
+            }
+            NodeEdge::End(node) => {
+                if let Some(element) = node.as_element() {
+                    ancestor_filter.pop(element);
+                }
+            }
         }
-    });
+    }
+}
+
+/// Like `apply_scoped_stylesheet_to_node`, but only applies the rules that match `element` itself
+/// rather than any of its descendants.
+pub fn apply_stylesheet_to_element(
+    element: &NodeDataRef<ElementData>,
+    sheet: &Stylesheet,
+    origin: CascadeOrigin,
+    device: &Device,
+    scope: SelectorScope,
+) {
+    let ancestor_filter = AncestorFilter::for_ancestors_of(element.as_node());
+    apply_matching_rules(element, sheet, &ancestor_filter, origin, scope, device);
+}
+
+/// Adds the declarations of the rules in `sheet` that match `element`, whose ancestors
+/// `ancestor_filter` must contain, and whose media match `device` (and whose container queries
+/// match the query containers of `element`).  The rules have the given
+/// origin, and are in the given scope relative to `element`.
+fn apply_matching_rules(
+    element: &NodeDataRef<ElementData>,
+    sheet: &Stylesheet,
+    ancestor_filter: &AncestorFilter,
+    origin: CascadeOrigin,
+    scope: SelectorScope,
+    device: &Device,
+) {
+    let matching_rules = sheet
+        .selector_map
+        .matching_rules(element, ancestor_filter, scope);
+    for (rule_index, specificity) in matching_rules {
+        if let CssRule::Style(style_rule) = &sheet.rules[rule_index] {
+            if !style_rule.matches_device(device)
+                || !style_rule.matches_containers(element.as_node())
+            {
+                continue;
+            }
+            let source = StyleSource::Rule {
+                sheet_id: sheet.id,
+                rule_index,
+            };
+            add_rule_declarations(
+                element,
+                style_rule,
+                source,
+                specificity,
+                sheet,
+                origin.clone(),
+            );
+        }
+    }
+}
+
+/// Adds the declarations of `style_rule` to `matching_node`, as one block of normal declarations
+/// and one of important declarations.
+fn add_rule_declarations(
+    matching_node: &NodeDataRef<ElementData>,
+    style_rule: &StyleRule,
+    source: StyleSource,
+    specificity: Specificity,
+    sheet: &Stylesheet,
+    origin: CascadeOrigin,
+) {
+    let (important, normal): (Vec<_>, Vec<_>) = style_rule
+        .block
+        .declarations()
+        .iter()
+        .enumerate()
+        .map(|(index, decl)| ContextualPropertyDeclaration {
+            inner_decl: decl.clone(),
+            important: style_rule
+                .block
+                .declarations_importance()
+                .get(index)
+                .expect("important bit not set for declaration"),
+            origin: CssOrigin::Sheet(StylesheetOrigin {
+                sheet_name: sheet.name.clone(),
+                cascade_origin: origin.clone(),
+            }),
+            source_location: Some(style_rule.source_location),
+            specificity,
+        })
+        .partition(|decl| decl.important);
+    let add_block = |decls| {
+        if let Some(block) = ApplicableDeclarationBlock::new(source.clone(), decls) {
+            matching_node.as_node().add_declaration_block(block);
+        }
+    };
+    add_block(normal);
+    add_block(important);
 }
 
 #[derive(Debug)]
@@ -84,8 +205,15 @@ impl<'i> From<ParseError<'i, StyleParseErrorKind<'i>>> for StylesheetParseErr<'i
 pub struct Stylesheet {
     /// Name of the stylesheet
     name: String,
+    /// Identifies the current rules of the stylesheet in the rule tree.  This changes whenever a
+    /// rule is added, since that can change the indices of existing rules.
+    id: usize,
     /// These rules should be de-duplicated before being accepted into the Vec.
     rules: Vec<CssRule>,
+    /// The selectors of `rules`, bucketed for matching.
+    selector_map: SelectorMap,
+    /// What was dropped while parsing the stylesheet.
+    diagnostics: Vec<CssDiagnostic>,
 }
 
 impl Stylesheet {
@@ -104,16 +232,50 @@ impl Stylesheet {
         &self.rules
     }
 
-    /// Adds a new rule to the stylesheet, de-duplicating rules with the same selectors and
-    /// conflicting `property: value`s.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// A diagnostic for each rule and declaration dropped while parsing the stylesheet, because it
+    /// was invalid or unsupported, in source order.
+    pub fn diagnostics(&self) -> &[CssDiagnostic] {
+        &self.diagnostics
+    }
+
+    /// Whether any rule of this stylesheet applies on one of `device` and `other_device` but not
+    /// the other, in which case styles must be applied anew when one replaces the other.
+    pub fn rules_differ_between(&self, device: &Device, other_device: &Device) -> bool {
+        self.rules.iter().any(|rule| match rule {
+            CssRule::Style(style_rule) => {
+                style_rule.matches_device(device) != style_rule.matches_device(other_device)
+            }
+            CssRule::Media(_) | CssRule::Container(_) | CssRule::None => false,
+        })
+    }
+
+    /// Adds a new rule to the stylesheet, de-duplicating rules with the same selectors, media,
+    /// container conditions, and conflicting `property: value`s.
     pub fn add_rule(&mut self, new_rule: CssRule) {
+        if let CssRule::Media(MediaRule { rules }) | CssRule::Container(ContainerRule { rules }) =
+            new_rule
+        {
+            // The style rules of `@media` and `@container` rules are added alongside the others,
+            // since each knows the media and containers it's conditioned on.
+            for style_rule in rules {
+                self.add_rule(CssRule::Style(style_rule));
+            }
+            return;
+        }
         let mut obsolete_rule_indices = Vec::new();
         match &new_rule {
             CssRule::Style(new_style) => {
                 for (rule_index, existing_rule) in self.rules.iter_mut().enumerate() {
                     match existing_rule {
                         CssRule::Style(existing_style) => {
-                            if existing_style.selectors.eq(&new_style.selectors) {
+                            if existing_style.selectors.eq(&new_style.selectors)
+                                && existing_style.media == new_style.media
+                                && existing_style.containers == new_style.containers
+                            {
                                 let mut obsolete_prop_indices = Vec::new();
                                 for (prop_index, existing_prop) in
                                     existing_style.block.declarations().iter().enumerate()
@@ -127,7 +289,8 @@ impl Stylesheet {
                                     }
                                 }
 
-                                for index in obsolete_prop_indices {
+                                obsolete_prop_indices.dedup();
+                                for index in obsolete_prop_indices.into_iter().rev() {
                                     existing_style.block.remove_decl(index);
                                 }
                                 if existing_style.block.declarations().is_empty() {
@@ -136,22 +299,38 @@ impl Stylesheet {
                                 }
                             }
                         }
-                        CssRule::None => {}
+                        CssRule::Media(_) | CssRule::Container(_) | CssRule::None => {}
                     }
                 }
 
-                for index in obsolete_rule_indices {
+                let rules_removed = !obsolete_rule_indices.is_empty();
+                // Remove the last rules first, so that the indices of those yet to be removed
+                // don't shift.
+                for index in obsolete_rule_indices.into_iter().rev() {
                     self.rules.remove(index);
                 }
+                if rules_removed {
+                    // The indices of the remaining rules have shifted.
+                    self.selector_map = SelectorMap::new(&self.rules);
+                }
             }
-            CssRule::None => {}
+            CssRule::Media(_) | CssRule::Container(_) | CssRule::None => {}
         }
+        self.selector_map.insert_rule(self.rules.len(), &new_rule);
         self.rules.push(new_rule);
+        self.id = NEXT_STYLESHEET_ID.fetch_add(1, Ordering::Relaxed);
     }
 }
 
+/// The next id to give the rules of a stylesheet.  Ids start at 1, so that stylesheets without
+/// rules are the only ones with an id of 0.
+static NEXT_STYLESHEET_ID: AtomicUsize = AtomicUsize::new(1);
+
 #[cfg(test)]
 mod tests {
+    use crate::style::diagnostics::CssDiagnosticKind;
+    use crate::style::media::MediaType;
+    use crate::style::select::Selectors;
     use crate::style::test_utils::font_size_px_or_panic;
 
     // Note this useful idiom: importing names from outer (for mod tests) scope.
@@ -160,11 +339,9 @@ mod tests {
     #[test]
     // TODO: Create integration test that exercises this as well
     fn selects_last_rules_prop_in_dupes_across_rules() {
-        let mut sheet_a = parse_css_to_stylesheet(None, &mut ".a { font-size: 12px; }".to_owned())
-            .expect("failed getting sheet_a for cross-block deduping test");
+        let mut sheet_a = parse_css_to_stylesheet(None, &mut ".a { font-size: 12px; }".to_owned());
         // We won't actually use this sheet — just extract the `font-size` rule from it
-        let mut sheet_b = parse_css_to_stylesheet(None, &mut ".a { font-size: 16px; }".to_owned())
-            .expect("failed getting sheet_b for cross-block deduping test");
+        let mut sheet_b = parse_css_to_stylesheet(None, &mut ".a { font-size: 16px; }".to_owned());
         sheet_a.add_rule(sheet_b.rules.remove(0));
 
         // The only PropertyDeclaration in the first rule, `font-size: 12px`, is obsoleted by the
@@ -179,4 +356,236 @@ mod tests {
             _ => panic!("should always be a `StyleRule` CssRule"),
         }
     }
+
+    #[test]
+    fn media_rules_only_apply_to_matching_devices() {
+        let sheet = parse_css_to_stylesheet(
+            None,
+            &mut ".a { font-size: 12px; }
+                @media print { .a { font-size: 16px; } @media not screen { .b { color: red } } }"
+                .to_owned(),
+        );
+        // The `@media print` rule doesn't obsolete the rule outside it, since they apply to
+        // different media.
+        assert_eq!(sheet.rules.len(), 3);
+        let print = Device::new(MediaType::Print, 800., 600.);
+        let screen = Device::new(MediaType::Screen, 800., 600.);
+        let matches = |device: &Device| {
+            sheet
+                .rules
+                .iter()
+                .map(|rule| match rule {
+                    CssRule::Style(style_rule) => style_rule.matches_device(device),
+                    _ => panic!("should always be a `StyleRule` CssRule"),
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(matches(&print), vec![true, true, true]);
+        assert_eq!(matches(&screen), vec![true, false, false]);
+        assert!(sheet.rules_differ_between(&print, &screen));
+        assert!(!sheet.rules_differ_between(&screen, &Device::new(MediaType::Screen, 1., 1.)));
+    }
+
+    #[test]
+    fn skips_invalid_rules_and_declarations() {
+        let sheet = parse_css_to_stylesheet(
+            None,
+            &mut ".a { color: red; colour: blue; width: 10px 20px; font-size: 12px }
+                @unsupported foo;
+                ]] .x { color: green }
+                .b { color: blue }
+                @media print { !! { color: red } .c { color: green } }
+                .d { color: }"
+                .to_owned(),
+        );
+        let selectors = sheet
+            .rules
+            .iter()
+            .map(|rule| match rule {
+                CssRule::Style(style_rule) => (
+                    style_rule.selectors.to_string(),
+                    style_rule.block.declarations().len(),
+                ),
+                _ => panic!("should always be a `StyleRule` CssRule"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            selectors,
+            vec![
+                (".a".to_owned(), 2),
+                (".b".to_owned(), 1),
+                (".c".to_owned(), 1),
+                (".d".to_owned(), 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn records_diagnostics_for_what_it_skips() {
+        let sheet = parse_css_to_stylesheet(
+            Some("author.css".to_owned()),
+            &mut "p {\n  colr: red;\n  color: bogus;\n  display: block;\n}\n\
+                  div > > p { color: red }\n\
+                  @font-face { font-family: x }\n\
+                  @media screen { div { widht: 1px } }\n"
+                .to_owned(),
+        );
+        assert_eq!(sheet.name(), "author.css");
+        let diagnostics = sheet
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| (diagnostic.line, diagnostic.kind.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            diagnostics,
+            vec![
+                (2, CssDiagnosticKind::UnknownProperty("colr".to_owned())),
+                (
+                    3,
+                    CssDiagnosticKind::InvalidDeclaration("color: bogus".to_owned())
+                ),
+                (
+                    6,
+                    CssDiagnosticKind::InvalidSelector("div > > p".to_owned())
+                ),
+                (
+                    7,
+                    CssDiagnosticKind::UnsupportedAtRule("font-face".to_owned())
+                ),
+                (8, CssDiagnosticKind::UnknownProperty("widht".to_owned())),
+            ]
+        );
+        assert!(sheet.diagnostics()[0]
+            .to_string()
+            .ends_with(": unknown property `colr`"));
+        assert!(
+            parse_css_to_stylesheet(None, &mut "p { color: red }".to_owned())
+                .diagnostics()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn obsoleting_several_declarations_and_rules_keeps_the_rest() {
+        let sheet = parse_css_to_stylesheet(
+            None,
+            &mut ".a { color: red; display: block; font-size: 12px !important }
+                .b { color: red }
+                .a { color: blue; font-size: 16px }
+                .b { color: blue }
+                .a { display: inline; color: green; font-size: 20px }"
+                .to_owned(),
+        );
+        let rules = sheet
+            .rules
+            .iter()
+            .map(|rule| match rule {
+                CssRule::Style(style_rule) => (
+                    style_rule.selectors.to_string(),
+                    style_rule.block.declarations().len(),
+                    style_rule.block.declarations_importance().len(),
+                ),
+                _ => panic!("should always be a `StyleRule` CssRule"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rules,
+            vec![(".b".to_owned(), 1, 1), (".a".to_owned(), 3, 3)]
+        );
+    }
+
+    /// Feeds pseudo-random input, made of CSS-like fragments and arbitrary bytes, into stylesheet
+    /// and selector parsing, which must never panic.  Each run is seeded the same, so failures
+    /// reproduce.
+    #[test]
+    fn never_panics_on_malformed_input() {
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+
+        let fragments = [
+            "{",
+            "}",
+            "(",
+            ")",
+            "[",
+            "]",
+            ";",
+            ":",
+            ",",
+            " ",
+            "\n",
+            "\\",
+            "\"",
+            "'",
+            "/*",
+            "*/",
+            "<!--",
+            "-->",
+            "@media",
+            "@container",
+            "@import",
+            "@font-face",
+            "!important",
+            "!",
+            "div",
+            ".a",
+            "#b",
+            "*",
+            ">",
+            "+",
+            "~",
+            "|",
+            "::before",
+            ":hover",
+            ":not(",
+            ":nth-child(2n+1)",
+            "[href^=",
+            "color",
+            "display",
+            "border",
+            "margin",
+            "font-size",
+            "background",
+            "red",
+            "#fff",
+            "#12345",
+            "rgb(",
+            "rgba(1,2,3,",
+            "1px",
+            "-2.5em",
+            "50%",
+            "1e999px",
+            "auto",
+            "inherit",
+            "url(",
+            "screen",
+            "print",
+            "and",
+            "not",
+            "only",
+            "(min-width:",
+            "2dppx",
+            "(prefers-color-scheme:",
+            "\u{0}",
+            "\u{fffd}",
+            "é",
+            "🚀",
+        ];
+        let mut rng = StdRng::seed_from_u64(166);
+        for _ in 0..2000 {
+            let mut css = String::new();
+            for _ in 0..rng.gen_range(0..48) {
+                if rng.gen_bool(0.1) {
+                    let bytes = (0..rng.gen_range(1..8))
+                        .map(|_| rng.gen())
+                        .collect::<Vec<u8>>();
+                    css.push_str(&String::from_utf8_lossy(&bytes));
+                } else {
+                    css.push_str(fragments[rng.gen_range(0..fragments.len())]);
+                }
+            }
+            let _ = Selectors::compile_str(&css);
+            parse_css_to_stylesheet(None, &mut css);
+        }
+    }
 }
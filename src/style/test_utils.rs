@@ -1,13 +1,71 @@
 use crate::dom::parser::parse_html;
 use crate::dom::traits::TendrilSink;
 use crate::dom::tree::{NodeData, NodeRef};
+use crate::layout::box_tree::build_box_tree;
+use crate::layout::global_layout;
+use crate::layout::layout_box::LayoutBox;
+use crate::page::user_agent_stylesheet;
+use crate::style::apply_styles;
+use crate::style::media::{Device, MediaType};
 use crate::style::properties::{ContextualPropertyDeclaration, PropertyDeclaration};
 use crate::style::select::Specificity;
+use crate::style::stylesheet::parse_css_to_stylesheet;
 use crate::style::values::computed::Display;
 use crate::style::values::specified::length::{AbsoluteLength, LengthPercentage};
 use crate::style::values::specified::{FontSize, NoCalcLength};
 use crate::style::CssOrigin;
 
+/// The width of the viewport documents are styled and laid out in by `styled_document` and
+/// `laid_out_document`, in CSS pixels.
+pub const TEST_VIEWPORT_WIDTH: f32 = 800.;
+/// The height of the viewport documents are styled and laid out in, in CSS pixels.
+pub const TEST_VIEWPORT_HEIGHT: f32 = 600.;
+
+/// A screen the size of the test viewport.
+pub fn test_device() -> Device {
+    Device::new(MediaType::Screen, TEST_VIEWPORT_WIDTH, TEST_VIEWPORT_HEIGHT)
+}
+
+/// Styles (or restyles) `document` for the test device, with the user agent stylesheet and `css`
+/// as the only author stylesheet.
+pub fn style(document: &NodeRef, css: &str) {
+    apply_styles(
+        document.clone(),
+        &test_device(),
+        &[user_agent_stylesheet()],
+        &[],
+        &[parse_css_to_stylesheet(None, &mut css.to_owned())],
+    );
+}
+
+/// Parses `html` and styles it like `style`.
+pub fn styled_document(html: &str, css: &str) -> NodeRef {
+    let document = parse_html().one(html);
+    style(&document, css);
+    document
+}
+
+/// Builds the box tree of the styled `document` and lays it out in the test viewport.
+pub fn laid_out_box_tree(document: &NodeRef) -> LayoutBox {
+    let mut box_tree =
+        build_box_tree(document.clone(), None).expect("the root element should generate a box");
+    lay_out(&mut box_tree);
+    box_tree
+}
+
+/// Parses and styles `html` like `styled_document`, then lays it out like `laid_out_box_tree`.
+/// Returns the document along with its box tree.
+pub fn laid_out_document(html: &str, css: &str) -> (NodeRef, LayoutBox) {
+    let document = styled_document(html, css);
+    let box_tree = laid_out_box_tree(&document);
+    (document, box_tree)
+}
+
+/// Lays out `box_tree` (again) in the test viewport, at a scale factor of 1.
+pub fn lay_out(box_tree: &mut LayoutBox) {
+    global_layout(box_tree, TEST_VIEWPORT_WIDTH, TEST_VIEWPORT_HEIGHT, 1.);
+}
+
 pub fn font_size_px_or_panic(prop_decl: &PropertyDeclaration) -> &f32 {
     match prop_decl {
         PropertyDeclaration::FontSize(font_size) => match font_size {
@@ -18,6 +76,9 @@ pub fn font_size_px_or_panic(prop_decl: &PropertyDeclaration) -> &f32 {
                         AbsoluteLength::Px(float_val) => &float_val,
                         _ => panic!("should always be `px` AbsoluteLength units"),
                     },
+                    NoCalcLength::ViewportPercentage(_) => {
+                        panic!("should always be `px` AbsoluteLength units")
+                    }
                 },
                 _ => panic!("should always be a `length` variant, not a `calc` or `percentage`"),
             },
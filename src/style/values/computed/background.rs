@@ -1,11 +1,12 @@
 use crate::style::values::computed::{ComputeContext, ComputeValueWithContext, ValueDefault};
 use crate::style::values::specified;
-use cssparser::RGBA;
+use crate::style::StyleParseErrorKind;
+use cssparser::{ParseError, Parser, RGBA};
 
 /// Computed values for the `background-color` property.
 ///
 /// https://www.w3.org/TR/2017/CR-css-backgrounds-3-20171017/#propdef-background-color
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct BackgroundColor(RGBA);
 
 impl BackgroundColor {
@@ -13,6 +14,9 @@ impl BackgroundColor {
         BackgroundColor(match specified::BackgroundColor::initial_value().unit() {
             specified::ColorUnit::CurrentColor => computed_color_prop,
             specified::ColorUnit::Numeric(rgba) => rgba,
+            specified::ColorUnit::System(_) => {
+                unreachable!("the initial background color isn't a system color")
+            }
         })
     }
 
@@ -36,3 +40,38 @@ impl ValueDefault for specified::BackgroundColor {
         BackgroundColor::initial_value(context.color().rgba())
     }
 }
+
+/// Computed values for the `background-image` property.  Only a single background layer whose
+/// image is a `url()` is currently supported.
+///
+/// https://www.w3.org/TR/2017/CR-css-backgrounds-3-20171017/#propdef-background-image
+#[derive(Clone, Debug, PartialEq)]
+pub enum BackgroundImage {
+    None,
+    Url(String),
+}
+
+impl BackgroundImage {
+    pub fn initial_value() -> BackgroundImage {
+        BackgroundImage::None
+    }
+
+    pub fn parse<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        if input.try_parse(|i| i.expect_ident_matching("none")).is_ok() {
+            return Ok(BackgroundImage::None);
+        }
+        Ok(BackgroundImage::Url(
+            input.expect_url()?.as_ref().to_owned(),
+        ))
+    }
+}
+
+impl ValueDefault for BackgroundImage {
+    type ComputedValue = BackgroundImage;
+
+    fn value_default(_context: &ComputeContext) -> Self::ComputedValue {
+        BackgroundImage::initial_value()
+    }
+}
@@ -1,7 +1,5 @@
 use crate::style::values::computed::length::CSSPixelLength;
-use crate::style::values::computed::{
-    ComputeContext, ComputeValue, ComputeValueWithContext, ValueDefault,
-};
+use crate::style::values::computed::{ComputeContext, ComputeValueWithContext, ValueDefault};
 use crate::style::values::specified;
 use crate::style::values::specified::border::LineWidth;
 use crate::style::StyleParseErrorKind;
@@ -26,23 +24,27 @@ pub fn compute_border_side_color(
             .expect("border-color property computed before the color property")
             .rgba(),
         specified::ColorUnit::Numeric(rgba) => rgba,
+        specified::ColorUnit::System(system_color) => {
+            system_color.compute_value_with_context(context)
+        }
     }
 }
 
 pub fn compute_border_side_width(
     self_line_width: LineWidth,
     computed_side_style: LineStyle,
+    context: &ComputeContext,
 ) -> CSSPixelLength {
     match computed_side_style {
         LineStyle::None | LineStyle::Hidden => CSSPixelLength::new(0.),
-        _ => self_line_width.compute_value(),
+        _ => self_line_width.compute_value_with_context(context),
     }
 }
 
 /// Computed `border-bottom-color`.
 ///
 /// https://www.w3.org/TR/2017/CR-css-backgrounds-3-20171017/#the-border-color
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct BorderBottomColor {
     pub rgba: RGBA,
 }
@@ -83,7 +85,7 @@ impl ValueDefault for specified::BorderBottomColor {
 /// Computed `border-left-color`.
 ///
 /// https://www.w3.org/TR/2017/CR-css-backgrounds-3-20171017/#the-border-color
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct BorderLeftColor {
     pub rgba: RGBA,
 }
@@ -124,7 +126,7 @@ impl ValueDefault for specified::BorderLeftColor {
 /// Computed `border-right-color`.
 ///
 /// https://www.w3.org/TR/2017/CR-css-backgrounds-3-20171017/#the-border-color
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct BorderRightColor {
     pub rgba: RGBA,
 }
@@ -165,7 +167,7 @@ impl ValueDefault for specified::BorderRightColor {
 /// Computed `border-top-color`.
 ///
 /// https://www.w3.org/TR/2017/CR-css-backgrounds-3-20171017/#the-border-color
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct BorderTopColor {
     pub rgba: RGBA,
 }
@@ -206,28 +208,21 @@ impl ValueDefault for specified::BorderTopColor {
 /// Computed `border-bottom-width`.
 ///
 /// https://www.w3.org/TR/2017/CR-css-backgrounds-3-20171017/#the-border-width
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct BorderBottomWidth {
     pub size: CSSPixelLength,
 }
 
-impl BorderBottomWidth {
-    pub fn initial_value(computed_bottom_style: LineStyle) -> BorderBottomWidth {
-        BorderBottomWidth {
-            size: compute_border_side_width(
-                specified::BorderBottomWidth::initial_value().line_width,
-                computed_bottom_style,
-            ),
-        }
-    }
-}
-
 impl ComputeValueWithContext for specified::BorderBottomWidth {
     type ComputedValue = BorderBottomWidth;
 
     fn compute_value_with_context(&self, context: &ComputeContext) -> Self::ComputedValue {
         BorderBottomWidth {
-            size: compute_border_side_width(self.line_width, context.border_bottom_style()),
+            size: compute_border_side_width(
+                self.line_width,
+                context.border_bottom_style(),
+                context,
+            ),
         }
     }
 }
@@ -236,35 +231,30 @@ impl ValueDefault for specified::BorderBottomWidth {
     type ComputedValue = BorderBottomWidth;
 
     fn value_default(context: &ComputeContext) -> Self::ComputedValue {
-        BorderBottomWidth::initial_value(context.border_bottom_style())
+        BorderBottomWidth {
+            size: compute_border_side_width(
+                specified::BorderBottomWidth::initial_value().line_width,
+                context.border_bottom_style(),
+                context,
+            ),
+        }
     }
 }
 
 /// Computed `border-left-width`.
 ///
 /// https://www.w3.org/TR/2017/CR-css-backgrounds-3-20171017/#the-border-width
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct BorderLeftWidth {
     pub size: CSSPixelLength,
 }
 
-impl BorderLeftWidth {
-    pub fn initial_value(computed_left_style: LineStyle) -> BorderLeftWidth {
-        BorderLeftWidth {
-            size: compute_border_side_width(
-                specified::BorderLeftWidth::initial_value().line_width,
-                computed_left_style,
-            ),
-        }
-    }
-}
-
 impl ComputeValueWithContext for specified::BorderLeftWidth {
     type ComputedValue = BorderLeftWidth;
 
     fn compute_value_with_context(&self, context: &ComputeContext) -> Self::ComputedValue {
         BorderLeftWidth {
-            size: compute_border_side_width(self.line_width, context.border_left_style()),
+            size: compute_border_side_width(self.line_width, context.border_left_style(), context),
         }
     }
 }
@@ -273,35 +263,30 @@ impl ValueDefault for specified::BorderLeftWidth {
     type ComputedValue = BorderLeftWidth;
 
     fn value_default(context: &ComputeContext) -> Self::ComputedValue {
-        BorderLeftWidth::initial_value(context.border_left_style())
+        BorderLeftWidth {
+            size: compute_border_side_width(
+                specified::BorderLeftWidth::initial_value().line_width,
+                context.border_left_style(),
+                context,
+            ),
+        }
     }
 }
 
 /// Computed `border-right-width`.
 ///
 /// https://www.w3.org/TR/2017/CR-css-backgrounds-3-20171017/#the-border-width
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct BorderRightWidth {
     pub size: CSSPixelLength,
 }
 
-impl BorderRightWidth {
-    pub fn initial_value(computed_right_style: LineStyle) -> BorderRightWidth {
-        BorderRightWidth {
-            size: compute_border_side_width(
-                specified::BorderRightWidth::initial_value().line_width,
-                computed_right_style,
-            ),
-        }
-    }
-}
-
 impl ComputeValueWithContext for specified::BorderRightWidth {
     type ComputedValue = BorderRightWidth;
 
     fn compute_value_with_context(&self, context: &ComputeContext) -> Self::ComputedValue {
         BorderRightWidth {
-            size: compute_border_side_width(self.line_width, context.border_right_style()),
+            size: compute_border_side_width(self.line_width, context.border_right_style(), context),
         }
     }
 }
@@ -310,35 +295,30 @@ impl ValueDefault for specified::BorderRightWidth {
     type ComputedValue = BorderRightWidth;
 
     fn value_default(context: &ComputeContext) -> Self::ComputedValue {
-        BorderRightWidth::initial_value(context.border_right_style())
+        BorderRightWidth {
+            size: compute_border_side_width(
+                specified::BorderRightWidth::initial_value().line_width,
+                context.border_right_style(),
+                context,
+            ),
+        }
     }
 }
 
 /// Computed `border-top-width`.
 ///
 /// https://www.w3.org/TR/2017/CR-css-backgrounds-3-20171017/#the-border-width
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct BorderTopWidth {
     pub size: CSSPixelLength,
 }
 
-impl BorderTopWidth {
-    pub fn initial_value(computed_top_style: LineStyle) -> BorderTopWidth {
-        BorderTopWidth {
-            size: compute_border_side_width(
-                specified::BorderTopWidth::initial_value().line_width,
-                computed_top_style,
-            ),
-        }
-    }
-}
-
 impl ComputeValueWithContext for specified::BorderTopWidth {
     type ComputedValue = BorderTopWidth;
 
     fn compute_value_with_context(&self, context: &ComputeContext) -> Self::ComputedValue {
         BorderTopWidth {
-            size: compute_border_side_width(self.line_width, context.border_top_style()),
+            size: compute_border_side_width(self.line_width, context.border_top_style(), context),
         }
     }
 }
@@ -347,7 +327,13 @@ impl ValueDefault for specified::BorderTopWidth {
     type ComputedValue = BorderTopWidth;
 
     fn value_default(context: &ComputeContext) -> Self::ComputedValue {
-        BorderTopWidth::initial_value(context.border_top_style())
+        BorderTopWidth {
+            size: compute_border_side_width(
+                specified::BorderTopWidth::initial_value().line_width,
+                context.border_top_style(),
+                context,
+            ),
+        }
     }
 }
 
@@ -387,15 +373,15 @@ impl LineStyle {
     }
 }
 
-impl ComputeValue for LineWidth {
+impl ComputeValueWithContext for LineWidth {
     type ComputedValue = CSSPixelLength;
 
-    fn compute_value(&self) -> Self::ComputedValue {
+    fn compute_value_with_context(&self, context: &ComputeContext) -> Self::ComputedValue {
         match self {
             LineWidth::Thin => CSSPixelLength::new(1.),
             LineWidth::Medium => CSSPixelLength::new(3.),
             LineWidth::Thick => CSSPixelLength::new(5.),
-            LineWidth::Length(no_calc_len) => no_calc_len.compute_value(),
+            LineWidth::Length(no_calc_len) => no_calc_len.compute_value_with_context(context),
         }
     }
 }
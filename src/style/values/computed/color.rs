@@ -1,6 +1,9 @@
+use crate::style::media::{ColorScheme, Device};
 use crate::style::values::computed::{ComputeContext, ComputeValueWithContext, ValueDefault};
 use crate::style::values::specified;
-use cssparser::RGBA;
+use crate::style::values::specified::SystemColor;
+use crate::style::StyleParseErrorKind;
+use cssparser::{ParseError, Parser, RGBA};
 
 impl ComputeValueWithContext for specified::ColorUnit {
     type ComputedValue = RGBA;
@@ -9,14 +12,122 @@ impl ComputeValueWithContext for specified::ColorUnit {
         match self {
             specified::ColorUnit::CurrentColor => context.parent_computed_values.color.rgba(),
             specified::ColorUnit::Numeric(rgba) => *rgba,
+            specified::ColorUnit::System(system_color) => {
+                system_color.compute_value_with_context(context)
+            }
+        }
+    }
+}
+
+impl ComputeValueWithContext for SystemColor {
+    type ComputedValue = RGBA;
+
+    /// Resolves the system color in the color scheme used for the node being computed.  The
+    /// colors are Chrome's.
+    fn compute_value_with_context(&self, context: &ComputeContext) -> Self::ComputedValue {
+        let (red, green, blue) = match (context.used_color_scheme(), self) {
+            (ColorScheme::Light, SystemColor::Canvas) => (255, 255, 255),
+            (ColorScheme::Light, SystemColor::CanvasText) => (0, 0, 0),
+            (ColorScheme::Light, SystemColor::LinkText) => (0, 0, 238),
+            (ColorScheme::Light, SystemColor::ButtonFace) => (239, 239, 239),
+            (ColorScheme::Light, SystemColor::ButtonText) => (0, 0, 0),
+            (ColorScheme::Light, SystemColor::ButtonBorder) => (118, 118, 118),
+            (ColorScheme::Light, SystemColor::Field) => (255, 255, 255),
+            (ColorScheme::Light, SystemColor::FieldText) => (0, 0, 0),
+            (ColorScheme::Dark, SystemColor::Canvas) => (18, 18, 18),
+            (ColorScheme::Dark, SystemColor::CanvasText) => (255, 255, 255),
+            (ColorScheme::Dark, SystemColor::LinkText) => (158, 158, 255),
+            (ColorScheme::Dark, SystemColor::ButtonFace) => (107, 107, 107),
+            (ColorScheme::Dark, SystemColor::ButtonText) => (255, 255, 255),
+            (ColorScheme::Dark, SystemColor::ButtonBorder) => (107, 107, 107),
+            (ColorScheme::Dark, SystemColor::Field) => (59, 59, 59),
+            (ColorScheme::Dark, SystemColor::FieldText) => (255, 255, 255),
+        };
+        RGBA::new(red, green, blue, 255)
+    }
+}
+
+/// Computed values for the `color-scheme` property, which lists the color schemes an element can
+/// be rendered in.  The system colors of its used color scheme are used for the element (e.g. in
+/// the UA stylesheet's default text and background colors).
+///
+/// TODO: The `color-scheme` `<meta>` tag isn't supported yet.
+///
+/// https://drafts.csswg.org/css-color-adjust-1/#color-scheme-prop
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SupportedColorSchemes {
+    pub light: bool,
+    pub dark: bool,
+}
+
+impl SupportedColorSchemes {
+    /// `normal`, meaning the element supports no color schemes in particular, and so is rendered
+    /// in the light color scheme.
+    pub fn initial_value() -> SupportedColorSchemes {
+        SupportedColorSchemes::default()
+    }
+
+    /// The color scheme used for elements supporting these color schemes on `device`: the one the
+    /// user prefers, if it's supported, and otherwise light unless only dark is supported.
+    ///
+    /// https://drafts.csswg.org/css-color-adjust-1/#used-color-scheme
+    pub fn used(self, device: &Device) -> ColorScheme {
+        match device.color_scheme() {
+            ColorScheme::Dark if self.dark => ColorScheme::Dark,
+            ColorScheme::Light if self.light => ColorScheme::Light,
+            _ if self.dark && !self.light => ColorScheme::Dark,
+            _ => ColorScheme::Light,
+        }
+    }
+
+    pub fn parse<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        if input
+            .try_parse(|input| input.expect_ident_matching("normal"))
+            .is_ok()
+        {
+            return Ok(SupportedColorSchemes::initial_value());
+        }
+        let location = input.current_source_location();
+        let mut schemes = SupportedColorSchemes::initial_value();
+        let mut has_scheme = false;
+        // Any identifier is allowed (so that color schemes can be added in future), but only
+        // `light` and `dark` are supported.  `only` stops forced colors from overriding the color
+        // scheme, which makes no difference, since forced colors aren't supported.
+        while let Ok(ident) = input.try_parse(|input| input.expect_ident_cloned()) {
+            match_ignore_ascii_case! { &ident,
+                "light" => schemes.light = true,
+                "dark" => schemes.dark = true,
+                "only" => continue,
+                "normal" => {
+                    return Err(location.new_custom_error(
+                        StyleParseErrorKind::OtherInvalidValue(ident.clone()),
+                    ))
+                }
+                _ => {}
+            }
+            has_scheme = true;
+        }
+        if !has_scheme {
+            return Err(location.new_custom_error(StyleParseErrorKind::UnspecifiedError));
         }
+        Ok(schemes)
+    }
+}
+
+impl ValueDefault for SupportedColorSchemes {
+    type ComputedValue = SupportedColorSchemes;
+
+    fn value_default(context: &ComputeContext) -> Self::ComputedValue {
+        context.parent_computed_values.color_scheme
     }
 }
 
 /// Computed value for the `color` property.
 ///
 /// https://www.w3.org/TR/css-color-3/#foreground
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Color(RGBA);
 
 impl Color {
@@ -67,3 +178,51 @@ impl ValueDefault for specified::Color {
         context.parent_computed_values.color
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::style::media::MediaType;
+    use cssparser::ParserInput;
+
+    fn parse_color_scheme(css: &str) -> Option<SupportedColorSchemes> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        parser
+            .parse_entirely(|input| SupportedColorSchemes::parse(input))
+            .ok()
+    }
+
+    #[test]
+    fn parses_color_schemes() {
+        let light_dark = SupportedColorSchemes {
+            light: true,
+            dark: true,
+        };
+        assert_eq!(
+            parse_color_scheme("normal"),
+            Some(SupportedColorSchemes::default())
+        );
+        assert_eq!(parse_color_scheme("dark light"), Some(light_dark));
+        assert_eq!(
+            parse_color_scheme("light sepia dark only"),
+            Some(light_dark)
+        );
+        assert_eq!(parse_color_scheme("only"), None);
+        assert_eq!(parse_color_scheme("light normal"), None);
+    }
+
+    #[test]
+    fn uses_the_preferred_color_scheme_if_supported() {
+        let mut device = Device::new(MediaType::Screen, 800., 600.);
+        device.set_color_scheme(ColorScheme::Dark);
+        let used = |css| parse_color_scheme(css).unwrap().used(&device);
+        assert_eq!(used("normal"), ColorScheme::Light);
+        assert_eq!(used("light"), ColorScheme::Light);
+        assert_eq!(used("light dark"), ColorScheme::Dark);
+        device.set_color_scheme(ColorScheme::Light);
+        let used = |css| parse_color_scheme(css).unwrap().used(&device);
+        assert_eq!(used("dark"), ColorScheme::Dark);
+        assert_eq!(used("dark light"), ColorScheme::Light);
+    }
+}
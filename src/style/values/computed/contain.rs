@@ -0,0 +1,265 @@
+use crate::style::values::computed::length::CSSPixelLength;
+use crate::style::values::computed::{ComputeContext, ComputeValueWithContext, ValueDefault};
+use crate::style::values::specified;
+use crate::style::StyleParseErrorKind;
+use cssparser::{ParseError, Parser};
+use std::ops::BitOr;
+
+/// Computed values for the `contain` property, which isolates a box's subtree from the rest of the
+/// page in the given ways, letting the browser skip work outside of it when the subtree changes.
+///
+/// TODO: Style containment scopes counters and quotes to the subtree, which Kosmonaut doesn't
+/// support yet, so it has no effect.
+///
+/// https://drafts.csswg.org/css-contain-2/#contain-property
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Contain {
+    /// The box is sized as if it had no contents.
+    pub size: bool,
+    /// Nothing inside the box affects the layout of anything outside of it (save for its size),
+    /// and the box establishes a stacking context.
+    pub layout: bool,
+    pub style: bool,
+    /// Nothing inside the box is painted outside of its padding box, and the box establishes a
+    /// stacking context.
+    pub paint: bool,
+}
+
+impl Contain {
+    pub fn initial_value() -> Contain {
+        Contain::default()
+    }
+
+    /// Parses `none`, `strict`, `content`, or any combination of `size`, `layout`, `style`, and
+    /// `paint`, each at most once.
+    pub fn parse<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        let location = input.current_source_location();
+        let ident = input.expect_ident_cloned()?;
+        let mut contain = match_ignore_ascii_case! { &ident,
+            "none" => return Ok(Contain::initial_value()),
+            "strict" => return Ok(Contain { size: true, layout: true, style: true, paint: true }),
+            "content" => return Ok(Contain { size: false, layout: true, style: true, paint: true }),
+            _ => Contain::initial_value(),
+        };
+        let mut ident = Some(ident);
+        while let Some(keyword) = ident {
+            let already_contained = match_ignore_ascii_case! { &keyword,
+                "size" => std::mem::replace(&mut contain.size, true),
+                "layout" => std::mem::replace(&mut contain.layout, true),
+                "style" => std::mem::replace(&mut contain.style, true),
+                "paint" => std::mem::replace(&mut contain.paint, true),
+                _ => true,
+            };
+            if already_contained {
+                return Err(location.new_custom_error(StyleParseErrorKind::UnspecifiedError));
+            }
+            ident = input.try_parse(|input| input.expect_ident_cloned()).ok();
+        }
+        Ok(contain)
+    }
+
+    /// Whether nothing inside the box can affect the layout of anything outside of it, including
+    /// its size, so the box can be laid out again without laying out anything else.
+    pub fn isolates_layout(self) -> bool {
+        self.size && self.layout
+    }
+
+    /// Whether the box establishes a stacking context.
+    pub fn creates_stacking_context(self) -> bool {
+        self.layout || self.paint
+    }
+}
+
+impl BitOr for Contain {
+    type Output = Contain;
+
+    /// The containment of a box that has both this containment and `other`'s.
+    fn bitor(self, other: Contain) -> Contain {
+        Contain {
+            size: self.size || other.size,
+            layout: self.layout || other.layout,
+            style: self.style || other.style,
+            paint: self.paint || other.paint,
+        }
+    }
+}
+
+impl ValueDefault for Contain {
+    type ComputedValue = Contain;
+
+    fn value_default(_context: &ComputeContext) -> Self::ComputedValue {
+        Contain::initial_value()
+    }
+}
+
+/// Computed values for the `content-visibility` property, which lets a box skip laying out and
+/// painting its contents (as if they were `display: none`, but keeping their styles), either
+/// always, or while the box is off-screen, which makes very long documents fast to lay out.
+///
+/// TODO: The contents of skipped boxes are still styled, since styles are matched against the
+/// whole document at once.
+///
+/// https://drafts.csswg.org/css-contain-2/#content-visibility
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ContentVisibility {
+    Visible,
+    /// The box skips its contents unless it's relevant to the user (e.g. it's on or near the
+    /// screen, or contains the focused element).
+    Auto,
+    /// The box always skips its contents.
+    Hidden,
+}
+
+impl ContentVisibility {
+    pub fn initial_value() -> ContentVisibility {
+        ContentVisibility::Visible
+    }
+
+    pub fn parse<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        try_match_ident_ignore_ascii_case! { input,
+            "visible" => Ok(ContentVisibility::Visible),
+            "auto" => Ok(ContentVisibility::Auto),
+            "hidden" => Ok(ContentVisibility::Hidden),
+        }
+    }
+
+    /// Whether a box with this `content-visibility` skips its contents, given whether its element
+    /// is relevant to the user.
+    ///
+    /// https://drafts.csswg.org/css-contain-2/#skips-its-contents
+    pub fn skips_contents(self, relevant_to_user: bool) -> bool {
+        match self {
+            ContentVisibility::Visible => false,
+            ContentVisibility::Auto => !relevant_to_user,
+            ContentVisibility::Hidden => true,
+        }
+    }
+
+    /// The containment this `content-visibility` gives a box on top of its `contain`, given
+    /// whether its element is relevant to the user.  Boxes that skip their contents have size
+    /// containment, while `auto` boxes have layout, style, and paint containment either way, so
+    /// skipping their contents (or not) can't change the layout of anything else.
+    pub fn containment(self, relevant_to_user: bool) -> Contain {
+        let skips_contents = self.skips_contents(relevant_to_user);
+        match self {
+            ContentVisibility::Visible => Contain::initial_value(),
+            ContentVisibility::Auto | ContentVisibility::Hidden => Contain {
+                size: skips_contents,
+                layout: true,
+                style: true,
+                paint: true,
+            },
+        }
+    }
+}
+
+impl ValueDefault for ContentVisibility {
+    type ComputedValue = ContentVisibility;
+
+    fn value_default(_context: &ComputeContext) -> Self::ComputedValue {
+        ContentVisibility::initial_value()
+    }
+}
+
+/// Computed value of `contain-intrinsic-width` or `contain-intrinsic-height` (see
+/// `specified::ContainIntrinsicSize`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ContainIntrinsicSize {
+    /// The size a box with size containment has in this axis, as if it had a single child of
+    /// this size, or `None` if it's sized as if it had no children.
+    pub size: Option<CSSPixelLength>,
+}
+
+impl ContainIntrinsicSize {
+    pub fn initial_value() -> ContainIntrinsicSize {
+        ContainIntrinsicSize { size: None }
+    }
+}
+
+impl ComputeValueWithContext for specified::ContainIntrinsicSize {
+    type ComputedValue = ContainIntrinsicSize;
+
+    fn compute_value_with_context(&self, context: &ComputeContext) -> Self::ComputedValue {
+        ContainIntrinsicSize {
+            size: self
+                .length
+                .map(|length| length.compute_value_with_context(context)),
+        }
+    }
+}
+
+impl ValueDefault for specified::ContainIntrinsicSize {
+    type ComputedValue = ContainIntrinsicSize;
+
+    fn value_default(_context: &ComputeContext) -> Self::ComputedValue {
+        ContainIntrinsicSize::initial_value()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cssparser::ParserInput;
+
+    fn parse_contain(css: &str) -> Option<Contain> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        parser.parse_entirely(Contain::parse).ok()
+    }
+
+    #[test]
+    fn parses_contain() {
+        assert_eq!(parse_contain("none"), Some(Contain::initial_value()));
+        assert_eq!(
+            parse_contain("strict"),
+            Some(Contain {
+                size: true,
+                layout: true,
+                style: true,
+                paint: true
+            })
+        );
+        assert_eq!(
+            parse_contain("Paint layout"),
+            Some(Contain {
+                size: false,
+                layout: true,
+                style: false,
+                paint: true
+            })
+        );
+        assert_eq!(parse_contain("size size"), None);
+        assert_eq!(parse_contain("content paint"), None);
+        assert_eq!(parse_contain("layout none"), None);
+    }
+
+    #[test]
+    fn content_visibility_contains_and_skips_contents() {
+        let parse = |css| {
+            let mut input = ParserInput::new(css);
+            let mut parser = Parser::new(&mut input);
+            parser.parse_entirely(ContentVisibility::parse).ok()
+        };
+        assert_eq!(parse("Auto"), Some(ContentVisibility::Auto));
+        assert_eq!(parse("none"), None);
+
+        assert!(!ContentVisibility::Auto.skips_contents(true));
+        assert!(ContentVisibility::Auto.skips_contents(false));
+        assert!(ContentVisibility::Hidden.skips_contents(true));
+        let relevant = ContentVisibility::Auto.containment(true);
+        assert!(relevant.layout && relevant.paint && !relevant.size);
+        assert!(ContentVisibility::Auto.containment(false).isolates_layout());
+        assert_eq!(
+            ContentVisibility::Visible.containment(false),
+            Contain::initial_value()
+        );
+        assert_eq!(
+            parse_contain("paint").unwrap() | parse_contain("size").unwrap(),
+            parse_contain("size paint").unwrap()
+        );
+    }
+}
@@ -0,0 +1,183 @@
+use crate::style::values::computed::{ComputeContext, Contain, ValueDefault};
+use crate::style::StyleParseErrorKind;
+use cssparser::{ParseError, Parser, Token};
+
+/// Computed values for the `container-type` property, which makes an element a query container,
+/// whose size the `@container` rules of its descendants are evaluated against (see
+/// `ContainerCondition`).
+///
+/// https://drafts.csswg.org/css-contain-3/#container-type
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ContainerType {
+    /// The element isn't a query container.
+    Normal,
+    /// Its size can be queried in both axes.
+    Size,
+    /// Its size can only be queried in its inline axis.
+    InlineSize,
+}
+
+impl ContainerType {
+    pub fn initial_value() -> ContainerType {
+        ContainerType::Normal
+    }
+
+    pub fn parse<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        try_match_ident_ignore_ascii_case! { input,
+            "normal" => Ok(ContainerType::Normal),
+            "size" => Ok(ContainerType::Size),
+            "inline-size" => Ok(ContainerType::InlineSize),
+        }
+    }
+
+    /// Whether the size of a query container of this type can be queried in its block axis too.
+    pub fn queries_block_size(self) -> bool {
+        self == ContainerType::Size
+    }
+
+    /// The containment this `container-type` gives a box on top of its `contain`, so that what a
+    /// container's descendants look like can't change the size they're queried against.
+    ///
+    /// Query containers of `inline-size` should have inline-size containment, but the inline size
+    /// of block containers never depends on their contents in Kosmonaut, so they get layout and
+    /// style containment alone.
+    pub fn containment(self) -> Contain {
+        match self {
+            ContainerType::Normal => Contain::initial_value(),
+            ContainerType::Size => Contain {
+                size: true,
+                layout: true,
+                style: true,
+                paint: false,
+            },
+            ContainerType::InlineSize => Contain {
+                size: false,
+                layout: true,
+                style: true,
+                paint: false,
+            },
+        }
+    }
+}
+
+impl ValueDefault for ContainerType {
+    type ComputedValue = ContainerType;
+
+    fn value_default(_context: &ComputeContext) -> Self::ComputedValue {
+        ContainerType::initial_value()
+    }
+}
+
+/// Computed values for the `container-name` property, the names `@container` rules can select an
+/// element's query container by.
+///
+/// https://drafts.csswg.org/css-contain-3/#container-name
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ContainerName {
+    /// The names given, which is empty for `none`.
+    pub names: Vec<String>,
+}
+
+impl ContainerName {
+    pub fn initial_value() -> ContainerName {
+        ContainerName::default()
+    }
+
+    pub fn parse<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        if input
+            .try_parse(|input| input.expect_ident_matching("none"))
+            .is_ok()
+        {
+            return Ok(ContainerName::initial_value());
+        }
+        let mut names = vec![parse_container_name(input)?];
+        while let Ok(name) = input.try_parse(parse_container_name) {
+            names.push(name);
+        }
+        Ok(ContainerName { names })
+    }
+
+    /// Parses the `container` shorthand, a `container-name` optionally followed by a `/` and a
+    /// `container-type`, into its longhands.
+    pub fn parse_shorthand<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<(ContainerName, ContainerType), ParseError<'i, StyleParseErrorKind<'i>>> {
+        let name = ContainerName::parse(input)?;
+        let container_type = if input.try_parse(|input| input.expect_delim('/')).is_ok() {
+            ContainerType::parse(input)?
+        } else {
+            ContainerType::initial_value()
+        };
+        Ok((name, container_type))
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.iter().any(|own_name| own_name == name)
+    }
+}
+
+/// Parses a single container name, a case-sensitive identifier.
+pub(crate) fn parse_container_name<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<String, ParseError<'i, StyleParseErrorKind<'i>>> {
+    let location = input.current_source_location();
+    let ident = input.expect_ident_cloned()?;
+    match_ignore_ascii_case! { &ident,
+        // Keywords that can't be container names, as they'd be ambiguous.
+        "none" | "and" | "not" | "or" | "inherit" | "initial" | "unset" | "default" => {
+            Err(location.new_unexpected_token_error(Token::Ident(ident.clone())))
+        },
+        _ => Ok(ident.to_string()),
+    }
+}
+
+impl ValueDefault for ContainerName {
+    type ComputedValue = ContainerName;
+
+    fn value_default(_context: &ComputeContext) -> Self::ComputedValue {
+        ContainerName::initial_value()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cssparser::ParserInput;
+
+    fn parse_container(css: &str) -> Option<(ContainerName, ContainerType)> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        parser.parse_entirely(ContainerName::parse_shorthand).ok()
+    }
+
+    fn names(names: &[&str]) -> ContainerName {
+        ContainerName {
+            names: names.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn parses_container() {
+        assert_eq!(
+            parse_container("none"),
+            Some((ContainerName::initial_value(), ContainerType::Normal))
+        );
+        assert_eq!(
+            parse_container("sidebar Card / Inline-Size"),
+            Some((names(&["sidebar", "Card"]), ContainerType::InlineSize))
+        );
+        assert_eq!(
+            parse_container("main / size"),
+            Some((names(&["main"]), ContainerType::Size))
+        );
+        assert_eq!(parse_container("main none"), None);
+        assert_eq!(parse_container("/ size"), None);
+        assert_eq!(parse_container("main / block-size"), None);
+        assert!(names(&["Card"]).contains("Card"));
+        assert!(!names(&["Card"]).contains("card"));
+    }
+}
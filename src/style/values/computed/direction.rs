@@ -2,7 +2,7 @@ use crate::style::values::computed::{ComputeContext, ValueDefault};
 use crate::style::StyleParseErrorKind;
 use cssparser::{ParseError, Parser};
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 /// https://drafts.csswg.org/css-writing-modes-4/#propdef-direction
 pub enum Direction {
     Ltr,
@@ -62,6 +62,17 @@ impl WritingMode {
         }
     }
 
+    /// The keyword this value is specified with.
+    pub fn as_css_str(&self) -> &'static str {
+        match self {
+            WritingMode::HorizontalTb => "horizontal-tb",
+            WritingMode::VerticalRl => "vertical-rl",
+            WritingMode::VerticalLr => "vertical-lr",
+            WritingMode::SidewaysRl => "sideways-rl",
+            WritingMode::SidewaysLr => "sideways-lr",
+        }
+    }
+
     pub fn is_horizontal(&self) -> bool {
         match self {
             WritingMode::VerticalLr
@@ -48,6 +48,19 @@ impl Display {
     pub fn initial_value() -> Self {
         Display::new_full_display(OuterDisplay::Inline, InnerDisplay::Flow)
     }
+
+    /// This display with a block outer display type, which boxes that can't be inline-level
+    /// (e.g. absolutely positioned ones) get.  `display: none` is unchanged.
+    ///
+    /// https://drafts.csswg.org/css-display/#blockify
+    pub fn blockified(self) -> Display {
+        match self {
+            Display::Full(full_display) => {
+                Display::new_full_display(OuterDisplay::Block, full_display.inner())
+            }
+            Display::Box(_) => self,
+        }
+    }
 }
 
 impl ValueDefault for Display {
@@ -1,11 +1,11 @@
 use crate::style::values::computed::length::CSSPixelLength;
-use crate::style::values::computed::{
-    ComputeContext, ComputeValue, ComputeValueWithContext, ValueDefault,
-};
+use crate::style::values::computed::{ComputeContext, ComputeValueWithContext, ValueDefault};
 use crate::style::values::specified;
 use crate::style::values::specified::font::KeywordSize;
-use crate::style::values::specified::{LengthPercentage, NoCalcLength};
+use crate::style::values::specified::LengthPercentage;
+use crate::style::StyleParseErrorKind;
 use app_units::Au;
+use cssparser::{ParseError, Parser, Token};
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 /// The computed value of font-size
@@ -50,9 +50,9 @@ impl ComputeValueWithContext for specified::FontSize {
                 keyword_size.compute_value_with_context(&context),
                 Some(*keyword_size),
             ),
-            specified::FontSize::Length(LengthPercentage::Length(NoCalcLength::Absolute(
-                abs_len,
-            ))) => (abs_len.compute_value(), None),
+            specified::FontSize::Length(LengthPercentage::Length(no_calc_length)) => {
+                (no_calc_length.compute_value_with_context(context), None)
+            }
             specified::FontSize::Length(specified::LengthPercentage::Percentage(percentage)) => {
                 let parent_font = context.parent_computed_values.font_size;
                 (
@@ -91,3 +91,438 @@ impl ComputeValueWithContext for KeywordSize {
         .into()
     }
 }
+
+/// Computed values for the `font-family` property: the font families to render text with, in order
+/// of preference.  Later families are fallen back on for characters earlier ones can't render.
+///
+/// https://drafts.csswg.org/css-fonts-4/#font-family-prop
+#[derive(Clone, Debug, PartialEq)]
+pub struct FontFamily(pub Vec<SingleFontFamily>);
+
+impl FontFamily {
+    /// The initial value is UA-dependent.  Like other browsers, Kosmonaut defaults to `serif`.
+    pub fn initial_value() -> FontFamily {
+        FontFamily(vec![SingleFontFamily::Generic(GenericFontFamily::Serif)])
+    }
+
+    pub fn parse<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        Ok(FontFamily(
+            input.parse_comma_separated(|i| SingleFontFamily::parse(i))?,
+        ))
+    }
+}
+
+impl ValueDefault for FontFamily {
+    type ComputedValue = FontFamily;
+
+    fn value_default(context: &ComputeContext) -> Self::ComputedValue {
+        context.parent_computed_values.font_family.clone()
+    }
+}
+
+/// A single entry of a `font-family` list.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum SingleFontFamily {
+    /// The name of a font family, e.g. `"Times New Roman"` or `Helvetica`.
+    FamilyName(String),
+    Generic(GenericFontFamily),
+}
+
+impl SingleFontFamily {
+    /// Parses a family name, either as a string or as a sequence of identifiers joined by single
+    /// spaces, or a generic family keyword.
+    ///
+    /// https://drafts.csswg.org/css-fonts-4/#family-name-value
+    pub fn parse<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        if let Ok(name) = input.try_parse(|i| i.expect_string_cloned()) {
+            return Ok(SingleFontFamily::FamilyName(name.as_ref().to_owned()));
+        }
+        let first_ident = input.expect_ident_cloned()?;
+        let mut name = first_ident.as_ref().to_owned();
+        let mut ident_count = 1;
+        while let Ok(ident) = input.try_parse(|i| i.expect_ident_cloned()) {
+            name.push(' ');
+            name.push_str(&ident);
+            ident_count += 1;
+        }
+        // Generic families are keywords, so only unquoted single identifiers can be one.
+        if ident_count == 1 {
+            if let Some(generic) = GenericFontFamily::from_keyword(&name) {
+                return Ok(SingleFontFamily::Generic(generic));
+            }
+        }
+        Ok(SingleFontFamily::FamilyName(name))
+    }
+}
+
+/// https://drafts.csswg.org/css-fonts-4/#generic-font-families
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum GenericFontFamily {
+    Cursive,
+    Fantasy,
+    Monospace,
+    SansSerif,
+    Serif,
+    SystemUi,
+}
+
+impl GenericFontFamily {
+    fn from_keyword(keyword: &str) -> Option<GenericFontFamily> {
+        match_ignore_ascii_case! { keyword,
+            "cursive" => Some(GenericFontFamily::Cursive),
+            "fantasy" => Some(GenericFontFamily::Fantasy),
+            "monospace" => Some(GenericFontFamily::Monospace),
+            "sans-serif" => Some(GenericFontFamily::SansSerif),
+            "serif" => Some(GenericFontFamily::Serif),
+            "system-ui" => Some(GenericFontFamily::SystemUi),
+            _ => None,
+        }
+    }
+}
+
+/// Computed values for the `font-weight` property: a numeric weight between 1 and 1000, where
+/// 400 is normal and 700 is bold.
+///
+/// https://drafts.csswg.org/css-fonts-4/#font-weight-prop
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FontWeight(pub f32);
+
+impl FontWeight {
+    pub fn initial_value() -> FontWeight {
+        FontWeight(400.)
+    }
+
+    /// Whether text of this weight is bold, which is when fonts too light for it are made bolder.
+    pub fn is_bold(self) -> bool {
+        self.0 >= 600.
+    }
+}
+
+impl ValueDefault for specified::FontWeight {
+    type ComputedValue = FontWeight;
+
+    fn value_default(context: &ComputeContext) -> Self::ComputedValue {
+        context.parent_computed_values.font_weight
+    }
+}
+
+impl ComputeValueWithContext for specified::FontWeight {
+    type ComputedValue = FontWeight;
+
+    /// Relative weights are computed from the parent's weight.
+    ///
+    /// https://drafts.csswg.org/css-fonts-4/#relative-weights
+    fn compute_value_with_context(&self, context: &ComputeContext) -> Self::ComputedValue {
+        let parent_weight = context.parent_computed_values.font_weight.0;
+        FontWeight(match self {
+            specified::FontWeight::Absolute(weight) => *weight,
+            specified::FontWeight::Bolder if parent_weight < 350. => 400.,
+            specified::FontWeight::Bolder if parent_weight < 550. => 700.,
+            specified::FontWeight::Bolder if parent_weight < 900. => 900.,
+            specified::FontWeight::Bolder => parent_weight,
+            specified::FontWeight::Lighter if parent_weight < 100. => parent_weight,
+            specified::FontWeight::Lighter if parent_weight < 550. => 100.,
+            specified::FontWeight::Lighter if parent_weight < 750. => 400.,
+            specified::FontWeight::Lighter => 700.,
+        })
+    }
+}
+
+/// Computed values for the `font-style` property.
+///
+/// https://drafts.csswg.org/css-fonts-4/#font-style-prop
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum FontStyle {
+    Normal,
+    Italic,
+    /// TODO: Keep the angle of `oblique <angle>` once variable fonts are supported.  For now, it's
+    /// parsed but oblique text is always slanted by the font's (or a synthesized) default angle.
+    Oblique,
+}
+
+impl FontStyle {
+    pub fn initial_value() -> FontStyle {
+        FontStyle::Normal
+    }
+
+    pub fn parse<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        let style = try_match_ident_ignore_ascii_case! { input,
+            "normal" => FontStyle::Normal,
+            "italic" => FontStyle::Italic,
+            "oblique" => FontStyle::Oblique,
+        };
+        if style == FontStyle::Oblique {
+            let _ = input.try_parse(|i| {
+                let location = i.current_source_location();
+                match *i.next()? {
+                    Token::Dimension {
+                        value, ref unit, ..
+                    } if unit.eq_ignore_ascii_case("deg") && (-90. ..=90.).contains(&value) => {
+                        Ok(())
+                    }
+                    _ => Err(location.new_custom_error(StyleParseErrorKind::UnspecifiedError)),
+                }
+            });
+        }
+        Ok(style)
+    }
+}
+
+impl ValueDefault for FontStyle {
+    type ComputedValue = FontStyle;
+
+    fn value_default(context: &ComputeContext) -> Self::ComputedValue {
+        context.parent_computed_values.font_style
+    }
+}
+
+/// Computed values for the `font-variant-caps` property: which glyphs to render letters with,
+/// e.g. small capitals rather than lowercase letters.
+///
+/// https://drafts.csswg.org/css-fonts-4/#font-variant-caps-prop
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum FontVariantCaps {
+    Normal,
+    SmallCaps,
+    AllSmallCaps,
+    PetiteCaps,
+    AllPetiteCaps,
+    Unicase,
+    TitlingCaps,
+}
+
+impl FontVariantCaps {
+    pub fn initial_value() -> FontVariantCaps {
+        FontVariantCaps::Normal
+    }
+
+    pub fn parse<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        try_match_ident_ignore_ascii_case! { input,
+            "normal" => Ok(FontVariantCaps::Normal),
+            "small-caps" => Ok(FontVariantCaps::SmallCaps),
+            "all-small-caps" => Ok(FontVariantCaps::AllSmallCaps),
+            "petite-caps" => Ok(FontVariantCaps::PetiteCaps),
+            "all-petite-caps" => Ok(FontVariantCaps::AllPetiteCaps),
+            "unicase" => Ok(FontVariantCaps::Unicase),
+            "titling-caps" => Ok(FontVariantCaps::TitlingCaps),
+        }
+    }
+}
+
+impl ValueDefault for FontVariantCaps {
+    type ComputedValue = FontVariantCaps;
+
+    fn value_default(context: &ComputeContext) -> Self::ComputedValue {
+        context.parent_computed_values.font_variant_caps
+    }
+}
+
+/// Computed values for the `font-feature-settings` property: OpenType features to turn on or
+/// off when shaping text, passed through to the shaper as they are.  `normal` is an empty list.
+///
+/// https://drafts.csswg.org/css-fonts-4/#font-feature-settings-prop
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct FontFeatureSettings(pub Vec<FeatureTagValue>);
+
+impl FontFeatureSettings {
+    pub fn initial_value() -> FontFeatureSettings {
+        FontFeatureSettings(Vec::new())
+    }
+
+    pub fn parse<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        if input
+            .try_parse(|i| i.expect_ident_matching("normal"))
+            .is_ok()
+        {
+            return Ok(FontFeatureSettings::initial_value());
+        }
+        Ok(FontFeatureSettings(
+            input.parse_comma_separated(|i| FeatureTagValue::parse(i))?,
+        ))
+    }
+}
+
+impl ValueDefault for FontFeatureSettings {
+    type ComputedValue = FontFeatureSettings;
+
+    fn value_default(context: &ComputeContext) -> Self::ComputedValue {
+        context.parent_computed_values.font_feature_settings.clone()
+    }
+}
+
+/// A single entry of a `font-feature-settings` list: an OpenType feature tag, and the value to
+/// set the feature to.  For most features, 0 turns the feature off and 1 turns it on, but some
+/// choose between several alternate glyphs with larger values.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct FeatureTagValue {
+    pub tag: [u8; 4],
+    pub value: u32,
+}
+
+impl FeatureTagValue {
+    /// Parses a feature tag, which is a string of four ASCII characters, followed by an optional
+    /// non-negative integer, `on` (1), or `off` (0).  The value defaults to 1.
+    ///
+    /// https://drafts.csswg.org/css-fonts-4/#feature-tag-value
+    pub fn parse<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        let location = input.current_source_location();
+        let tag_string = input.expect_string()?;
+        let is_valid_tag =
+            tag_string.len() == 4 && tag_string.bytes().all(|byte| (0x20..=0x7E).contains(&byte));
+        if !is_valid_tag {
+            return Err(location.new_custom_error(StyleParseErrorKind::UnspecifiedError));
+        }
+        let mut tag = [0; 4];
+        tag.copy_from_slice(tag_string.as_bytes());
+
+        let value = input
+            .try_parse(|i| {
+                let location = i.current_source_location();
+                match *i.next()? {
+                    Token::Number {
+                        int_value: Some(value),
+                        ..
+                    } if value >= 0 => Ok(value as u32),
+                    Token::Ident(ref ident) if ident.eq_ignore_ascii_case("on") => Ok(1),
+                    Token::Ident(ref ident) if ident.eq_ignore_ascii_case("off") => Ok(0),
+                    _ => Err(location.new_custom_error(StyleParseErrorKind::UnspecifiedError)),
+                }
+            })
+            .unwrap_or(1);
+        Ok(FeatureTagValue { tag, value })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::style::media::{Device, MediaType};
+    use crate::style::values::computed::ComputedValues;
+    use cssparser::ParserInput;
+
+    fn parse_font_family(css: &str) -> Option<FontFamily> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        parser.parse_entirely(|input| FontFamily::parse(input)).ok()
+    }
+
+    #[test]
+    fn parses_family_names_and_generic_families() {
+        assert_eq!(
+            parse_font_family("\"Noto Sans\", Times   New Roman, SANS-SERIF, \"serif\""),
+            Some(FontFamily(vec![
+                SingleFontFamily::FamilyName("Noto Sans".to_owned()),
+                SingleFontFamily::FamilyName("Times New Roman".to_owned()),
+                SingleFontFamily::Generic(GenericFontFamily::SansSerif),
+                // Quoted keywords are family names, not generic families.
+                SingleFontFamily::FamilyName("serif".to_owned()),
+            ]))
+        );
+        assert_eq!(parse_font_family("Arial,"), None);
+        assert_eq!(parse_font_family("12px"), None);
+    }
+
+    fn compute_font_weight(weight: specified::FontWeight, parent_weight: f32) -> f32 {
+        let device = Device::new(MediaType::Screen, 800., 600.);
+        let parent_computed_values = ComputedValues {
+            font_weight: FontWeight(parent_weight),
+            ..ComputedValues::default()
+        };
+        let context = ComputeContext {
+            device: &device,
+            parent_computed_values: &parent_computed_values,
+            computed_color: None,
+            computed_color_scheme: None,
+            computed_border_styles: None,
+        };
+        weight.compute_value_with_context(&context).0
+    }
+
+    #[test]
+    fn relative_font_weights_step_from_the_parent_weight() {
+        use specified::FontWeight::{Absolute, Bolder, Lighter};
+        assert_eq!(compute_font_weight(Absolute(550.), 100.), 550.);
+        assert_eq!(compute_font_weight(Bolder, 300.), 400.);
+        assert_eq!(compute_font_weight(Bolder, 400.), 700.);
+        assert_eq!(compute_font_weight(Bolder, 700.), 900.);
+        assert_eq!(compute_font_weight(Bolder, 950.), 950.);
+        assert_eq!(compute_font_weight(Lighter, 50.), 50.);
+        assert_eq!(compute_font_weight(Lighter, 400.), 100.);
+        assert_eq!(compute_font_weight(Lighter, 700.), 400.);
+        assert_eq!(compute_font_weight(Lighter, 900.), 700.);
+    }
+
+    #[test]
+    fn parses_font_style_with_an_optional_oblique_angle() {
+        let parse_font_style = |css| {
+            let mut input = ParserInput::new(css);
+            let mut parser = Parser::new(&mut input);
+            parser.parse_entirely(|input| FontStyle::parse(input)).ok()
+        };
+        assert_eq!(parse_font_style("ITALIC"), Some(FontStyle::Italic));
+        assert_eq!(parse_font_style("oblique"), Some(FontStyle::Oblique));
+        assert_eq!(parse_font_style("oblique 10deg"), Some(FontStyle::Oblique));
+        assert_eq!(parse_font_style("oblique 100deg"), None);
+        assert_eq!(parse_font_style("italic 10deg"), None);
+    }
+
+    #[test]
+    fn parses_font_variant_caps() {
+        let parse_font_variant_caps = |css| {
+            let mut input = ParserInput::new(css);
+            let mut parser = Parser::new(&mut input);
+            parser
+                .parse_entirely(|input| FontVariantCaps::parse(input))
+                .ok()
+        };
+        assert_eq!(
+            parse_font_variant_caps("Small-Caps"),
+            Some(FontVariantCaps::SmallCaps)
+        );
+        assert_eq!(
+            parse_font_variant_caps("all-petite-caps"),
+            Some(FontVariantCaps::AllPetiteCaps)
+        );
+        assert_eq!(parse_font_variant_caps("small-caps unicase"), None);
+    }
+
+    #[test]
+    fn parses_font_feature_settings() {
+        let parse_font_feature_settings = |css| {
+            let mut input = ParserInput::new(css);
+            let mut parser = Parser::new(&mut input);
+            parser
+                .parse_entirely(|input| FontFeatureSettings::parse(input))
+                .ok()
+        };
+        let feature = |tag: &[u8; 4], value| FeatureTagValue { tag: *tag, value };
+        assert_eq!(
+            parse_font_feature_settings("normal"),
+            Some(FontFeatureSettings(vec![]))
+        );
+        assert_eq!(
+            parse_font_feature_settings("\"liga\" off, \"smcp\", \"swsh\" 2, \"kern\" ON"),
+            Some(FontFeatureSettings(vec![
+                feature(b"liga", 0),
+                feature(b"smcp", 1),
+                feature(b"swsh", 2),
+                feature(b"kern", 1),
+            ]))
+        );
+        assert_eq!(parse_font_feature_settings("\"smcp\" -1"), None);
+        assert_eq!(parse_font_feature_settings("\"small\""), None);
+        assert_eq!(parse_font_feature_settings("smcp"), None);
+    }
+}
@@ -1,11 +1,9 @@
 use crate::style::values::computed::length::{LengthPercentage, LengthPercentageOrAuto};
-use crate::style::values::computed::{
-    ComputeContext, ComputeValue, ComputeValueWithContext, ValueDefault,
-};
+use crate::style::values::computed::{ComputeContext, ComputeValueWithContext, ValueDefault};
 use crate::style::values::specified;
 
 /// Computed value of a `height`.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Height {
     pub size: LengthPercentageOrAuto,
 }
@@ -21,7 +19,7 @@ impl Height {
 impl ComputeValueWithContext for specified::Height {
     type ComputedValue = Height;
 
-    fn compute_value_with_context(&self, _context: &ComputeContext) -> Self::ComputedValue {
+    fn compute_value_with_context(&self, context: &ComputeContext) -> Self::ComputedValue {
         let computed_lp_auto: LengthPercentageOrAuto = match self {
             // TODO: I think we repeat computing the value of specified::LengthPercentageOrAuto a lot...eventually consider
             // simply implementing `ComputeValueWithContext` for specified::LengthPercentageOrAuto.
@@ -29,7 +27,7 @@ impl ComputeValueWithContext for specified::Height {
                 specified::LengthPercentageOrAuto::Auto => LengthPercentageOrAuto::Auto,
                 specified::LengthPercentageOrAuto::LengthPercentage(lp) => match lp {
                     specified::LengthPercentage::Length(no_calc_length) => {
-                        no_calc_length.compute_value().into()
+                        no_calc_length.compute_value_with_context(context).into()
                     }
                     specified::LengthPercentage::Percentage(percentage) => {
                         LengthPercentageOrAuto::LengthPercentage(LengthPercentage::Percentage(
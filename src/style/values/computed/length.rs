@@ -1,4 +1,6 @@
-use crate::style::values::computed::{ComputeValue, Percentage};
+use crate::style::values::computed::{
+    ComputeContext, ComputeValue, ComputeValueWithContext, Percentage,
+};
 use crate::style::values::{specified, CSSFloat};
 use app_units::Au;
 use std::cmp::Ordering;
@@ -238,12 +240,18 @@ impl ComputeValue for specified::AbsoluteLength {
     }
 }
 
-impl ComputeValue for specified::NoCalcLength {
+impl ComputeValueWithContext for specified::NoCalcLength {
     type ComputedValue = CSSPixelLength;
 
-    fn compute_value(&self) -> Self::ComputedValue {
+    fn compute_value_with_context(&self, context: &ComputeContext) -> Self::ComputedValue {
         match self {
             specified::NoCalcLength::Absolute(abs_len) => abs_len.compute_value(),
+            specified::NoCalcLength::ViewportPercentage(viewport_len) => {
+                CSSPixelLength(viewport_len.to_px(
+                    context.device.viewport_width(),
+                    context.device.viewport_height(),
+                ))
+            }
         }
     }
 }
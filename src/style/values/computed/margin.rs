@@ -1,11 +1,9 @@
 use crate::style::values::computed::length::{LengthPercentage, LengthPercentageOrAuto};
-use crate::style::values::computed::{
-    ComputeContext, ComputeValue, ComputeValueWithContext, ValueDefault,
-};
+use crate::style::values::computed::{ComputeContext, ComputeValueWithContext, ValueDefault};
 use crate::style::values::specified;
 
 /// Computed value of a `margin-bottom`.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct MarginBottom {
     pub size: LengthPercentageOrAuto,
 }
@@ -21,9 +19,9 @@ impl MarginBottom {
 impl ComputeValueWithContext for specified::MarginBottom {
     type ComputedValue = MarginBottom;
 
-    fn compute_value_with_context(&self, _context: &ComputeContext) -> Self::ComputedValue {
+    fn compute_value_with_context(&self, context: &ComputeContext) -> Self::ComputedValue {
         MarginBottom {
-            size: computed_margin_size(&self.lp_or_auto),
+            size: computed_margin_size(&self.lp_or_auto, context),
         }
     }
 }
@@ -37,7 +35,7 @@ impl ValueDefault for specified::MarginBottom {
 }
 
 /// Computed value of a `margin-left`.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct MarginLeft {
     pub size: LengthPercentageOrAuto,
 }
@@ -53,9 +51,9 @@ impl MarginLeft {
 impl ComputeValueWithContext for specified::MarginLeft {
     type ComputedValue = MarginLeft;
 
-    fn compute_value_with_context(&self, _context: &ComputeContext) -> Self::ComputedValue {
+    fn compute_value_with_context(&self, context: &ComputeContext) -> Self::ComputedValue {
         MarginLeft {
-            size: computed_margin_size(&self.lp_or_auto),
+            size: computed_margin_size(&self.lp_or_auto, context),
         }
     }
 }
@@ -69,7 +67,7 @@ impl ValueDefault for specified::MarginLeft {
 }
 
 /// Computed value of a `margin-right`.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct MarginRight {
     pub size: LengthPercentageOrAuto,
 }
@@ -85,9 +83,9 @@ impl MarginRight {
 impl ComputeValueWithContext for specified::MarginRight {
     type ComputedValue = MarginRight;
 
-    fn compute_value_with_context(&self, _context: &ComputeContext) -> Self::ComputedValue {
+    fn compute_value_with_context(&self, context: &ComputeContext) -> Self::ComputedValue {
         MarginRight {
-            size: computed_margin_size(&self.lp_or_auto),
+            size: computed_margin_size(&self.lp_or_auto, context),
         }
     }
 }
@@ -101,7 +99,7 @@ impl ValueDefault for specified::MarginRight {
 }
 
 /// Computed value of a `margin-top`.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct MarginTop {
     pub size: LengthPercentageOrAuto,
 }
@@ -117,9 +115,9 @@ impl MarginTop {
 impl ComputeValueWithContext for specified::MarginTop {
     type ComputedValue = MarginTop;
 
-    fn compute_value_with_context(&self, _context: &ComputeContext) -> Self::ComputedValue {
+    fn compute_value_with_context(&self, context: &ComputeContext) -> Self::ComputedValue {
         MarginTop {
-            size: computed_margin_size(&self.lp_or_auto),
+            size: computed_margin_size(&self.lp_or_auto, context),
         }
     }
 }
@@ -132,12 +130,15 @@ impl ValueDefault for specified::MarginTop {
     }
 }
 
-fn computed_margin_size(lp_auto: &specified::LengthPercentageOrAuto) -> LengthPercentageOrAuto {
+fn computed_margin_size(
+    lp_auto: &specified::LengthPercentageOrAuto,
+    context: &ComputeContext,
+) -> LengthPercentageOrAuto {
     match lp_auto {
         specified::LengthPercentageOrAuto::Auto => LengthPercentageOrAuto::Auto,
         specified::LengthPercentageOrAuto::LengthPercentage(lp) => match lp {
             specified::LengthPercentage::Length(no_calc_length) => {
-                no_calc_length.compute_value().into()
+                no_calc_length.compute_value_with_context(context).into()
             }
             specified::LengthPercentage::Percentage(percentage) => {
                 LengthPercentageOrAuto::LengthPercentage(LengthPercentage::Percentage(*percentage))
@@ -3,15 +3,24 @@
 pub mod background;
 pub mod border;
 pub mod color;
+pub mod contain;
+pub mod container;
 pub mod direction;
 pub mod display;
 pub mod font;
 pub mod height;
 pub mod length;
 pub mod margin;
+pub mod object;
+pub mod overflow;
 pub mod padding;
 pub mod percentage;
+pub mod position;
+pub mod scroll_snap;
+pub mod text;
+pub mod ui;
 pub mod width;
+pub mod will_change;
 
 use crate::style::values::computed::height::Height;
 pub use crate::style::values::computed::margin::{
@@ -23,9 +32,12 @@ pub use crate::style::values::computed::padding::{
 use crate::style::values::computed::width::Width;
 
 use crate::dom::tree::NodeRef;
+use crate::style::media::{ColorScheme, Device};
 use crate::style::properties::id::LonghandId;
-use crate::style::properties::PropertyDeclaration;
+use crate::style::properties::{ContextualPropertyDeclaration, PropertyDeclaration};
+use crate::style::rule_tree::RuleNode;
 use crate::style::values::specified;
+use crate::style::values::specified::SystemColor;
 
 use crate::layout::flow::FlowSide;
 pub use crate::style::values::computed::direction::WritingMode;
@@ -33,19 +45,34 @@ use crate::style::values::computed::length::{
     CSSPixelLength, LengthPercentage, LengthPercentageOrAuto,
 };
 use crate::Side;
-pub use background::BackgroundColor;
+pub use background::{BackgroundColor, BackgroundImage};
 pub use border::LineStyle;
 pub use border::{
     border_side_initial_style, BorderBottomColor, BorderBottomWidth, BorderLeftColor,
     BorderLeftWidth, BorderRightColor, BorderRightWidth, BorderTopColor, BorderTopWidth,
 };
-pub use color::Color;
+pub use color::{Color, SupportedColorSchemes};
+pub use contain::{Contain, ContainIntrinsicSize, ContentVisibility};
+pub use container::{ContainerName, ContainerType};
 use cssparser::RGBA;
 pub use direction::Direction;
 pub use display::Display;
-pub use font::FontSize;
+pub use font::{
+    FeatureTagValue, FontFamily, FontFeatureSettings, FontSize, FontStyle, FontVariantCaps,
+    FontWeight,
+};
+pub use object::{ObjectFit, ObjectPosition};
+pub use overflow::{Overflow, ScrollBehavior};
 pub use percentage::Percentage;
+pub use position::Position;
+pub use scroll_snap::{
+    ScrollMargin, ScrollPadding, ScrollSnapAlign, ScrollSnapAxis, ScrollSnapStrictness,
+    ScrollSnapType, SnapAlignment,
+};
 use strum::IntoEnumIterator;
+pub use text::{TabSize, WhiteSpace};
+pub use ui::Cursor;
+pub use will_change::{AnimateableFeature, WillChange};
 
 /// A trait to represent the conversion between computed and specified values where a context is
 /// required to properly compute the specified value.
@@ -86,9 +113,10 @@ pub trait ValueDefault {
 /// A finalized set of computed values.
 ///
 /// https://www.w3.org/TR/2018/CR-css-cascade-3-20180828/#computed-value
-#[derive(Debug, Clone, Builder)]
+#[derive(Debug, Clone, Builder, PartialEq)]
 pub struct ComputedValues {
     pub background_color: BackgroundColor,
+    pub background_image: BackgroundImage,
     pub border_bottom_color: BorderBottomColor,
     pub border_left_color: BorderLeftColor,
     pub border_right_color: BorderRightColor,
@@ -102,23 +130,122 @@ pub struct ComputedValues {
     pub border_right_width: BorderRightWidth,
     pub border_top_width: BorderTopWidth,
     pub color: Color,
+    pub color_scheme: SupportedColorSchemes,
+    pub contain: Contain,
+    pub contain_intrinsic_height: ContainIntrinsicSize,
+    pub contain_intrinsic_width: ContainIntrinsicSize,
+    pub container_name: ContainerName,
+    pub container_type: ContainerType,
+    pub content_visibility: ContentVisibility,
+    pub cursor: Cursor,
     pub direction: Direction,
     pub display: Display,
+    pub font_family: FontFamily,
+    pub font_feature_settings: FontFeatureSettings,
     pub font_size: FontSize,
+    pub font_style: FontStyle,
+    pub font_variant_caps: FontVariantCaps,
+    pub font_weight: FontWeight,
     pub height: Height,
     pub margin_bottom: MarginBottom,
     pub margin_left: MarginLeft,
     pub margin_right: MarginRight,
     pub margin_top: MarginTop,
+    pub object_fit: ObjectFit,
+    pub object_position: ObjectPosition,
+    pub overflow_x: Overflow,
+    pub overflow_y: Overflow,
     pub padding_bottom: PaddingBottom,
     pub padding_left: PaddingLeft,
     pub padding_right: PaddingRight,
     pub padding_top: PaddingTop,
+    pub position: Position,
+    pub scroll_behavior: ScrollBehavior,
+    pub scroll_margin_bottom: ScrollMargin,
+    pub scroll_margin_left: ScrollMargin,
+    pub scroll_margin_right: ScrollMargin,
+    pub scroll_margin_top: ScrollMargin,
+    pub scroll_padding_bottom: ScrollPadding,
+    pub scroll_padding_left: ScrollPadding,
+    pub scroll_padding_right: ScrollPadding,
+    pub scroll_padding_top: ScrollPadding,
+    pub scroll_snap_align: ScrollSnapAlign,
+    pub scroll_snap_type: ScrollSnapType,
+    pub tab_size: TabSize,
+    pub white_space: WhiteSpace,
     pub width: Width,
+    pub will_change: WillChange,
     pub writing_mode: WritingMode,
 }
 
 impl ComputedValues {
+    /// Formats the computed value of `longhand` for style dumps.
+    pub fn dump_style_format(&self, longhand: LonghandId) -> String {
+        match longhand {
+            LonghandId::BackgroundColor => format!("{:?}", self.background_color),
+            LonghandId::BackgroundImage => format!("{:?}", self.background_image),
+            LonghandId::BorderBottomColor => format!("{:?}", self.border_bottom_color),
+            LonghandId::BorderBottomStyle => format!("{:?}", self.border_bottom_style),
+            LonghandId::BorderBottomWidth => format!("{:?}", self.border_bottom_width),
+            LonghandId::BorderLeftColor => format!("{:?}", self.border_left_color),
+            LonghandId::BorderLeftStyle => format!("{:?}", self.border_left_style),
+            LonghandId::BorderLeftWidth => format!("{:?}", self.border_left_width),
+            LonghandId::BorderRightColor => format!("{:?}", self.border_right_color),
+            LonghandId::BorderRightStyle => format!("{:?}", self.border_right_style),
+            LonghandId::BorderRightWidth => format!("{:?}", self.border_right_width),
+            LonghandId::BorderTopColor => format!("{:?}", self.border_top_color),
+            LonghandId::BorderTopStyle => format!("{:?}", self.border_top_style),
+            LonghandId::BorderTopWidth => format!("{:?}", self.border_top_width),
+            LonghandId::Color => format!("{:?}", self.color),
+            LonghandId::ColorScheme => format!("{:?}", self.color_scheme),
+            LonghandId::Contain => format!("{:?}", self.contain),
+            LonghandId::ContainIntrinsicHeight => format!("{:?}", self.contain_intrinsic_height),
+            LonghandId::ContainIntrinsicWidth => format!("{:?}", self.contain_intrinsic_width),
+            LonghandId::ContainerName => format!("{:?}", self.container_name),
+            LonghandId::ContainerType => format!("{:?}", self.container_type),
+            LonghandId::ContentVisibility => format!("{:?}", self.content_visibility),
+            LonghandId::Cursor => format!("{:?}", self.cursor),
+            LonghandId::Direction => format!("{:?}", self.direction),
+            LonghandId::Display => format!("{:?}", self.display),
+            LonghandId::FontFamily => format!("{:?}", self.font_family),
+            LonghandId::FontFeatureSettings => format!("{:?}", self.font_feature_settings),
+            LonghandId::FontSize => format!("{:?}", self.font_size),
+            LonghandId::FontStyle => format!("{:?}", self.font_style),
+            LonghandId::FontVariantCaps => format!("{:?}", self.font_variant_caps),
+            LonghandId::FontWeight => format!("{:?}", self.font_weight),
+            LonghandId::Height => format!("{:?}", self.height),
+            LonghandId::MarginBottom => format!("{:?}", self.margin_bottom),
+            LonghandId::MarginLeft => format!("{:?}", self.margin_left),
+            LonghandId::MarginRight => format!("{:?}", self.margin_right),
+            LonghandId::MarginTop => format!("{:?}", self.margin_top),
+            LonghandId::ObjectFit => format!("{:?}", self.object_fit),
+            LonghandId::ObjectPosition => format!("{:?}", self.object_position),
+            LonghandId::OverflowX => format!("{:?}", self.overflow_x),
+            LonghandId::OverflowY => format!("{:?}", self.overflow_y),
+            LonghandId::PaddingBottom => format!("{:?}", self.padding_bottom),
+            LonghandId::PaddingLeft => format!("{:?}", self.padding_left),
+            LonghandId::PaddingRight => format!("{:?}", self.padding_right),
+            LonghandId::PaddingTop => format!("{:?}", self.padding_top),
+            LonghandId::Position => format!("{:?}", self.position),
+            LonghandId::ScrollBehavior => format!("{:?}", self.scroll_behavior),
+            LonghandId::ScrollMarginBottom => format!("{:?}", self.scroll_margin_bottom),
+            LonghandId::ScrollMarginLeft => format!("{:?}", self.scroll_margin_left),
+            LonghandId::ScrollMarginRight => format!("{:?}", self.scroll_margin_right),
+            LonghandId::ScrollMarginTop => format!("{:?}", self.scroll_margin_top),
+            LonghandId::ScrollPaddingBottom => format!("{:?}", self.scroll_padding_bottom),
+            LonghandId::ScrollPaddingLeft => format!("{:?}", self.scroll_padding_left),
+            LonghandId::ScrollPaddingRight => format!("{:?}", self.scroll_padding_right),
+            LonghandId::ScrollPaddingTop => format!("{:?}", self.scroll_padding_top),
+            LonghandId::ScrollSnapAlign => format!("{:?}", self.scroll_snap_align),
+            LonghandId::ScrollSnapType => format!("{:?}", self.scroll_snap_type),
+            LonghandId::TabSize => format!("{:?}", self.tab_size),
+            LonghandId::WhiteSpace => format!("{:?}", self.white_space),
+            LonghandId::Width => format!("{:?}", self.width),
+            LonghandId::WillChange => format!("{:?}", self.will_change),
+            LonghandId::WritingMode => format!("{:?}", self.writing_mode),
+        }
+    }
+
     pub fn border_style(&self, side: Side) -> LineStyle {
         match side {
             Side::Bottom => self.border_bottom_style,
@@ -153,6 +280,19 @@ impl ComputedValues {
         }
     }
 
+    /// The block size a box with size containment has when its own block size is `auto` (see
+    /// `ContainIntrinsicSize`).
+    pub fn contain_intrinsic_block_size(
+        &self,
+        writing_mode: WritingMode,
+    ) -> Option<CSSPixelLength> {
+        if writing_mode.is_horizontal() {
+            self.contain_intrinsic_height.size
+        } else {
+            self.contain_intrinsic_width.size
+        }
+    }
+
     pub fn padding_flow_relative(
         &self,
         side: FlowSide,
@@ -276,8 +416,11 @@ impl Default for ComputedValues {
     fn default() -> Self {
         let initial_color_prop = Color::initial_value();
         let initial_border_style = border_side_initial_style();
+        // Borders with the initial style, `none`, have no width.
+        let initial_border_width = CSSPixelLength::new(0.);
         ComputedValues {
             background_color: BackgroundColor::initial_value(initial_color_prop.rgba()),
+            background_image: BackgroundImage::initial_value(),
             border_bottom_color: BorderBottomColor::initial_value(initial_color_prop.rgba()),
             border_left_color: BorderLeftColor::initial_value(initial_color_prop.rgba()),
             border_right_color: BorderRightColor::initial_value(initial_color_prop.rgba()),
@@ -286,24 +429,64 @@ impl Default for ComputedValues {
             border_left_style: initial_border_style,
             border_right_style: initial_border_style,
             border_top_style: initial_border_style,
-            border_bottom_width: BorderBottomWidth::initial_value(initial_border_style),
-            border_left_width: BorderLeftWidth::initial_value(initial_border_style),
-            border_right_width: BorderRightWidth::initial_value(initial_border_style),
-            border_top_width: BorderTopWidth::initial_value(initial_border_style),
+            border_bottom_width: BorderBottomWidth {
+                size: initial_border_width,
+            },
+            border_left_width: BorderLeftWidth {
+                size: initial_border_width,
+            },
+            border_right_width: BorderRightWidth {
+                size: initial_border_width,
+            },
+            border_top_width: BorderTopWidth {
+                size: initial_border_width,
+            },
             color: initial_color_prop,
+            color_scheme: SupportedColorSchemes::initial_value(),
+            contain: Contain::initial_value(),
+            contain_intrinsic_height: ContainIntrinsicSize::initial_value(),
+            contain_intrinsic_width: ContainIntrinsicSize::initial_value(),
+            container_name: ContainerName::initial_value(),
+            container_type: ContainerType::initial_value(),
+            content_visibility: ContentVisibility::initial_value(),
+            cursor: Cursor::initial_value(),
             direction: Direction::initial_value(),
             display: Display::initial_value(),
+            font_family: FontFamily::initial_value(),
+            font_feature_settings: FontFeatureSettings::initial_value(),
             font_size: FontSize::initial_value(),
+            font_style: FontStyle::initial_value(),
+            font_variant_caps: FontVariantCaps::initial_value(),
+            font_weight: FontWeight::initial_value(),
             height: Height::initial_value(),
             margin_bottom: MarginBottom::initial_value(),
             margin_left: MarginLeft::initial_value(),
             margin_right: MarginRight::initial_value(),
             margin_top: MarginTop::initial_value(),
+            object_fit: ObjectFit::initial_value(),
+            object_position: ObjectPosition::initial_value(),
+            overflow_x: Overflow::initial_value(),
+            overflow_y: Overflow::initial_value(),
             padding_bottom: PaddingBottom::initial_value(),
             padding_left: PaddingLeft::initial_value(),
             padding_right: PaddingRight::initial_value(),
             padding_top: PaddingTop::initial_value(),
+            position: Position::initial_value(),
+            scroll_behavior: ScrollBehavior::initial_value(),
+            scroll_margin_bottom: ScrollMargin::initial_value(),
+            scroll_margin_left: ScrollMargin::initial_value(),
+            scroll_margin_right: ScrollMargin::initial_value(),
+            scroll_margin_top: ScrollMargin::initial_value(),
+            scroll_padding_bottom: ScrollPadding::initial_value(),
+            scroll_padding_left: ScrollPadding::initial_value(),
+            scroll_padding_right: ScrollPadding::initial_value(),
+            scroll_padding_top: ScrollPadding::initial_value(),
+            scroll_snap_align: ScrollSnapAlign::initial_value(),
+            scroll_snap_type: ScrollSnapType::initial_value(),
+            tab_size: TabSize::initial_value(),
+            white_space: WhiteSpace::initial_value(),
             width: Width::initial_value(),
+            will_change: WillChange::initial_value(),
             writing_mode: WritingMode::initial_value(),
         }
     }
@@ -312,7 +495,10 @@ impl Default for ComputedValues {
 /// A `ComputeContext` is all the data a specified value could ever need to compute
 /// itself and be transformed to a computed value.
 pub struct ComputeContext<'a> {
-    // TODO: Viewport dimensions will be needed
+    /// The device the document is rendered on, whose viewport size viewport-percentage lengths
+    /// (e.g. `vw`) are relative to.
+    pub device: &'a Device,
+
     /// The computed values of the parent for cases where inheritance is necessary.  If the current
     /// node has no parent (it is the root node), this is `ComputedValues::default()`.
     pub parent_computed_values: &'a ComputedValues,
@@ -323,6 +509,12 @@ pub struct ComputeContext<'a> {
     /// `None` if `color` has not been computed yet.
     pub computed_color: Option<Color>,
 
+    /// The computed value of the `color-scheme` property for the node being computed, which
+    /// determines the color scheme system colors are resolved in.
+    ///
+    /// `None` if `color-scheme` has not been computed yet.
+    pub computed_color_scheme: Option<SupportedColorSchemes>,
+
     /// The computed value of the `border-<side>-style` properties for the node being computed.
     /// The computed values of `border-<side>-width` properties depend on the associated border
     /// style — namely, if the computed style is "none" or "hidden", then the border width is zero.
@@ -337,6 +529,13 @@ impl ComputeContext<'_> {
             .expect("color property not yet computed and applied to compute context")
     }
 
+    /// The color scheme used for the node being computed.
+    pub fn used_color_scheme(&self) -> ColorScheme {
+        self.computed_color_scheme
+            .expect("color-scheme property not yet computed and applied to compute context")
+            .used(self.device)
+    }
+
     pub fn border_bottom_style(&self) -> LineStyle {
         self.border_styles().bottom
     }
@@ -365,7 +564,7 @@ pub struct BorderSideStyleContext {
     pub top: LineStyle,
 }
 
-pub fn compute_values(node: NodeRef) {
+pub fn compute_values(node: NodeRef, device: &Device) {
     let mut cv_builder = ComputedValuesBuilder::default();
     let parent = node.parent();
     // If this is the root node (aka there is no parent to inherit properties from), just default all properties to
@@ -375,14 +574,26 @@ pub fn compute_values(node: NodeRef) {
         p.computed_values().clone()
     });
     let mut context = ComputeContext {
+        device,
         parent_computed_values: &parent_computed_values,
         computed_color: None,
+        computed_color_scheme: None,
         computed_border_styles: None,
     };
-    compute_early_properties(node.clone(), &mut context);
+    let rule_node = node.rule_node();
+    let rule_node = rule_node.as_deref();
+    compute_early_properties(rule_node, &mut context);
+    // The canvas is painted in the colors of the root element's color scheme.
+    if let (Some(_), Some(parent)) = (node.as_element(), node.parent()) {
+        if let Some(document) = parent.as_document() {
+            document
+                .canvas_color
+                .set(SystemColor::Canvas.compute_value_with_context(&context));
+        }
+    }
 
     LonghandId::iter().for_each(|longhand: LonghandId| {
-        match node.contextual_decls().get_by_longhand(longhand) {
+        match cascaded_decl(rule_node, longhand) {
             Some(contextual_decl) => {
                 match &contextual_decl.inner_decl {
                     PropertyDeclaration::BackgroundColor(background_color) => {
@@ -390,6 +601,12 @@ pub fn compute_values(node: NodeRef) {
                             background_color.compute_value_with_context(&context),
                         );
                     }
+                    PropertyDeclaration::BackgroundImage(background_image) => {
+                        // TODO: Per spec, the computed value of a `url()` is an absolute URL.  For
+                        // now we keep the URL as written and resolve it against the document when
+                        // the image is loaded.
+                        cv_builder.background_image(background_image.clone());
+                    }
                     PropertyDeclaration::BorderBottomColor(border_bottom_color) => {
                         cv_builder.border_bottom_color(
                             border_bottom_color.compute_value_with_context(&context),
@@ -445,6 +662,34 @@ pub fn compute_values(node: NodeRef) {
                     PropertyDeclaration::Color(_) => {
                         cv_builder.color(context.color());
                     }
+                    PropertyDeclaration::ColorScheme(color_scheme) => {
+                        cv_builder.color_scheme(*color_scheme);
+                    }
+                    PropertyDeclaration::Contain(contain) => {
+                        cv_builder.contain(*contain);
+                    }
+                    PropertyDeclaration::ContainIntrinsicHeight(contain_intrinsic_height) => {
+                        cv_builder.contain_intrinsic_height(
+                            contain_intrinsic_height.compute_value_with_context(&context),
+                        );
+                    }
+                    PropertyDeclaration::ContainIntrinsicWidth(contain_intrinsic_width) => {
+                        cv_builder.contain_intrinsic_width(
+                            contain_intrinsic_width.compute_value_with_context(&context),
+                        );
+                    }
+                    PropertyDeclaration::ContainerName(container_name) => {
+                        cv_builder.container_name(container_name.clone());
+                    }
+                    PropertyDeclaration::ContainerType(container_type) => {
+                        cv_builder.container_type(*container_type);
+                    }
+                    PropertyDeclaration::ContentVisibility(content_visibility) => {
+                        cv_builder.content_visibility(*content_visibility);
+                    }
+                    PropertyDeclaration::Cursor(cursor) => {
+                        cv_builder.cursor(*cursor);
+                    }
                     PropertyDeclaration::Direction(direction) => {
                         cv_builder.direction(*direction);
                     }
@@ -457,9 +702,24 @@ pub fn compute_values(node: NodeRef) {
                     PropertyDeclaration::Height(height) => {
                         cv_builder.height(height.compute_value_with_context(&context));
                     }
+                    PropertyDeclaration::FontFamily(font_family) => {
+                        cv_builder.font_family(font_family.clone());
+                    }
+                    PropertyDeclaration::FontFeatureSettings(font_feature_settings) => {
+                        cv_builder.font_feature_settings(font_feature_settings.clone());
+                    }
                     PropertyDeclaration::FontSize(font_size) => {
                         cv_builder.font_size(font_size.compute_value_with_context(&context));
                     }
+                    PropertyDeclaration::FontStyle(font_style) => {
+                        cv_builder.font_style(*font_style);
+                    }
+                    PropertyDeclaration::FontVariantCaps(font_variant_caps) => {
+                        cv_builder.font_variant_caps(*font_variant_caps);
+                    }
+                    PropertyDeclaration::FontWeight(font_weight) => {
+                        cv_builder.font_weight(font_weight.compute_value_with_context(&context));
+                    }
                     PropertyDeclaration::MarginBottom(margin_bottom) => {
                         cv_builder
                             .margin_bottom(margin_bottom.compute_value_with_context(&context));
@@ -473,6 +733,18 @@ pub fn compute_values(node: NodeRef) {
                     PropertyDeclaration::MarginTop(margin_top) => {
                         cv_builder.margin_top(margin_top.compute_value_with_context(&context));
                     }
+                    PropertyDeclaration::ObjectFit(object_fit) => {
+                        cv_builder.object_fit(*object_fit);
+                    }
+                    PropertyDeclaration::ObjectPosition(object_position) => {
+                        cv_builder.object_position(*object_position);
+                    }
+                    PropertyDeclaration::OverflowX(overflow_x) => {
+                        cv_builder.overflow_x(*overflow_x);
+                    }
+                    PropertyDeclaration::OverflowY(overflow_y) => {
+                        cv_builder.overflow_y(*overflow_y);
+                    }
                     PropertyDeclaration::PaddingBottom(padding_bottom) => {
                         cv_builder
                             .padding_bottom(padding_bottom.compute_value_with_context(&context));
@@ -487,9 +759,70 @@ pub fn compute_values(node: NodeRef) {
                     PropertyDeclaration::PaddingTop(padding_top) => {
                         cv_builder.padding_top(padding_top.compute_value_with_context(&context));
                     }
+                    PropertyDeclaration::Position(position) => {
+                        cv_builder.position(*position);
+                    }
+                    PropertyDeclaration::ScrollBehavior(scroll_behavior) => {
+                        cv_builder.scroll_behavior(*scroll_behavior);
+                    }
+                    PropertyDeclaration::ScrollMarginBottom(scroll_margin_bottom) => {
+                        cv_builder.scroll_margin_bottom(
+                            scroll_margin_bottom.compute_value_with_context(&context),
+                        );
+                    }
+                    PropertyDeclaration::ScrollMarginLeft(scroll_margin_left) => {
+                        cv_builder.scroll_margin_left(
+                            scroll_margin_left.compute_value_with_context(&context),
+                        );
+                    }
+                    PropertyDeclaration::ScrollMarginRight(scroll_margin_right) => {
+                        cv_builder.scroll_margin_right(
+                            scroll_margin_right.compute_value_with_context(&context),
+                        );
+                    }
+                    PropertyDeclaration::ScrollMarginTop(scroll_margin_top) => {
+                        cv_builder.scroll_margin_top(
+                            scroll_margin_top.compute_value_with_context(&context),
+                        );
+                    }
+                    PropertyDeclaration::ScrollPaddingBottom(scroll_padding_bottom) => {
+                        cv_builder.scroll_padding_bottom(
+                            scroll_padding_bottom.compute_value_with_context(&context),
+                        );
+                    }
+                    PropertyDeclaration::ScrollPaddingLeft(scroll_padding_left) => {
+                        cv_builder.scroll_padding_left(
+                            scroll_padding_left.compute_value_with_context(&context),
+                        );
+                    }
+                    PropertyDeclaration::ScrollPaddingRight(scroll_padding_right) => {
+                        cv_builder.scroll_padding_right(
+                            scroll_padding_right.compute_value_with_context(&context),
+                        );
+                    }
+                    PropertyDeclaration::ScrollPaddingTop(scroll_padding_top) => {
+                        cv_builder.scroll_padding_top(
+                            scroll_padding_top.compute_value_with_context(&context),
+                        );
+                    }
+                    PropertyDeclaration::ScrollSnapAlign(scroll_snap_align) => {
+                        cv_builder.scroll_snap_align(*scroll_snap_align);
+                    }
+                    PropertyDeclaration::ScrollSnapType(scroll_snap_type) => {
+                        cv_builder.scroll_snap_type(*scroll_snap_type);
+                    }
+                    PropertyDeclaration::TabSize(tab_size) => {
+                        cv_builder.tab_size(tab_size.compute_value_with_context(&context));
+                    }
+                    PropertyDeclaration::WhiteSpace(white_space) => {
+                        cv_builder.white_space(*white_space);
+                    }
                     PropertyDeclaration::Width(width) => {
                         cv_builder.width(width.compute_value_with_context(&context));
                     }
+                    PropertyDeclaration::WillChange(will_change) => {
+                        cv_builder.will_change(will_change.clone());
+                    }
                     PropertyDeclaration::WritingMode(writing_mode) => {
                         cv_builder.writing_mode(*writing_mode);
                     }
@@ -500,16 +833,41 @@ pub fn compute_values(node: NodeRef) {
             }
         };
     });
-    *node.computed_values_mut() = cv_builder
+    let mut computed_values = cv_builder
         .build()
         .expect("couldn't build computed values - maybe a field wasn't given to the builder?");
+    // Absolutely positioned boxes are always block-level.
+    // https://www.w3.org/TR/CSS2/visuren.html#dis-pos-flo
+    if computed_values.position.is_absolutely_positioned() {
+        computed_values.display = computed_values.display.blockified();
+    }
+    *node.computed_values_mut() = computed_values;
+}
+
+/// The declaration of `longhand` that wins the cascade among those in `rule_node` and its
+/// ancestors, if any.
+fn cascaded_decl(
+    rule_node: Option<&RuleNode>,
+    longhand: LonghandId,
+) -> Option<&ContextualPropertyDeclaration> {
+    rule_node.and_then(|rule_node| rule_node.get_by_longhand(longhand))
 }
 
 /// Computes "early phase" properties and adds them to the compute context.  Early phase properties
 /// are those that are depended upon by other properties to compute properly (hence their addition
 /// to the compute context).
-fn compute_early_properties(node: NodeRef, context: &mut ComputeContext) {
-    if let Some(contextual_decl) = node.contextual_decls().get_by_longhand(LonghandId::Color) {
+fn compute_early_properties(rule_node: Option<&RuleNode>, context: &mut ComputeContext) {
+    // System colors, including those `color` may be, depend on the used color scheme.
+    if let Some(contextual_decl) = cascaded_decl(rule_node, LonghandId::ColorScheme) {
+        context.computed_color_scheme = match &contextual_decl.inner_decl {
+            PropertyDeclaration::ColorScheme(color_scheme) => Some(*color_scheme),
+            _ => panic!("needed color-scheme property declaration"),
+        }
+    } else {
+        context.computed_color_scheme = Some(SupportedColorSchemes::value_default(&context));
+    }
+
+    if let Some(contextual_decl) = cascaded_decl(rule_node, LonghandId::Color) {
         context.computed_color = match &contextual_decl.inner_decl {
             PropertyDeclaration::Color(color) => Some(color.compute_value_with_context(&context)),
             _ => panic!("needed color property declaration"),
@@ -518,25 +876,21 @@ fn compute_early_properties(node: NodeRef, context: &mut ComputeContext) {
         context.computed_color = Some(specified::Color::value_default(&context));
     }
 
-    context.computed_border_styles = Some(compute_border_styles_early(node));
+    context.computed_border_styles = Some(compute_border_styles_early(rule_node));
 }
 
-fn compute_border_styles_early(node: NodeRef) -> BorderSideStyleContext {
-    let bottom = if let Some(contextual_decl) = node
-        .contextual_decls()
-        .get_by_longhand(LonghandId::BorderBottomStyle)
-    {
-        match &contextual_decl.inner_decl {
-            PropertyDeclaration::BorderBottomStyle(line_style) => *line_style,
-            _ => panic!("needed border-bottom-style property declaration"),
-        }
-    } else {
-        border_side_initial_style()
-    };
+fn compute_border_styles_early(rule_node: Option<&RuleNode>) -> BorderSideStyleContext {
+    let bottom =
+        if let Some(contextual_decl) = cascaded_decl(rule_node, LonghandId::BorderBottomStyle) {
+            match &contextual_decl.inner_decl {
+                PropertyDeclaration::BorderBottomStyle(line_style) => *line_style,
+                _ => panic!("needed border-bottom-style property declaration"),
+            }
+        } else {
+            border_side_initial_style()
+        };
 
-    let left = if let Some(contextual_decl) = node
-        .contextual_decls()
-        .get_by_longhand(LonghandId::BorderLeftStyle)
+    let left = if let Some(contextual_decl) = cascaded_decl(rule_node, LonghandId::BorderLeftStyle)
     {
         match &contextual_decl.inner_decl {
             PropertyDeclaration::BorderLeftStyle(line_style) => *line_style,
@@ -546,22 +900,17 @@ fn compute_border_styles_early(node: NodeRef) -> BorderSideStyleContext {
         border_side_initial_style()
     };
 
-    let right = if let Some(contextual_decl) = node
-        .contextual_decls()
-        .get_by_longhand(LonghandId::BorderRightStyle)
-    {
-        match &contextual_decl.inner_decl {
-            PropertyDeclaration::BorderRightStyle(line_style) => *line_style,
-            _ => panic!("needed border-right-style property declaration"),
-        }
-    } else {
-        border_side_initial_style()
-    };
+    let right =
+        if let Some(contextual_decl) = cascaded_decl(rule_node, LonghandId::BorderRightStyle) {
+            match &contextual_decl.inner_decl {
+                PropertyDeclaration::BorderRightStyle(line_style) => *line_style,
+                _ => panic!("needed border-right-style property declaration"),
+            }
+        } else {
+            border_side_initial_style()
+        };
 
-    let top = if let Some(contextual_decl) = node
-        .contextual_decls()
-        .get_by_longhand(LonghandId::BorderTopStyle)
-    {
+    let top = if let Some(contextual_decl) = cascaded_decl(rule_node, LonghandId::BorderTopStyle) {
         match &contextual_decl.inner_decl {
             PropertyDeclaration::BorderTopStyle(line_style) => *line_style,
             _ => panic!("needed border-top-style property declaration"),
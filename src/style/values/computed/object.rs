@@ -0,0 +1,218 @@
+use crate::style::values::computed::length::LengthPercentage;
+use crate::style::values::computed::{ComputeContext, ComputeValue, Percentage, ValueDefault};
+use crate::style::values::specified;
+use crate::style::StyleParseErrorKind;
+use cssparser::{ParseError, Parser};
+
+/// Computed values for the `object-fit` property, which determines how the content of a replaced
+/// element is sized to its content box.
+///
+/// https://drafts.csswg.org/css-images-3/#the-object-fit
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ObjectFit {
+    Fill,
+    Contain,
+    Cover,
+    None,
+    ScaleDown,
+}
+
+impl ObjectFit {
+    pub fn initial_value() -> ObjectFit {
+        ObjectFit::Fill
+    }
+
+    pub fn parse<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        try_match_ident_ignore_ascii_case! { input,
+            "fill" => Ok(ObjectFit::Fill),
+            "contain" => Ok(ObjectFit::Contain),
+            "cover" => Ok(ObjectFit::Cover),
+            "none" => Ok(ObjectFit::None),
+            "scale-down" => Ok(ObjectFit::ScaleDown),
+        }
+    }
+}
+
+impl ValueDefault for ObjectFit {
+    type ComputedValue = ObjectFit;
+
+    fn value_default(_context: &ComputeContext) -> Self::ComputedValue {
+        ObjectFit::initial_value()
+    }
+}
+
+/// Computed values for the `object-position` property, which determines where the content of a
+/// replaced element is placed within its content box.  Each offset is a length, or a percentage of
+/// the difference between the size of the content box and the size of the content.
+///
+/// TODO: The four-value `<position>` syntax (e.g. `right 10px bottom 20%`) isn't supported, as it
+/// needs `calc()` to represent its computed value.
+///
+/// https://drafts.csswg.org/css-images-3/#the-object-position
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ObjectPosition {
+    pub horizontal: LengthPercentage,
+    pub vertical: LengthPercentage,
+}
+
+/// One component of a `<position>` value.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PositionComponent {
+    Left,
+    Right,
+    Top,
+    Bottom,
+    Center,
+    LengthPercentage(LengthPercentage),
+}
+
+impl PositionComponent {
+    fn parse<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        if let Ok(lp) = input.try_parse(specified::LengthPercentage::parse) {
+            return Ok(PositionComponent::LengthPercentage(match lp {
+                specified::LengthPercentage::Length(specified::NoCalcLength::Absolute(abs_len)) => {
+                    LengthPercentage::Length(abs_len.compute_value())
+                }
+                // TODO: `object-position` is computed as it's parsed, before the size of the
+                // viewport is known, so viewport-percentage lengths aren't supported yet.
+                specified::LengthPercentage::Length(
+                    specified::NoCalcLength::ViewportPercentage(_),
+                ) => {
+                    return Err(input.new_custom_error(StyleParseErrorKind::UnspecifiedError));
+                }
+                specified::LengthPercentage::Percentage(percentage) => {
+                    LengthPercentage::Percentage(percentage)
+                }
+            }));
+        }
+        try_match_ident_ignore_ascii_case! { input,
+            "left" => Ok(PositionComponent::Left),
+            "right" => Ok(PositionComponent::Right),
+            "top" => Ok(PositionComponent::Top),
+            "bottom" => Ok(PositionComponent::Bottom),
+            "center" => Ok(PositionComponent::Center),
+        }
+    }
+
+    fn is_horizontal_keyword(self) -> bool {
+        matches!(self, PositionComponent::Left | PositionComponent::Right)
+    }
+
+    fn is_vertical_keyword(self) -> bool {
+        matches!(self, PositionComponent::Top | PositionComponent::Bottom)
+    }
+
+    fn to_length_percentage(self) -> LengthPercentage {
+        match self {
+            PositionComponent::Left | PositionComponent::Top => {
+                LengthPercentage::Percentage(Percentage(0.))
+            }
+            PositionComponent::Center => LengthPercentage::Percentage(Percentage(0.5)),
+            PositionComponent::Right | PositionComponent::Bottom => {
+                LengthPercentage::Percentage(Percentage::hundred())
+            }
+            PositionComponent::LengthPercentage(lp) => lp,
+        }
+    }
+}
+
+impl ObjectPosition {
+    pub fn initial_value() -> ObjectPosition {
+        ObjectPosition {
+            horizontal: LengthPercentage::Percentage(Percentage(0.5)),
+            vertical: LengthPercentage::Percentage(Percentage(0.5)),
+        }
+    }
+
+    pub fn parse<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        let location = input.current_source_location();
+        let first = PositionComponent::parse(input)?;
+        let second = input
+            .try_parse(PositionComponent::parse)
+            .unwrap_or(PositionComponent::Center);
+        // Keywords may be given in either order (e.g. `top left`), but lengths and percentages are
+        // always horizontal first.
+        let (horizontal, vertical) =
+            if first.is_vertical_keyword() || second.is_horizontal_keyword() {
+                (second, first)
+            } else {
+                (first, second)
+            };
+        if horizontal.is_vertical_keyword() || vertical.is_horizontal_keyword() {
+            return Err(location.new_custom_error(StyleParseErrorKind::UnspecifiedError));
+        }
+        Ok(ObjectPosition {
+            horizontal: horizontal.to_length_percentage(),
+            vertical: vertical.to_length_percentage(),
+        })
+    }
+}
+
+impl ValueDefault for ObjectPosition {
+    type ComputedValue = ObjectPosition;
+
+    fn value_default(_context: &ComputeContext) -> Self::ComputedValue {
+        ObjectPosition::initial_value()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::style::values::computed::length::CSSPixelLength;
+    use cssparser::ParserInput;
+
+    fn parse_object_position(css: &str) -> Option<ObjectPosition> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        parser
+            .parse_entirely(|input| ObjectPosition::parse(input))
+            .ok()
+    }
+
+    fn percentage(value: f32) -> LengthPercentage {
+        LengthPercentage::Percentage(Percentage(value))
+    }
+
+    #[test]
+    fn parses_object_position_keywords_in_either_order() {
+        let expected = ObjectPosition {
+            horizontal: percentage(0.),
+            vertical: percentage(1.),
+        };
+        assert_eq!(parse_object_position("left bottom"), Some(expected));
+        assert_eq!(parse_object_position("bottom left"), Some(expected));
+        assert_eq!(
+            parse_object_position("top"),
+            Some(ObjectPosition {
+                horizontal: percentage(0.5),
+                vertical: percentage(0.),
+            })
+        );
+        assert_eq!(parse_object_position("left right"), None);
+    }
+
+    #[test]
+    fn parses_object_position_lengths_and_percentages() {
+        assert_eq!(
+            parse_object_position("10px 25%"),
+            Some(ObjectPosition {
+                horizontal: LengthPercentage::Length(CSSPixelLength::new(10.)),
+                vertical: percentage(0.25),
+            })
+        );
+        assert_eq!(
+            parse_object_position("right 5px"),
+            Some(ObjectPosition {
+                horizontal: percentage(1.),
+                vertical: LengthPercentage::Length(CSSPixelLength::new(5.)),
+            })
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use crate::style::values::computed::{ComputeContext, ValueDefault};
+use crate::style::StyleParseErrorKind;
+use cssparser::{ParseError, Parser};
+
+/// Computed values for the `overflow-x` and `overflow-y` properties, which determine what's done
+/// with the content of a box that overflows its padding box.
+///
+/// TODO: Only the viewport, which the `overflow` of the root element (or `<body>`) propagates
+/// to, honors `overflow` so far.  Other boxes neither clip nor scroll what overflows them.
+///
+/// https://drafts.csswg.org/css-overflow-3/#overflow-properties
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Overflow {
+    Visible,
+    Hidden,
+    Clip,
+    Scroll,
+    Auto,
+}
+
+impl Overflow {
+    pub fn initial_value() -> Overflow {
+        Overflow::Visible
+    }
+
+    pub fn parse<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        try_match_ident_ignore_ascii_case! { input,
+            "visible" => Ok(Overflow::Visible),
+            "hidden" => Ok(Overflow::Hidden),
+            "clip" => Ok(Overflow::Clip),
+            "scroll" => Ok(Overflow::Scroll),
+            "auto" => Ok(Overflow::Auto),
+        }
+    }
+
+    /// Parses the `overflow` shorthand, which sets `overflow-x` to its first value, and
+    /// `overflow-y` to its second (or its first, if there's only one).
+    ///
+    /// https://drafts.csswg.org/css-overflow-3/#propdef-overflow
+    pub fn parse_shorthand<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<(Self, Self), ParseError<'i, StyleParseErrorKind<'i>>> {
+        let overflow_x = Overflow::parse(input)?;
+        let overflow_y = input.try_parse(Overflow::parse).unwrap_or(overflow_x);
+        Ok((overflow_x, overflow_y))
+    }
+
+    /// The overflow of the viewport when this is propagated to it.  The viewport can't show what
+    /// overflows it, so `visible` is used as `auto`, and `clip` as `hidden`.
+    ///
+    /// https://drafts.csswg.org/css-overflow-3/#overflow-propagation
+    pub fn used_for_viewport(self) -> Overflow {
+        match self {
+            Overflow::Visible => Overflow::Auto,
+            Overflow::Clip => Overflow::Hidden,
+            overflow => overflow,
+        }
+    }
+
+    /// Whether the user can scroll a scroll container with this overflow to see what overflows
+    /// it.  Boxes with `hidden` overflow can only be scrolled programmatically.
+    pub fn is_user_scrollable(self) -> bool {
+        matches!(self, Overflow::Scroll | Overflow::Auto)
+    }
+}
+
+impl ValueDefault for Overflow {
+    type ComputedValue = Overflow;
+
+    fn value_default(_context: &ComputeContext) -> Self::ComputedValue {
+        Overflow::initial_value()
+    }
+}
+
+/// Computed values for the `scroll-behavior` property, which determines whether a scroll container
+/// scrolls smoothly when it's scrolled programmatically or by navigation (e.g. to a fragment),
+/// rather than by the user.
+///
+/// https://drafts.csswg.org/cssom-view/#propdef-scroll-behavior
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ScrollBehavior {
+    /// Scroll instantly.
+    Auto,
+    Smooth,
+}
+
+impl ScrollBehavior {
+    pub fn initial_value() -> ScrollBehavior {
+        ScrollBehavior::Auto
+    }
+
+    pub fn parse<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        try_match_ident_ignore_ascii_case! { input,
+            "auto" => Ok(ScrollBehavior::Auto),
+            "smooth" => Ok(ScrollBehavior::Smooth),
+        }
+    }
+}
+
+impl ValueDefault for ScrollBehavior {
+    type ComputedValue = ScrollBehavior;
+
+    fn value_default(_context: &ComputeContext) -> Self::ComputedValue {
+        ScrollBehavior::initial_value()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cssparser::ParserInput;
+
+    fn parse_overflow(css: &str) -> Option<(Overflow, Overflow)> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        parser.parse_entirely(Overflow::parse_shorthand).ok()
+    }
+
+    #[test]
+    fn parses_the_overflow_shorthand() {
+        assert_eq!(
+            parse_overflow("hidden"),
+            Some((Overflow::Hidden, Overflow::Hidden))
+        );
+        assert_eq!(
+            parse_overflow("CLIP auto"),
+            Some((Overflow::Clip, Overflow::Auto))
+        );
+        assert_eq!(parse_overflow("scroll auto hidden"), None);
+        assert_eq!(parse_overflow("overlay"), None);
+    }
+
+    #[test]
+    fn parses_scroll_behavior() {
+        let parse_scroll_behavior = |css| {
+            let mut input = ParserInput::new(css);
+            let mut parser = Parser::new(&mut input);
+            parser.parse_entirely(ScrollBehavior::parse).ok()
+        };
+        assert_eq!(
+            parse_scroll_behavior("Smooth"),
+            Some(ScrollBehavior::Smooth)
+        );
+        assert_eq!(parse_scroll_behavior("auto"), Some(ScrollBehavior::Auto));
+        assert_eq!(parse_scroll_behavior("instant"), None);
+    }
+}
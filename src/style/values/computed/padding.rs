@@ -1,11 +1,9 @@
 use crate::style::values::computed::length::LengthPercentage;
-use crate::style::values::computed::{
-    ComputeContext, ComputeValue, ComputeValueWithContext, ValueDefault,
-};
+use crate::style::values::computed::{ComputeContext, ComputeValueWithContext, ValueDefault};
 use crate::style::values::specified;
 
 /// Computed value of a `padding-bottom`.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct PaddingBottom {
     pub size: LengthPercentage,
 }
@@ -21,9 +19,9 @@ impl PaddingBottom {
 impl ComputeValueWithContext for specified::PaddingBottom {
     type ComputedValue = PaddingBottom;
 
-    fn compute_value_with_context(&self, _context: &ComputeContext) -> Self::ComputedValue {
+    fn compute_value_with_context(&self, context: &ComputeContext) -> Self::ComputedValue {
         PaddingBottom {
-            size: computed_padding_size(&self.length_percentage),
+            size: computed_padding_size(&self.length_percentage, context),
         }
     }
 }
@@ -37,7 +35,7 @@ impl ValueDefault for specified::PaddingBottom {
 }
 
 /// Computed value of a `padding-left`.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct PaddingLeft {
     pub size: LengthPercentage,
 }
@@ -53,9 +51,9 @@ impl PaddingLeft {
 impl ComputeValueWithContext for specified::PaddingLeft {
     type ComputedValue = PaddingLeft;
 
-    fn compute_value_with_context(&self, _context: &ComputeContext) -> Self::ComputedValue {
+    fn compute_value_with_context(&self, context: &ComputeContext) -> Self::ComputedValue {
         PaddingLeft {
-            size: computed_padding_size(&self.length_percentage),
+            size: computed_padding_size(&self.length_percentage, context),
         }
     }
 }
@@ -69,7 +67,7 @@ impl ValueDefault for specified::PaddingLeft {
 }
 
 /// Computed value of a `padding-right`.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct PaddingRight {
     pub size: LengthPercentage,
 }
@@ -85,9 +83,9 @@ impl PaddingRight {
 impl ComputeValueWithContext for specified::PaddingRight {
     type ComputedValue = PaddingRight;
 
-    fn compute_value_with_context(&self, _context: &ComputeContext) -> Self::ComputedValue {
+    fn compute_value_with_context(&self, context: &ComputeContext) -> Self::ComputedValue {
         PaddingRight {
-            size: computed_padding_size(&self.length_percentage),
+            size: computed_padding_size(&self.length_percentage, context),
         }
     }
 }
@@ -101,7 +99,7 @@ impl ValueDefault for specified::PaddingRight {
 }
 
 /// Computed value of a `padding-top`.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct PaddingTop {
     pub size: LengthPercentage,
 }
@@ -117,9 +115,9 @@ impl PaddingTop {
 impl ComputeValueWithContext for specified::PaddingTop {
     type ComputedValue = PaddingTop;
 
-    fn compute_value_with_context(&self, _context: &ComputeContext) -> Self::ComputedValue {
+    fn compute_value_with_context(&self, context: &ComputeContext) -> Self::ComputedValue {
         PaddingTop {
-            size: computed_padding_size(&self.length_percentage),
+            size: computed_padding_size(&self.length_percentage, context),
         }
     }
 }
@@ -132,10 +130,13 @@ impl ValueDefault for specified::PaddingTop {
     }
 }
 
-fn computed_padding_size(lp: &specified::LengthPercentage) -> LengthPercentage {
+fn computed_padding_size(
+    lp: &specified::LengthPercentage,
+    context: &ComputeContext,
+) -> LengthPercentage {
     match lp {
         specified::LengthPercentage::Length(no_calc_length) => {
-            no_calc_length.compute_value().into()
+            no_calc_length.compute_value_with_context(context).into()
         }
         specified::LengthPercentage::Percentage(percentage) => {
             LengthPercentage::Percentage(*percentage)
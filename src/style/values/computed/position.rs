@@ -0,0 +1,72 @@
+use crate::style::values::computed::{ComputeContext, ValueDefault};
+use crate::style::StyleParseErrorKind;
+use cssparser::{ParseError, Parser};
+
+/// The positioning scheme used to lay out a box.
+///
+/// https://drafts.csswg.org/css-position/#position-property
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Position {
+    Static,
+    Relative,
+    Absolute,
+    Fixed,
+    Sticky,
+}
+
+impl Position {
+    pub fn initial_value() -> Position {
+        Position::Static
+    }
+
+    pub fn parse<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        try_match_ident_ignore_ascii_case! { input,
+            "static" => Ok(Position::Static),
+            "relative" => Ok(Position::Relative),
+            "absolute" => Ok(Position::Absolute),
+            "fixed" => Ok(Position::Fixed),
+            "sticky" => Ok(Position::Sticky),
+        }
+    }
+
+    /// Whether boxes with this position are absolutely positioned, taking them out of flow.
+    ///
+    /// https://drafts.csswg.org/css-position/#absolute-positioning-scheme
+    pub fn is_absolutely_positioned(self) -> bool {
+        match self {
+            Position::Absolute | Position::Fixed => true,
+            Position::Static | Position::Relative | Position::Sticky => false,
+        }
+    }
+}
+
+impl ValueDefault for Position {
+    type ComputedValue = Position;
+
+    fn value_default(_context: &ComputeContext) -> Self::ComputedValue {
+        Position::initial_value()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cssparser::ParserInput;
+
+    #[test]
+    fn parses_position() {
+        let parse_position = |css| {
+            let mut input = ParserInput::new(css);
+            let mut parser = Parser::new(&mut input);
+            parser.parse_entirely(Position::parse).ok()
+        };
+        assert_eq!(parse_position("Absolute"), Some(Position::Absolute));
+        assert_eq!(parse_position("static"), Some(Position::Static));
+        assert_eq!(parse_position("sticky"), Some(Position::Sticky));
+        assert_eq!(parse_position("absolute fixed"), None);
+        assert!(Position::Fixed.is_absolutely_positioned());
+        assert!(!Position::Relative.is_absolutely_positioned());
+    }
+}
@@ -0,0 +1,273 @@
+use crate::style::values::computed::length::{
+    CSSPixelLength, LengthPercentage, LengthPercentageOrAuto,
+};
+use crate::style::values::computed::{ComputeContext, ComputeValueWithContext, ValueDefault};
+use crate::style::values::specified;
+use crate::style::StyleParseErrorKind;
+use cssparser::{ParseError, Parser};
+
+/// Computed values for the `scroll-snap-type` property, which makes a scroll container snap to
+/// the snap areas of its descendants in the given axes once it's scrolled.
+///
+/// TODO: Only the viewport, which the root element's `scroll-snap-type` applies to, snaps so far,
+/// since it's the only scroll container Kosmonaut supports.
+///
+/// https://drafts.csswg.org/css-scroll-snap-1/#scroll-snap-type
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ScrollSnapType {
+    None,
+    Snap {
+        axis: ScrollSnapAxis,
+        strictness: ScrollSnapStrictness,
+    },
+}
+
+/// The axes a scroll container snaps in.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ScrollSnapAxis {
+    X,
+    Y,
+    Block,
+    Inline,
+    Both,
+}
+
+/// How strictly a scroll container snaps.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ScrollSnapStrictness {
+    /// Always rest on a snap position when there is one.
+    Mandatory,
+    /// Only rest on a snap position when a scroll ends close enough to it.
+    Proximity,
+}
+
+impl ScrollSnapType {
+    pub fn initial_value() -> ScrollSnapType {
+        ScrollSnapType::None
+    }
+
+    pub fn parse<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        if input
+            .try_parse(|input| input.expect_ident_matching("none"))
+            .is_ok()
+        {
+            return Ok(ScrollSnapType::None);
+        }
+        let axis = try_match_ident_ignore_ascii_case! { input,
+            "x" => ScrollSnapAxis::X,
+            "y" => ScrollSnapAxis::Y,
+            "block" => ScrollSnapAxis::Block,
+            "inline" => ScrollSnapAxis::Inline,
+            "both" => ScrollSnapAxis::Both,
+        };
+        let strictness = input
+            .try_parse(|input| {
+                try_match_ident_ignore_ascii_case! { input,
+                    "mandatory" => Ok(ScrollSnapStrictness::Mandatory),
+                    "proximity" => Ok(ScrollSnapStrictness::Proximity),
+                }
+            })
+            .unwrap_or(ScrollSnapStrictness::Proximity);
+        Ok(ScrollSnapType::Snap { axis, strictness })
+    }
+}
+
+impl ValueDefault for ScrollSnapType {
+    type ComputedValue = ScrollSnapType;
+
+    fn value_default(_context: &ComputeContext) -> Self::ComputedValue {
+        ScrollSnapType::initial_value()
+    }
+}
+
+/// Computed values for the `scroll-snap-align` property, which makes a box's snap area (its
+/// border box, outset by its `scroll-margin`) a place a snapping scroll container can rest, by
+/// aligning the area with the container's snapport in each axis.
+///
+/// https://drafts.csswg.org/css-scroll-snap-1/#scroll-snap-align
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ScrollSnapAlign {
+    pub block: SnapAlignment,
+    pub inline: SnapAlignment,
+}
+
+/// How a snap area is aligned with the snapport in one axis.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SnapAlignment {
+    /// The box doesn't snap in this axis.
+    None,
+    Start,
+    End,
+    Center,
+}
+
+impl SnapAlignment {
+    fn parse<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        try_match_ident_ignore_ascii_case! { input,
+            "none" => Ok(SnapAlignment::None),
+            "start" => Ok(SnapAlignment::Start),
+            "end" => Ok(SnapAlignment::End),
+            "center" => Ok(SnapAlignment::Center),
+        }
+    }
+}
+
+impl ScrollSnapAlign {
+    pub fn initial_value() -> ScrollSnapAlign {
+        ScrollSnapAlign {
+            block: SnapAlignment::None,
+            inline: SnapAlignment::None,
+        }
+    }
+
+    /// Parses the alignment in the block axis, followed by that in the inline axis (or the same
+    /// alignment again, if there's only one).
+    pub fn parse<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        let block = SnapAlignment::parse(input)?;
+        let inline = input.try_parse(SnapAlignment::parse).unwrap_or(block);
+        Ok(ScrollSnapAlign { block, inline })
+    }
+
+    /// Whether the box has a snap area at all.
+    pub fn is_none(self) -> bool {
+        self.block == SnapAlignment::None && self.inline == SnapAlignment::None
+    }
+}
+
+impl ValueDefault for ScrollSnapAlign {
+    type ComputedValue = ScrollSnapAlign;
+
+    fn value_default(_context: &ComputeContext) -> Self::ComputedValue {
+        ScrollSnapAlign::initial_value()
+    }
+}
+
+/// Computed value of a `scroll-margin-<side>`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScrollMargin {
+    pub size: CSSPixelLength,
+}
+
+impl ScrollMargin {
+    pub fn initial_value() -> ScrollMargin {
+        ScrollMargin {
+            size: CSSPixelLength::new(0.),
+        }
+    }
+}
+
+impl ComputeValueWithContext for specified::ScrollMargin {
+    type ComputedValue = ScrollMargin;
+
+    fn compute_value_with_context(&self, context: &ComputeContext) -> Self::ComputedValue {
+        ScrollMargin {
+            size: self.length.compute_value_with_context(context),
+        }
+    }
+}
+
+impl ValueDefault for specified::ScrollMargin {
+    type ComputedValue = ScrollMargin;
+
+    fn value_default(_context: &ComputeContext) -> Self::ComputedValue {
+        ScrollMargin::initial_value()
+    }
+}
+
+/// Computed value of a `scroll-padding-<side>`.  Percentages are of the scroll container's size
+/// in the same axis, and `auto` is used as zero.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScrollPadding {
+    pub size: LengthPercentageOrAuto,
+}
+
+impl ScrollPadding {
+    pub fn initial_value() -> ScrollPadding {
+        ScrollPadding {
+            size: LengthPercentageOrAuto::Auto,
+        }
+    }
+}
+
+impl ComputeValueWithContext for specified::ScrollPadding {
+    type ComputedValue = ScrollPadding;
+
+    fn compute_value_with_context(&self, context: &ComputeContext) -> Self::ComputedValue {
+        let size = match self.lp_or_auto {
+            specified::LengthPercentageOrAuto::LengthPercentage(
+                specified::LengthPercentage::Length(length),
+            ) => length.compute_value_with_context(context).into(),
+            specified::LengthPercentageOrAuto::LengthPercentage(
+                specified::LengthPercentage::Percentage(percentage),
+            ) => LengthPercentageOrAuto::LengthPercentage(LengthPercentage::Percentage(percentage)),
+            specified::LengthPercentageOrAuto::Auto => LengthPercentageOrAuto::Auto,
+        };
+        ScrollPadding { size }
+    }
+}
+
+impl ValueDefault for specified::ScrollPadding {
+    type ComputedValue = ScrollPadding;
+
+    fn value_default(_context: &ComputeContext) -> Self::ComputedValue {
+        ScrollPadding::initial_value()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cssparser::ParserInput;
+
+    #[test]
+    fn parses_scroll_snap_type_and_align() {
+        let parse_type = |css| {
+            let mut input = ParserInput::new(css);
+            let mut parser = Parser::new(&mut input);
+            parser.parse_entirely(ScrollSnapType::parse).ok()
+        };
+        assert_eq!(parse_type("none"), Some(ScrollSnapType::None));
+        assert_eq!(
+            parse_type("x mandatory"),
+            Some(ScrollSnapType::Snap {
+                axis: ScrollSnapAxis::X,
+                strictness: ScrollSnapStrictness::Mandatory
+            })
+        );
+        assert_eq!(
+            parse_type("Block"),
+            Some(ScrollSnapType::Snap {
+                axis: ScrollSnapAxis::Block,
+                strictness: ScrollSnapStrictness::Proximity
+            })
+        );
+        assert_eq!(parse_type("mandatory"), None);
+
+        let parse_align = |css| {
+            let mut input = ParserInput::new(css);
+            let mut parser = Parser::new(&mut input);
+            parser.parse_entirely(ScrollSnapAlign::parse).ok()
+        };
+        assert_eq!(
+            parse_align("center"),
+            Some(ScrollSnapAlign {
+                block: SnapAlignment::Center,
+                inline: SnapAlignment::Center
+            })
+        );
+        assert_eq!(
+            parse_align("none start"),
+            Some(ScrollSnapAlign {
+                block: SnapAlignment::None,
+                inline: SnapAlignment::Start
+            })
+        );
+        assert_eq!(parse_align("start end center"), None);
+    }
+}
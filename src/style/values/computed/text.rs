@@ -0,0 +1,100 @@
+use crate::style::values::computed::length::CSSPixelLength;
+use crate::style::values::computed::{ComputeContext, ComputeValueWithContext, ValueDefault};
+use crate::style::values::specified;
+use crate::style::values::CSSFloat;
+use crate::style::StyleParseErrorKind;
+use cssparser::{ParseError, Parser};
+
+/// Computed values for the `tab-size` property.
+///
+/// https://drafts.csswg.org/css-text-3/#tab-size-property
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TabSize {
+    /// Tab stops are this many times the advance of a space apart.
+    Number(CSSFloat),
+    Length(CSSPixelLength),
+}
+
+impl TabSize {
+    pub fn initial_value() -> TabSize {
+        TabSize::Number(8.)
+    }
+}
+
+impl ComputeValueWithContext for specified::TabSize {
+    type ComputedValue = TabSize;
+
+    fn compute_value_with_context(&self, context: &ComputeContext) -> Self::ComputedValue {
+        match self {
+            specified::TabSize::Number(number) => TabSize::Number(*number),
+            specified::TabSize::Length(length) => {
+                TabSize::Length(length.compute_value_with_context(context))
+            }
+        }
+    }
+}
+
+impl ValueDefault for specified::TabSize {
+    type ComputedValue = TabSize;
+
+    fn value_default(context: &ComputeContext) -> Self::ComputedValue {
+        context.parent_computed_values.tab_size
+    }
+}
+
+/// Computed values for the `white-space` property: whether spaces, tabs, and newlines in text are
+/// collapsed or kept as they are, and whether lines wrap.
+///
+/// https://drafts.csswg.org/css-text-3/#white-space-property
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum WhiteSpace {
+    Normal,
+    Pre,
+    Nowrap,
+    PreWrap,
+    PreLine,
+}
+
+impl WhiteSpace {
+    pub fn initial_value() -> WhiteSpace {
+        WhiteSpace::Normal
+    }
+
+    pub fn parse<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        try_match_ident_ignore_ascii_case! { input,
+            "normal" => Ok(WhiteSpace::Normal),
+            "pre" => Ok(WhiteSpace::Pre),
+            "nowrap" => Ok(WhiteSpace::Nowrap),
+            "pre-wrap" => Ok(WhiteSpace::PreWrap),
+            "pre-line" => Ok(WhiteSpace::PreLine),
+        }
+    }
+
+    /// Whether spaces and tabs are kept as they are, rather than collapsed into single spaces.
+    pub fn preserves_spaces(self) -> bool {
+        matches!(self, WhiteSpace::Pre | WhiteSpace::PreWrap)
+    }
+
+    /// Whether newlines are kept as forced line breaks, rather than collapsed like spaces.
+    pub fn preserves_newlines(self) -> bool {
+        matches!(
+            self,
+            WhiteSpace::Pre | WhiteSpace::PreWrap | WhiteSpace::PreLine
+        )
+    }
+
+    /// Whether lines wrap between words when they'd overflow.
+    pub fn wraps(self) -> bool {
+        !matches!(self, WhiteSpace::Pre | WhiteSpace::Nowrap)
+    }
+}
+
+impl ValueDefault for WhiteSpace {
+    type ComputedValue = WhiteSpace;
+
+    fn value_default(context: &ComputeContext) -> Self::ComputedValue {
+        context.parent_computed_values.white_space
+    }
+}
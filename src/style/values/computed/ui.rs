@@ -0,0 +1,157 @@
+use crate::style::values::computed::{ComputeContext, ValueDefault};
+use crate::style::StyleParseErrorKind;
+use cssparser::{ParseError, Parser};
+
+/// Computed values for the `cursor` property, which determines the cursor shown while the mouse is
+/// over an element.
+///
+/// TODO: Custom `url()` cursor images are parsed but not shown; the keyword every `cursor` value
+/// must end with (which they fall back on) is used instead.
+///
+/// https://drafts.csswg.org/css-ui-4/#cursor
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Cursor {
+    /// A cursor based on what's under the mouse: a text cursor over text, and the default cursor
+    /// otherwise.
+    Auto,
+    Default,
+    /// No cursor is shown.
+    None,
+    ContextMenu,
+    Help,
+    Pointer,
+    Progress,
+    Wait,
+    Cell,
+    Crosshair,
+    Text,
+    VerticalText,
+    Alias,
+    Copy,
+    Move,
+    NoDrop,
+    NotAllowed,
+    Grab,
+    Grabbing,
+    EResize,
+    NResize,
+    NeResize,
+    NwResize,
+    SResize,
+    SeResize,
+    SwResize,
+    WResize,
+    EwResize,
+    NsResize,
+    NeswResize,
+    NwseResize,
+    ColResize,
+    RowResize,
+    AllScroll,
+    ZoomIn,
+    ZoomOut,
+}
+
+impl Cursor {
+    pub fn initial_value() -> Cursor {
+        Cursor::Auto
+    }
+
+    pub fn parse<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        while input.try_parse(skip_cursor_image).is_ok() {}
+        try_match_ident_ignore_ascii_case! { input,
+            "auto" => Ok(Cursor::Auto),
+            "default" => Ok(Cursor::Default),
+            "none" => Ok(Cursor::None),
+            "context-menu" => Ok(Cursor::ContextMenu),
+            "help" => Ok(Cursor::Help),
+            "pointer" => Ok(Cursor::Pointer),
+            "progress" => Ok(Cursor::Progress),
+            "wait" => Ok(Cursor::Wait),
+            "cell" => Ok(Cursor::Cell),
+            "crosshair" => Ok(Cursor::Crosshair),
+            "text" => Ok(Cursor::Text),
+            "vertical-text" => Ok(Cursor::VerticalText),
+            "alias" => Ok(Cursor::Alias),
+            "copy" => Ok(Cursor::Copy),
+            "move" => Ok(Cursor::Move),
+            "no-drop" => Ok(Cursor::NoDrop),
+            "not-allowed" => Ok(Cursor::NotAllowed),
+            "grab" => Ok(Cursor::Grab),
+            "grabbing" => Ok(Cursor::Grabbing),
+            "e-resize" => Ok(Cursor::EResize),
+            "n-resize" => Ok(Cursor::NResize),
+            "ne-resize" => Ok(Cursor::NeResize),
+            "nw-resize" => Ok(Cursor::NwResize),
+            "s-resize" => Ok(Cursor::SResize),
+            "se-resize" => Ok(Cursor::SeResize),
+            "sw-resize" => Ok(Cursor::SwResize),
+            "w-resize" => Ok(Cursor::WResize),
+            "ew-resize" => Ok(Cursor::EwResize),
+            "ns-resize" => Ok(Cursor::NsResize),
+            "nesw-resize" => Ok(Cursor::NeswResize),
+            "nwse-resize" => Ok(Cursor::NwseResize),
+            "col-resize" => Ok(Cursor::ColResize),
+            "row-resize" => Ok(Cursor::RowResize),
+            "all-scroll" => Ok(Cursor::AllScroll),
+            "zoom-in" => Ok(Cursor::ZoomIn),
+            "zoom-out" => Ok(Cursor::ZoomOut),
+        }
+    }
+}
+
+/// Skips a custom cursor image, along with its hotspot (if any) and the comma after it.
+fn skip_cursor_image<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<(), ParseError<'i, StyleParseErrorKind<'i>>> {
+    input.expect_url()?;
+    input.try_parse(skip_hotspot).ok();
+    input.expect_comma()?;
+    Ok(())
+}
+
+fn skip_hotspot<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<(), ParseError<'i, StyleParseErrorKind<'i>>> {
+    input.expect_number()?;
+    input.expect_number()?;
+    Ok(())
+}
+
+impl ValueDefault for Cursor {
+    type ComputedValue = Cursor;
+
+    fn value_default(context: &ComputeContext) -> Self::ComputedValue {
+        context.parent_computed_values.cursor
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cssparser::ParserInput;
+
+    fn parse_cursor(css: &str) -> Option<Cursor> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        parser.parse_entirely(|input| Cursor::parse(input)).ok()
+    }
+
+    #[test]
+    fn parses_cursor_keywords() {
+        assert_eq!(parse_cursor("pointer"), Some(Cursor::Pointer));
+        assert_eq!(parse_cursor("NWSE-resize"), Some(Cursor::NwseResize));
+        assert_eq!(parse_cursor("hand"), None);
+    }
+
+    #[test]
+    fn falls_back_from_custom_images_to_the_keyword() {
+        assert_eq!(
+            parse_cursor("url(hand.cur) 2 3, url('grab.png'), grab"),
+            Some(Cursor::Grab)
+        );
+        assert_eq!(parse_cursor("url(hand.cur)"), None);
+    }
+}
@@ -0,0 +1,185 @@
+use crate::style::values::computed::{ComputeContext, ValueDefault};
+use crate::style::StyleParseErrorKind;
+use cssparser::{ParseError, Parser, Token};
+
+/// The properties that create a stacking context when they have a value other than their initial
+/// value, so that hinting they'll change does too.
+///
+/// https://drafts.csswg.org/css-will-change/#valdef-will-change-custom-ident
+const STACKING_CONTEXT_PROPERTIES: &[&str] = &[
+    "backdrop-filter",
+    "clip-path",
+    "contain",
+    "filter",
+    "isolation",
+    "mask",
+    "mask-image",
+    "mix-blend-mode",
+    "opacity",
+    "perspective",
+    "position",
+    "rotate",
+    "scale",
+    "transform",
+    "translate",
+];
+
+/// The properties that make an element the containing block of all of its positioned
+/// descendants, `position: fixed` ones included, when they have a value other than their initial
+/// value, so that hinting they'll change does too.
+///
+/// https://drafts.csswg.org/css-transforms-1/#containing-block-for-all-descendants
+const CONTAINING_BLOCK_PROPERTIES: &[&str] = &[
+    "backdrop-filter",
+    "contain",
+    "filter",
+    "perspective",
+    "rotate",
+    "scale",
+    "transform",
+    "translate",
+];
+
+/// Computed values for the `will-change` property, which hints that the given features of an
+/// element are about to change (e.g. be animated), so the browser can prepare for them ahead of
+/// time.  Hinting a property that would create a stacking context creates one straight away, and
+/// the element is painted into a compositor layer of its own, so changing it doesn't repaint the
+/// rest of the page.  Likewise, hinting a property that would make the element the containing
+/// block of its positioned descendants (like `transform`) makes it one straight away.
+///
+/// https://drafts.csswg.org/css-will-change/#will-change
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WillChange {
+    /// The features hinted at, which is empty for `auto`.
+    features: Vec<AnimateableFeature>,
+}
+
+/// A feature of an element that `will-change` hints is about to change.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AnimateableFeature {
+    /// The scroll position of the element, if it's a scroll container.
+    ScrollPosition,
+    /// The content of the element.
+    Contents,
+    /// The property with the given name, in lowercase.  Properties Kosmonaut doesn't know are
+    /// kept, but have no effect.
+    Property(String),
+}
+
+impl WillChange {
+    pub fn initial_value() -> WillChange {
+        WillChange {
+            features: Vec::new(),
+        }
+    }
+
+    pub fn parse<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        if input
+            .try_parse(|input| input.expect_ident_matching("auto"))
+            .is_ok()
+        {
+            return Ok(WillChange::initial_value());
+        }
+        let features = input.parse_comma_separated(|input| {
+            let location = input.current_source_location();
+            let ident = input.expect_ident_cloned()?;
+            Ok(match_ignore_ascii_case! { &ident,
+                "scroll-position" => AnimateableFeature::ScrollPosition,
+                "contents" => AnimateableFeature::Contents,
+                // Keywords that can't be custom identifiers, as they'd be ambiguous.
+                "will-change" | "none" | "all" | "auto" | "inherit" | "initial" | "unset"
+                    | "default" => {
+                    return Err(location.new_unexpected_token_error(Token::Ident(ident.clone())))
+                },
+                _ => AnimateableFeature::Property(ident.to_ascii_lowercase()),
+            })
+        })?;
+        Ok(WillChange { features })
+    }
+
+    /// Whether any property hinted at would create a stacking context, which makes the element
+    /// create one.
+    pub fn creates_stacking_context(&self) -> bool {
+        self.hints_any_of(STACKING_CONTEXT_PROPERTIES)
+    }
+
+    /// Whether any property hinted at would make the element the containing block of its
+    /// `position: fixed` descendants, and so of its absolutely positioned ones too.
+    pub fn contains_fixed_position_descendants(&self) -> bool {
+        self.hints_any_of(CONTAINING_BLOCK_PROPERTIES)
+    }
+
+    /// Whether any property hinted at would make the element the containing block of its
+    /// absolutely positioned descendants, which `position` does as well as the properties that
+    /// contain fixed position ones.
+    pub fn contains_absolutely_positioned_descendants(&self) -> bool {
+        self.contains_fixed_position_descendants() || self.hints_any_of(&["position"])
+    }
+
+    fn hints_any_of(&self, properties: &[&str]) -> bool {
+        self.features.iter().any(|feature| match feature {
+            AnimateableFeature::Property(property) => properties.contains(&property.as_str()),
+            _ => false,
+        })
+    }
+}
+
+impl ValueDefault for WillChange {
+    type ComputedValue = WillChange;
+
+    fn value_default(_context: &ComputeContext) -> Self::ComputedValue {
+        WillChange::initial_value()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cssparser::ParserInput;
+
+    fn parse_will_change(css: &str) -> Option<WillChange> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        parser.parse_entirely(WillChange::parse).ok()
+    }
+
+    #[test]
+    fn parses_will_change() {
+        assert_eq!(parse_will_change("auto"), Some(WillChange::initial_value()));
+        assert_eq!(
+            parse_will_change("Scroll-Position, OPACITY, --custom"),
+            Some(WillChange {
+                features: vec![
+                    AnimateableFeature::ScrollPosition,
+                    AnimateableFeature::Property("opacity".to_owned()),
+                    AnimateableFeature::Property("--custom".to_owned()),
+                ]
+            })
+        );
+        assert_eq!(parse_will_change("contents, auto"), None);
+        assert_eq!(parse_will_change("none"), None);
+        assert_eq!(parse_will_change("transform,"), None);
+    }
+
+    #[test]
+    fn hinting_stacking_context_properties_creates_stacking_contexts() {
+        let creates_stacking_context =
+            |css| parse_will_change(css).unwrap().creates_stacking_context();
+        assert!(creates_stacking_context("scroll-position, transform"));
+        assert!(creates_stacking_context("opacity"));
+        assert!(!creates_stacking_context("auto"));
+        assert!(!creates_stacking_context("contents, background-color"));
+    }
+
+    #[test]
+    fn hinting_containing_block_properties_contains_positioned_descendants() {
+        let will_change = |css| parse_will_change(css).unwrap();
+        assert!(will_change("opacity, transform").contains_fixed_position_descendants());
+        assert!(will_change("filter").contains_absolutely_positioned_descendants());
+        assert!(will_change("position").contains_absolutely_positioned_descendants());
+        assert!(!will_change("position").contains_fixed_position_descendants());
+        assert!(!will_change("opacity").contains_absolutely_positioned_descendants());
+    }
+}
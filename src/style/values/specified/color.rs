@@ -46,12 +46,17 @@ pub enum ColorUnit {
     CurrentColor,
     /// A numeric `color`.
     Numeric(cssparser::RGBA),
+    /// A system color, which depends on the color scheme used.
+    System(SystemColor),
 }
 
 impl ColorUnit {
     pub fn parse<'i, 't>(
         input: &mut Parser<'i, 't>,
     ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        if let Ok(system_color) = input.try_parse(SystemColor::parse) {
+            return Ok(ColorUnit::System(system_color));
+        }
         let component_parser = ComponentParser {};
         match input.try_parse(|i| CSSParserColor::parse_with(&component_parser, i)) {
             Ok(value) => Ok(match value {
@@ -88,6 +93,47 @@ impl ColorUnit {
     }
 }
 
+/// The colors of the user's color scheme (or rather, of the color scheme used for an element),
+/// such as the default text and background colors.
+///
+/// https://drafts.csswg.org/css-color-4/#css-system-colors
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SystemColor {
+    /// The background of documents.
+    Canvas,
+    /// Text on a `Canvas` background.
+    CanvasText,
+    /// Text in links.
+    LinkText,
+    /// The background of buttons.
+    ButtonFace,
+    /// Text on a `ButtonFace` background.
+    ButtonText,
+    /// The border of buttons.
+    ButtonBorder,
+    /// The background of text fields.
+    Field,
+    /// Text on a `Field` background.
+    FieldText,
+}
+
+impl SystemColor {
+    pub fn parse<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        try_match_ident_ignore_ascii_case! { input,
+            "canvas" => Ok(SystemColor::Canvas),
+            "canvastext" => Ok(SystemColor::CanvasText),
+            "linktext" => Ok(SystemColor::LinkText),
+            "buttonface" => Ok(SystemColor::ButtonFace),
+            "buttontext" => Ok(SystemColor::ButtonText),
+            "buttonborder" => Ok(SystemColor::ButtonBorder),
+            "field" => Ok(SystemColor::Field),
+            "fieldtext" => Ok(SystemColor::FieldText),
+        }
+    }
+}
+
 struct ComponentParser;
 impl<'i> ColorComponentParser<'i> for ComponentParser {
     type Error = StyleParseErrorKind<'i>;
@@ -0,0 +1,89 @@
+use crate::style::values::specified::NoCalcLength;
+use crate::style::StyleParseErrorKind;
+use cssparser::{ParseError, Parser, Token};
+
+/// Specified values for the `contain-intrinsic-width` and `contain-intrinsic-height` properties,
+/// which give a box with size containment the size it would have if it had a single child of the
+/// given size, rather than none, so boxes whose contents are skipped (see `ContentVisibility`)
+/// keep a placeholder size.
+///
+/// TODO: `auto <length>` should use the size the box last had when its contents weren't skipped,
+/// falling back to the length, but Kosmonaut doesn't remember those sizes yet, so it's treated
+/// as the length alone.
+///
+/// https://drafts.csswg.org/css-sizing-4/#intrinsic-size-override
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ContainIntrinsicSize {
+    /// The size given, or `None` for `none`.
+    pub length: Option<NoCalcLength>,
+}
+
+impl ContainIntrinsicSize {
+    pub fn parse<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        if input
+            .try_parse(|input| input.expect_ident_matching("none"))
+            .is_ok()
+        {
+            return Ok(ContainIntrinsicSize { length: None });
+        }
+        let _ = input.try_parse(|input| input.expect_ident_matching("auto"));
+        let location = input.current_source_location();
+        let token = input.next()?;
+        match *token {
+            Token::Dimension {
+                value, ref unit, ..
+            } if value >= 0. => NoCalcLength::parse_dimension(value, unit)
+                .map(|length| ContainIntrinsicSize {
+                    length: Some(length),
+                })
+                .map_err(|()| location.new_unexpected_token_error(token.clone())),
+            _ => Err(location.new_unexpected_token_error(token.clone())),
+        }
+    }
+
+    /// Parses the `contain-intrinsic-size` shorthand into its width and height longhands, where
+    /// a missing height is the same as the width.
+    pub fn parse_shorthand<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<[Self; 2], ParseError<'i, StyleParseErrorKind<'i>>> {
+        let width = ContainIntrinsicSize::parse(input)?;
+        let height = input
+            .try_parse(ContainIntrinsicSize::parse)
+            .unwrap_or(width);
+        Ok([width, height])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::style::values::specified::AbsoluteLength;
+    use cssparser::ParserInput;
+
+    fn px(value: f32) -> ContainIntrinsicSize {
+        ContainIntrinsicSize {
+            length: Some(NoCalcLength::Absolute(AbsoluteLength::Px(value))),
+        }
+    }
+
+    #[test]
+    fn parses_contain_intrinsic_sizes() {
+        let parse = |css| {
+            let mut input = ParserInput::new(css);
+            let mut parser = Parser::new(&mut input);
+            parser
+                .parse_entirely(ContainIntrinsicSize::parse_shorthand)
+                .ok()
+        };
+        assert_eq!(parse("100px"), Some([px(100.), px(100.)]));
+        assert_eq!(
+            parse("none auto 20px"),
+            Some([ContainIntrinsicSize { length: None }, px(20.)])
+        );
+        assert_eq!(parse("-10px"), None);
+        assert_eq!(parse("auto"), None);
+        assert_eq!(parse("10%"), None);
+    }
+}
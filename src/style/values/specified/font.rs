@@ -67,3 +67,36 @@ impl FontSize {
         FontSize::Keyword(KeywordSize::Medium)
     }
 }
+
+/// A specified `font-weight` value.
+///
+/// https://drafts.csswg.org/css-fonts-4/#font-weight-prop
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FontWeight {
+    /// A numeric weight between 1 and 1000, which `normal` and `bold` are aliases of.
+    Absolute(f32),
+    /// One weight bolder than the parent's.
+    Bolder,
+    /// One weight lighter than the parent's.
+    Lighter,
+}
+
+impl FontWeight {
+    pub fn parse<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        let location = input.current_source_location();
+        if let Ok(weight) = input.try_parse(|i| i.expect_number()) {
+            if !(1. ..=1000.).contains(&weight) {
+                return Err(location.new_custom_error(StyleParseErrorKind::UnspecifiedError));
+            }
+            return Ok(FontWeight::Absolute(weight));
+        }
+        try_match_ident_ignore_ascii_case! { input,
+            "normal" => Ok(FontWeight::Absolute(400.)),
+            "bold" => Ok(FontWeight::Absolute(700.)),
+            "bolder" => Ok(FontWeight::Bolder),
+            "lighter" => Ok(FontWeight::Lighter),
+        }
+    }
+}
@@ -26,6 +26,10 @@ pub enum NoCalcLength {
     ///
     /// <https://drafts.csswg.org/css-values/#absolute-length>
     Absolute(AbsoluteLength),
+    /// A viewport-percentage length
+    ///
+    /// <https://drafts.csswg.org/css-values/#viewport-relative-lengths>
+    ViewportPercentage(ViewportPercentageLength),
 }
 
 /// Represents an absolute length with its unit
@@ -80,6 +84,34 @@ impl AbsoluteLength {
     }
 }
 
+/// Represents a viewport-percentage length with its unit, each of which is a percentage of the
+/// size of the initial containing block.
+/// <https://drafts.csswg.org/css-values/#viewport-relative-lengths>
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ViewportPercentageLength {
+    /// A percentage of the viewport's width (vw)
+    Vw(CSSFloat),
+    /// A percentage of the viewport's height (vh)
+    Vh(CSSFloat),
+    /// A percentage of the viewport's smaller dimension (vmin)
+    Vmin(CSSFloat),
+    /// A percentage of the viewport's larger dimension (vmax)
+    Vmax(CSSFloat),
+}
+
+impl ViewportPercentageLength {
+    /// Convert this into a pixel value, given the size of the viewport in pixels.
+    pub fn to_px(self, viewport_width: CSSFloat, viewport_height: CSSFloat) -> CSSFloat {
+        let (percentage, basis) = match self {
+            ViewportPercentageLength::Vw(value) => (value, viewport_width),
+            ViewportPercentageLength::Vh(value) => (value, viewport_height),
+            ViewportPercentageLength::Vmin(value) => (value, viewport_width.min(viewport_height)),
+            ViewportPercentageLength::Vmax(value) => (value, viewport_width.max(viewport_height)),
+        };
+        percentage * basis / 100.
+    }
+}
+
 impl NoCalcLength {
     /// Parse a given absolute or relative dimension.
     pub fn parse_dimension(value: CSSFloat, unit: &str) -> Result<Self, ()> {
@@ -91,6 +123,10 @@ impl NoCalcLength {
             "q" => NoCalcLength::Absolute(AbsoluteLength::Q(value)),
             "pt" => NoCalcLength::Absolute(AbsoluteLength::Pt(value)),
             "pc" => NoCalcLength::Absolute(AbsoluteLength::Pc(value)),
+            "vw" => NoCalcLength::ViewportPercentage(ViewportPercentageLength::Vw(value)),
+            "vh" => NoCalcLength::ViewportPercentage(ViewportPercentageLength::Vh(value)),
+            "vmin" => NoCalcLength::ViewportPercentage(ViewportPercentageLength::Vmin(value)),
+            "vmax" => NoCalcLength::ViewportPercentage(ViewportPercentageLength::Vmax(value)),
             _ => return Err(())
         })
     }
@@ -12,11 +12,14 @@
 pub mod background;
 pub mod border;
 pub mod color;
+pub mod contain;
 pub mod font;
 pub mod height;
 pub mod length;
 pub mod margin;
 pub mod padding;
+pub mod scroll_snap;
+pub mod text;
 pub mod width;
 
 pub use background::BackgroundColor;
@@ -32,9 +35,12 @@ pub use border::BorderTopWidth;
 
 pub use color::Color;
 pub use color::ColorUnit;
+pub use color::SystemColor;
+
+pub use contain::ContainIntrinsicSize;
 
-pub use font::FontSize;
 pub use font::FONT_MEDIUM_PX;
+pub use font::{FontSize, FontWeight};
 
 pub use height::Height;
 
@@ -53,4 +59,8 @@ pub use padding::PaddingLeft;
 pub use padding::PaddingRight;
 pub use padding::PaddingTop;
 
+pub use scroll_snap::{ScrollMargin, ScrollPadding};
+
+pub use text::TabSize;
+
 pub use width::Width;
@@ -0,0 +1,111 @@
+use crate::style::values::specified::{LengthPercentage, LengthPercentageOrAuto, NoCalcLength};
+use crate::style::StyleParseErrorKind;
+use cssparser::{ParseError, Parser};
+
+/// Specified values for the `scroll-margin-<side>` properties, which outset a snap area from the
+/// border box of the box it belongs to.
+///
+/// https://drafts.csswg.org/css-scroll-snap-1/#scroll-margin
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScrollMargin {
+    pub length: NoCalcLength,
+}
+
+impl ScrollMargin {
+    pub fn parse<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        let location = input.current_source_location();
+        match LengthPercentage::parse(input)? {
+            LengthPercentage::Length(length) => Ok(ScrollMargin { length }),
+            LengthPercentage::Percentage(_) => {
+                Err(location.new_custom_error(StyleParseErrorKind::UnspecifiedError))
+            }
+        }
+    }
+
+    /// Parses the `scroll-margin` shorthand into its top, right, bottom, and left longhands.
+    pub fn parse_shorthand<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<[Self; 4], ParseError<'i, StyleParseErrorKind<'i>>> {
+        parse_sides(input, ScrollMargin::parse)
+    }
+}
+
+/// Specified values for the `scroll-padding-<side>` properties, which inset the snapport (the
+/// part of a scroll container snap areas are aligned within) from the scroll container's edges.
+///
+/// https://drafts.csswg.org/css-scroll-snap-1/#scroll-padding
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScrollPadding {
+    pub lp_or_auto: LengthPercentageOrAuto,
+}
+
+impl ScrollPadding {
+    pub fn parse<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        LengthPercentageOrAuto::parse(input).map(|lp_or_auto| ScrollPadding { lp_or_auto })
+    }
+
+    /// Parses the `scroll-padding` shorthand into its top, right, bottom, and left longhands.
+    pub fn parse_shorthand<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<[Self; 4], ParseError<'i, StyleParseErrorKind<'i>>> {
+        parse_sides(input, ScrollPadding::parse)
+    }
+}
+
+/// Parses one to four values of a shorthand for the top, right, bottom, and left sides of a box,
+/// in that order, where missing values are copied from the opposite side (or from the top, for
+/// the right side).
+///
+/// https://drafts.csswg.org/css-box-4/#margin-shorthand
+fn parse_sides<'i, 't, T: Copy>(
+    input: &mut Parser<'i, 't>,
+    parse: fn(&mut Parser<'i, 't>) -> Result<T, ParseError<'i, StyleParseErrorKind<'i>>>,
+) -> Result<[T; 4], ParseError<'i, StyleParseErrorKind<'i>>> {
+    let top = parse(input)?;
+    let right = input.try_parse(parse).unwrap_or(top);
+    let bottom = input.try_parse(parse).unwrap_or(top);
+    let left = input.try_parse(parse).unwrap_or(right);
+    Ok([top, right, bottom, left])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::style::values::specified::AbsoluteLength;
+    use cssparser::ParserInput;
+
+    fn px(px: f32) -> ScrollMargin {
+        ScrollMargin {
+            length: NoCalcLength::Absolute(AbsoluteLength::Px(px)),
+        }
+    }
+
+    #[test]
+    fn parses_scroll_margin_shorthand() {
+        let parse_scroll_margin = |css| {
+            let mut input = ParserInput::new(css);
+            let mut parser = Parser::new(&mut input);
+            parser.parse_entirely(ScrollMargin::parse_shorthand).ok()
+        };
+        assert_eq!(parse_scroll_margin("1px"), Some([px(1.); 4]));
+        assert_eq!(
+            parse_scroll_margin("1px 2px"),
+            Some([px(1.), px(2.), px(1.), px(2.)])
+        );
+        assert_eq!(
+            parse_scroll_margin("1px 2px 3px"),
+            Some([px(1.), px(2.), px(3.), px(2.)])
+        );
+        assert_eq!(
+            parse_scroll_margin("1px 2px 3px 4px"),
+            Some([px(1.), px(2.), px(3.), px(4.)])
+        );
+        // Scroll margins can't be percentages, and there are only four sides.
+        assert_eq!(parse_scroll_margin("10%"), None);
+        assert_eq!(parse_scroll_margin("1px 2px 3px 4px 5px"), None);
+    }
+}
@@ -0,0 +1,59 @@
+use crate::style::values::specified::NoCalcLength;
+use crate::style::values::CSSFloat;
+use crate::style::StyleParseErrorKind;
+use cssparser::{ParseError, Parser, Token};
+
+/// Specified values for the `tab-size` property: how far apart tab stops are, as either a number
+/// of spaces or a length.
+///
+/// https://drafts.csswg.org/css-text-3/#tab-size-property
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TabSize {
+    Number(CSSFloat),
+    Length(NoCalcLength),
+}
+
+impl TabSize {
+    pub fn parse<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, ParseError<'i, StyleParseErrorKind<'i>>> {
+        let location = input.current_source_location();
+        let token = input.next()?;
+        match *token {
+            Token::Number { value, .. } if value >= 0. => Ok(TabSize::Number(value)),
+            Token::Dimension {
+                value, ref unit, ..
+            } if value >= 0. => NoCalcLength::parse_dimension(value, unit)
+                .map(TabSize::Length)
+                .map_err(|()| location.new_unexpected_token_error(token.clone())),
+            _ => Err(location.new_unexpected_token_error(token.clone())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::style::values::specified::AbsoluteLength;
+    use cssparser::ParserInput;
+
+    fn parse_tab_size(css: &str) -> Option<TabSize> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        parser.parse_entirely(|input| TabSize::parse(input)).ok()
+    }
+
+    #[test]
+    fn parses_numbers_and_lengths() {
+        assert_eq!(parse_tab_size("4"), Some(TabSize::Number(4.)));
+        assert_eq!(parse_tab_size("0"), Some(TabSize::Number(0.)));
+        assert_eq!(
+            parse_tab_size("20px"),
+            Some(TabSize::Length(NoCalcLength::Absolute(AbsoluteLength::Px(
+                20.
+            ))))
+        );
+        assert_eq!(parse_tab_size("-2"), None);
+        assert_eq!(parse_tab_size("10%"), None);
+    }
+}
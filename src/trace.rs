@@ -0,0 +1,49 @@
+//! Tracing where the time goes in loading, styling, laying out, and painting pages, so that slow
+//! phases on big pages can be profiled.
+//!
+//! Each phase is instrumented with a `tracing` span.  When tracing is started, spans are written to
+//! a file in the Chrome trace event format, which can be opened in `chrome://tracing`, Perfetto
+//! (https://ui.perfetto.dev), or Speedscope.
+//!
+//! https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU
+
+use std::cell::RefCell;
+use std::path::Path;
+
+use tracing_chrome::{ChromeLayerBuilder, FlushGuard};
+use tracing_subscriber::prelude::*;
+
+thread_local! {
+    /// Flushes the trace being written, if any, when dropped.
+    static FLUSH_GUARD: RefCell<Option<FlushGuard>> = RefCell::new(None);
+}
+
+/// Ends the trace being written when dropped.
+#[must_use]
+pub struct Trace {
+    _private: (),
+}
+
+impl Drop for Trace {
+    fn drop(&mut self) {
+        finish();
+    }
+}
+
+/// Starts writing spans entered on any thread to a Chrome trace at `path`, until `finish` is
+/// called or the returned `Trace` is dropped.  Must only be called once.
+pub fn start(path: &Path) -> Trace {
+    let (chrome_layer, flush_guard) = ChromeLayerBuilder::new().file(path.to_path_buf()).build();
+    tracing_subscriber::registry().with(chrome_layer).init();
+    FLUSH_GUARD.with(|guard| *guard.borrow_mut() = Some(flush_guard));
+    Trace { _private: () }
+}
+
+/// Finishes writing the trace started on this thread, if any.  Spans entered afterwards aren't
+/// written.
+///
+/// This only needs calling on paths that exit the process without unwinding to the `Trace`
+/// returned by `start` (e.g. the window's event loop, which never returns).
+pub fn finish() {
+    FLUSH_GUARD.with(|guard| guard.borrow_mut().take());
+}
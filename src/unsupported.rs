@@ -0,0 +1,217 @@
+//! Reporting the CSS and HTML features pages use that Kosmonaut doesn't implement, so work on the
+//! engine can be prioritized by what real pages need.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use cssparser::{Parser, ParserInput};
+
+use crate::dom::iter::NodeIterator;
+use crate::dom::tree::NodeRef;
+use crate::style::diagnostics::{CssDiagnostic, CssDiagnosticKind};
+use crate::style::properties::parse_property_declaration_list_with_diagnostics;
+use crate::style::stylesheet::Stylesheet;
+
+/// Elements whose behavior Kosmonaut doesn't implement, such as `<canvas>` (whose contents are
+/// never drawn), `<table>` (which needs table layout), or `<style>` (whose stylesheet isn't
+/// applied).
+const UNSUPPORTED_ELEMENTS: &[&str] = &[
+    "audio", "canvas", "dialog", "embed", "frameset", "math", "object", "style", "svg", "table",
+    "video",
+];
+
+/// A feature a page used that Kosmonaut doesn't implement.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UnsupportedFeature {
+    /// A property we don't know, by name.
+    CssProperty(String),
+    /// A value of a known property that we can't parse, as declared, e.g. `display: flex`.
+    CssValue(String),
+    /// An at-rule we don't support, by name.
+    CssAtRule(String),
+    /// A pseudo-class or pseudo-element we don't support, by name.
+    CssPseudoClassOrElement(String),
+    /// A selector we can't parse (for some reason other than an unsupported pseudo-class).
+    CssSelector(String),
+    /// An element we don't support, by local name.
+    HtmlElement(String),
+    /// A `<link rel=stylesheet>`, since only stylesheets given on the command line are applied.
+    HtmlStylesheetLink,
+}
+
+impl fmt::Display for UnsupportedFeature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UnsupportedFeature::CssProperty(name) => write!(f, "CSS property `{}`", name),
+            UnsupportedFeature::CssValue(declaration) => write!(f, "CSS value `{}`", declaration),
+            UnsupportedFeature::CssAtRule(name) => write!(f, "CSS at-rule `@{}`", name),
+            UnsupportedFeature::CssPseudoClassOrElement(name) => {
+                write!(f, "CSS pseudo-class or pseudo-element `:{}`", name)
+            }
+            UnsupportedFeature::CssSelector(selector) => write!(f, "CSS selector `{}`", selector),
+            UnsupportedFeature::HtmlElement(name) => write!(f, "HTML element `<{}>`", name),
+            UnsupportedFeature::HtmlStylesheetLink => {
+                write!(f, "HTML element `<link rel=stylesheet>`")
+            }
+        }
+    }
+}
+
+/// The unsupported features encountered while loading pages, and how many times each was used.
+#[derive(Clone, Debug, Default)]
+pub struct UnsupportedFeatures {
+    uses: BTreeMap<UnsupportedFeature, usize>,
+}
+
+impl UnsupportedFeatures {
+    pub fn new() -> UnsupportedFeatures {
+        UnsupportedFeatures::default()
+    }
+
+    pub fn record(&mut self, feature: UnsupportedFeature) {
+        *self.uses.entry(feature).or_insert(0) += 1;
+    }
+
+    /// Records the unsupported features behind the diagnostics of `sheet`.
+    pub fn record_stylesheet(&mut self, sheet: &Stylesheet) {
+        for diagnostic in sheet.diagnostics() {
+            self.record_diagnostic(diagnostic);
+        }
+    }
+
+    /// Records the unsupported elements in `document`, and the unsupported features of the
+    /// declarations in its `style` attributes.
+    pub fn record_document(&mut self, document: &NodeRef) {
+        for element in document.descendants().elements() {
+            let name = &*element.name.local;
+            if UNSUPPORTED_ELEMENTS.contains(&name) {
+                self.record(UnsupportedFeature::HtmlElement(name.to_owned()));
+            }
+            let attributes = element.attributes.borrow();
+            if name == "link"
+                && attributes.get(local_name!("rel")).map_or(false, |rel| {
+                    rel.split_ascii_whitespace()
+                        .any(|keyword| keyword.eq_ignore_ascii_case("stylesheet"))
+                })
+            {
+                self.record(UnsupportedFeature::HtmlStylesheetLink);
+            }
+            if let Some(style) = attributes.get(local_name!("style")) {
+                let mut input = ParserInput::new(style);
+                let mut diagnostics = Vec::new();
+                parse_property_declaration_list_with_diagnostics(
+                    &mut Parser::new(&mut input),
+                    &mut diagnostics,
+                );
+                for diagnostic in &diagnostics {
+                    self.record_diagnostic(diagnostic);
+                }
+            }
+        }
+    }
+
+    fn record_diagnostic(&mut self, diagnostic: &CssDiagnostic) {
+        let feature = match &diagnostic.kind {
+            CssDiagnosticKind::UnknownProperty(name) => {
+                UnsupportedFeature::CssProperty(name.to_ascii_lowercase())
+            }
+            CssDiagnosticKind::InvalidDeclaration(declaration) => {
+                UnsupportedFeature::CssValue(declaration.clone())
+            }
+            CssDiagnosticKind::InvalidSelector(selector) => {
+                UnsupportedFeature::CssSelector(selector.clone())
+            }
+            CssDiagnosticKind::UnsupportedPseudoClassOrElement(name) => {
+                UnsupportedFeature::CssPseudoClassOrElement(name.to_ascii_lowercase())
+            }
+            CssDiagnosticKind::UnsupportedAtRule(name) => {
+                UnsupportedFeature::CssAtRule(name.to_ascii_lowercase())
+            }
+            // Rules that are just malformed don't point at any feature to implement.
+            CssDiagnosticKind::InvalidRule(_) => return,
+        };
+        self.record(feature);
+    }
+
+    /// The features encountered, most used first, with how many times each was used.
+    pub fn by_uses(&self) -> Vec<(&UnsupportedFeature, usize)> {
+        let mut uses = self
+            .uses
+            .iter()
+            .map(|(feature, &count)| (feature, count))
+            .collect::<Vec<_>>();
+        // The sort is stable, so features used equally often stay in order.
+        uses.sort_by(|(_, count), (_, other_count)| other_count.cmp(count));
+        uses
+    }
+
+    /// A summary of the features encountered, e.g.:
+    ///
+    /// ```text
+    /// 2 unsupported features encountered, most used first:
+    ///     3  CSS property `grid-template-columns`
+    ///     1  HTML element `<canvas>`
+    /// ```
+    pub fn summary(&self) -> String {
+        if self.uses.is_empty() {
+            return "no unsupported features encountered\n".to_owned();
+        }
+        let mut summary = format!(
+            "{} unsupported features encountered, most used first:\n",
+            self.uses.len()
+        );
+        for (feature, count) in self.by_uses() {
+            summary.push_str(&format!("{:>5}  {}\n", count, feature));
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::parser::parse_html;
+    use crate::dom::traits::TendrilSink;
+    use crate::style::stylesheet::parse_css_to_stylesheet;
+
+    #[test]
+    fn records_unsupported_css_and_html() {
+        let sheet = parse_css_to_stylesheet(
+            None,
+            &mut ".a { display: flex; gap: 1px }
+                .b { GAP: 2px }
+                p:fullscreen { color: red }
+                @supports (display: grid) { .c { color: red } }"
+                .to_owned(),
+        );
+        let document = parse_html().one(
+            "<link rel=\"icon stylesheet\" href=a.css><canvas></canvas>\
+             <div style=\"gap: 3px; color: red\"><table><tr><td></td></tr></table></div>",
+        );
+        let mut features = UnsupportedFeatures::new();
+        features.record_stylesheet(&sheet);
+        features.record_document(&document);
+        assert_eq!(
+            features.by_uses(),
+            vec![
+                (&UnsupportedFeature::CssProperty("gap".to_owned()), 3),
+                (&UnsupportedFeature::CssValue("display: flex".to_owned()), 1),
+                (&UnsupportedFeature::CssAtRule("supports".to_owned()), 1),
+                (
+                    &UnsupportedFeature::CssPseudoClassOrElement("fullscreen".to_owned()),
+                    1
+                ),
+                (&UnsupportedFeature::HtmlElement("canvas".to_owned()), 1),
+                (&UnsupportedFeature::HtmlElement("table".to_owned()), 1),
+                (&UnsupportedFeature::HtmlStylesheetLink, 1),
+            ]
+        );
+        assert!(features.summary().starts_with(
+            "7 unsupported features encountered, most used first:\n    3  CSS property `gap`\n"
+        ));
+        assert_eq!(
+            UnsupportedFeatures::new().summary(),
+            "no unsupported features encountered\n"
+        );
+    }
+}
@@ -0,0 +1,80 @@
+//! Bindings for running the engine in a web page (or any other WebAssembly host with JavaScript),
+//! wrapping `Page`.  Build them without the window, e.g. with
+//! `wasm-pack build --target web -- --no-default-features`, and use them like:
+//!
+//! ```text
+//! import init, { WasmPage } from "./pkg/kosmonaut_core.js";
+//!
+//! await init();
+//! const page = new WasmPage("<div style='width: 50px; height: 50px; background: red'></div>", "");
+//! page.set_viewport(canvas.width, canvas.height, window.devicePixelRatio);
+//! page.render_to_canvas(canvas.getContext("2d"));
+//! ```
+//!
+//! There's no file system to load images from, so images aren't displayed, and text isn't painted,
+//! since it's painted with system fonts.
+
+use std::path::Path;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::Clamped;
+use web_sys::{CanvasRenderingContext2d, ImageData};
+
+use crate::layout::rect::Point;
+use crate::page::Page;
+use crate::style::stylesheet::parse_css_to_stylesheet;
+
+/// A page, as exposed to JavaScript.
+#[wasm_bindgen]
+pub struct WasmPage {
+    page: Page,
+}
+
+#[wasm_bindgen]
+impl WasmPage {
+    /// Loads the document `html` is the source of, styled by the user agent stylesheet and the
+    /// author stylesheet `css`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(html: &str, css: &str) -> WasmPage {
+        let author_sheets = vec![parse_css_to_stylesheet(None, &mut css.to_owned())];
+        WasmPage {
+            page: Page::load_html(html, Path::new(""), author_sheets),
+        }
+    }
+
+    /// Sets the viewport to `width` by `height` device pixels, at `scale_factor` device pixels
+    /// per CSS pixel.
+    pub fn set_viewport(&mut self, width: f32, height: f32, scale_factor: f32) {
+        self.page.set_viewport(width, height, scale_factor);
+    }
+
+    pub fn layout(&mut self) {
+        self.page.layout();
+    }
+
+    /// Renders the page into straight RGBA pixels the size of its viewport in device pixels,
+    /// returned as a `Uint8Array`.
+    pub fn render_rgba(&mut self) -> Vec<u8> {
+        self.page.render().frame(0).pixels().to_vec()
+    }
+
+    /// Renders the page into `context`'s canvas, with its top left corner at the canvas's.
+    pub fn render_to_canvas(&mut self, context: &CanvasRenderingContext2d) -> Result<(), JsValue> {
+        let image = self.page.render();
+        let image_data = ImageData::new_with_u8_clamped_array_and_sh(
+            Clamped(image.frame(0).pixels()),
+            image.width(),
+            image.height(),
+        )?;
+        context.put_image_data(&image_data, 0., 0.)
+    }
+
+    /// The local name of the element painted at (`x`, `y`), in CSS pixels relative to the
+    /// viewport, or `undefined` if nothing was painted there.
+    pub fn hit_test(&mut self, x: f32, y: f32) -> Option<String> {
+        self.page.hit_test(Point { x, y }).first().and_then(|node| {
+            node.as_element()
+                .map(|element| element.name.local.to_string())
+        })
+    }
+}
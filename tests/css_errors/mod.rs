@@ -0,0 +1,88 @@
+#[cfg(test)]
+mod tests {
+    use crate::util::CommandUnderTest;
+
+    #[test]
+    fn logs_css_errors_with_source_locations() {
+        let mut cmd = CommandUnderTest::new();
+        cmd.arg("dump-layout")
+            .arg("--test-fonts")
+            .arg("--filter")
+            .arg("div")
+            .arg("--viewport")
+            .arg("800x600")
+            .arg("--dpr")
+            .arg("1")
+            .arg("--log-css-errors")
+            .arg("--files")
+            .arg("tests/websrc/css-errors/invalid.html")
+            .arg("tests/websrc/css-errors/invalid.css")
+            .succeeds()
+            .stdout_is("DIV BlockContainer at (8, 8) size 784x10\n");
+        // The UA stylesheet may have its own diagnostics, so only the author sheet's are checked.
+        let author_diagnostics = cmd
+            .stderr()
+            .lines()
+            .filter(|line| line.starts_with("tests/websrc/css-errors/invalid.css:"))
+            .map(|line| {
+                let mut parts = line.splitn(4, ':');
+                let line_number = parts.nth(1).unwrap_or("");
+                let message = parts.nth(1).unwrap_or("").trim();
+                format!("{}: {}", line_number, message)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            author_diagnostics,
+            vec![
+                "3: unknown property `colr`",
+                "4: invalid declaration `width: 10px 20px`",
+                "7: invalid selector `div > > p`",
+                "11: unsupported at-rule `@font-face`",
+            ]
+        );
+    }
+
+    #[test]
+    fn css_errors_are_not_logged_by_default() {
+        CommandUnderTest::new()
+            .arg("dump-layout")
+            .arg("--test-fonts")
+            .arg("--viewport")
+            .arg("800x600")
+            .arg("--dpr")
+            .arg("1")
+            .arg("--filter")
+            .arg("div")
+            .arg("--files")
+            .arg("tests/websrc/css-errors/invalid.html")
+            .arg("tests/websrc/css-errors/invalid.css")
+            .succeeds()
+            .no_stderr();
+    }
+
+    #[test]
+    fn reports_unsupported_features() {
+        CommandUnderTest::new()
+            .arg("dump-layout")
+            .arg("--test-fonts")
+            .arg("--viewport")
+            .arg("800x600")
+            .arg("--dpr")
+            .arg("1")
+            .arg("--filter")
+            .arg("div")
+            .arg("--report-unsupported")
+            .arg("--files")
+            .arg("tests/websrc/css-errors/invalid.html")
+            .arg("tests/websrc/css-errors/invalid.css")
+            .succeeds()
+            .stderr_is(
+                "5 unsupported features encountered, most used first:\n    \
+                     1  CSS property `colr`\n    \
+                     1  CSS value `width: 10px 20px`\n    \
+                     1  CSS at-rule `@font-face`\n    \
+                     1  CSS selector `div > > p`\n    \
+                     1  HTML element `<link rel=stylesheet>`\n",
+            );
+    }
+}
@@ -0,0 +1,59 @@
+#[cfg(test)]
+mod tests {
+    use crate::util::CommandUnderTest;
+
+    /// Dumps the layout of the div in `tests/websrc/media/breakpoints.html`, whose size depends on
+    /// which of its media queries match given `media_args`.
+    fn dump_breakpoints_div(media_args: &[&str], expected: &str) {
+        CommandUnderTest::new()
+            .arg("dump-layout")
+            .arg("--test-fonts")
+            .arg("--filter")
+            .arg("div")
+            .args(media_args)
+            .arg("--files")
+            .arg("tests/websrc/media/breakpoints.html")
+            .arg("tests/websrc/media/breakpoints.css")
+            .succeeds()
+            .stdout_is(expected);
+    }
+
+    #[test]
+    fn viewport_size_selects_breakpoint() {
+        dump_breakpoints_div(
+            &["--viewport", "800x600", "--dpr", "1"],
+            "DIV BlockContainer at (8, 8) size 784x10\n",
+        );
+        dump_breakpoints_div(
+            &["--viewport", "1200x600", "--dpr", "1"],
+            "DIV BlockContainer at (8, 8) size 1184x20\n",
+        );
+    }
+
+    #[test]
+    fn device_pixel_ratio_selects_resolution_breakpoint() {
+        dump_breakpoints_div(
+            &["--viewport", "1600x1200", "--dpr", "2"],
+            "DIV BlockContainer at (8, 8) size 100x10\n",
+        );
+    }
+
+    #[test]
+    fn print_media_and_dark_color_scheme_are_emulated() {
+        dump_breakpoints_div(
+            &["--viewport", "800x600", "--dpr", "1", "--media", "print"],
+            "DIV BlockContainer at (8, 8) size 784x30\n",
+        );
+        dump_breakpoints_div(
+            &[
+                "--viewport",
+                "800x600",
+                "--dpr",
+                "1",
+                "--prefers-color-scheme",
+                "dark",
+            ],
+            "DIV BlockContainer at (8, 8) size 784x40\n",
+        );
+    }
+}
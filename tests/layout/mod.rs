@@ -1,14 +1,18 @@
 use crate::util::CommandUnderTest;
 
-pub use kosmonaut::cli::DumpLayoutVerbosity;
+pub use kosmonaut_core::layout::DumpLayoutVerbosity;
 
 pub mod directional;
 pub mod display;
+pub mod media;
+pub mod replaced;
+pub mod shadow;
 
 #[cfg(test)]
 mod tests {
     use crate::layout::{dump_layout_cmd, dump_layout_cmd_verbose_scaled, DumpLayoutVerbosity};
     use insta::assert_snapshot;
+    use tempdir::TempDir;
 
     #[test]
     fn rainbow_divs_baseline() {
@@ -32,6 +36,104 @@ mod tests {
             .succeeds();
         assert_snapshot!(dump_layout_cmd.stdout());
     }
+
+    #[test]
+    fn rainbow_divs_json() {
+        let mut dump_layout_cmd = dump_layout_cmd(DumpLayoutVerbosity::NonVerbose);
+        dump_layout_cmd
+            .arg("--format")
+            .arg("json")
+            .arg("--files")
+            .arg("tests/websrc/rainbow-divs.html")
+            .arg("tests/websrc/rainbow-divs.css")
+            .succeeds();
+        let html: serde_json::Value =
+            serde_json::from_str(dump_layout_cmd.stdout()).expect("layout dump wasn't JSON");
+        assert_eq!(html["node"], "HTML");
+        assert_eq!(html["box"], "BlockContainer");
+        assert_eq!(html["writing_mode"], "horizontal-tb");
+        assert_eq!(html["rect"]["width"], 1920.);
+        assert_eq!(html["rect"]["height"], 184.);
+        let body = &html["children"][0];
+        assert_eq!(body["node"], "BODY");
+        assert_eq!(body["rect"]["x"], 8.);
+        assert_eq!(body["margin"]["left"], 8.);
+        assert_eq!(body["children"][0]["node"], "DIV");
+    }
+
+    #[test]
+    fn rainbow_divs_filtered() {
+        let mut dump_layout_cmd = dump_layout_cmd(DumpLayoutVerbosity::NonVerbose);
+        dump_layout_cmd
+            .arg("--filter")
+            .arg(".b, .d")
+            .arg("--files")
+            .arg("tests/websrc/rainbow-divs.html")
+            .arg("tests/websrc/rainbow-divs.css")
+            .succeeds();
+        assert_eq!(
+            dump_layout_cmd.stdout(),
+            "DIV BlockContainer at (32, 32) size 1856x120\n\
+             DIV BlockContainer at (56, 56) size 1808x72\n"
+        );
+    }
+
+    #[test]
+    fn rainbow_divs_filtered_with_descendants() {
+        let mut dump_layout_cmd = dump_layout_cmd(DumpLayoutVerbosity::NonVerbose);
+        dump_layout_cmd
+            .arg("--filter")
+            .arg("div.f")
+            .arg("--include-descendants")
+            .arg("--files")
+            .arg("tests/websrc/rainbow-divs.html")
+            .arg("tests/websrc/rainbow-divs.css")
+            .succeeds();
+        assert_eq!(
+            dump_layout_cmd.stdout(),
+            "DIV BlockContainer at (80, 80) size 1760x24\n  \
+             DIV BlockContainer at (92, 92) size 1736x0\n"
+        );
+    }
+
+    #[test]
+    fn rainbow_divs_diff() {
+        // The baseline dump, but with a shorter body and without the innermost div.
+        let tempdir = TempDir::new("rainbow_divs_diff").unwrap();
+        let previous_dump_path = tempdir.path().join("rainbow-divs-layout.txt");
+        std::fs::write(
+            &previous_dump_path,
+            "HTML BlockContainer at (0, 0) size 1920x184\n  \
+             BODY BlockContainer at (8, 8) size 1904x160\n    \
+             DIV BlockContainer at (20, 20) size 1880x144\n      \
+             DIV BlockContainer at (32, 32) size 1856x120\n        \
+             DIV BlockContainer at (44, 44) size 1832x96\n          \
+             DIV BlockContainer at (56, 56) size 1808x72\n            \
+             DIV BlockContainer at (68, 68) size 1784x48\n              \
+             DIV BlockContainer at (80, 80) size 1760x24\n",
+        )
+        .unwrap();
+        let mut dump_layout_cmd = dump_layout_cmd(DumpLayoutVerbosity::NonVerbose);
+        dump_layout_cmd
+            .arg("--diff")
+            .arg(&previous_dump_path)
+            .arg("--files")
+            .arg("tests/websrc/rainbow-divs.html")
+            .arg("tests/websrc/rainbow-divs.css")
+            .succeeds();
+        let innermost_div = vec!["DIV BlockContainer"; 7].join(" > ");
+        assert_eq!(
+            dump_layout_cmd.stdout(),
+            format!(
+                "HTML BlockContainer > BODY BlockContainer\n  \
+                 - at (8, 8) size 1904x160\n  \
+                 + at (8, 8) size 1904x168\n\
+                 HTML BlockContainer > BODY BlockContainer > {}\n  \
+                 + at (92, 92) size 1736x0\n",
+                innermost_div
+            )
+        );
+    }
 }
 
 pub(crate) static LAYOUT_DUMP_INNER_WINDOW_WIDTH_PX: f32 = 1920.;
@@ -53,6 +155,8 @@ pub fn dump_layout_cmd_verbose_scaled(
     cmd.arg(format!("{}", verbosity.to_cli_string()));
     cmd.arg("--scale-factor");
     cmd.arg(format!("{}", scale_factor));
+    // Lay text out with the same font on every machine.
+    cmd.arg("--test-fonts");
     cmd
 }
 
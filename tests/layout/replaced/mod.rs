@@ -0,0 +1,85 @@
+#[cfg(test)]
+mod tests {
+    use crate::layout::{dump_layout_cmd, dump_layout_cmd_verbose_scaled, DumpLayoutVerbosity};
+    use insta::assert_snapshot;
+
+    #[test]
+    fn block_img_sized_by_natural_dimensions_and_ratio() {
+        let mut dump_layout_cmd = dump_layout_cmd(DumpLayoutVerbosity::NonVerbose);
+        dump_layout_cmd
+            .arg("--files")
+            .arg("tests/websrc/replaced/img-block.html")
+            .arg("tests/websrc/replaced/img-block.css")
+            .succeeds();
+        assert_snapshot!(dump_layout_cmd.stdout());
+    }
+
+    #[test]
+    fn img_srcset_candidates_scale_natural_dimensions() {
+        let mut dump_layout_cmd = dump_layout_cmd(DumpLayoutVerbosity::NonVerbose);
+        dump_layout_cmd
+            .arg("--files")
+            .arg("tests/websrc/replaced/img-srcset.html")
+            .arg("tests/websrc/replaced/img-block.css")
+            .succeeds();
+        assert_snapshot!(dump_layout_cmd.stdout());
+    }
+
+    #[test]
+    fn img_srcset_selects_candidate_for_device_pixel_ratio() {
+        let mut dump_layout_cmd =
+            dump_layout_cmd_verbose_scaled(DumpLayoutVerbosity::NonVerbose, 2.0);
+        dump_layout_cmd
+            .arg("--files")
+            .arg("tests/websrc/replaced/img-srcset.html")
+            .arg("tests/websrc/replaced/img-block.css")
+            .succeeds();
+        assert_snapshot!(dump_layout_cmd.stdout());
+    }
+
+    #[test]
+    fn img_svg_sized_by_natural_dimensions() {
+        let mut dump_layout_cmd = dump_layout_cmd(DumpLayoutVerbosity::NonVerbose);
+        dump_layout_cmd
+            .arg("--files")
+            .arg("tests/websrc/replaced/img-svg.html")
+            .arg("tests/websrc/replaced/img-block.css")
+            .succeeds();
+        assert_snapshot!(dump_layout_cmd.stdout());
+    }
+
+    #[test]
+    fn form_controls_laid_out_as_atomic_inlines() {
+        let mut dump_layout_cmd = dump_layout_cmd(DumpLayoutVerbosity::NonVerbose);
+        dump_layout_cmd
+            .arg("--files")
+            .arg("tests/websrc/replaced/form-controls.html")
+            .arg("tests/websrc/replaced/form-controls.css")
+            .succeeds();
+        assert_snapshot!(dump_layout_cmd.stdout());
+    }
+
+    #[test]
+    fn iframe_documents_laid_out_in_their_content_boxes() {
+        let mut dump_layout_cmd = dump_layout_cmd(DumpLayoutVerbosity::NonVerbose);
+        dump_layout_cmd
+            .arg("--files")
+            .arg("tests/websrc/replaced/iframe.html")
+            .arg("tests/websrc/replaced/iframe.css")
+            .succeeds();
+        let dump = dump_layout_cmd.stdout();
+        // Each nested document's box tree is dumped under its iframe's box, in a viewport the size
+        // of the iframe's content box (which is the default size unless the iframe is sized).
+        assert!(dump.contains(
+            "    IFRAME ReplacedBox at (10, 10) size 300x150\n\
+             \x20     HTML BlockContainer at (0, 0) size 300x36\n\
+             \x20       BODY BlockContainer at (8, 8) size 284x20\n"
+        ));
+        assert!(dump.contains(
+            "    IFRAME ReplacedBox at (10, 164) size 100x50\n\
+             \x20     HTML BlockContainer at (0, 0) size 100x36\n"
+        ));
+        // Iframes whose documents can't be loaded are left empty.
+        assert!(dump.ends_with("    IFRAME ReplacedBox at (10, 218) size 300x150\n"));
+    }
+}
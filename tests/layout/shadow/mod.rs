@@ -0,0 +1,25 @@
+#[cfg(test)]
+mod tests {
+    use crate::layout::{dump_layout_cmd, DumpLayoutVerbosity};
+
+    #[test]
+    fn shadow_trees_render_in_place_of_their_hosts_children_with_scoped_styles() {
+        let mut dump_layout_cmd = dump_layout_cmd(DumpLayoutVerbosity::NonVerbose);
+        dump_layout_cmd
+            .arg("--files")
+            .arg("tests/websrc/shadow/declarative.html")
+            .arg("tests/websrc/shadow/declarative.css")
+            .succeeds();
+        let dump = dump_layout_cmd.stdout();
+        // The host is sized by the `:host` rule of its shadow tree, whose `<p>` is styled by the
+        // shadow tree's stylesheet, and whose part is styled by the document's.  The host's own
+        // `<p>` is rendered in the shadow tree's slot, styled by the document's stylesheet.
+        assert!(dump.contains(
+            "    DIV BlockContainer at (8, 8) size 200x60\n\
+             \x20     P BlockContainer at (8, 8) size 200x20\n\
+             \x20     DIV BlockContainer at (8, 28) size 200x30\n\
+             \x20     P BlockContainer at (8, 58) size 200x10\n"
+        ));
+        assert!(dump.contains("    P BlockContainer at (8, 68) size "));
+    }
+}
@@ -1,3 +1,8 @@
 mod util;
 
+mod css_errors;
 mod layout;
+mod memory;
+mod reftest;
+mod trace;
+mod wpt;
@@ -0,0 +1,45 @@
+#[cfg(test)]
+mod tests {
+    use crate::util::CommandUnderTest;
+
+    #[test]
+    fn reports_memory_retained_by_each_part_of_the_page() {
+        let mut cmd = CommandUnderTest::new();
+        cmd.arg("--memory-report")
+            .arg("--test-fonts")
+            .arg("--files")
+            .arg("tests/websrc/rainbow-divs.html")
+            .arg("tests/websrc/rainbow-divs.css")
+            .succeeds();
+        let parts = cmd
+            .stdout()
+            .lines()
+            .map(|line| {
+                let (part, size) = line.split_at(16);
+                (part.trim().to_owned(), size.trim().to_owned())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            parts.iter().map(|(part, _)| &part[..]).collect::<Vec<_>>(),
+            vec![
+                "stylesheets",
+                "DOM",
+                "computed styles",
+                "image cache",
+                "box tree",
+                "display list",
+                "total",
+            ]
+        );
+        for (part, size) in &parts {
+            if part != "image cache" {
+                assert!(
+                    !size.starts_with('-') && size != "0 B",
+                    "{} retained {}",
+                    part,
+                    size
+                );
+            }
+        }
+    }
+}
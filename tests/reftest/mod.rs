@@ -0,0 +1,43 @@
+#[cfg(test)]
+mod tests {
+    use crate::util::CommandUnderTest;
+
+    #[test]
+    fn reftests_pass() {
+        CommandUnderTest::new()
+            .arg("reftest")
+            .arg("tests/reftests/reftest.list")
+            .arg("--test-fonts")
+            .succeeds()
+            .stdout_is(
+                "PASS == tests/reftests/border-box.html tests/reftests/border-box-ref.html\n\
+                 PASS != tests/reftests/background-color.html tests/reftests/background-color-notref.html\n\
+                 PASS == tests/reftests/test-fonts.html tests/reftests/test-fonts-ref.html\n\
+                 3 of 3 reftests passed\n",
+            );
+    }
+
+    #[test]
+    fn failing_reftests_are_reported_with_diff_images() {
+        let diff_dir = std::env::temp_dir().join("kosmonaut-failing-reftest");
+        CommandUnderTest::new()
+            .arg("reftest")
+            .arg("tests/websrc/reftest/failing.list")
+            .arg("--diff-dir")
+            .arg(&diff_dir)
+            .fails()
+            .stdout_is(
+                "FAIL == tests/websrc/reftest/../../reftests/border-box.html \
+                 tests/websrc/reftest/../../reftests/background-color-notref.html \
+                 (10000 pixels differ, by up to 255)\n\
+                 0 of 1 reftests passed\n",
+            );
+        for image in &[
+            "border-box.png",
+            "border-box-ref.png",
+            "border-box-diff.png",
+        ] {
+            assert!(diff_dir.join(image).exists(), "{} wasn't written", image);
+        }
+    }
+}
@@ -0,0 +1,42 @@
+#[cfg(test)]
+mod tests {
+    use crate::util::CommandUnderTest;
+
+    #[test]
+    fn traces_each_phase_to_a_chrome_trace() {
+        let trace_path = std::env::temp_dir().join("kosmonaut-dump-layout-trace.json");
+        let _ = std::fs::remove_file(&trace_path);
+        CommandUnderTest::new()
+            .arg("dump-layout")
+            .arg("--test-fonts")
+            .arg("--viewport")
+            .arg("800x600")
+            .arg("--dpr")
+            .arg("1")
+            .arg("--trace-output")
+            .arg(&trace_path)
+            .arg("--files")
+            .arg("tests/websrc/rainbow-divs.html")
+            .arg("tests/websrc/rainbow-divs.css")
+            .succeeds();
+        let trace = std::fs::read_to_string(&trace_path).expect("trace wasn't written");
+        assert!(
+            trace.trim_start().starts_with('['),
+            "trace isn't a JSON array"
+        );
+        for phase in &[
+            "parse_html",
+            "apply_styles",
+            "match_stylesheet",
+            "cascade_and_compute",
+            "build_box_tree",
+            "layout",
+        ] {
+            assert!(
+                trace.contains(&format!("\"{}\"", phase)),
+                "{} wasn't traced",
+                phase
+            );
+        }
+    }
+}
@@ -172,6 +172,10 @@ impl CommandUnderTest {
     pub fn stdout(&self) -> &str {
         &self.stdout
     }
+
+    pub fn stderr(&self) -> &str {
+        &self.stderr
+    }
 }
 
 impl fmt::Debug for CommandUnderTest {
@@ -0,0 +1,59 @@
+#[cfg(test)]
+mod tests {
+    use crate::util::CommandUnderTest;
+    use serde_json::json;
+
+    #[test]
+    fn reports_reftest_crashtest_and_check_layout_results() {
+        let mut wpt_cmd = CommandUnderTest::new();
+        wpt_cmd
+            .arg("wpt")
+            .arg("tests/websrc/wpt")
+            .arg("--include")
+            .arg("css/box")
+            .succeeds();
+        let report: serde_json::Value =
+            serde_json::from_str(wpt_cmd.stdout()).expect("wptreport wasn't JSON");
+        assert_eq!(report["run_info"]["product"], "kosmonaut");
+        assert_eq!(
+            report["results"],
+            json!([
+                {
+                    "test": "/css/box/border-box.html",
+                    "status": "PASS",
+                    "message": null,
+                    "subtests": [],
+                },
+                {
+                    "test": "/css/box/check-layout.html",
+                    "status": "OK",
+                    "message": null,
+                    "subtests": [
+                        {
+                            "name": ".test 1",
+                            "status": "PASS",
+                            "message": null,
+                        },
+                        {
+                            "name": ".test 2",
+                            "status": "FAIL",
+                            "message": "data-expected-height was 10, not 20",
+                        },
+                    ],
+                },
+                {
+                    "test": "/css/box/crashtests/empty-div.html",
+                    "status": "PASS",
+                    "message": null,
+                    "subtests": [],
+                },
+                {
+                    "test": "/css/box/wrong-color.html",
+                    "status": "FAIL",
+                    "message": "10000 pixels differ, by up to 255",
+                    "subtests": [],
+                },
+            ])
+        );
+    }
+}